@@ -0,0 +1,274 @@
+//! # Actions Module
+//!
+//! Raw key counts say how often C was typed, not how often something was
+//! copied. This module names well-known shortcuts as actions (copy, paste,
+//! cut, undo, redo, save, find, alt-tab), counted per day next to the keys.
+//!
+//! A shortcut is a key pressed while exactly a set of modifiers is held:
+//! Ctrl+Shift+C is not copy, and Ctrl+Alt+Z is not undo. Keys are named by
+//! their place on a US keyboard like everywhere else in ctrlq, so on other
+//! layouts a shortcut is the key in the same place. More actions, or other
+//! shortcuts for the built-in ones, come from the `[actions]` table of the
+//! config:
+//!
+//! ```toml
+//! [actions]
+//! "build" = "CTRL+SHIFT+B"
+//! ```
+
+use crate::keymap;
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, HashMap};
+
+/// Built-in actions and their shortcuts; an action may have several.
+pub const BUILTIN_ACTIONS: [(&str, &str); 11] = [
+    ("copy", "CTRL+C"),
+    ("paste", "CTRL+V"),
+    ("cut", "CTRL+X"),
+    ("undo", "CTRL+Z"),
+    ("redo", "CTRL+Y"),
+    ("redo", "CTRL+SHIFT+Z"),
+    ("save", "CTRL+S"),
+    ("find", "CTRL+F"),
+    ("alt-tab", "ALT+TAB"),
+    ("alt-tab", "ALT+SHIFT+TAB"),
+    ("alt-tab", "META+TAB"),
+];
+
+/// Modifiers held while a key is pressed; left and right count the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    /// Either Ctrl key
+    pub ctrl: bool,
+    /// Either Shift key
+    pub shift: bool,
+    /// Either Alt key, including AltGr
+    pub alt: bool,
+    /// Either Super (Windows) key
+    pub meta: bool,
+}
+
+impl Modifiers {
+    /// Collects the modifiers among held keys.
+    ///
+    /// # Arguments
+    /// * `held` - Evdev codes of the keys held down
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::actions::Modifiers;
+    ///
+    /// // Left Ctrl, right Shift and A
+    /// let modifiers = Modifiers::of([29, 54, 30]);
+    /// assert!(modifiers.ctrl && modifiers.shift && !modifiers.alt && !modifiers.meta);
+    /// assert_eq!(Modifiers::of([30]), Modifiers::default());
+    /// ```
+    pub fn of(held: impl IntoIterator<Item = u16>) -> Self {
+        let mut modifiers = Self::default();
+        for code in held {
+            match code {
+                29 | 97 => modifiers.ctrl = true,
+                42 | 54 => modifiers.shift = true,
+                56 | 100 => modifiers.alt = true,
+                125 | 126 => modifiers.meta = true,
+                _ => {}
+            }
+        }
+        modifiers
+    }
+
+    /// Whether no modifier is held.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A key together with the exact set of modifiers held for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Combo {
+    /// Modifiers that must be held, and no others
+    pub modifiers: Modifiers,
+    /// Evdev code of the key
+    pub code: u16,
+}
+
+impl Combo {
+    /// Parses a shortcut such as `CTRL+SHIFT+B`.
+    ///
+    /// Modifiers are `CTRL`, `SHIFT`, `ALT` and `META` (or `SUPER`, `WIN`),
+    /// in any order and case; the key comes last and takes any name
+    /// [`keymap::code_from_label`] knows (`B`, `TAB`, `F5`, `/`).
+    ///
+    /// # Arguments
+    /// * `spec` - The shortcut
+    ///
+    /// # Returns
+    /// `Result<Combo>` - The combo, or an error naming what is wrong
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::actions::{Combo, Modifiers};
+    ///
+    /// let build = Combo::parse("ctrl+Shift+B").unwrap();
+    /// assert_eq!(build.modifiers, Modifiers { ctrl: true, shift: true, ..Modifiers::default() });
+    /// assert_eq!(build.code, 48);
+    /// assert_eq!(Combo::parse("F5").unwrap().modifiers, Modifiers::default());
+    ///
+    /// assert!(Combo::parse("CTRL+SHIFT").is_err());
+    /// assert!(Combo::parse("HYPER+X").is_err());
+    /// ```
+    pub fn parse(spec: &str) -> Result<Self> {
+        let parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+        let (key, modifier_names) = parts.split_last().context("empty shortcut")?;
+        let mut modifiers = Modifiers::default();
+        for name in modifier_names {
+            let flag = match name.to_uppercase().as_str() {
+                "CTRL" | "CONTROL" => &mut modifiers.ctrl,
+                "SHIFT" => &mut modifiers.shift,
+                "ALT" => &mut modifiers.alt,
+                "META" | "SUPER" | "WIN" => &mut modifiers.meta,
+                _ => bail!("unknown modifier '{}' in '{}', expected CTRL, SHIFT, ALT or META", name, spec),
+            };
+            *flag = true;
+        }
+        let code = keymap::code_from_label(key).with_context(|| format!("unknown key '{}' in '{}'", key, spec))?;
+        if keymap::classify(code) == keymap::KeyClass::Modifier {
+            bail!("'{}' has no key after its modifiers", spec);
+        }
+        Ok(Self { modifiers, code })
+    }
+}
+
+/// Shortcuts by the action they stand for, see the module docs.
+///
+/// # Example
+/// ```
+/// use ctrlq::actions::{ActionTable, Modifiers};
+/// use std::collections::BTreeMap;
+///
+/// let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+/// let ctrl_shift = Modifiers { shift: true, ..ctrl };
+/// let custom = BTreeMap::from([("build".to_string(), "CTRL+SHIFT+B".to_string())]);
+/// let table = ActionTable::new(&custom).unwrap();
+///
+/// assert_eq!(table.action(ctrl, 46), Some("copy"));
+/// // Ctrl+Shift+C copies in terminals, but isn't Ctrl+C
+/// assert_eq!(table.action(ctrl_shift, 46), None);
+/// assert_eq!(table.action(ctrl_shift, 44), Some("redo"));
+/// assert_eq!(table.action(ctrl_shift, 48), Some("build"));
+/// assert_eq!(table.action(Modifiers::default(), 46), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ActionTable {
+    combos: HashMap<Combo, String>,
+}
+
+impl Default for ActionTable {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new()).expect("built-in shortcuts parse")
+    }
+}
+
+impl ActionTable {
+    /// Creates the table of the built-in actions and the configured ones.
+    ///
+    /// A configured action with the name of a built-in one replaces its
+    /// shortcuts; a configured shortcut of a built-in action moves to the
+    /// configured one.
+    ///
+    /// # Arguments
+    /// * `custom` - Shortcuts by action name, from the `[actions]` table of the config
+    ///
+    /// # Returns
+    /// `Result<ActionTable>` - The table, or an error for the first shortcut that doesn't parse
+    pub fn new(custom: &BTreeMap<String, String>) -> Result<Self> {
+        let mut combos = HashMap::new();
+        for (name, spec) in BUILTIN_ACTIONS {
+            if !custom.contains_key(name) {
+                combos.insert(Combo::parse(spec)?, name.to_string());
+            }
+        }
+        for (name, spec) in custom {
+            let combo = Combo::parse(spec).with_context(|| format!("invalid shortcut for action '{}'", name))?;
+            combos.insert(combo, name.clone());
+        }
+        Ok(Self { combos })
+    }
+
+    /// Returns the action a key press stands for.
+    ///
+    /// # Arguments
+    /// * `modifiers` - Modifiers held at the press
+    /// * `code` - Evdev code of the pressed key
+    ///
+    /// # Returns
+    /// `Option<&str>` - The action name, or None if the press is no known shortcut
+    pub fn action(&self, modifiers: Modifiers, code: u16) -> Option<&str> {
+        self.combos.get(&Combo { modifiers, code }).map(String::as_str)
+    }
+}
+
+/// Returns the plural of an action name for counts, e.g. `pastes`, `copies`.
+///
+/// # Example
+/// ```
+/// use ctrlq::actions::plural;
+///
+/// assert_eq!(plural("paste"), "pastes");
+/// assert_eq!(plural("copy"), "copies");
+/// assert_eq!(plural("alt-tab"), "alt-tabs");
+/// assert_eq!(plural("fix"), "fixes");
+/// ```
+pub fn plural(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix('y')
+        && !stem.ends_with(['a', 'e', 'i', 'o', 'u'])
+    {
+        format!("{}ies", stem)
+    } else if name.ends_with(['s', 'x', 'z']) || name.ends_with("sh") || name.ends_with("ch") {
+        format!("{}es", name)
+    } else {
+        format!("{}s", name)
+    }
+}
+
+/// Summarizes action counts as a line, most frequent first.
+///
+/// # Arguments
+/// * `counts` - Presses by action name, e.g. of one day
+/// * `limit` - Most actions to name
+///
+/// # Returns
+/// `Option<String>` - E.g. `84 pastes, 31 undos, 57 saves`, or None without any action
+///
+/// # Example
+/// ```
+/// use ctrlq::actions::summary_line;
+/// use std::collections::HashMap;
+///
+/// let day = HashMap::from([("undo".to_string(), 31), ("paste".to_string(), 84), ("copy".to_string(), 1)]);
+/// assert_eq!(summary_line(&day, 2).as_deref(), Some("84 pastes, 31 undos"));
+/// assert_eq!(summary_line(&day, 5).as_deref(), Some("84 pastes, 31 undos, 1 copy"));
+/// assert_eq!(summary_line(&HashMap::new(), 3), None);
+/// ```
+pub fn summary_line(counts: &HashMap<String, u64>, limit: usize) -> Option<String> {
+    let parts: Vec<String> = sorted(counts)
+        .into_iter()
+        .take(limit)
+        .map(|(name, count)| {
+            let name = if count == 1 { name.to_string() } else { plural(name) };
+            format!("{} {}", count, name)
+        })
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+/// Action counts sorted by count (descending), then name.
+pub fn sorted(counts: &HashMap<String, u64>) -> Vec<(&str, u64)> {
+    let mut sorted: Vec<(&str, u64)> = counts
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(name, count)| (name.as_str(), *count))
+        .collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    sorted
+}
@@ -0,0 +1,173 @@
+//! # Archive Module
+//!
+//! Keeps the data file small. Days from before the previous month rarely
+//! change, so instead of rewriting them with every save they are moved into
+//! one file per month in a directory next to the data file:
+//!
+//! ```text
+//! keystroke_data.json
+//! keystroke_data.archive/2024-03.json
+//! keystroke_data.archive/2024-04.json
+//! ```
+//!
+//! A running logger only keeps the current and the previous month in memory,
+//! so "last 7 days" and week comparisons never need the archive. Views of the
+//! whole history load it on demand with [`load_days`] or [`load_into`].
+
+use crate::keylogger::{day_key, DayStats, KeyStats};
+use crate::paths;
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Returns the archive directory belonging to a data file.
+///
+/// # Arguments
+/// * `data_file` - Path of the statistics data file
+///
+/// # Returns
+/// `PathBuf` - e.g. `keystroke_data.archive` next to `keystroke_data.json`
+pub fn archive_dir(data_file: &Path) -> PathBuf {
+    data_file.with_extension("archive")
+}
+
+/// Returns the first day that stays in the data file: the first of the
+/// previous month.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::archive::cutoff;
+///
+/// let today = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+/// assert_eq!(cutoff(today), NaiveDate::from_ymd_opt(2023, 12, 1).unwrap());
+/// ```
+pub fn cutoff(today: NaiveDate) -> NaiveDate {
+    let first = today.with_day(1).unwrap_or(today);
+    first.checked_sub_months(chrono::Months::new(1)).unwrap_or(first)
+}
+
+/// Moves days before [`cutoff`] out of `stats` into the monthly archive files.
+///
+/// Archived days already on disk are kept; a day present in both is
+/// replaced by the in-memory one.
+///
+/// # Arguments
+/// * `stats` - Statistics to move old days out of
+/// * `data_file` - The data file the archive belongs to
+/// * `today` - Current local date
+/// * `pretty` - Whether to write indented JSON
+///
+/// # Returns
+/// `Result<usize>` - Number of days moved
+pub fn archive_old_days(stats: &mut KeyStats, data_file: &Path, today: NaiveDate, pretty: bool) -> Result<usize> {
+    let keep_from = day_key(cutoff(today));
+    let old: Vec<String> = stats
+        .daily_stats
+        .keys()
+        .filter(|date| date.as_str() < keep_from.as_str())
+        .cloned()
+        .collect();
+    if old.is_empty() {
+        return Ok(0);
+    }
+
+    let mut months: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for date in old {
+        months.entry(date.chars().take(7).collect()).or_default().push(date);
+    }
+
+    let dir = archive_dir(data_file);
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let mut moved = 0;
+    for (month, dates) in months {
+        let path = dir.join(format!("{}.json", month));
+        let mut days = read_month(&path)?;
+        for date in dates {
+            if let Some(day) = stats.daily_stats.remove(&date) {
+                days.insert(date, day);
+                moved += 1;
+            }
+        }
+        paths::write_atomic(&path, |writer| {
+            if pretty {
+                serde_json::to_writer_pretty(writer, &days)?;
+            } else {
+                serde_json::to_writer(writer, &days)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(moved)
+}
+
+/// Reads one month file; a missing file is an empty month.
+fn read_month(path: &Path) -> Result<BTreeMap<String, DayStats>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let file = std::fs::File::open(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_reader(std::io::BufReader::new(file)).with_context(|| format!("invalid archive file {}", path.display()))
+}
+
+/// Loads all archived days of a data file.
+///
+/// # Arguments
+/// * `data_file` - The data file the archive belongs to
+///
+/// # Returns
+/// `Result<HashMap<String, DayStats>>` - Archived days by date, empty without an archive
+pub fn load_days(data_file: &Path) -> Result<HashMap<String, DayStats>> {
+    let dir = archive_dir(data_file);
+    let mut days = HashMap::new();
+    if !dir.is_dir() {
+        return Ok(days);
+    }
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            days.extend(read_month(&path)?);
+        }
+    }
+    Ok(days)
+}
+
+/// Adds the archived days of a data file to `stats`, for views and commands
+/// that need the whole history. Days already in `stats` win.
+///
+/// # Arguments
+/// * `stats` - Statistics loaded from the data file
+/// * `data_file` - The data file the archive belongs to
+pub fn load_into(stats: &mut KeyStats, data_file: &Path) -> Result<()> {
+    for (date, day) in load_days(data_file)? {
+        stats.daily_stats.entry(date).or_insert(day);
+    }
+    Ok(())
+}
+
+/// Deletes the archive of a data file, e.g. after a full reset.
+pub fn clear(data_file: &Path) -> Result<()> {
+    let dir = archive_dir(data_file);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).with_context(|| format!("failed to delete {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Copies the archive of one data file to another, e.g. for `profile copy`.
+pub fn copy(from: &Path, to: &Path) -> Result<()> {
+    let source = archive_dir(from);
+    if !source.is_dir() {
+        return Ok(());
+    }
+    let target = archive_dir(to);
+    std::fs::create_dir_all(&target).with_context(|| format!("failed to create {}", target.display()))?;
+    for entry in std::fs::read_dir(&source)? {
+        let entry = entry?;
+        std::fs::copy(entry.path(), target.join(entry.file_name()))
+            .with_context(|| format!("failed to copy {}", entry.path().display()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,438 @@
+//! # Backup Module
+//!
+//! Full backups of everything ctrlq keeps: the data file of every profile,
+//! their monthly archives and `config.toml`. A backup is a tar file,
+//! compressed with zstd when its name ends in `.zst` (`ctrlq-backup.tar.zst`),
+//! so it can be inspected with `tar tvf`, holding the files and a
+//! `manifest.json`:
+//!
+//! ```text
+//! manifest.json
+//! data/keystroke_data.json
+//! data/keystroke_data.archive/2024-03.json
+//! data/keystroke_data.work.json
+//! config/config.toml
+//! ```
+//!
+//! The manifest records the backup format version and a SHA-256 checksum
+//! of every file. [`restore`] verifies all of them before it writes
+//! anything. Raw event logs (`--raw-log`) are not included; they can be
+//! large and are only needed to rebuild statistics.
+
+use crate::config::Config;
+use crate::paths;
+use crate::profile;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Version of the backup format, bumped on incompatible changes.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Name of the manifest inside a backup.
+const MANIFEST: &str = "manifest.json";
+
+/// Directory of the data files inside a backup.
+const DATA_PREFIX: &str = "data/";
+
+/// Path of the configuration file inside a backup.
+const CONFIG_ENTRY: &str = "config/config.toml";
+
+/// First bytes of a zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// First bytes of a gzip file.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// zstd level of compressed backups, the zstd command's default.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Describes the contents of a backup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Always [`BACKUP_FORMAT_VERSION`] when written by this version
+    pub format_version: u32,
+    /// Version of ctrlq that wrote the backup
+    pub ctrlq_version: String,
+    /// When the backup was made
+    pub created: DateTime<Utc>,
+    /// Every file in the backup except the manifest
+    pub files: Vec<ManifestEntry>,
+}
+
+/// One file of a backup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path inside the backup, e.g. `data/keystroke_data.json`
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-256 of the contents, lowercase hex
+    pub sha256: String,
+}
+
+/// Where backed up files come from and are restored to.
+#[derive(Debug, Clone)]
+pub struct Locations {
+    /// The data directory with the profiles' data files and archives
+    pub data_dir: PathBuf,
+    /// The configuration file
+    pub config_file: PathBuf,
+}
+
+impl Locations {
+    /// The data directory and configuration file in use.
+    pub fn current() -> Result<Self> {
+        Ok(Self { data_dir: profile::data_dir()?, config_file: Config::path() })
+    }
+
+    /// Maps a path inside a backup to the file it belongs to.
+    fn resolve(&self, path: &str) -> Result<PathBuf> {
+        if path == CONFIG_ENTRY {
+            return Ok(self.config_file.clone());
+        }
+        match path.strip_prefix(DATA_PREFIX) {
+            Some(rest) if !rest.is_empty() && rest.split('/').all(|part| !part.is_empty() && part != "." && part != "..") => {
+                Ok(self.data_dir.join(rest))
+            }
+            _ => bail!("unexpected file '{}' in the backup", path),
+        }
+    }
+}
+
+/// Outcome of [`restore`].
+#[derive(Debug, Clone, Default)]
+pub struct Restored {
+    /// Files written
+    pub written: Vec<PathBuf>,
+    /// Files that already had the backed up contents
+    pub unchanged: usize,
+}
+
+/// Writes a backup of all profiles, their archives and the configuration.
+///
+/// # Arguments
+/// * `locations` - Where the files are
+/// * `output` - The backup file to write, compressed with zstd if its name
+///   ends in `.zst`
+///
+/// # Returns
+/// `Result<Manifest>` - The manifest of the written backup
+pub fn create(locations: &Locations, output: &Path) -> Result<Manifest> {
+    let files = collect(locations)?;
+    if files.is_empty() {
+        bail!("nothing to back up in {}", locations.data_dir.display());
+    }
+
+    let manifest = Manifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        ctrlq_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: Utc::now(),
+        files: files
+            .iter()
+            .map(|(path, contents)| ManifestEntry {
+                path: path.clone(),
+                size: contents.len() as u64,
+                sha256: sha256_hex(contents),
+            })
+            .collect(),
+    };
+
+    let name = output.file_name().unwrap_or_default().to_string_lossy();
+    if name.ends_with(".gz") {
+        bail!("gzip backups are not supported; name the backup .tar.zst for zstd, or .tar");
+    }
+    let file = File::create(output).with_context(|| format!("failed to write {}", output.display()))?;
+    let writer = BufWriter::new(file);
+    let written = if name.ends_with(".zst") {
+        let mut encoder = zstd::Encoder::new(writer, ZSTD_LEVEL)?;
+        write_tar(&mut encoder, &manifest, &files)?;
+        encoder.finish()?.flush()
+    } else {
+        let mut writer = writer;
+        write_tar(&mut writer, &manifest, &files)?;
+        writer.flush()
+    };
+    written.with_context(|| format!("failed to write {}", output.display()))?;
+    Ok(manifest)
+}
+
+/// Writes the manifest and the files as a tar archive.
+fn write_tar(writer: &mut impl Write, manifest: &Manifest, files: &[(String, Vec<u8>)]) -> Result<()> {
+    let mut tar = tar::Builder::new(writer);
+    let mtime = manifest.created.timestamp().max(0) as u64;
+    let manifest = serde_json::to_vec_pretty(manifest)?;
+    for (path, contents) in std::iter::once((MANIFEST, manifest.as_slice()))
+        .chain(files.iter().map(|(path, contents)| (path.as_str(), contents.as_slice())))
+    {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_entry_type(tar::EntryType::Regular);
+        tar.append_data(&mut header, path, contents).with_context(|| format!("failed to add {}", path))?;
+    }
+    tar.finish()?;
+    Ok(())
+}
+
+/// Reads a backup and verifies every file against its checksum.
+///
+/// # Arguments
+/// * `backup` - The backup file, plain or compressed with zstd
+///
+/// # Returns
+/// `Result<(Manifest, BTreeMap<String, Vec<u8>>)>` - The manifest and the
+/// verified files by their path inside the backup
+///
+/// # Example
+/// Sizes in the tar headers are not trusted:
+/// ```
+/// use ctrlq::backup;
+///
+/// // An entry that claims to hold a terabyte but ends after a few bytes
+/// let mut header = tar::Header::new_gnu();
+/// header.set_path("manifest.json")?;
+/// header.set_size(1 << 40);
+/// header.set_cksum();
+/// let mut bytes = header.as_bytes().to_vec();
+/// bytes.extend_from_slice(&[b'{'; 512]);
+///
+/// let file = std::env::temp_dir().join(format!("ctrlq-backup-size-doc-{}.tar", std::process::id()));
+/// std::fs::write(&file, bytes)?;
+/// let error = backup::read(&file).unwrap_err();
+/// assert!(error.to_string().contains("is not a ctrlq backup"));
+/// std::fs::remove_file(&file)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn read(backup: &Path) -> Result<(Manifest, BTreeMap<String, Vec<u8>>)> {
+    let file = File::open(backup).with_context(|| format!("failed to read {}", backup.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut magic = [0u8; 4];
+    let peeked = reader.read(&mut magic)?;
+    let reader = std::io::Cursor::new(magic[..peeked].to_vec()).chain(reader);
+    let files = if magic == ZSTD_MAGIC {
+        read_tar(zstd::Decoder::new(reader)?)
+    } else if magic.starts_with(&GZIP_MAGIC) {
+        bail!("{} is compressed with gzip; decompress it first with gunzip", backup.display());
+    } else {
+        read_tar(reader)
+    };
+    let mut files = files.with_context(|| format!("{} is not a ctrlq backup", backup.display()))?;
+    let manifest: Manifest = match files.remove(MANIFEST) {
+        Some(json) => serde_json::from_slice(&json).context("invalid manifest.json")?,
+        None => bail!("{} is not a ctrlq backup: no manifest.json", backup.display()),
+    };
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        bail!(
+            "the backup has format version {}, this ctrlq reads up to {}; update ctrlq first",
+            manifest.format_version,
+            BACKUP_FORMAT_VERSION
+        );
+    }
+
+    for entry in &manifest.files {
+        let Some(contents) = files.get(&entry.path) else {
+            bail!("the backup is incomplete: {} is missing", entry.path);
+        };
+        if contents.len() as u64 != entry.size || sha256_hex(contents) != entry.sha256 {
+            bail!("checksum mismatch for {}: the backup is damaged", entry.path);
+        }
+    }
+    if let Some(path) = files.keys().find(|path| !manifest.files.iter().any(|entry| &entry.path == *path)) {
+        bail!("{} is in the backup but not in its manifest", path);
+    }
+    Ok((manifest, files))
+}
+
+/// Restores a backup.
+///
+/// Every checksum is verified before anything is written. Files that
+/// changed after the backup was made are only overwritten with `force`,
+/// and profiles that a running logger holds are never touched. Files that
+/// are not in the backup are left alone.
+///
+/// # Arguments
+/// * `locations` - Where to restore to
+/// * `backup` - The backup file
+/// * `force` - Overwrite files that are newer than the backup
+///
+/// # Returns
+/// `Result<Restored>` - What was written, or an error before any change
+///
+/// # Example
+/// A backup round trip gives back identical files, and newer data is only
+/// overwritten with `force`:
+/// ```
+/// use ctrlq::backup::{self, Locations};
+/// use ctrlq::keylogger::{KeyLogger, KeyStats};
+///
+/// let root = std::env::temp_dir().join(format!("ctrlq-backup-doc-{}", std::process::id()));
+/// let home = Locations { data_dir: root.join("data"), config_file: root.join("config.toml") };
+/// std::fs::create_dir_all(home.data_dir.join("keystroke_data.archive"))?;
+///
+/// let mut stats = KeyStats::new();
+/// stats.add_keypress("KEY_30");
+/// let data_file = home.data_dir.join("keystroke_data.json");
+/// KeyLogger::save_stats(&stats, &data_file, false)?;
+/// std::fs::write(home.data_dir.join("keystroke_data.archive/2024-03.json"), "{}")?;
+/// std::fs::write(&home.config_file, "daily_goal = 15000\n")?;
+///
+/// let file = root.join("backup.tar");
+/// let manifest = backup::create(&home, &file)?;
+/// assert_eq!(manifest.files.len(), 3);
+///
+/// // On a new machine
+/// let other = Locations { data_dir: root.join("other"), config_file: root.join("other.toml") };
+/// let restored = backup::restore(&other, &file, false)?;
+/// assert_eq!(restored.written.len(), 3);
+/// assert_eq!(std::fs::read(&data_file)?, std::fs::read(other.data_dir.join("keystroke_data.json"))?);
+/// assert_eq!(std::fs::read(&home.config_file)?, std::fs::read(&other.config_file)?);
+///
+/// // Typing went on after the backup: restoring needs --force
+/// stats.add_keypress("KEY_31");
+/// KeyLogger::save_stats(&stats, &data_file, false)?;
+/// let refused = backup::restore(&home, &file, false).unwrap_err();
+/// assert!(refused.to_string().contains("--force"));
+/// let restored = backup::restore(&home, &file, true)?;
+/// assert_eq!((restored.written.len(), restored.unchanged), (1, 2));
+/// assert_eq!(std::fs::read(&data_file)?, std::fs::read(other.data_dir.join("keystroke_data.json"))?);
+///
+/// // A damaged backup is refused before anything is written
+/// let mut bytes = std::fs::read(&file)?;
+/// let at = bytes.windows(6).position(|w| w == b"KEY_30").unwrap();
+/// bytes[at + 4] = b'9';
+/// std::fs::write(&file, bytes)?;
+/// assert!(backup::restore(&other, &file, true).unwrap_err().to_string().contains("checksum"));
+///
+/// // A .zst name compresses the backup with zstd; restoring detects it
+/// let compressed = root.join("backup.tar.zst");
+/// backup::create(&home, &compressed)?;
+/// assert_eq!(std::fs::read(&compressed)?[..4], [0x28, 0xb5, 0x2f, 0xfd]);
+/// let restored = backup::restore(&other, &compressed, true)?;
+/// assert_eq!(restored.written.len() + restored.unchanged, 3);
+/// assert_eq!(std::fs::read(&data_file)?, std::fs::read(other.data_dir.join("keystroke_data.json"))?);
+/// # std::fs::remove_dir_all(&root)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn restore(locations: &Locations, backup: &Path, force: bool) -> Result<Restored> {
+    let (manifest, files) = read(backup)?;
+
+    let mut restored = Restored::default();
+    let mut pending = Vec::new();
+    let mut newer = Vec::new();
+    for (path, contents) in files {
+        let target = locations.resolve(&path)?;
+        if target.parent() == Some(locations.data_dir.as_path())
+            && profile::is_locked(&target)
+        {
+            bail!(
+                "{} is being logged by a running ctrlq instance; stop it before restoring",
+                target.display()
+            );
+        }
+        match std::fs::read(&target) {
+            Ok(current) if current == contents => {
+                restored.unchanged += 1;
+                continue;
+            }
+            Ok(_) => {
+                let modified = std::fs::metadata(&target).and_then(|meta| meta.modified()).ok();
+                if modified.is_some_and(|modified| DateTime::<Utc>::from(modified) > manifest.created) {
+                    newer.push(target.display().to_string());
+                }
+            }
+            Err(_) => {}
+        }
+        pending.push((target, contents));
+    }
+
+    if !newer.is_empty() && !force {
+        bail!(
+            "{} changed after the backup was made ({}); use --force to overwrite",
+            newer.join(", "),
+            manifest.created.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+        );
+    }
+
+    for (target, contents) in pending {
+        if let Some(dir) = target.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        // An interrupted restore leaves each file either as it was or restored
+        paths::write_atomic(&target, |writer| Ok(writer.write_all(&contents)?))?;
+        restored.written.push(target);
+    }
+    Ok(restored)
+}
+
+/// Gathers the files to back up, by their path inside the backup.
+fn collect(locations: &Locations) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let dir = &locations.data_dir;
+    let entries = std::fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if profile::profile_name(&name).is_some() && path.is_file() {
+            files.push((format!("{}{}", DATA_PREFIX, name), read_file(&path)?));
+        } else if name.ends_with(".archive") && path.is_dir() {
+            for month in std::fs::read_dir(&path)? {
+                let month = month?.path();
+                if month.is_file() {
+                    let month_name = month.file_name().unwrap_or_default().to_string_lossy();
+                    files.push((format!("{}{}/{}", DATA_PREFIX, name, month_name), read_file(&month)?));
+                }
+            }
+        }
+    }
+    if locations.config_file.is_file() {
+        files.push((CONFIG_ENTRY.to_string(), read_file(&locations.config_file)?));
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+/// Reads the regular files of a tar archive.
+fn read_tar(reader: impl Read) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type() != tar::EntryType::Regular {
+            continue;
+        }
+        let path = entry.path()?;
+        let Some(name) = path.to_str().map(str::to_string) else {
+            bail!("file name {} is not UTF-8", path.display());
+        };
+        // The size in the header is the archive's word; growing as the
+        // bytes arrive keeps a made-up size from allocating anything
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents).with_context(|| format!("{} is cut off", name))?;
+        files.insert(name, contents);
+    }
+    Ok(files)
+}
+
+/// SHA-256 of some bytes as lowercase hex, the checksum of the manifest.
+///
+/// # Example
+/// ```
+/// use ctrlq::backup::sha256_hex;
+///
+/// assert_eq!(sha256_hex(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+/// assert_eq!(sha256_hex(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+/// ```
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,533 @@
+//! # Bench Module
+//!
+//! A reproducible benchmark of the statistics pipeline, behind `ctrlq
+//! bench`. It needs no keyboard and no root: a synthetic [`EventSource`]
+//! feeds the logging thread a fixed, skewed mix of keys on top of a
+//! synthetic history, and the run reports
+//!
+//! - events per second through the logging loop,
+//! - allocations per event (only where the binary counts them, see
+//!   [`CountingAllocator`]),
+//! - the cost of one snapshot as published to the UI and the status line,
+//! - the time of a full save of the data file,
+//! - the time a start takes to load the data file, once with the whole
+//!   history in it (as from before the archive) and once after the logger
+//!   moved the old days to the archive. `--days 12000` makes a history of
+//!   about 50 MB.
+//!
+//! Runs are deterministic apart from timing, so two builds can be compared:
+//! save a run with `--save-baseline` and pass it to a later run with
+//! `--baseline`.
+
+use crate::config::{Config, Overrides};
+use crate::keylogger::{
+    day_key, today, DayStats, EventSource, KeyEvent, KeyLogger, KeyState, KeyStats, LiveSnapshot, ACTIVE_POLL_INTERVAL,
+};
+use crate::keymap::{self, KeyClass};
+use crate::numbers;
+use crate::paths;
+use crate::profile;
+use crate::status_line::StatusSnapshot;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Events handed to the logging loop per poll.
+const BATCH: usize = 20_000;
+
+/// Time between two synthetic key presses.
+const PRESS_GAP: Duration = Duration::from_millis(80);
+
+/// Snapshots and saves timed after the run, for an average.
+const REPEATS: u32 = 10;
+
+/// Seed of the key mix, so every run types the same keys.
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Allocations made through [`CountingAllocator`].
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting allocations for the bench.
+///
+/// The library can't install it; a binary that wants allocation counts
+/// does, at the cost of one relaxed atomic add per allocation. The ctrlq
+/// binary only installs it when built with the `bench-alloc` feature:
+///
+/// ```
+/// #[global_allocator]
+/// static ALLOCATOR: ctrlq::bench::CountingAllocator = ctrlq::bench::CountingAllocator;
+/// # fn main() {}
+/// ```
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Allocations so far, or None if [`CountingAllocator`] isn't installed.
+fn allocations() -> Option<u64> {
+    // Nothing starts up without allocating
+    Some(ALLOCATIONS.load(Ordering::Relaxed)).filter(|&count| count > 0)
+}
+
+/// What to run, from the options of `ctrlq bench`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchOptions {
+    /// Key events fed to the logging loop, presses and releases
+    pub events: u64,
+    /// Distinct keys typed, at most the keys ctrlq knows
+    pub distinct_keys: usize,
+    /// Days of synthetic history in the data file before the run
+    pub days: u32,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self { events: 1_000_000, distinct_keys: 120, days: 365 }
+    }
+}
+
+/// Parses a count such as `1_000_000` or `1000000`.
+///
+/// # Example
+/// ```
+/// use ctrlq::bench::parse_count;
+///
+/// assert_eq!(parse_count("1_000_000").unwrap(), 1_000_000);
+/// assert_eq!(parse_count("365").unwrap(), 365);
+/// assert!(parse_count("1e6").is_err());
+/// ```
+pub fn parse_count(text: &str) -> Result<u64> {
+    text.replace('_', "")
+        .parse()
+        .with_context(|| format!("invalid count '{}', expected a number such as 1_000_000", text))
+}
+
+/// Results of a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// Key events fed to the logging loop
+    pub events: u64,
+    /// Distinct keys typed
+    pub distinct_keys: usize,
+    /// Days of synthetic history
+    pub days: u32,
+    /// Events per second through the logging loop, not counting its sleeps
+    /// between polls
+    pub events_per_sec: f64,
+    /// Allocations per event in the logging loop, if counted
+    pub allocations_per_event: Option<f64>,
+    /// Time of one snapshot: a copy of the statistics for the UI and the
+    /// live and status snapshots
+    pub snapshot_ms: f64,
+    /// Allocations of one snapshot, if counted
+    pub snapshot_allocations: Option<f64>,
+    /// Time of one full save of the data file
+    pub save_ms: f64,
+    /// Size of the saved data file
+    pub save_bytes: u64,
+    /// Time to load the data file with the whole synthetic history in it
+    #[serde(default)]
+    pub unsplit_load_ms: f64,
+    /// Size of the data file with the whole synthetic history in it
+    #[serde(default)]
+    pub unsplit_bytes: u64,
+    /// Time to load the data file once old days were archived, as every
+    /// later start does
+    #[serde(default)]
+    pub load_ms: f64,
+}
+
+impl BenchReport {
+    /// Reads a report saved with [`BenchReport::save`].
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("{} is not a saved bench run", path.display()))
+    }
+
+    /// Writes the report as JSON, for a later `--baseline`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// Keys ctrlq knows, characters first, without modifiers and lock keys that
+/// would change how the following keys are counted.
+fn key_pool() -> Vec<u16> {
+    let mut pool: Vec<u16> = (1..=keymap::KEY_MAX)
+        .filter(|&code| keymap::is_known_code(code))
+        .filter(|&code| keymap::classify(code) != KeyClass::Modifier && code != keymap::KEY_NUMLOCK)
+        .collect();
+    pool.sort_by_key(|&code| !keymap::is_character_key(code));
+    pool
+}
+
+/// A xorshift generator, enough for a reproducible key mix.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// An index below `len`, the first ones much more likely, like the
+    /// letters of real typing.
+    fn skewed(&mut self, len: usize) -> usize {
+        let unit = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+        ((unit * unit) * len as f64) as usize
+    }
+}
+
+/// Builds statistics with a working day of typing on each of the `days`
+/// days before `today`, spread over `keys` with the first ones used most.
+fn synthetic_history(days: u32, keys: &[u16], today: chrono::NaiveDate) -> KeyStats {
+    let mut rng = Rng(SEED);
+    let mut stats = KeyStats::new();
+    // Shares of a day's keystrokes per key, most used first
+    let weights: Vec<f64> = (0..keys.len()).map(|index| 1.0 / (index + 1) as f64).collect();
+    let weight_sum: f64 = weights.iter().sum();
+    for offset in 1..=days {
+        let Some(date) = today.checked_sub_days(chrono::Days::new(u64::from(offset))) else {
+            break;
+        };
+        let mut day = DayStats { hourly_keystrokes: vec![0; 24], ..DayStats::default() };
+        for minute in 9 * 60..17 * 60 {
+            let count = (rng.next() % 80) as u32;
+            day.minute_keystrokes.add(minute, count);
+            day.hourly_keystrokes[usize::from(minute / 60)] += u64::from(count);
+            day.keystrokes += u64::from(count);
+        }
+        day.most_active_hour =
+            (0..24u8).max_by_key(|&hour| (day.hourly_keystrokes[usize::from(hour)], std::cmp::Reverse(hour)));
+        for (&code, weight) in keys.iter().zip(&weights) {
+            let count = (day.keystrokes as f64 * weight / weight_sum) as u64;
+            if count > 0 {
+                let name = keymap::key_name(code);
+                *stats.key_counts.entry(name.clone()).or_insert(0) += count;
+                day.key_distribution.insert(name, count);
+            }
+        }
+        stats.total_keystrokes += day.keystrokes;
+        stats.daily_stats.insert(day_key(date), day);
+    }
+    stats
+}
+
+/// Progress of the run, as seen by the source.
+enum Mark {
+    /// The first batch was handed out
+    Started { allocations: Option<u64> },
+    /// The loop came back after the last batch
+    Finished { busy: Duration, allocations: Option<u64> },
+}
+
+/// Types the key mix in batches of [`BATCH`] events.
+struct SyntheticSource {
+    keys: Vec<u16>,
+    rng: Rng,
+    /// Presses still to type
+    presses: u64,
+    /// Time of the next synthetic press
+    clock: SystemTime,
+    /// When `read_events` last returned
+    returned: Option<Instant>,
+    /// Time the loop spent between polls, without its sleeps
+    busy: Duration,
+    marks: mpsc::Sender<Mark>,
+    finished: bool,
+}
+
+impl EventSource for SyntheticSource {
+    fn label(&self) -> &str {
+        "synthetic"
+    }
+
+    fn read_events(&mut self) -> Result<Vec<KeyEvent>> {
+        let now = Instant::now();
+        match self.returned {
+            None => {
+                let _ = self.marks.send(Mark::Started { allocations: allocations() });
+            }
+            Some(returned) if !self.finished => {
+                self.busy += now.saturating_duration_since(returned).saturating_sub(ACTIVE_POLL_INTERVAL);
+            }
+            Some(_) => {}
+        }
+        if self.presses == 0 {
+            if !self.finished {
+                self.finished = true;
+                let _ = self.marks.send(Mark::Finished { busy: self.busy, allocations: allocations() });
+            }
+            return Ok(Vec::new());
+        }
+
+        let presses = self.presses.min(BATCH as u64 / 2);
+        self.presses -= presses;
+        let mut events = Vec::with_capacity(presses as usize * 2);
+        for _ in 0..presses {
+            let code = self.keys[self.rng.skewed(self.keys.len())];
+            for state in [KeyState::Pressed, KeyState::Released] {
+                events.push(KeyEvent { code, state, time: self.clock });
+            }
+            self.clock += PRESS_GAP;
+        }
+        self.returned = Some(Instant::now());
+        Ok(events)
+    }
+}
+
+/// Runs the benchmark in a scratch directory.
+///
+/// Points the data, state and config directories at `scratch` for the rest
+/// of the process (see [`paths`]), so call it before other threads start.
+/// The default profile there gets the synthetic history; the logging thread
+/// then counts the events with the default config and saves as usual.
+///
+/// # Arguments
+/// * `options` - What to run
+/// * `scratch` - An empty directory, left with the files of the run
+///
+/// # Returns
+/// `Result<BenchReport>` - The measurements
+///
+/// # Example
+/// The throughput guards against regressions, with a threshold low enough
+/// for an unoptimized build on a slow machine:
+/// ```standalone_crate
+/// use ctrlq::bench::{self, BenchOptions};
+///
+/// let scratch = std::env::temp_dir().join(format!("ctrlq-bench-{}", std::process::id()));
+/// let options = BenchOptions { events: 40_000, distinct_keys: 60, days: 90 };
+/// let report = bench::run(options, &scratch)?;
+///
+/// assert_eq!(report.events, 40_000);
+/// assert!(report.events_per_sec > 5_000.0, "{:.0} events/s", report.events_per_sec);
+/// assert!(report.save_bytes > 0);
+/// // The logger archived all but the last two months
+/// assert!(report.unsplit_bytes > report.save_bytes);
+/// // Only counted where the binary installs the counting allocator
+/// assert_eq!(report.allocations_per_event, None);
+/// std::fs::remove_dir_all(&scratch)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn run(options: BenchOptions, scratch: &Path) -> Result<BenchReport> {
+    if options.events < 2 {
+        bail!("at least 2 events are needed, a press and its release");
+    }
+    std::fs::create_dir_all(scratch).with_context(|| format!("failed to create {}", scratch.display()))?;
+    unsafe {
+        for var in [paths::DATA_DIR_VAR, paths::STATE_DIR_VAR, paths::CONFIG_DIR_VAR] {
+            std::env::set_var(var, scratch);
+        }
+    }
+
+    let mut keys = key_pool();
+    keys.truncate(options.distinct_keys.max(1));
+    let data_file = profile::data_file(profile::DEFAULT_PROFILE)?;
+    KeyLogger::save_stats(&synthetic_history(options.days, &keys, today()), &data_file, false)?;
+    let unsplit_bytes = std::fs::metadata(&data_file)?.len();
+    let started = Instant::now();
+    std::hint::black_box(KeyLogger::load_stats(&data_file)?);
+    let unsplit_load_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    let (marks, mark_rx) = mpsc::channel();
+    let source = SyntheticSource {
+        keys: keys.clone(),
+        rng: Rng(SEED),
+        presses: options.events / 2,
+        clock: SystemTime::now(),
+        returned: None,
+        busy: Duration::ZERO,
+        marks,
+        finished: false,
+    };
+    let mut logger = KeyLogger::new(Vec::new(), Config::default(), Overrides::default(), profile::DEFAULT_PROFILE)?;
+    logger.log_from(vec![Box::new(source)]);
+    let channels = logger.start_logging()?;
+    let stopped = || anyhow::anyhow!("the logging thread stopped before the run finished");
+    let Mark::Started { allocations: start_allocations } = mark_rx.recv().map_err(|_| stopped())? else {
+        bail!("the run finished before it started");
+    };
+    let Mark::Finished { busy, allocations: end_allocations } = mark_rx.recv().map_err(|_| stopped())? else {
+        bail!("the run started twice");
+    };
+    channels.shutdown_tx.send(true)?;
+    logger.wait();
+    drop(logger);
+
+    let events = options.events / 2 * 2;
+    let allocations_per_event = start_allocations
+        .zip(end_allocations)
+        .map(|(start, end)| end.saturating_sub(start) as f64 / events as f64);
+
+    // The statistics as the loop holds them: the history minus archived days
+    let started = Instant::now();
+    let stats = KeyLogger::load_stats(&data_file)?;
+    let load_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let before = allocations();
+    let started = Instant::now();
+    for _ in 0..REPEATS {
+        let copy = std::hint::black_box(stats.clone());
+        std::hint::black_box(LiveSnapshot::from_stats(&copy));
+        std::hint::black_box(StatusSnapshot::from_stats(&copy, today()));
+    }
+    let snapshot_ms = started.elapsed().as_secs_f64() * 1000.0 / f64::from(REPEATS);
+    let snapshot_allocations =
+        before.zip(allocations()).map(|(before, after)| after.saturating_sub(before) as f64 / f64::from(REPEATS));
+
+    let save_file = scratch.join("bench-save.json");
+    let started = Instant::now();
+    for _ in 0..REPEATS {
+        KeyLogger::save_stats(&stats, &save_file, false)?;
+    }
+    let save_ms = started.elapsed().as_secs_f64() * 1000.0 / f64::from(REPEATS);
+    let save_bytes = std::fs::metadata(&save_file)?.len();
+
+    Ok(BenchReport {
+        events,
+        distinct_keys: keys.len(),
+        days: options.days,
+        events_per_sec: events as f64 / busy.as_secs_f64().max(f64::EPSILON),
+        allocations_per_event,
+        snapshot_ms,
+        snapshot_allocations,
+        save_ms,
+        save_bytes,
+        unsplit_load_ms,
+        unsplit_bytes,
+        load_ms,
+    })
+}
+
+/// A row of [`comparison_table`].
+struct Row {
+    label: &'static str,
+    value: Option<f64>,
+    baseline: Option<f64>,
+    format: fn(f64) -> String,
+}
+
+/// Writes a run as a table, next to a baseline run if given.
+///
+/// Changes are relative to the baseline; only events per second are better
+/// when they grow.
+///
+/// # Example
+/// ```
+/// use ctrlq::bench::{comparison_table, BenchReport};
+///
+/// let run = BenchReport {
+///     events: 1_000_000, distinct_keys: 120, days: 365,
+///     events_per_sec: 2_400_000.0, allocations_per_event: Some(0.5),
+///     snapshot_ms: 1.2, snapshot_allocations: Some(900.0),
+///     save_ms: 8.0, save_bytes: 1_500_000,
+///     unsplit_load_ms: 90.0, unsplit_bytes: 4_000_000, load_ms: 15.0,
+/// };
+/// let baseline = BenchReport { events_per_sec: 2_000_000.0, save_ms: 10.0, ..run.clone() };
+/// let table = comparison_table(&run, Some(&baseline));
+///
+/// assert!(table.contains("Events/s"));
+/// assert!(table.lines().any(|line| line.contains("2,400,000") && line.contains("+20.0%")));
+/// assert!(table.lines().any(|line| line.starts_with("Full save") && line.contains("-20.0%")));
+/// ```
+pub fn comparison_table(run: &BenchReport, baseline: Option<&BenchReport>) -> String {
+    let count = |value: f64| numbers::format_count(value.round() as u64);
+    let ratio = |value: f64| format!("{:.2}", value);
+    let millis = |value: f64| format!("{:.2} ms", value);
+    let rows = [
+        Row {
+            label: "Events/s",
+            value: Some(run.events_per_sec),
+            baseline: baseline.map(|b| b.events_per_sec),
+            format: count,
+        },
+        Row {
+            label: "Allocations/event",
+            value: run.allocations_per_event,
+            baseline: baseline.and_then(|b| b.allocations_per_event),
+            format: ratio,
+        },
+        Row {
+            label: "Snapshot",
+            value: Some(run.snapshot_ms),
+            baseline: baseline.map(|b| b.snapshot_ms),
+            format: millis,
+        },
+        Row {
+            label: "Snapshot allocations",
+            value: run.snapshot_allocations,
+            baseline: baseline.and_then(|b| b.snapshot_allocations),
+            format: count,
+        },
+        Row { label: "Full save", value: Some(run.save_ms), baseline: baseline.map(|b| b.save_ms), format: millis },
+        Row {
+            label: "Data file bytes",
+            value: Some(run.save_bytes as f64),
+            baseline: baseline.map(|b| b.save_bytes as f64),
+            format: count,
+        },
+        Row {
+            label: "Startup load",
+            value: Some(run.load_ms),
+            baseline: baseline.map(|b| b.load_ms),
+            format: millis,
+        },
+        Row {
+            label: "Unsplit startup load",
+            value: Some(run.unsplit_load_ms),
+            baseline: baseline.map(|b| b.unsplit_load_ms),
+            format: millis,
+        },
+        Row {
+            label: "Unsplit file bytes",
+            value: Some(run.unsplit_bytes as f64),
+            baseline: baseline.map(|b| b.unsplit_bytes as f64),
+            format: count,
+        },
+    ];
+
+    let show = |value: Option<f64>, format: fn(f64) -> String| value.map_or_else(|| "-".to_string(), format);
+    let mut lines = Vec::new();
+    if baseline.is_some() {
+        lines.push(format!("{:<22} {:>14} {:>14} {:>9}", "Metric", "This run", "Baseline", "Change"));
+    } else {
+        lines.push(format!("{:<22} {:>14}", "Metric", "This run"));
+    }
+    for row in rows {
+        let mut line = format!("{:<22} {:>14}", row.label, show(row.value, row.format));
+        if baseline.is_some() {
+            let change = match (row.value, row.baseline) {
+                (Some(value), Some(base)) if base > 0.0 => format!("{:+.1}%", (value - base) / base * 100.0),
+                _ => "-".to_string(),
+            };
+            line.push_str(&format!(" {:>14} {:>9}", show(row.baseline, row.format), change));
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,544 @@
+//! # Bindings Module
+//!
+//! Keys of the terminal UI, from the `[keys]` table of the config. Each
+//! entry binds an action to one key or a list of keys:
+//!
+//! ```toml
+//! [keys]
+//! next_tab = ["l", "Tab"]
+//! prev_tab = ["h", "shift+tab"]
+//! quit = "ctrl+q"
+//! pause = "F5"
+//! ```
+//!
+//! A key is a character (`q`, `?`, `R`), a named key (`Tab`, `Enter`,
+//! `Esc`, `Space`, `Backspace`, `Up`, `Down`, `Left`, `Right`, `PageUp`,
+//! `PageDown`, `Home`, `End`, `F1` to `F12`) or either of them after
+//! `ctrl+`, `alt+` or `shift+`. Names are case-insensitive, characters are
+//! not: `R` is Shift+R.
+//!
+//! Actions left out keep their default keys (see [`UiAction::default_keys`]),
+//! except keys the table gives to another action, and `[]` unbinds an
+//! action. Unknown actions, unreadable keys and a key bound to two actions
+//! make the config fail to load. Keys that only mean something on one tab,
+//! such as `v` on the Heatmap, are not configurable; a configured action
+//! wins over them.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Actions of the terminal UI that keys can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum UiAction {
+    /// Stop logging and exit
+    Quit,
+    /// Next tab
+    NextTab,
+    /// Previous tab
+    PrevTab,
+    /// Open the command palette
+    Commands,
+    /// Open the reset menu
+    Reset,
+    /// Pause counting, or resume it
+    Pause,
+    /// Count outside `log_hours` for the rest of the day, or stop
+    OutsideHours,
+    /// Save the statistics without waiting
+    Save,
+    /// Show or hide the help
+    Help,
+    /// Cycle the key class filter
+    ClassFilter,
+    /// Pick another keyboard
+    Device,
+    /// Tag the current session
+    Tag,
+    /// Show the problems of the logging thread
+    Problems,
+    /// Close whatever is open
+    Close,
+    /// Open or pick the selected entry
+    Select,
+    /// Move up in a list
+    ScrollUp,
+    /// Move down in a list
+    ScrollDown,
+    /// Move left in a timeline
+    ScrollLeft,
+    /// Move right in a timeline
+    ScrollRight,
+    /// Move a page up
+    PageUp,
+    /// Move a page down
+    PageDown,
+    /// Move to the first entry
+    Home,
+    /// Move to the last entry
+    End,
+}
+
+impl UiAction {
+    /// All actions, in the order of the help.
+    pub const ALL: [UiAction; 23] = [
+        Self::NextTab,
+        Self::PrevTab,
+        Self::ScrollUp,
+        Self::ScrollDown,
+        Self::ScrollLeft,
+        Self::ScrollRight,
+        Self::PageUp,
+        Self::PageDown,
+        Self::Home,
+        Self::End,
+        Self::Select,
+        Self::ClassFilter,
+        Self::Reset,
+        Self::Pause,
+        Self::OutsideHours,
+        Self::Save,
+        Self::Problems,
+        Self::Device,
+        Self::Tag,
+        Self::Commands,
+        Self::Help,
+        Self::Close,
+        Self::Quit,
+    ];
+
+    /// Returns the name of the action in the `[keys]` table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::NextTab => "next_tab",
+            Self::PrevTab => "prev_tab",
+            Self::Commands => "commands",
+            Self::Reset => "reset",
+            Self::Pause => "pause",
+            Self::OutsideHours => "outside_hours",
+            Self::Save => "save",
+            Self::Help => "help",
+            Self::ClassFilter => "class_filter",
+            Self::Device => "device",
+            Self::Tag => "tag",
+            Self::Problems => "problems",
+            Self::Close => "close",
+            Self::Select => "select",
+            Self::ScrollUp => "scroll_up",
+            Self::ScrollDown => "scroll_down",
+            Self::ScrollLeft => "scroll_left",
+            Self::ScrollRight => "scroll_right",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+            Self::Home => "home",
+            Self::End => "end",
+        }
+    }
+
+    /// What the action does, shown in the help.
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::NextTab => "next tab",
+            Self::PrevTab => "previous tab",
+            Self::Commands => "command palette, type part of a command below",
+            Self::Reset => "reset menu: session, today or everything",
+            Self::Pause => "pause counting, or resume it",
+            Self::OutsideHours => "count outside log_hours until midnight, or stop",
+            Self::Save => "save the statistics now",
+            Self::Help => "toggle this help",
+            Self::ClassFilter => "cycle key class filter (Top Keys, Heatmap)",
+            Self::Device => "pick another keyboard to log, without restarting",
+            Self::Tag => "tag the current session, with an optional note",
+            Self::Problems => "show problems of the logging thread (saves, devices, ...)",
+            Self::Close => "close help, problems, details and browsing",
+            Self::Select => "session details (Sessions), pick a key (History)",
+            Self::ScrollUp => "previous session (Sessions) or key (History)",
+            Self::ScrollDown => "next session (Sessions) or key (History)",
+            Self::ScrollLeft => "earlier day (History) or minute (Timeline)",
+            Self::ScrollRight => "later day (History) or minute (Timeline)",
+            Self::PageUp => "page up (Sessions), earlier week (History)",
+            Self::PageDown => "page down (Sessions), later week (History)",
+            Self::Home => "back to the current session (Sessions)",
+            Self::End => "oldest session (Sessions)",
+        }
+    }
+
+    /// Keys of the action when the config doesn't bind it.
+    pub fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Self::Quit => &["q"],
+            Self::NextTab => &["Tab"],
+            Self::PrevTab => &["shift+Tab"],
+            Self::Commands => &[":"],
+            Self::Reset => &["r"],
+            Self::OutsideHours => &["o"],
+            Self::Pause | Self::Save => &[],
+            Self::Help => &["?"],
+            Self::ClassFilter => &["c"],
+            Self::Device => &["d"],
+            Self::Tag => &["n"],
+            Self::Problems => &["e"],
+            Self::Close => &["Esc"],
+            Self::Select => &["Enter"],
+            Self::ScrollUp => &["Up", "k"],
+            Self::ScrollDown => &["Down", "j"],
+            Self::ScrollLeft => &["Left", "h"],
+            Self::ScrollRight => &["Right", "l"],
+            Self::PageUp => &["PageUp"],
+            Self::PageDown => &["PageDown"],
+            Self::Home => &["Home"],
+            Self::End => &["End"],
+        }
+    }
+
+    /// Parses an action name of the `[keys]` table.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+impl TryFrom<String> for UiAction {
+    type Error = anyhow::Error;
+
+    fn try_from(name: String) -> Result<Self> {
+        match Self::parse(&name) {
+            Some(action) => Ok(action),
+            None => {
+                let names: Vec<&str> = Self::ALL.iter().map(|action| action.name()).collect();
+                bail!("unknown action '{}', expected one of {}", name, names.join(", "))
+            }
+        }
+    }
+}
+
+impl From<UiAction> for String {
+    fn from(action: UiAction) -> Self {
+        action.name().to_string()
+    }
+}
+
+/// A key without modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Key {
+    /// A character, `R` for Shift+R
+    Char(char),
+    /// A function key, `F(5)` for F5
+    F(u8),
+    /// Tab
+    Tab,
+    /// Shift+Tab
+    BackTab,
+    /// Enter
+    Enter,
+    /// Escape
+    Esc,
+    /// Backspace
+    Backspace,
+    /// Arrow up
+    Up,
+    /// Arrow down
+    Down,
+    /// Arrow left
+    Left,
+    /// Arrow right
+    Right,
+    /// Page up
+    PageUp,
+    /// Page down
+    PageDown,
+    /// Home
+    Home,
+    /// End
+    End,
+}
+
+/// Named keys by their lowercase names, first the one shown.
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("tab", Key::Tab),
+    ("enter", Key::Enter),
+    ("return", Key::Enter),
+    ("esc", Key::Esc),
+    ("escape", Key::Esc),
+    ("backspace", Key::Backspace),
+    ("space", Key::Char(' ')),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("pageup", Key::PageUp),
+    ("pgup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("pgdn", Key::PageDown),
+    ("home", Key::Home),
+    ("end", Key::End),
+];
+
+/// A key with its modifiers, as bound in the `[keys]` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeySpec {
+    /// The key
+    pub key: Key,
+    /// Whether Ctrl is held
+    pub ctrl: bool,
+    /// Whether Alt is held
+    pub alt: bool,
+    /// Whether Shift is held, for keys other than characters and Tab
+    pub shift: bool,
+}
+
+impl KeySpec {
+    /// Creates a key spec, folding Shift into characters and Tab the way
+    /// terminals report them.
+    ///
+    /// # Arguments
+    /// * `key` - The key
+    /// * `ctrl` - Whether Ctrl is held
+    /// * `alt` - Whether Alt is held
+    /// * `shift` - Whether Shift is held
+    pub fn new(key: Key, ctrl: bool, alt: bool, shift: bool) -> Self {
+        let (key, shift) = match key {
+            Key::Char(c) if shift => (Key::Char(c.to_uppercase().next().unwrap_or(c)), false),
+            // Terminals can't tell Ctrl+A from Ctrl+Shift+A
+            Key::Char(c) if ctrl => (Key::Char(c.to_ascii_lowercase()), false),
+            Key::Char(_) => (key, false),
+            Key::Tab if shift => (Key::BackTab, false),
+            Key::BackTab => (key, false),
+            _ => (key, shift),
+        };
+        Self { key, ctrl, alt, shift }
+    }
+
+    /// Parses a key as written in the `[keys]` table.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::bindings::{Key, KeySpec};
+    ///
+    /// assert_eq!(KeySpec::parse("q").unwrap(), KeySpec::new(Key::Char('q'), false, false, false));
+    /// assert_eq!(KeySpec::parse("Ctrl+C").unwrap(), KeySpec::new(Key::Char('c'), true, false, false));
+    /// assert_eq!(KeySpec::parse("shift+tab").unwrap().key, Key::BackTab);
+    /// assert_eq!(KeySpec::parse("F5").unwrap().to_string(), "F5");
+    /// assert_eq!(KeySpec::parse("alt++").unwrap().to_string(), "Alt++");
+    ///
+    /// assert!(KeySpec::parse("ctrl+").is_err());
+    /// assert!(KeySpec::parse("F13").is_err());
+    /// assert!(KeySpec::parse("hyper+x").is_err());
+    /// ```
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        // The key itself may be `+`, as in `ctrl++`
+        let (modifiers, key) = match spec.strip_suffix('+') {
+            Some(rest) if rest.is_empty() || rest.ends_with('+') => (rest.strip_suffix('+').unwrap_or(rest), "+"),
+            _ => match spec.rsplit_once('+') {
+                Some((modifiers, key)) => (modifiers, key),
+                None => ("", spec),
+            },
+        };
+        let (mut ctrl, mut alt, mut shift) = (false, false, false);
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => ctrl = true,
+                "alt" => alt = true,
+                "shift" => shift = true,
+                _ => bail!("unknown modifier '{}' in key '{}', expected ctrl, alt or shift", modifier, spec),
+            }
+        }
+
+        let lower = key.to_lowercase();
+        let mut chars = key.chars();
+        let key = if let (Some(c), None) = (chars.next(), chars.next()) {
+            Key::Char(c)
+        } else if let Some(&(_, key)) = KEY_NAMES.iter().find(|(name, _)| *name == lower) {
+            key
+        } else if let Some(number) = lower.strip_prefix('f').and_then(|number| number.parse::<u8>().ok())
+            && (1..=12).contains(&number)
+        {
+            Key::F(number)
+        } else {
+            bail!("unknown key '{}', expected a character, a name such as Tab or PageUp, or F1 to F12", spec);
+        };
+        Ok(Self::new(key, ctrl, alt, shift))
+    }
+}
+
+impl std::fmt::Display for KeySpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        match self.key {
+            Key::Char(' ') => write!(f, "Space"),
+            Key::Char(c) if self.ctrl || self.alt => write!(f, "{}", c.to_ascii_uppercase()),
+            Key::Char(c) => write!(f, "{}", c),
+            Key::F(number) => write!(f, "F{}", number),
+            Key::Tab => write!(f, "Tab"),
+            Key::BackTab => write!(f, "Shift+Tab"),
+            Key::Enter => write!(f, "Enter"),
+            Key::Esc => write!(f, "Esc"),
+            Key::Backspace => write!(f, "Backspace"),
+            Key::Up => write!(f, "↑"),
+            Key::Down => write!(f, "↓"),
+            Key::Left => write!(f, "←"),
+            Key::Right => write!(f, "→"),
+            Key::PageUp => write!(f, "PgUp"),
+            Key::PageDown => write!(f, "PgDn"),
+            Key::Home => write!(f, "Home"),
+            Key::End => write!(f, "End"),
+        }
+    }
+}
+
+/// Keys of one action as written in the config: one key or several.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawKeys {
+    /// `"q"`
+    One(String),
+    /// `["q", "ctrl+c"]`
+    Many(Vec<String>),
+}
+
+/// Keys of one action in the `[keys]` table, checked one entry at a time
+/// so that errors point at the entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawKeys", into = "RawKeys")]
+struct BoundKeys {
+    /// The keys, in the order they were given
+    specs: Vec<KeySpec>,
+    /// The keys as written, for saving the config again
+    raw: RawKeys,
+}
+
+impl TryFrom<RawKeys> for BoundKeys {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawKeys) -> Result<Self> {
+        let specs = match &raw {
+            RawKeys::One(spec) => vec![KeySpec::parse(spec)?],
+            RawKeys::Many(specs) => specs.iter().map(|spec| KeySpec::parse(spec)).collect::<Result<Vec<_>>>()?,
+        };
+        Ok(Self { specs, raw })
+    }
+}
+
+impl From<BoundKeys> for RawKeys {
+    fn from(keys: BoundKeys) -> Self {
+        keys.raw
+    }
+}
+
+/// The keys of the terminal UI, see the module docs.
+///
+/// # Example
+/// ```
+/// use ctrlq::bindings::{KeySpec, UiAction};
+/// use ctrlq::config::Config;
+///
+/// let config: Config = toml::from_str(r#"
+///     [keys]
+///     next_tab = ["l", "Tab"]
+///     prev_tab = "h"
+///     pause = "F5"
+/// "#)?;
+/// let key = |spec| config.keys.action(&KeySpec::parse(spec).unwrap());
+///
+/// assert_eq!(key("l"), Some(UiAction::NextTab));
+/// assert_eq!(key("F5"), Some(UiAction::Pause));
+/// // Unbound actions keep their defaults, but not the keys taken from them
+/// assert_eq!(key("q"), Some(UiAction::Quit));
+/// assert_eq!(key("Right"), Some(UiAction::ScrollRight));
+/// assert_eq!(key("shift+tab"), None);
+/// assert_eq!(config.keys.label(UiAction::ScrollLeft), "←");
+///
+/// let twice = "[keys]\nquit = \"x\"\nhelp = \"x\"";
+/// let error = toml::from_str::<Config>(twice).unwrap_err().to_string();
+/// assert!(error.contains("key 'x' is bound to both quit and help"));
+/// assert!(toml::from_str::<Config>("[keys]\nquit = \"ctrl+\"").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "BTreeMap<UiAction, BoundKeys>", into = "BTreeMap<UiAction, BoundKeys>")]
+pub struct KeyBindings {
+    /// Action of each bound key
+    actions: BTreeMap<KeySpec, UiAction>,
+    /// Keys of each action, in the order they were given
+    keys: BTreeMap<UiAction, Vec<KeySpec>>,
+    /// The table as written, for saving the config again
+    table: BTreeMap<UiAction, BoundKeys>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::try_from(BTreeMap::new()).expect("the default keys are valid")
+    }
+}
+
+impl TryFrom<BTreeMap<UiAction, BoundKeys>> for KeyBindings {
+    type Error = anyhow::Error;
+
+    fn try_from(table: BTreeMap<UiAction, BoundKeys>) -> Result<Self> {
+        let mut actions = BTreeMap::new();
+        let mut keys = BTreeMap::new();
+        for (&action, bound) in &table {
+            for &spec in &bound.specs {
+                if let Some(other) = actions.insert(spec, action) {
+                    bail!("key '{}' is bound to both {} and {}", spec, other.name(), action.name());
+                }
+            }
+            keys.insert(action, bound.specs.clone());
+        }
+
+        // Defaults of the actions left out, except keys the table took
+        for action in UiAction::ALL {
+            if keys.contains_key(&action) {
+                continue;
+            }
+            let mut defaults = Vec::new();
+            for spec in action.default_keys() {
+                let spec = KeySpec::parse(spec)?;
+                if !actions.contains_key(&spec) {
+                    defaults.push(spec);
+                }
+            }
+            for &spec in &defaults {
+                actions.insert(spec, action);
+            }
+            keys.insert(action, defaults);
+        }
+        Ok(Self { actions, keys, table })
+    }
+}
+
+impl From<KeyBindings> for BTreeMap<UiAction, BoundKeys> {
+    fn from(bindings: KeyBindings) -> Self {
+        bindings.table
+    }
+}
+
+impl KeyBindings {
+    /// Returns the action bound to a key, if any.
+    pub fn action(&self, spec: &KeySpec) -> Option<UiAction> {
+        self.actions.get(spec).copied()
+    }
+
+    /// Returns the keys bound to an action.
+    pub fn keys(&self, action: UiAction) -> &[KeySpec] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the keys of an action for the help, such as `↑/k`, or `-`
+    /// if it is unbound.
+    pub fn label(&self, action: UiAction) -> String {
+        let keys: Vec<String> = self.keys(action).iter().map(KeySpec::to_string).collect();
+        if keys.is_empty() { "-".to_string() } else { keys.join("/") }
+    }
+}
@@ -0,0 +1,295 @@
+//! # Calibration Module
+//!
+//! A short typing test that compares ctrlq's WPM estimate with the speed a
+//! typing test would measure, for `ctrlq calibrate`.
+//!
+//! The UI shows a sentence while the logger forwards every keystroke of the
+//! keyboard as the character it typed ([`TypedKey`]). [`TypingTest`] scores
+//! them against the sentence the way typing tests do: WPM counts the
+//! characters that ended up correct, accuracy the keystrokes that hit the
+//! right character. The same keystrokes also give the WPM ctrlq would have
+//! estimated from its character key counts, and their ratio is the
+//! calibration factor.
+//!
+//! Results are kept per keyboard in `calibration.json` in the data
+//! directory, newest last; with `calibrated_wpm = true` in the config the
+//! Overview shows the WPM estimate multiplied by the latest factor.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Sentences of the typing test. Each uses capitals and punctuation, so
+/// the test also covers Shift.
+pub const PROMPTS: &[&str] = &[
+    "The quick brown fox jumps over the lazy dog, then naps in the sun until dinner is ready.",
+    "Pack my box with five dozen liquor jugs; the movers arrive at 9 and leave by noon.",
+    "Sphinx of black quartz, judge my vow! Few quiz answers were right on the first try.",
+    "How vexingly quick daft zebras jump. Keep your wrists level and your shoulders loose.",
+    "Good code reads like a story: short lines, clear names, and no surprises at the end.",
+];
+
+/// What a keystroke did to the text of a typing test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Typed {
+    /// Typed a character, with Shift and Caps Lock applied
+    Char(char),
+    /// Removed the last character
+    Backspace,
+}
+
+/// A keystroke forwarded by the logger while a typing test runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedKey {
+    /// Label of the keyboard it came from
+    pub device: String,
+    /// evdev key code
+    pub code: u16,
+    /// What it typed
+    pub typed: Typed,
+    /// Kernel timestamp of the key event
+    pub time: SystemTime,
+}
+
+/// Picks a test sentence for a number such as the current time.
+pub fn prompt(seed: u64) -> &'static str {
+    PROMPTS[(seed % PROMPTS.len() as u64) as usize]
+}
+
+/// A typing test in progress.
+#[derive(Debug, Clone)]
+pub struct TypingTest {
+    /// The sentence to type
+    pub prompt: String,
+    /// What was typed so far, after backspaces
+    pub typed: String,
+    /// Time of the first and the latest keystroke
+    span: Option<(SystemTime, SystemTime)>,
+    /// Keystrokes ctrlq counts as characters, see [`crate::keymap::is_character_key`]
+    char_keystrokes: u64,
+    /// Keystrokes that typed the expected character
+    correct_keystrokes: u64,
+    /// Keystrokes per keyboard, to tell which one the test measured
+    devices: HashMap<String, u64>,
+}
+
+impl TypingTest {
+    /// Starts a test of the given sentence.
+    pub fn new(prompt: &str) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            typed: String::new(),
+            span: None,
+            char_keystrokes: 0,
+            correct_keystrokes: 0,
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Applies a keystroke. Keystrokes after the end of the sentence are ignored.
+    pub fn input(&mut self, key: &TypedKey) {
+        if self.is_finished() {
+            return;
+        }
+        let start = self.span.map_or(key.time, |(start, _)| start);
+        self.span = Some((start, key.time));
+        *self.devices.entry(key.device.clone()).or_insert(0) += 1;
+        if crate::keymap::is_character_key(key.code) {
+            self.char_keystrokes += 1;
+        }
+        match key.typed {
+            Typed::Char(c) => {
+                if self.prompt.chars().nth(self.typed.chars().count()) == Some(c) {
+                    self.correct_keystrokes += 1;
+                }
+                self.typed.push(c);
+            }
+            Typed::Backspace => {
+                self.typed.pop();
+            }
+        }
+    }
+
+    /// Whether the whole sentence was typed.
+    pub fn is_finished(&self) -> bool {
+        self.typed.chars().count() >= self.prompt.chars().count()
+    }
+
+    /// Characters of the typed text that match the sentence.
+    pub fn correct_chars(&self) -> usize {
+        self.typed.chars().zip(self.prompt.chars()).filter(|(typed, expected)| typed == expected).count()
+    }
+
+    /// Scores the test.
+    ///
+    /// # Arguments
+    /// * `at` - When the test ended, stored with the result
+    ///
+    /// # Returns
+    /// `Option<Calibration>` - The result, or None until the sentence is
+    /// typed or if the keystrokes took no time at all
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::calibration::{Typed, TypedKey, TypingTest};
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let mut test = TypingTest::new("ab ab");
+    /// let start = SystemTime::now();
+    /// // a, b, x (wrong), backspace, space, a, b: one keystroke every 2 seconds
+    /// let keys = [(30, Typed::Char('a')), (48, Typed::Char('b')), (45, Typed::Char('x')),
+    ///     (14, Typed::Backspace), (57, Typed::Char(' ')), (30, Typed::Char('a')), (48, Typed::Char('b'))];
+    /// for (i, (code, typed)) in keys.into_iter().enumerate() {
+    ///     assert!(!test.is_finished());
+    ///     let time = start + Duration::from_secs(2 * i as u64);
+    ///     test.input(&TypedKey { device: "Keychron K2".to_string(), code, typed, time });
+    /// }
+    ///
+    /// let result = test.result(chrono::Utc::now()).unwrap();
+    /// assert_eq!(result.device, "Keychron K2");
+    /// // 5 correct characters in 12 seconds: 1 word in 0.2 minutes
+    /// assert!((result.wpm - 5.0).abs() < 1e-9);
+    /// // 6 character keystrokes, one of them wrong
+    /// assert!((result.accuracy - 5.0 / 6.0).abs() < 1e-9);
+    /// assert!((result.estimated_wpm - 6.0).abs() < 1e-9);
+    /// assert!((result.factor - 5.0 / 6.0).abs() < 1e-9);
+    /// ```
+    pub fn result(&self, at: DateTime<Utc>) -> Option<Calibration> {
+        if !self.is_finished() {
+            return None;
+        }
+        let (start, end) = self.span?;
+        let minutes = end.duration_since(start).ok()?.as_secs_f64() / 60.0;
+        if minutes <= 0.0 || self.char_keystrokes == 0 {
+            return None;
+        }
+        let wpm = self.correct_chars() as f64 / 5.0 / minutes;
+        let estimated_wpm = self.char_keystrokes as f64 / 5.0 / minutes;
+        let device = self
+            .devices
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(device, _)| device.clone())
+            .unwrap_or_default();
+        Some(Calibration {
+            at,
+            device,
+            wpm,
+            accuracy: self.correct_keystrokes as f64 / self.char_keystrokes as f64,
+            estimated_wpm,
+            factor: wpm / estimated_wpm,
+        })
+    }
+}
+
+/// The result of one typing test.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    /// When the test was taken
+    pub at: DateTime<Utc>,
+    /// Label of the keyboard used for most of the test
+    pub device: String,
+    /// Speed measured against the sentence, from correct characters only
+    pub wpm: f64,
+    /// Share of character keystrokes that typed the expected character
+    pub accuracy: f64,
+    /// What ctrlq's WPM formula gives for the same keystrokes
+    pub estimated_wpm: f64,
+    /// `wpm / estimated_wpm`, applied to displayed WPM with `calibrated_wpm`
+    pub factor: f64,
+}
+
+/// Every calibration taken, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationHistory {
+    /// The results
+    pub runs: Vec<Calibration>,
+}
+
+impl CalibrationHistory {
+    /// Returns the history file.
+    ///
+    /// # Arguments
+    /// * `data_file` - Path of the statistics data file
+    ///
+    /// # Returns
+    /// `PathBuf` - `calibration.json` next to the data file, shared by all
+    /// profiles since a keyboard types the same in every one
+    pub fn path(data_file: &Path) -> PathBuf {
+        data_file.parent().unwrap_or(Path::new(".")).join("calibration.json")
+    }
+
+    /// Loads the history, or an empty one if the file doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("invalid calibration history in {}", path.display()))
+    }
+
+    /// Adds a result and writes the history.
+    ///
+    /// # Arguments
+    /// * `path` - The history file
+    /// * `run` - Result of a typing test
+    ///
+    /// # Returns
+    /// `Result<()>` - Success or an error naming the file
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, Utc};
+    /// use ctrlq::calibration::{Calibration, CalibrationHistory};
+    ///
+    /// let dir = std::env::temp_dir().join(format!("ctrlq-calibration-doc-{}", std::process::id()));
+    /// let path = CalibrationHistory::path(&dir.join("keystroke_data.json"));
+    /// let run = |device: &str, factor: f64, days_ago: i64| Calibration {
+    ///     at: Utc::now() - Duration::days(days_ago),
+    ///     device: device.to_string(),
+    ///     wpm: 80.0 * factor,
+    ///     accuracy: 0.97,
+    ///     estimated_wpm: 80.0,
+    ///     factor,
+    /// };
+    ///
+    /// let mut history = CalibrationHistory::load(&path)?;
+    /// history.record(&path, run("Keychron K2", 0.9, 2))?;
+    /// history.record(&path, run("ThinkPad", 1.1, 1))?;
+    /// history.record(&path, run("Keychron K2", 0.8, 0))?;
+    ///
+    /// let history = CalibrationHistory::load(&path)?;
+    /// assert_eq!(history.runs.len(), 3);
+    /// assert_eq!(history.factor_for(Some("Keychron K2")), Some(0.8));
+    /// assert_eq!(history.factor_for(Some("ThinkPad")), Some(1.1));
+    /// // Keyboards without a calibration use the latest one
+    /// assert_eq!(history.factor_for(Some("Laptop")), Some(0.8));
+    /// std::fs::remove_dir_all(&dir)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn record(&mut self, path: &Path, run: Calibration) -> Result<()> {
+        self.runs.push(run);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Returns the latest calibration of a keyboard.
+    pub fn latest(&self, device: &str) -> Option<&Calibration> {
+        self.runs.iter().rev().find(|run| run.device == device)
+    }
+
+    /// Returns the factor for a keyboard: its latest calibration, or the
+    /// latest of any keyboard if it has none.
+    pub fn factor_for(&self, device: Option<&str>) -> Option<f64> {
+        device
+            .and_then(|device| self.latest(device))
+            .or(self.runs.last())
+            .map(|run| run.factor)
+    }
+}
@@ -0,0 +1,564 @@
+//! # Configuration Module
+//!
+//! Loads user configuration from `config.toml` in the ctrlq config
+//! directory. Every field has a sensible default, so a missing file or a
+//! partially filled one behaves exactly like the built-in defaults.
+//!
+//! A running logger reloads the file when it changes or on `SIGHUP`.
+//! Everything except `devices`, `data_dir`, `anonymize`, `case_sensitive`,
+//! `tick_ms`, `keys` and the `xkb_` settings takes effect immediately; changing
+//! those needs a restart.
+//!
+//! `ctrlq setup` writes this file for new users.
+//!
+//! ## Example
+//! ```toml
+//! # Keyboards to log when no --device is given
+//! devices = ["/dev/input/by-id/usb-Keychron_K2-event-kbd"]
+//!
+//! # Where data files are kept (default: ~/.local/share/ctrlq)
+//! data_dir = "/home/you/.local/share/ctrlq"
+//!
+//! # Don't count volume keys or mouse side buttons
+//! exclude_classes = ["media", "mouse"]
+//!
+//! # Keystrokes per day to aim for
+//! daily_goal = 15000
+//!
+//! # Seconds between automatic saves of the data file
+//! save_interval = 30
+//!
+//! # On battery (feature `battery`): seconds between saves, and KiB of raw log
+//! # records collected before they are written (see the power module)
+//! battery_save_interval = 300
+//! battery_raw_log_kb = 256
+//!
+//! # Indent the data file for reading it by hand (same as --pretty-json)
+//! pretty_json = false
+//!
+//! # Also save the last 100 keys typed, which are otherwise kept in memory
+//! # for the Recent Keys of the UI only
+//! persist_sequences = false
+//!
+//! # Seconds without key events before the logger slows down to save power
+//! idle_threshold = 5
+//!
+//! # Milliseconds a key may stay down before its hold time counts as an
+//! # anomaly; a press without release or auto-repeat for this long is dropped
+//! max_hold_ms = 5000
+//!
+//! # Minutes without any key events before checking whether another process
+//! # grabbed the keyboard (0 = never, same as --warn-idle-minutes)
+//! warn_idle_minutes = 30
+//!
+//! # Minutes after startup in which the logged keyboard must see this many
+//! # character key presses; if another keyboard sees them instead, ctrlq
+//! # suggests switching to it (0 = don't check)
+//! silent_device_minutes = 3
+//! silent_device_presses = 20
+//!
+//! # Characters of a device name shown before it is cut off with "…" (0 = never)
+//! device_name_width = 40
+//!
+//! # Between groups of digits in counts such as 3,482,119: ",", ".", " ",
+//! # "'" or "" for none; with "." or " " the decimal mark is ","
+//! thousands_separator = ","
+//!
+//! # Milliseconds between UI updates; raise it over slow SSH links (same as --tick-ms)
+//! tick_ms = 250
+//!
+//! # Days that keep per-minute activity; `ctrlq compact` reduces older days
+//! # to hourly counts (0 = keep it forever)
+//! minute_days = 90
+//!
+//! # Most distinct keys counted one by one; further new keys are counted as
+//! # UNKNOWN, so a misbehaving device can't bloat the data file (0 = no limit)
+//! max_tracked_keys = 1000
+//!
+//! # Count keystrokes reported with `ctrlq ping` (e.g. typed over SSH) as
+//! # typed characters, so they count towards WPM
+//! remote_wpm = false
+//!
+//! # Desktop notifications (requires the `notifications` feature)
+//! notifications = true
+//!
+//! # Record only the class of each key, never which key (same as --anonymize)
+//! anonymize = false
+//!
+//! # Count `A` apart from `a` and `!` apart from `1` (same as --case-sensitive)
+//! case_sensitive = false
+//!
+//! # Write a Markdown summary of each finished week to summaries/ in the data
+//! # directory (false is the same as --no-auto-summary)
+//! auto_summary = true
+//!
+//! # Also show typing speed multiplied by the factor of `ctrlq calibrate`
+//! calibrated_wpm = false
+//!
+//! # Stop counting while the screen is locked, so passwords and PINs typed
+//! # there stay out (needs logind; same as --pause-on-lock)
+//! pause_on_lock = false
+//!
+//! # Suggest a break (in the UI and as a notification) after this many
+//! # minutes of typing without 5 minutes away from the keyboard
+//! break_reminders = false
+//! break_after_minutes = 50
+//!
+//! # Text file offered after English and code by 'u' on the Top Keys tab,
+//! # to compare your keys with (see the surprise module)
+//! unusual_reference = "/home/you/notes.md"
+//!
+//! # XKB layouts for counting typed characters (feature `xkb`, same as
+//! # --xkb-layout); unset reads /etc/default/keyboard, "" turns it off
+//! xkb_layout = "us,hu"
+//! xkb_variant = ""
+//! # Options of the layout switch hotkey (default: from /etc/default/keyboard)
+//! xkb_options = "grp:alt_shift_toggle"
+//!
+//! # Only log keystrokes in these hours; a [log_hours] table sets them by
+//! # weekday (see the schedule module)
+//! log_hours = "09:00-18:00"
+//!
+//! # Shortcuts counted as named actions, next to the built-in copy, paste,
+//! # cut, undo, redo, save, find and alt-tab; a built-in name replaces its
+//! # shortcuts (see the actions module)
+//! [actions]
+//! "build" = "CTRL+SHIFT+B"
+//!
+//! # Shell commands run on session_end, daily_goal_reached, save and
+//! # record_broken, never as root (see the hooks module; --no-hooks turns
+//! # them off)
+//! [hooks]
+//! session_end = "notify-send \"$CTRLQ_SESSION_WPM WPM\""
+//!
+//! # Keys of the terminal UI by action; actions left out keep their default
+//! # keys (see the bindings module)
+//! [keys]
+//! next_tab = ["l", "Tab"]
+//! prev_tab = ["h", "shift+Tab"]
+//!
+//! # Keystrokes not counted: any rule that matches suppresses the key (see
+//! # the ignore module)
+//! [[ignore]]
+//! device = "Keychron"
+//! keys = ["numpad"]
+//! between = ["22:00", "07:00"]
+//! ```
+
+use crate::bindings::KeyBindings;
+use crate::ignore::IgnoreRule;
+use crate::keymap::{self, KeyClass};
+use crate::numbers::Separator;
+use crate::paths;
+use crate::schedule::Schedule;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// User configuration for ctrlq.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Keyboard devices logged when none are given on the command line
+    pub devices: Vec<String>,
+    /// Directory of the data files, if not the default
+    pub data_dir: Option<PathBuf>,
+    /// Key classes that are ignored entirely when counting keystrokes
+    pub exclude_classes: Vec<KeyClass>,
+    /// Daily keystroke goal, if any
+    pub daily_goal: Option<u64>,
+    /// Seconds between automatic saves of the data file
+    pub save_interval: u64,
+    /// Seconds between automatic saves on battery, see [`crate::power`]
+    pub battery_save_interval: u64,
+    /// KiB of raw log records buffered on battery before they are written
+    pub battery_raw_log_kb: usize,
+    /// Whether the data file is written as indented JSON
+    pub pretty_json: bool,
+    /// Whether the last keys typed are saved in the data file
+    pub persist_sequences: bool,
+    /// Seconds without key events after which the logger wakes up less often
+    pub idle_threshold: u64,
+    /// Minutes without key events after which to warn about a grabbed device (0 = never)
+    pub warn_idle_minutes: u64,
+    /// Minutes after startup in which to look for a wrong device (0 = never)
+    pub silent_device_minutes: u64,
+    /// Character key presses that show a keyboard is in use, see [`crate::keylogger::SilentDeviceCheck`]
+    pub silent_device_presses: u64,
+    /// Milliseconds a key may stay down before its hold time counts as an anomaly
+    pub max_hold_ms: u64,
+    /// Milliseconds between checks of the terminal UI for something to redraw
+    pub tick_ms: u64,
+    /// Characters of a device name shown before it is cut off (0 = never)
+    pub device_name_width: usize,
+    /// Separator between groups of digits in counts, see [`crate::numbers`]
+    pub thousands_separator: Separator,
+    /// Days that keep per-minute activity before `ctrlq compact` reduces them to hours (0 = forever)
+    pub minute_days: u64,
+    /// Most distinct keys in the key counts before new ones are counted as
+    /// [`crate::keymap::UNKNOWN_KEY`] (0 = no limit)
+    pub max_tracked_keys: usize,
+    /// Whether keystrokes reported with `ctrlq ping` count as typed
+    /// characters, and so towards WPM
+    pub remote_wpm: bool,
+    /// Whether desktop notifications are sent
+    pub notifications: bool,
+    /// Whether only key classes are recorded instead of individual keys
+    pub anonymize: bool,
+    /// Whether shifted characters are counted apart from unshifted ones
+    pub case_sensitive: bool,
+    /// Whether a summary of each finished week is written, see [`crate::summary`]
+    pub auto_summary: bool,
+    /// Whether the Overview also shows WPM corrected by the latest
+    /// calibration, see [`crate::calibration`]
+    pub calibrated_wpm: bool,
+    /// Whether counting pauses while the session is locked, see [`crate::lock`]
+    pub pause_on_lock: bool,
+    /// Whether breaks are suggested after long typing, see [`crate::keylogger::BreakReminder`]
+    pub break_reminders: bool,
+    /// Minutes of typing after which a break is suggested
+    pub break_after_minutes: u64,
+    /// Text file the Top Keys tab can compare keys with, see [`crate::surprise`]
+    pub unusual_reference: Option<PathBuf>,
+    /// XKB layouts for character counts, see [`crate::xkb`]
+    pub xkb_layout: Option<String>,
+    /// XKB variants of `xkb_layout`
+    pub xkb_variant: Option<String>,
+    /// XKB options, which include the layout switch hotkey
+    pub xkb_options: Option<String>,
+    /// Shortcuts by action name, see [`crate::actions`]
+    pub actions: BTreeMap<String, String>,
+    /// Shell commands by event name, see [`crate::hooks`]
+    pub hooks: BTreeMap<String, String>,
+    /// Rules for keystrokes that are not counted, see [`crate::ignore`]
+    pub ignore: Vec<IgnoreRule>,
+    /// Hours in which keystrokes are logged, all the time if None, see
+    /// [`crate::schedule`]
+    pub log_hours: Option<Schedule>,
+    /// Keys of the terminal UI, see [`crate::bindings`]
+    pub keys: KeyBindings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            devices: Vec::new(),
+            data_dir: None,
+            exclude_classes: Vec::new(),
+            daily_goal: None,
+            save_interval: 30,
+            battery_save_interval: 300,
+            battery_raw_log_kb: 256,
+            pretty_json: false,
+            persist_sequences: false,
+            idle_threshold: 5,
+            warn_idle_minutes: 30,
+            silent_device_minutes: 3,
+            silent_device_presses: 20,
+            max_hold_ms: 5000,
+            tick_ms: 250,
+            device_name_width: 40,
+            thousands_separator: Separator::default(),
+            minute_days: 90,
+            max_tracked_keys: 1000,
+            remote_wpm: false,
+            notifications: true,
+            anonymize: false,
+            case_sensitive: false,
+            auto_summary: true,
+            calibrated_wpm: false,
+            pause_on_lock: false,
+            break_reminders: false,
+            break_after_minutes: 50,
+            unusual_reference: None,
+            xkb_layout: None,
+            xkb_variant: None,
+            xkb_options: None,
+            actions: BTreeMap::new(),
+            hooks: BTreeMap::new(),
+            ignore: Vec::new(),
+            log_hours: None,
+            keys: KeyBindings::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Returns the path of the configuration file.
+    ///
+    /// # Returns
+    /// `PathBuf` - `~/.config/ctrlq/config.toml` by default, see [`crate::paths`]
+    pub fn path() -> PathBuf {
+        paths::config_dir().join("config.toml")
+    }
+
+    /// Loads the configuration file, falling back to defaults if it doesn't exist.
+    ///
+    /// # Returns
+    /// `Result<Self>` - Parsed configuration or a parse error naming the file
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("invalid config in {}", path.display()))
+    }
+
+    /// Writes the configuration file, creating its directory if needed.
+    ///
+    /// Comments in an existing file are not kept.
+    ///
+    /// # Returns
+    /// `Result<PathBuf>` - Path of the written file
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&path, toml::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Returns the time between automatic saves, never less than one second.
+    pub fn save_interval(&self) -> Duration {
+        Duration::from_secs(self.save_interval.max(1))
+    }
+
+    /// Returns the time between automatic saves on battery, never less than
+    /// on mains power.
+    pub fn battery_save_interval(&self) -> Duration {
+        Duration::from_secs(self.battery_save_interval).max(self.save_interval())
+    }
+
+    /// Returns how long without key events counts as idle, never less than one second.
+    pub fn idle_threshold(&self) -> Duration {
+        Duration::from_secs(self.idle_threshold.max(1))
+    }
+
+    /// Returns how long without key events to wait before probing for a
+    /// grabbed device, or None if the warning is turned off.
+    pub fn warn_idle_after(&self) -> Option<Duration> {
+        (self.warn_idle_minutes > 0).then(|| Duration::from_secs(self.warn_idle_minutes * 60))
+    }
+
+    /// Returns the typing time after which a break is suggested, or None if
+    /// break reminders are off.
+    pub fn break_after(&self) -> Option<Duration> {
+        (self.break_reminders && self.break_after_minutes > 0).then(|| Duration::from_secs(self.break_after_minutes * 60))
+    }
+
+    /// Returns how long after startup to compare the logged keyboard with
+    /// the others, or None if the check is turned off.
+    pub fn silent_device_after(&self) -> Option<Duration> {
+        (self.silent_device_minutes > 0).then(|| Duration::from_secs(self.silent_device_minutes * 60))
+    }
+
+    /// Returns the longest hold time recorded as it is, never less than 100 ms.
+    pub fn max_hold(&self) -> Duration {
+        Duration::from_millis(self.max_hold_ms.max(100))
+    }
+
+    /// Returns the tick rate of the terminal UI, never less than 20 ms.
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_ms.max(20))
+    }
+
+    /// Takes over the settings of a reloaded configuration that are safe to
+    /// change while logging.
+    ///
+    /// # Arguments
+    /// * `new` - The freshly loaded configuration
+    ///
+    /// # Returns
+    /// `Vec<&'static str>` - Settings that changed but need a restart to apply
+    pub fn apply_reload(&mut self, new: Config) -> Vec<&'static str> {
+        let mut needs_restart = Vec::new();
+        if new.devices != self.devices {
+            needs_restart.push("devices");
+        }
+        if new.data_dir != self.data_dir {
+            needs_restart.push("data_dir");
+        }
+        if new.anonymize != self.anonymize {
+            needs_restart.push("anonymize");
+        }
+        if new.case_sensitive != self.case_sensitive {
+            needs_restart.push("case_sensitive");
+        }
+        if new.tick_ms != self.tick_ms {
+            needs_restart.push("tick_ms");
+        }
+        if new.keys != self.keys {
+            needs_restart.push("keys");
+        }
+        if (&new.xkb_layout, &new.xkb_variant, &new.xkb_options)
+            != (&self.xkb_layout, &self.xkb_variant, &self.xkb_options)
+        {
+            needs_restart.push("xkb_layout");
+        }
+
+        let (anonymize, case_sensitive) = (self.anonymize, self.case_sensitive);
+        *self = new;
+        self.anonymize = anonymize;
+        self.case_sensitive = case_sensitive;
+        needs_restart
+    }
+
+    /// Checks whether a key code should be counted under this configuration.
+    ///
+    /// # Arguments
+    /// * `code` - The evdev key code
+    pub fn counts_key(&self, code: u16) -> bool {
+        !self.exclude_classes.contains(&keymap::classify(code))
+    }
+}
+
+/// Settings forced from the command line, which win over the config file
+/// and are re-applied after every reload.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    /// `--no-notifications` was given
+    pub no_notifications: bool,
+    /// `--no-auto-summary` was given
+    pub no_auto_summary: bool,
+    /// `--anonymize` was given
+    pub anonymize: bool,
+    /// `--case-sensitive` was given
+    pub case_sensitive: bool,
+    /// `--pretty-json` was given
+    pub pretty_json: bool,
+    /// Value of `--warn-idle-minutes`, if given
+    pub warn_idle_minutes: Option<u64>,
+    /// Value of `--tick-ms`, if given
+    pub tick_ms: Option<u64>,
+    /// Value of `--xkb-layout`, if given
+    pub xkb_layout: Option<String>,
+    /// `--pause-on-lock` was given
+    pub pause_on_lock: bool,
+    /// `--no-hooks` was given
+    pub no_hooks: bool,
+}
+
+impl Overrides {
+    /// Applies the overrides to a configuration.
+    pub fn apply(&self, config: &mut Config) {
+        if self.no_notifications {
+            config.notifications = false;
+        }
+        if self.no_auto_summary {
+            config.auto_summary = false;
+        }
+        if self.anonymize {
+            config.anonymize = true;
+        }
+        if self.case_sensitive {
+            config.case_sensitive = true;
+        }
+        if self.pretty_json {
+            config.pretty_json = true;
+        }
+        if let Some(minutes) = self.warn_idle_minutes {
+            config.warn_idle_minutes = minutes;
+        }
+        if let Some(tick_ms) = self.tick_ms {
+            config.tick_ms = tick_ms;
+        }
+        if let Some(layout) = &self.xkb_layout {
+            config.xkb_layout = Some(layout.clone());
+        }
+        if self.pause_on_lock {
+            config.pause_on_lock = true;
+        }
+        if self.no_hooks {
+            config.hooks.clear();
+        }
+    }
+}
+
+/// Detects changes to the configuration file by polling its modification
+/// time, and reloads on `SIGHUP`.
+pub struct ConfigWatcher {
+    /// Path of the watched file
+    path: PathBuf,
+    /// Modification time seen at the last load (None if the file was missing)
+    last_modified: Option<SystemTime>,
+    /// Set by the signal handler thread when `SIGHUP` arrives
+    hangup: Arc<AtomicBool>,
+}
+
+impl Default for ConfigWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigWatcher {
+    /// Starts watching the configuration file.
+    ///
+    /// Listening for `SIGHUP` is best effort: if the handler can't be
+    /// installed, changes are still picked up by polling.
+    pub fn new() -> Self {
+        let path = Config::path();
+        let hangup = Arc::new(AtomicBool::new(false));
+        listen_for_hangup(hangup.clone());
+
+        Self {
+            last_modified: modified(&path),
+            path,
+            hangup,
+        }
+    }
+
+    /// Checks for a changed file or a pending `SIGHUP`.
+    ///
+    /// # Returns
+    /// `Option<Result<Config>>` - The reloaded configuration if a reload was
+    /// due, or None if nothing changed
+    pub fn poll(&mut self) -> Option<Result<Config>> {
+        let hangup = self.hangup.swap(false, Ordering::Relaxed);
+        let current = modified(&self.path);
+        if !hangup && current == self.last_modified {
+            return None;
+        }
+
+        self.last_modified = current;
+        Some(Config::load())
+    }
+}
+
+/// Returns the modification time of a file, if it exists.
+fn modified(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Sets `flag` whenever the process receives `SIGHUP`.
+fn listen_for_hangup(flag: Arc<AtomicBool>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_io().build() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("⚠️  Can't listen for SIGHUP, config changes are still detected: {}", e);
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            let Ok(mut hangups) = signal(SignalKind::hangup()) else {
+                return;
+            };
+            while hangups.recv().await.is_some() {
+                flag.store(true, Ordering::Relaxed);
+            }
+        });
+    });
+}
@@ -0,0 +1,79 @@
+//! # Drift Module
+//!
+//! How the share of a key changes over the weeks, for seeing the effect of
+//! new keybindings or a new layout: after moving "save" from Ctrl+S to a
+//! leader key, S should drop and the leader key rise. Each week's share is
+//! the key's keystrokes in percent of all keystrokes that week, from the
+//! daily statistics summed into ISO weeks by [`report::weekly_totals`]. The
+//! History tab plots up to [`MAX_DRIFT_KEYS`] keys over each other.
+
+use crate::report::{self, WeekTotals};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+/// Most keys plotted together.
+pub const MAX_DRIFT_KEYS: usize = 3;
+
+/// The weekly share of one key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftSeries {
+    /// Evdev code of the key
+    pub code: u16,
+    /// Monday of each week and the key's share of it in percent
+    pub points: Vec<(NaiveDate, f64)>,
+}
+
+impl DriftSeries {
+    /// Computes a key's share of each week.
+    ///
+    /// # Arguments
+    /// * `weeks` - Weekly totals, see [`report::weekly_totals`]
+    /// * `code` - Evdev code of the key
+    ///
+    /// # Returns
+    /// `DriftSeries` - One point per week with keystrokes, in week order
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use ctrlq::drift::DriftSeries;
+    /// use ctrlq::keylogger::KeyStats;
+    /// use ctrlq::report::weekly_totals;
+    ///
+    /// let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    /// let mut stats = KeyStats::new();
+    /// // S falls from a fifth to a twentieth of the keystrokes
+    /// stats.add_counts("KEY_31", 20, Some(day(2024, 12, 24)));
+    /// stats.add_counts("KEY_30", 80, Some(day(2024, 12, 24)));
+    /// stats.add_counts("KEY_31", 5, Some(day(2024, 12, 31)));
+    /// stats.add_counts("KEY_30", 95, Some(day(2025, 1, 2)));
+    ///
+    /// let drift = DriftSeries::new(&weekly_totals(&stats.daily_stats), 31);
+    /// assert_eq!(drift.points, [(day(2024, 12, 23), 20.0), (day(2024, 12, 30), 5.0)]);
+    /// assert_eq!(drift.change(), Some(-15.0));
+    /// ```
+    pub fn new(weeks: &BTreeMap<NaiveDate, WeekTotals>, code: u16) -> Self {
+        let points = weeks
+            .iter()
+            .filter_map(|(&monday, week)| {
+                let count = week.keys.get(&code).copied().unwrap_or(0);
+                Some((monday, report::percent(count, week.keystrokes)?))
+            })
+            .collect();
+        Self { code, points }
+    }
+
+    /// Returns the highest weekly share in percent.
+    pub fn peak(&self) -> f64 {
+        self.points.iter().map(|(_, share)| *share).fold(0.0, f64::max)
+    }
+
+    /// Returns how many percentage points the share moved from the first
+    /// week to the last, None with fewer than two weeks.
+    pub fn change(&self) -> Option<f64> {
+        match self.points.as_slice() {
+            [(_, first), .., (_, last)] => Some(last - first),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! # Heatmap Module
+//!
+//! Compares how two keyboards, or two profiles, are used key by key. Raw
+//! counts of a keyboard typed on all day and one used now and then can't
+//! be compared, so each side is normalized to per-mille of its own
+//! keystrokes first. The Heatmap tab shows the two sides next to each
+//! other, or one keyboard colored by which side leans on each key more.
+//!
+//! The keyboard itself is a [`HeatGrid`]: the keys of the heatmap with a
+//! value and a level each, which the Heatmap tab turns into colored spans
+//! and `ctrlq heatmap` into ANSI colors or plain characters.
+
+use crate::keymap::{self, KeyClass};
+use crate::numbers;
+use std::collections::HashMap;
+
+/// Difference in per-mille points from which a key leans towards one side.
+pub const LEAN_PER_MILLE: f64 = 2.0;
+
+/// Difference in per-mille points from which a key leans strongly.
+pub const STRONG_LEAN_PER_MILLE: f64 = 10.0;
+
+/// Normalizes key counts to per-mille of their total.
+///
+/// # Arguments
+/// * `counts` - Keystrokes by evdev code
+///
+/// # Returns
+/// `HashMap<u16, f64>` - Per-mille by evdev code, summing to 1000 (empty
+/// for no keystrokes)
+///
+/// # Example
+/// ```
+/// use ctrlq::heatmap::per_mille;
+/// use std::collections::HashMap;
+///
+/// let shares = per_mille(&HashMap::from([(30, 750), (48, 250), (46, 0)]));
+/// assert_eq!(shares[&30], 750.0);
+/// assert_eq!(shares[&48], 250.0);
+/// assert_eq!(shares[&46], 0.0);
+///
+/// // The same distribution at another scale normalizes the same
+/// assert_eq!(per_mille(&HashMap::from([(30, 3), (48, 1)])), HashMap::from([(30, 750.0), (48, 250.0)]));
+/// assert!(per_mille(&HashMap::from([(30, 0)])).is_empty());
+/// ```
+pub fn per_mille(counts: &HashMap<u16, u64>) -> HashMap<u16, f64> {
+    let total: u64 = counts.values().sum();
+    if total == 0 {
+        return HashMap::new();
+    }
+    counts
+        .iter()
+        .map(|(&code, &count)| (code, count as f64 * 1000.0 / total as f64))
+        .collect()
+}
+
+/// Which side of a comparison uses a key relatively more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lean {
+    /// At least [`STRONG_LEAN_PER_MILLE`] more on the first side
+    StrongA,
+    /// At least [`LEAN_PER_MILLE`] more on the first side
+    A,
+    /// About the same share on both sides
+    Even,
+    /// At least [`LEAN_PER_MILLE`] more on the second side
+    B,
+    /// At least [`STRONG_LEAN_PER_MILLE`] more on the second side
+    StrongB,
+}
+
+impl Lean {
+    /// Classifies the difference of a key's shares.
+    ///
+    /// # Arguments
+    /// * `a` - Per-mille of the key on the first side
+    /// * `b` - Per-mille of the key on the second side
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::heatmap::Lean;
+    ///
+    /// assert_eq!(Lean::of(120.0, 100.0), Lean::StrongA);
+    /// assert_eq!(Lean::of(13.0, 10.0), Lean::A);
+    /// assert_eq!(Lean::of(10.5, 10.0), Lean::Even);
+    /// assert_eq!(Lean::of(10.0, 12.0), Lean::B);
+    /// assert_eq!(Lean::of(0.0, 10.0), Lean::StrongB);
+    /// ```
+    pub fn of(a: f64, b: f64) -> Self {
+        let delta = a - b;
+        if delta >= STRONG_LEAN_PER_MILLE {
+            Self::StrongA
+        } else if delta >= LEAN_PER_MILLE {
+            Self::A
+        } else if delta <= -STRONG_LEAN_PER_MILLE {
+            Self::StrongB
+        } else if delta <= -LEAN_PER_MILLE {
+            Self::B
+        } else {
+            Self::Even
+        }
+    }
+}
+
+/// Two key distributions normalized for comparing, see the module docs.
+///
+/// # Example
+/// ```
+/// use ctrlq::heatmap::{HeatmapComparison, Lean};
+/// use std::collections::HashMap;
+///
+/// // The laptop types E a lot more, the ergo board has most of the spaces
+/// let laptop = HashMap::from([(18, 300), (57, 100), (30, 100)]);
+/// let ergo = HashMap::from([(18, 1000), (57, 2200), (30, 800)]);
+/// let comparison = HeatmapComparison::new("laptop", &laptop, "ergo", &ergo);
+///
+/// assert_eq!(comparison.a[&18], 600.0);
+/// assert_eq!(comparison.b[&18], 250.0);
+/// assert_eq!(comparison.delta(18), 350.0);
+/// assert_eq!(comparison.lean(18), Lean::StrongA);
+/// assert_eq!(comparison.lean(57), Lean::StrongB);
+/// assert_eq!(comparison.lean(30), Lean::Even);
+/// // Keys neither side typed
+/// assert_eq!(comparison.lean(16), Lean::Even);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatmapComparison {
+    /// Name of the first side, e.g. a device label or profile
+    pub label_a: String,
+    /// Name of the second side
+    pub label_b: String,
+    /// Per-mille by evdev code of the first side
+    pub a: HashMap<u16, f64>,
+    /// Per-mille by evdev code of the second side
+    pub b: HashMap<u16, f64>,
+}
+
+impl HeatmapComparison {
+    /// Normalizes two key distributions for comparing.
+    ///
+    /// # Arguments
+    /// * `label_a` - Name of the first side
+    /// * `counts_a` - Keystrokes by evdev code of the first side
+    /// * `label_b` - Name of the second side
+    /// * `counts_b` - Keystrokes by evdev code of the second side
+    pub fn new(label_a: &str, counts_a: &HashMap<u16, u64>, label_b: &str, counts_b: &HashMap<u16, u64>) -> Self {
+        Self {
+            label_a: label_a.to_string(),
+            label_b: label_b.to_string(),
+            a: per_mille(counts_a),
+            b: per_mille(counts_b),
+        }
+    }
+
+    /// Returns how many per-mille points more the first side uses a key.
+    pub fn delta(&self, code: u16) -> f64 {
+        self.a.get(&code).copied().unwrap_or(0.0) - self.b.get(&code).copied().unwrap_or(0.0)
+    }
+
+    /// Returns which side uses a key relatively more.
+    pub fn lean(&self, code: u16) -> Lean {
+        Lean::of(self.a.get(&code).copied().unwrap_or(0.0), self.b.get(&code).copied().unwrap_or(0.0))
+    }
+}
+
+/// How much a key is used compared with the most used key: the color scale
+/// of the heatmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Heat {
+    /// Not used at all
+    Unused,
+    /// Up to 20% of the most used key
+    VeryLow,
+    /// Up to 40%
+    Low,
+    /// Up to 60%
+    Medium,
+    /// Up to 80%
+    High,
+    /// More than 80%
+    VeryHigh,
+}
+
+impl Heat {
+    /// All levels, hottest first as in the legend.
+    pub const ALL: [Heat; 6] = [Self::VeryHigh, Self::High, Self::Medium, Self::Low, Self::VeryLow, Self::Unused];
+
+    /// Classifies a key used `intensity` (0 to 1) as much as the most used key.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::heatmap::Heat;
+    ///
+    /// assert_eq!(Heat::of(1.0), Heat::VeryHigh);
+    /// assert_eq!(Heat::of(0.5), Heat::Medium);
+    /// assert_eq!(Heat::of(0.01), Heat::VeryLow);
+    /// assert_eq!(Heat::of(0.0), Heat::Unused);
+    /// ```
+    pub fn of(intensity: f64) -> Self {
+        if intensity > 0.8 {
+            Self::VeryHigh
+        } else if intensity > 0.6 {
+            Self::High
+        } else if intensity > 0.4 {
+            Self::Medium
+        } else if intensity > 0.2 {
+            Self::Low
+        } else if intensity > 0.0 {
+            Self::VeryLow
+        } else {
+            Self::Unused
+        }
+    }
+
+    /// Name of the level in the legend.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::VeryHigh => "Very High",
+            Self::High => "High",
+            Self::Medium => "Medium",
+            Self::Low => "Low",
+            Self::VeryLow => "Very Low",
+            Self::Unused => "Unused",
+        }
+    }
+
+    /// Character standing in for the color where there are no colors.
+    pub fn symbol(self) -> char {
+        match self {
+            Self::VeryHigh => '@',
+            Self::High => '#',
+            Self::Medium => '+',
+            Self::Low => '-',
+            Self::VeryLow => '.',
+            Self::Unused => ' ',
+        }
+    }
+}
+
+/// One key of a [`HeatGrid`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatCell<T> {
+    /// Label printed on the key, e.g. `Q` or `SPACE`
+    pub label: String,
+    /// Evdev code of the key
+    pub code: u16,
+    /// Value shown next to the label, if any
+    pub value: Option<String>,
+    /// Level the key is colored by, e.g. a [`Heat`] or a [`Lean`]
+    pub level: T,
+}
+
+impl<T> HeatCell<T> {
+    /// Returns the label with its value, e.g. `Q[120]`, or the bare label.
+    pub fn text(&self) -> String {
+        match &self.value {
+            Some(value) => format!("{}[{}]", self.label, value),
+            None => self.label.clone(),
+        }
+    }
+}
+
+/// The keys of the heatmap ([`keymap::HEATMAP_ROWS`] and the space bar),
+/// each with a value and a level, independent of how they are drawn.
+///
+/// # Example
+/// ```
+/// use ctrlq::heatmap::{Heat, HeatGrid};
+/// use std::collections::HashMap;
+///
+/// // E typed four times as often as A, nothing else
+/// let grid = HeatGrid::of_counts(&HashMap::from([(18, 40), (30, 10)]), None);
+/// let e = &grid.rows[1][2];
+/// assert_eq!((e.text(), e.level), ("E[40]".to_string(), Heat::VeryHigh));
+/// assert_eq!(grid.rows[2][0].level, Heat::Low);
+/// assert_eq!(grid.rows[1][0].text(), "Q");
+/// // The space bar always shows its count
+/// assert_eq!(grid.space.text(), "SPACE[0]");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatGrid<T> {
+    /// Rows of keys, number row first
+    pub rows: Vec<Vec<HeatCell<T>>>,
+    /// The space bar, below the rows
+    pub space: HeatCell<T>,
+}
+
+impl<T> HeatGrid<T> {
+    /// Lays out the heatmap keys with the value and level `cell` returns
+    /// for each code. The space bar shows `0` rather than no value.
+    pub fn new(cell: impl Fn(u16) -> (Option<String>, T)) -> Self {
+        let key = |label: String, code: u16| {
+            let (value, level) = cell(code);
+            HeatCell { label, code, value, level }
+        };
+        let rows = keymap::HEATMAP_ROWS
+            .iter()
+            .map(|(labels, codes)| labels.chars().zip(codes.iter()).map(|(label, &code)| key(label.to_string(), code)).collect())
+            .collect();
+        let mut space = key("SPACE".to_string(), keymap::KEY_SPACE);
+        space.value.get_or_insert_with(|| "0".to_string());
+        Self { rows, space }
+    }
+}
+
+impl HeatGrid<Heat> {
+    /// Heat of each key by its keystrokes, relative to the most used key.
+    ///
+    /// # Arguments
+    /// * `counts` - Keystrokes by evdev code
+    /// * `class_filter` - Only keys of this class, the others count as unused
+    pub fn of_counts(counts: &HashMap<u16, u64>, class_filter: Option<KeyClass>) -> Self {
+        let in_filter = |code: u16| class_filter.is_none_or(|class| keymap::classify(code) == class);
+        let max_count = counts.iter().filter(|(code, _)| in_filter(**code)).map(|(_, count)| *count).max().unwrap_or(0);
+        Self::new(|code| {
+            let count = if in_filter(code) { counts.get(&code).copied().unwrap_or(0) } else { 0 };
+            let intensity = if max_count > 0 { count as f64 / max_count as f64 } else { 0.0 };
+            ((count > 0).then(|| numbers::format_compact(count)), Heat::of(intensity))
+        })
+    }
+
+    /// Heat of each key by its per-mille, see [`per_mille`].
+    pub fn of_shares(shares: &HashMap<u16, f64>) -> Self {
+        let max_share = shares.values().copied().fold(0.0, f64::max);
+        Self::new(|code| {
+            let share = shares.get(&code).copied().unwrap_or(0.0);
+            let intensity = if max_share > 0.0 { share / max_share } else { 0.0 };
+            ((share > 0.0).then(|| format!("{:.0}", share)), Heat::of(intensity))
+        })
+    }
+}
+
+impl HeatGrid<Lean> {
+    /// Which side of a comparison leans on each key, with the difference in
+    /// per-mille points where it is noticeable.
+    pub fn of_comparison(comparison: &HeatmapComparison) -> Self {
+        Self::new(|code| {
+            let delta = comparison.delta(code);
+            ((delta.abs() >= 0.5).then(|| format!("{:+.0}", delta)), comparison.lean(code))
+        })
+    }
+}
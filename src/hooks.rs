@@ -0,0 +1,244 @@
+//! # Hooks Module
+//!
+//! Shell commands run on events of the logger, from the `[hooks]` table of
+//! the config:
+//!
+//! ```toml
+//! [hooks]
+//! session_end = "notify-send \"$CTRLQ_SESSION_WPM WPM\""
+//! daily_goal_reached = "~/bin/celebrate"
+//! ```
+//!
+//! Each hook runs with `sh -c` and learns about the event from environment
+//! variables: `CTRLQ_EVENT`, `CTRLQ_DATE`, `CTRLQ_TOTAL`, `CTRLQ_TODAY` and
+//! `CTRLQ_DATA_FILE` always, plus those of its event (see [`HookEvent`]).
+//!
+//! Hooks never block logging: they run detached, are killed after
+//! [`HOOK_TIMEOUT`], and a failing or hanging hook is only reported. They
+//! never run as root either. Under sudo they run as the invoking user
+//! (`SUDO_USER`), like desktop notifications; a root process without a sudo
+//! user runs no hooks at all. `--no-hooks` turns them off.
+
+use crate::keylogger::{day_key, today, KeyStats, TypingSession};
+use crate::privileges::{sudo_user, Privileges};
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Time a hook may run before it is killed.
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Exit status of `timeout` when it had to stop the hook.
+const TIMED_OUT_STATUS: i32 = 124;
+
+/// Events a hook can run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HookEvent {
+    /// A typing session was closed; with `CTRLQ_SESSION_WPM`,
+    /// `CTRLQ_SESSION_KEYSTROKES` and `CTRLQ_SESSION_SECONDS`
+    SessionEnd,
+    /// Today's keystrokes reached `daily_goal`; with `CTRLQ_GOAL`
+    DailyGoalReached,
+    /// The data file was saved, regularly, on request or on exit
+    Save,
+    /// A personal record was broken; with `CTRLQ_RECORD`
+    RecordBroken,
+}
+
+impl HookEvent {
+    /// All events, in the order of the docs.
+    pub const ALL: [HookEvent; 4] = [Self::SessionEnd, Self::DailyGoalReached, Self::Save, Self::RecordBroken];
+
+    /// Returns the name of the event in the `[hooks]` table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SessionEnd => "session_end",
+            Self::DailyGoalReached => "daily_goal_reached",
+            Self::Save => "save",
+            Self::RecordBroken => "record_broken",
+        }
+    }
+
+    /// Parses an event name of the `[hooks]` table.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::hooks::HookEvent;
+    ///
+    /// assert_eq!(HookEvent::parse("session_end"), Some(HookEvent::SessionEnd));
+    /// assert_eq!(HookEvent::parse("on_save"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.name() == name)
+    }
+}
+
+/// Hook commands by event, see the module docs.
+///
+/// # Example
+/// ```
+/// use ctrlq::hooks::{HookEvent, Hooks};
+/// use std::collections::BTreeMap;
+/// use std::sync::mpsc;
+/// use std::time::Duration;
+///
+/// let hooks = Hooks::new(&BTreeMap::from([("save".to_string(), "exit 3".to_string())]))?;
+/// assert!(hooks.has(HookEvent::Save));
+/// assert!(!hooks.has(HookEvent::SessionEnd));
+///
+/// // A failing hook is reported, not returned
+/// let (failure_tx, failure_rx) = mpsc::channel();
+/// hooks.run(HookEvent::Save, Vec::new(), move |failure| failure_tx.send(failure).unwrap());
+/// let failure = failure_rx.recv_timeout(Duration::from_secs(10))?;
+/// assert!(failure.starts_with("Hook 'save'"));
+///
+/// let typo = BTreeMap::from([("on_save".to_string(), "true".to_string())]);
+/// assert!(Hooks::new(&typo).is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    commands: BTreeMap<HookEvent, String>,
+}
+
+impl Hooks {
+    /// Creates the hooks of the `[hooks]` table of the config.
+    ///
+    /// # Arguments
+    /// * `table` - Shell commands by event name
+    ///
+    /// # Returns
+    /// `Result<Hooks>` - The hooks, or an error for the first unknown event
+    pub fn new(table: &BTreeMap<String, String>) -> Result<Self> {
+        let mut commands = BTreeMap::new();
+        for (name, command) in table {
+            let Some(event) = HookEvent::parse(name) else {
+                let names: Vec<&str> = HookEvent::ALL.iter().map(|event| event.name()).collect();
+                bail!("unknown hook event '{}', expected one of {}", name, names.join(", "));
+            };
+            if !command.trim().is_empty() {
+                commands.insert(event, command.clone());
+            }
+        }
+        Ok(Self { commands })
+    }
+
+    /// Whether a hook is set for the event.
+    pub fn has(&self, event: HookEvent) -> bool {
+        self.commands.contains_key(&event)
+    }
+
+    /// Runs the hook of an event, if there is one, without waiting for it.
+    ///
+    /// The hook is waited for on a thread of its own, which reports a hook
+    /// that fails, times out or can't be started through `on_failure`.
+    ///
+    /// # Arguments
+    /// * `event` - The event that happened
+    /// * `vars` - Environment variables for the hook, see [`event_vars`]
+    /// * `on_failure` - Called with a description if the hook doesn't succeed
+    pub fn run<F>(&self, event: HookEvent, vars: Vec<(&'static str, String)>, on_failure: F)
+    where
+        F: FnOnce(String) + Send + 'static,
+    {
+        let Some(script) = self.commands.get(&event) else {
+            return;
+        };
+        let name = event.name();
+        let mut command = match sudo_user() {
+            Some((user, _)) => {
+                let mut command = Command::new("sudo");
+                command.args(["-n", "-u", &user, "env"]);
+                command
+            }
+            None if Privileges::detect().is_root() => {
+                on_failure(format!("Hook '{}' not run: hooks never run as root, start ctrlq with sudo instead", name));
+                return;
+            }
+            None => Command::new("env"),
+        };
+        // sudo resets the environment, so the variables go through env
+        command
+            .args(vars.iter().map(|(key, value)| format!("{}={}", key, value)))
+            .arg(format!("CTRLQ_EVENT={}", name))
+            .args(["timeout", "--kill-after=5", &HOOK_TIMEOUT.as_secs().to_string(), "sh", "-c", script])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                on_failure(format!("Hook '{}' not started: {}", name, e));
+                return;
+            }
+        };
+        std::thread::spawn(move || {
+            let failure = match child.wait() {
+                Ok(status) if status.success() => return,
+                Ok(status) => match status.code() {
+                    Some(TIMED_OUT_STATUS) => format!("Hook '{}' killed after {}s", name, HOOK_TIMEOUT.as_secs()),
+                    Some(code) => format!("Hook '{}' exited with status {}", name, code),
+                    None => format!("Hook '{}' was killed", name),
+                },
+                Err(e) => format!("Hook '{}' could not be waited for: {}", name, e),
+            };
+            on_failure(failure);
+        });
+    }
+}
+
+/// Environment variables every hook gets, next to `CTRLQ_EVENT`.
+///
+/// # Arguments
+/// * `stats` - The statistics as of the event
+/// * `data_file` - Path of the data file
+///
+/// # Example
+/// ```
+/// use ctrlq::hooks::event_vars;
+/// use ctrlq::keylogger::KeyStats;
+/// use ctrlq::keymap;
+/// use std::path::Path;
+///
+/// let mut stats = KeyStats::new();
+/// stats.add_keypress(&keymap::key_name(30));
+/// let vars = event_vars(&stats, Path::new("/tmp/keystroke_data.json"));
+/// assert!(vars.contains(&("CTRLQ_TOTAL", "1".to_string())));
+/// assert!(vars.contains(&("CTRLQ_TODAY", "1".to_string())));
+/// ```
+pub fn event_vars(stats: &KeyStats, data_file: &Path) -> Vec<(&'static str, String)> {
+    let date = day_key(today());
+    let today = stats.daily_stats.get(&date).map_or(0, |day| day.keystrokes);
+    vec![
+        ("CTRLQ_DATE", date),
+        ("CTRLQ_TOTAL", stats.total_keystrokes.to_string()),
+        ("CTRLQ_TODAY", today.to_string()),
+        ("CTRLQ_DATA_FILE", data_file.display().to_string()),
+    ]
+}
+
+/// Environment variables of a `session_end` hook.
+///
+/// # Example
+/// ```
+/// use ctrlq::hooks::session_vars;
+/// use ctrlq::keylogger::KeyStats;
+/// use ctrlq::keymap;
+///
+/// let mut stats = KeyStats::new();
+/// stats.add_keypress(&keymap::key_name(30));
+/// let vars = session_vars(&stats.current_session());
+/// assert!(vars.contains(&("CTRLQ_SESSION_KEYSTROKES", "1".to_string())));
+/// // Too short for a speed
+/// assert!(vars.contains(&("CTRLQ_SESSION_WPM", String::new())));
+/// ```
+pub fn session_vars(session: &TypingSession) -> Vec<(&'static str, String)> {
+    vec![
+        ("CTRLQ_SESSION_WPM", session.wpm.map(|wpm| format!("{:.1}", wpm)).unwrap_or_default()),
+        ("CTRLQ_SESSION_KEYSTROKES", session.keystrokes.to_string()),
+        ("CTRLQ_SESSION_SECONDS", session.duration().num_seconds().to_string()),
+    ]
+}
@@ -0,0 +1,279 @@
+//! # Ignore Module
+//!
+//! Rules for keystrokes that are not counted, finer than `exclude_classes`:
+//! keys of one keyboard only, or everything typed in a time window. Rules
+//! are `[[ignore]]` tables of the config:
+//!
+//! ```toml
+//! # Nothing from the work keyboard at night
+//! [[ignore]]
+//! device = "/dev/input/by-id/usb-Work_Keyboard-event-kbd"
+//! between = ["22:00", "07:00"]
+//!
+//! # The numpad and F1 of the external keyboard, always
+//! [[ignore]]
+//! device = "Keychron"
+//! keys = ["numpad", "F1"]
+//! ```
+//!
+//! A rule matches a key event when all of its parts do, and a part that is
+//! left out matches everything:
+//!
+//! - `device` - The device path as logged, or part of the device name
+//!   (case-insensitive)
+//! - `keys` - Key names such as `A`, `KP1` or `F1` (see
+//!   [`keymap::code_from_label`]) and key classes such as `numpad`
+//! - `between` - Local time window from the first time up to the second,
+//!   past midnight if the second is earlier; `[["12:00", "13:00"],
+//!   ["22:00", "07:00"]]` gives several windows, which must not overlap
+//!
+//! An event matching any rule is not counted, and not written to the raw
+//! log either. The Debug tab shows how many presses each rule suppressed,
+//! counted against the first rule that matched. Invalid rules make the
+//! config fail to load.
+
+use crate::keymap::{self, KeyClass};
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// Minutes in a day, the length of the clock that windows live on.
+const DAY_MINUTES: u32 = 24 * 60;
+
+/// A local time window, past midnight if it ends earlier than it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    /// First minute of the window
+    pub start: NaiveTime,
+    /// First minute after the window
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    /// Parses a window from its `HH:MM` start and end.
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::NaiveTime;
+    /// use ctrlq::ignore::TimeWindow;
+    ///
+    /// let night = TimeWindow::parse("22:00", "07:00").unwrap();
+    /// assert!(night.contains(NaiveTime::from_hms_opt(23, 30, 0).unwrap()));
+    /// assert!(night.contains(NaiveTime::from_hms_opt(6, 59, 59).unwrap()));
+    /// assert!(!night.contains(NaiveTime::from_hms_opt(7, 0, 0).unwrap()));
+    ///
+    /// assert!(TimeWindow::parse("22:00", "22:00").is_err());
+    /// assert!(TimeWindow::parse("24:00", "07:00").is_err());
+    /// ```
+    pub fn parse(start: &str, end: &str) -> Result<Self> {
+        let time = |text: &str| {
+            NaiveTime::parse_from_str(text.trim(), "%H:%M")
+                .with_context(|| format!("invalid time '{}', expected HH:MM such as 07:30", text))
+        };
+        let window = Self { start: time(start)?, end: time(end)? };
+        if window.start == window.end {
+            bail!("time window {} is empty, start and end must differ", window);
+        }
+        Ok(window)
+    }
+
+    /// Whether a time of day falls into the window.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// The window as minute ranges of one day, split at midnight.
+    fn ranges(&self) -> Vec<(u32, u32)> {
+        let minute = |time: NaiveTime| time.hour() * 60 + time.minute();
+        let (start, end) = (minute(self.start), minute(self.end));
+        if start < end { vec![(start, end)] } else { vec![(start, DAY_MINUTES), (0, end)] }
+    }
+
+    /// Whether two windows share a minute.
+    pub fn overlaps(&self, other: &TimeWindow) -> bool {
+        self.ranges()
+            .iter()
+            .any(|&(start, end)| other.ranges().iter().any(|&(other_start, other_end)| start < other_end && other_start < end))
+    }
+}
+
+impl std::fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+/// A key or a whole class of keys named in `keys`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMatch {
+    /// One key, by evdev code
+    Key(u16),
+    /// Every key of a class
+    Class(KeyClass),
+}
+
+impl KeyMatch {
+    /// Parses a class name (`numpad`) or a key name (`KP1`, `F1`, `A`).
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::ignore::KeyMatch;
+    /// use ctrlq::keymap::KeyClass;
+    ///
+    /// assert_eq!(KeyMatch::parse("Numpad").unwrap(), KeyMatch::Class(KeyClass::Numpad));
+    /// assert_eq!(KeyMatch::parse("F1").unwrap(), KeyMatch::Key(59));
+    /// assert!(KeyMatch::parse("F99").is_err());
+    /// ```
+    pub fn parse(name: &str) -> Result<Self> {
+        if let Some(class) = KeyClass::ALL.into_iter().find(|class| class.label().eq_ignore_ascii_case(name.trim())) {
+            return Ok(Self::Class(class));
+        }
+        match keymap::code_from_label(name) {
+            Some(code) => Ok(Self::Key(code)),
+            None => bail!("unknown key '{}', expected a key such as A or KP1, or a class such as numpad", name),
+        }
+    }
+
+    /// Whether a key code is matched.
+    pub fn matches(&self, code: u16) -> bool {
+        match self {
+            Self::Key(key) => *key == code,
+            Self::Class(class) => keymap::classify(code) == *class,
+        }
+    }
+}
+
+/// `between` as written in the config: one window or several.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Between {
+    /// `["22:00", "07:00"]`
+    One([String; 2]),
+    /// `[["12:00", "13:00"], ["22:00", "07:00"]]`
+    Many(Vec<[String; 2]>),
+}
+
+/// An `[[ignore]]` table as written in the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawIgnoreRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    device: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    keys: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    between: Option<Between>,
+}
+
+/// A rule for key events that are not counted, see the module docs.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveTime;
+/// use ctrlq::config::Config;
+///
+/// let config: Config = toml::from_str(r#"
+///     [[ignore]]
+///     device = "keychron"
+///     keys = ["numpad"]
+///
+///     [[ignore]]
+///     between = ["22:00", "07:00"]
+/// "#)?;
+/// let (numpad, night) = (&config.ignore[0], &config.ignore[1]);
+/// let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+/// let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+///
+/// // KP1 on the Keychron, but not on another keyboard or A on the Keychron
+/// assert!(numpad.matches("/dev/input/event5", "Keychron K2", 79, noon));
+/// assert!(!numpad.matches("/dev/input/event3", "AT Translated Set 2 keyboard", 79, noon));
+/// assert!(!numpad.matches("/dev/input/event5", "Keychron K2", 30, noon));
+/// // Every key of every keyboard at night
+/// assert!(night.matches("/dev/input/event3", "AT Translated Set 2 keyboard", 30, midnight));
+/// assert_eq!(night.to_string(), "all keys, 22:00-07:00");
+///
+/// let overlapping = r#"
+///     [[ignore]]
+///     between = [["22:00", "07:00"], ["06:00", "08:00"]]
+/// "#;
+/// let error = toml::from_str::<Config>(overlapping).unwrap_err().to_string();
+/// assert!(error.contains("time windows 22:00-07:00 and 06:00-08:00 overlap"));
+/// assert!(toml::from_str::<Config>("[[ignore]]\nbetween = [\"7:00\", \"7:00\"]").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawIgnoreRule", into = "RawIgnoreRule")]
+pub struct IgnoreRule {
+    /// Device path or part of the device name, all devices if None
+    pub device: Option<String>,
+    /// Keys and classes, all keys if empty
+    pub keys: Vec<KeyMatch>,
+    /// Time windows, all day if empty
+    pub windows: Vec<TimeWindow>,
+    /// The rule as written, for saving the config again
+    raw: RawIgnoreRule,
+}
+
+impl TryFrom<RawIgnoreRule> for IgnoreRule {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawIgnoreRule) -> Result<Self> {
+        let device = raw.device.clone().filter(|device| !device.trim().is_empty());
+        let keys = raw.keys.iter().map(|key| KeyMatch::parse(key)).collect::<Result<Vec<_>>>()?;
+        let pairs = match &raw.between {
+            None => Vec::new(),
+            Some(Between::One(pair)) => vec![pair.clone()],
+            Some(Between::Many(pairs)) => pairs.clone(),
+        };
+        let windows = pairs.iter().map(|[start, end]| TimeWindow::parse(start, end)).collect::<Result<Vec<_>>>()?;
+        for (index, window) in windows.iter().enumerate() {
+            if let Some(other) = windows[index + 1..].iter().find(|other| window.overlaps(other)) {
+                bail!("time windows {} and {} overlap, merge them into one", window, other);
+            }
+        }
+        if device.is_none() && keys.is_empty() && windows.is_empty() {
+            bail!("ignore rule without device, keys or between would ignore every keystroke");
+        }
+        Ok(Self { device, keys, windows, raw })
+    }
+}
+
+impl From<IgnoreRule> for RawIgnoreRule {
+    fn from(rule: IgnoreRule) -> Self {
+        rule.raw
+    }
+}
+
+impl IgnoreRule {
+    /// Whether a key event is ignored by this rule.
+    ///
+    /// # Arguments
+    /// * `device_path` - Path of the device as logged
+    /// * `device_name` - Name of the device, see `keylogger::device_labels`
+    /// * `code` - Evdev code of the key
+    /// * `time` - Local time of the event
+    pub fn matches(&self, device_path: &str, device_name: &str, code: u16, time: NaiveTime) -> bool {
+        let device = self.device.as_deref().is_none_or(|device| {
+            device == device_path || device_name.to_lowercase().contains(&device.to_lowercase())
+        });
+        let key = self.keys.is_empty() || self.keys.iter().any(|key| key.matches(code));
+        let window = self.windows.is_empty() || self.windows.iter().any(|window| window.contains(time));
+        device && key && window
+    }
+}
+
+impl std::fmt::Display for IgnoreRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(device) = &self.device {
+            parts.push(device.clone());
+        }
+        parts.push(if self.raw.keys.is_empty() { "all keys".to_string() } else { self.raw.keys.join(" ") });
+        parts.extend(self.windows.iter().map(TimeWindow::to_string));
+        write!(f, "{}", parts.join(", "))
+    }
+}
@@ -0,0 +1,180 @@
+//! # Import Module
+//!
+//! Converts per-key count exports from other tools into ctrlq statistics so
+//! they can be merged into the data file.
+//!
+//! ## Formats
+//! - `whatpulse` - WhatPulse keyboard heatmap export: a header row naming a
+//!   `Key` column and a `Count` (or `Presses`) column; other columns are ignored.
+//! - `csv` - Generic CSV with the columns `key,count[,date]`. `key` is a key
+//!   label (`A`, `Space`, `;`, `KEY_A`), `count` a non-negative integer and
+//!   the optional `date` is `YYYY-MM-DD`. A header row is optional.
+//!
+//! Rows with dates also populate `daily_stats`; rows without dates only
+//! update lifetime key counts. Unknown key labels are reported and skipped.
+
+use crate::keylogger::KeyStats;
+use crate::keymap;
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use std::path::Path;
+
+/// Supported import formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// WhatPulse keyboard heatmap export
+    WhatPulse,
+    /// Generic `key,count[,date]` CSV
+    Csv,
+}
+
+impl ImportFormat {
+    /// Parses a format name as given on the command line.
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "whatpulse" => Ok(ImportFormat::WhatPulse),
+            "csv" => Ok(ImportFormat::Csv),
+            _ => bail!("unknown import format '{}': expected whatpulse or csv", name),
+        }
+    }
+}
+
+/// A row that couldn't be imported.
+#[derive(Debug, Clone)]
+pub struct SkippedRow {
+    /// 1-based line number in the input file
+    pub line: usize,
+    /// Why the row was skipped
+    pub reason: String,
+}
+
+/// Result of converting an export file.
+#[derive(Debug)]
+pub struct Import {
+    /// Imported counts as statistics, ready to merge
+    pub stats: KeyStats,
+    /// Number of rows imported
+    pub rows: usize,
+    /// Rows that were skipped
+    pub skipped: Vec<SkippedRow>,
+}
+
+/// Reads and converts an export file.
+///
+/// # Arguments
+/// * `path` - File to import
+/// * `format` - Format of the file
+///
+/// # Returns
+/// `Result<Import>` - Converted statistics, or an error if the file can't be read
+/// or has no usable header
+pub fn import_file(path: &Path, format: ImportFormat) -> Result<Import> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse(&content, format)
+}
+
+/// Converts the contents of an export file.
+///
+/// # Arguments
+/// * `content` - File contents
+/// * `format` - Format of the contents
+///
+/// # Returns
+/// `Result<Import>` - Converted statistics
+pub fn parse(content: &str, format: ImportFormat) -> Result<Import> {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    // Column indexes of key, count and date
+    let mut columns = (0, 1, Some(2));
+
+    match format {
+        ImportFormat::WhatPulse => {
+            let Some((_, header)) = lines.next() else {
+                bail!("empty WhatPulse export");
+            };
+            let header: Vec<String> = split_csv_line(header).iter().map(|h| h.to_lowercase()).collect();
+            let find = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+            let key = find(&["key", "key name", "keyname"]).context("WhatPulse export has no 'Key' column")?;
+            let count = find(&["count", "presses", "keys", "total"]).context("WhatPulse export has no 'Count' column")?;
+            columns = (key, count, None);
+        }
+        ImportFormat::Csv => {}
+    }
+
+    let mut import = Import {
+        stats: KeyStats::new(),
+        rows: 0,
+        skipped: Vec::new(),
+    };
+
+    for (number, line) in lines {
+        let fields = split_csv_line(line);
+        let field = |i: usize| fields.get(i).map(|f| f.trim()).unwrap_or("");
+
+        let (label, count_text) = (field(columns.0), field(columns.1));
+        let Ok(count) = count_text.replace('_', "").parse::<u64>() else {
+            // A header row in a generic CSV is fine; anything later is an error
+            if !(format == ImportFormat::Csv && import.rows == 0 && import.skipped.is_empty()) {
+                import.skipped.push(SkippedRow {
+                    line: number,
+                    reason: format!("invalid count '{}'", count_text),
+                });
+            }
+            continue;
+        };
+
+        let Some(code) = keymap::code_from_label(label) else {
+            import.skipped.push(SkippedRow {
+                line: number,
+                reason: format!("unknown key '{}'", label),
+            });
+            continue;
+        };
+
+        let date = match columns.2.map(field).filter(|d| !d.is_empty()) {
+            Some(text) => match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+                Ok(date) => Some(date),
+                Err(_) => {
+                    import.skipped.push(SkippedRow {
+                        line: number,
+                        reason: format!("invalid date '{}'", text),
+                    });
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        import.stats.add_counts(&keymap::key_name(code), count, date);
+        import.rows += 1;
+    }
+
+    Ok(import)
+}
+
+/// Splits a CSV line into fields, honouring double-quoted fields.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
@@ -0,0 +1,200 @@
+//! # Integrity Module
+//!
+//! Consistency checks of a data file, behind `ctrlq fsck`. The data file
+//! keeps the same keystrokes at several levels of detail: lifetime totals,
+//! per-key counts, days with their key distribution and hourly and
+//! per-minute counts. Bugs of earlier versions (double counting, days
+//! bucketed in UTC) can leave these levels disagreeing with each other.
+//!
+//! [`check`] lists what disagrees. [`repair`] recomputes the totals from the
+//! most detailed data at hand: the lifetime total and each day's total from
+//! the key counts, hourly counts from the per-minute counts. Sessions that
+//! were stored twice are dropped. What can't be derived again, such as
+//! overlapping sessions or days in the future, is only reported.
+
+use crate::keylogger::{day_key, DayStats, KeyStats, TypingSession, REMOTE_DEVICE};
+use chrono::{DateTime, Duration, Local, Utc};
+use std::collections::HashMap;
+
+/// Something in the statistics that doesn't add up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// What is wrong, e.g. `2024-05-01: 1200 keystrokes, but its keys add up to 1180`
+    pub description: String,
+    /// Whether [`repair`] fixes it
+    pub repairable: bool,
+}
+
+impl Issue {
+    fn new(repairable: bool, description: String) -> Self {
+        Self { description, repairable }
+    }
+}
+
+/// Keystrokes reported with `ctrlq ping`, which have no key.
+fn remote(counts: &HashMap<String, u64>) -> u64 {
+    counts.get(REMOTE_DEVICE).copied().unwrap_or(0)
+}
+
+/// Lists the inconsistencies of the statistics.
+///
+/// # Arguments
+/// * `stats` - Statistics with their archived days, see `KeyLogger::load_full_stats`
+/// * `now` - Current time; later timestamps are reported
+///
+/// # Returns
+/// `Vec<Issue>` - Problems found, lifetime totals first, then by day and session
+///
+/// # Example
+/// ```
+/// use chrono::{Duration, Utc};
+/// use ctrlq::integrity;
+/// use ctrlq::keylogger::KeyStats;
+/// use ctrlq::keymap;
+///
+/// let mut stats = KeyStats::new();
+/// for _ in 0..10 {
+///     stats.add_keypress(&keymap::key_name(30));
+/// }
+/// assert!(integrity::check(&stats, Utc::now()).is_empty());
+///
+/// // Counted twice by an old bug, and a session stored twice
+/// stats.total_keystrokes = 20;
+/// stats.end_session();
+/// stats.typing_sessions.push(stats.typing_sessions[0].clone());
+/// let issues = integrity::check(&stats, Utc::now());
+/// assert_eq!(issues[0].description, "total_keystrokes is 20, but the key counts add up to 10");
+/// assert!(issues.iter().all(|issue| issue.repairable));
+///
+/// assert_eq!(integrity::repair(&mut stats), 2);
+/// assert_eq!(stats.total_keystrokes, 10);
+/// assert_eq!(stats.typing_sessions.len(), 1);
+/// assert!(integrity::check(&stats, Utc::now()).is_empty());
+///
+/// // A session in the future can't be repaired, only reported
+/// stats.typing_sessions[0].end = Utc::now() + Duration::days(2);
+/// let issues = integrity::check(&stats, Utc::now());
+/// assert_eq!(issues.len(), 1);
+/// assert!(!issues[0].repairable);
+/// ```
+pub fn check(stats: &KeyStats, now: DateTime<Utc>) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let key_total = stats.key_counts.values().sum::<u64>() + remote(&stats.device_counts);
+    if key_total != stats.total_keystrokes {
+        issues.push(Issue::new(
+            !stats.key_counts.is_empty(),
+            format!("total_keystrokes is {}, but the key counts add up to {}", stats.total_keystrokes, key_total),
+        ));
+    }
+
+    let today = day_key(now.with_timezone(&Local).date_naive());
+    let mut days: Vec<_> = stats.daily_stats.iter().collect();
+    days.sort_by(|a, b| a.0.cmp(b.0));
+    for (date, day) in days {
+        if date.as_str() > today.as_str() {
+            issues.push(Issue::new(false, format!("{}: the day is in the future", date)));
+        }
+        let expected = day_total(day);
+        if let Some(expected) = expected
+            && expected != day.keystrokes
+        {
+            issues.push(Issue::new(
+                true,
+                format!("{}: {} keystrokes, but its keys add up to {}", date, day.keystrokes, expected),
+            ));
+        }
+        let hourly: u64 = day.hourly_keystrokes.iter().sum();
+        let day_keystrokes = expected.unwrap_or(day.keystrokes);
+        if !day.hourly_keystrokes.is_empty() && hourly != day_keystrokes {
+            issues.push(Issue::new(
+                hourly_from_minutes(day, day_keystrokes).is_some(),
+                format!("{}: the hourly counts add up to {}, the day has {} keystrokes", date, hourly, day_keystrokes),
+            ));
+        }
+    }
+
+    let mut sessions: Vec<&TypingSession> = stats.typing_sessions.iter().collect();
+    sessions.sort_by_key(|session| session.start);
+    let mut previous: Option<&TypingSession> = None;
+    for session in sessions {
+        let start = session.start.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+        if session.end < session.start {
+            issues.push(Issue::new(false, format!("session {}: ends before it starts", start)));
+        }
+        // A minute of slack for clocks that were adjusted since
+        if session.end > now + Duration::minutes(1) {
+            issues.push(Issue::new(false, format!("session {}: ends in the future", start)));
+        }
+        match previous {
+            Some(last) if last.start == session.start && last.end == session.end => {
+                issues.push(Issue::new(true, format!("session {}: stored twice", start)));
+                continue;
+            }
+            Some(last) if session.start < last.end => {
+                issues.push(Issue::new(false, format!("session {}: starts before the session before it ends", start)));
+            }
+            _ => {}
+        }
+        previous = Some(session);
+    }
+
+    issues
+}
+
+/// Recomputes the totals that [`check`] found disagreeing from the most
+/// detailed data available and drops sessions stored twice.
+///
+/// # Arguments
+/// * `stats` - Statistics with their archived days
+///
+/// # Returns
+/// `usize` - Number of repaired issues; the others stay as they are
+pub fn repair(stats: &mut KeyStats) -> usize {
+    let mut repaired = 0;
+
+    let key_total = stats.key_counts.values().sum::<u64>() + remote(&stats.device_counts);
+    if !stats.key_counts.is_empty() && key_total != stats.total_keystrokes {
+        stats.total_keystrokes = key_total;
+        repaired += 1;
+    }
+
+    for day in stats.daily_stats.values_mut() {
+        if let Some(expected) = day_total(day)
+            && expected != day.keystrokes
+        {
+            day.keystrokes = expected;
+            repaired += 1;
+        }
+        if !day.hourly_keystrokes.is_empty()
+            && day.hourly_keystrokes.iter().sum::<u64>() != day.keystrokes
+            && let Some(hourly) = hourly_from_minutes(day, day.keystrokes)
+        {
+            day.hourly_keystrokes = hourly;
+            day.most_active_hour = day.busiest_hour();
+            repaired += 1;
+        }
+    }
+
+    let before = stats.typing_sessions.len();
+    stats.typing_sessions.sort_by_key(|session| session.start);
+    stats.typing_sessions.dedup_by(|a, b| a.start == b.start && a.end == b.end);
+    repaired += before - stats.typing_sessions.len();
+
+    repaired
+}
+
+/// A day's total according to its key distribution, if it has one.
+fn day_total(day: &DayStats) -> Option<u64> {
+    (!day.key_distribution.is_empty()).then(|| day.key_distribution.values().sum::<u64>() + remote(&day.device_keystrokes))
+}
+
+/// Hourly counts derived from a day's minute data, if that data agrees
+/// with the day's total.
+fn hourly_from_minutes(day: &DayStats, keystrokes: u64) -> Option<Vec<u64>> {
+    if day.minute_keystrokes.is_empty() {
+        return None;
+    }
+    let hourly = day.minute_keystrokes.hourly();
+    (hourly.iter().sum::<u64>() == keystrokes).then_some(hourly)
+}
@@ -0,0 +1,305 @@
+//! # IPC Module
+//!
+//! A small control socket so that CLI commands can talk to a running
+//! logger instead of editing the data file underneath it.
+//!
+//! The socket lives in the state directory (`keystroke_data.sock`, see
+//! [`crate::paths`]) and is only accessible to its owner. Under sudo it is
+//! created in the state directory of the user who ran sudo and handed to
+//! them, so their `ctrlq context` and prompt hooks reach the logger. The protocol is one JSON request per line,
+//! answered by one JSON response line:
+//!
+//! ```text
+//! → {"cmd":"reset","scope":"today"}
+//! ← {"ok":true}
+//! → {"cmd":"set_context","value":"myrepo"}
+//! ← {"ok":true}
+//! → {"cmd":"ping","count":40}
+//! ← {"ok":true}
+//! → {"cmd":"exported"}
+//! ← {"ok":true}
+//! → {"cmd":"status"}
+//! ← {"ok":true,"status":{"date":"2024-05-01","today":12412,"total":3482119,"wpm":71.6,"streak":9}}
+//! ```
+
+use crate::paths;
+use crate::privileges;
+use crate::status_line::StatusSnapshot;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// How long a client waits for the running instance to answer.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A command sent to a running instance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// Reset statistics; `scope` is `session`, `today` or `all`
+    Reset {
+        scope: String,
+        #[serde(default)]
+        keep_records: bool,
+    },
+    /// Attribute new keystrokes to a context (project); `default` clears it
+    SetContext { value: String },
+    /// Count keystrokes no logged keyboard saw, e.g. typed over SSH
+    Ping { count: u32 },
+    /// Statistics were exported, see `KeyStats::last_export_at`
+    Exported,
+    /// Ask for the values of `ctrlq status-line`
+    Status,
+}
+
+/// The answer to a [`Request`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Response {
+    /// Whether the request was accepted
+    pub ok: bool,
+    /// Why the request was rejected, if it was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The answer to [`Request::Status`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<StatusSnapshot>,
+}
+
+/// The user a control socket is created for when ctrlq runs under sudo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Owner {
+    /// User id
+    pub uid: u32,
+    /// Primary group id
+    pub gid: u32,
+    /// Home directory, which holds the user's state directory
+    pub home: PathBuf,
+}
+
+impl Owner {
+    /// Returns the user who ran `sudo ctrlq`, or `None` without sudo.
+    pub fn sudo() -> Option<Self> {
+        let (_, uid) = privileges::sudo_user()?;
+        let (gid, home) = privileges::user_entry(uid)?;
+        Some(Self { uid, gid, home })
+    }
+}
+
+/// Returns the control socket path belonging to a data file.
+///
+/// # Arguments
+/// * `data_file` - Path of the statistics data file
+///
+/// # Returns
+/// `PathBuf` - e.g. `keystroke_data.sock` in the state directory, see
+/// [`paths`]; under sudo the state directory of the user who ran sudo
+pub fn socket_path(data_file: &Path) -> PathBuf {
+    socket_path_for(data_file, Owner::sudo().as_ref())
+}
+
+/// Returns the control socket path of a data file for a given owner.
+///
+/// # Arguments
+/// * `data_file` - Path of the statistics data file
+/// * `owner` - The sudo user, see [`Owner::sudo`]; `None` uses this
+///   process's state directory
+///
+/// # Example
+/// ```
+/// use ctrlq::ipc::{Owner, socket_path_for};
+/// use std::path::{Path, PathBuf};
+///
+/// let owner = Owner { uid: 1000, gid: 100, home: PathBuf::from("/home/alice") };
+/// let path = socket_path_for(Path::new("/root/.local/share/ctrlq/keystroke_data.json"), Some(&owner));
+/// # if std::env::var_os("CTRLQ_STATE_DIR").is_none() && std::env::var_os("XDG_STATE_HOME").is_none() {
+/// assert_eq!(path, PathBuf::from("/home/alice/.local/state/ctrlq/keystroke_data.sock"));
+/// # }
+/// ```
+pub fn socket_path_for(data_file: &Path, owner: Option<&Owner>) -> PathBuf {
+    let dir = match owner {
+        Some(owner) => paths::Dirs::resolve(|name| std::env::var_os(name), Some(owner.home.clone())).state,
+        None => paths::state_dir(),
+    };
+    paths::state_file(&dir, data_file, "sock")
+}
+
+/// A listening control socket. The socket file is removed when dropped.
+#[derive(Debug)]
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Starts listening on `path` and answers requests on a background thread.
+///
+/// A socket file left behind by a crashed instance is replaced. If another
+/// instance is still answering on it, an error is returned instead.
+///
+/// # Arguments
+/// * `path` - Socket path, see [`socket_path`]
+/// * `handler` - Called for each request; returns the status for
+///   [`Request::Status`], and an error is reported to the client
+///
+/// # Returns
+/// `Result<Server>` - Guard that removes the socket file when dropped
+pub fn serve<F>(path: &Path, handler: F) -> Result<Server>
+where
+    F: Fn(Request) -> Result<Option<StatusSnapshot>> + Send + 'static,
+{
+    serve_as(path, Owner::sudo().as_ref(), handler)
+}
+
+/// Like [`serve`], but hands the socket to `owner`.
+///
+/// The socket, the staging directory it is bound in and any directories
+/// created for it are owned by `owner`, so a socket made by `sudo ctrlq`
+/// can be reached and later replaced by the user who ran sudo.
+///
+/// # Example
+/// ```
+/// use ctrlq::ipc::{self, Owner, Request};
+/// use std::os::unix::fs::{MetadataExt, PermissionsExt};
+///
+/// let home = std::env::temp_dir().join(format!("ctrlq-ipc-owner-{}", std::process::id()));
+/// std::fs::create_dir_all(&home)?;
+/// // Only root may hand files to someone else
+/// let me = std::fs::metadata(&home)?.uid();
+/// let (uid, gid) = if me == 0 { (4242, 4243) } else { (me, std::fs::metadata(&home)?.gid()) };
+/// let owner = Owner { uid, gid, home: home.clone() };
+///
+/// let path = home.join(".local/state/ctrlq/keystroke_data.sock");
+/// let server = ipc::serve_as(&path, Some(&owner), |_| Ok(None))?;
+/// for file in [&path, &home.join(".local/state/ctrlq"), &home.join(".local/state"), &home.join(".local")] {
+///     let meta = std::fs::symlink_metadata(file)?;
+///     assert_eq!((meta.uid(), meta.gid()), (uid, gid), "{}", file.display());
+/// }
+/// assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+/// assert!(ipc::send(&path, &Request::Exported)?.unwrap().ok);
+///
+/// drop(server);
+/// std::fs::remove_dir_all(&home)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn serve_as<F>(path: &Path, owner: Option<&Owner>, handler: F) -> Result<Server>
+where
+    F: Fn(Request) -> Result<Option<StatusSnapshot>> + Send + 'static,
+{
+    if let Some(dir) = path.parent() {
+        create_dir_owned(dir, owner).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            bail!("another ctrlq instance is already listening on {}", path.display());
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove stale socket {}", path.display()))?;
+    }
+
+    // Bound in a directory only the owner can enter and moved into place
+    // once restricted, so no other user can connect in between
+    let staging = path.with_extension(format!("{}.bind", std::process::id()));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&staging)
+        .and_then(|()| chown(&staging, owner))
+        .with_context(|| format!("failed to create {}", staging.display()))?;
+    let staged = staging.join("sock");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        chown(&staged, owner)?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_dir_all(&staging);
+    let listener = bound.with_context(|| format!("failed to listen on {}", path.display()))?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let _ = handle_client(stream, &handler);
+        }
+    });
+
+    Ok(Server { path: path.to_path_buf() })
+}
+
+/// Creates a directory and its missing parents, handing the new ones to `owner`.
+fn create_dir_owned(dir: &Path, owner: Option<&Owner>) -> std::io::Result<()> {
+    let missing: Vec<&Path> = dir.ancestors().take_while(|dir| !dir.exists()).collect();
+    std::fs::create_dir_all(dir)?;
+    missing.into_iter().try_for_each(|dir| chown(dir, owner))
+}
+
+/// Hands a file to `owner`; does nothing without one.
+fn chown(path: &Path, owner: Option<&Owner>) -> std::io::Result<()> {
+    match owner {
+        Some(owner) => std::os::unix::fs::chown(path, Some(owner.uid), Some(owner.gid)),
+        None => Ok(()),
+    }
+}
+
+/// Reads one request from a client and writes the response.
+fn handle_client<F>(stream: UnixStream, handler: &F) -> Result<()>
+where
+    F: Fn(Request) -> Result<Option<StatusSnapshot>>,
+{
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let result = serde_json::from_str::<Request>(line.trim())
+        .context("invalid request")
+        .and_then(handler);
+    let response = match result {
+        Ok(status) => Response { ok: true, error: None, status },
+        Err(e) => Response { ok: false, error: Some(format!("{:#}", e)), status: None },
+    };
+
+    let mut stream = &stream;
+    writeln!(stream, "{}", serde_json::to_string(&response)?)?;
+    Ok(())
+}
+
+/// Sends a request to the instance listening on `path`.
+///
+/// # Arguments
+/// * `path` - Socket path, see [`socket_path`]
+/// * `request` - The command to send
+///
+/// # Returns
+/// `Result<Option<Response>>` - `None` if no instance is running
+pub fn send(path: &Path, request: &Request) -> Result<Option<Response>> {
+    let stream = match UnixStream::connect(path) {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("failed to connect to {}", path.display()));
+        }
+    };
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut writer = &stream;
+    writeln!(writer, "{}", serde_json::to_string(request)?)?;
+
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut line)
+        .context("no answer from the running instance")?;
+    let response = serde_json::from_str(line.trim()).context("invalid answer from the running instance")?;
+    Ok(Some(response))
+}
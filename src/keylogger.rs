@@ -4,28 +4,171 @@
 //! Handles device discovery, keystroke capture, statistics computation,
 //! and data persistence.
 
-use anyhow::Result;
-use chrono::{DateTime, Utc};
+use crate::actions::{ActionTable, Modifiers};
+use crate::archive;
+use crate::calibration::{Typed, TypedKey};
+use crate::config::{Config, ConfigWatcher, Overrides};
+use crate::ipc;
+use crate::paths;
+use crate::profile;
+use crate::rawlog::{self, RawLogWriter};
+use crate::keymap::{self, CategoryBreakdown, KeyClass};
+use crate::layout;
+use crate::lock::LockWatcher;
+use crate::power::PowerCheck;
+use crate::hooks::{self, HookEvent, Hooks};
+use crate::ignore::IgnoreRule;
+use crate::notifications::Notifier;
+use crate::numbers;
+use crate::report;
+use crate::schedule::Schedule;
+use crate::status_line::{self, StatusSnapshot};
+use crate::summary;
+use crate::xkb::{XkbNames, XkbTranslator};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Timelike, Utc};
 use evdev::Device;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::watch;
 
-/// Represents a single keystroke event with timing information.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct KeyPress {
-    /// The key that was pressed (human-readable format)
-    pub key: String,
-    /// When the keystroke occurred
-    pub timestamp: DateTime<Utc>,
-    /// How long the key was held (if available)
-    pub duration: Option<Duration>,
+/// Minutes covered by one bucket of the day timeline.
+pub const TIMELINE_BUCKET_MINUTES: u32 = 5;
+
+/// Seconds of keystrokes kept by [`RecentKeys`] for the live ticker.
+pub const RECENT_KEYS_WINDOW_SECS: i64 = 60;
+
+/// Keystrokes each of two periods needs before key shares are compared
+/// across them, see [`share_changes`].
+pub const SHARE_CHANGE_MIN_KEYSTROKES: u64 = 100;
+
+/// Longest pause between two keystrokes that still counts as typing time
+/// for the speed by hour, see [`speed_by_hour`].
+pub const ACTIVE_TYPING_GAP: Duration = Duration::from_secs(5);
+
+/// Longest pause between two keystrokes that still counts as sustained
+/// typing for break reminders, see [`BreakReminder`].
+pub const BREAK_ACTIVE_GAP: Duration = Duration::from_secs(30);
+
+/// Pause without keystrokes that counts as a break, see [`BreakReminder`].
+pub const BREAK_LENGTH: Duration = Duration::from_secs(5 * 60);
+
+/// How soon after a navigation key Enter counts as picking from a menu or a
+/// completion list rather than ending a line, see [`BoundaryTracker`].
+pub const LINE_NAVIGATION_GAP: Duration = Duration::from_secs(2);
+
+/// Longest pause between two character keys that still makes them a
+/// bigram, see [`BigramTracker`].
+pub const BIGRAM_MAX_GAP: Duration = Duration::from_secs(1);
+
+/// Recent words whose average length stands for a word deleted with
+/// Ctrl+Backspace, see [`DeletionTracker`].
+pub const WORD_LENGTH_WINDOW: usize = 20;
+
+/// Word length assumed before any word was typed, as for WPM.
+pub const DEFAULT_WORD_LENGTH: u64 = 5;
+
+/// Typing time an hour of a day needs before its speed counts, in seconds.
+pub const SPEED_MIN_ACTIVE_SECS: u64 = 60;
+
+/// Number of most used keys kept with each recorded session.
+pub const SESSION_TOP_KEYS: usize = 10;
+
+/// Minutes after the first keystroke of a session kept for the warm-up curve.
+pub const WARMUP_MINUTES: usize = 30;
+
+/// Minutes at the start of a session whose pace is compared with the
+/// session average.
+pub const WARMUP_WINDOW: usize = 5;
+
+/// Wall-clock time that may drift from monotonic time between two checks
+/// before it counts as a clock jump (suspend, resume or a clock change).
+pub const CLOCK_JUMP_THRESHOLD: Duration = Duration::from_secs(300);
+
+/// Time a [`LockTimer`] collects before it is added to the day.
+pub const LOCK_TIME_STEP: Duration = Duration::from_secs(10);
+
+/// Data file size above which startup warns that loading gets slow, see
+/// [`data_file_size_warning`].
+pub const LARGE_DATA_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Returns a warning for a data file above [`LARGE_DATA_FILE_BYTES`].
+///
+/// # Arguments
+/// * `data_file` - Path of the statistics data file
+///
+/// # Returns
+/// `Option<String>` - The warning, or None for a missing or small file
+pub fn data_file_size_warning(data_file: &Path) -> Option<String> {
+    let size = std::fs::metadata(data_file).ok()?.len();
+    (size > LARGE_DATA_FILE_BYTES).then(|| {
+        format!(
+            "⚠️  {} is {} MiB and slow to load; ctrlq compact makes it smaller",
+            data_file.display(),
+            size.div_ceil(1024 * 1024)
+        )
+    })
 }
 
+/// Number of distinct device errors kept in [`Diagnostics`].
+pub const DIAGNOSTIC_ERROR_HISTORY: usize = 20;
+
+/// Context keystrokes are attributed to until `ctrlq context` sets one.
+pub const DEFAULT_CONTEXT: &str = "default";
+
+/// Longest accepted context name, in characters.
+pub const MAX_CONTEXT_LEN: usize = 64;
+
+/// Longest accepted session tag, in characters.
+pub const MAX_TAG_LEN: usize = 32;
+
+/// Longest accepted session note, in characters.
+pub const MAX_NOTE_LEN: usize = 200;
+
+/// Device label of keystrokes reported by `ctrlq ping` instead of read from
+/// a keyboard, e.g. typed over SSH.
+pub const REMOTE_DEVICE: &str = "remote";
+
+/// Parts of the names of keyboards built into laptops: the i8042 keyboard
+/// of most laptops, and Apple's.
+pub const BUILT_IN_KEYBOARDS: [&str; 2] = ["AT Translated Set 2", "Internal Keyboard"];
+
+/// Most keystrokes one `ctrlq ping` may report.
+pub const MAX_REMOTE_BATCH: u32 = 10_000;
+
+/// How often devices are polled while typing.
+pub const ACTIVE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often devices are polled while idle.
+pub const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Longest time between two statistics snapshots while typing.
+pub const ACTIVE_SNAPSHOT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Longest time between two statistics snapshots while idle.
+pub const IDLE_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Shortest time between two statistics snapshots; changes in between are
+/// sent together with the next one.
+pub const MIN_SNAPSHOT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Name of the logging thread, so a panic hook can tell its panics apart.
+pub const LOGGER_THREAD: &str = "ctrlq-logger";
+
+/// Version of the data file written by [`KeyLogger::save_stats`], stored
+/// as `format_version`.
+///
+/// - 0 (no `format_version`): always contains `key_sequences`, the last
+///   100 keys typed
+/// - 1: `key_sequences` is only written with `persist_sequences = true`;
+///   the sequences of older files are still loaded, once
+pub const DATA_FORMAT_VERSION: u32 = 1;
+
 /// Comprehensive typing statistics and session data.
 ///
 /// Tracks all keystroke data, calculates statistics, and maintains
@@ -34,450 +177,7457 @@ pub struct KeyPress {
 pub struct KeyStats {
     /// Count of each key pressed
     pub key_counts: HashMap<String, u64>,
-    /// Recent sequence of keystrokes (limited to last 100)
+    /// Recent sequence of keystrokes (limited to last 100); kept in memory
+    /// and only saved if [`KeyStats::persist_sequences`] is set
+    #[serde(default, skip_serializing)]
     pub key_sequences: Vec<String>,
+    /// Whether [`KeyStats::key_sequences`] is written to the data file
+    /// (`persist_sequences` in the config)
+    #[serde(skip)]
+    pub persist_sequences: bool,
+    /// Set by the logger whenever the statistics change, so snapshots with
+    /// the same revision are identical, see [`KeyStats::bump_revision`]
+    #[serde(skip)]
+    pub revision: u64,
+    /// [`DATA_FORMAT_VERSION`] of the file the statistics were loaded from
+    #[serde(default, skip_serializing)]
+    pub format_version: u32,
     /// When the current session started
     pub session_start: DateTime<Utc>,
-    /// Total keystrokes in current session
+    /// Total keystrokes recorded
     pub total_keystrokes: u64,
     /// Historical typing sessions
     pub typing_sessions: Vec<TypingSession>,
     /// Daily statistics by date
     pub daily_stats: HashMap<String, DayStats>,
+    /// Today's keystrokes in five-minute buckets
+    #[serde(default)]
+    pub timeline: DayTimeline,
+    /// Keystrokes in the current session
+    #[serde(default)]
+    pub session_keystrokes: u64,
+    /// Character-producing keystrokes (letters, digits, punctuation, space)
+    /// in the current session
+    #[serde(default)]
+    pub char_keystrokes: u64,
+    /// Backspace presses in the current session
+    #[serde(default)]
+    pub corrections: u64,
+    /// Words typed in the current session, see [`BoundaryTracker`]
+    #[serde(default)]
+    pub session_words: u64,
+    /// Personal bests kept across sessions
+    #[serde(default)]
+    pub records: Records,
+    /// How many keys were held down at once when keys were pressed
+    #[serde(default)]
+    pub rollover: Rollover,
+    /// Key counts of the current session
+    #[serde(skip)]
+    pub session_key_counts: HashMap<String, u64>,
+    /// Keystrokes in each minute of the current session
+    #[serde(skip)]
+    pub session_timeline: Vec<u32>,
+    /// When the first keystroke of the current session happened
+    #[serde(skip)]
+    pub session_first_key: Option<DateTime<Utc>>,
+    /// Character keystrokes per minute after the first keystroke of the
+    /// current session
+    #[serde(skip)]
+    pub session_warmup: [u32; WARMUP_MINUTES],
+    /// When the last keystroke happened, to measure typing time
+    #[serde(skip)]
+    pub last_keypress: Option<DateTime<Utc>>,
+    /// Lifetime keystrokes per keyboard, keyed by device label
+    #[serde(default)]
+    pub device_counts: HashMap<String, u64>,
+    /// Lifetime keystrokes per key of each keyboard, keyed by device label
+    /// and stored key name, for comparing their heatmaps (see
+    /// [`crate::heatmap`]). Not reduced by a reset of today
+    #[serde(default)]
+    pub device_key_counts: HashMap<String, HashMap<String, u64>>,
+    /// Lifetime keystrokes per context (project), see `ctrlq context`
+    #[serde(default)]
+    pub context_counts: HashMap<String, u64>,
+    /// Lifetime count of each character typed on the active XKB layout;
+    /// empty unless built with the `xkb` feature, see [`crate::xkb`]
+    #[serde(default)]
+    pub char_counts: HashMap<String, u64>,
+    /// Lifetime count of each pair of character keys typed in a row, keyed
+    /// by both key names separated by a space (`KEY_18 KEY_32`), see
+    /// [`BigramTracker`]
+    #[serde(default)]
+    pub bigram_counts: HashMap<String, u64>,
+    /// Keystrokes per context in the current session
+    #[serde(skip)]
+    pub session_context_counts: HashMap<String, u64>,
+    /// Keystrokes per keyboard in the current session, keyed by device label
+    #[serde(skip)]
+    pub session_device_counts: HashMap<String, u64>,
+    /// Context new keystrokes are attributed to (None = [`DEFAULT_CONTEXT`])
+    #[serde(skip)]
+    pub active_context: Option<String>,
+    /// Tag of the current session, see [`TypingSession::tag`]
+    #[serde(skip)]
+    pub session_tag: Option<String>,
+    /// Note of the current session, see [`TypingSession::note`]
+    #[serde(skip)]
+    pub session_note: Option<String>,
+    /// Whether keys were recorded as classes only (`--anonymize`)
+    #[serde(default)]
+    pub anonymized: bool,
+    /// Whether shifted characters were recorded apart (`--case-sensitive`),
+    /// as `KEY_<code>_S` names
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// First day whose `sessions` count every closed session; days before
+    /// it were recorded by versions that didn't count them (None = no
+    /// session closed yet)
+    #[serde(default)]
+    pub sessions_counted_since: Option<NaiveDate>,
+    /// When statistics were last exported with `ctrlq export`, `--export`
+    /// or `:export report`, None if never; shown before a reset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_export_at: Option<DateTime<Utc>>,
+    /// Event and device health counters of the running logger
+    #[serde(skip)]
+    pub diagnostics: Diagnostics,
+    /// Character keystrokes per second over the last five minutes,
+    /// used for the rolling WPM records
+    #[serde(skip)]
+    recent_chars: VecDeque<(i64, u32)>,
+    /// Keystrokes of the last minute with their time; unlike
+    /// `key_sequences` it is not capped at 100 keys
+    #[serde(skip)]
+    pub recent_keys: RecentKeys,
+    /// When the keys at the end of `key_sequences` were typed; sequences
+    /// loaded from the data file have no times
+    #[serde(skip)]
+    key_sequence_times: VecDeque<DateTime<Utc>>,
+    /// Logger uptime below a whole second, not added to
+    /// [`DayStats::coverage_seconds`] yet
+    #[serde(skip)]
+    coverage_pending: Duration,
 }
 
-/// Information about a single typing session.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TypingSession {
-    /// Session start time
-    pub start: DateTime<Utc>,
-    /// Session end time
-    pub end: DateTime<Utc>,
-    /// Total keystrokes in session
-    pub keystrokes: u64,
-    /// Calculated words per minute (if available)
-    pub wpm: Option<f64>,
+/// A single personal best and when it was achieved.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Record<T> {
+    /// The record value
+    pub value: T,
+    /// When the record was set (for session records, when the session started)
+    pub achieved: DateTime<Utc>,
 }
 
-/// Statistics aggregated by day.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DayStats {
-    /// Total keystrokes for the day
-    pub keystrokes: u64,
-    /// Number of typing sessions
-    pub sessions: u64,
-    /// Hour of day with most activity (0-23)
-    pub most_active_hour: Option<u8>,
-    /// Key usage distribution for the day
-    pub key_distribution: HashMap<String, u64>,
+/// Personal typing records persisted across sessions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Records {
+    /// Highest WPM over any rolling one-minute window
+    pub best_wpm_1m: Option<Record<f64>>,
+    /// Highest WPM over any rolling five-minute window
+    pub best_wpm_5m: Option<Record<f64>>,
+    /// Most keystrokes recorded in a single day
+    pub most_keystrokes_day: Option<Record<u64>>,
+    /// Longest session in seconds
+    pub longest_session_secs: Option<Record<u64>>,
 }
 
-impl KeyStats {
-    /// Creates a new empty KeyStats instance.
-    ///
-    /// Initializes all counters to zero and sets the session start time
-    /// to the current UTC time.
-    pub fn new() -> Self {
-        Self {
-            key_counts: HashMap::new(),
-            key_sequences: Vec::new(),
-            session_start: Utc::now(),
-            total_keystrokes: 0,
-            typing_sessions: Vec::new(),
-            daily_stats: HashMap::new(),
-        }
-    }
-
-    /// Records a new keystroke and updates all relevant statistics.
-    ///
-    /// # Arguments
-    /// * `key` - The key that was pressed (human-readable format)
-    pub fn add_keypress(&mut self, key: &str) {
-        *self.key_counts.entry(key.to_string()).or_insert(0) += 1;
-        self.total_keystrokes += 1;
-        
-        self.key_sequences.push(key.to_string());
-        if self.key_sequences.len() > 100 {
-            self.key_sequences.remove(0);
+impl Records {
+    /// Keeps the better of each record from `self` and `other`.
+    pub fn merge(&mut self, other: &Records) {
+        fn best<T: PartialOrd + Copy>(a: &mut Option<Record<T>>, b: Option<Record<T>>) {
+            if let Some(b) = b
+                && a.is_none_or(|a| b.value > a.value)
+            {
+                *a = Some(b);
+            }
         }
 
-        let today = Utc::now().format("%Y-%m-%d").to_string();
-        let day_stats = self.daily_stats.entry(today).or_insert_with(|| DayStats {
-            keystrokes: 0,
-            sessions: 0,
-            most_active_hour: None,
-            key_distribution: HashMap::new(),
-        });
-        
-        day_stats.keystrokes += 1;
-        *day_stats.key_distribution.entry(key.to_string()).or_insert(0) += 1;
+        best(&mut self.best_wpm_1m, other.best_wpm_1m);
+        best(&mut self.best_wpm_5m, other.best_wpm_5m);
+        best(&mut self.most_keystrokes_day, other.most_keystrokes_day);
+        best(&mut self.longest_session_secs, other.longest_session_secs);
     }
 
-    /// Calculates the current words per minute (WPM) for this session.
+    /// Lists the records in `self` that beat an earlier snapshot.
     ///
-    /// Uses a rough estimation of 5 characters per word. Returns None
-    /// if insufficient data is available for calculation.
+    /// Records that didn't exist before are not reported, so the first
+    /// minute of a fresh data file doesn't announce a record on every key.
+    /// Day and session records are only reported once, when a new day or
+    /// session takes over the record.
+    ///
+    /// # Arguments
+    /// * `previous` - Records from an earlier snapshot
     ///
     /// # Returns
-    /// `Option<f64>` - WPM if calculable, None otherwise
-    pub fn get_wpm(&self) -> Option<f64> {
-        if self.total_keystrokes < 5 {
-            return None;
+    /// `Vec<String>` - Human-readable description of each broken record
+    pub fn broken_since(&self, previous: &Records) -> Vec<String> {
+        let mut broken = Vec::new();
+
+        if let (Some(old), Some(new)) = (previous.best_wpm_1m, self.best_wpm_1m)
+            && new.value > old.value
+        {
+            broken.push(format!("best 1-min WPM {:.1}", new.value));
         }
-        
-        let elapsed = Utc::now().signed_duration_since(self.session_start);
-        let minutes = elapsed.num_seconds() as f64 / 60.0;
-        
-        if minutes > 0.0 {
-            Some((self.total_keystrokes as f64 / 5.0) / minutes)
-        } else {
-            None
+        if let (Some(old), Some(new)) = (previous.best_wpm_5m, self.best_wpm_5m)
+            && new.value > old.value
+        {
+            broken.push(format!("best 5-min WPM {:.1}", new.value));
+        }
+        if let (Some(old), Some(new)) = (previous.most_keystrokes_day, self.most_keystrokes_day)
+            && new.value > old.value
+            && new.achieved.date_naive() != old.achieved.date_naive()
+        {
+            broken.push(format!("most keystrokes in a day ({})", numbers::format_count(new.value)));
         }
+        if let (Some(old), Some(new)) = (previous.longest_session_secs, self.longest_session_secs)
+            && new.value > old.value
+            && new.achieved != old.achieved
+        {
+            broken.push(format!("longest session ({} min)", new.value / 60));
+        }
+
+        broken
     }
+}
 
-    /// Gets the top N most frequently pressed keys.
-    ///
-    /// Returns a sorted list of keys by press count in descending order.
+/// Rollover statistics: how many keys were held down at once when a key
+/// was pressed. Fast typists often press the next key before releasing the
+/// previous one.
+///
+/// Modifiers are left out, so Shift+A is a single key and not a chord.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rollover {
+    /// Presses by keys down at the time, the new one included; index 0
+    /// counts presses with nothing else held
+    pub presses_by_depth: Vec<u64>,
+    /// Keys held down right now
+    #[serde(skip)]
+    pub current: u32,
+}
+
+impl Rollover {
+    /// Counts a key press.
     ///
     /// # Arguments
-    /// * `limit` - Maximum number of keys to return
+    /// * `depth` - Keys down including the pressed one, at least 1
     ///
-    /// # Returns
-    /// `Vec<(String, u64)>` - List of (key, count) pairs
-    pub fn get_top_keys(&self, limit: usize) -> Vec<(String, u64)> {
-        let mut sorted: Vec<_> = self.key_counts.iter().collect();
-        sorted.sort_by(|a, b| b.1.cmp(a.1));
-        sorted.into_iter()
-            .take(limit)
-            .map(|(k, v)| (k.clone(), *v))
-            .collect()
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::Rollover;
+    ///
+    /// let mut rollover = Rollover::default();
+    /// for depth in [1, 2, 1, 3, 2] {
+    ///     rollover.record(depth);
+    /// }
+    /// assert_eq!(rollover.max_depth(), 3);
+    /// assert_eq!(rollover.chorded_presses(), 3);
+    /// assert_eq!(rollover.presses_by_depth, vec![2, 2, 1]);
+    /// ```
+    pub fn record(&mut self, depth: u32) {
+        let index = depth.max(1) as usize - 1;
+        if self.presses_by_depth.len() <= index {
+            self.presses_by_depth.resize(index + 1, 0);
+        }
+        self.presses_by_depth[index] += 1;
     }
 
-    /// Resets all statistics to their initial state.
-    ///
-    /// Clears all keystroke counts, resets session timing,
-    /// and reinitializes all tracking data.
-    pub fn reset(&mut self) {
-        self.key_counts.clear();
-        self.key_sequences.clear();
-        self.session_start = Utc::now();
-        self.total_keystrokes = 0;
-        self.typing_sessions.clear();
-        self.daily_stats.clear();
+    /// Most keys ever held down at once, 0 before the first press.
+    pub fn max_depth(&self) -> u32 {
+        self.presses_by_depth
+            .iter()
+            .rposition(|&count| count > 0)
+            .map_or(0, |index| index as u32 + 1)
+    }
+
+    /// Presses made while at least one other key was held.
+    pub fn chorded_presses(&self) -> u64 {
+        self.presses_by_depth.iter().skip(1).sum()
+    }
+
+    /// Adds the presses of another data file.
+    pub fn merge(&mut self, other: &Rollover) {
+        if self.presses_by_depth.len() < other.presses_by_depth.len() {
+            self.presses_by_depth.resize(other.presses_by_depth.len(), 0);
+        }
+        for (count, other) in self.presses_by_depth.iter_mut().zip(&other.presses_by_depth) {
+            *count += other;
+        }
     }
 }
 
-/// Main keylogger implementation that monitors keyboard input.
+/// Counts the held keys of one source that take part in rollover.
 ///
-/// Handles device management, keystroke capture, statistics tracking,
-/// and data persistence. Supports both UI and headless operation modes.
-pub struct KeyLogger {
-    /// Path to the keyboard input device (e.g., /dev/input/event8)
-    device_path: String,
-    /// Current keystroke statistics and session data
-    stats: KeyStats,
-    /// Path to the JSON data file for persistence
-    data_file: PathBuf,
-    /// Whether the keylogger is currently running
-    is_running: bool,
-    /// Track key press timing for hold duration calculation
-    #[allow(dead_code)]
-    key_down_times: HashMap<u16, Instant>,
+/// # Arguments
+/// * `held` - Key codes held down on the source
+///
+/// # Returns
+/// `u32` - Number of held keys that are not modifiers
+pub fn rollover_depth(held: impl IntoIterator<Item = u16>) -> u32 {
+    held.into_iter()
+        .filter(|&code| keymap::classify(code) != KeyClass::Modifier)
+        .count() as u32
 }
 
-impl KeyLogger {
-    /// Creates a new KeyLogger instance for the specified device.
-    ///
-    /// Initializes the data directory, loads existing statistics from file
-    /// if available, and prepares the keylogger for operation.
-    ///
-    /// # Arguments
-    /// * `device_path` - Path to the keyboard input device
-    ///
-    /// # Returns
-    /// `Result<Self>` - New KeyLogger instance or error
-    pub fn new(device_path: String) -> Result<Self> {
-        let data_dir = dirs::data_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("ctrlq");
-        
-        std::fs::create_dir_all(&data_dir)?;
-        let data_file = data_dir.join("keystroke_data.json");
-
-        let stats = if data_file.exists() {
-            let content = std::fs::read_to_string(&data_file)?;
-            serde_json::from_str(&content).unwrap_or_else(|_| KeyStats::new())
-        } else {
-            KeyStats::new()
-        };
+/// Word and line boundaries found in one key press by [`BoundaryTracker`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Boundary {
+    /// The press ended a word
+    pub word: bool,
+    /// The press ended a line
+    pub line: bool,
+}
 
-        Ok(Self {
-            device_path,
-            stats,
-            data_file,
-            is_running: false,
-            key_down_times: HashMap::new(),
-        })
-    }
+/// Finds the ends of words and lines in the key presses of the logging
+/// loop, for counting real words instead of keystrokes / 5.
+///
+/// A word ends when space, Enter, Tab or punctuation follows a letter;
+/// digits and backspace don't interrupt a word, other keys drop it. A line
+/// ends with Enter after something was typed on it, unless a navigation
+/// key came within [`LINE_NAVIGATION_GAP`] (picking from a menu with the
+/// arrows). Feed it presses only: auto-repeats of space or Enter would
+/// otherwise end words and lines that were never typed.
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::{Boundary, BoundaryTracker};
+/// use std::time::{Duration, Instant};
+///
+/// const H: u16 = 35;
+/// const I: u16 = 23;
+/// const SPACE: u16 = 57;
+/// const ENTER: u16 = 28;
+/// const DOWN: u16 = 108;
+///
+/// let now = Instant::now();
+/// let mut tracker = BoundaryTracker::default();
+/// let mut count = |codes: &[u16], at: Instant| {
+///     codes.iter().fold((0, 0), |(words, lines), &code| {
+///         let Boundary { word, line } = tracker.press(code, at);
+///         (words + word as u32, lines + line as u32)
+///     })
+/// };
+///
+/// // "hi hi⏎": two words, one line; the extra spaces end nothing
+/// assert_eq!(count(&[H, I, SPACE, SPACE, SPACE, H, I, ENTER], now), (2, 1));
+/// // Enter on an empty line, and Enter right after the arrows
+/// assert_eq!(count(&[ENTER], now), (0, 0));
+/// assert_eq!(count(&[H, I, DOWN, ENTER], now), (0, 0));
+/// // The same Enter a while after moving the cursor ends the line
+/// assert_eq!(count(&[H, DOWN], now), (0, 0));
+/// assert_eq!(count(&[ENTER], now + Duration::from_secs(5)), (0, 1));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BoundaryTracker {
+    /// A letter was typed since the last boundary
+    in_word: bool,
+    /// Something was typed since the last Enter
+    line_has_text: bool,
+    /// When the last navigation key was pressed
+    last_navigation: Option<Instant>,
+}
 
-    /// Starts the keystroke monitoring in a background thread.
+impl BoundaryTracker {
+    /// Feeds one key press.
     ///
-    /// Creates communication channels for statistics updates, shutdown signals,
-    /// and reset commands. Spawns a background thread to handle the actual
-    /// keystroke monitoring loop.
+    /// # Arguments
+    /// * `code` - The evdev key code
+    /// * `now` - When the key was pressed
     ///
     /// # Returns
-    /// `Result<(Receiver<KeyStats>, Sender<bool>, Sender<()>)>` - Channels for
-    /// receiving statistics updates, sending shutdown signal, and sending reset signal
-    pub fn start_logging(&mut self) -> Result<(mpsc::Receiver<KeyStats>, watch::Sender<bool>, mpsc::Sender<()>)> {
-        let (stats_tx, stats_rx) = mpsc::channel();
-        let (shutdown_tx, shutdown_rx) = watch::channel(false);
-        let (reset_tx, reset_rx) = mpsc::channel();
-        
-        let device_path = self.device_path.clone();
-        let mut stats = self.stats.clone();
-        let data_file = self.data_file.clone();
-        
-        self.is_running = true;
-
-        thread::spawn(move || {
-            if let Err(e) = Self::logging_loop(device_path, &mut stats, data_file, stats_tx, shutdown_rx, reset_rx) {
-                eprintln!("Logging error: {}", e);
+    /// `Boundary` - Whether the press ended a word or a line
+    pub fn press(&mut self, code: u16, now: Instant) -> Boundary {
+        let mut boundary = Boundary::default();
+        match keymap::classify(code) {
+            KeyClass::Alpha => {
+                self.in_word = true;
+                self.line_has_text = true;
             }
-        });
-
-        Ok((stats_rx, shutdown_tx, reset_tx))
+            KeyClass::Digit => self.line_has_text = true,
+            KeyClass::Whitespace | KeyClass::Punctuation => {
+                boundary.word = std::mem::take(&mut self.in_word);
+                if code == keymap::KEY_ENTER {
+                    let navigating = self
+                        .last_navigation
+                        .is_some_and(|at| now.saturating_duration_since(at) < LINE_NAVIGATION_GAP);
+                    boundary.line = std::mem::take(&mut self.line_has_text) && !navigating;
+                } else if code != keymap::KEY_SPACE {
+                    self.line_has_text = true;
+                }
+            }
+            KeyClass::Editing | KeyClass::Modifier => {}
+            KeyClass::Navigation => {
+                self.in_word = false;
+                self.last_navigation = Some(now);
+            }
+            _ => self.in_word = false,
+        }
+        boundary
     }
+}
 
-    /// Main monitoring loop that runs in a background thread.
-    ///
-    /// Continuously monitors the specified device for keystroke events,
-    /// updates statistics, handles reset commands, and periodically saves data.
+/// Characters typed and deleted by one key event, see [`DeletionTracker`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Edit {
+    /// Characters typed
+    pub typed: u64,
+    /// Characters deleted
+    pub deleted: u64,
+}
+
+/// Estimates the characters typed and deleted by key events, for how much
+/// of what was typed survives.
+///
+/// Presses and auto-repeats of character keys (letters, digits,
+/// punctuation, space, Enter and Tab) type a character each, unless Ctrl,
+/// Alt or Meta is held. Backspace and Delete delete a character per press
+/// and per auto-repeat, since a held Backspace keeps deleting. With Ctrl
+/// they delete a word: the part of the current word typed so far, or
+/// between words the average length of the last [`WORD_LENGTH_WINDOW`]
+/// words ([`DEFAULT_WORD_LENGTH`] before the first). These are estimates:
+/// selections, undo and the mouse are invisible to it. Feed it every key
+/// event, repeats included.
+///
+/// # Example
+/// ```
+/// use ctrlq::actions::Modifiers;
+/// use ctrlq::keylogger::{DeletionTracker, Edit, KeyState};
+///
+/// const H: u16 = 35;
+/// const I: u16 = 23;
+/// const SPACE: u16 = 57;
+/// const BACKSPACE: u16 = 14;
+/// const C: u16 = 46;
+///
+/// let mut tracker = DeletionTracker::default();
+/// let mut run = |script: &[(u16, KeyState, bool)]| {
+///     script.iter().fold(Edit::default(), |total, &(code, state, ctrl)| {
+///         let modifiers = Modifiers { ctrl, ..Modifiers::default() };
+///         let edit = tracker.event(code, state, modifiers);
+///         Edit { typed: total.typed + edit.typed, deleted: total.deleted + edit.deleted }
+///     })
+/// };
+/// let press = |code| (code, KeyState::Pressed, false);
+/// let release = |code| (code, KeyState::Released, false);
+///
+/// // "hi hi" and one Backspace: 5 typed, 1 deleted
+/// let typed = run(&[press(H), release(H), press(I), press(SPACE), press(H), press(I), press(BACKSPACE)]);
+/// assert_eq!(typed, Edit { typed: 5, deleted: 1 });
+///
+/// // Backspace held through three auto-repeats deletes four characters
+/// let held = run(&[press(BACKSPACE), (BACKSPACE, KeyState::Repeated, false), (BACKSPACE, KeyState::Repeated, false),
+///     (BACKSPACE, KeyState::Repeated, false), release(BACKSPACE)]);
+/// assert_eq!(held, Edit { typed: 0, deleted: 4 });
+///
+/// // Ctrl+C types nothing; Ctrl+Backspace mid-word deletes what was typed of it
+/// let word = run(&[press(SPACE), press(H), press(I), press(H), (C, KeyState::Pressed, true),
+///     (BACKSPACE, KeyState::Pressed, true)]);
+/// assert_eq!(word, Edit { typed: 4, deleted: 3 });
+///
+/// // Between words it deletes an average word: "hi" was the only one, 2 letters
+/// let between = run(&[(BACKSPACE, KeyState::Pressed, true)]);
+/// assert_eq!(between, Edit { typed: 0, deleted: 2 });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DeletionTracker {
+    /// Characters of the current word typed so far
+    current_word: u64,
+    /// Lengths of the last finished words, oldest first
+    word_lengths: VecDeque<u64>,
+}
+
+impl DeletionTracker {
+    /// Feeds one key event.
     ///
     /// # Arguments
-    /// * `device_path` - Path to the keyboard device to monitor
-    /// * `stats` - Mutable reference to the statistics structure
-    /// * `data_file` - Path to save statistics data
-    /// * `stats_tx` - Channel to send statistics updates
-    /// * `shutdown_rx` - Channel to receive shutdown signals
-    /// * `reset_rx` - Channel to receive reset commands
-    fn logging_loop(
-        device_path: String,
-        stats: &mut KeyStats,
-        data_file: PathBuf,
-        stats_tx: mpsc::Sender<KeyStats>,
-        shutdown_rx: watch::Receiver<bool>,
-        reset_rx: mpsc::Receiver<()>,
-    ) -> Result<()> {
-        let mut device = Device::open(&device_path)?;
-        println!("🎯 Keylogger started on device: {}", device_path);
-        println!("📊 Data will be saved to: {}", data_file.display());
-        
-        let mut key_down_times = HashMap::new();
-        let mut last_save = Instant::now();
-        
-        loop {
-            if shutdown_rx.has_changed().unwrap_or(false) && *shutdown_rx.borrow() {
-                break;
+    /// * `code` - The evdev key code
+    /// * `state` - Press, auto-repeat or release
+    /// * `modifiers` - Modifiers held at the event
+    ///
+    /// # Returns
+    /// `Edit` - The characters the event typed or deleted
+    pub fn event(&mut self, code: u16, state: KeyState, modifiers: Modifiers) -> Edit {
+        if state == KeyState::Released {
+            return Edit::default();
+        }
+        let shortcut = modifiers.ctrl || modifiers.alt || modifiers.meta;
+        if code == keymap::KEY_BACKSPACE || code == keymap::KEY_DELETE {
+            let deleted = if !modifiers.ctrl {
+                if code == keymap::KEY_BACKSPACE {
+                    self.current_word = self.current_word.saturating_sub(1);
+                }
+                1
+            } else if code == keymap::KEY_BACKSPACE && self.current_word > 0 {
+                std::mem::take(&mut self.current_word)
+            } else {
+                self.word_length()
+            };
+            return Edit { typed: 0, deleted };
+        }
+        if shortcut {
+            return Edit::default();
+        }
+        match keymap::classify(code) {
+            KeyClass::Modifier => Edit::default(),
+            KeyClass::Alpha | KeyClass::Digit => {
+                self.current_word += 1;
+                Edit { typed: 1, deleted: 0 }
             }
-
-            if let Ok(()) = reset_rx.try_recv() {
-                println!("🔄 Resetting statistics...");
-                *stats = KeyStats::new();
-                let _ = Self::save_stats(stats, &data_file);
-            }
-
-            match device.fetch_events() {
-                Ok(events) => {
-                    for event in events {
-                        if event.event_type() == evdev::EventType::KEY {
-                            let key_code = event.code();
-                            let key_name = format!("KEY_{}", key_code);
-                            
-                            match event.value() {
-                                1 => {
-                                    key_down_times.insert(key_code, Instant::now());
-                                    stats.add_keypress(&key_name);
-                                }
-                                0 => {
-                                    if let Some(down_time) = key_down_times.remove(&key_code) {
-                                        let _duration = down_time.elapsed();
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
+            KeyClass::Whitespace | KeyClass::Punctuation => {
+                let length = std::mem::take(&mut self.current_word);
+                if length > 0 {
+                    if self.word_lengths.len() == WORD_LENGTH_WINDOW {
+                        self.word_lengths.pop_front();
                     }
+                    self.word_lengths.push_back(length);
                 }
-                Err(_) => {
-                    thread::sleep(Duration::from_millis(10));
-                }
+                Edit { typed: 1, deleted: 0 }
             }
-
-            if stats_tx.send(stats.clone()).is_err() {
-                break;
-            }
-
-            if last_save.elapsed() > Duration::from_secs(30) {
-                if let Err(e) = Self::save_stats(stats, &data_file) {
-                    eprintln!("Failed to save stats: {}", e);
-                }
-                last_save = Instant::now();
+            _ => {
+                // Cursor movement leaves the word behind
+                self.current_word = 0;
+                Edit::default()
             }
-
-            thread::sleep(Duration::from_millis(10));
         }
+    }
 
-        let _ = Self::save_stats(stats, &data_file);
-        println!("📁 Keylogger stopped and data saved");
-        Ok(())
+    /// Average length of the recent words, rounded.
+    fn word_length(&self) -> u64 {
+        if self.word_lengths.is_empty() {
+            return DEFAULT_WORD_LENGTH;
+        }
+        let total: u64 = self.word_lengths.iter().sum();
+        let count = self.word_lengths.len() as u64;
+        (total + count / 2) / count
     }
+}
 
-    /// Saves keystroke statistics to a JSON file.
-    ///
-    /// Serializes the current statistics to pretty-printed JSON format
-    /// and writes it to the specified file path.
+/// Pairs up consecutive character keys for the same-finger bigrams of
+/// [`crate::layout::same_finger_bigrams`].
+///
+/// Only keys of the character block pair up, and only within
+/// [`BIGRAM_MAX_GAP`] of each other. Any other key in between (space,
+/// Enter, Backspace, arrows, ...) breaks the pair, so words typed after
+/// moving the cursor don't pair with where it started; modifiers don't,
+/// so Shift inside a word keeps its bigrams. Feed it presses only.
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::BigramTracker;
+/// use std::time::{Duration, Instant};
+///
+/// const E: u16 = 18;
+/// const D: u16 = 32;
+/// const SHIFT: u16 = 42;
+/// const SPACE: u16 = 57;
+/// const DOWN: u16 = 108;
+///
+/// let start = Instant::now();
+/// let at = |ms: u64| start + Duration::from_millis(ms);
+/// let mut tracker = BigramTracker::default();
+///
+/// assert_eq!(tracker.press(E, at(0)), None);
+/// assert_eq!(tracker.press(SHIFT, at(100)), None);
+/// assert_eq!(tracker.press(D, at(200)), Some((E, D)));
+/// // Navigation in between breaks the pair
+/// assert_eq!(tracker.press(DOWN, at(300)), None);
+/// assert_eq!(tracker.press(E, at(400)), None);
+/// // So do a space and a long pause
+/// assert_eq!(tracker.press(SPACE, at(500)), None);
+/// assert_eq!(tracker.press(D, at(600)), None);
+/// assert_eq!(tracker.press(E, at(2000)), None);
+/// assert_eq!(tracker.press(D, at(2100)), Some((E, D)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BigramTracker {
+    /// The last character key and when it was pressed
+    last: Option<(u16, Instant)>,
+}
+
+impl BigramTracker {
+    /// Feeds one key press.
     ///
     /// # Arguments
-    /// * `stats` - Statistics to save
-    /// * `data_file` - File path to save to
+    /// * `code` - The evdev key code
+    /// * `now` - When the key was pressed
     ///
     /// # Returns
-    /// `Result<()>` - Success or file I/O error
-    fn save_stats(stats: &KeyStats, data_file: &PathBuf) -> Result<()> {
-        let json = serde_json::to_string_pretty(stats)?;
-        std::fs::write(data_file, json)?;
-        Ok(())
+    /// `Option<(u16, u16)>` - The bigram the press completed, if any
+    pub fn press(&mut self, code: u16, now: Instant) -> Option<(u16, u16)> {
+        if keymap::classify(code) == KeyClass::Modifier {
+            return None;
+        }
+        if layout::finger(code).is_none() {
+            self.last = None;
+            return None;
+        }
+        let previous = self.last.replace((code, now));
+        previous
+            .filter(|(_, at)| now.saturating_duration_since(*at) <= BIGRAM_MAX_GAP)
+            .map(|(first, _)| (first, code))
+    }
+}
+
+/// Whether a laptop was typed on at a desk or on the go, told by the
+/// keyboard a session was mostly typed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dock {
+    /// Mostly typed on an external keyboard
+    Docked,
+    /// Mostly typed on the built-in keyboard
+    Mobile,
+}
+
+impl Dock {
+    /// Both, in the order of `--dock`.
+    pub const ALL: [Dock; 2] = [Self::Docked, Self::Mobile];
+
+    /// Returns the name used in the data file, in the UI and for `--dock`.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Docked => "docked",
+            Self::Mobile => "mobile",
+        }
+    }
+
+    /// Parses a [`Dock::label`].
+    pub fn parse(label: &str) -> Result<Self> {
+        match Self::ALL.into_iter().find(|dock| dock.label() == label.trim().to_lowercase()) {
+            Some(dock) => Ok(dock),
+            None => bail!("unknown dock '{}', expected docked or mobile", label),
+        }
+    }
+
+    /// Tells the dock from the keystrokes per keyboard of a session.
+    ///
+    /// Only laptops are docked or not: unless a built-in keyboard (see
+    /// [`BUILT_IN_KEYBOARDS`]) was ever logged, there is no answer.
+    /// Keystrokes reported with `ctrlq ping` don't count.
+    ///
+    /// # Arguments
+    /// * `session_devices` - Keystrokes per device label in the session
+    /// * `devices` - Every device label ever logged, see `KeyStats::device_counts`
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::{Dock, REMOTE_DEVICE};
+    /// use std::collections::HashMap;
+    ///
+    /// let laptop = "AT Translated Set 2 keyboard";
+    /// let external = "Keychron K2";
+    /// let devices = [laptop, external, REMOTE_DEVICE].map(String::from);
+    /// let session = |counts: &[(&str, u64)]| counts.iter().map(|&(label, count)| (label.to_string(), count)).collect::<HashMap<_, _>>();
+    ///
+    /// assert_eq!(Dock::of_session(&session(&[(external, 900), (laptop, 40)]), devices.iter()), Some(Dock::Docked));
+    /// assert_eq!(Dock::of_session(&session(&[(laptop, 500), (REMOTE_DEVICE, 800)]), devices.iter()), Some(Dock::Mobile));
+    /// assert_eq!(Dock::of_session(&session(&[(REMOTE_DEVICE, 800)]), devices.iter()), None);
+    /// // A desktop: no built-in keyboard anywhere
+    /// assert_eq!(Dock::of_session(&session(&[(external, 900)]), [external.to_string()].iter()), None);
+    /// ```
+    pub fn of_session<'a>(
+        session_devices: &HashMap<String, u64>,
+        mut devices: impl Iterator<Item = &'a String>,
+    ) -> Option<Self> {
+        if !devices.any(|label| is_built_in_keyboard(label)) {
+            return None;
+        }
+        let (label, _) = session_devices
+            .iter()
+            .filter(|&(label, &count)| label != REMOTE_DEVICE && count > 0)
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))?;
+        Some(if is_built_in_keyboard(label) { Self::Mobile } else { Self::Docked })
+    }
+}
+
+impl std::fmt::Display for Dock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+/// Whether a device label names a keyboard built into a laptop, see
+/// [`BUILT_IN_KEYBOARDS`].
+pub fn is_built_in_keyboard(label: &str) -> bool {
+    let label = label.to_lowercase();
+    BUILT_IN_KEYBOARDS.iter().any(|name| label.contains(&name.to_lowercase()))
+}
+
+/// Information about a single typing session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypingSession {
+    /// Session start time
+    pub start: DateTime<Utc>,
+    /// Session end time
+    pub end: DateTime<Utc>,
+    /// Total keystrokes in session
+    pub keystrokes: u64,
+    /// Calculated words per minute (if available)
+    pub wpm: Option<f64>,
+    /// Words per minute after subtracting corrected characters (if available)
+    #[serde(default)]
+    pub net_wpm: Option<f64>,
+    /// Words typed, counted at word boundaries (see [`BoundaryTracker`])
+    #[serde(default)]
+    pub words: u64,
+    /// Words per minute based on [`TypingSession::words`] (if available)
+    #[serde(default)]
+    pub real_wpm: Option<f64>,
+    /// Backspace presses in session
+    #[serde(default)]
+    pub corrections: u64,
+    /// Most pressed keys in session, most used first
+    #[serde(default)]
+    pub top_keys: Vec<(String, u64)>,
+    /// Keystrokes in each minute of the session
+    #[serde(default)]
+    pub timeline: Vec<u32>,
+    /// Keystrokes per context (project) in session, most used first
+    #[serde(default)]
+    pub contexts: Vec<(String, u64)>,
+    /// Seconds from the session start to its first keystroke (None for
+    /// sessions recorded before this was tracked)
+    #[serde(default)]
+    pub first_key_secs: Option<u64>,
+    /// Character keystrokes in each of the first [`WARMUP_MINUTES`] minutes
+    /// after the first keystroke
+    #[serde(default)]
+    pub warmup: [u32; WARMUP_MINUTES],
+    /// Label given with `:tag`, e.g. `deep-work`, see [`parse_session_label`]
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Free text given with the tag
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Keystrokes per category, see [`keymap::categorize`] (empty for
+    /// sessions recorded before categories were tracked)
+    #[serde(default, skip_serializing_if = "CategoryBreakdown::is_empty")]
+    pub categories: CategoryBreakdown,
+    /// Docked or mobile, from the keyboard the session was mostly typed on;
+    /// None off laptops and for sessions recorded before this was tracked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dock: Option<Dock>,
+}
+
+impl TypingSession {
+    /// Returns the share of keystrokes that were backspaces.
+    ///
+    /// # Returns
+    /// `Option<f64>` - Error rate between 0 and 1, or None for empty sessions
+    pub fn error_rate(&self) -> Option<f64> {
+        (self.keystrokes > 0).then(|| self.corrections as f64 / self.keystrokes as f64)
+    }
+
+    /// Returns how long the session lasted.
+    pub fn duration(&self) -> chrono::Duration {
+        self.end - self.start
+    }
+
+    /// Minutes the session went on after its first keystroke, or None if
+    /// that wasn't tracked.
+    pub fn minutes_after_first_key(&self) -> Option<f64> {
+        let first_key = self.start + chrono::Duration::seconds(self.first_key_secs? as i64);
+        Some(self.end.signed_duration_since(first_key).num_seconds().max(0) as f64 / 60.0)
+    }
+
+    /// Words per minute in the first [`WARMUP_WINDOW`] minutes after the
+    /// first keystroke, to compare with [`TypingSession::wpm`].
+    ///
+    /// # Returns
+    /// `Option<f64>` - The warm-up WPM, or None for shorter sessions and
+    /// sessions recorded before warm-up tracking
+    pub fn warmup_wpm(&self) -> Option<f64> {
+        if self.minutes_after_first_key()? < WARMUP_WINDOW as f64 {
+            return None;
+        }
+        let chars: u32 = self.warmup[..WARMUP_WINDOW].iter().sum();
+        Some(chars as f64 / 5.0 / WARMUP_WINDOW as f64)
+    }
+}
+
+/// How long sessions last, how far apart they are and how many there are a
+/// day, see [`KeyStats::session_patterns`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionPatterns {
+    /// Finished sessions that started in the period
+    pub sessions: usize,
+    /// Average length of those sessions
+    pub average_length: Option<chrono::Duration>,
+    /// Length that nine in ten sessions don't exceed
+    pub p90_length: Option<chrono::Duration>,
+    /// Average pause between two sessions that start on the same day
+    pub average_gap: Option<chrono::Duration>,
+    /// Average sessions per day with keystrokes, from `counted_from` on
+    pub sessions_per_day: Option<f64>,
+    /// First day of the period with session counts; None if sessions have
+    /// never been counted, see [`KeyStats::sessions_counted_since`]
+    pub counted_from: Option<NaiveDate>,
+}
+
+/// Average typing speed by minute since the first keystroke of a session,
+/// see [`KeyStats::warmup_curve`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarmupCurve {
+    /// Average WPM in each minute, None where no session lasted that long
+    pub wpm: Vec<Option<f64>>,
+    /// Sessions the curve is based on
+    pub sessions: usize,
+}
+
+impl WarmupCurve {
+    /// Averages the warm-up of the given sessions, see
+    /// [`KeyStats::warmup_curve`]; e.g. of one day's sessions.
+    ///
+    /// # Returns
+    /// `Option<WarmupCurve>` - The curve, or None if no session with
+    /// warm-up data lasted a full minute
+    pub fn from_sessions<'a>(sessions: impl IntoIterator<Item = &'a TypingSession>) -> Option<WarmupCurve> {
+        let mut sums = [(0.0, 0usize); WARMUP_MINUTES];
+        let mut count = 0;
+        for session in sessions {
+            let Some(minutes) = session.minutes_after_first_key() else {
+                continue;
+            };
+            let complete = (minutes as usize).min(WARMUP_MINUTES);
+            if complete == 0 {
+                continue;
+            }
+            count += 1;
+            for (sum, &chars) in sums.iter_mut().zip(&session.warmup[..complete]) {
+                sum.0 += chars as f64 / 5.0;
+                sum.1 += 1;
+            }
+        }
+        (count > 0).then(|| WarmupCurve {
+            wpm: sums.iter().map(|&(sum, sessions)| (sessions > 0).then(|| sum / sessions as f64)).collect(),
+            sessions: count,
+        })
+    }
+
+    /// The minute from which typing runs at full speed: a three minute
+    /// average of at least 90% of the pace in the second half of the curve.
+    ///
+    /// # Returns
+    /// `Option<usize>` - Minutes after the first keystroke, or None if the
+    /// sessions are too short to tell
+    pub fn full_speed_after(&self) -> Option<usize> {
+        let plateau: Vec<f64> = self.wpm[WARMUP_MINUTES / 2..].iter().flatten().copied().collect();
+        if plateau.len() < WARMUP_WINDOW {
+            return None;
+        }
+        let full_speed = 0.9 * plateau.iter().sum::<f64>() / plateau.len() as f64;
+        (0..self.wpm.len()).find(|&minute| {
+            let window: Vec<f64> = self.wpm[minute..(minute + 3).min(self.wpm.len())].iter().flatten().copied().collect();
+            !window.is_empty() && window.iter().sum::<f64>() / window.len() as f64 >= full_speed
+        })
+    }
+}
+
+/// Keystrokes in one bucket of the day timeline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimelineBucket {
+    /// Keystrokes in the bucket
+    pub keystrokes: u32,
+    /// Keystrokes per key in the bucket
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub keys: HashMap<String, u32>,
+}
+
+/// Today's keystrokes in [`TIMELINE_BUCKET_MINUTES`]-minute buckets, local time.
+///
+/// Only the current day is kept; the first keystroke of a new day starts
+/// a fresh timeline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DayTimeline {
+    /// Local date the buckets belong to (`YYYY-MM-DD`)
+    pub date: String,
+    /// Buckets from midnight onwards; trailing empty buckets are omitted
+    pub buckets: Vec<TimelineBucket>,
+}
+
+impl DayTimeline {
+    /// Number of buckets in a full day.
+    pub const BUCKETS_PER_DAY: usize = (24 * 60 / TIMELINE_BUCKET_MINUTES) as usize;
+
+    /// Records a keystroke at the given local time, starting over on a new day.
+    pub fn add(&mut self, key: &str, at: DateTime<chrono::Local>) {
+        let date = at.format("%Y-%m-%d").to_string();
+        if self.date != date {
+            self.date = date;
+            self.buckets.clear();
+        }
+
+        let index = Self::bucket_index(at);
+        if self.buckets.len() <= index {
+            self.buckets.resize_with(index + 1, TimelineBucket::default);
+        }
+        let bucket = &mut self.buckets[index];
+        bucket.keystrokes += 1;
+        *bucket.keys.entry(key.to_string()).or_insert(0) += 1;
+    }
+
+    /// Returns the bucket a local time falls into.
+    pub fn bucket_index(at: DateTime<chrono::Local>) -> usize {
+        use chrono::Timelike;
+        ((at.hour() * 60 + at.minute()) / TIMELINE_BUCKET_MINUTES) as usize
+    }
+
+    /// Returns today's buckets from midnight up to the current bucket.
+    ///
+    /// # Arguments
+    /// * `now` - Current local time
+    ///
+    /// # Returns
+    /// `Vec<TimelineBucket>` - Buckets for today, empty ones included; all
+    /// empty if the timeline belongs to another day
+    pub fn today(&self, now: DateTime<chrono::Local>) -> Vec<TimelineBucket> {
+        let len = Self::bucket_index(now) + 1;
+        let mut buckets = if self.date == now.format("%Y-%m-%d").to_string() {
+            self.buckets.clone()
+        } else {
+            Vec::new()
+        };
+        buckets.resize_with(len.max(buckets.len()), TimelineBucket::default);
+        buckets
+    }
+}
+
+/// Keystrokes of the last [`RECENT_KEYS_WINDOW_SECS`] seconds with a
+/// running count per key, for the "last minute" ticker.
+///
+/// Keys that fall out of the window are evicted from the front of the
+/// buffer when a key is pushed or [`RecentKeys::evict`] is called, so
+/// neither looks at more than the expired keys.
+///
+/// # Example
+/// ```
+/// use chrono::{Duration, TimeZone, Utc};
+/// use ctrlq::keylogger::RecentKeys;
+///
+/// let start = Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+/// let mut recent = RecentKeys::default();
+/// for i in 0..30 {
+///     recent.push("KEY_103", start + Duration::seconds(i));
+/// }
+/// for i in 0..10 {
+///     recent.push("KEY_18", start + Duration::seconds(40 + i));
+/// }
+/// assert_eq!(recent.top(3), [("KEY_103", 30), ("KEY_18", 10)]);
+///
+/// // 75 seconds in, the arrow keys up to 15 seconds have expired
+/// recent.evict(start + Duration::seconds(75));
+/// assert_eq!(recent.top(3), [("KEY_103", 14), ("KEY_18", 10)]);
+/// assert_eq!(recent.len(), 24);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RecentKeys {
+    /// Keys in the order they were pressed, oldest first
+    entries: VecDeque<(DateTime<Utc>, String)>,
+    /// Keystrokes per key in `entries`
+    counts: HashMap<String, u32>,
+}
+
+impl RecentKeys {
+    /// Records a keystroke and evicts the ones that fell out of the window.
+    pub fn push(&mut self, key: &str, at: DateTime<Utc>) {
+        self.evict(at);
+        self.entries.push_back((at, key.to_string()));
+        *self.counts.entry(key.to_string()).or_insert(0) += 1;
+    }
+
+    /// Drops the keystrokes older than the window ending at `now`.
+    pub fn evict(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - chrono::Duration::seconds(RECENT_KEYS_WINDOW_SECS);
+        while self.entries.front().is_some_and(|(at, _)| *at <= cutoff) {
+            let Some((_, key)) = self.entries.pop_front() else {
+                break;
+            };
+            if let Some(count) = self.counts.get_mut(&key) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Number of keystrokes in the window as of the last eviction.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no keystrokes are in the window as of the last eviction.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the most pressed keys in the window, most pressed first.
+    ///
+    /// Call [`RecentKeys::evict`] first to drop keys that expired since the
+    /// last keystroke.
+    ///
+    /// # Arguments
+    /// * `n` - Number of keys to return
+    ///
+    /// # Returns
+    /// `Vec<(&str, u32)>` - Stored key names and their keystrokes
+    pub fn top(&self, n: usize) -> Vec<(&str, u32)> {
+        let mut keys: Vec<(&str, u32)> = self.counts.iter().map(|(key, &count)| (key.as_str(), count)).collect();
+        keys.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        keys.truncate(n);
+        keys
+    }
+}
+
+/// Keystrokes in each minute of one day, local time.
+///
+/// Only minutes with keystrokes are kept. In the data file the minutes are
+/// written as one string of `gap:count` pairs, where the gap is the number
+/// of minutes since the previous active minute (since midnight for the
+/// first one) and a count of 1 is left out, so an active day costs a few
+/// bytes per typed minute.
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::MinuteActivity;
+///
+/// let mut minutes = MinuteActivity::default();
+/// minutes.add(9 * 60, 12);
+/// minutes.add(9 * 60 + 1, 1);
+/// minutes.add(9 * 60 + 5, 7);
+///
+/// let encoded = minutes.encode();
+/// assert_eq!(encoded, "540:12,1,4:7");
+/// assert_eq!(MinuteActivity::decode(&encoded).unwrap(), minutes);
+/// assert_eq!(minutes.hourly()[9], 20);
+/// assert_eq!(minutes.buckets(5)[108..], [13, 7]);
+///
+/// // Garbage is rejected instead of silently dropping minutes
+/// assert!(MinuteActivity::decode("540:12,x").is_err());
+/// assert!(MinuteActivity::decode("1500").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MinuteActivity {
+    counts: BTreeMap<u16, u32>,
+}
+
+impl MinuteActivity {
+    /// Minutes in a day.
+    pub const MINUTES_PER_DAY: u16 = 24 * 60;
+
+    /// Adds keystrokes to a minute of the day (0-1439).
+    pub fn add(&mut self, minute: u16, count: u32) {
+        if count > 0 && minute < Self::MINUTES_PER_DAY {
+            *self.counts.entry(minute).or_insert(0) += count;
+        }
+    }
+
+    /// Keystrokes in a minute of the day (0-1439).
+    pub fn get(&self, minute: u16) -> u32 {
+        self.counts.get(&minute).copied().unwrap_or(0)
+    }
+
+    /// Adds the keystrokes of another day, e.g. when merging data files.
+    pub fn merge(&mut self, other: &MinuteActivity) {
+        for (&minute, &count) in &other.counts {
+            self.add(minute, count);
+        }
+    }
+
+    /// Whether no keystrokes were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Returns the active minutes and their keystrokes, earliest first.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, u32)> + '_ {
+        self.counts.iter().map(|(&minute, &count)| (minute, count))
+    }
+
+    /// Keystrokes in each hour, index 0-23; what the minutes are
+    /// downsampled to.
+    pub fn hourly(&self) -> Vec<u64> {
+        let mut hours = vec![0; 24];
+        for (minute, count) in self.iter() {
+            hours[(minute / 60) as usize] += count as u64;
+        }
+        hours
+    }
+
+    /// Sums the minutes into buckets of the given length from midnight.
+    ///
+    /// # Arguments
+    /// * `minutes` - Minutes per bucket
+    ///
+    /// # Returns
+    /// `Vec<u32>` - Keystrokes per bucket up to the last active one
+    pub fn buckets(&self, minutes: u32) -> Vec<u32> {
+        let minutes = minutes.max(1);
+        let mut buckets = Vec::new();
+        for (minute, count) in self.iter() {
+            let index = (minute as u32 / minutes) as usize;
+            if buckets.len() <= index {
+                buckets.resize(index + 1, 0);
+            }
+            buckets[index] += count;
+        }
+        buckets
+    }
+
+    /// Encodes the minutes as written to the data file.
+    pub fn encode(&self) -> String {
+        let mut previous = 0;
+        let mut pairs = Vec::with_capacity(self.counts.len());
+        for (minute, count) in self.iter() {
+            let gap = minute - previous;
+            previous = minute;
+            pairs.push(if count == 1 { gap.to_string() } else { format!("{}:{}", gap, count) });
+        }
+        pairs.join(",")
+    }
+
+    /// Decodes minutes written by [`MinuteActivity::encode`].
+    ///
+    /// # Returns
+    /// `Result<MinuteActivity>` - The minutes, or an error for malformed
+    /// pairs and minutes past the end of the day
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let mut activity = Self::default();
+        let mut minute: u16 = 0;
+        for pair in encoded.split(',').filter(|pair| !pair.is_empty()) {
+            let (gap, count) = pair.split_once(':').unwrap_or((pair, "1"));
+            let gap: u16 = gap.parse().with_context(|| format!("invalid minute gap in {:?}", pair))?;
+            let count: u32 = count.parse().with_context(|| format!("invalid keystroke count in {:?}", pair))?;
+            minute = minute
+                .checked_add(gap)
+                .filter(|&minute| minute < Self::MINUTES_PER_DAY)
+                .with_context(|| format!("minute past the end of the day in {:?}", pair))?;
+            activity.add(minute, count);
+        }
+        Ok(activity)
+    }
+}
+
+impl TryFrom<String> for MinuteActivity {
+    type Error = anyhow::Error;
+
+    fn try_from(encoded: String) -> Result<Self> {
+        Self::decode(&encoded)
+    }
+}
+
+impl From<MinuteActivity> for String {
+    fn from(activity: MinuteActivity) -> String {
+        activity.encode()
+    }
+}
+
+/// Statistics aggregated by day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DayStats {
+    /// Total keystrokes for the day
+    pub keystrokes: u64,
+    /// Number of typing sessions
+    pub sessions: u64,
+    /// Hour of day with most activity (0-23)
+    pub most_active_hour: Option<u8>,
+    /// Key usage distribution for the day
+    pub key_distribution: HashMap<String, u64>,
+    /// Keystrokes per keyboard for the day, keyed by device label
+    #[serde(default)]
+    pub device_keystrokes: HashMap<String, u64>,
+    /// Keystrokes per context (project) for the day
+    #[serde(default)]
+    pub context_keystrokes: HashMap<String, u64>,
+    /// Characters typed for the day on the active XKB layout, see [`crate::xkb`]
+    #[serde(default)]
+    pub char_counts: HashMap<String, u64>,
+    /// Pairs of character keys typed in a row for the day, see [`KeyStats::bigram_counts`]
+    #[serde(default)]
+    pub bigram_counts: HashMap<String, u64>,
+    /// Words typed, counted at word boundaries (see [`BoundaryTracker`])
+    #[serde(default)]
+    pub words: u64,
+    /// Lines typed: Enter after typing, outside of menu navigation
+    #[serde(default)]
+    pub lines: u64,
+    /// Keystrokes in each hour of the day (local time), index 0-23
+    #[serde(default)]
+    pub hourly_keystrokes: Vec<u64>,
+    /// Character keystrokes in each hour of the day, for the speed by hour
+    #[serde(default)]
+    pub hourly_chars: Vec<u64>,
+    /// Milliseconds of typing in each hour of the day: pauses between
+    /// keystrokes up to [`ACTIVE_TYPING_GAP`]
+    #[serde(default)]
+    pub hourly_active_ms: Vec<u64>,
+    /// Keystrokes in each minute of the day (local time); dropped by
+    /// `ctrlq compact` once the day is older than `minute_days`
+    #[serde(default, skip_serializing_if = "MinuteActivity::is_empty")]
+    pub minute_keystrokes: MinuteActivity,
+    /// Shortcuts pressed for the day by action name, see [`crate::actions`]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub actions: HashMap<String, u64>,
+    /// Breaks suggested by the break reminder, see [`BreakReminder`]
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub breaks_suggested: u64,
+    /// Suggested breaks that were taken
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub breaks_taken: u64,
+    /// Characters typed, estimated by [`DeletionTracker`]
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub chars_typed: u64,
+    /// Characters deleted with Backspace and Delete, estimated the same way
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub chars_deleted: u64,
+    /// Milliseconds Caps Lock was on while counting, see [`LockTimer`]
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub caps_lock_ms: u64,
+    /// Letters typed with Caps Lock on
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub caps_lock_letters: u64,
+    /// Runs of letters typed with Caps Lock on, see [`ModifierState::caps_letter`]
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub caps_lock_runs: u64,
+    /// Seconds the logger ran and counted, summed over every run of the
+    /// day; 0 for days from before it was tracked, see [`DayStats::coverage`]
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub coverage_seconds: u64,
+}
+
+/// Share of a day below which it is flagged as low coverage.
+pub const LOW_COVERAGE: f64 = 0.25;
+
+/// How long the logger ran on a day, see [`DayStats::coverage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Coverage {
+    /// Seconds the logger ran and counted
+    pub ran_secs: u64,
+    /// Seconds it could have: the day or its logging hours, up to now
+    pub expected_secs: u64,
+}
+
+impl Coverage {
+    /// Share of the expected time the logger ran, at most 1 (merged data
+    /// files can add up to more).
+    pub fn share(&self) -> f64 {
+        (self.ran_secs as f64 / self.expected_secs.max(1) as f64).min(1.0)
+    }
+
+    /// Whether the logger ran for less than [`LOW_COVERAGE`] of the day, so
+    /// its keystrokes say little about how much was typed.
+    pub fn is_low(&self) -> bool {
+        self.share() < LOW_COVERAGE
+    }
+}
+
+/// Today's typing so far compared with a typical day of the same weekday
+/// at the same time of day, see [`KeyStats::pace_trend`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaceTrend {
+    /// Keystrokes today up to now
+    pub so_far: u64,
+    /// Keystrokes usually typed by this time on this weekday
+    pub typical: f64,
+    /// Past days of this weekday the typical value is based on
+    pub days: usize,
+}
+
+impl PaceTrend {
+    /// Relative difference to the typical pace (0.18 = 18% ahead).
+    pub fn change(&self) -> f64 {
+        self.so_far as f64 / self.typical - 1.0
+    }
+}
+
+/// Statistics aggregated over a range of days, derived from `daily_stats`.
+#[derive(Debug, Clone)]
+pub struct PeriodSummary {
+    /// Human-readable name of the period (e.g. "2024-05-01" or "this week")
+    pub label: String,
+    /// First day of the period (inclusive)
+    pub start: NaiveDate,
+    /// Last day of the period (inclusive)
+    pub end: NaiveDate,
+    /// Days in the range without any recorded data
+    pub missing_days: Vec<NaiveDate>,
+    /// Total keystrokes in the period
+    pub keystrokes: u64,
+    /// Average WPM of sessions started in the period
+    pub wpm: Option<f64>,
+    /// Share of keystrokes that were backspaces
+    pub error_rate: Option<f64>,
+    /// Combined key distribution of the period
+    pub key_distribution: HashMap<String, u64>,
+    /// Gross WPM in each hour of the day, see [`speed_by_hour`]
+    pub speed_by_hour: Vec<Option<f64>>,
+    /// Shortcuts pressed in the period by action name
+    pub actions: HashMap<String, u64>,
+}
+
+impl PeriodSummary {
+    /// Number of days covered by the period.
+    pub fn day_count(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+
+    /// Share of the period's keystrokes that went to `key` (0.0-1.0).
+    pub fn share(&self, key: &str) -> f64 {
+        if self.keystrokes == 0 {
+            return 0.0;
+        }
+        self.key_distribution.get(key).copied().unwrap_or(0) as f64 / self.keystrokes as f64
+    }
+}
+
+/// One calendar month aggregated from `daily_stats`, see
+/// [`KeyStats::month_stats`]. Derived on demand and never saved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonthStats {
+    /// First day of the month
+    pub month: NaiveDate,
+    /// Days of the month up to today: all of them for a past month
+    pub days: u32,
+    /// Whether the month is still running, so its totals are not final
+    pub partial: bool,
+    /// Total keystrokes in the month
+    pub keystrokes: u64,
+    /// Days with at least one keystroke
+    pub active_days: u32,
+    /// Most pressed keys of the month, most pressed first
+    pub top_keys: Vec<(String, u64)>,
+    /// Average WPM of the sessions started in the month
+    pub wpm: Option<f64>,
+}
+
+impl MonthStats {
+    /// Returns the month as `YYYY-MM`.
+    pub fn label(&self) -> String {
+        self.month.format("%Y-%m").to_string()
+    }
+
+    /// Average keystrokes per day typed on.
+    pub fn per_active_day(&self) -> Option<f64> {
+        (self.active_days > 0).then(|| self.keystrokes as f64 / self.active_days as f64)
+    }
+
+    /// Average keystrokes per calendar day so far.
+    pub fn per_day(&self) -> Option<f64> {
+        (self.days > 0).then(|| self.keystrokes as f64 / self.days as f64)
+    }
+
+    /// Relative change from the previous month (0.12 = 12% more).
+    ///
+    /// Keystrokes per calendar day are compared rather than totals, so a
+    /// running month and months of different lengths compare fairly.
+    ///
+    /// # Returns
+    /// `Option<f64>` - The change, or None if the previous month had no
+    /// keystrokes
+    pub fn change(&self, previous: &MonthStats) -> Option<f64> {
+        let before = previous.per_day().filter(|&per_day| per_day > 0.0)?;
+        Some(self.per_day()? / before - 1.0)
+    }
+}
+
+/// How one key's share of keystrokes changed between two periods.
+#[derive(Debug, Clone)]
+pub struct KeyChange {
+    /// Stored key name (`KEY_<code>`)
+    pub key: String,
+    /// Share of keystrokes in the first period (0.0-1.0)
+    pub share_a: f64,
+    /// Share of keystrokes in the second period (0.0-1.0)
+    pub share_b: f64,
+}
+
+impl KeyChange {
+    /// Change in share from the first to the second period, in percentage points.
+    pub fn delta_points(&self) -> f64 {
+        (self.share_b - self.share_a) * 100.0
+    }
+}
+
+/// Side-by-side comparison of two periods.
+///
+/// Deltas are always "second minus first", so the first period acts as
+/// the baseline.
+#[derive(Debug, Clone)]
+pub struct DayComparison {
+    /// Baseline period
+    pub a: PeriodSummary,
+    /// Period compared against the baseline
+    pub b: PeriodSummary,
+    /// Share changes for the top 5 keys of the second period
+    pub top_keys: Vec<KeyChange>,
+    /// Keys whose share changed the most, in either direction
+    pub biggest_movers: Vec<KeyChange>,
+}
+
+impl DayComparison {
+    /// Change in total keystrokes.
+    pub fn keystroke_delta(&self) -> i64 {
+        self.b.keystrokes as i64 - self.a.keystrokes as i64
+    }
+
+    /// Change in average WPM, if both periods have one.
+    pub fn wpm_delta(&self) -> Option<f64> {
+        Some(self.b.wpm? - self.a.wpm?)
+    }
+
+    /// Change in error rate in percentage points, if both periods have one.
+    pub fn error_rate_delta(&self) -> Option<f64> {
+        Some((self.b.error_rate? - self.a.error_rate?) * 100.0)
+    }
+}
+
+impl DayStats {
+    /// Share of the typed characters that were deleted again, in percent.
+    ///
+    /// # Returns
+    /// `Option<f64>` - Over 100 when older text was deleted, None before
+    /// anything was typed
+    pub fn deleted_percent(&self) -> Option<f64> {
+        report::percent(self.chars_deleted, self.chars_typed)
+    }
+
+    /// Whole minutes Caps Lock was on that day.
+    pub fn caps_lock_minutes(&self) -> u64 {
+        self.caps_lock_ms / 60_000
+    }
+
+    /// How long the logger ran on a day against how long it could have:
+    /// the whole day, or its `log_hours`, and for today only the part
+    /// that has passed.
+    ///
+    /// # Arguments
+    /// * `date` - The day these statistics belong to
+    /// * `log_hours` - Logging hours from the config, if any
+    /// * `now` - Current local time
+    ///
+    /// # Returns
+    /// `Option<Coverage>` - None for days from before uptime was tracked
+    /// and days without logging hours
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use ctrlq::keylogger::DayStats;
+    /// use ctrlq::schedule::Schedule;
+    ///
+    /// let day = DayStats { coverage_seconds: 6 * 3600, ..DayStats::default() };
+    /// let (date, later) = (NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 4).unwrap());
+    /// let now = later.and_hms_opt(12, 0, 0).unwrap();
+    ///
+    /// // A quarter of the day is not low yet; a quarter of 09:00-12:00 is
+    /// let all_day = day.coverage(date, None, now).unwrap();
+    /// assert_eq!((all_day.share(), all_day.is_low()), (0.25, false));
+    /// let short = DayStats { coverage_seconds: 2000, ..DayStats::default() };
+    /// assert!(short.coverage(date, Some(&Schedule::parse("09:00-12:00")?), now).unwrap().is_low());
+    /// // Today only counts until now
+    /// assert_eq!(day.coverage(later, None, now).unwrap().share(), 0.5);
+    /// assert!(DayStats::default().coverage(date, None, now).is_none());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn coverage(&self, date: NaiveDate, log_hours: Option<&Schedule>, now: NaiveDateTime) -> Option<Coverage> {
+        if self.coverage_seconds == 0 {
+            return None;
+        }
+        let expected = match log_hours {
+            Some(schedule) => schedule.logged_seconds(date, now),
+            None => Schedule::all_day_seconds(date, now),
+        };
+        (expected > 0).then_some(Coverage { ran_secs: self.coverage_seconds, expected_secs: expected })
+    }
+
+    /// Shannon entropy of the day's key distribution in bits.
+    ///
+    /// # Returns
+    /// `Option<f64>` - Entropy, or None if no keys were recorded that day
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::DayStats;
+    ///
+    /// let mut day = DayStats::default();
+    /// assert_eq!(day.entropy(), None);
+    /// day.key_distribution.insert("KEY_30".to_string(), 2);
+    /// day.key_distribution.insert("KEY_31".to_string(), 1);
+    /// day.key_distribution.insert("KEY_32".to_string(), 1);
+    /// assert_eq!(day.entropy(), Some(1.5));
+    /// ```
+    pub fn entropy(&self) -> Option<f64> {
+        entropy(self.key_distribution.values().copied())
+    }
+
+    /// Keystrokes of the day per category, from its key distribution.
+    pub fn category_breakdown(&self) -> CategoryBreakdown {
+        CategoryBreakdown::of(&self.key_distribution)
+    }
+
+    /// Hour with the most keystrokes, the earliest one on ties.
+    ///
+    /// # Returns
+    /// `Option<u8>` - Local hour (0-23), or None without hourly data
+    pub fn busiest_hour(&self) -> Option<u8> {
+        self.hourly_keystrokes
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(&a.0)))
+            .map(|(hour, _)| hour as u8)
+    }
+
+    /// Drops the day's minute data, keeping only the hourly counts.
+    ///
+    /// # Returns
+    /// `bool` - Whether there was minute data to drop
+    pub fn downsample_minutes(&mut self) -> bool {
+        if self.minute_keystrokes.is_empty() {
+            return false;
+        }
+        if self.hourly_keystrokes.iter().sum::<u64>() == 0 {
+            self.hourly_keystrokes = self.minute_keystrokes.hourly();
+        }
+        self.minute_keystrokes = MinuteActivity::default();
+        true
+    }
+}
+
+/// Adds to one hour of a per-hour list, growing it as needed.
+fn add_to_hour(hours: &mut Vec<u64>, hour: usize, count: u64) {
+    if hours.len() <= hour {
+        hours.resize(hour + 1, 0);
+    }
+    hours[hour] += count;
+}
+
+/// Typing speed in each hour of the day, averaged over several days.
+///
+/// Hours of a day with less than [`SPEED_MIN_ACTIVE_SECS`] of typing are
+/// left out, so a few quick keystrokes don't show up as extreme speeds. The
+/// remaining days are weighted by their typing time.
+///
+/// # Arguments
+/// * `days` - The days to average
+///
+/// # Returns
+/// `Vec<Option<f64>>` - Gross WPM for each local hour 0-23, None for hours
+/// without enough typing
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::{speed_by_hour, DayStats};
+///
+/// let mut monday = DayStats::default();
+/// monday.hourly_chars = vec![0; 24];
+/// monday.hourly_active_ms = vec![0; 24];
+/// // 9:00: 600 characters in two minutes of typing is 60 WPM
+/// (monday.hourly_chars[9], monday.hourly_active_ms[9]) = (600, 120_000);
+/// // 14:00: 20 characters in five seconds would be 48 WPM, but proves nothing
+/// (monday.hourly_chars[14], monday.hourly_active_ms[14]) = (20, 5_000);
+///
+/// let mut tuesday = monday.clone();
+/// // 9:00: 1000 characters in eight minutes is 25 WPM
+/// (tuesday.hourly_chars[9], tuesday.hourly_active_ms[9]) = (1000, 480_000);
+///
+/// let speed = speed_by_hour([&monday, &tuesday]);
+/// assert_eq!(speed.len(), 24);
+/// // 1600 characters in ten minutes
+/// assert_eq!(speed[9], Some(32.0));
+/// assert_eq!(speed[14], None);
+/// ```
+pub fn speed_by_hour<'a>(days: impl IntoIterator<Item = &'a DayStats>) -> Vec<Option<f64>> {
+    let mut totals = [(0u64, 0u64); 24];
+    for day in days {
+        for (hour, &active_ms) in day.hourly_active_ms.iter().enumerate().take(24) {
+            if active_ms >= SPEED_MIN_ACTIVE_SECS * 1000 {
+                totals[hour].0 += day.hourly_chars.get(hour).copied().unwrap_or(0);
+                totals[hour].1 += active_ms;
+            }
+        }
+    }
+    totals
+        .iter()
+        .map(|&(chars, active_ms)| (active_ms > 0).then(|| chars as f64 / 5.0 / (active_ms as f64 / 60_000.0)))
+        .collect()
+}
+
+/// Returns the current local date, the day statistics are filed under.
+pub fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+/// Returns the `daily_stats` key of a day.
+///
+/// # Arguments
+/// * `date` - Local date
+///
+/// # Returns
+/// `String` - The date as `YYYY-MM-DD`
+pub fn day_key(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// Leaves zero counters out of the data file.
+fn is_zero(count: &u64) -> bool {
+    *count == 0
+}
+
+/// Builds stable, human-readable labels for a set of input devices.
+///
+/// Devices are labelled by their name rather than their event path, which
+/// changes between boots. When several attached devices share a name, each
+/// gets a short hash of its physical path appended, e.g. `USB Keyboard (3fa2)`.
+///
+/// # Arguments
+/// * `devices` - (name, physical path) of each device, in order
+///
+/// # Returns
+/// `Vec<String>` - One label per device, in the same order
+pub fn device_labels(devices: &[(String, Option<String>)]) -> Vec<String> {
+    devices
+        .iter()
+        .map(|(name, phys)| {
+            let duplicate = devices.iter().filter(|(other, _)| other == name).count() > 1;
+            match phys {
+                Some(phys) if duplicate => format!("{} ({:04x})", name, short_hash(phys)),
+                _ => name.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Cleans up a device name as reported by the kernel, which may hold
+/// invalid UTF-8 or control characters.
+///
+/// Invalid bytes become `�`, control characters become spaces and runs of
+/// whitespace collapse into one space. Labels, scoring and any matching on
+/// device names use this form.
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::sanitize_device_name;
+///
+/// assert_eq!(sanitize_device_name(b"USB\tKeyboard \n"), "USB Keyboard");
+/// // Invalid UTF-8 and an escape sequence that would recolor the terminal
+/// assert_eq!(sanitize_device_name(b"Bad\xff\xfeName\x1b[31m"), "Bad\u{FFFD}\u{FFFD}Name [31m");
+/// assert_eq!(sanitize_device_name(b"\x00\x01"), "");
+/// ```
+pub fn sanitize_device_name(raw: &[u8]) -> String {
+    String::from_utf8_lossy(raw)
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Shortens a name for display, ending it with "…" when it was cut.
+///
+/// # Arguments
+/// * `name` - The name, usually from [`sanitize_device_name`]
+/// * `width` - Maximum length in characters (0 = never cut)
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::truncate_name;
+///
+/// assert_eq!(truncate_name("Keychron K2", 20), "Keychron K2");
+/// assert_eq!(truncate_name(&"Very Long Name ".repeat(20), 12), "Very Long N…");
+/// assert_eq!(truncate_name("Ünïcödé Keyboard", 8), "Ünïcödé…");
+/// assert_eq!(truncate_name("Keychron K2", 0), "Keychron K2");
+/// ```
+pub fn truncate_name(name: &str, width: usize) -> String {
+    if width == 0 || name.chars().count() <= width {
+        return name.to_string();
+    }
+    let mut cut: String = name.chars().take(width - 1).collect();
+    cut.truncate(cut.trim_end().len());
+    cut.push('…');
+    cut
+}
+
+/// Reads the raw name of an event device.
+///
+/// evdev hands out names already converted to UTF-8, so the bytes come from
+/// sysfs when it is available.
+fn raw_device_name(path: &str, device: &Device) -> Option<Vec<u8>> {
+    let sysfs = std::fs::canonicalize(path).ok().and_then(|resolved| {
+        let event = resolved.file_name()?.to_os_string();
+        std::fs::read(Path::new("/sys/class/input").join(event).join("device/name")).ok()
+    });
+    match sysfs {
+        Some(mut raw) => {
+            if raw.last() == Some(&b'\n') {
+                raw.pop();
+            }
+            Some(raw)
+        }
+        None => device.name().map(|name| name.as_bytes().to_vec()),
+    }
+}
+
+/// 16-bit FNV-1a style hash, stable across runs and Rust versions.
+fn short_hash(text: &str) -> u16 {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    (hash ^ (hash >> 16) ^ (hash >> 32) ^ (hash >> 48)) as u16
+}
+
+/// Computes the Shannon entropy (in bits) of a distribution of counts.
+///
+/// Zero counts are ignored. A distribution concentrated on a single key has
+/// an entropy of 0; `n` equally used keys give `log2(n)`.
+///
+/// # Arguments
+/// * `counts` - Occurrence count of each key
+///
+/// # Returns
+/// `Option<f64>` - Entropy in bits, or None if all counts are zero
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::entropy;
+///
+/// assert_eq!(entropy([7]), Some(0.0));
+/// assert_eq!(entropy([1, 1]), Some(1.0));
+/// assert_eq!(entropy([2, 1, 1]), Some(1.5));
+/// assert_eq!(entropy([4, 0, 4, 0]), Some(1.0));
+/// assert_eq!(entropy([0, 0]), None);
+/// assert_eq!(entropy([]), None);
+/// ```
+pub fn entropy(counts: impl IntoIterator<Item = u64>) -> Option<f64> {
+    let counts: Vec<u64> = counts.into_iter().filter(|c| *c > 0).collect();
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+
+    let total = total as f64;
+    let bits = counts
+        .iter()
+        .map(|&c| {
+            let p = c as f64 / total;
+            -p * p.log2()
+        })
+        .sum::<f64>();
+
+    // Avoid displaying "-0.00" for single-key distributions
+    Some(bits.max(0.0))
+}
+
+/// Splits the input of `:tag` into the tag and an optional note.
+///
+/// The tag is the first word; letters, digits, `-`, `_`, `.` and `/` are
+/// allowed so it can be typed again for `ctrlq report --tag`. The rest of
+/// the input is the note.
+///
+/// # Arguments
+/// * `input` - e.g. `deep-work parser rewrite`
+///
+/// # Returns
+/// `Result<(String, Option<String>)>` - The tag and the note, or an error
+/// describing the problem
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::parse_session_label;
+///
+/// assert_eq!(
+///     parse_session_label("deep-work  parser rewrite ")?,
+///     ("deep-work".to_string(), Some("parser rewrite".to_string()))
+/// );
+/// assert_eq!(parse_session_label("review")?, ("review".to_string(), None));
+/// assert!(parse_session_label("").is_err());
+/// assert!(parse_session_label("deep,work").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_session_label(input: &str) -> Result<(String, Option<String>)> {
+    let input = input.trim();
+    let (tag, note) = match input.split_once(char::is_whitespace) {
+        Some((tag, note)) => (tag, Some(note.trim())),
+        None => (input, None),
+    };
+    if tag.is_empty() {
+        bail!("tag can't be empty");
+    }
+    if tag.chars().count() > MAX_TAG_LEN {
+        bail!("tag is longer than {} characters", MAX_TAG_LEN);
+    }
+    if let Some(c) = tag.chars().find(|c| !c.is_alphanumeric() && !"-_./".contains(*c)) {
+        bail!("tag can't contain '{}': use letters, digits, - _ . or /", c);
+    }
+    if let Some(note) = note {
+        if note.chars().count() > MAX_NOTE_LEN {
+            bail!("note is longer than {} characters", MAX_NOTE_LEN);
+        }
+        if note.chars().any(char::is_control) {
+            bail!("note can't contain control characters");
+        }
+    }
+    Ok((tag.to_string(), note.map(str::to_string)))
+}
+
+/// Orders counts by count, highest first, and by name for equal counts.
+fn sorted_counts(counts: &HashMap<String, u64>) -> Vec<(String, u64)> {
+    let mut sorted: Vec<(String, u64)> = counts
+        .iter()
+        .map(|(name, &count)| (name.clone(), count))
+        .collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted
+}
+
+/// Checks that a context name can be stored and displayed.
+///
+/// # Arguments
+/// * `name` - Context name as sent by `ctrlq context`
+///
+/// # Returns
+/// `Result<()>` - Ok, or an error describing the problem
+pub fn validate_context(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        bail!("context name can't be empty");
+    }
+    if name.chars().count() > MAX_CONTEXT_LEN {
+        bail!("context name is longer than {} characters", MAX_CONTEXT_LEN);
+    }
+    if name.chars().any(char::is_control) {
+        bail!("context name can't contain control characters");
+    }
+    Ok(())
+}
+
+/// Checks the number of keystrokes reported by `ctrlq ping`.
+///
+/// # Arguments
+/// * `count` - Keystrokes to count, between 1 and [`MAX_REMOTE_BATCH`]
+///
+/// # Returns
+/// `Result<()>` - Ok, or an error describing the problem
+pub fn validate_remote_count(count: u32) -> Result<()> {
+    if count == 0 {
+        bail!("the keystroke count must be at least 1");
+    }
+    if count > MAX_REMOTE_BATCH {
+        bail!("at most {} keystrokes can be counted at once", MAX_REMOTE_BATCH);
+    }
+    Ok(())
+}
+
+/// Picks the most pressed keys from a key distribution.
+///
+/// # Arguments
+/// * `counts` - Keystrokes per stored key name
+/// * `limit` - Maximum number of keys to return
+/// * `class` - Class to restrict to, or None for all keys
+///
+/// # Returns
+/// `Vec<(String, u64)>` - List of (key, count) pairs, highest count first,
+/// equal counts by key name
+pub fn top_keys(counts: &HashMap<String, u64>, limit: usize, class: Option<KeyClass>) -> Vec<(String, u64)> {
+    let mut sorted: Vec<_> = counts
+        .iter()
+        .filter(|(k, _)| class.is_none_or(|c| keymap::classify_name(k) == c))
+        .collect();
+    // Ties in key name order, so the list doesn't reshuffle between runs
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    sorted.into_iter()
+        .take(limit)
+        .map(|(k, v)| (k.clone(), *v))
+        .collect()
+}
+
+/// Adds up key counts by physical key, shifted variants included.
+fn physical_counts(key_counts: &HashMap<String, u64>) -> HashMap<u16, u64> {
+    let mut counts = HashMap::new();
+    for (key, &count) in key_counts {
+        if let Some(code) = keymap::parse_code(key) {
+            *counts.entry(code).or_insert(0) += count;
+        }
+    }
+    counts
+}
+
+/// Moves the counts of junk key names to [`keymap::UNKNOWN_KEY`], adding
+/// the names to `folded`.
+fn fold_junk<T: Copy + Default + std::ops::AddAssign>(counts: &mut HashMap<String, T>, folded: &mut HashSet<String>) {
+    let junk: Vec<String> = counts.keys().filter(|key| keymap::is_junk_name(key)).cloned().collect();
+    for key in junk {
+        if let Some(count) = counts.remove(&key) {
+            *counts.entry(keymap::UNKNOWN_KEY.to_string()).or_default() += count;
+        }
+        folded.insert(key);
+    }
+}
+
+/// A value derived from the statistics, such as a sorted list of top keys,
+/// kept until what it was derived from changes.
+///
+/// The key is [`KeyStats::revision`] together with the view settings the
+/// value depends on. [`Derived::get`] only computes the value again when
+/// the key differs from the one it was computed for, so a UI that redraws
+/// many times per snapshot sorts once per change rather than once per draw.
+///
+/// # Example
+/// A simulated minute of typing: the logger sends 20 snapshots a second,
+/// a quarter of them with changes, and the UI draws after each snapshot
+/// and on 4 ticks in between:
+/// ```
+/// use ctrlq::keylogger::{top_keys, Derived, KeyStats};
+/// use std::cell::Cell;
+///
+/// let mut stats = KeyStats::new();
+/// for code in 0..200 {
+///     stats.key_counts.insert(format!("KEY_{}", code), code);
+/// }
+/// let sorts = Cell::new(0);
+/// let sorted = |stats: &KeyStats| {
+///     sorts.set(sorts.get() + 1);
+///     top_keys(&stats.key_counts, 20, None)
+/// };
+///
+/// let (mut uncached, mut cache) = (0, Derived::default());
+/// for snapshot in 0..1200 {
+///     if snapshot % 4 == 0 {
+///         stats.revision += 1;
+///         *stats.key_counts.get_mut("KEY_7").unwrap() += 1;
+///     }
+///     for _ in 0..5 {
+///         // Before: every draw sorted the keys
+///         uncached += sorted(&stats).len();
+///         // After: a draw sorts only for a new revision
+///         assert_eq!(cache.get(stats.revision, || sorted(&stats)).len(), 20);
+///     }
+/// }
+///
+/// assert_eq!(uncached, 6000 * 20);
+/// assert_eq!(cache.computations(), 300);
+/// assert_eq!(sorts.get(), 6000 + 300);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Derived<K, V> {
+    /// The key and the value computed for it
+    cached: Option<(K, V)>,
+    /// How often a value was computed
+    computations: u64,
+}
+
+impl<K, V> Default for Derived<K, V> {
+    fn default() -> Self {
+        Self { cached: None, computations: 0 }
+    }
+}
+
+impl<K: PartialEq, V> Derived<K, V> {
+    /// Returns the value for `key`, computing it only if the cached one
+    /// belongs to another key.
+    ///
+    /// # Arguments
+    /// * `key` - What the value depends on, starting with the revision
+    /// * `compute` - Derives the value
+    pub fn get(&mut self, key: K, compute: impl FnOnce() -> V) -> &V {
+        if self.cached.as_ref().is_none_or(|(cached, _)| *cached != key) {
+            self.computations += 1;
+            self.cached = Some((key, compute()));
+        }
+        &self.cached.as_ref().expect("computed above").1
+    }
+
+    /// Returns the cached value, whatever key it was computed for.
+    pub fn value(&self) -> Option<&V> {
+        self.cached.as_ref().map(|(_, value)| value)
+    }
+
+    /// How often a value was computed, for measuring the cache.
+    pub fn computations(&self) -> u64 {
+        self.computations
+    }
+}
+
+/// How a key's share of all keystrokes changed from one period to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShareChange {
+    /// The key wasn't pressed in the previous period
+    New,
+    /// Relative change of the share: 0.25 when it grew by a quarter
+    Change(f64),
+}
+
+/// Compares each key's share of all keystrokes with a previous period.
+///
+/// # Arguments
+/// * `current` - Keystrokes per key in the period shown
+/// * `previous` - Keystrokes per key in the period before it
+///
+/// # Returns
+/// `Option<HashMap<String, ShareChange>>` - Change per key of `current`,
+/// or None if either period has too few keystrokes
+/// ([`SHARE_CHANGE_MIN_KEYSTROKES`]) for shares to mean much
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::{share_changes, ShareChange};
+/// use std::collections::HashMap;
+///
+/// let yesterday = HashMap::from([("KEY_18".to_string(), 100), ("KEY_57".to_string(), 100)]);
+/// let today = HashMap::from([
+///     ("KEY_18".to_string(), 200),
+///     ("KEY_57".to_string(), 50),
+///     ("KEY_103".to_string(), 150),
+/// ]);
+///
+/// let changes = share_changes(&today, &yesterday).unwrap();
+/// // Half the keystrokes yesterday, half today: unchanged
+/// assert_eq!(changes["KEY_18"], ShareChange::Change(0.0));
+/// assert_eq!(changes["KEY_57"], ShareChange::Change(-0.75));
+/// assert_eq!(changes["KEY_103"], ShareChange::New);
+///
+/// // A handful of keystrokes is no basis for a comparison
+/// let quiet = HashMap::from([("KEY_18".to_string(), 3)]);
+/// assert_eq!(share_changes(&today, &quiet), None);
+/// ```
+pub fn share_changes(current: &HashMap<String, u64>, previous: &HashMap<String, u64>) -> Option<HashMap<String, ShareChange>> {
+    let current_total: u64 = current.values().sum();
+    let previous_total: u64 = previous.values().sum();
+    if current_total < SHARE_CHANGE_MIN_KEYSTROKES || previous_total < SHARE_CHANGE_MIN_KEYSTROKES {
+        return None;
+    }
+
+    let changes = current
+        .iter()
+        .map(|(key, &count)| {
+            let change = match previous.get(key).copied().unwrap_or(0) {
+                0 => ShareChange::New,
+                before => {
+                    let share = count as f64 / current_total as f64;
+                    let share_before = before as f64 / previous_total as f64;
+                    ShareChange::Change(share / share_before - 1.0)
+                }
+            };
+            (key.clone(), change)
+        })
+        .collect();
+    Some(changes)
+}
+
+impl Default for KeyStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyStats {
+    /// Marks the statistics as changed.
+    ///
+    /// Revisions come from one counter for the whole process, so the
+    /// statistics of a restarted logger never share a revision with those
+    /// of the one before it.
+    pub fn bump_revision(&mut self) {
+        static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+        self.revision = NEXT_REVISION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Creates a new empty KeyStats instance.
+    ///
+    /// Initializes all counters to zero and sets the session start time
+    /// to the current UTC time.
+    pub fn new() -> Self {
+        Self {
+            key_counts: HashMap::new(),
+            key_sequences: Vec::new(),
+            persist_sequences: false,
+            revision: 0,
+            format_version: DATA_FORMAT_VERSION,
+            session_start: Utc::now(),
+            total_keystrokes: 0,
+            typing_sessions: Vec::new(),
+            daily_stats: HashMap::new(),
+            session_keystrokes: 0,
+            char_keystrokes: 0,
+            corrections: 0,
+            session_words: 0,
+            records: Records::default(),
+            rollover: Rollover::default(),
+            session_key_counts: HashMap::new(),
+            session_timeline: Vec::new(),
+            session_first_key: None,
+            last_keypress: None,
+            session_warmup: [0; WARMUP_MINUTES],
+            timeline: DayTimeline::default(),
+            device_counts: HashMap::new(),
+            device_key_counts: HashMap::new(),
+            context_counts: HashMap::new(),
+            char_counts: HashMap::new(),
+            bigram_counts: HashMap::new(),
+            session_context_counts: HashMap::new(),
+            session_device_counts: HashMap::new(),
+            active_context: None,
+            session_tag: None,
+            session_note: None,
+            anonymized: false,
+            case_sensitive: false,
+            sessions_counted_since: None,
+            last_export_at: None,
+            diagnostics: Diagnostics::default(),
+            recent_chars: VecDeque::new(),
+            recent_keys: RecentKeys::default(),
+            key_sequence_times: VecDeque::new(),
+            coverage_pending: Duration::ZERO,
+        }
+    }
+
+    /// Records a new keystroke and updates all relevant statistics.
+    ///
+    /// # Arguments
+    /// * `key` - The key that was pressed (human-readable format)
+    pub fn add_keypress(&mut self, key: &str) {
+        self.add_keypress_at(key, chrono::Local::now());
+    }
+
+    /// Records a keystroke that happened at a given time.
+    ///
+    /// # Arguments
+    /// * `key` - Stored key name
+    /// * `at` - When the key was pressed; decides the day and hour it is filed under
+    pub fn add_keypress_at(&mut self, key: &str, at: DateTime<chrono::Local>) {
+        use chrono::Timelike;
+
+        *self.key_counts.entry(key.to_string()).or_insert(0) += 1;
+        *self.session_key_counts.entry(key.to_string()).or_insert(0) += 1;
+        self.total_keystrokes += 1;
+        self.session_keystrokes += 1;
+
+        let minute = at
+            .signed_duration_since(self.session_start)
+            .num_minutes()
+            .max(0) as usize;
+        if self.session_timeline.len() <= minute {
+            self.session_timeline.resize(minute + 1, 0);
+        }
+        self.session_timeline[minute] += 1;
+        self.timeline.add(key, at);
+
+        let at_utc = at.with_timezone(&Utc);
+        let first_key = *self.session_first_key.get_or_insert(at_utc);
+        let pause = self.last_keypress.replace(at_utc).and_then(|last| at_utc.signed_duration_since(last).to_std().ok());
+        let is_char = keymap::is_character_name(key);
+
+        if is_char {
+            self.char_keystrokes += 1;
+
+            let offset = at_utc.signed_duration_since(first_key).num_minutes();
+            if let Ok(offset) = usize::try_from(offset)
+                && offset < WARMUP_MINUTES
+            {
+                self.session_warmup[offset] += 1;
+            }
+
+            let second = at.timestamp();
+            match self.recent_chars.back_mut() {
+                Some((last, count)) if *last == second => *count += 1,
+                _ => self.recent_chars.push_back((second, 1)),
+            }
+        } else if keymap::parse_code(key) == Some(keymap::KEY_BACKSPACE) {
+            self.corrections += 1;
+        }
+        
+        self.key_sequences.push(key.to_string());
+        self.key_sequence_times.push_back(at_utc);
+        if self.key_sequences.len() > 100 {
+            self.key_sequences.remove(0);
+        }
+        while self.key_sequence_times.len() > self.key_sequences.len() {
+            self.key_sequence_times.pop_front();
+        }
+        self.recent_keys.push(key, at_utc);
+
+        let day_stats = self.daily_stats.entry(day_key(at.date_naive())).or_default();
+        
+        day_stats.keystrokes += 1;
+        *day_stats.key_distribution.entry(key.to_string()).or_insert(0) += 1;
+        let hour = at.hour() as usize;
+        add_to_hour(&mut day_stats.hourly_keystrokes, hour, 1);
+        if is_char {
+            add_to_hour(&mut day_stats.hourly_chars, hour, 1);
+        }
+        if let Some(pause) = pause.filter(|pause| *pause <= ACTIVE_TYPING_GAP) {
+            add_to_hour(&mut day_stats.hourly_active_ms, hour, pause.as_millis() as u64);
+        }
+        day_stats.minute_keystrokes.add((at.hour() * 60 + at.minute()) as u16, 1);
+    }
+
+    /// Attributes a keystroke to the keyboard it came from.
+    ///
+    /// Call alongside [`KeyStats::add_keypress`] when the device is known.
+    ///
+    /// # Arguments
+    /// * `device` - Label of the device, see [`device_labels`]
+    /// * `key` - Stored key name, as passed to [`KeyStats::add_keypress`]
+    pub fn add_device_keypress(&mut self, device: &str, key: &str) {
+        *self.device_counts.entry(device.to_string()).or_insert(0) += 1;
+        *self.session_device_counts.entry(device.to_string()).or_insert(0) += 1;
+        *self
+            .device_key_counts
+            .entry(device.to_string())
+            .or_default()
+            .entry(key.to_string())
+            .or_insert(0) += 1;
+
+        let day_stats = self.daily_stats.entry(day_key(today())).or_default();
+        *day_stats.device_keystrokes.entry(device.to_string()).or_insert(0) += 1;
+    }
+
+    /// Records keystrokes that no logged keyboard saw, e.g. typed over SSH
+    /// and reported with `ctrlq ping`.
+    ///
+    /// They count towards the totals, the day, the hour and the session and
+    /// are attributed to [`REMOTE_DEVICE`], but as no key is known they
+    /// don't show in key counts. They only count towards WPM as typed
+    /// characters when `as_chars` is set (the `remote_wpm` setting).
+    ///
+    /// # Arguments
+    /// * `count` - Keystrokes to add
+    /// * `as_chars` - Whether they count as typed characters
+    /// * `at` - When they were typed
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::{KeyStats, REMOTE_DEVICE};
+    /// use ctrlq::keymap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// stats.add_keypress(&keymap::key_name(30));
+    /// stats.add_device_keypress("laptop", &keymap::key_name(30));
+    /// stats.add_remote_keystrokes(40, false, chrono::Local::now());
+    ///
+    /// assert_eq!(stats.total_keystrokes, 41);
+    /// assert_eq!(stats.session_keystrokes, 41);
+    /// assert_eq!(stats.device_counts[REMOTE_DEVICE], 40);
+    /// assert_eq!(stats.key_counts.values().sum::<u64>(), 1);
+    /// // Left out of WPM unless remote_wpm is set
+    /// assert_eq!(stats.char_keystrokes, 1);
+    /// ```
+    pub fn add_remote_keystrokes(&mut self, count: u32, as_chars: bool, at: DateTime<chrono::Local>) {
+        use chrono::Timelike;
+
+        let total = u64::from(count);
+        self.total_keystrokes += total;
+        self.session_keystrokes += total;
+        *self.device_counts.entry(REMOTE_DEVICE.to_string()).or_insert(0) += total;
+        *self.session_device_counts.entry(REMOTE_DEVICE.to_string()).or_insert(0) += total;
+
+        let minute = at
+            .signed_duration_since(self.session_start)
+            .num_minutes()
+            .max(0) as usize;
+        if self.session_timeline.len() <= minute {
+            self.session_timeline.resize(minute + 1, 0);
+        }
+        self.session_timeline[minute] += count;
+        self.session_first_key.get_or_insert(at.with_timezone(&Utc));
+        if as_chars {
+            self.char_keystrokes += total;
+        }
+
+        let day_stats = self.daily_stats.entry(day_key(at.date_naive())).or_default();
+        day_stats.keystrokes += total;
+        *day_stats.device_keystrokes.entry(REMOTE_DEVICE.to_string()).or_insert(0) += total;
+        let hour = at.hour() as usize;
+        add_to_hour(&mut day_stats.hourly_keystrokes, hour, total);
+        if as_chars {
+            add_to_hour(&mut day_stats.hourly_chars, hour, total);
+        }
+        day_stats.minute_keystrokes.add((at.hour() * 60 + at.minute()) as u16, count);
+    }
+
+    /// Counts a character produced by a keystroke, for the Top Keys tab's
+    /// characters mode. Call alongside [`KeyStats::add_keypress`].
+    pub fn add_char(&mut self, c: char) {
+        *self.char_counts.entry(c.to_string()).or_insert(0) += 1;
+
+        let day_stats = self.daily_stats.entry(day_key(today())).or_default();
+        *day_stats.char_counts.entry(c.to_string()).or_insert(0) += 1;
+    }
+
+    /// Counts a shortcut recognized as an action, see [`crate::actions`].
+    ///
+    /// Call alongside [`KeyStats::add_keypress`] for the key of the shortcut.
+    ///
+    /// # Arguments
+    /// * `action` - Name of the action, e.g. `paste`
+    pub fn add_action(&mut self, action: &str) {
+        let day_stats = self.daily_stats.entry(day_key(today())).or_default();
+        *day_stats.actions.entry(action.to_string()).or_insert(0) += 1;
+    }
+
+    /// Counts a break suggested today by the break reminder.
+    pub fn add_break_suggested(&mut self) {
+        self.daily_stats.entry(day_key(today())).or_default().breaks_suggested += 1;
+    }
+
+    /// Counts a suggested break that was taken today.
+    pub fn add_break_taken(&mut self) {
+        self.daily_stats.entry(day_key(today())).or_default().breaks_taken += 1;
+    }
+
+    /// Sums the suggested and taken breaks of the last days.
+    ///
+    /// # Arguments
+    /// * `days` - Days to sum, today included
+    ///
+    /// # Returns
+    /// `(u64, u64)` - Breaks taken and breaks suggested
+    pub fn break_compliance(&self, days: u64) -> (u64, u64) {
+        let end = today();
+        let Some(start) = days.checked_sub(1).and_then(|back| end.checked_sub_days(chrono::Days::new(back))) else {
+            return (0, 0);
+        };
+        start
+            .iter_days()
+            .take_while(|day| *day <= end)
+            .filter_map(|day| self.daily_stats.get(&day_key(day)))
+            .fold((0, 0), |(taken, suggested), day| (taken + day.breaks_taken, suggested + day.breaks_suggested))
+    }
+
+    /// Counts two character keys typed in a row, see [`BigramTracker`].
+    ///
+    /// # Arguments
+    /// * `first` - evdev code of the first key
+    /// * `second` - evdev code of the second key
+    pub fn add_bigram(&mut self, first: u16, second: u16) {
+        let pair = format!("{} {}", keymap::key_name(first), keymap::key_name(second));
+        *self.bigram_counts.entry(pair.clone()).or_insert(0) += 1;
+
+        let day_stats = self.daily_stats.entry(day_key(today())).or_default();
+        *day_stats.bigram_counts.entry(pair).or_insert(0) += 1;
+    }
+
+    /// Attributes a keystroke to the active context.
+    ///
+    /// Call alongside [`KeyStats::add_keypress`]; without a context set
+    /// through [`KeyStats::set_context`] it counts towards [`DEFAULT_CONTEXT`].
+    pub fn add_context_keypress(&mut self) {
+        let context = self.current_context().to_string();
+        *self.context_counts.entry(context.clone()).or_insert(0) += 1;
+        *self.session_context_counts.entry(context.clone()).or_insert(0) += 1;
+
+        let day_stats = self.daily_stats.entry(day_key(today())).or_default();
+        *day_stats.context_keystrokes.entry(context).or_insert(0) += 1;
+    }
+
+    /// Counts the characters a key event typed and deleted for today.
+    ///
+    /// Call with what a [`DeletionTracker`] found in the event.
+    pub fn add_edit(&mut self, edit: Edit) {
+        if edit == Edit::default() {
+            return;
+        }
+        let day_stats = self.daily_stats.entry(day_key(today())).or_default();
+        day_stats.chars_typed += edit.typed;
+        day_stats.chars_deleted += edit.deleted;
+    }
+
+    /// Adds time with Caps Lock on to today, as handed out by a [`LockTimer`].
+    pub fn add_caps_lock_time(&mut self, on: Duration) {
+        let day_stats = self.daily_stats.entry(day_key(today())).or_default();
+        day_stats.caps_lock_ms += on.as_millis() as u64;
+    }
+
+    /// Adds time the logger ran and counted to today, as handed out by a
+    /// [`LockTimer`]; parts of a second wait for the next call.
+    pub fn add_coverage_time(&mut self, ran: Duration) {
+        self.coverage_pending += ran;
+        let seconds = self.coverage_pending.as_secs();
+        if seconds == 0 {
+            return;
+        }
+        self.coverage_pending -= Duration::from_secs(seconds);
+        self.daily_stats.entry(day_key(today())).or_default().coverage_seconds += seconds;
+    }
+
+    /// Counts a letter typed with Caps Lock on for today.
+    ///
+    /// # Arguments
+    /// * `new_run` - Whether the letter starts a run, see [`ModifierState::caps_letter`]
+    pub fn add_caps_lock_letter(&mut self, new_run: bool) {
+        let day_stats = self.daily_stats.entry(day_key(today())).or_default();
+        day_stats.caps_lock_letters += 1;
+        if new_run {
+            day_stats.caps_lock_runs += 1;
+        }
+    }
+
+    /// Counts the word and line a key press ended, if any.
+    ///
+    /// Call alongside [`KeyStats::add_keypress`] with what a
+    /// [`BoundaryTracker`] found in the same press.
+    pub fn add_boundary(&mut self, boundary: Boundary) {
+        if boundary == Boundary::default() {
+            return;
+        }
+        let day_stats = self.daily_stats.entry(day_key(today())).or_default();
+        if boundary.word {
+            self.session_words += 1;
+            day_stats.words += 1;
+        }
+        if boundary.line {
+            day_stats.lines += 1;
+        }
+    }
+
+    /// Returns the context new keystrokes are attributed to.
+    pub fn current_context(&self) -> &str {
+        self.active_context.as_deref().unwrap_or(DEFAULT_CONTEXT)
+    }
+
+    /// Switches the context new keystrokes are attributed to.
+    ///
+    /// # Arguments
+    /// * `context` - Project or other context name; [`DEFAULT_CONTEXT`] clears it
+    pub fn set_context(&mut self, context: &str) {
+        self.active_context = (context != DEFAULT_CONTEXT).then(|| context.to_string());
+    }
+
+    /// Whether keystrokes were ever attributed to a context other than
+    /// [`DEFAULT_CONTEXT`], i.e. whether contexts are worth showing.
+    pub fn uses_contexts(&self) -> bool {
+        self.active_context.is_some() || self.context_counts.keys().any(|c| c != DEFAULT_CONTEXT)
+    }
+
+    /// Returns lifetime keystrokes per physical key, adding shifted
+    /// variants (`KEY_<code>_S`) to their key.
+    ///
+    /// # Returns
+    /// `HashMap<u16, u64>` - Keystrokes by evdev code
+    pub fn physical_key_counts(&self) -> HashMap<u16, u64> {
+        physical_counts(&self.key_counts)
+    }
+
+    /// Returns lifetime keystrokes per physical key of one keyboard, like
+    /// [`KeyStats::physical_key_counts`].
+    ///
+    /// # Arguments
+    /// * `device` - Label of the device, see [`device_labels`]
+    ///
+    /// # Returns
+    /// `HashMap<u16, u64>` - Keystrokes by evdev code; empty for keyboards
+    /// logged before per-keyboard key counts were kept
+    pub fn device_physical_key_counts(&self, device: &str) -> HashMap<u16, u64> {
+        self.device_key_counts.get(device).map(physical_counts).unwrap_or_default()
+    }
+
+    /// Share of keystrokes on shiftable keys (letters, digits, punctuation)
+    /// that were typed shifted, a rough measure of pinky load.
+    ///
+    /// # Returns
+    /// `Option<f64>` - Fraction between 0 and 1, or None without case-sensitive data
+    pub fn shifted_share(&self) -> Option<f64> {
+        if !self.case_sensitive {
+            return None;
+        }
+        let mut shifted = 0;
+        let mut total = 0;
+        for (key, &count) in &self.key_counts {
+            if keymap::parse_code(key).is_some_and(keymap::is_shiftable) {
+                total += count;
+                if keymap::is_shifted_name(key) {
+                    shifted += count;
+                }
+            }
+        }
+        (total > 0).then(|| shifted as f64 / total as f64)
+    }
+
+    /// Label of a stored key name for display: the typed character for
+    /// case-sensitive statistics (`a`, `A`, `!`), the key name otherwise.
+    pub fn key_label(&self, key: &str) -> String {
+        if self.case_sensitive {
+            keymap::typed_name(key)
+        } else {
+            keymap::display_name(key)
+        }
+    }
+
+    /// Returns lifetime keystrokes per device, most used first.
+    ///
+    /// # Returns
+    /// `Vec<(String, u64)>` - (device label, keystrokes) pairs
+    pub fn device_totals(&self) -> Vec<(String, u64)> {
+        sorted_counts(&self.device_counts)
+    }
+
+    /// Returns lifetime keystrokes per context, most used first.
+    ///
+    /// # Returns
+    /// `Vec<(String, u64)>` - (context, keystrokes) pairs
+    pub fn context_totals(&self) -> Vec<(String, u64)> {
+        sorted_counts(&self.context_counts)
+    }
+
+    /// Calculates the current gross words per minute (WPM) for this session.
+    ///
+    /// Only character-producing keys count towards typed characters, using
+    /// the usual estimate of 5 characters per word. Returns None if
+    /// insufficient data is available for calculation.
+    ///
+    /// # Returns
+    /// `Option<f64>` - WPM if calculable, None otherwise
+    pub fn get_wpm(&self) -> Option<f64> {
+        self.wpm_at(Utc::now())
+    }
+
+    /// Calculates the gross WPM of this session as of a given time, see
+    /// [`KeyStats::get_wpm`].
+    ///
+    /// # Arguments
+    /// * `now` - End of the measured span
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, Local, TimeZone};
+    /// use ctrlq::keylogger::KeyStats;
+    ///
+    /// let start = Local.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+    /// let mut stats = KeyStats::new();
+    /// stats.session_start = start.to_utc();
+    /// // 300 presses of A in a minute: 60 words of 5 characters
+    /// for i in 0..300 {
+    ///     stats.add_keypress_at("KEY_30", start + Duration::milliseconds(200 * i));
+    /// }
+    /// let end = start.to_utc() + Duration::seconds(60);
+    /// assert_eq!(stats.wpm_at(end), Some(60.0));
+    /// assert_eq!(stats.wpm_at(start.to_utc() + Duration::seconds(120)), Some(30.0));
+    ///
+    /// // Fewer than 5 characters, or no time passed, can't be measured
+    /// let mut short = KeyStats::new();
+    /// short.session_start = start.to_utc();
+    /// for _ in 0..4 {
+    ///     short.add_keypress_at("KEY_30", start);
+    /// }
+    /// assert_eq!(short.wpm_at(end), None);
+    /// assert_eq!(stats.wpm_at(start.to_utc()), None);
+    /// ```
+    pub fn wpm_at(&self, now: DateTime<Utc>) -> Option<f64> {
+        Self::wpm(self.char_keystrokes, self.session_start, now)
+    }
+
+    /// Calculates the net words per minute for this session.
+    ///
+    /// Like [`KeyStats::get_wpm`], but every backspace removes one typed
+    /// character from the count, so corrected mistakes don't inflate the speed.
+    ///
+    /// # Returns
+    /// `Option<f64>` - Net WPM if calculable, None otherwise
+    pub fn get_net_wpm(&self) -> Option<f64> {
+        self.net_wpm_at(Utc::now())
+    }
+
+    /// Calculates the net WPM of this session as of a given time, see
+    /// [`KeyStats::get_net_wpm`].
+    ///
+    /// # Arguments
+    /// * `now` - End of the measured span
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, Local, TimeZone};
+    /// use ctrlq::keylogger::KeyStats;
+    ///
+    /// let start = Local.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+    /// let mut stats = KeyStats::new();
+    /// stats.session_start = start.to_utc();
+    /// // 300 characters and 30 corrections in a minute: 270 kept
+    /// for i in 0..330 {
+    ///     let key = if i % 11 == 10 { "KEY_14" } else { "KEY_30" };
+    ///     stats.add_keypress_at(key, start + Duration::milliseconds(180 * i));
+    /// }
+    /// let end = start.to_utc() + Duration::seconds(60);
+    /// assert_eq!(stats.wpm_at(end), Some(60.0));
+    /// assert_eq!(stats.net_wpm_at(end), Some(54.0));
+    ///
+    /// // More corrections than characters is 0, not negative
+    /// stats.corrections = 1_000;
+    /// assert_eq!(stats.net_wpm_at(end), Some(0.0));
+    /// // Without a gross WPM there is no net WPM either
+    /// assert_eq!(KeyStats::new().net_wpm_at(end), None);
+    /// ```
+    pub fn net_wpm_at(&self, now: DateTime<Utc>) -> Option<f64> {
+        self.wpm_at(now)?;
+        let kept = self.char_keystrokes.saturating_sub(self.corrections);
+        Self::wpm(kept, self.session_start, now).or(Some(0.0))
+    }
+
+    /// Calculates words per minute for this session from the words actually
+    /// typed, see [`BoundaryTracker`], instead of characters / 5.
+    ///
+    /// # Returns
+    /// `Option<f64>` - Real WPM, or None before the first word
+    pub fn get_real_wpm(&self) -> Option<f64> {
+        Self::real_wpm(self.session_words, self.session_start, Utc::now())
+    }
+
+    /// Computes WPM for a number of typed words over a time span.
+    fn real_wpm(words: u64, start: DateTime<Utc>, end: DateTime<Utc>) -> Option<f64> {
+        let minutes = end.signed_duration_since(start).num_seconds() as f64 / 60.0;
+        (words > 0 && minutes > 0.0).then(|| words as f64 / minutes)
+    }
+
+    /// Computes WPM for a number of typed characters over a time span.
+    ///
+    /// # Arguments
+    /// * `chars` - Character keystrokes typed
+    /// * `start` - Start of the measured span
+    /// * `end` - End of the measured span
+    fn wpm(chars: u64, start: DateTime<Utc>, end: DateTime<Utc>) -> Option<f64> {
+        if chars < 5 {
+            return None;
+        }
+
+        let minutes = end.signed_duration_since(start).num_seconds() as f64 / 60.0;
+
+        if minutes > 0.0 {
+            Some((chars as f64 / 5.0) / minutes)
+        } else {
+            None
+        }
+    }
+
+    /// Most pressed keys of the last minute, for the live ticker.
+    ///
+    /// Evicts the keystrokes that expired by `now` first, so a ticker
+    /// drains while nothing is typed.
+    ///
+    /// # Arguments
+    /// * `n` - Number of keys to return
+    /// * `now` - End of the window
+    ///
+    /// # Returns
+    /// `Vec<(String, u32)>` - Stored key names and their keystrokes, most pressed first
+    pub fn top_recent_keys(&mut self, n: usize, now: DateTime<Utc>) -> Vec<(String, u32)> {
+        self.recent_keys.evict(now);
+        self.recent_keys.top(n).into_iter().map(|(key, count)| (key.to_string(), count)).collect()
+    }
+
+    /// The last keys typed with when they were typed, oldest first.
+    ///
+    /// # Returns
+    /// `Vec<(&str, Option<DateTime<Utc>>)>` - Stored key names of
+    /// `key_sequences`; keys loaded from the data file have no time
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, Utc};
+    /// use ctrlq::keylogger::KeyStats;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// stats.key_sequences.push("KEY_30".to_string());
+    /// let at = Utc::now() - Duration::minutes(2);
+    /// stats.add_keypress_at("KEY_48", at.into());
+    ///
+    /// assert_eq!(stats.recent_sequence(), [("KEY_30", None), ("KEY_48", Some(at))]);
+    /// ```
+    pub fn recent_sequence(&self) -> Vec<(&str, Option<DateTime<Utc>>)> {
+        let untimed = self.key_sequences.len().saturating_sub(self.key_sequence_times.len());
+        self.key_sequences
+            .iter()
+            .enumerate()
+            .map(|(index, key)| {
+                let at = index.checked_sub(untimed).and_then(|index| self.key_sequence_times.get(index)).copied();
+                (key.as_str(), at)
+            })
+            .collect()
+    }
+
+    /// Shannon entropy of the key distribution of the current session, in bits.
+    ///
+    /// Measures how varied typing is: prose concentrates on letters and
+    /// space, while code spreads keystrokes over many more symbols.
+    ///
+    /// # Returns
+    /// `Option<f64>` - Entropy, or None if no keys were pressed in the session
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::KeyStats;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// assert_eq!(stats.get_entropy(), None);
+    /// stats.add_keypress("KEY_30");
+    /// assert_eq!(stats.get_entropy(), Some(0.0));
+    /// stats.add_keypress("KEY_31");
+    /// assert_eq!(stats.get_entropy(), Some(1.0));
+    ///
+    /// // Earlier sessions only count towards the lifetime key counts
+    /// stats.key_counts.insert("KEY_32".to_string(), 1_000);
+    /// assert_eq!(stats.get_entropy(), Some(1.0));
+    /// ```
+    pub fn get_entropy(&self) -> Option<f64> {
+        entropy(self.session_key_counts.values().copied())
+    }
+
+    /// Key diversity for every recorded day, oldest first.
+    ///
+    /// # Returns
+    /// `Vec<(String, f64)>` - List of (date, entropy) pairs for days with keystrokes
+    pub fn daily_entropy(&self) -> Vec<(String, f64)> {
+        let mut days: Vec<_> = self.daily_stats
+            .iter()
+            .filter_map(|(date, day)| day.entropy().map(|e| (date.clone(), e)))
+            .collect();
+        days.sort_by(|a, b| a.0.cmp(&b.0));
+        days
+    }
+
+    /// Combines the key distributions of the last `days` days, today included.
+    ///
+    /// # Arguments
+    /// * `days` - Number of days to cover; 1 for just today
+    ///
+    /// # Returns
+    /// `HashMap<String, u64>` - Keystrokes per stored key name
+    pub fn recent_key_distribution(&self, days: u64) -> HashMap<String, u64> {
+        let today = today();
+        match days.checked_sub(1).and_then(|back| today.checked_sub_days(chrono::Days::new(back))) {
+            Some(first) => self.key_distribution_between(first, today),
+            None => HashMap::new(),
+        }
+    }
+
+    /// Combines the key distributions of an inclusive range of days.
+    ///
+    /// # Arguments
+    /// * `start` - First day of the range
+    /// * `end` - Last day of the range
+    ///
+    /// # Returns
+    /// `HashMap<String, u64>` - Keystrokes per stored key name; empty for
+    /// days without statistics
+    pub fn key_distribution_between(&self, start: NaiveDate, end: NaiveDate) -> HashMap<String, u64> {
+        self.counts_between(start, end, |day| &day.key_distribution)
+    }
+
+    /// Combines the character counts of the last `days` days, today included,
+    /// like [`KeyStats::recent_key_distribution`].
+    pub fn recent_char_distribution(&self, days: u64) -> HashMap<String, u64> {
+        let today = today();
+        match days.checked_sub(1).and_then(|back| today.checked_sub_days(chrono::Days::new(back))) {
+            Some(first) => self.char_distribution_between(first, today),
+            None => HashMap::new(),
+        }
+    }
+
+    /// Combines the character counts of an inclusive range of days.
+    pub fn char_distribution_between(&self, start: NaiveDate, end: NaiveDate) -> HashMap<String, u64> {
+        self.counts_between(start, end, |day| &day.char_counts)
+    }
+
+    /// Combines the bigram counts of the last `days` days, today included,
+    /// like [`KeyStats::recent_key_distribution`].
+    pub fn recent_bigram_distribution(&self, days: u64) -> HashMap<String, u64> {
+        let today = today();
+        match days.checked_sub(1).and_then(|back| today.checked_sub_days(chrono::Days::new(back))) {
+            Some(first) => self.bigram_distribution_between(first, today),
+            None => HashMap::new(),
+        }
+    }
+
+    /// Combines the bigram counts of an inclusive range of days.
+    pub fn bigram_distribution_between(&self, start: NaiveDate, end: NaiveDate) -> HashMap<String, u64> {
+        self.counts_between(start, end, |day| &day.bigram_counts)
+    }
+
+    /// Sums one per-day count map over an inclusive range of days.
+    fn counts_between(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        counts_of: fn(&DayStats) -> &HashMap<String, u64>,
+    ) -> HashMap<String, u64> {
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for day in start.iter_days().take_while(|day| *day <= end) {
+            if let Some(stats) = self.daily_stats.get(&day_key(day)) {
+                for (key, count) in counts_of(stats) {
+                    *counts.entry(key.clone()).or_insert(0) += count;
+                }
+            }
+        }
+        counts
+    }
+
+    /// Returns the finished sessions that overlapped a local day, oldest first.
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Local, NaiveDate, TimeZone, Utc};
+    /// use ctrlq::keylogger::{KeyStats, TypingSession};
+    ///
+    /// let mut stats = KeyStats::new();
+    /// // 23:30 to 00:30: counts for both days
+    /// stats.typing_sessions.push(TypingSession {
+    ///     start: Local.with_ymd_and_hms(2024, 5, 1, 23, 30, 0).unwrap().with_timezone(&Utc),
+    ///     end: Local.with_ymd_and_hms(2024, 5, 2, 0, 30, 0).unwrap().with_timezone(&Utc),
+    ///     keystrokes: 800,
+    ///     wpm: None,
+    ///     net_wpm: None,
+    ///     words: 0,
+    ///     real_wpm: None,
+    ///     corrections: 0,
+    ///     top_keys: Vec::new(),
+    ///     timeline: Vec::new(),
+    ///     contexts: Vec::new(),
+    ///     first_key_secs: None,
+    ///     warmup: [0; 30],
+    ///     tag: None,
+    ///     note: None,
+    ///     categories: Default::default(),
+    ///     dock: None,
+    /// });
+    ///
+    /// assert_eq!(stats.sessions_on(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()).len(), 1);
+    /// assert_eq!(stats.sessions_on(NaiveDate::from_ymd_opt(2024, 5, 2).unwrap()).len(), 1);
+    /// assert!(stats.sessions_on(NaiveDate::from_ymd_opt(2024, 5, 3).unwrap()).is_empty());
+    /// ```
+    pub fn sessions_on(&self, date: NaiveDate) -> Vec<&TypingSession> {
+        self.typing_sessions
+            .iter()
+            .filter(|s| {
+                s.start.with_timezone(&chrono::Local).date_naive() <= date
+                    && date <= s.end.with_timezone(&chrono::Local).date_naive()
+            })
+            .collect()
+    }
+
+    /// Computes the lengths, gaps and daily counts of the sessions that
+    /// started on a local day in `start..=end`.
+    ///
+    /// Lengths and gaps come from the finished sessions. Sessions per day
+    /// need the per-day counts, which older data files don't have, so they
+    /// start at [`KeyStats::sessions_counted_since`]; the running session
+    /// counts for today.
+    ///
+    /// # Arguments
+    /// * `start` - First day
+    /// * `end` - Last day
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, Local, NaiveDate, TimeZone, Utc};
+    /// use ctrlq::keylogger::{day_key, KeyStats, TypingSession};
+    ///
+    /// let session = |day: u32, hour: u32, minutes: i64| {
+    ///     let start = Local.with_ymd_and_hms(2024, 5, day, hour, 0, 0).unwrap().with_timezone(&Utc);
+    ///     TypingSession {
+    ///         start,
+    ///         end: start + Duration::minutes(minutes),
+    ///         keystrokes: 100,
+    ///         wpm: None,
+    ///         net_wpm: None,
+    ///         words: 0,
+    ///         real_wpm: None,
+    ///         corrections: 0,
+    ///         top_keys: Vec::new(),
+    ///         timeline: Vec::new(),
+    ///         contexts: Vec::new(),
+    ///         first_key_secs: None,
+    ///         warmup: [0; 30],
+    ///         tag: None,
+    ///         note: None,
+    ///         categories: Default::default(),
+    ///         dock: None,
+    ///     }
+    /// };
+    /// let (may_1, may_2) = (NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 5, 2).unwrap());
+    /// let mut stats = KeyStats::new();
+    /// stats.typing_sessions = vec![session(1, 9, 20), session(1, 10, 40), session(2, 9, 10), session(2, 23, 90)];
+    ///
+    /// let patterns = stats.session_patterns(may_1, may_2);
+    /// assert_eq!(patterns.sessions, 4);
+    /// assert_eq!(patterns.average_length, Some(Duration::minutes(40)));
+    /// assert_eq!(patterns.p90_length, Some(Duration::minutes(90)));
+    /// // 40 minutes on May 1 and 13:50 on May 2; the night in between is no gap
+    /// assert_eq!(patterns.average_gap, Some(Duration::minutes(435)));
+    ///
+    /// // Data from before sessions were counted per day
+    /// assert_eq!(patterns.sessions_per_day, None);
+    /// assert_eq!(patterns.counted_from, None);
+    ///
+    /// stats.sessions_counted_since = Some(may_2);
+    /// stats.add_counts("KEY_30", 500, Some(may_1));
+    /// stats.add_counts("KEY_30", 500, Some(may_2));
+    /// stats.daily_stats.get_mut(&day_key(may_2)).unwrap().sessions = 2;
+    /// let patterns = stats.session_patterns(may_1, may_2);
+    /// assert_eq!(patterns.sessions_per_day, Some(2.0));
+    /// assert_eq!(patterns.counted_from, Some(may_2));
+    /// ```
+    pub fn session_patterns(&self, start: NaiveDate, end: NaiveDate) -> SessionPatterns {
+        let local_day = |time: &DateTime<Utc>| time.with_timezone(&chrono::Local).date_naive();
+        let mut sessions: Vec<&TypingSession> = self.typing_sessions
+            .iter()
+            .filter(|s| (start..=end).contains(&local_day(&s.start)))
+            .collect();
+        sessions.sort_by_key(|s| s.start);
+
+        let mut lengths: Vec<chrono::Duration> = sessions.iter().map(|s| s.duration()).collect();
+        lengths.sort();
+        let average = |durations: &[chrono::Duration]| {
+            (!durations.is_empty()).then(|| durations.iter().sum::<chrono::Duration>() / durations.len() as i32)
+        };
+        // Nearest rank: the shortest length at least 90% of the sessions fit in
+        let p90_length = (!lengths.is_empty()).then(|| lengths[(lengths.len() * 9).div_ceil(10) - 1]);
+        let gaps: Vec<chrono::Duration> = sessions
+            .windows(2)
+            .filter(|pair| local_day(&pair[0].start) == local_day(&pair[1].start) && pair[1].start >= pair[0].end)
+            .map(|pair| pair[1].start - pair[0].end)
+            .collect();
+
+        let counted_from = self.sessions_counted_since.map(|since| since.max(start));
+        let open_day = (self.session_keystrokes > 0).then(|| local_day(&self.session_start));
+        let daily: Vec<u64> = counted_from
+            .into_iter()
+            .flat_map(|from| from.iter_days().take_while(|day| *day <= end))
+            .filter_map(|date| {
+                let day = self.daily_stats.get(&day_key(date)).filter(|day| day.keystrokes > 0)?;
+                Some(day.sessions + u64::from(open_day == Some(date)))
+            })
+            .collect();
+
+        SessionPatterns {
+            sessions: sessions.len(),
+            average_length: average(&lengths),
+            p90_length,
+            average_gap: average(&gaps),
+            sessions_per_day: (!daily.is_empty()).then(|| daily.iter().sum::<u64>() as f64 / daily.len() as f64),
+            counted_from,
+        }
+    }
+
+    /// Averages the warm-up of all finished sessions: the WPM of each minute
+    /// after a session's first keystroke, over the sessions that lasted
+    /// through that minute.
+    ///
+    /// # Returns
+    /// `Option<WarmupCurve>` - The curve, or None before any session with
+    /// warm-up data lasted a full minute
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, TimeZone, Utc};
+    /// use ctrlq::keylogger::{KeyStats, TypingSession};
+    ///
+    /// // Two 30 minute sessions at 20 WPM for five minutes, then 40 WPM
+    /// let mut warmup = [200; 30];
+    /// warmup[..5].fill(100);
+    /// let mut stats = KeyStats::new();
+    /// for day in 1..=2 {
+    ///     let start = Utc.with_ymd_and_hms(2024, 5, day, 8, 0, 0).unwrap();
+    ///     stats.typing_sessions.push(TypingSession {
+    ///         start,
+    ///         end: start + Duration::minutes(31),
+    ///         keystrokes: 5500,
+    ///         wpm: Some(35.5),
+    ///         net_wpm: None,
+    ///         words: 0,
+    ///         real_wpm: None,
+    ///         corrections: 0,
+    ///         top_keys: Vec::new(),
+    ///         timeline: Vec::new(),
+    ///         contexts: Vec::new(),
+    ///         first_key_secs: Some(60),
+    ///         warmup,
+    ///         tag: None,
+    ///         note: None,
+    ///         categories: Default::default(),
+    ///         dock: None,
+    ///     });
+    /// }
+    ///
+    /// assert_eq!(stats.typing_sessions[0].warmup_wpm(), Some(20.0));
+    /// let curve = stats.warmup_curve().unwrap();
+    /// assert_eq!(curve.sessions, 2);
+    /// assert_eq!((curve.wpm[0], curve.wpm[29]), (Some(20.0), Some(40.0)));
+    /// assert_eq!(curve.full_speed_after(), Some(5));
+    /// ```
+    pub fn warmup_curve(&self) -> Option<WarmupCurve> {
+        WarmupCurve::from_sessions(&self.typing_sessions)
+    }
+
+    /// Aggregates `daily_stats` over an inclusive range of days.
+    ///
+    /// Days without data are listed in `missing_days` and count as zero.
+    ///
+    /// # Arguments
+    /// * `start` - First day of the range
+    /// * `end` - Last day of the range
+    /// * `label` - Name used when displaying the period
+    ///
+    /// # Returns
+    /// `PeriodSummary` - Aggregated statistics for the range
+    pub fn summarize_period(&self, start: NaiveDate, end: NaiveDate, label: &str) -> PeriodSummary {
+        let mut summary = PeriodSummary {
+            label: label.to_string(),
+            start,
+            end,
+            missing_days: Vec::new(),
+            keystrokes: 0,
+            wpm: None,
+            error_rate: None,
+            key_distribution: HashMap::new(),
+            speed_by_hour: Vec::new(),
+            actions: HashMap::new(),
+        };
+
+        let mut days = Vec::new();
+        for day in start.iter_days().take_while(|d| *d <= end) {
+            match self.daily_stats.get(&day_key(day)) {
+                Some(stats) => {
+                    days.push(stats);
+                    summary.keystrokes += stats.keystrokes;
+                    for (key, count) in &stats.key_distribution {
+                        *summary.key_distribution.entry(key.clone()).or_insert(0) += count;
+                    }
+                    for (action, count) in &stats.actions {
+                        *summary.actions.entry(action.clone()).or_insert(0) += count;
+                    }
+                }
+                None => summary.missing_days.push(day),
+            }
+        }
+        summary.speed_by_hour = speed_by_hour(days);
+
+        let session_wpms: Vec<f64> = self.typing_sessions
+            .iter()
+            .filter(|s| (start..=end).contains(&s.start.with_timezone(&chrono::Local).date_naive()))
+            .filter_map(|s| s.wpm)
+            .collect();
+        if !session_wpms.is_empty() {
+            summary.wpm = Some(session_wpms.iter().sum::<f64>() / session_wpms.len() as f64);
+        }
+
+        if summary.keystrokes > 0 {
+            let backspaces = summary.key_distribution
+                .get(&keymap::key_name(keymap::KEY_BACKSPACE))
+                .copied()
+                .unwrap_or(0);
+            summary.error_rate = Some(backspaces as f64 / summary.keystrokes as f64);
+        }
+
+        summary
+    }
+
+    /// Aggregates one calendar month of `daily_stats`.
+    ///
+    /// Days are local dates, like the keys of `daily_stats`. A running
+    /// month only covers the days up to `today`.
+    ///
+    /// # Arguments
+    /// * `month` - Any day of the month
+    /// * `today` - The current day
+    /// * `archived` - Archived days to include, see [`archive::load_days`];
+    ///   days in memory win
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Local, NaiveDate, TimeZone};
+    /// use ctrlq::keylogger::KeyStats;
+    /// use ctrlq::keymap;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// for (month, day, presses) in [(5, 3, 40), (5, 20, 20), (6, 1, 30), (6, 2, 15)] {
+    ///     for _ in 0..presses {
+    ///         stats.add_keypress_at(&keymap::key_name(30), Local.with_ymd_and_hms(2024, month, day, 10, 0, 0).unwrap());
+    ///     }
+    /// }
+    /// let today = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+    /// let may = stats.month_stats(NaiveDate::from_ymd_opt(2024, 5, 17).unwrap(), today, &HashMap::new());
+    /// let june = stats.month_stats(today, today, &HashMap::new());
+    ///
+    /// assert_eq!((may.label(), may.days, may.partial), ("2024-05".to_string(), 31, false));
+    /// assert_eq!((may.keystrokes, may.active_days, may.per_active_day()), (60, 2, Some(30.0)));
+    /// assert_eq!((june.days, june.partial, june.keystrokes), (10, true, 45));
+    /// assert_eq!(june.top_keys, vec![(keymap::key_name(30), 45)]);
+    /// // 4.5 a day so far after 60/31 in May
+    /// assert_eq!(format!("{:.0}%", june.change(&may).unwrap() * 100.0), "132%");
+    /// ```
+    pub fn month_stats(&self, month: NaiveDate, today: NaiveDate, archived: &HashMap<String, DayStats>) -> MonthStats {
+        let start = month.with_day(1).unwrap_or(month);
+        let end = start
+            .checked_add_months(chrono::Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .unwrap_or(start);
+        let last = end.min(today);
+
+        let (mut keystrokes, mut active_days) = (0, 0);
+        let mut distribution = HashMap::new();
+        for date in start.iter_days().take_while(|date| *date <= last) {
+            let key = day_key(date);
+            let Some(day) = self.daily_stats.get(&key).or_else(|| archived.get(&key)) else {
+                continue;
+            };
+            keystrokes += day.keystrokes;
+            if day.keystrokes > 0 {
+                active_days += 1;
+            }
+            for (key, count) in &day.key_distribution {
+                *distribution.entry(key.clone()).or_insert(0) += count;
+            }
+        }
+
+        let wpms: Vec<f64> = self
+            .typing_sessions
+            .iter()
+            .filter(|session| (start..=last).contains(&session.start.with_timezone(&chrono::Local).date_naive()))
+            .filter_map(|session| session.wpm)
+            .collect();
+        MonthStats {
+            month: start,
+            days: if last < start { 0 } else { (last - start).num_days() as u32 + 1 },
+            partial: (start..end).contains(&today),
+            keystrokes,
+            active_days,
+            top_keys: top_keys(&distribution, 5, None),
+            wpm: (!wpms.is_empty()).then(|| wpms.iter().sum::<f64>() / wpms.len() as f64),
+        }
+    }
+
+    /// Aggregates the last `count` months up to the current one, oldest first.
+    ///
+    /// # Arguments
+    /// * `count` - Number of months, the current one included
+    /// * `today` - The current day
+    /// * `archived` - Archived days to include, see [`KeyStats::month_stats`]
+    pub fn recent_months(&self, count: u32, today: NaiveDate, archived: &HashMap<String, DayStats>) -> Vec<MonthStats> {
+        let current = today.with_day(1).unwrap_or(today);
+        (0..count)
+            .rev()
+            .filter_map(|back| current.checked_sub_months(chrono::Months::new(back)))
+            .map(|month| self.month_stats(month, today, archived))
+            .collect()
+    }
+
+    /// Average keystrokes typed by the end of each hour on past days of a
+    /// weekday: entry `h` is the usual total from midnight through hour `h`.
+    ///
+    /// Only days before `before` with hourly data take part.
+    ///
+    /// # Arguments
+    /// * `weekday` - Weekday to build the curve for
+    /// * `before` - First day not to include (usually today)
+    ///
+    /// # Returns
+    /// `Option<([f64; 24], usize)>` - The cumulative curve and the number of
+    /// days it is based on, or None without any such day
+    pub fn typical_curve(&self, weekday: chrono::Weekday, before: NaiveDate) -> Option<([f64; 24], usize)> {
+        let mut curve = [0.0; 24];
+        let mut days = 0;
+        for (date, day) in &self.daily_stats {
+            let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+                continue;
+            };
+            if date >= before || date.weekday() != weekday || day.hourly_keystrokes.is_empty() {
+                continue;
+            }
+            days += 1;
+            let mut total = 0;
+            for (hour, point) in curve.iter_mut().enumerate() {
+                total += day.hourly_keystrokes.get(hour).copied().unwrap_or(0);
+                *point += total as f64;
+            }
+        }
+        if days == 0 {
+            return None;
+        }
+        curve.iter_mut().for_each(|point| *point /= days as f64);
+        Some((curve, days))
+    }
+
+    /// Compares today's keystrokes so far with what is typical for this
+    /// weekday by this time of day.
+    ///
+    /// Within the current hour the typical value is interpolated, so the
+    /// comparison doesn't jump on the hour.
+    ///
+    /// # Arguments
+    /// * `now` - Current local time
+    ///
+    /// # Returns
+    /// `Option<PaceTrend>` - The comparison, or None without history for
+    /// this weekday or if nothing is usually typed by this time
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Local, TimeZone};
+    /// use ctrlq::keylogger::KeyStats;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// // Two earlier Tuesdays: 100 keystrokes at 9:00 and 300 at 14:00 each
+    /// for day in [7, 14] {
+    ///     for (hour, count) in [(9, 100), (14, 300)] {
+    ///         for _ in 0..count {
+    ///             stats.add_keypress_at("KEY_30", Local.with_ymd_and_hms(2024, 5, day, hour, 0, 0).unwrap());
+    ///         }
+    ///     }
+    /// }
+    /// // Today, Tuesday the 21st: 236 keystrokes in the morning
+    /// for _ in 0..236 {
+    ///     stats.add_keypress_at("KEY_30", Local.with_ymd_and_hms(2024, 5, 21, 10, 0, 0).unwrap());
+    /// }
+    ///
+    /// // At 14:30, a typical Tuesday has 100 + half of 300 behind it
+    /// let trend = stats.pace_trend(Local.with_ymd_and_hms(2024, 5, 21, 14, 30, 0).unwrap()).unwrap();
+    /// assert_eq!((trend.so_far, trend.typical, trend.days), (236, 250.0, 2));
+    /// assert!((trend.change() + 0.056).abs() < 0.001);
+    ///
+    /// // Nothing to compare with before the first keystroke of a typical Tuesday
+    /// assert!(stats.pace_trend(Local.with_ymd_and_hms(2024, 5, 21, 8, 0, 0).unwrap()).is_none());
+    /// // And no Wednesdays recorded yet
+    /// assert!(stats.pace_trend(Local.with_ymd_and_hms(2024, 5, 22, 12, 0, 0).unwrap()).is_none());
+    /// ```
+    pub fn pace_trend(&self, now: DateTime<chrono::Local>) -> Option<PaceTrend> {
+        let date = now.date_naive();
+        let (curve, days) = self.typical_curve(date.weekday(), date)?;
+
+        let hour = now.hour() as usize;
+        let before_hour = hour.checked_sub(1).map_or(0.0, |previous| curve[previous]);
+        let hour_fraction = (now.minute() * 60 + now.second()) as f64 / 3600.0;
+        let typical = before_hour + (curve[hour] - before_hour) * hour_fraction;
+        if typical <= 0.0 {
+            return None;
+        }
+
+        let so_far = self
+            .daily_stats
+            .get(&day_key(date))
+            .map_or(0, |day| day.hourly_keystrokes.iter().take(hour + 1).sum());
+        Some(PaceTrend { so_far, typical, days })
+    }
+
+    /// Compares two periods side by side.
+    ///
+    /// # Arguments
+    /// * `a` - Baseline period as (first day, last day, label)
+    /// * `b` - Compared period as (first day, last day, label)
+    ///
+    /// # Returns
+    /// `DayComparison` - Both summaries plus per-key share changes
+    pub fn compare_periods(
+        &self,
+        a: (NaiveDate, NaiveDate, &str),
+        b: (NaiveDate, NaiveDate, &str),
+    ) -> DayComparison {
+        let a = self.summarize_period(a.0, a.1, a.2);
+        let b = self.summarize_period(b.0, b.1, b.2);
+
+        let change = |key: &String| KeyChange {
+            key: key.clone(),
+            share_a: a.share(key),
+            share_b: b.share(key),
+        };
+
+        let mut top: Vec<_> = b.key_distribution.iter().collect();
+        top.sort_by(|x, y| y.1.cmp(x.1).then(x.0.cmp(y.0)));
+        let top_keys = top.into_iter().take(5).map(|(k, _)| change(k)).collect();
+
+        let mut all_keys: Vec<&String> = a.key_distribution.keys().chain(b.key_distribution.keys()).collect();
+        all_keys.sort();
+        all_keys.dedup();
+        let mut biggest_movers: Vec<KeyChange> = all_keys.into_iter().map(change).collect();
+        biggest_movers.sort_by(|x, y| y.delta_points().abs().total_cmp(&x.delta_points().abs()));
+        biggest_movers.truncate(5);
+
+        DayComparison { a, b, top_keys, biggest_movers }
+    }
+
+    /// Compares two single days.
+    ///
+    /// # Arguments
+    /// * `a` - Baseline day
+    /// * `b` - Compared day
+    ///
+    /// # Returns
+    /// `DayComparison` - Comparison of the two days; missing days count as zero
+    pub fn diff_days(&self, a: NaiveDate, b: NaiveDate) -> DayComparison {
+        let label_a = a.format("%Y-%m-%d").to_string();
+        let label_b = b.format("%Y-%m-%d").to_string();
+        self.compare_periods((a, a, &label_a), (b, b, &label_b))
+    }
+
+    /// Adds keystroke counts for a key, optionally attributed to a day.
+    ///
+    /// Used when importing data from other tools. Counts without a date
+    /// only update the lifetime totals.
+    ///
+    /// # Arguments
+    /// * `key` - Stored key name (`KEY_<code>`)
+    /// * `count` - Number of presses to add
+    /// * `date` - Day the presses belong to, if known
+    ///
+    /// Counts saturate at `u64::MAX`, so a corrupt or hostile import file
+    /// can't make them wrap around.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::KeyStats;
+    ///
+    /// let mut stats = KeyStats::default();
+    /// stats.add_counts("KEY_30", u64::MAX, None);
+    /// stats.add_counts("KEY_30", 2, None);
+    /// assert_eq!(stats.key_counts["KEY_30"], u64::MAX);
+    /// assert_eq!(stats.total_keystrokes, u64::MAX);
+    /// ```
+    pub fn add_counts(&mut self, key: &str, count: u64, date: Option<NaiveDate>) {
+        let key_count = self.key_counts.entry(key.to_string()).or_insert(0);
+        *key_count = key_count.saturating_add(count);
+        self.total_keystrokes = self.total_keystrokes.saturating_add(count);
+
+        if let Some(date) = date {
+            let day = self.daily_stats
+                .entry(day_key(date))
+                .or_default();
+            day.keystrokes = day.keystrokes.saturating_add(count);
+            let day_count = day.key_distribution.entry(key.to_string()).or_insert(0);
+            *day_count = day_count.saturating_add(count);
+        }
+    }
+
+    /// Merges statistics from another data file into this one.
+    ///
+    /// Key counts and daily statistics are summed, sessions are appended
+    /// (skipping sessions already present), and each record keeps the better
+    /// of the two values. The current session is left untouched.
+    ///
+    /// # Arguments
+    /// * `other` - Statistics to merge in
+    pub fn merge(&mut self, other: &KeyStats) {
+        if self.is_empty() {
+            self.anonymized = other.anonymized;
+        }
+        self.case_sensitive |= other.case_sensitive;
+        for (key, count) in &other.key_counts {
+            *self.key_counts.entry(key.clone()).or_insert(0) += count;
+        }
+        self.total_keystrokes += other.total_keystrokes;
+        self.rollover.merge(&other.rollover);
+        for (device, count) in &other.device_counts {
+            *self.device_counts.entry(device.clone()).or_insert(0) += count;
+        }
+        for (device, counts) in &other.device_key_counts {
+            let merged = self.device_key_counts.entry(device.clone()).or_default();
+            for (key, count) in counts {
+                *merged.entry(key.clone()).or_insert(0) += count;
+            }
+        }
+        for (context, count) in &other.context_counts {
+            *self.context_counts.entry(context.clone()).or_insert(0) += count;
+        }
+        for (c, count) in &other.char_counts {
+            *self.char_counts.entry(c.clone()).or_insert(0) += count;
+        }
+        for (pair, count) in &other.bigram_counts {
+            *self.bigram_counts.entry(pair.clone()).or_insert(0) += count;
+        }
+
+        for (date, other_day) in &other.daily_stats {
+            let day = self.daily_stats.entry(date.clone()).or_default();
+            day.keystrokes += other_day.keystrokes;
+            day.sessions += other_day.sessions;
+            day.words += other_day.words;
+            day.lines += other_day.lines;
+            for (key, count) in &other_day.key_distribution {
+                *day.key_distribution.entry(key.clone()).or_insert(0) += count;
+            }
+            for (device, count) in &other_day.device_keystrokes {
+                *day.device_keystrokes.entry(device.clone()).or_insert(0) += count;
+            }
+            for (context, count) in &other_day.context_keystrokes {
+                *day.context_keystrokes.entry(context.clone()).or_insert(0) += count;
+            }
+            for (c, count) in &other_day.char_counts {
+                *day.char_counts.entry(c.clone()).or_insert(0) += count;
+            }
+            for (pair, count) in &other_day.bigram_counts {
+                *day.bigram_counts.entry(pair.clone()).or_insert(0) += count;
+            }
+            for (action, count) in &other_day.actions {
+                *day.actions.entry(action.clone()).or_insert(0) += count;
+            }
+            day.breaks_suggested += other_day.breaks_suggested;
+            day.breaks_taken += other_day.breaks_taken;
+            day.chars_typed += other_day.chars_typed;
+            day.chars_deleted += other_day.chars_deleted;
+            day.caps_lock_ms += other_day.caps_lock_ms;
+            day.caps_lock_letters += other_day.caps_lock_letters;
+            day.caps_lock_runs += other_day.caps_lock_runs;
+            day.coverage_seconds += other_day.coverage_seconds;
+            for (hours, other_hours) in [
+                (&mut day.hourly_keystrokes, &other_day.hourly_keystrokes),
+                (&mut day.hourly_chars, &other_day.hourly_chars),
+                (&mut day.hourly_active_ms, &other_day.hourly_active_ms),
+            ] {
+                for (hour, &count) in other_hours.iter().enumerate() {
+                    add_to_hour(hours, hour, count);
+                }
+            }
+            day.minute_keystrokes.merge(&other_day.minute_keystrokes);
+            if day.most_active_hour.is_none() {
+                day.most_active_hour = other_day.most_active_hour;
+            }
+        }
+
+        for session in &other.typing_sessions {
+            let duplicate = self.typing_sessions
+                .iter()
+                .any(|s| s.start == session.start && s.end == session.end);
+            if !duplicate {
+                self.typing_sessions.push(session.clone());
+            }
+        }
+        self.typing_sessions.sort_by_key(|s| s.start);
+        // Only from the later day on are both sides known to count sessions
+        self.sessions_counted_since = self.sessions_counted_since.max(other.sessions_counted_since);
+        self.last_export_at = self.last_export_at.max(other.last_export_at);
+
+        self.records.merge(&other.records);
+    }
+
+    /// Downsamples the minute data of days before `before` to hourly counts.
+    ///
+    /// # Arguments
+    /// * `before` - First day that keeps its minute data
+    ///
+    /// # Returns
+    /// `usize` - Number of days that were downsampled
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Local, NaiveDate, TimeZone};
+    /// use ctrlq::keylogger::{day_key, KeyStats};
+    /// use ctrlq::keymap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// for day in 1..=3 {
+    ///     let at = Local.with_ymd_and_hms(2024, 5, day, 9, 30, 0).unwrap();
+    ///     stats.add_keypress_at(&keymap::key_name(30), at);
+    /// }
+    ///
+    /// assert_eq!(stats.downsample_minutes(NaiveDate::from_ymd_opt(2024, 5, 3).unwrap()), 2);
+    /// let may_1 = &stats.daily_stats[&day_key(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap())];
+    /// assert!(may_1.minute_keystrokes.is_empty());
+    /// assert_eq!(may_1.hourly_keystrokes[9], 1);
+    /// let may_3 = &stats.daily_stats[&day_key(NaiveDate::from_ymd_opt(2024, 5, 3).unwrap())];
+    /// assert_eq!(may_3.minute_keystrokes.iter().collect::<Vec<_>>(), [(9 * 60 + 30, 1)]);
+    /// ```
+    pub fn downsample_minutes(&mut self, before: NaiveDate) -> usize {
+        let before = day_key(before);
+        let mut downsampled = 0;
+        for (date, day) in &mut self.daily_stats {
+            if date.as_str() < before.as_str() && day.downsample_minutes() {
+                downsampled += 1;
+            }
+        }
+        downsampled
+    }
+
+    /// Folds the counts of keys with codes evdev doesn't know into
+    /// [`keymap::UNKNOWN_KEY`], everywhere key names are counted.
+    ///
+    /// # Returns
+    /// `usize` - Number of distinct key names that were folded
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::KeyStats;
+    /// use ctrlq::keymap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// stats.add_keypress(&keymap::key_name(30));
+    /// // Left by a misbehaving device before codes were checked
+    /// for code in [900, 901, 901, 60000] {
+    ///     stats.add_keypress(&keymap::key_name(code));
+    /// }
+    ///
+    /// assert_eq!(stats.fold_junk_keys(), 3);
+    /// assert_eq!(stats.key_counts.len(), 2);
+    /// assert_eq!(stats.key_counts[keymap::UNKNOWN_KEY], 4);
+    /// let today = &stats.daily_stats[&ctrlq::keylogger::day_key(ctrlq::keylogger::today())];
+    /// assert_eq!(today.key_distribution[keymap::UNKNOWN_KEY], 4);
+    /// assert_eq!(stats.total_keystrokes, 5);
+    /// assert_eq!(stats.fold_junk_keys(), 0);
+    /// ```
+    pub fn fold_junk_keys(&mut self) -> usize {
+        let mut folded = HashSet::new();
+        fold_junk(&mut self.key_counts, &mut folded);
+        fold_junk(&mut self.session_key_counts, &mut folded);
+        for counts in self.device_key_counts.values_mut() {
+            fold_junk(counts, &mut folded);
+        }
+        for day in self.daily_stats.values_mut() {
+            fold_junk(&mut day.key_distribution, &mut folded);
+        }
+        for bucket in &mut self.timeline.buckets {
+            fold_junk(&mut bucket.keys, &mut folded);
+        }
+        for key in &mut self.key_sequences {
+            if keymap::is_junk_name(key) {
+                *key = keymap::UNKNOWN_KEY.to_string();
+            }
+        }
+        folded.len()
+    }
+
+    /// Checks whether a key would be one too many for `max_keys`, the
+    /// `max_tracked_keys` setting.
+    ///
+    /// # Arguments
+    /// * `key` - Stored name of a key about to be counted
+    /// * `max_keys` - Most distinct keys to track (0 = no limit)
+    ///
+    /// # Returns
+    /// `bool` - True if the key is new and the limit is reached; it is
+    /// then counted as [`keymap::UNKNOWN_KEY`]
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::KeyStats;
+    /// use ctrlq::keymap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// stats.add_keypress(&keymap::key_name(30));
+    /// stats.add_keypress(&keymap::key_name(48));
+    ///
+    /// assert!(stats.over_key_limit(&keymap::key_name(46), 2));
+    /// assert!(!stats.over_key_limit(&keymap::key_name(30), 2));
+    /// assert!(!stats.over_key_limit(keymap::UNKNOWN_KEY, 2));
+    /// assert!(!stats.over_key_limit(&keymap::key_name(46), 0));
+    /// ```
+    pub fn over_key_limit(&self, key: &str, max_keys: usize) -> bool {
+        max_keys > 0
+            && key != keymap::UNKNOWN_KEY
+            && self.key_counts.len() >= max_keys
+            && !self.key_counts.contains_key(key)
+    }
+
+    /// Gets the top N most frequently pressed keys.
+    ///
+    /// Returns a sorted list of keys by press count in descending order.
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum number of keys to return
+    ///
+    /// # Returns
+    /// `Vec<(String, u64)>` - List of (key, count) pairs
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::KeyStats;
+    /// use ctrlq::keymap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// for key in [30, 30, 48] {
+    ///     stats.add_keypress(&keymap::key_name(key));
+    /// }
+    ///
+    /// let top = stats.get_top_keys(1);
+    /// assert_eq!(keymap::display_name(&top[0].0), "A");
+    /// assert_eq!(top[0].1, 2);
+    /// ```
+    pub fn get_top_keys(&self, limit: usize) -> Vec<(String, u64)> {
+        self.get_top_keys_by_class(limit, None)
+    }
+
+    /// Gets the top N most frequently pressed keys within a key class.
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum number of keys to return
+    /// * `class` - Class to restrict to, or None for all keys
+    ///
+    /// # Returns
+    /// `Vec<(String, u64)>` - List of (key, count) pairs
+    pub fn get_top_keys_by_class(&self, limit: usize, class: Option<KeyClass>) -> Vec<(String, u64)> {
+        top_keys(&self.key_counts, limit, class)
+    }
+
+    /// Totals keystrokes per key class.
+    ///
+    /// Classes with no keystrokes are omitted. The result is ordered
+    /// by count, highest first.
+    ///
+    /// # Returns
+    /// `Vec<(KeyClass, u64)>` - List of (class, count) pairs
+    pub fn class_totals(&self) -> Vec<(KeyClass, u64)> {
+        let mut totals: HashMap<KeyClass, u64> = HashMap::new();
+        for (key, count) in &self.key_counts {
+            *totals.entry(keymap::classify_name(key)).or_insert(0) += count;
+        }
+
+        let mut sorted: Vec<_> = totals.into_iter().collect();
+        sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        sorted
+    }
+
+    /// Totals keystrokes per category: letters, code symbols, prose
+    /// punctuation, whitespace and navigation, see [`keymap::categorize`].
+    ///
+    /// # Returns
+    /// `CategoryBreakdown` - Keystrokes per category over all keys
+    pub fn get_category_breakdown(&self) -> CategoryBreakdown {
+        CategoryBreakdown::of(&self.key_counts)
+    }
+
+    /// Updates personal records from the current session and today's totals.
+    ///
+    /// Called by the logging loop after processing new events. Rolling WPM
+    /// records are computed from character keystrokes in the last one and
+    /// five minutes.
+    pub fn update_records(&mut self) {
+        let now = Utc::now();
+        let now_secs = now.timestamp();
+
+        while let Some((second, _)) = self.recent_chars.front() {
+            if now_secs - second >= 300 {
+                self.recent_chars.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let chars_5m: u32 = self.recent_chars.iter().map(|(_, c)| c).sum();
+        let chars_1m: u32 = self.recent_chars
+            .iter()
+            .filter(|(second, _)| now_secs - second < 60)
+            .map(|(_, c)| c)
+            .sum();
+
+        let wpm_1m = chars_1m as f64 / 5.0;
+        let wpm_5m = chars_5m as f64 / 5.0 / 5.0;
+
+        if wpm_1m > 0.0 && self.records.best_wpm_1m.is_none_or(|r| wpm_1m > r.value) {
+            self.records.best_wpm_1m = Some(Record { value: wpm_1m, achieved: now });
+        }
+        if wpm_5m > 0.0 && self.records.best_wpm_5m.is_none_or(|r| wpm_5m > r.value) {
+            self.records.best_wpm_5m = Some(Record { value: wpm_5m, achieved: now });
+        }
+
+        let today = day_key(now.with_timezone(&chrono::Local).date_naive());
+        if let Some(day) = self.daily_stats.get(&today)
+            && self.records.most_keystrokes_day.is_none_or(|r| day.keystrokes > r.value)
+        {
+            self.records.most_keystrokes_day = Some(Record { value: day.keystrokes, achieved: now });
+        }
+
+        if self.session_keystrokes > 0 {
+            let secs = now.signed_duration_since(self.session_start).num_seconds().max(0) as u64;
+            if self.records.longest_session_secs.is_none_or(|r| secs > r.value) {
+                self.records.longest_session_secs = Some(Record { value: secs, achieved: self.session_start });
+            }
+        }
+    }
+
+    /// Starts a new session, clearing the per-session counters.
+    pub fn start_session(&mut self) {
+        self.session_start = Utc::now();
+        self.session_keystrokes = 0;
+        self.char_keystrokes = 0;
+        self.corrections = 0;
+        self.session_words = 0;
+        self.recent_chars.clear();
+        self.session_key_counts.clear();
+        self.session_context_counts.clear();
+        self.session_device_counts.clear();
+        self.session_timeline.clear();
+        self.session_first_key = None;
+        self.session_warmup = [0; WARMUP_MINUTES];
+        self.session_tag = None;
+        self.session_note = None;
+    }
+
+    /// Closes the current session and records it in `typing_sessions`.
+    ///
+    /// Sessions without any keystrokes are not recorded. A new session
+    /// is started afterwards.
+    pub fn end_session(&mut self) {
+        if self.session_keystrokes > 0 {
+            let session = self.current_session();
+            self.count_session(&session);
+            self.typing_sessions.push(session);
+        }
+        self.start_session();
+    }
+
+    /// Counts a closed session in `sessions` of the day it started on, or
+    /// of the day of its first keystroke if nothing was typed on that day.
+    fn count_session(&mut self, session: &TypingSession) {
+        let start = session.start.with_timezone(&chrono::Local).date_naive();
+        let day = if self.daily_stats.contains_key(&day_key(start)) {
+            start
+        } else {
+            self.session_first_key.unwrap_or(session.end).with_timezone(&chrono::Local).date_naive()
+        };
+        self.sessions_counted_since.get_or_insert(start);
+        self.daily_stats.entry(day_key(day)).or_default().sessions += 1;
+    }
+
+    /// Splits the session around a clock jump, so a suspend gap or clock
+    /// change never counts as session time.
+    ///
+    /// The current session is closed at `jump.before` and a new one starts
+    /// at `jump.after`.
+    ///
+    /// # Arguments
+    /// * `jump` - The detected jump, see [`ClockMonitor`]
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, Utc};
+    /// use ctrlq::keylogger::{ClockJump, KeyStats};
+    /// use ctrlq::keymap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// stats.add_keypress(&keymap::key_name(30));
+    ///
+    /// // Suspended for eight hours
+    /// let before = stats.session_start + Duration::minutes(10);
+    /// let after = before + Duration::hours(8);
+    /// stats.split_session_at(ClockJump { before, after });
+    ///
+    /// assert_eq!(stats.typing_sessions[0].end, before);
+    /// assert_eq!(stats.typing_sessions[0].duration(), Duration::minutes(10));
+    /// assert_eq!(stats.session_start, after);
+    /// assert_eq!(stats.session_keystrokes, 0);
+    /// ```
+    pub fn split_session_at(&mut self, jump: ClockJump) {
+        if self.session_keystrokes > 0 {
+            // A backward jump can put the new start before the old one
+            let end = jump.before.max(self.session_start);
+            let session = self.session_until(end);
+            self.count_session(&session);
+            self.typing_sessions.push(session);
+        }
+        self.start_session();
+        self.session_start = jump.after;
+    }
+
+    /// Returns the current session as if it ended now.
+    pub fn current_session(&self) -> TypingSession {
+        self.session_until(Utc::now())
+    }
+
+    /// Returns the session in progress as saved in a data file by a
+    /// running logger, ending at `now`.
+    ///
+    /// The session's own per-minute counts and keys are only kept in
+    /// memory, so its timeline is taken from the days' minute data instead
+    /// and its top keys are empty.
+    ///
+    /// # Returns
+    /// `Option<TypingSession>` - The session, or None without keystrokes in it
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, Utc};
+    /// use ctrlq::keylogger::{KeyStats, TypingSession};
+    /// use ctrlq::keymap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// assert!(stats.open_session(Utc::now()).is_none());
+    /// for _ in 0..3 {
+    ///     stats.add_keypress(&keymap::key_name(30));
+    /// }
+    /// // As loaded from a data file, without the in-memory timeline
+    /// let mut saved: KeyStats = serde_json::from_str(&serde_json::to_string(&stats).unwrap()).unwrap();
+    /// saved.daily_stats = stats.daily_stats.clone();
+    /// let session = saved.open_session(Utc::now() + Duration::seconds(1)).unwrap();
+    /// assert_eq!(session.keystrokes, 3);
+    /// assert_eq!(session.timeline.iter().sum::<u32>(), 3);
+    /// ```
+    pub fn open_session(&self, now: DateTime<Utc>) -> Option<TypingSession> {
+        if self.session_keystrokes == 0 {
+            return None;
+        }
+        let mut session = self.session_until(now);
+        if session.timeline.is_empty() {
+            let minutes = now.signed_duration_since(self.session_start).num_minutes().max(0);
+            session.timeline = (0..=minutes)
+                .map(|minute| {
+                    let at = (self.session_start + chrono::Duration::minutes(minute)).with_timezone(&chrono::Local);
+                    let minute_of_day = (at.hour() * 60 + at.minute()) as u16;
+                    self.daily_stats
+                        .get(&day_key(at.date_naive()))
+                        .map_or(0, |day| day.minute_keystrokes.get(minute_of_day))
+                })
+                .collect();
+        }
+        Some(session)
+    }
+
+    /// Returns the current session as if it ended at `end`.
+    fn session_until(&self, end: DateTime<Utc>) -> TypingSession {
+        let mut top_keys: Vec<(String, u64)> = self.session_key_counts
+            .iter()
+            .map(|(key, &count)| (key.clone(), count))
+            .collect();
+        top_keys.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_keys.truncate(SESSION_TOP_KEYS);
+
+        let wpm = Self::wpm(self.char_keystrokes, self.session_start, end);
+        let kept = self.char_keystrokes.saturating_sub(self.corrections);
+        TypingSession {
+            start: self.session_start,
+            end,
+            keystrokes: self.session_keystrokes,
+            wpm,
+            net_wpm: wpm.and(Self::wpm(kept, self.session_start, end).or(Some(0.0))),
+            words: self.session_words,
+            real_wpm: Self::real_wpm(self.session_words, self.session_start, end),
+            corrections: self.corrections,
+            top_keys,
+            timeline: self.session_timeline.clone(),
+            contexts: sorted_counts(&self.session_context_counts),
+            first_key_secs: self.session_first_key
+                .map(|first_key| first_key.signed_duration_since(self.session_start).num_seconds().max(0) as u64),
+            warmup: self.session_warmup,
+            tag: self.session_tag.clone(),
+            note: self.session_note.clone(),
+            categories: CategoryBreakdown::of(&self.session_key_counts),
+            dock: Dock::of_session(&self.session_device_counts, self.device_counts.keys()),
+        }
+    }
+
+    /// Resets all statistics to their initial state.
+    ///
+    /// Clears all keystroke counts, resets session timing,
+    /// and reinitializes all tracking data, including records.
+    pub fn reset(&mut self) {
+        self.reset_keeping_records();
+        self.records = Records::default();
+    }
+
+    /// Checks whether no keystrokes have been recorded at all.
+    pub fn is_empty(&self) -> bool {
+        self.total_keystrokes == 0 && self.key_counts.is_empty()
+    }
+
+    /// Resets all statistics like [`KeyStats::reset`] but keeps personal records.
+    pub fn reset_keeping_records(&mut self) {
+        self.key_counts.clear();
+        self.device_counts.clear();
+        self.device_key_counts.clear();
+        self.context_counts.clear();
+        self.char_counts.clear();
+        self.bigram_counts.clear();
+        self.rollover = Rollover::default();
+        self.key_sequences.clear();
+        self.key_sequence_times.clear();
+        self.recent_keys = RecentKeys::default();
+        self.session_start = Utc::now();
+        self.total_keystrokes = 0;
+        self.typing_sessions.clear();
+        self.daily_stats.clear();
+        self.timeline = DayTimeline::default();
+        // Nothing of what is counted from now on was exported
+        self.last_export_at = None;
+        self.start_session();
+    }
+
+    /// Finalizes a finished day when the logger runs past midnight.
+    ///
+    /// Fills in the day's `most_active_hour`, makes sure `sessions` covers
+    /// the sessions that closed and started that day, and clears the day
+    /// timeline, so views of "today" start empty. Keystrokes after midnight
+    /// are already filed under the new day, and a session running across
+    /// midnight is counted for its first day when it closes.
+    ///
+    /// # Arguments
+    /// * `date` - The day that just ended
+    ///
+    /// # Returns
+    /// `u64` - Keystrokes of the finished day
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Local, TimeZone};
+    /// use ctrlq::keylogger::{day_key, KeyStats};
+    /// use ctrlq::keymap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// let before = Local.with_ymd_and_hms(2024, 5, 1, 23, 59, 0).unwrap();
+    /// let after = Local.with_ymd_and_hms(2024, 5, 2, 0, 1, 0).unwrap();
+    /// stats.add_keypress_at(&keymap::key_name(30), before);
+    /// stats.add_keypress_at(&keymap::key_name(30), before);
+    /// stats.add_keypress_at(&keymap::key_name(31), after);
+    ///
+    /// assert_eq!(stats.finish_day(before.date_naive()), 2);
+    /// let may_1 = &stats.daily_stats[&day_key(before.date_naive())];
+    /// assert_eq!(may_1.most_active_hour, Some(23));
+    /// assert_eq!(stats.daily_stats[&day_key(after.date_naive())].keystrokes, 1);
+    /// ```
+    pub fn finish_day(&mut self, date: NaiveDate) -> u64 {
+        let started_that_day = |start: &DateTime<Utc>| start.with_timezone(&chrono::Local).date_naive() == date;
+        let sessions = self.typing_sessions.iter().filter(|s| started_that_day(&s.start)).count() as u64;
+
+        if self.timeline.date == day_key(date) {
+            self.timeline = DayTimeline::default();
+        }
+
+        let Some(day) = self.daily_stats.get_mut(&day_key(date)) else {
+            return 0;
+        };
+        day.most_active_hour = day.busiest_hour().or(day.most_active_hour);
+        day.sessions = day.sessions.max(sessions);
+        day.keystrokes
+    }
+
+    /// Clears today's statistics and takes them out of the lifetime totals.
+    ///
+    /// Sessions and records are kept. A new session is started.
+    pub fn reset_today(&mut self) {
+        if let Some(day) = self.daily_stats.remove(&day_key(today())) {
+            self.total_keystrokes = self.total_keystrokes.saturating_sub(day.keystrokes);
+            for (key, count) in &day.key_distribution {
+                if let Some(total) = self.key_counts.get_mut(key) {
+                    *total = total.saturating_sub(*count);
+                }
+            }
+            for (device, count) in &day.device_keystrokes {
+                if let Some(total) = self.device_counts.get_mut(device) {
+                    *total = total.saturating_sub(*count);
+                }
+            }
+            for (context, count) in &day.context_keystrokes {
+                if let Some(total) = self.context_counts.get_mut(context) {
+                    *total = total.saturating_sub(*count);
+                }
+            }
+            for (c, count) in &day.char_counts {
+                if let Some(total) = self.char_counts.get_mut(c) {
+                    *total = total.saturating_sub(*count);
+                }
+            }
+            for (pair, count) in &day.bigram_counts {
+                if let Some(total) = self.bigram_counts.get_mut(pair) {
+                    *total = total.saturating_sub(*count);
+                }
+            }
+            self.key_counts.retain(|_, count| *count > 0);
+            self.device_counts.retain(|_, count| *count > 0);
+            self.context_counts.retain(|_, count| *count > 0);
+            self.char_counts.retain(|_, count| *count > 0);
+            self.bigram_counts.retain(|_, count| *count > 0);
+        }
+        self.timeline = DayTimeline::default();
+        self.key_sequences.clear();
+        self.key_sequence_times.clear();
+        self.recent_keys = RecentKeys::default();
+        self.start_session();
+    }
+
+    /// Describes what a reset of the given scope would remove.
+    ///
+    /// For [`ResetScope::All`] archived days are only covered if they were
+    /// loaded, see [`crate::archive::load_into`].
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::{KeyStats, ResetScope};
+    /// use ctrlq::keymap;
+    ///
+    /// let mut stats = KeyStats::new();
+    /// for _ in 0..3 {
+    ///     stats.add_keypress(&keymap::key_name(30));
+    /// }
+    /// let loss = stats.reset_loss(ResetScope::All { keep_records: false });
+    /// assert_eq!((loss.keystrokes, loss.sessions), (3, 1));
+    /// assert!(loss.backup);
+    /// assert_eq!(loss.export_line(), "Never exported");
+    ///
+    /// assert!(!stats.reset_loss(ResetScope::Session).backup);
+    /// ```
+    pub fn reset_loss(&self, scope: ResetScope) -> ResetLoss {
+        let today = today();
+        // The session in progress is lost by every reset
+        let open = u64::from(self.session_keystrokes > 0);
+        let (keystrokes, days, sessions) = match scope {
+            ResetScope::Session => {
+                let start = self.session_start.with_timezone(&chrono::Local).date_naive();
+                (self.session_keystrokes, (open > 0).then_some((start, today)), open)
+            }
+            ResetScope::Today => match self.daily_stats.get(&day_key(today)) {
+                Some(day) => (day.keystrokes, Some((today, today)), day.sessions + open),
+                None => (0, None, open),
+            },
+            ResetScope::All { .. } => {
+                let mut dates = self
+                    .daily_stats
+                    .iter()
+                    .filter(|(_, day)| day.keystrokes > 0)
+                    .filter_map(|(date, _)| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+                let first = dates.next().map(|date| (date, date));
+                let days = dates.fold(first, |range, date| range.map(|(from, to)| (from.min(date), to.max(date))));
+                (self.total_keystrokes, days, self.typing_sessions.len() as u64 + open)
+            }
+        };
+        ResetLoss {
+            keystrokes,
+            days,
+            sessions,
+            backup: matches!(scope, ResetScope::All { .. }),
+            last_export_at: self.last_export_at,
+        }
+    }
+
+    /// Applies a reset of the given scope.
+    ///
+    /// Used by the logging loop and by `ctrlq reset` when no instance is
+    /// running, so both behave the same.
+    pub fn apply_reset(&mut self, scope: ResetScope) {
+        match scope {
+            ResetScope::Session => self.start_session(),
+            ResetScope::Today => self.reset_today(),
+            ResetScope::All { keep_records: true } => self.reset_keeping_records(),
+            ResetScope::All { keep_records: false } => self.reset(),
+        }
+    }
+}
+
+/// What a reset clears.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetScope {
+    /// Restart the current session (WPM measurement, session counters)
+    Session,
+    /// Remove today's statistics
+    Today,
+    /// Remove everything, optionally keeping personal records
+    All {
+        /// Whether personal records survive
+        keep_records: bool,
+    },
+}
+
+impl ResetScope {
+    /// Parses a scope name as given on the command line or over IPC.
+    pub fn parse(name: &str, keep_records: bool) -> Result<Self> {
+        match name {
+            "session" => Ok(ResetScope::Session),
+            "today" => Ok(ResetScope::Today),
+            "all" => Ok(ResetScope::All { keep_records }),
+            _ => bail!("unknown reset scope '{}': expected session, today or all", name),
+        }
+    }
+
+    /// Name of the scope as accepted by [`ResetScope::parse`].
+    pub fn name(self) -> &'static str {
+        match self {
+            ResetScope::Session => "session",
+            ResetScope::Today => "today",
+            ResetScope::All { .. } => "all",
+        }
+    }
+
+    /// Message shown after the reset was applied.
+    pub fn done_message(self) -> &'static str {
+        match self {
+            ResetScope::Session => "🔄 Session restarted",
+            ResetScope::Today => "🔄 Today's statistics reset",
+            ResetScope::All { keep_records: true } => "🔄 Statistics reset (personal records kept)",
+            ResetScope::All { keep_records: false } => "🔄 Statistics reset",
+        }
+    }
+}
+
+/// What a reset is about to remove, see [`KeyStats::reset_loss`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResetLoss {
+    /// Keystrokes removed
+    pub keystrokes: u64,
+    /// First and last day with keystrokes that are removed
+    pub days: Option<(NaiveDate, NaiveDate)>,
+    /// Typing sessions removed, the one in progress included
+    pub sessions: u64,
+    /// Whether a backup of the data file is written first
+    pub backup: bool,
+    /// When the statistics were last exported, see [`KeyStats::last_export_at`]
+    pub last_export_at: Option<DateTime<Utc>>,
+}
+
+impl ResetLoss {
+    /// Returns the keystrokes, sessions and days removed, e.g. `12,000
+    /// keystrokes in 42 sessions, 2024-03-01 to 2024-05-20`.
+    pub fn summary(&self) -> String {
+        let sessions = if self.sessions == 1 { "1 session".to_string() } else { format!("{} sessions", numbers::format_count(self.sessions)) };
+        let days = match self.days {
+            Some((from, to)) if from == to => format!(", {}", from),
+            Some((from, to)) => format!(", {} to {}", from, to),
+            None => String::new(),
+        };
+        format!("{} keystrokes in {}{}", numbers::format_count(self.keystrokes), sessions, days)
+    }
+
+    /// Says whether a backup is written first.
+    pub fn backup_line(&self) -> &'static str {
+        if self.backup { "A backup of the data file is written first" } else { "No backup is written" }
+    }
+
+    /// Says when the statistics were last exported, in local time.
+    pub fn export_line(&self) -> String {
+        match self.last_export_at {
+            Some(at) => format!("Last exported {}", at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")),
+            None => "Never exported".to_string(),
+        }
+    }
+}
+
+/// A jump of the wall clock relative to monotonic time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockJump {
+    /// Last wall-clock time seen before the jump
+    pub before: DateTime<Utc>,
+    /// Wall-clock time seen after the jump
+    pub after: DateTime<Utc>,
+}
+
+impl ClockJump {
+    /// How far the wall clock jumped beyond the monotonic time that passed
+    /// (negative for a clock set backwards).
+    pub fn gap(&self) -> chrono::Duration {
+        self.after.signed_duration_since(self.before)
+    }
+}
+
+/// Detects suspend/resume and clock changes by comparing how far the wall
+/// clock and the monotonic clock moved between two checks.
+///
+/// The monotonic clock stops while the machine is suspended and ignores
+/// clock adjustments, so a large difference means the wall-clock time in
+/// between was not spent logging.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockMonitor {
+    /// Wall-clock time of the last check
+    last_wall: DateTime<Utc>,
+    /// Monotonic time of the last check
+    last_mono: Instant,
+}
+
+impl ClockMonitor {
+    /// Starts monitoring from the given pair of clock readings.
+    pub fn new(wall: DateTime<Utc>, mono: Instant) -> Self {
+        Self { last_wall: wall, last_mono: mono }
+    }
+
+    /// Compares new clock readings with the previous ones.
+    ///
+    /// # Arguments
+    /// * `wall` - Current wall-clock time
+    /// * `mono` - Current monotonic time
+    ///
+    /// # Returns
+    /// `Option<ClockJump>` - The jump, if the clocks diverged by more than
+    /// [`CLOCK_JUMP_THRESHOLD`] in either direction
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Duration, TimeZone, Utc};
+    /// use ctrlq::keylogger::ClockMonitor;
+    /// use std::time::{Duration as StdDuration, Instant};
+    ///
+    /// let wall = Utc.with_ymd_and_hms(2024, 5, 1, 23, 0, 0).unwrap();
+    /// let mono = Instant::now();
+    /// let mut monitor = ClockMonitor::new(wall, mono);
+    ///
+    /// // Both clocks move together: no jump
+    /// let tick = StdDuration::from_secs(1);
+    /// assert_eq!(monitor.check(wall + Duration::seconds(1), mono + tick), None);
+    ///
+    /// // Overnight suspend: the wall clock moves 8 hours, the monotonic clock 10ms
+    /// let resumed = wall + Duration::hours(8);
+    /// let jump = monitor.check(resumed, mono + tick + StdDuration::from_millis(10)).unwrap();
+    /// assert_eq!(jump.before, wall + Duration::seconds(1));
+    /// assert_eq!(jump.after, resumed);
+    ///
+    /// // The clock is set back an hour
+    /// let mono = mono + StdDuration::from_secs(2);
+    /// let jump = monitor.check(resumed - Duration::hours(1), mono).unwrap();
+    /// assert!(jump.gap() < Duration::zero());
+    /// ```
+    pub fn check(&mut self, wall: DateTime<Utc>, mono: Instant) -> Option<ClockJump> {
+        let mono_elapsed = mono.saturating_duration_since(self.last_mono);
+        let wall_elapsed = wall.signed_duration_since(self.last_wall);
+        let expected = chrono::Duration::from_std(mono_elapsed).unwrap_or(chrono::Duration::MAX);
+        let threshold = chrono::Duration::from_std(CLOCK_JUMP_THRESHOLD).unwrap_or(chrono::Duration::MAX);
+
+        let jump = ((wall_elapsed - expected).abs() > threshold)
+            .then_some(ClockJump { before: self.last_wall, after: wall });
+        self.last_wall = wall;
+        self.last_mono = mono;
+        jump
+    }
+}
+
+/// Paces the logging loop: fast while keys arrive, slower once nothing
+/// has happened for a while, so an idle logger rarely wakes up.
+#[derive(Debug, Clone, Copy)]
+pub struct Cadence {
+    /// How long without activity counts as idle
+    idle_after: Duration,
+    /// When the last activity happened
+    last_activity: Instant,
+}
+
+impl Cadence {
+    /// Starts out active, as if something had just happened at `now`.
+    ///
+    /// # Arguments
+    /// * `idle_after` - Time without activity after which the loop slows down
+    /// * `now` - Current monotonic time
+    pub fn new(idle_after: Duration, now: Instant) -> Self {
+        Self { idle_after, last_activity: now }
+    }
+
+    /// Changes the idle threshold, e.g. after a config reload.
+    pub fn set_idle_after(&mut self, idle_after: Duration) {
+        self.idle_after = idle_after;
+    }
+
+    /// Records activity, returning to the fast pace immediately.
+    pub fn activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// Whether nothing has happened for longer than the idle threshold.
+    pub fn is_idle(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_activity) > self.idle_after
+    }
+
+    /// How long to sleep before polling the devices again.
+    pub fn poll_interval(&self, now: Instant) -> Duration {
+        if self.is_idle(now) { IDLE_POLL_INTERVAL } else { ACTIVE_POLL_INTERVAL }
+    }
+
+    /// Checks whether an unchanged snapshot should still be sent, so that
+    /// listeners see time pass.
+    ///
+    /// # Arguments
+    /// * `now` - Current monotonic time
+    /// * `last_sent` - When the previous snapshot was sent
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::Cadence;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let start = Instant::now();
+    /// let mut cadence = Cadence::new(Duration::from_secs(5), start);
+    ///
+    /// // An idle minute with no events at all
+    /// let (mut now, mut last_sent, mut wakeups, mut sends) = (start, start, 0, 0);
+    /// while now < start + Duration::from_secs(60) {
+    ///     now += cadence.poll_interval(now);
+    ///     wakeups += 1;
+    ///     if cadence.snapshot_due(now, last_sent) {
+    ///         last_sent = now;
+    ///         sends += 1;
+    ///     }
+    /// }
+    /// // 20 snapshots in the first 5s, then one every 2s, instead of 240
+    /// assert!(sends <= 50, "{} snapshots", sends);
+    /// assert!(wakeups < 1200, "{} wakeups", wakeups);
+    ///
+    /// // The first key event brings back the fast pace
+    /// cadence.activity(now);
+    /// assert_eq!(cadence.poll_interval(now), Duration::from_millis(10));
+    /// ```
+    pub fn snapshot_due(&self, now: Instant, last_sent: Instant) -> bool {
+        let interval = if self.is_idle(now) { IDLE_SNAPSHOT_INTERVAL } else { ACTIVE_SNAPSHOT_INTERVAL };
+        now.saturating_duration_since(last_sent) >= interval
+    }
+}
+
+/// Watches for devices that go silent, which usually means another
+/// process (a Wayland compositor, a remapper) grabbed them with `EVIOCGRAB`
+/// and ctrlq no longer sees any events.
+#[derive(Debug, Clone, Copy)]
+pub struct IdleWatchdog {
+    /// Silence after which the devices are probed (None = disabled)
+    warn_after: Option<Duration>,
+    /// When the last key event arrived
+    last_event: Instant,
+    /// When the devices were last probed in this stretch of silence
+    last_probe: Option<Instant>,
+    /// Whether a warning is showing for this stretch of silence
+    warned: bool,
+}
+
+impl IdleWatchdog {
+    /// Starts watching as if an event had just arrived at `now`.
+    ///
+    /// # Arguments
+    /// * `warn_after` - Silence after which to probe, or None to never warn
+    /// * `now` - Current monotonic time
+    pub fn new(warn_after: Option<Duration>, now: Instant) -> Self {
+        Self { warn_after, last_event: now, last_probe: None, warned: false }
+    }
+
+    /// Changes the threshold, e.g. after a config reload.
+    pub fn set_warn_after(&mut self, warn_after: Option<Duration>) {
+        self.warn_after = warn_after;
+    }
+
+    /// Records a key event.
+    ///
+    /// # Returns
+    /// `Option<Duration>` - The silence that just ended, if a warning was
+    /// showing for it
+    pub fn activity(&mut self, now: Instant) -> Option<Duration> {
+        let silence = now.saturating_duration_since(self.last_event);
+        let was_warned = std::mem::take(&mut self.warned);
+        self.last_event = now;
+        self.last_probe = None;
+        was_warned.then_some(silence)
+    }
+
+    /// Checks whether the devices should be probed now: after `warn_after`
+    /// of silence, and again every `warn_after` while probes find nothing
+    /// wrong. Nothing is due once a warning is showing.
+    ///
+    /// # Returns
+    /// `Option<Duration>` - How long the devices have been silent, if a probe is due
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::IdleWatchdog;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let start = Instant::now();
+    /// let minutes = |m: u64| start + Duration::from_secs(m * 60);
+    /// let mut watchdog = IdleWatchdog::new(Some(Duration::from_secs(30 * 60)), start);
+    ///
+    /// assert_eq!(watchdog.probe_due(minutes(29)), None);
+    /// assert_eq!(watchdog.probe_due(minutes(32)), Some(Duration::from_secs(32 * 60)));
+    /// // Probed and found idle, not grabbed: the next probe is 30 min later
+    /// assert_eq!(watchdog.probe_due(minutes(40)), None);
+    /// assert!(watchdog.probe_due(minutes(62)).is_some());
+    ///
+    /// // This time the probe found a grab, so a warning shows until typing resumes
+    /// watchdog.warn();
+    /// assert_eq!(watchdog.probe_due(minutes(95)), None);
+    /// assert_eq!(watchdog.activity(minutes(100)), Some(Duration::from_secs(100 * 60)));
+    /// assert_eq!(watchdog.activity(minutes(101)), None);
+    ///
+    /// // --warn-idle-minutes 0 turns it off
+    /// let mut off = IdleWatchdog::new(None, start);
+    /// assert_eq!(off.probe_due(minutes(600)), None);
+    /// ```
+    pub fn probe_due(&mut self, now: Instant) -> Option<Duration> {
+        let warn_after = self.warn_after?;
+        let silence = now.saturating_duration_since(self.last_event);
+        let since_probe = self.last_probe.map_or(silence, |probe| now.saturating_duration_since(probe));
+        if self.warned || silence < warn_after || since_probe < warn_after {
+            return None;
+        }
+        self.last_probe = Some(now);
+        Some(silence)
+    }
+
+    /// Marks the current stretch of silence as warned about.
+    pub fn warn(&mut self) {
+        self.warned = true;
+    }
+}
+
+/// What [`BreakReminder`] noticed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakEvent {
+    /// Typing went on for the reminder period without a break; carries the
+    /// typing time so far
+    Due(Duration),
+    /// A break of [`BREAK_LENGTH`] was taken after one was due
+    Taken,
+}
+
+/// Suggests a break after long stretches of typing, the RSI guard.
+///
+/// Keystrokes less than [`BREAK_ACTIVE_GAP`] apart add their gap to the
+/// typing time of the current block; longer pauses add nothing but don't
+/// end the block either, so reading a page or a short phone call doesn't
+/// count as a rest. Only a pause of [`BREAK_LENGTH`] does: it ends the
+/// block and, if a break was due, counts as taken. A block is reminded of
+/// once, however long it goes on.
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::{BreakEvent, BreakReminder};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let secs = |s: u64| start + Duration::from_secs(s);
+/// let mut reminder = BreakReminder::new(Some(Duration::from_secs(50 * 60)));
+///
+/// // A keystroke every 10 seconds for 50 minutes
+/// let mut events = Vec::new();
+/// for s in (0..=50 * 60).step_by(10) {
+///     events.extend(reminder.key_press(secs(s)));
+/// }
+/// assert_eq!(events, vec![BreakEvent::Due(Duration::from_secs(50 * 60))]);
+/// assert!(reminder.is_due());
+///
+/// // No second reminder for the same block, even after another hour, and
+/// // a four-minute pause is not a break
+/// let mut events = Vec::new();
+/// for s in (50 * 60 + 240..=110 * 60).step_by(10) {
+///     events.extend(reminder.key_press(secs(s)));
+/// }
+/// assert!(events.is_empty());
+///
+/// // Five minutes away is a break: the reminder clears and the next block starts over
+/// assert_eq!(reminder.check(secs(115 * 60)), Some(BreakEvent::Taken));
+/// assert!(!reminder.is_due());
+/// assert_eq!(reminder.key_press(secs(116 * 60)), None);
+/// assert_eq!(reminder.active(), Duration::ZERO);
+///
+/// // Keystrokes a minute apart are no sustained typing
+/// let mut slow = BreakReminder::new(Some(Duration::from_secs(50 * 60)));
+/// for minute in 0..120 {
+///     assert_eq!(slow.key_press(secs(minute * 60)), None);
+/// }
+/// assert_eq!(slow.active(), Duration::ZERO);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BreakReminder {
+    /// Typing time after which a break is due (None = no reminders)
+    remind_after: Option<Duration>,
+    /// Typing time of the current block
+    active: Duration,
+    /// When the last keystroke was pressed (None = no block going on)
+    last_press: Option<Instant>,
+    /// Whether a break was suggested for the current block
+    due: bool,
+}
+
+impl BreakReminder {
+    /// Starts without a block of typing.
+    ///
+    /// # Arguments
+    /// * `remind_after` - Typing time after which a break is due, None for no reminders
+    pub fn new(remind_after: Option<Duration>) -> Self {
+        Self { remind_after, active: Duration::ZERO, last_press: None, due: false }
+    }
+
+    /// Changes the reminder period, e.g. after a config reload. A due break
+    /// stays due until it is taken.
+    pub fn set_remind_after(&mut self, remind_after: Option<Duration>) {
+        self.remind_after = remind_after;
+    }
+
+    /// Records a keystroke.
+    ///
+    /// # Arguments
+    /// * `now` - Current monotonic time
+    ///
+    /// # Returns
+    /// `Option<BreakEvent>` - Taken if the pause before it was a break after
+    /// one was due, Due if the block just reached the reminder period
+    pub fn key_press(&mut self, now: Instant) -> Option<BreakEvent> {
+        let taken = self.check(now);
+        if let Some(last) = self.last_press {
+            let gap = now.saturating_duration_since(last);
+            if gap < BREAK_ACTIVE_GAP {
+                self.active += gap;
+            }
+        }
+        self.last_press = Some(now);
+        if taken.is_none()
+            && !self.due
+            && let Some(after) = self.remind_after
+            && self.active >= after
+        {
+            self.due = true;
+            return Some(BreakEvent::Due(self.active));
+        }
+        taken
+    }
+
+    /// Ends the block once the pause since the last keystroke is a break.
+    ///
+    /// # Arguments
+    /// * `now` - Current monotonic time
+    ///
+    /// # Returns
+    /// `Option<BreakEvent>` - Taken if a break was due, None otherwise
+    pub fn check(&mut self, now: Instant) -> Option<BreakEvent> {
+        let last = self.last_press?;
+        if now.saturating_duration_since(last) < BREAK_LENGTH {
+            return None;
+        }
+        let was_due = self.due;
+        *self = Self::new(self.remind_after);
+        was_due.then_some(BreakEvent::Taken)
+    }
+
+    /// Whether a break is due and not taken yet.
+    pub fn is_due(&self) -> bool {
+        self.due
+    }
+
+    /// Typing time of the current block.
+    pub fn active(&self) -> Duration {
+        self.active
+    }
+}
+
+/// A keyboard the logger reads right now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggedDevice {
+    /// Device path as given, e.g. a `by-id` link or `/dev/input/event3`
+    pub path: String,
+    /// Its label, see [`device_labels`]
+    pub label: String,
+}
+
+/// Another keyboard that was typed on while the logged one stayed silent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceSuggestion {
+    /// Event device path of the keyboard
+    pub path: String,
+    /// Its label, see [`device_labels`]
+    pub label: String,
+    /// Character key presses it saw
+    pub presses: u64,
+}
+
+/// What [`SilentDeviceCheck::verdict`] found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SilentDeviceVerdict {
+    /// Still within the first minutes and the logged keyboard is quiet so far
+    Pending,
+    /// The logged keyboard is in use, or no other keyboard was either
+    Fine,
+    /// The logged keyboard stayed silent while this one was typed on
+    Silent(DeviceSuggestion),
+}
+
+/// Notices a wrong keyboard being logged: in the first minutes after
+/// startup the logger also reads the other keyboard candidates (without
+/// grabbing them) and compares their character key presses with those of
+/// the logged keyboards.
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::{SilentDeviceCheck, SilentDeviceVerdict};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let minutes = |m: u64| start + Duration::from_secs(m * 60);
+/// let mut check = SilentDeviceCheck::new(Duration::from_secs(3 * 60), 20, start);
+///
+/// check.record_logged(2);
+/// check.record_other("/dev/input/event5", "Keychron K2", 150);
+/// check.record_other("/dev/input/event9", "Logitech MX Keys", 30);
+/// assert_eq!(check.verdict(minutes(2)), SilentDeviceVerdict::Pending);
+/// let SilentDeviceVerdict::Silent(suggestion) = check.verdict(minutes(3)) else {
+///     panic!("the logged keyboard was silent");
+/// };
+/// assert_eq!(suggestion.label, "Keychron K2");
+/// assert_eq!(suggestion.presses, 150);
+///
+/// // Typing on the logged keyboard settles it, even after the warning
+/// check.record_logged(18);
+/// assert_eq!(check.verdict(minutes(4)), SilentDeviceVerdict::Fine);
+///
+/// // Nobody typed at all: nothing to suggest
+/// let idle = SilentDeviceCheck::new(Duration::from_secs(3 * 60), 20, start);
+/// assert_eq!(idle.verdict(minutes(3)), SilentDeviceVerdict::Fine);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SilentDeviceCheck {
+    /// End of the first minutes
+    until: Instant,
+    /// Presses that show a keyboard is in use
+    min_presses: u64,
+    /// Presses on the logged keyboards
+    logged: u64,
+    /// Presses on each other keyboard by path, with its label
+    others: BTreeMap<String, (String, u64)>,
+}
+
+impl SilentDeviceCheck {
+    /// Starts the check.
+    ///
+    /// # Arguments
+    /// * `window` - How long after startup to compare, see [`Config::silent_device_after`]
+    /// * `min_presses` - Character key presses that show a keyboard is in use
+    /// * `now` - Current monotonic time
+    pub fn new(window: Duration, min_presses: u64, now: Instant) -> Self {
+        Self { until: now + window, min_presses: min_presses.max(1), logged: 0, others: BTreeMap::new() }
+    }
+
+    /// Counts character key presses on the logged keyboards.
+    pub fn record_logged(&mut self, presses: u64) {
+        self.logged += presses;
+    }
+
+    /// Counts character key presses on another keyboard.
+    pub fn record_other(&mut self, path: &str, label: &str, presses: u64) {
+        self.others.entry(path.to_string()).or_insert_with(|| (label.to_string(), 0)).1 += presses;
+    }
+
+    /// Decides whether the wrong keyboard is logged.
+    ///
+    /// # Returns
+    /// `SilentDeviceVerdict` - Fine as soon as the logged keyboards saw
+    /// enough presses; after the first minutes the busiest other keyboard
+    /// if it saw enough of them
+    pub fn verdict(&self, now: Instant) -> SilentDeviceVerdict {
+        if self.logged >= self.min_presses {
+            return SilentDeviceVerdict::Fine;
+        }
+        if now < self.until {
+            return SilentDeviceVerdict::Pending;
+        }
+        self.others
+            .iter()
+            .filter(|(_, (_, presses))| *presses >= self.min_presses)
+            .max_by(|a, b| a.1.1.cmp(&b.1.1).then_with(|| b.0.cmp(a.0)))
+            .map_or(SilentDeviceVerdict::Fine, |(path, (label, presses))| {
+                SilentDeviceVerdict::Silent(DeviceSuggestion { path: path.clone(), label: label.clone(), presses: *presses })
+            })
+    }
+}
+
+/// Counts the presses of character keys among key events, which mouse
+/// buttons and media keys don't add to.
+fn character_presses(events: &[KeyEvent]) -> u64 {
+    events
+        .iter()
+        .filter(|event| event.state == KeyState::Pressed && keymap::is_character_key(event.code))
+        .count() as u64
+}
+
+/// Opens the keyboard candidates other than the logged ones, for
+/// [`SilentDeviceCheck`]. Devices that can't be opened are left out.
+///
+/// # Arguments
+/// * `logged` - Paths of the logged keyboards
+///
+/// # Returns
+/// `Vec<(String, EvdevSource)>` - Each other keyboard with its event device path
+fn other_keyboards(logged: &[String]) -> Vec<(String, EvdevSource)> {
+    let logged: Vec<PathBuf> = logged.iter().filter_map(|path| std::fs::canonicalize(path).ok()).collect();
+    find_keyboard_devices(&paths::input_dir())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|device| std::fs::canonicalize(&device.path).is_ok_and(|node| !logged.contains(&node)))
+        .filter_map(|device| {
+            let source = EvdevSource::open_all(std::slice::from_ref(&device.path)).ok()?.pop()?;
+            Some((device.path, source))
+        })
+        .collect()
+}
+
+/// What probing a silent device found out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrabState {
+    /// Another process holds an exclusive grab
+    Grabbed,
+    /// Nobody holds a grab; the keyboard is just not being used
+    Free,
+    /// The device is gone
+    Gone,
+    /// The source can't tell
+    Unknown,
+}
+
+/// Whether a key went down, up or is auto-repeating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    /// The key was released
+    Released,
+    /// The key was pressed
+    Pressed,
+    /// The key is held and auto-repeating
+    Repeated,
+}
+
+/// A single key event from an [`EventSource`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The evdev key code (e.g. 30 for `KEY_A`)
+    pub code: u16,
+    /// What happened to the key
+    pub state: KeyState,
+    /// When the event happened, as reported by the device
+    pub time: SystemTime,
+}
+
+/// A source of key events, such as an evdev keyboard.
+///
+/// The logging loop polls every source on each tick, so implementations
+/// must not block: return an empty list when nothing happened.
+pub trait EventSource: Send {
+    /// Stable, human-readable name used for per-device statistics.
+    fn label(&self) -> &str;
+
+    /// Returns the key events received since the last call.
+    ///
+    /// # Returns
+    /// `Result<Vec<KeyEvent>>` - New events (possibly none), or a read error
+    fn read_events(&mut self) -> Result<Vec<KeyEvent>>;
+
+    /// Returns how many raw events of each type (e.g. `KEY`, `MISC`) were
+    /// read since the last call. Used for diagnostics only.
+    fn take_event_types(&mut self) -> HashMap<String, u64> {
+        HashMap::new()
+    }
+
+    /// Caps Lock and Num Lock as the device reports them right now, or None
+    /// if it can't tell. Queried when logging starts and whenever the
+    /// device was opened again or could not be read for a while.
+    fn lock_state(&self) -> Option<LockState> {
+        None
+    }
+
+    /// Returns the lock state if its LEDs changed since the last call.
+    ///
+    /// The desktop sets the LEDs after a lock key, so this corrects the
+    /// state guessed from the lock key presses, for example after a lock
+    /// key typed while the device was not read.
+    fn take_lock_change(&mut self) -> Option<LockState> {
+        None
+    }
+
+    /// Checks whether another process grabbed the device, for
+    /// [`IdleWatchdog`]. Only called after a long silence.
+    fn probe_grab(&mut self) -> GrabState {
+        GrabState::Unknown
+    }
+}
+
+/// An evdev input device used as an [`EventSource`].
+pub struct EvdevSource {
+    /// The opened, non-blocking device
+    device: Device,
+    /// Label from [`device_labels`]
+    label: String,
+    /// Raw events of every type read since the last [`EventSource::take_event_types`]
+    event_types: HashMap<String, u64>,
+    /// Lock state as of the last LED event
+    locks: LockState,
+    /// Whether an LED event arrived since the last [`EventSource::take_lock_change`]
+    locks_changed: bool,
+}
+
+impl EvdevSource {
+    /// Opens several devices at once so identical keyboards get distinct labels.
+    ///
+    /// # Arguments
+    /// * `paths` - Event device paths (e.g. `/dev/input/event3`)
+    ///
+    /// # Returns
+    /// `Result<Vec<EvdevSource>>` - One source per path, in order
+    pub fn open_all(paths: &[String]) -> Result<Vec<EvdevSource>> {
+        let mut devices = Vec::new();
+        for path in paths {
+            let device = Device::open(path)
+                .with_context(|| format!("failed to open {}", path))?;
+            // Non-blocking reads keep shutdown and the deadline responsive while idle
+            device.set_nonblocking(true)?;
+            devices.push(device);
+        }
+
+        let labels = device_labels(
+            &devices
+                .iter()
+                .zip(paths)
+                .map(|(device, path)| {
+                    let name = raw_device_name(path, device)
+                        .map(|raw| sanitize_device_name(&raw))
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or_else(|| path.clone());
+                    (name, device.physical_path().map(str::to_string))
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        Ok(devices
+            .into_iter()
+            .zip(labels)
+            .map(|(device, label)| {
+                let mut source =
+                    EvdevSource { device, label, event_types: HashMap::new(), locks: LockState::default(), locks_changed: false };
+                source.locks = source.lock_state().unwrap_or_default();
+                source
+            })
+            .collect())
+    }
+}
+
+impl EventSource for EvdevSource {
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn read_events(&mut self) -> Result<Vec<KeyEvent>> {
+        let events = match self.device.fetch_events() {
+            Ok(events) => events,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let event_types = &mut self.event_types;
+        let (locks, locks_changed) = (&mut self.locks, &mut self.locks_changed);
+        Ok(events
+            .inspect(|event| {
+                *event_types.entry(format!("{:?}", event.event_type())).or_insert(0) += 1;
+                // After SYN_DROPPED, evdev reads the LEDs again and reports
+                // what changed as LED events, so these stay in sync
+                if event.event_type() == evdev::EventType::LED {
+                    match evdev::LedCode(event.code()) {
+                        evdev::LedCode::LED_CAPSL => locks.caps_lock = event.value() != 0,
+                        evdev::LedCode::LED_NUML => locks.num_lock = event.value() != 0,
+                        _ => return,
+                    }
+                    *locks_changed = true;
+                }
+            })
+            .filter(|event| event.event_type() == evdev::EventType::KEY)
+            .filter_map(|event| {
+                let state = match event.value() {
+                    0 => KeyState::Released,
+                    1 => KeyState::Pressed,
+                    2 => KeyState::Repeated,
+                    _ => return None,
+                };
+                Some(KeyEvent { code: event.code(), state, time: event.timestamp() })
+            })
+            .collect())
+    }
+
+    fn take_event_types(&mut self) -> HashMap<String, u64> {
+        std::mem::take(&mut self.event_types)
+    }
+
+    fn lock_state(&self) -> Option<LockState> {
+        let leds = self.device.get_led_state().ok()?;
+        Some(LockState {
+            caps_lock: leds.contains(evdev::LedCode::LED_CAPSL),
+            num_lock: leds.contains(evdev::LedCode::LED_NUML),
+        })
+    }
+
+    fn take_lock_change(&mut self) -> Option<LockState> {
+        std::mem::take(&mut self.locks_changed).then_some(self.locks)
+    }
+
+    fn probe_grab(&mut self) -> GrabState {
+        // Reading the key state fails once the device is unplugged or revoked
+        if let Err(e) = self.device.get_key_state() {
+            return if e.raw_os_error() == Some(ENODEV) { GrabState::Gone } else { GrabState::Unknown };
+        }
+        // EVIOCGRAB is refused while someone else holds it; if it works,
+        // let go right away so the rest of the system keeps its input
+        match self.device.grab() {
+            Ok(()) => {
+                let _ = self.device.ungrab();
+                GrabState::Free
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::ResourceBusy => GrabState::Grabbed,
+            Err(e) if e.raw_os_error() == Some(ENODEV) => GrabState::Gone,
+            Err(_) => GrabState::Unknown,
+        }
+    }
+}
+
+/// `errno` of an unplugged or revoked input device (Linux numbering).
+const ENODEV: i32 = 19;
+
+/// Caps Lock and Num Lock of a keyboard, as its LEDs show them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockState {
+    /// Whether Caps Lock is on
+    pub caps_lock: bool,
+    /// Whether Num Lock is on
+    pub num_lock: bool,
+}
+
+/// Adds up the time Caps Lock stays on, or the logger keeps counting,
+/// handing it out in steps of [`LOCK_TIME_STEP`] so the statistics don't
+/// change on every pass of the logging loop.
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::LockTimer;
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let at = |secs: u64| start + Duration::from_secs(secs);
+/// let mut timer = LockTimer::default();
+///
+/// assert_eq!(timer.tick(true, at(0)), None);
+/// assert_eq!(timer.tick(true, at(4)), None);
+/// assert_eq!(timer.tick(true, at(12)), Some(Duration::from_secs(12)));
+/// // Turning it off hands out the rest, and the time off doesn't count
+/// assert_eq!(timer.tick(false, at(15)), Some(Duration::from_secs(3)));
+/// assert_eq!(timer.tick(false, at(60)), None);
+/// assert_eq!(timer.tick(true, at(61)), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LockTimer {
+    /// When the lock was last seen on
+    since: Option<Instant>,
+    /// Time on not handed out yet
+    pending: Duration,
+}
+
+impl LockTimer {
+    /// Records whether the lock is on now.
+    ///
+    /// # Returns
+    /// `Option<Duration>` - Time to add once a step is full or the lock
+    /// went off
+    pub fn tick(&mut self, on: bool, now: Instant) -> Option<Duration> {
+        if let Some(since) = self.since {
+            self.pending += now.saturating_duration_since(since);
+        }
+        self.since = on.then_some(now);
+        if self.pending >= LOCK_TIME_STEP || (!on && !self.pending.is_zero()) {
+            return Some(std::mem::take(&mut self.pending));
+        }
+        None
+    }
+}
+
+/// Shift, Caps Lock and Num Lock state of one source, for case-sensitive
+/// counting and the Caps Lock statistics.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModifierState {
+    /// Shift keys currently held
+    shift_held: u8,
+    /// Whether Caps Lock is on
+    caps_lock: bool,
+    /// Whether Num Lock is on
+    num_lock: bool,
+    /// Whether a letter was typed since Caps Lock was turned on
+    in_caps_run: bool,
+}
+
+impl ModifierState {
+    /// Starts with Caps Lock in the given state, Num Lock off and no Shift held.
+    pub fn new(caps_lock: bool) -> Self {
+        Self { caps_lock, ..Self::default() }
+    }
+
+    /// Starts with the lock state a source reports, see [`EventSource::lock_state`].
+    pub fn of(source: &dyn EventSource) -> Self {
+        let mut state = Self::default();
+        if let Some(locks) = source.lock_state() {
+            state.sync(locks);
+        }
+        state
+    }
+
+    /// Updates the state from a key event.
+    ///
+    /// The lock keys toggle their lock on every press, which is what the
+    /// desktop does too; [`ModifierState::sync`] corrects the guess once
+    /// the LEDs say otherwise.
+    pub fn update(&mut self, event: KeyEvent) {
+        match (event.code, event.state) {
+            (keymap::KEY_LEFTSHIFT | keymap::KEY_RIGHTSHIFT, KeyState::Pressed) => {
+                self.shift_held = self.shift_held.saturating_add(1);
+            }
+            (keymap::KEY_LEFTSHIFT | keymap::KEY_RIGHTSHIFT, KeyState::Released) => {
+                self.shift_held = self.shift_held.saturating_sub(1);
+            }
+            (keymap::KEY_CAPSLOCK, KeyState::Pressed) => self.set_caps_lock(!self.caps_lock),
+            (keymap::KEY_NUMLOCK, KeyState::Pressed) => self.num_lock = !self.num_lock,
+            _ => {}
+        }
+    }
+
+    /// Takes over the lock state the device reports, see
+    /// [`EventSource::take_lock_change`].
+    pub fn sync(&mut self, locks: LockState) {
+        self.set_caps_lock(locks.caps_lock);
+        self.num_lock = locks.num_lock;
+    }
+
+    /// Returns the lock state as tracked so far.
+    pub fn locks(&self) -> LockState {
+        LockState { caps_lock: self.caps_lock, num_lock: self.num_lock }
+    }
+
+    fn set_caps_lock(&mut self, on: bool) {
+        if on != self.caps_lock {
+            self.caps_lock = on;
+            self.in_caps_run = false;
+        }
+    }
+
+    /// Marks a letter as typed with Caps Lock on.
+    ///
+    /// Letters typed between turning Caps Lock on and off again form a
+    /// run, so a forgotten Caps Lock shows up as one run however long it
+    /// stays on.
+    ///
+    /// # Returns
+    /// `Option<bool>` - None with Caps Lock off, otherwise whether the
+    /// letter starts a new run
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::{KeyEvent, KeyState, LockState, ModifierState};
+    /// use ctrlq::keymap;
+    /// use std::time::SystemTime;
+    ///
+    /// let mut state = ModifierState::new(false);
+    /// assert_eq!(state.caps_letter(), None);
+    ///
+    /// let time = SystemTime::now();
+    /// state.update(KeyEvent { code: keymap::KEY_CAPSLOCK, state: KeyState::Pressed, time });
+    /// assert_eq!(state.caps_letter(), Some(true));
+    /// assert_eq!(state.caps_letter(), Some(false));
+    ///
+    /// // The LEDs disagree: Caps Lock was turned off while nobody was looking
+    /// state.sync(LockState { caps_lock: false, num_lock: true });
+    /// assert_eq!(state.caps_letter(), None);
+    /// assert_eq!(state.locks(), LockState { caps_lock: false, num_lock: true });
+    /// ```
+    pub fn caps_letter(&mut self) -> Option<bool> {
+        if !self.caps_lock {
+            return None;
+        }
+        Some(!std::mem::replace(&mut self.in_caps_run, true))
+    }
+
+    /// Returns the stored name of a key pressed in this state.
+    ///
+    /// Caps Lock only shifts letters, and Shift with Caps Lock types a
+    /// lowercase letter again.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::{KeyEvent, KeyState, ModifierState};
+    /// use ctrlq::keymap;
+    /// use std::time::SystemTime;
+    ///
+    /// let mut state = ModifierState::new(true);
+    /// assert_eq!(state.key_name(30), keymap::shifted_name(30)); // Caps Lock: A
+    /// assert_eq!(state.key_name(2), keymap::key_name(2)); // still 1
+    ///
+    /// let time = SystemTime::now();
+    /// state.update(KeyEvent { code: keymap::KEY_LEFTSHIFT, state: KeyState::Pressed, time });
+    /// assert_eq!(state.key_name(30), keymap::key_name(30)); // a
+    /// assert_eq!(state.key_name(2), keymap::shifted_name(2)); // !
+    /// ```
+    pub fn key_name(&self, code: u16) -> String {
+        if !keymap::is_shiftable(code) {
+            return keymap::key_name(code);
+        }
+        let caps = self.caps_lock && keymap::classify(code) == KeyClass::Alpha;
+        if (self.shift_held > 0) != caps {
+            keymap::shifted_name(code)
+        } else {
+            keymap::key_name(code)
+        }
+    }
+
+    /// Returns the character a key types in this state on a US layout, for
+    /// comparing keystrokes with the sentence of `ctrlq calibrate`.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::{KeyEvent, KeyState, ModifierState};
+    /// use ctrlq::keymap;
+    /// use std::time::SystemTime;
+    ///
+    /// let mut state = ModifierState::new(false);
+    /// assert_eq!(state.typed_char(30), Some('a'));
+    /// assert_eq!(state.typed_char(keymap::KEY_SPACE), Some(' '));
+    /// assert_eq!(state.typed_char(keymap::KEY_ENTER), None);
+    ///
+    /// let time = SystemTime::now();
+    /// state.update(KeyEvent { code: keymap::KEY_RIGHTSHIFT, state: KeyState::Pressed, time });
+    /// assert_eq!(state.typed_char(30), Some('A'));
+    /// assert_eq!(state.typed_char(51), Some('<'));
+    ///
+    /// // The numpad types digits with Num Lock only
+    /// assert_eq!(state.typed_char(79), None);
+    /// state.update(KeyEvent { code: keymap::KEY_NUMLOCK, state: KeyState::Pressed, time });
+    /// assert_eq!(state.typed_char(79), Some('1'));
+    /// ```
+    pub fn typed_char(&self, code: u16) -> Option<char> {
+        if code == keymap::KEY_SPACE {
+            return Some(' ');
+        }
+        if keymap::classify(code) == KeyClass::Numpad {
+            return keymap::numpad_char(code, self.num_lock);
+        }
+        let (plain, shifted) = keymap::us_layout_chars(code)?;
+        Some(if keymap::is_shifted_name(&self.key_name(code)) { shifted } else { plain })
+    }
+}
+
+/// A read error of one device, repeated `count` times in a row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceError {
+    /// Label of the device
+    pub device: String,
+    /// The error message
+    pub message: String,
+    /// When the error first occurred
+    pub first_seen: DateTime<chrono::Local>,
+    /// When the error last occurred
+    pub last_seen: DateTime<chrono::Local>,
+    /// How often it occurred in a row
+    pub count: u64,
+}
+
+/// Counters describing the health of the event stream, for spotting
+/// keyboards that ghost, double-report or drop releases.
+///
+/// Kept next to [`KeyStats`] while logging but never saved.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    /// Raw events by evdev type (`KEY`, `MISC`, `SYNCHRONIZATION`, ...)
+    pub event_types: BTreeMap<String, u64>,
+    /// Key press events
+    pub presses: u64,
+    /// Key release events
+    pub releases: u64,
+    /// Auto-repeat events
+    pub repeats: u64,
+    /// Key codes evdev has no name for, with how often they were seen
+    pub unknown_codes: BTreeMap<u16, u64>,
+    /// Releases of keys that were not pressed (or were already released)
+    pub orphan_releases: u64,
+    /// Presses of keys that were already down (a missed release)
+    pub double_presses: u64,
+    /// Keys that stayed down without release or repeat for longer than the
+    /// maximum hold time, with how often each got stuck
+    pub stuck_keys: BTreeMap<u16, u64>,
+    /// Releases with a hold time, see [`HeldKeys`]
+    pub holds: u64,
+    /// Sum of those hold times, each capped at the maximum hold time
+    pub hold_total: Duration,
+    /// Holds longer than the maximum plus presses that never saw a release
+    pub hold_anomalies: u64,
+    /// Total device read errors
+    pub error_count: u64,
+    /// Most recent device errors, oldest first
+    pub errors: VecDeque<DeviceError>,
+    /// Warning about devices that stopped sending events, see [`IdleWatchdog`]
+    pub idle_warning: Option<String>,
+    /// Active XKB layout when characters are counted, see [`crate::xkb`]
+    pub xkb_layout: Option<String>,
+    /// Keyboard typed on while the logged one stayed silent, see [`SilentDeviceCheck`]
+    pub device_suggestion: Option<DeviceSuggestion>,
+    /// Keyboards being logged, which [`LoggerCommand::SwitchDevice`] replaces
+    pub devices: Vec<LoggedDevice>,
+    /// Whether counting is paused because the screen is locked, see [`crate::lock`]
+    pub screen_locked: bool,
+    /// Whether disk writes are throttled because of battery power, see [`crate::power`]
+    pub power_saving: bool,
+    /// Today's `log_hours` while keystrokes are not counted outside them,
+    /// see [`crate::schedule`]
+    pub outside_hours: Option<String>,
+    /// Whether keystrokes outside `log_hours` are counted until midnight
+    pub hours_override: bool,
+    /// Whether Caps Lock is on for any logged keyboard, see [`ModifierState`]
+    pub caps_lock: bool,
+    /// Typing time without a break once one is due, see [`BreakReminder`]
+    pub break_due: Option<Duration>,
+    /// Each `[[ignore]]` rule of the config with the presses it suppressed,
+    /// see [`crate::ignore`]
+    pub ignored: Vec<(String, u64)>,
+}
+
+impl Diagnostics {
+    /// Counts one key event by its state and code.
+    pub fn record_key_event(&mut self, event: KeyEvent) {
+        match event.state {
+            KeyState::Pressed => self.presses += 1,
+            KeyState::Released => self.releases += 1,
+            KeyState::Repeated => self.repeats += 1,
+        }
+        if !keymap::is_known_code(event.code) {
+            *self.unknown_codes.entry(event.code).or_insert(0) += 1;
+        }
+    }
+
+    /// Adds raw event counts returned by [`EventSource::take_event_types`].
+    pub fn record_event_types(&mut self, counts: HashMap<String, u64>) {
+        for (event_type, count) in counts {
+            *self.event_types.entry(event_type).or_insert(0) += count;
+        }
+    }
+
+    /// Records a device read error. An error that repeats the previous one
+    /// only bumps its count, so a failing device doesn't flood the history.
+    ///
+    /// # Arguments
+    /// * `device` - Label of the device
+    /// * `message` - The error message
+    /// * `now` - When the error occurred
+    pub fn record_error(&mut self, device: &str, message: &str, now: DateTime<chrono::Local>) {
+        self.error_count += 1;
+        if let Some(last) = self.errors.back_mut()
+            && last.device == device
+            && last.message == message
+        {
+            last.last_seen = now;
+            last.count += 1;
+            return;
+        }
+
+        if self.errors.len() >= DIAGNOSTIC_ERROR_HISTORY {
+            self.errors.pop_front();
+        }
+        self.errors.push_back(DeviceError {
+            device: device.to_string(),
+            message: message.to_string(),
+            first_seen: now,
+            last_seen: now,
+            count: 1,
+        });
+    }
+
+    /// Lists the ignore rules of the config, keeping the counts of rules
+    /// that stay and starting new ones at zero.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::config::Config;
+    /// use ctrlq::keylogger::Diagnostics;
+    ///
+    /// let config: Config = toml::from_str("[[ignore]]\nkeys = [\"numpad\"]")?;
+    /// let mut diagnostics = Diagnostics::default();
+    /// diagnostics.set_ignore_rules(&config.ignore);
+    /// diagnostics.record_ignored(0);
+    /// assert_eq!(diagnostics.ignored, [("numpad".to_string(), 1)]);
+    ///
+    /// let reloaded: Config = toml::from_str("[[ignore]]\nkeys = [\"numpad\"]\n[[ignore]]\nkeys = [\"F1\"]")?;
+    /// diagnostics.set_ignore_rules(&reloaded.ignore);
+    /// assert_eq!(diagnostics.ignored, [("numpad".to_string(), 1), ("F1".to_string(), 0)]);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_ignore_rules(&mut self, rules: &[IgnoreRule]) {
+        self.ignored = rules
+            .iter()
+            .map(|rule| {
+                let name = rule.to_string();
+                let count = self.ignored.iter().find(|(known, _)| *known == name).map_or(0, |(_, count)| *count);
+                (name, count)
+            })
+            .collect();
+    }
+
+    /// Counts a press suppressed by the ignore rule at `index`.
+    pub fn record_ignored(&mut self, index: usize) {
+        if let Some((_, count)) = self.ignored.get_mut(index) {
+            *count += 1;
+        }
+    }
+
+    /// Total keys that got stuck.
+    pub fn stuck_key_count(&self) -> u64 {
+        self.stuck_keys.values().sum()
+    }
+
+    /// Average time a key was held down, or None before the first release.
+    pub fn average_hold(&self) -> Option<Duration> {
+        (self.holds > 0).then(|| self.hold_total / self.holds as u32)
+    }
+}
+
+/// Probes silent sources and describes the problem, if there seems to be one.
+///
+/// # Arguments
+/// * `sources` - All sources being logged
+/// * `silence` - How long no key events arrived
+///
+/// # Returns
+/// `Option<String>` - The warning, or None if every source is free and
+/// the keyboards are simply not in use
+fn silence_warning(sources: &mut [Box<dyn EventSource>], silence: Duration, name_width: usize) -> Option<String> {
+    let probes: Vec<(String, GrabState)> = sources
+        .iter_mut()
+        .map(|source| (truncate_name(source.label(), name_width), source.probe_grab()))
+        .collect();
+    let with = |state| {
+        probes
+            .iter()
+            .filter(|(_, probe)| *probe == state)
+            .map(|(label, _)| label.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let minutes = silence.as_secs() / 60;
+
+    let grabbed = with(GrabState::Grabbed);
+    if !grabbed.is_empty() {
+        return Some(format!("No events for {} min - {} grabbed by another process", minutes, grabbed));
+    }
+    let gone = with(GrabState::Gone);
+    if !gone.is_empty() {
+        return Some(format!("No events for {} min - {} disappeared", minutes, gone));
+    }
+    if probes.iter().all(|(_, probe)| *probe == GrabState::Free) {
+        return None;
+    }
+    Some(format!("No events for {} min - device may be grabbed by another process", minutes))
+}
+
+/// Extracts the message of a caught panic.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// The data file as written by [`KeyLogger::save_stats`]: the statistics
+/// plus what they can't decide to write on their own.
+#[derive(Serialize)]
+struct SavedStats<'a> {
+    /// Always [`DATA_FORMAT_VERSION`]
+    format_version: u32,
+    /// The statistics, without `key_sequences`
+    #[serde(flatten)]
+    stats: &'a KeyStats,
+    /// The last keys typed, if the user opted in to saving them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_sequences: Option<&'a [String]>,
+}
+
+/// A key that is currently held down on one of the sources.
+#[derive(Debug, Clone, Copy)]
+struct HeldKey {
+    /// When the key went down
+    pressed_at: Instant,
+    /// Last press or auto-repeat of the key
+    last_seen: Instant,
+}
+
+/// Keys currently held down on each source, and how long they were held.
+///
+/// Hold times run from press to release and are capped at a maximum: a
+/// longer hold is recorded as the maximum and counted as an anomaly in
+/// [`Diagnostics`]. A press that sees neither a release nor an auto-repeat
+/// for that long never gets released (some Fn layers don't send one), so
+/// [`HeldKeys::expire`] drops it as stuck instead of keeping it forever.
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::{Diagnostics, HeldKeys, KeyEvent, KeyState};
+/// use std::time::{Duration, Instant, SystemTime};
+///
+/// let event = |code, state| KeyEvent { code, state, time: SystemTime::now() };
+/// let start = Instant::now();
+/// let at = |ms| start + Duration::from_millis(ms);
+/// let mut held = HeldKeys::new(Duration::from_secs(5));
+/// let mut diagnostics = Diagnostics::default();
+///
+/// // A normal keystroke, 80 ms down
+/// held.record(0, event(30, KeyState::Pressed), at(0), &mut diagnostics);
+/// held.record(0, event(30, KeyState::Released), at(80), &mut diagnostics);
+///
+/// // Fn pressed without a release, Backspace held with auto-repeat
+/// held.record(0, event(464, KeyState::Pressed), at(100), &mut diagnostics);
+/// held.record(0, event(14, KeyState::Pressed), at(100), &mut diagnostics);
+/// for ms in (600..=5100).step_by(100) {
+///     held.record(0, event(14, KeyState::Repeated), at(ms), &mut diagnostics);
+/// }
+/// assert_eq!(held.expire(at(5100), &mut diagnostics), vec![464]);
+/// assert_eq!(held.codes(0).collect::<Vec<_>>(), vec![14]);
+///
+/// // Released after 6.9 s, which counts as 5 s
+/// held.record(0, event(14, KeyState::Released), at(7000), &mut diagnostics);
+/// assert!(held.is_empty());
+/// assert_eq!(diagnostics.hold_anomalies, 2);
+/// assert_eq!(diagnostics.stuck_keys.get(&464), Some(&1));
+/// assert_eq!(diagnostics.average_hold(), Some(Duration::from_millis(2540)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HeldKeys {
+    /// Held keys by (source index, key code), so two keyboards don't mix
+    keys: HashMap<(usize, u16), HeldKey>,
+    /// Longest hold time that is recorded as it is
+    max_hold: Duration,
+}
+
+impl HeldKeys {
+    /// Creates an empty set of held keys.
+    ///
+    /// # Arguments
+    /// * `max_hold` - Longest hold time recorded as it is, see [`Config::max_hold`]
+    pub fn new(max_hold: Duration) -> Self {
+        Self { keys: HashMap::new(), max_hold }
+    }
+
+    /// Changes the maximum hold time, e.g. after a config reload.
+    pub fn set_max_hold(&mut self, max_hold: Duration) {
+        self.max_hold = max_hold;
+    }
+
+    /// Tracks one key event and counts what it says about the keyboard.
+    ///
+    /// # Arguments
+    /// * `source` - Index of the source the event came from
+    /// * `event` - The key event
+    /// * `now` - When the event was read
+    /// * `diagnostics` - Counters for double presses, orphan releases and hold times
+    pub fn record(&mut self, source: usize, event: KeyEvent, now: Instant, diagnostics: &mut Diagnostics) {
+        let id = (source, event.code);
+        match event.state {
+            KeyState::Pressed => {
+                if self.keys.insert(id, HeldKey { pressed_at: now, last_seen: now }).is_some() {
+                    diagnostics.double_presses += 1;
+                }
+            }
+            KeyState::Released => match self.keys.remove(&id) {
+                Some(held) => {
+                    let hold = now.saturating_duration_since(held.pressed_at);
+                    if hold > self.max_hold {
+                        diagnostics.hold_anomalies += 1;
+                    }
+                    diagnostics.holds += 1;
+                    diagnostics.hold_total += hold.min(self.max_hold);
+                }
+                None => diagnostics.orphan_releases += 1,
+            },
+            KeyState::Repeated => {
+                if let Some(held) = self.keys.get_mut(&id) {
+                    held.last_seen = now;
+                }
+            }
+        }
+    }
+
+    /// Drops keys that saw neither a release nor an auto-repeat for longer
+    /// than the maximum hold time, counting them as stuck.
+    ///
+    /// # Returns
+    /// `Vec<u16>` - Codes of the dropped keys
+    pub fn expire(&mut self, now: Instant, diagnostics: &mut Diagnostics) -> Vec<u16> {
+        let mut expired = Vec::new();
+        self.keys.retain(|&(_, code), held| {
+            if now.saturating_duration_since(held.last_seen) < self.max_hold {
+                return true;
+            }
+            *diagnostics.stuck_keys.entry(code).or_insert(0) += 1;
+            diagnostics.hold_anomalies += 1;
+            expired.push(code);
+            false
+        });
+        expired.sort_unstable();
+        expired
+    }
+
+    /// Codes of the keys held on one source.
+    pub fn codes(&self, source: usize) -> impl Iterator<Item = u16> + '_ {
+        self.keys.keys().filter(move |&&(i, _)| i == source).map(|&(_, code)| code)
+    }
+
+    /// Codes of the keys held on any source.
+    pub fn all_codes(&self) -> impl Iterator<Item = u16> + '_ {
+        self.keys.keys().map(|&(_, code)| code)
+    }
+
+    /// Whether no key is held.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Forgets all held keys without counting them, e.g. across a suspend.
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+}
+
+/// Main keylogger implementation that monitors keyboard input.
+///
+/// Handles device management, keystroke capture, statistics tracking,
+/// and data persistence. Supports both UI and headless operation modes.
+pub struct KeyLogger {
+    /// Paths of the keyboard input devices (e.g., /dev/input/event8)
+    device_paths: Vec<String>,
+    /// User configuration (excluded key classes, etc.)
+    config: Config,
+    /// Command line settings that survive config reloads
+    overrides: Overrides,
+    /// Current keystroke statistics and session data
+    stats: KeyStats,
+    /// Path to the JSON data file for persistence
+    data_file: PathBuf,
+    /// Whether the keylogger is currently running
+    is_running: bool,
+    /// Optional publisher of live snapshots for the stream server
+    live_tx: Option<watch::Sender<LiveSnapshot>>,
+    /// When the logger should stop on its own, if limited
+    deadline: Option<Instant>,
+    /// Where to write a report of the session on exit, if requested
+    export_path: Option<PathBuf>,
+    /// Directory of the raw event log, if enabled
+    raw_log_dir: Option<PathBuf>,
+    /// Sources to log from instead of the devices, see [`KeyLogger::log_from`]
+    sources: Option<Vec<Box<dyn EventSource>>>,
+    /// Handle of the logging thread, once started
+    worker: Option<thread::JoinHandle<()>>,
+    /// Control socket for CLI commands, removed when the logger is dropped
+    control: Option<ipc::Server>,
+    /// Lock that keeps other instances off this profile's data file
+    _lock: profile::ProfileLock,
+}
+
+/// Compact live view of the statistics, published for external dashboards.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LiveSnapshot {
+    /// Total keystrokes recorded
+    pub total_keystrokes: u64,
+    /// Keystrokes today
+    pub today_keystrokes: u64,
+    /// Keystrokes in the current session
+    pub session_keystrokes: u64,
+    /// Current gross WPM, rounded to one decimal
+    pub wpm: Option<f64>,
+    /// Current net WPM, rounded to one decimal
+    pub net_wpm: Option<f64>,
+    /// Class of the most recent key
+    pub last_key_class: Option<KeyClass>,
+}
+
+impl LiveSnapshot {
+    /// Builds a live snapshot from the full statistics.
+    pub fn from_stats(stats: &KeyStats) -> Self {
+        let round = |v: f64| (v * 10.0).round() / 10.0;
+        Self {
+            total_keystrokes: stats.total_keystrokes,
+            today_keystrokes: stats.daily_stats.get(&day_key(today())).map(|d| d.keystrokes).unwrap_or(0),
+            session_keystrokes: stats.session_keystrokes,
+            wpm: stats.get_wpm().map(round),
+            net_wpm: stats.get_net_wpm().map(round),
+            last_key_class: stats.key_sequences.last().map(|k| keymap::classify_name(k)),
+        }
+    }
+}
+
+/// A problem of the logging thread, for the UI to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoggerProblem {
+    /// Something failed but logging goes on, e.g. a save or a device read
+    Error(String),
+    /// The logging thread stopped; nothing is counted until it is restarted
+    Crashed(String),
+}
+
+/// An instruction for the logging thread from the UI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoggerCommand {
+    /// Stop counting keystrokes until [`LoggerCommand::Resume`]
+    Pause,
+    /// Count keystrokes again
+    Resume,
+    /// Save the statistics now instead of at the next save interval
+    Save,
+    /// Start (true) or stop forwarding typed characters for `ctrlq calibrate`
+    Capture(bool),
+    /// Log this keyboard instead of the current ones, without restarting
+    SwitchDevice(String),
+    /// Note that the statistics were just exported, see [`KeyStats::last_export_at`]
+    MarkExported,
+    /// Count keystrokes outside `log_hours` for the rest of the day (true),
+    /// or follow them again, see [`crate::schedule`]
+    OutsideHours(bool),
+    /// Tag the current session (None removes the tag), with an optional note
+    TagSession {
+        /// The tag, see [`parse_session_label`]
+        tag: Option<String>,
+        /// The note
+        note: Option<String>,
+    },
+}
+
+/// Channels for talking to a running logger, returned by [`KeyLogger::start_logging`].
+pub struct LoggerChannels {
+    /// Statistics snapshots from the logger
+    pub stats_rx: mpsc::Receiver<KeyStats>,
+    /// Sends the shutdown signal
+    pub shutdown_tx: watch::Sender<bool>,
+    /// Sends reset commands
+    pub reset_tx: mpsc::Sender<ResetScope>,
+    /// Sends pause, resume, save, capture and device switch commands
+    pub command_tx: mpsc::Sender<LoggerCommand>,
+    /// Short messages for the user, such as "config reloaded"
+    pub notice_rx: mpsc::Receiver<String>,
+    /// Errors and crashes of the logging thread
+    pub problem_rx: mpsc::Receiver<LoggerProblem>,
+    /// Reports errors of helpers outside the logging thread, such as the
+    /// stream server, next to its own
+    pub problem_tx: mpsc::Sender<LoggerProblem>,
+    /// Typed characters while [`LoggerCommand::Capture`] is on
+    pub typed_rx: mpsc::Receiver<TypedKey>,
+}
+
+/// Channels connecting the logging thread to the rest of the application.
+struct WorkerChannels {
+    /// Full statistics snapshots for the UI
+    stats_tx: mpsc::Sender<KeyStats>,
+    /// Short messages for the user
+    notice_tx: mpsc::Sender<String>,
+    /// Errors that don't stop logging
+    problem_tx: mpsc::Sender<LoggerProblem>,
+    /// Typed characters for a typing test in the UI
+    typed_tx: mpsc::Sender<TypedKey>,
+    /// Shutdown signal
+    shutdown_rx: watch::Receiver<bool>,
+    /// Reset commands
+    reset_rx: mpsc::Receiver<ResetScope>,
+    /// Pause, resume, save, capture and device switch commands from the UI
+    command_rx: mpsc::Receiver<LoggerCommand>,
+    /// Context switches from `ctrlq context`
+    context_rx: mpsc::Receiver<String>,
+    /// Keystrokes reported by `ctrlq ping`
+    remote_rx: mpsc::Receiver<u32>,
+    /// Live snapshots for the stream server, if enabled
+    live_tx: Option<watch::Sender<LiveSnapshot>>,
+    /// Values of `ctrlq status-line`, answered on the control socket
+    status_tx: watch::Sender<StatusSnapshot>,
+    /// Time at which the loop stops as if shutdown was requested
+    deadline: Option<Instant>,
+    /// Raw event log, if `--raw-log` is enabled
+    raw_log: Option<RawLogWriter>,
+    /// Sources to read instead of opening the devices, see [`KeyLogger::log_from`]
+    sources: Option<Vec<Box<dyn EventSource>>>,
+}
+
+impl KeyLogger {
+    /// Creates a new KeyLogger instance for the specified devices.
+    ///
+    /// Initializes the data directory, loads existing statistics from file
+    /// if available, and prepares the keylogger for operation.
+    ///
+    /// # Arguments
+    /// * `device_paths` - Paths of the keyboard input devices to log together
+    /// * `config` - User configuration
+    /// * `overrides` - Command line settings that win over the config file
+    /// * `profile` - Profile whose data file is used, see [`profile`]
+    ///
+    /// # Returns
+    /// `Result<Self>` - New KeyLogger instance, or an error if the profile is
+    /// already being logged by another instance
+    pub fn new(
+        device_paths: Vec<String>,
+        mut config: Config,
+        overrides: Overrides,
+        profile: &str,
+    ) -> Result<Self> {
+        overrides.apply(&mut config);
+        let data_file = profile::data_file(profile)?;
+        let lock = profile::lock(&data_file)?;
+        let mut stats = Self::load_stats(&data_file)?;
+        // A data file from before the archive holds every day; move the
+        // old ones out now, so it is parsed in full only this once
+        if archive::archive_old_days(&mut stats, &data_file, today(), config.pretty_json)? > 0 {
+            Self::save_stats(&stats, &data_file, config.pretty_json)?;
+        }
+        if !stats.is_empty() && stats.anonymized != config.anonymize {
+            if stats.anonymized {
+                bail!(
+                    "{} was captured with --anonymize; run with --anonymize again, \
+                     or `ctrlq reset` to start a new file with per-key data",
+                    data_file.display()
+                );
+            }
+            bail!(
+                "{} already contains per-key data, so --anonymize can't make it shareable; \
+                 run `ctrlq reset` first to start an anonymized file",
+                data_file.display()
+            );
+        }
+        stats.anonymized = config.anonymize;
+        // Plain names stay valid in case-sensitive data, so the flag only
+        // ever turns on for a data file
+        stats.case_sensitive |= config.case_sensitive && !config.anonymize;
+        stats.persist_sequences = config.persist_sequences;
+        stats.start_session();
+
+        Ok(Self {
+            device_paths,
+            config,
+            overrides,
+            stats,
+            data_file,
+            is_running: false,
+            live_tx: None,
+            deadline: None,
+            export_path: None,
+            raw_log_dir: None,
+            sources: None,
+            worker: None,
+            control: None,
+            _lock: lock,
+        })
+    }
+
+    /// Returns the default profile's data file path, creating its directory if needed.
+    ///
+    /// # Returns
+    /// `Result<PathBuf>` - `~/.local/share/ctrlq/keystroke_data.json` or
+    /// `./ctrlq/keystroke_data.json` as a fallback
+    pub fn default_data_file() -> Result<PathBuf> {
+        profile::data_file(profile::DEFAULT_PROFILE)
+    }
+
+    /// Loads statistics from a data file.
+    ///
+    /// A missing file yields fresh, empty statistics. A file that can't be
+    /// read or is not a data file is an error, so that a damaged file is
+    /// never saved over with empty statistics.
+    ///
+    /// # Arguments
+    /// * `data_file` - File path to load from
+    ///
+    /// # Returns
+    /// `Result<KeyStats>` - Loaded statistics, or a file I/O or parse error
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::KeyLogger;
+    ///
+    /// let file = std::env::temp_dir().join(format!("ctrlq-load-doc-{}.json", std::process::id()));
+    /// assert_eq!(KeyLogger::load_stats(&file)?.total_keystrokes, 0);
+    ///
+    /// // Cut off halfway, e.g. by a full disk
+    /// let damaged = r#"{"key_counts":{"KEY_30":1},"total_keys"#;
+    /// std::fs::write(&file, damaged)?;
+    /// let error = KeyLogger::load_stats(&file).unwrap_err();
+    /// assert!(error.to_string().contains("is not a valid data file"));
+    /// assert_eq!(std::fs::read_to_string(&file)?, damaged);
+    /// std::fs::remove_file(&file)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn load_stats(data_file: &Path) -> Result<KeyStats> {
+        if !data_file.exists() {
+            return Ok(KeyStats::new());
+        }
+        Self::read_stats(data_file).map_err(|e| {
+            // A read failing halfway says nothing about the file
+            if e.downcast_ref::<serde_json::Error>().is_none_or(serde_json::Error::is_io) {
+                return e;
+            }
+            e.context(format!(
+                "{} is not a valid data file; restore a backup or move it away to start over",
+                data_file.display()
+            ))
+        })
+    }
+
+    /// Reads statistics from a data file, parsing it as it is read instead
+    /// of holding the whole file in memory as text first.
+    ///
+    /// # Arguments
+    /// * `data_file` - File path to read from
+    ///
+    /// # Returns
+    /// `Result<KeyStats>` - The statistics, or an error if the file can't be
+    /// read or is not a data file
+    pub fn read_stats(data_file: &Path) -> Result<KeyStats> {
+        let file = std::fs::File::open(data_file).with_context(|| format!("failed to read {}", data_file.display()))?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    /// Loads statistics like [`KeyLogger::load_stats`], together with the
+    /// days moved to the monthly archive.
+    ///
+    /// Use this for the whole history and before changing a data file
+    /// outside the logger, so archived days are merged rather than replaced.
+    ///
+    /// # Arguments
+    /// * `data_file` - File path to load from
+    ///
+    /// # Returns
+    /// `Result<KeyStats>` - Loaded statistics or file I/O error
+    pub fn load_full_stats(data_file: &Path) -> Result<KeyStats> {
+        let mut stats = Self::load_stats(data_file)?;
+        archive::load_into(&mut stats, data_file)?;
+        Ok(stats)
+    }
+
+    /// Publishes compact live snapshots to the given channel while logging.
+    ///
+    /// Must be called before [`KeyLogger::start_logging`].
+    ///
+    /// # Arguments
+    /// * `live_tx` - Channel that receives a snapshot whenever it changes
+    pub fn publish_live(&mut self, live_tx: watch::Sender<LiveSnapshot>) {
+        self.live_tx = Some(live_tx);
+    }
+
+    /// Stops logging automatically at the given time.
+    ///
+    /// Must be called before [`KeyLogger::start_logging`].
+    ///
+    /// # Arguments
+    /// * `deadline` - When to shut down, as if Ctrl+C was pressed
+    pub fn stop_at(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Writes a report of the logged session when logging stops.
+    ///
+    /// Files ending in `.json` get JSON, anything else Markdown.
+    /// Must be called before [`KeyLogger::start_logging`].
+    ///
+    /// # Arguments
+    /// * `path` - File to write the session report to
+    pub fn export_session_on_exit(&mut self, path: PathBuf) {
+        self.export_path = Some(path);
+    }
+
+    /// Enables the raw event log: every counted key event is appended to a
+    /// daily binary file, see [`rawlog`].
+    ///
+    /// # Returns
+    /// `Result<PathBuf>` - Directory of the log, or an error in anonymized
+    /// mode, where recording individual keys is not allowed
+    pub fn log_raw_events(&mut self) -> Result<PathBuf> {
+        if self.config.anonymize {
+            bail!("--raw-log records individual keys and can't be combined with --anonymize");
+        }
+        let dir = rawlog::log_dir(&self.data_file);
+        self.raw_log_dir = Some(dir.clone());
+        Ok(dir)
+    }
+
+    /// Logs key events from the given sources instead of the devices, e.g.
+    /// from a remapping daemon or a scripted source in tests.
+    ///
+    /// Must be called before [`KeyLogger::start_logging`]. The sources are
+    /// used by the first logging thread only; a restart after a crash opens
+    /// the devices.
+    ///
+    /// # Arguments
+    /// * `sources` - The sources, read in this order on every pass
+    ///
+    /// # Example
+    /// A reset covers the keystrokes still queued when it arrives, backup
+    /// included, and only later keystrokes count toward the fresh statistics:
+    /// ```standalone_crate
+    /// use ctrlq::config::{Config, Overrides};
+    /// use ctrlq::keylogger::{EventSource, KeyEvent, KeyLogger, KeyState, ResetScope};
+    /// use std::collections::HashMap;
+    /// use std::sync::mpsc;
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// /// Hands out the events sent to it, like a device queue.
+    /// struct Scripted(mpsc::Receiver<KeyEvent>);
+    ///
+    /// impl EventSource for Scripted {
+    ///     fn label(&self) -> &str {
+    ///         "scripted"
+    ///     }
+    ///     fn read_events(&mut self) -> anyhow::Result<Vec<KeyEvent>> {
+    ///         Ok(self.0.try_iter().collect())
+    ///     }
+    /// }
+    ///
+    /// // A process of its own, so nothing else sees this environment
+    /// let dir = std::env::temp_dir().join(format!("ctrlq-reset-order-{}", std::process::id()));
+    /// unsafe {
+    ///     for var in ["CTRLQ_DATA_DIR", "CTRLQ_STATE_DIR", "CTRLQ_CONFIG_DIR"] {
+    ///         std::env::set_var(var, &dir);
+    ///     }
+    /// }
+    /// let type_key = |queue: &mpsc::Sender<KeyEvent>, code: u16, times: usize| {
+    ///     for _ in 0..times {
+    ///         for state in [KeyState::Pressed, KeyState::Released] {
+    ///             queue.send(KeyEvent { code, state, time: SystemTime::now() }).unwrap();
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// let (queue, events) = mpsc::channel();
+    /// let mut logger = KeyLogger::new(Vec::new(), Config::default(), Overrides::default(), "default")?;
+    /// logger.log_from(vec![Box::new(Scripted(events))]);
+    /// let channels = logger.start_logging()?;
+    /// let wait_for = |done: &dyn Fn(&HashMap<String, u64>) -> bool| loop {
+    ///     let stats = channels.stats_rx.recv_timeout(Duration::from_secs(10)).expect("no statistics");
+    ///     if done(&stats.key_counts) {
+    ///         break;
+    ///     }
+    /// };
+    ///
+    /// type_key(&queue, 30, 3);
+    /// wait_for(&|keys| keys.get("KEY_30") == Some(&3));
+    /// // Two more A still queued when the reset arrives, one B after it
+    /// type_key(&queue, 30, 2);
+    /// channels.reset_tx.send(ResetScope::All { keep_records: false })?;
+    /// wait_for(&|keys| keys.is_empty());
+    /// type_key(&queue, 48, 1);
+    /// wait_for(&|keys| keys.contains_key("KEY_48"));
+    /// channels.shutdown_tx.send(true)?;
+    /// logger.wait();
+    ///
+    /// let saved = KeyLogger::load_stats(&dir.join("keystroke_data.json"))?;
+    /// assert_eq!(saved.key_counts, HashMap::from([("KEY_48".to_string(), 1)]));
+    /// assert_eq!(saved.total_keystrokes, 1);
+    /// let backup = std::fs::read_dir(&dir)?
+    ///     .map(|entry| entry.unwrap().path())
+    ///     .find(|path| path.extension().is_some_and(|ext| ext == "bak"))
+    ///     .expect("backup before the reset");
+    /// assert_eq!(KeyLogger::load_stats(&backup)?.key_counts, HashMap::from([("KEY_30".to_string(), 5)]));
+    /// drop(logger);
+    /// std::fs::remove_dir_all(&dir)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn log_from(&mut self, sources: Vec<Box<dyn EventSource>>) {
+        self.sources = Some(sources);
+    }
+
+    /// Waits for the logging thread to finish its final save and export.
+    ///
+    /// Call after sending the shutdown signal; returns immediately if
+    /// logging was never started.
+    pub fn wait(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        self.is_running = false;
+    }
+
+    /// Starts the keystroke monitoring in a background thread.
+    ///
+    /// Creates communication channels for statistics updates, shutdown signals,
+    /// reset commands and notices. Spawns a background thread to handle the
+    /// actual keystroke monitoring loop.
+    ///
+    /// # Returns
+    /// `Result<LoggerChannels>` - Channels for talking to the running logger
+    pub fn start_logging(&mut self) -> Result<LoggerChannels> {
+        let (stats_tx, stats_rx) = mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (reset_tx, reset_rx) = mpsc::channel();
+        let (command_tx, command_rx) = mpsc::channel();
+        let (notice_tx, notice_rx) = mpsc::channel();
+        let (problem_tx, problem_rx) = mpsc::channel();
+        let (context_tx, context_rx) = mpsc::channel();
+        let (remote_tx, remote_rx) = mpsc::channel();
+        let (typed_tx, typed_rx) = mpsc::channel();
+        let (status_tx, status_rx) = watch::channel(StatusSnapshot::from_stats(&self.stats, today()));
+        
+        let device_paths = self.device_paths.clone();
+        let config = self.config.clone();
+        let overrides = self.overrides.clone();
+        let mut stats = self.stats.clone();
+        let data_file = self.data_file.clone();
+        let channels = WorkerChannels {
+            stats_tx,
+            notice_tx,
+            problem_tx: problem_tx.clone(),
+            typed_tx,
+            shutdown_rx,
+            reset_rx,
+            command_rx,
+            context_rx,
+            remote_rx,
+            live_tx: self.live_tx.clone(),
+            status_tx,
+            deadline: self.deadline,
+            raw_log: self.raw_log_dir.as_deref().map(RawLogWriter::open).transpose()?,
+            sources: self.sources.take(),
+        };
+        let export_path = self.export_path.clone();
+
+        // CLI commands such as `ctrlq reset` reach the worker through the socket
+        let control_tx = reset_tx.clone();
+        let export_tx = command_tx.clone();
+        let socket = ipc::socket_path(&self.data_file);
+        match ipc::serve(&socket, move |request| match request {
+            ipc::Request::Reset { scope, keep_records } => {
+                let scope = ResetScope::parse(&scope, keep_records)?;
+                control_tx.send(scope).context("logger is shutting down")?;
+                Ok(None)
+            }
+            ipc::Request::SetContext { value } => {
+                validate_context(&value)?;
+                context_tx.send(value).context("logger is shutting down")?;
+                Ok(None)
+            }
+            ipc::Request::Ping { count } => {
+                validate_remote_count(count)?;
+                remote_tx.send(count).context("logger is shutting down")?;
+                Ok(None)
+            }
+            ipc::Request::Exported => {
+                export_tx.send(LoggerCommand::MarkExported).context("logger is shutting down")?;
+                Ok(None)
+            }
+            ipc::Request::Status => Ok(Some(status_rx.borrow().clone())),
+        }) {
+            Ok(server) => self.control = Some(server),
+            Err(e) => {
+                let _ = problem_tx.send(LoggerProblem::Error(format!("Control socket disabled: {:#}", e)));
+            }
+        }
+        
+        self.is_running = true;
+
+        let pretty = config.pretty_json;
+        let helper_problem_tx = problem_tx.clone();
+        // Held until a crash is reported, so the UI learns why statistics
+        // stopped before it sees them stop
+        let stats_guard = channels.stats_tx.clone();
+        self.worker = Some(thread::Builder::new().name(LOGGER_THREAD.to_string()).spawn(move || {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                Self::logging_loop(
+                    &device_paths,
+                    config,
+                    overrides,
+                    &mut stats,
+                    data_file.clone(),
+                    channels,
+                    export_path.as_deref(),
+                )
+            }));
+            let message = match result {
+                Ok(Ok(())) => return,
+                Ok(Err(e)) => format!("{:#}", e),
+                Err(panic) => {
+                    // Keep what was counted up to the panic
+                    stats.end_session();
+                    let _ = Self::persist_stats(&mut stats, &data_file, pretty);
+                    panic_message(panic.as_ref())
+                }
+            };
+            if problem_tx.send(LoggerProblem::Crashed(message.clone())).is_err() {
+                eprintln!("Logging error: {}", message);
+            }
+            drop(stats_guard);
+        })?);
+
+        Ok(LoggerChannels {
+            stats_rx,
+            shutdown_tx,
+            reset_tx,
+            command_tx,
+            notice_rx,
+            problem_rx,
+            problem_tx: helper_problem_tx,
+            typed_rx,
+        })
+    }
+
+    /// Starts logging again after the logging thread stopped, e.g. after a
+    /// crash. Statistics are reloaded from the data file, which the crashed
+    /// thread saved on its way out, and a new session begins.
+    ///
+    /// # Returns
+    /// `Result<LoggerChannels>` - Channels for the new logging thread
+    pub fn restart_logging(&mut self) -> Result<LoggerChannels> {
+        self.wait();
+        // Removes the old socket so the new thread can listen on it
+        self.control = None;
+        let mut stats = Self::load_stats(&self.data_file)?;
+        stats.anonymized = self.stats.anonymized;
+        stats.case_sensitive = self.stats.case_sensitive;
+        stats.persist_sequences = self.stats.persist_sequences;
+        stats.start_session();
+        self.stats = stats;
+        self.start_logging()
+    }
+
+    /// Writes last week's summary if it is due, see [`summary::write_due`],
+    /// and reports the outcome to the UI.
+    fn write_due_summary(
+        stats: &KeyStats,
+        data_file: &Path,
+        notice_tx: &mpsc::Sender<String>,
+        problem_tx: &mpsc::Sender<LoggerProblem>,
+    ) {
+        match summary::write_due(stats, data_file, today()) {
+            Ok(Some(path)) => {
+                let _ = notice_tx.send(format!("📝 Weekly summary written to {}", path.display()));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                let _ = problem_tx.send(LoggerProblem::Error(format!("Weekly summary not written: {:#}", e)));
+            }
+        }
+    }
+
+    /// Builds the shortcuts counted as actions. A shortcut in the config that
+    /// doesn't parse is reported, and only the built-in actions are counted.
+    fn action_table(config: &Config, problem_tx: &mpsc::Sender<LoggerProblem>) -> ActionTable {
+        ActionTable::new(&config.actions).unwrap_or_else(|e| {
+            let _ = problem_tx.send(LoggerProblem::Error(format!("Custom actions not counted: {:#}", e)));
+            ActionTable::default()
+        })
+    }
+
+    /// Creates the hooks of the config; unknown events are reported and
+    /// leave all hooks off.
+    fn hooks(config: &Config, problem_tx: &mpsc::Sender<LoggerProblem>) -> Hooks {
+        Hooks::new(&config.hooks).unwrap_or_else(|e| {
+            let _ = problem_tx.send(LoggerProblem::Error(format!("Hooks not run: {:#}", e)));
+            Hooks::default()
+        })
+    }
+
+    /// Runs a hook and reports its failure as a problem.
+    fn run_hook(hooks: &Hooks, event: HookEvent, vars: Vec<(&'static str, String)>, problem_tx: &mpsc::Sender<LoggerProblem>) {
+        let problem_tx = problem_tx.clone();
+        hooks.run(event, vars, move |failure| {
+            let _ = problem_tx.send(LoggerProblem::Error(failure));
+        });
+    }
+
+    /// Runs the session_end hook for each session closed after the first
+    /// `seen` ones.
+    fn run_session_hooks(
+        hooks: &Hooks,
+        stats: &KeyStats,
+        data_file: &Path,
+        seen: usize,
+        problem_tx: &mpsc::Sender<LoggerProblem>,
+    ) {
+        if !hooks.has(HookEvent::SessionEnd) {
+            return;
+        }
+        for session in stats.typing_sessions.iter().skip(seen) {
+            let mut vars = hooks::event_vars(stats, data_file);
+            vars.extend(hooks::session_vars(session));
+            Self::run_hook(hooks, HookEvent::SessionEnd, vars, problem_tx);
+        }
+    }
+
+    /// Creates the notifier of the config. Without a session bus to send
+    /// them to, this says so once and notifications stay off.
+    fn notifier(config: &Config, problem_tx: &mpsc::Sender<LoggerProblem>) -> Notifier {
+        Notifier::new(config.notifications).unwrap_or_else(|e| {
+            let _ = problem_tx.send(LoggerProblem::Error(format!("Notifications disabled: {:#}", e)));
+            Notifier::disabled()
+        })
+    }
+
+    /// Reports a notification that couldn't be sent as a problem.
+    fn report_notification(sent: std::io::Result<()>, problem_tx: &mpsc::Sender<LoggerProblem>) {
+        if let Err(e) = sent {
+            let _ = problem_tx.send(LoggerProblem::Error(format!("Notification not sent: {}", e)));
+        }
+    }
+
+    /// Starts watching the screen lock for `pause_on_lock`. If logind can't
+    /// be reached, this says so once and logging goes on without pausing.
+    fn watch_lock(notice_tx: &mpsc::Sender<String>, problem_tx: &mpsc::Sender<LoggerProblem>) -> Option<LockWatcher> {
+        match LockWatcher::start() {
+            Ok(watcher) => {
+                let _ = notice_tx.send(format!("🔒 Pausing while session {} is locked", watcher.session()));
+                Some(watcher)
+            }
+            Err(e) => {
+                let _ = problem_tx.send(LoggerProblem::Error(format!("Not pausing on screen lock: {:#}", e)));
+                None
+            }
+        }
+    }
+
+    /// Main monitoring loop that runs in a background thread.
+    ///
+    /// Continuously monitors the specified device for keystroke events,
+    /// updates statistics, handles reset commands, and periodically saves data.
+    ///
+    /// Each pass handles, in this order: clock jumps and midnight, config
+    /// reloads, commands, screen lock, contexts and remote keystrokes, the
+    /// events of the devices, and last the resets. Resets are taken before
+    /// the devices are read and applied after their events, so a reset and
+    /// its backup cover every keystroke queued before the reset arrived, and
+    /// none of them ends up in the fresh statistics.
+    ///
+    /// # Arguments
+    /// * `device_paths` - Paths of the keyboard devices to monitor
+    /// * `config` - User configuration used to filter keys, reloaded when the file changes
+    /// * `overrides` - Command line settings re-applied after each reload
+    /// * `stats` - Mutable reference to the statistics structure
+    /// * `data_file` - Path to save statistics data
+    /// * `channels` - Channels for statistics updates, notices, shutdown and reset signals
+    /// * `export_path` - Where to write a session report on exit, if anywhere
+    fn logging_loop(
+        device_paths: &[String],
+        mut config: Config,
+        overrides: Overrides,
+        stats: &mut KeyStats,
+        data_file: PathBuf,
+        channels: WorkerChannels,
+        export_path: Option<&Path>,
+    ) -> Result<()> {
+        let WorkerChannels {
+            stats_tx,
+            notice_tx,
+            problem_tx,
+            typed_tx,
+            shutdown_rx,
+            reset_rx,
+            command_rx,
+            context_rx,
+            remote_rx,
+            live_tx,
+            status_tx,
+            deadline,
+            mut raw_log,
+            sources: custom_sources,
+        } = channels;
+        // The devices and the data file were announced by the caller
+        let mut sources: Vec<Box<dyn EventSource>> = match custom_sources {
+            Some(sources) => sources,
+            None => EvdevSource::open_all(device_paths)?
+                .into_iter()
+                .map(|source| Box::new(source) as Box<dyn EventSource>)
+                .collect(),
+        };
+        let mut modifiers: Vec<ModifierState> = sources.iter().map(|source| ModifierState::of(source.as_ref())).collect();
+        stats.diagnostics.devices = device_paths
+            .iter()
+            .zip(&sources)
+            .map(|(path, source)| LoggedDevice { path: path.clone(), label: source.label().to_string() })
+            .collect();
+        // Characters only say which keys were typed, like the keys themselves
+        let mut xkb = match XkbNames::resolve(&config) {
+            Some(names) if cfg!(feature = "xkb") && !config.anonymize => match XkbTranslator::new(&names) {
+                Ok(xkb) => {
+                    let _ = notice_tx.send(format!("🔤 Counting characters for XKB layout(s) {}", names.layout));
+                    Some(xkb)
+                }
+                Err(e) => {
+                    let _ = problem_tx.send(LoggerProblem::Error(format!("Characters not counted: {:#}", e)));
+                    None
+                }
+            },
+            _ => None,
+        };
+        if let (Some(xkb), Some(state)) = (&mut xkb, modifiers.first()) {
+            xkb.set_locks(state.locks());
+        }
+        
+        let mut held_keys = HeldKeys::new(config.max_hold());
+        let mut actions = Self::action_table(&config, &problem_tx);
+        let mut hooks = Self::hooks(&config, &problem_tx);
+        stats.diagnostics.set_ignore_rules(&config.ignore);
+        // Sessions closed from here on run the session_end hook
+        let mut sessions_seen = stats.typing_sessions.len();
+        let mut boundaries = BoundaryTracker::default();
+        let mut deletions = DeletionTracker::default();
+        let mut bigrams = BigramTracker::default();
+        let mut last_save = Instant::now();
+        let mut dirty = false;
+        let mut last_update = Instant::now();
+        // Changes not sent to the UI yet, see MIN_SNAPSHOT_INTERVAL
+        let mut unsent = false;
+        // max_tracked_keys is only reported once per run
+        let mut key_limit_warned = false;
+        // Loaded statistics differ from the empty ones the UI starts with
+        stats.bump_revision();
+        let mut notifier = Self::notifier(&config, &problem_tx);
+        let mut goal_notified_for: Option<String> = None;
+        let mut failed_saves = 0;
+        let mut config_watcher = ConfigWatcher::new();
+        let mut last_config_check = Instant::now();
+        let mut clock = ClockMonitor::new(Utc::now(), Instant::now());
+        let mut cadence = Cadence::new(config.idle_threshold(), Instant::now());
+        let mut watchdog = IdleWatchdog::new(config.warn_idle_after(), Instant::now());
+        let mut break_reminder = BreakReminder::new(config.break_after());
+        let mut device_check = config
+            .silent_device_after()
+            .map(|window| SilentDeviceCheck::new(window, config.silent_device_presses, Instant::now()));
+        // Read but never counted, only compared with the logged keyboards
+        let mut other_sources = if device_check.is_some() { other_keyboards(device_paths) } else { Vec::new() };
+        let mut lock_watcher = if config.pause_on_lock { Self::watch_lock(&notice_tx, &problem_tx) } else { None };
+        let mut power = PowerCheck::default();
+        let mut caps_timer = LockTimer::default();
+        let mut uptime_timer = LockTimer::default();
+        let mut current_day = today();
+        let mut paused = false;
+        // Day on which log_hours are overridden, see LoggerCommand::OutsideHours
+        let mut hours_override: Option<NaiveDate> = None;
+        // Whether the UI runs a typing test, see `ctrlq calibrate`
+        let mut capturing = false;
+        if config.auto_summary {
+            Self::write_due_summary(stats, &data_file, &notice_tx, &problem_tx);
+        }
+        
+        loop {
+            if shutdown_rx.has_changed().unwrap_or(false) && *shutdown_rx.borrow() {
+                break;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                let _ = notice_tx.send("⏱️  Time limit reached, stopping...".to_string());
+                break;
+            }
+
+            let mut changed = false;
+
+            if let Some(jump) = clock.check(Utc::now(), Instant::now()) {
+                // Suspend/resume or a clock change: keep the gap out of the session
+                stats.split_session_at(jump);
+                held_keys.clear();
+                changed = true;
+                let _ = Self::persist_stats(stats, &data_file, config.pretty_json);
+                last_save = Instant::now();
+                let _ = notice_tx.send(format!(
+                    "⏰ Clock jumped by {} - session closed at {}, new session started",
+                    report::format_duration(jump.gap().abs().to_std().unwrap_or_default()),
+                    jump.before.with_timezone(&chrono::Local).format("%H:%M")
+                ));
+            }
+
+            let day = today();
+            if day != current_day {
+                // Past midnight: close the books on yesterday
+                let keystrokes = stats.finish_day(current_day);
+                let new_week = day.iso_week() != current_day.iso_week();
+                current_day = day;
+                changed = true;
+                let _ = Self::persist_stats(stats, &data_file, config.pretty_json);
+                if let Some(log) = &mut raw_log {
+                    let _ = log.flush();
+                }
+                last_save = Instant::now();
+                let _ = notice_tx.send(format!("🌅 New day - {} keystrokes yesterday", numbers::format_count(keystrokes)));
+                if new_week && config.auto_summary {
+                    Self::write_due_summary(stats, &data_file, &notice_tx, &problem_tx);
+                }
+            }
+
+            if last_config_check.elapsed() >= Duration::from_secs(1) {
+                last_config_check = Instant::now();
+                match config_watcher.poll() {
+                    Some(Ok(mut new_config)) => {
+                        overrides.apply(&mut new_config);
+                        let notifications_changed = new_config.notifications != config.notifications;
+                        let needs_restart = config.apply_reload(new_config);
+                        numbers::set_separator(config.thousands_separator);
+                        cadence.set_idle_after(config.idle_threshold());
+                        watchdog.set_warn_after(config.warn_idle_after());
+                        break_reminder.set_remind_after(config.break_after());
+                        held_keys.set_max_hold(config.max_hold());
+                        actions = Self::action_table(&config, &problem_tx);
+                        hooks = Self::hooks(&config, &problem_tx);
+                        stats.diagnostics.set_ignore_rules(&config.ignore);
+                        stats.persist_sequences = config.persist_sequences;
+                        if notifications_changed {
+                            notifier = Self::notifier(&config, &problem_tx);
+                        }
+                        if config.pause_on_lock != lock_watcher.is_some() {
+                            lock_watcher = if config.pause_on_lock { Self::watch_lock(&notice_tx, &problem_tx) } else { None };
+                        }
+                        let notice = if needs_restart.is_empty() {
+                            "🔧 Config reloaded".to_string()
+                        } else {
+                            format!("🔧 Config reloaded (restart to apply: {})", needs_restart.join(", "))
+                        };
+                        let _ = notice_tx.send(notice);
+                    }
+                    Some(Err(e)) => {
+                        let _ = problem_tx.send(LoggerProblem::Error(format!("Config not reloaded: {:#}", e)));
+                    }
+                    None => {}
+                }
+            }
+
+            while let Ok(command) = command_rx.try_recv() {
+                match command {
+                    LoggerCommand::Pause | LoggerCommand::Resume => {
+                        paused = command == LoggerCommand::Pause;
+                        // Keys held across the pause would count as stuck
+                        held_keys.clear();
+                        let _ = notice_tx.send(
+                            if paused { "⏸️  Paused - keystrokes are not counted" } else { "▶️  Counting again" }.to_string(),
+                        );
+                    }
+                    LoggerCommand::Save => {
+                        match Self::persist_stats(stats, &data_file, config.pretty_json) {
+                            Ok(()) => {
+                                dirty = false;
+                                let _ = notice_tx.send(format!("💾 Saved to {}", data_file.display()));
+                                Self::run_hook(&hooks, HookEvent::Save, hooks::event_vars(stats, &data_file), &problem_tx);
+                            }
+                            Err(e) => {
+                                let _ = problem_tx.send(LoggerProblem::Error(format!("Failed to save stats: {:#}", e)));
+                            }
+                        }
+                        if let Some(log) = &mut raw_log {
+                            let _ = log.flush();
+                        }
+                        last_save = Instant::now();
+                    }
+                    LoggerCommand::MarkExported => stats.last_export_at = Some(Utc::now()),
+                    LoggerCommand::OutsideHours(on) => {
+                        hours_override = on.then_some(current_day);
+                        let _ = notice_tx.send(
+                            match (on, &config.log_hours) {
+                                (_, None) => "⏰ No log_hours in the config - keystrokes are always counted",
+                                (true, Some(_)) => "🌙 Counting outside logging hours until midnight",
+                                (false, Some(_)) => "⏰ Following the logging hours again",
+                            }
+                            .to_string(),
+                        );
+                    }
+                    LoggerCommand::Capture(on) => capturing = on,
+                    LoggerCommand::TagSession { tag, note } => {
+                        let _ = notice_tx.send(match &tag {
+                            Some(tag) => format!("🏷️  Session tagged {}", tag),
+                            None => "🏷️  Session tag removed".to_string(),
+                        });
+                        stats.session_tag = tag;
+                        stats.session_note = note;
+                    }
+                    // The new keyboard is opened before the old one is let go, so
+                    // a failure leaves logging as it was
+                    LoggerCommand::SwitchDevice(path) => match EvdevSource::open_all(std::slice::from_ref(&path)) {
+                        Ok(opened) => {
+                            sources = opened.into_iter().map(|source| Box::new(source) as Box<dyn EventSource>).collect();
+                            // The lock keys may have been pressed while it was unplugged
+                            modifiers = sources.iter().map(|source| ModifierState::of(source.as_ref())).collect();
+                            if let (Some(xkb), Some(state)) = (&mut xkb, modifiers.first()) {
+                                xkb.set_locks(state.locks());
+                            }
+                            held_keys.clear();
+                            watchdog = IdleWatchdog::new(config.warn_idle_after(), Instant::now());
+                            stats.diagnostics.idle_warning = None;
+                            stats.diagnostics.device_suggestion = None;
+                            device_check = None;
+                            other_sources.clear();
+                            // Only an auto-detected keyboard is remembered, so only that one is replaced
+                            if !profile::remembered_devices(&data_file).is_empty()
+                                && let Some(link) = device_alias(&path)
+                                && let Err(e) = profile::remember_devices(&data_file, &[link])
+                            {
+                                let _ = problem_tx.send(LoggerProblem::Error(format!("Keyboard not remembered: {:#}", e)));
+                            }
+                            let label = sources[0].label().to_string();
+                            let _ = notice_tx.send(format!(
+                                "🎯 Now logging {} ({})",
+                                truncate_name(&label, config.device_name_width),
+                                stable_device_path(&path)
+                            ));
+                            stats.diagnostics.devices = vec![LoggedDevice { path, label }];
+                        }
+                        Err(e) => {
+                            let _ = problem_tx.send(LoggerProblem::Error(format!("Device not switched: {:#}", e)));
+                        }
+                    },
+                }
+                changed = true;
+            }
+
+            // Paused like the manual pause, so the lock screen is simply idle time
+            let locked = lock_watcher.as_ref().is_some_and(LockWatcher::is_locked);
+            if locked != stats.diagnostics.screen_locked {
+                stats.diagnostics.screen_locked = locked;
+                held_keys.clear();
+                changed = true;
+                let _ = notice_tx.send(
+                    if locked { "🔒 Paused (screen locked) - keystrokes are not counted" } else { "🔓 Screen unlocked - counting again" }
+                        .to_string(),
+                );
+            }
+
+            // Dropped like while paused; a session ends at each edge of the hours
+            let now = chrono::Local::now().naive_local();
+            stats.diagnostics.hours_override = hours_override == Some(now.date()) && config.log_hours.is_some();
+            let outside_hours = config
+                .log_hours
+                .as_ref()
+                .filter(|schedule| !stats.diagnostics.hours_override && !schedule.contains(now))
+                .map(|schedule| schedule.hours_on(now.date()).to_string());
+            if outside_hours != stats.diagnostics.outside_hours {
+                if outside_hours.is_some() != stats.diagnostics.outside_hours.is_some() {
+                    stats.end_session();
+                    held_keys.clear();
+                    let _ = notice_tx.send(match &outside_hours {
+                        Some(hours) => format!("🌙 Outside logging hours ({} today) - keystrokes are not counted", hours),
+                        None => "⏰ Logging hours - counting again".to_string(),
+                    });
+                }
+                stats.diagnostics.outside_hours = outside_hours;
+                changed = true;
+            }
+
+            // Only disk writes change, so a failed reading just keeps the normal cadence
+            let on_battery = power.on_battery(Instant::now());
+            if on_battery != stats.diagnostics.power_saving {
+                stats.diagnostics.power_saving = on_battery;
+                let buffer_size = if on_battery { config.battery_raw_log_kb * 1024 } else { rawlog::DEFAULT_BUFFER_SIZE };
+                if let Some(log) = &mut raw_log {
+                    let _ = log.set_buffer_size(buffer_size);
+                }
+                changed = true;
+                let _ = notice_tx.send(if on_battery {
+                    format!("🔋 On battery - saving every {}s", config.battery_save_interval().as_secs())
+                } else {
+                    format!("🔌 On mains power - saving every {}s", config.save_interval().as_secs())
+                });
+            }
+
+            while let Ok(context) = context_rx.try_recv() {
+                stats.set_context(&context);
+                changed = true;
+                let _ = notice_tx.send(format!("📂 Context: {}", stats.current_context()));
+            }
+
+            // Typed where no logged keyboard sees it, e.g. over SSH
+            while let Ok(count) = remote_rx.try_recv() {
+                if paused || stats.diagnostics.screen_locked || stats.diagnostics.outside_hours.is_some() {
+                    continue;
+                }
+                stats.add_remote_keystrokes(count, config.remote_wpm, chrono::Local::now());
+                changed = true;
+            }
+
+            // Taken before the devices are read and applied after their
+            // events, so a reset covers everything typed before it arrived
+            let resets: Vec<ResetScope> = reset_rx.try_iter().collect();
+
+            for (index, source) in sources.iter_mut().enumerate() {
+                let result = source.read_events();
+                stats.diagnostics.record_event_types(source.take_event_types());
+                let events = match result {
+                    Ok(events) if !events.is_empty() => events,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        let message = format!("{:#}", e);
+                        stats.diagnostics.record_error(source.label(), &message, chrono::Local::now());
+                        // Only the first of a run of identical errors, the Debug tab counts the rest
+                        if stats.diagnostics.errors.back().is_some_and(|error| error.count == 1) {
+                            let label = truncate_name(source.label(), config.device_name_width);
+                            let _ = problem_tx.send(LoggerProblem::Error(format!("{}: {}", label, message)));
+                        }
+                        continue;
+                    }
+                };
+                if let Some(check) = &mut device_check {
+                    check.record_logged(character_presses(&events));
+                }
+                if let Some(silence) = watchdog.activity(Instant::now()) {
+                    stats.diagnostics.idle_warning = None;
+                    changed = true;
+                    let _ = notice_tx.send(format!(
+                        "✅ Key events are back after {}",
+                        report::format_duration(silence)
+                    ));
+                }
+                // Read but dropped while paused, so nothing is counted late on resume
+                if paused || stats.diagnostics.screen_locked || stats.diagnostics.outside_hours.is_some() {
+                    continue;
+                }
+                changed = true;
+                for event in events {
+                    stats.diagnostics.record_key_event(event);
+                    modifiers[index].update(event);
+                    held_keys.record(index, event, Instant::now(), &mut stats.diagnostics);
+                    let typed_char = xkb.as_mut().and_then(|xkb| xkb.feed(event));
+                    if capturing && event.state != KeyState::Released {
+                        let typed = match event.code {
+                            keymap::KEY_BACKSPACE => Some(Typed::Backspace),
+                            code => modifiers[index].typed_char(code).map(Typed::Char),
+                        };
+                        if let Some(typed) = typed {
+                            let device = source.label().to_string();
+                            let _ = typed_tx.send(TypedKey { device, code: event.code, typed, time: event.time });
+                        }
+                    }
+
+                    if !config.counts_key(event.code) {
+                        continue;
+                    }
+                    if !config.ignore.is_empty() {
+                        let path = stats.diagnostics.devices.get(index).map_or("", |device| device.path.as_str());
+                        let time = chrono::DateTime::<chrono::Local>::from(event.time).time();
+                        if let Some(rule) =
+                            config.ignore.iter().position(|rule| rule.matches(path, source.label(), event.code, time))
+                        {
+                            if event.state == KeyState::Pressed {
+                                stats.diagnostics.record_ignored(rule);
+                            }
+                            continue;
+                        }
+                    }
+                    if let Some(log) = &mut raw_log {
+                        let value = match event.state {
+                            KeyState::Released => 0,
+                            KeyState::Pressed => 1,
+                            KeyState::Repeated => 2,
+                        };
+                        if let Err(e) = log.append(event.time.into(), event.code, value) {
+                            let _ = problem_tx.send(LoggerProblem::Error(format!("Raw log disabled: {:#}", e)));
+                            raw_log = None;
+                        }
+                    }
+                    // Before the presses are filtered: a held Backspace deletes on every repeat
+                    stats.add_edit(deletions.event(event.code, event.state, Modifiers::of(held_keys.all_codes())));
+                    if event.state != KeyState::Pressed {
+                        continue;
+                    }
+                    let key_name = if config.anonymize {
+                        keymap::anonymized_name(event.code)
+                    } else if !keymap::is_known_code(event.code) {
+                        // The code itself is in the diagnostics, see record_key_event
+                        keymap::UNKNOWN_KEY.to_string()
+                    } else if config.case_sensitive {
+                        modifiers[index].key_name(event.code)
+                    } else {
+                        keymap::key_name(event.code)
+                    };
+                    let key_name = if stats.over_key_limit(&key_name, config.max_tracked_keys) {
+                        if !key_limit_warned {
+                            key_limit_warned = true;
+                            let _ = problem_tx.send(LoggerProblem::Error(format!(
+                                "{} distinct keys counted (max_tracked_keys): new keys are counted as {}",
+                                stats.key_counts.len(),
+                                keymap::UNKNOWN_KEY
+                            )));
+                        }
+                        keymap::UNKNOWN_KEY.to_string()
+                    } else {
+                        key_name
+                    };
+                    stats.add_keypress(&key_name);
+                    if keymap::classify(event.code) == KeyClass::Alpha
+                        && let Some(new_run) = modifiers[index].caps_letter()
+                    {
+                        stats.add_caps_lock_letter(new_run);
+                    }
+                    match break_reminder.key_press(Instant::now()) {
+                        Some(BreakEvent::Due(active)) => {
+                            stats.add_break_suggested();
+                            stats.diagnostics.break_due = Some(active);
+                            Self::report_notification(notifier.break_due(active), &problem_tx);
+                        }
+                        Some(BreakEvent::Taken) => {
+                            stats.add_break_taken();
+                            stats.diagnostics.break_due = None;
+                        }
+                        None => {}
+                    }
+                    if let Some(c) = typed_char {
+                        stats.add_char(c);
+                    }
+                    stats.add_device_keypress(source.label(), &key_name);
+                    stats.add_context_keypress();
+                    stats.add_boundary(boundaries.press(event.code, Instant::now()));
+                    // Pairs of keys say even more about what was typed than the keys
+                    if let Some((first, second)) = bigrams.press(event.code, Instant::now())
+                        && !config.anonymize
+                    {
+                        stats.add_bigram(first, second);
+                    }
+                    if keymap::classify(event.code) != KeyClass::Modifier {
+                        stats.rollover.record(rollover_depth(held_keys.codes(index)));
+                        // Modifiers of every source, so Ctrl on one half of a split keyboard counts
+                        if let Some(action) = actions.action(Modifiers::of(held_keys.all_codes()), event.code)
+                            && !config.anonymize
+                        {
+                            stats.add_action(action);
+                        }
+                    }
+                }
+            }
+
+            // After the key events, so a lock key press in the same batch
+            // isn't undone by the LEDs that still show the old state
+            for (index, source) in sources.iter_mut().enumerate() {
+                if let Some(locks) = source.take_lock_change() {
+                    modifiers[index].sync(locks);
+                    if let Some(xkb) = &mut xkb {
+                        xkb.set_locks(locks);
+                    }
+                }
+            }
+            let caps_lock = modifiers.iter().any(|state| state.locks().caps_lock);
+            if caps_lock != stats.diagnostics.caps_lock {
+                stats.diagnostics.caps_lock = caps_lock;
+                changed = true;
+            }
+            let counting = !paused && !stats.diagnostics.screen_locked && stats.diagnostics.outside_hours.is_none();
+            if let Some(on) = caps_timer.tick(caps_lock && counting, Instant::now()) {
+                stats.add_caps_lock_time(on);
+                // Saved and shown, but time passing is no typing activity
+                dirty = true;
+                unsent = true;
+            }
+            // Not dirty: an idle logger shouldn't write the disk just to say
+            // it ran, the uptime goes out with the next save
+            if let Some(ran) = uptime_timer.tick(counting, Instant::now()) {
+                stats.add_coverage_time(ran);
+                unsent = true;
+            }
+
+            for scope in resets {
+                if let ResetScope::All { .. } = scope {
+                    match Self::backup_stats(stats, &data_file) {
+                        Ok(backup) => {
+                            let _ = notice_tx.send(format!("💾 Backup saved to {}", backup.display()));
+                        }
+                        Err(e) => {
+                            let _ = problem_tx.send(LoggerProblem::Error(format!("Not reset, backup failed: {:#}", e)));
+                            continue;
+                        }
+                    }
+                }
+                stats.apply_reset(scope);
+                if let ResetScope::All { .. } = scope
+                    && let Err(e) = archive::clear(&data_file)
+                {
+                    let _ = problem_tx.send(LoggerProblem::Error(format!("Archive not cleared: {:#}", e)));
+                }
+                changed = true;
+                if let Err(e) = Self::persist_stats(stats, &data_file, config.pretty_json) {
+                    // Saved again with the next periodic save
+                    let _ = problem_tx.send(LoggerProblem::Error(format!("Reset not saved yet: {:#}", e)));
+                    continue;
+                }
+                let _ = notice_tx.send(scope.done_message().to_string());
+            }
+
+            if let Some(xkb) = &xkb {
+                stats.diagnostics.xkb_layout = xkb.active_layout();
+            }
+
+            if let Some(check) = &mut device_check {
+                for (path, source) in &mut other_sources {
+                    let presses = source.read_events().map_or(0, |events| character_presses(&events));
+                    source.take_event_types();
+                    if presses > 0 {
+                        check.record_other(path, source.label(), presses);
+                    }
+                }
+                match check.verdict(Instant::now()) {
+                    SilentDeviceVerdict::Pending => {}
+                    SilentDeviceVerdict::Fine => {
+                        changed |= stats.diagnostics.device_suggestion.take().is_some();
+                        device_check = None;
+                        other_sources.clear();
+                    }
+                    SilentDeviceVerdict::Silent(suggestion) => {
+                        // Stays until the logged keyboard is typed on or the device is switched
+                        other_sources.clear();
+                        if stats.diagnostics.device_suggestion.is_none() {
+                            let logged: Vec<String> = sources
+                                .iter()
+                                .map(|source| truncate_name(source.label(), config.device_name_width))
+                                .collect();
+                            let _ = notice_tx.send(format!(
+                                "⚠️  {} is silent, but '{}' was typed on - press 'd' to switch, or restart with -d {}",
+                                logged.join(", "),
+                                truncate_name(&suggestion.label, config.device_name_width),
+                                stable_device_path(&suggestion.path)
+                            ));
+                            stats.diagnostics.device_suggestion = Some(suggestion);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+
+            // Drop keys whose release never arrived so they don't skew hold times
+            for code in held_keys.expire(Instant::now(), &mut stats.diagnostics) {
+                changed = true;
+                // Keys that lose their release regularly would warn on every press
+                if stats.diagnostics.stuck_keys.get(&code) == Some(&1) {
+                    let _ = notice_tx.send(format!(
+                        "⚠️  {} looks stuck (no release for {}s)",
+                        keymap::display_name(&keymap::key_name(code)),
+                        config.max_hold().as_secs_f64()
+                    ));
+                }
+            }
+            // After the stuck-key cleanup, so a lost release can't hold the depth up
+            stats.rollover.current = rollover_depth(held_keys.all_codes());
+
+            if let Some(silence) = watchdog.probe_due(Instant::now())
+                && let Some(warning) = silence_warning(&mut sources, silence, config.device_name_width)
+            {
+                watchdog.warn();
+                let _ = notice_tx.send(format!("⚠️  {}", warning));
+                stats.diagnostics.idle_warning = Some(warning);
+                changed = true;
+            }
+
+            // The break is taken away from the keyboard, so nothing else notices it
+            if break_reminder.check(Instant::now()) == Some(BreakEvent::Taken) {
+                stats.add_break_taken();
+                stats.diagnostics.break_due = None;
+                let _ = notice_tx.send("☕ Break taken, welcome back".to_string());
+                changed = true;
+            }
+
+            let previous_records = stats.records.clone();
+            stats.update_records();
+            for record in stats.records.broken_since(&previous_records) {
+                Self::report_notification(notifier.record_broken(&record), &problem_tx);
+                let mut vars = hooks::event_vars(stats, &data_file);
+                vars.push(("CTRLQ_RECORD", record));
+                Self::run_hook(&hooks, HookEvent::RecordBroken, vars, &problem_tx);
+            }
+
+            if let Some(goal) = config.daily_goal {
+                let today = day_key(today());
+                let reached = stats.daily_stats.get(&today).is_some_and(|d| d.keystrokes >= goal);
+                if reached && goal_notified_for.as_deref() != Some(today.as_str()) {
+                    Self::report_notification(notifier.goal_reached(goal), &problem_tx);
+                    goal_notified_for = Some(today);
+                    let mut vars = hooks::event_vars(stats, &data_file);
+                    vars.push(("CTRLQ_GOAL", goal.to_string()));
+                    Self::run_hook(&hooks, HookEvent::DailyGoalReached, vars, &problem_tx);
+                }
+            }
+
+            // Closed by a clock jump or a reset; resets can also drop sessions
+            Self::run_session_hooks(&hooks, stats, &data_file, sessions_seen, &problem_tx);
+            sessions_seen = stats.typing_sessions.len();
+
+            if let Some(live_tx) = &live_tx {
+                let snapshot = LiveSnapshot::from_stats(stats);
+                live_tx.send_if_modified(|current| {
+                    if *current == snapshot {
+                        false
+                    } else {
+                        *current = snapshot;
+                        true
+                    }
+                });
+            }
+
+            let now = Instant::now();
+            if changed {
+                cadence.activity(now);
+                stats.bump_revision();
+            }
+            // Changes wait for the next snapshot slot rather than each
+            // sending a clone; unchanged snapshots still go out now and then
+            // for countdowns
+            unsent |= changed;
+            if (unsent && now.saturating_duration_since(last_update) >= MIN_SNAPSHOT_INTERVAL)
+                || cadence.snapshot_due(now, last_update)
+            {
+                if stats_tx.send(stats.clone()).is_err() {
+                    break;
+                }
+                status_tx.send_replace(StatusSnapshot::from_stats(stats, today()));
+                unsent = false;
+                last_update = Instant::now();
+            }
+
+            // Nothing new since the last save: leave the disk alone
+            dirty |= changed;
+            let save_interval =
+                if stats.diagnostics.power_saving { config.battery_save_interval() } else { config.save_interval() };
+            if dirty && last_save.elapsed() > save_interval {
+                match Self::persist_stats(stats, &data_file, config.pretty_json) {
+                    Ok(()) => {
+                        failed_saves = 0;
+                        dirty = false;
+                        Self::run_hook(&hooks, HookEvent::Save, hooks::event_vars(stats, &data_file), &problem_tx);
+                    }
+                    Err(e) => {
+                        let _ = problem_tx.send(LoggerProblem::Error(format!("Failed to save stats: {:#}", e)));
+                        failed_saves += 1;
+                        if failed_saves >= 3 {
+                            Self::report_notification(notifier.save_failed(&e.to_string(), failed_saves), &problem_tx);
+                        }
+                    }
+                }
+                if let Some(log) = &mut raw_log {
+                    let _ = log.flush();
+                }
+                last_save = Instant::now();
+            }
+
+            thread::sleep(cadence.poll_interval(Instant::now()));
+        }
+
+        if let Some(path) = export_path {
+            match report::write_session_export(path, &stats.current_session()) {
+                Ok(()) => {
+                    stats.last_export_at = Some(Utc::now());
+                    let _ = notice_tx.send(format!("📝 Session report written to {}", path.display()));
+                }
+                Err(e) => {
+                    let _ = problem_tx.send(LoggerProblem::Error(format!("Failed to write session report: {:#}", e)));
+                }
+            }
+        }
+
+        if let Some(log) = &mut raw_log {
+            let _ = log.flush();
+        }
+        if let Some(ran) = uptime_timer.tick(false, Instant::now()) {
+            stats.add_coverage_time(ran);
+        }
+        stats.end_session();
+        Self::run_session_hooks(&hooks, stats, &data_file, sessions_seen, &problem_tx);
+        match Self::persist_stats(stats, &data_file, config.pretty_json) {
+            Ok(()) => {
+                Self::run_hook(&hooks, HookEvent::Save, hooks::event_vars(stats, &data_file), &problem_tx);
+                let _ = notice_tx.send("📁 Keylogger stopped and data saved".to_string());
+            }
+            Err(e) => {
+                let _ = problem_tx.send(LoggerProblem::Error(format!("Failed to save stats: {:#}", e)));
+            }
+        }
+        Ok(())
     }
 
-    /// Gets a reference to the current keystroke statistics.
+    /// Saves keystroke statistics to a JSON file.
+    ///
+    /// Serializes the statistics as they are, archive-worthy days included,
+    /// and writes them to the specified file path. The last keys typed
+    /// ([`KeyStats::key_sequences`]) are left out unless
+    /// [`KeyStats::persist_sequences`] is set. The file is replaced with
+    /// [`paths::write_atomic`], so a crash or a full disk leaves the previous
+    /// save in place rather than half a file.
+    ///
+    /// # Arguments
+    /// * `stats` - Statistics to save
+    /// * `data_file` - File path to save to
+    /// * `pretty` - Whether to write indented JSON instead of compact JSON
     ///
     /// # Returns
-    /// `&KeyStats` - Reference to the current statistics
-    #[allow(dead_code)]
-    pub fn get_stats(&self) -> &KeyStats {
-        &self.stats
+    /// `Result<()>` - Success or file I/O error
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::{KeyLogger, DATA_FORMAT_VERSION};
+    ///
+    /// let file = std::env::temp_dir().join(format!("ctrlq-sequences-doc-{}.json", std::process::id()));
+    /// // A file from an older version, with the last keys typed in it
+    /// std::fs::write(&file, r#"{"key_counts":{"KEY_30":1},"key_sequences":["KEY_30"],
+    ///     "session_start":"2024-05-01T08:00:00Z","total_keystrokes":1,
+    ///     "typing_sessions":[],"daily_stats":{}}"#)?;
+    ///
+    /// // They are loaded once, for the Recent Keys of the UI...
+    /// let mut stats = KeyLogger::load_stats(&file)?;
+    /// assert_eq!((stats.format_version, stats.key_sequences.len()), (0, 1));
+    ///
+    /// // ...but not written back
+    /// KeyLogger::save_stats(&stats, &file, false)?;
+    /// let saved = KeyLogger::load_stats(&file)?;
+    /// assert_eq!((saved.format_version, saved.key_sequences.len()), (DATA_FORMAT_VERSION, 0));
+    ///
+    /// // Unless persist_sequences opts in
+    /// stats.persist_sequences = true;
+    /// KeyLogger::save_stats(&stats, &file, false)?;
+    /// assert_eq!(KeyLogger::load_stats(&file)?.key_sequences, vec!["KEY_30"]);
+    /// std::fs::remove_file(&file)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn save_stats(stats: &KeyStats, data_file: &Path, pretty: bool) -> Result<()> {
+        let saved = SavedStats {
+            format_version: DATA_FORMAT_VERSION,
+            stats,
+            key_sequences: stats.persist_sequences.then_some(stats.key_sequences.as_slice()),
+        };
+        paths::write_atomic(data_file, |writer| {
+            if pretty {
+                serde_json::to_writer_pretty(writer, &saved)?;
+            } else {
+                serde_json::to_writer(writer, &saved)?;
+            }
+            Ok(())
+        })
     }
 
-    /// Gets the path where keystroke data is saved.
+    /// Saves statistics to their data file after moving days older than
+    /// the previous month into the monthly archive, see [`archive`], and
+    /// updates the status file of `ctrlq status-line`.
+    ///
+    /// # Arguments
+    /// * `stats` - Statistics to save; archived days are removed from them
+    /// * `data_file` - The data file
+    /// * `pretty` - Whether to write indented JSON instead of compact JSON
     ///
     /// # Returns
-    /// `&PathBuf` - Reference to the data file path
-    pub fn get_data_path(&self) -> &PathBuf {
-        &self.data_file
+    /// `Result<()>` - Success or file I/O error
+    pub fn persist_stats(stats: &mut KeyStats, data_file: &Path, pretty: bool) -> Result<()> {
+        archive::archive_old_days(stats, data_file, today(), pretty)?;
+        Self::save_stats(stats, data_file, pretty)?;
+        // The status line falls back to this file; not worth failing a save for
+        let _ = StatusSnapshot::from_stats(stats, today()).save(&status_line::status_file(data_file));
+        Ok(())
     }
 
-    /// Starts the keylogger for UI mode with enhanced monitoring.
+    /// Writes a timestamped backup copy of the statistics, archived days
+    /// included, next to the data file.
     ///
-    /// Similar to `start_logging` but optimized for UI interaction
-    /// with more frequent statistics updates.
+    /// # Arguments
+    /// * `stats` - Statistics to back up
+    /// * `data_file` - The data file the backup belongs to
     ///
     /// # Returns
-    /// `Result<Receiver<KeyStats>>` - Channel for receiving statistics updates
-    pub fn start_ui_mode(&mut self) -> Result<mpsc::Receiver<KeyStats>> {
-        let (stats_tx, stats_rx) = mpsc::channel();
-        let device_path = self.device_path.clone();
-        let mut stats = self.stats.clone();
-        let data_file = self.data_file.clone();
-        
-        self.is_running = true;
+    /// `Result<PathBuf>` - Path of the backup, e.g. `keystroke_data.json.20240501-120000.bak`
+    pub fn backup_stats(stats: &KeyStats, data_file: &Path) -> Result<PathBuf> {
+        let file_name = data_file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "keystroke_data.json".to_string());
+        let backup = data_file.with_file_name(format!(
+            "{}.{}.bak",
+            file_name,
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        let mut full = stats.clone();
+        archive::load_into(&mut full, data_file)?;
+        Self::save_stats(&full, &backup, false)
+            .with_context(|| format!("failed to write backup {}", backup.display()))?;
+        Ok(backup)
+    }
+}
 
-        thread::spawn(move || {
-            // UI mode - more frequent updates
-            loop {
-                // Simplified UI monitoring loop
-                thread::sleep(Duration::from_millis(100));
-                let _ = stats_tx.send(stats.clone());
-            }
-        });
+/// Capabilities of an input device, as shown by `--list-devices`.
+#[derive(Debug, Clone, Serialize)]
+pub struct InputDeviceInfo {
+    /// Event device path (e.g. `/dev/input/event3`)
+    pub path: String,
+    /// Link to the device that survives reboots, see [`device_alias`]
+    pub stable_path: Option<String>,
+    /// Name reported by the device, see [`sanitize_device_name`]
+    pub name: Option<String>,
+    /// Name exactly as the kernel reports it, which need not be UTF-8
+    #[serde(skip)]
+    pub raw_name: Option<Vec<u8>>,
+    /// Physical path (e.g. `usb-0000:00:14.0-1/input0`)
+    pub physical_path: Option<String>,
+    /// USB or bus vendor ID
+    pub vendor_id: u16,
+    /// USB or bus product ID
+    pub product_id: u16,
+    /// Number of keys and buttons the device supports
+    pub key_count: usize,
+    /// Whether all letter keys A-Z are supported
+    pub has_letter_keys: bool,
+    /// Whether Enter and Space are supported
+    pub has_enter_and_space: bool,
+    /// Whether the device also reports relative axes (likely a mouse combo)
+    pub has_relative_axes: bool,
+    /// Whether the device reports absolute axes (touchpads, tablets, joysticks)
+    pub has_absolute_axes: bool,
+}
+
+impl InputDeviceInfo {
+    /// Reads the capabilities of an opened device.
+    ///
+    /// # Arguments
+    /// * `path` - Path the device was opened from
+    /// * `device` - The opened device
+    pub fn from_device(path: &str, device: &Device) -> Self {
+        let keys: Vec<u16> = device
+            .supported_keys()
+            .map(|keys| keys.iter().map(|key| key.code()).collect())
+            .unwrap_or_default();
+        let letters = keys.iter().filter(|&&code| keymap::classify(code) == KeyClass::Alpha).count();
+        let input_id = device.input_id();
+        let events = device.supported_events();
+        let raw_name = raw_device_name(path, device);
 
-        Ok(stats_rx)
+        Self {
+            path: path.to_string(),
+            stable_path: device_alias(path),
+            name: raw_name.as_deref().map(sanitize_device_name).filter(|name| !name.is_empty()),
+            raw_name,
+            physical_path: device.physical_path().map(str::to_string),
+            vendor_id: input_id.vendor(),
+            product_id: input_id.product(),
+            key_count: keys.len(),
+            has_letter_keys: letters == 26,
+            has_enter_and_space: keys.contains(&keymap::KEY_ENTER) && keys.contains(&keymap::KEY_SPACE),
+            has_relative_axes: events.contains(evdev::EventType::RELATIVE),
+            has_absolute_axes: events.contains(evdev::EventType::ABSOLUTE),
+        }
     }
 
-    /// Starts basic monitoring without UI integration.
+    /// Scores how much the device looks like a real keyboard.
     ///
-    /// Monitors keystrokes and saves data but doesn't provide
-    /// real-time statistics updates. Used for headless mode.
-    pub fn start_monitoring(&mut self) -> Result<()> {
-        let mut device = Device::open(&self.device_path)?;
-        println!("🎯 Keylogger started on device: {}", self.device_path);
-        
-        loop {
-            match device.fetch_events() {
-                Ok(events) => {
-                    for event in events {
-                        if event.event_type() == evdev::EventType::KEY && event.value() == 1 {
-                            let key_code = event.code();
-                            let key_name = format!("KEY_{}", key_code);
-                            self.stats.add_keypress(&key_name);
-                        }
-                    }
-                }
-                Err(_) => {
-                    thread::sleep(Duration::from_millis(10));
-                }
-            }
-            
-            // Periodic save
-            thread::sleep(Duration::from_millis(1000));
-            let _ = Self::save_stats(&self.stats, &self.data_file);
+    /// Letter keys, Enter and Space and "keyboard" in the name add points;
+    /// mouse axes and names of consumer-control or mouse interfaces take
+    /// points away. Devices above [`KEYBOARD_SCORE_THRESHOLD`] are confident
+    /// matches.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keylogger::{InputDeviceInfo, KEYBOARD_SCORE_THRESHOLD};
+    ///
+    /// let keyboard = InputDeviceInfo {
+    ///     path: "/dev/input/event3".to_string(),
+    ///     stable_path: Some("/dev/input/by-path/platform-i8042-serio-0-event-kbd".to_string()),
+    ///     name: Some("AT Translated Set 2 keyboard".to_string()),
+    ///     raw_name: None,
+    ///     physical_path: None,
+    ///     vendor_id: 1,
+    ///     product_id: 1,
+    ///     key_count: 120,
+    ///     has_letter_keys: true,
+    ///     has_enter_and_space: true,
+    ///     has_relative_axes: false,
+    ///     has_absolute_axes: false,
+    /// };
+    /// assert!(keyboard.score() > KEYBOARD_SCORE_THRESHOLD);
+    ///
+    /// // The consumer-control interface of the same keyboard: media keys only
+    /// let consumer = InputDeviceInfo {
+    ///     name: Some("Keychron K2 Consumer Control".to_string()),
+    ///     key_count: 300,
+    ///     has_letter_keys: false,
+    ///     has_enter_and_space: false,
+    ///     ..keyboard.clone()
+    /// };
+    /// assert!(consumer.score() < KEYBOARD_SCORE_THRESHOLD);
+    ///
+    /// // A mouse whose key interface reports letters for its macro buttons
+    /// let mouse = InputDeviceInfo {
+    ///     name: Some("Logitech G502 Mouse".to_string()),
+    ///     has_relative_axes: true,
+    ///     ..keyboard.clone()
+    /// };
+    /// assert!(mouse.score() < KEYBOARD_SCORE_THRESHOLD);
+    ///
+    /// // A name with invalid bytes and a NUL is matched on its sanitized form
+    /// let raw = b"Evil\xff\x00Keyboard".to_vec();
+    /// let garbled = InputDeviceInfo {
+    ///     name: Some(ctrlq::keylogger::sanitize_device_name(&raw)),
+    ///     raw_name: Some(raw),
+    ///     ..keyboard.clone()
+    /// };
+    /// assert!(garbled.score() > KEYBOARD_SCORE_THRESHOLD);
+    /// ```
+    pub fn score(&self) -> i32 {
+        let name = self.name.as_deref().unwrap_or("").to_lowercase();
+        let mut score = 0;
+        if self.has_letter_keys {
+            score += 40;
         }
+        if self.has_enter_and_space {
+            score += 20;
+        }
+        if name.contains("keyboard") {
+            score += 20;
+        }
+        if self.has_relative_axes {
+            score -= 30;
+        }
+        if self.has_absolute_axes {
+            score -= 30;
+        }
+        if name.contains("consumer control") || name.contains("mouse") {
+            score -= 50;
+        }
+        score
+    }
+
+    /// Name for display, cut to `width` characters, see [`truncate_name`].
+    pub fn display_name(&self, width: usize) -> String {
+        self.name.as_deref().map_or_else(|| "unnamed".to_string(), |name| truncate_name(name, width))
+    }
+
+    /// Whether the device looks like a keyboard at all.
+    pub fn is_keyboard_candidate(&self) -> bool {
+        self.has_letter_keys || self.key_count > 10
     }
+
+}
+
+/// Score above which a device is confidently a keyboard.
+pub const KEYBOARD_SCORE_THRESHOLD: i32 = 50;
+
+/// Picks the keyboard to log when none was given on the command line.
+///
+/// # Arguments
+/// * `devices` - Candidates from [`find_keyboard_devices`]
+///
+/// # Returns
+/// `Option<&InputDeviceInfo>` - The device, if exactly one scores above
+/// [`KEYBOARD_SCORE_THRESHOLD`]
+pub fn auto_select_keyboard(devices: &[InputDeviceInfo]) -> Option<&InputDeviceInfo> {
+    let mut confident = devices.iter().filter(|device| device.score() > KEYBOARD_SCORE_THRESHOLD);
+    let first = confident.next()?;
+    confident.next().is_none().then_some(first)
+}
+
+/// Outcome of scanning the input devices, see [`scan_input_devices`].
+#[derive(Debug, Clone, Default)]
+pub struct DeviceScan {
+    /// Likely keyboards, best candidate first
+    pub devices: Vec<InputDeviceInfo>,
+    /// Event devices looked at, whether or not they could be opened
+    pub scanned: usize,
+    /// Entries that couldn't be read or opened, e.g.
+    /// `/dev/input/event5: Permission denied (os error 13)`
+    pub warnings: Vec<String>,
 }
 
 /// Discovers available keyboard devices on the system.
 ///
-/// Scans `/dev/input/` directory for event devices and tests each one
-/// to determine if it's a keyboard by checking for key event support
-/// and a reasonable number of keys.
+/// Scans a directory of input devices for event devices with key support
+/// and ranks them by [`InputDeviceInfo::score`], then by key count. Entries
+/// that can't be read are skipped; [`scan_input_devices`] tells which.
+///
+/// # Arguments
+/// * `dir` - The directory to scan, usually [`paths::input_dir`]
+///
+/// # Returns
+/// `Result<Vec<InputDeviceInfo>>` - Likely keyboards, best candidate first
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::find_keyboard_devices;
+///
+/// // A container without devices mounted where they were expected
+/// let dir = std::env::temp_dir().join(format!("ctrlq-find-{}", std::process::id()));
+/// std::fs::create_dir_all(dir.join("by-id")).unwrap();
+/// std::fs::write(dir.join("event0"), "").unwrap();
+/// assert!(find_keyboard_devices(&dir).unwrap().is_empty());
+/// std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn find_keyboard_devices(dir: &Path) -> Result<Vec<InputDeviceInfo>> {
+    Ok(scan_input_devices(dir)?.devices)
+}
+
+/// Scans a directory of input devices for keyboards.
+///
+/// Only `event*` devices are looked at. Other files, directories such as
+/// `by-id` and links pointing nowhere are skipped silently; an entry that
+/// can't be read or an event device that can't be opened is skipped with a
+/// warning, so one broken entry doesn't stop the scan.
+///
+/// # Arguments
+/// * `dir` - The directory to scan, usually [`paths::input_dir`]
 ///
 /// # Returns
-/// `Result<Vec<String>>` - List of device paths that appear to be keyboards
-pub fn find_keyboard_devices() -> Result<Vec<String>> {
-    let mut devices = Vec::new();
-    
-    for entry in std::fs::read_dir("/dev/input")? {
-        let entry = entry?;
+/// `Result<DeviceScan>` - The keyboards found and the skipped entries, or
+/// an error if the directory itself can't be read
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::scan_input_devices;
+///
+/// let dir = std::env::temp_dir().join(format!("ctrlq-scan-{}", std::process::id()));
+/// std::fs::create_dir_all(dir.join("by-id")).unwrap();
+/// std::fs::create_dir_all(dir.join("event7")).unwrap();
+/// std::fs::write(dir.join("mouse0"), "").unwrap();
+/// std::fs::write(dir.join("event3"), "not a device").unwrap();
+/// std::os::unix::fs::symlink(dir.join("gone"), dir.join("event9")).unwrap();
+///
+/// let scan = scan_input_devices(&dir).unwrap();
+/// assert!(scan.devices.is_empty());
+/// assert_eq!(scan.scanned, 1);
+/// // Only the event device that isn't one is worth a warning
+/// assert_eq!(scan.warnings.len(), 1);
+/// assert!(scan.warnings[0].starts_with(&dir.join("event3").display().to_string()));
+///
+/// assert!(scan_input_devices(&dir.join("missing")).is_err());
+/// std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn scan_input_devices(dir: &Path) -> Result<DeviceScan> {
+    let mut scan = DeviceScan::default();
+    let entries = std::fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                scan.warnings.push(format!("{}: unreadable entry: {}", dir.display(), e));
+                continue;
+            }
+        };
         let path = entry.path();
-        
-        if let Some(filename) = path.file_name() {
-            if filename.to_string_lossy().starts_with("event") {
-                match Device::open(&path) {
-                    Ok(device) => {
-                        println!("🔍 Checking device: {}", path.display());
-                        if let Some(name) = device.name() {
-                            println!("   Name: {}", name);
-                            
-                            // Check if device supports key events
-                            if device.supported_events().contains(evdev::EventType::KEY) {
-                                if let Some(keys) = device.supported_keys() {
-                                    let key_count = keys.iter().count();
-                                    println!("   Supports {} keys", key_count);
-                                    
-                                    // More liberal detection - any device with key events
-                                    if key_count > 10 { // Reasonable threshold for a keyboard
-                                        devices.push(path.to_string_lossy().to_string());
-                                        println!("   ✅ Added as keyboard device");
-                                    } else {
-                                        println!("   ❌ Too few keys ({}) - likely not a keyboard", key_count);
-                                    }
-                                } else {
-                                    println!("   ❌ No key capabilities");
-                                }
-                            } else {
-                                println!("   ❌ No key events supported");
-                            }
-                        } else {
-                            println!("   ❌ No device name");
-                        }
-                    }
-                    Err(e) => {
-                        println!("   ❌ Failed to open: {}", e);
-                    }
-                }
+        if !entry.file_name().to_string_lossy().starts_with("event") {
+            continue;
+        }
+        // Follows links, so dangling ones fail here; devices aren't directories
+        match std::fs::metadata(&path) {
+            Ok(meta) if !meta.is_dir() => {}
+            _ => continue,
+        }
+
+        scan.scanned += 1;
+        let device = match Device::open(&path) {
+            Ok(device) => device,
+            Err(e) => {
+                scan.warnings.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+        if device.supported_events().contains(evdev::EventType::KEY) {
+            let info = InputDeviceInfo::from_device(&path.to_string_lossy(), &device);
+            if info.is_keyboard_candidate() {
+                scan.devices.push(info);
+            }
+        }
+    }
+
+    scan.devices.sort_by(|a, b| {
+        b.score()
+            .cmp(&a.score())
+            .then(b.key_count.cmp(&a.key_count))
+            .then(a.path.cmp(&b.path))
+    });
+    scan.warnings.sort();
+    Ok(scan)
+}
+
+/// Finds a name for a device that survives reboots and replugging.
+///
+/// Event numbers like `/dev/input/event3` are handed out in probe order, so
+/// they can change between boots. udev also links each keyboard from
+/// `/dev/input/by-id` (and `by-path`), which stays the same.
+///
+/// # Arguments
+/// * `path` - Event device path (e.g. `/dev/input/event3`)
+///
+/// # Returns
+/// `String` - The `by-id` or `by-path` link to the device, or `path` if there is none
+pub fn stable_device_path(path: &str) -> String {
+    device_alias(path).unwrap_or_else(|| path.to_string())
+}
+
+/// Finds the `/dev/input/by-id` link of a device, or its `by-path` link if
+/// it has no `by-id` one (built-in keyboards usually don't).
+///
+/// # Arguments
+/// * `path` - Event device path, or a link to it
+///
+/// # Returns
+/// `Option<String>` - The link, or None if udev made none
+pub fn device_alias(path: &str) -> Option<String> {
+    let target = std::fs::canonicalize(path).ok()?;
+    let input_dir = paths::input_dir();
+    for dir in [input_dir.join("by-id"), input_dir.join("by-path")] {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut links: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|link| std::fs::canonicalize(link).is_ok_and(|resolved| resolved == target))
+            .collect();
+        links.sort();
+        if let Some(link) = links.first() {
+            return Some(link.to_string_lossy().into_owned());
+        }
+    }
+    None
+}
+
+/// Resolves the devices to log to their event nodes.
+///
+/// Devices can be given as event nodes or as links to them, such as the
+/// `by-id` links [`stable_device_path`] picks. A device given twice under
+/// different names is only logged once.
+///
+/// # Arguments
+/// * `paths` - Device paths as given on the command line or in the config
+///
+/// # Returns
+/// `Result<Vec<(String, PathBuf)>>` - Each distinct device as given and its
+/// event node, or an error naming a missing device or a link to a removed one
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::resolve_devices;
+///
+/// let dir = std::env::temp_dir().join(format!("ctrlq-resolve-doc-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// let node = dir.join("event3");
+/// std::fs::write(&node, "")?;
+/// let link = dir.join("usb-Keychron_K2-event-kbd");
+/// std::os::unix::fs::symlink(&node, &link)?;
+///
+/// let given = [link.display().to_string(), node.display().to_string()];
+/// let resolved = resolve_devices(&given)?;
+/// assert_eq!(resolved, vec![(given[0].clone(), node.canonicalize()?)]);
+///
+/// // The keyboard was unplugged: udev removed the node, not yet the link
+/// std::fs::remove_file(&node)?;
+/// let error = resolve_devices(&given[..1]).unwrap_err().to_string();
+/// assert!(error.contains("no longer exists"));
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn resolve_devices(paths: &[String]) -> Result<Vec<(String, PathBuf)>> {
+    let mut resolved: Vec<(String, PathBuf)> = Vec::new();
+    for path in paths {
+        let node = match std::fs::canonicalize(path) {
+            Ok(node) => node,
+            Err(_) => match std::fs::read_link(path) {
+                Ok(target) => bail!(
+                    "{} points at {}, which no longer exists; is the keyboard unplugged?",
+                    path,
+                    target.display()
+                ),
+                Err(_) => bail!("device not found: {}", path),
+            },
+        };
+        if !resolved.iter().any(|(_, seen)| *seen == node) {
+            resolved.push((path.clone(), node));
+        }
+    }
+    Ok(resolved)
+}
+
+/// Listens on several devices at once until one of them has produced
+/// `presses` key presses, to find out which keyboard is being typed on.
+///
+/// Devices that can't be opened are skipped.
+///
+/// # Arguments
+/// * `paths` - Event device paths to listen on
+/// * `presses` - Key presses that identify a device
+/// * `timeout` - How long to wait at most
+/// * `on_press` - Called with the device path and its press count after every press
+///
+/// # Returns
+/// `Result<Option<String>>` - Path of the device that was typed on, `None`
+/// after the timeout, or an error if none of the devices could be opened
+pub fn identify_typing_device(
+    paths: &[String],
+    presses: u64,
+    timeout: Duration,
+    mut on_press: impl FnMut(&str, u64),
+) -> Result<Option<String>> {
+    let mut devices: Vec<(&String, Device)> = paths
+        .iter()
+        .filter_map(|path| {
+            let device = Device::open(path).ok()?;
+            device.set_nonblocking(true).ok()?;
+            Some((path, device))
+        })
+        .collect();
+    if devices.is_empty() {
+        bail!("none of the {} device(s) could be opened", paths.len());
+    }
+
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        for (path, device) in &mut devices {
+            let pressed = match device.fetch_events() {
+                Ok(events) => events
+                    .filter(|event| event.event_type() == evdev::EventType::KEY && event.value() == 1)
+                    .count() as u64,
+                Err(_) => 0,
+            };
+            if pressed == 0 {
+                continue;
+            }
+            let count = counts.entry(path.as_str()).or_insert(0);
+            *count += pressed;
+            on_press(path, *count);
+            if *count >= presses {
+                return Ok(Some(path.to_string()));
             }
         }
+        thread::sleep(ACTIVE_POLL_INTERVAL);
     }
-    
-    Ok(devices)
+    Ok(None)
 }
@@ -0,0 +1,739 @@
+//! # Keymap Module
+//!
+//! Maps raw evdev key codes to human-readable names and broad key classes.
+//! Statistics store keys as `KEY_<code>` strings, or as `CLASS_<class>` when
+//! captured in anonymized mode; this module is the single place that knows
+//! what those names actually mean.
+
+use evdev::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Evdev code of the space bar.
+pub const KEY_SPACE: u16 = 57;
+/// Evdev code of the left Shift key.
+pub const KEY_LEFTSHIFT: u16 = 42;
+/// Evdev code of the right Shift key.
+pub const KEY_RIGHTSHIFT: u16 = 54;
+/// Evdev code of the Caps Lock key.
+pub const KEY_CAPSLOCK: u16 = 58;
+/// Evdev code of the Num Lock key.
+pub const KEY_NUMLOCK: u16 = 69;
+/// Evdev code of the Enter key.
+pub const KEY_ENTER: u16 = 28;
+/// Evdev code of the backspace key.
+pub const KEY_BACKSPACE: u16 = 14;
+/// Evdev code of the (forward) Delete key.
+pub const KEY_DELETE: u16 = 111;
+/// Highest evdev key code (`KEY_MAX`); anything above it is not a key.
+pub const KEY_MAX: u16 = 0x2ff;
+
+/// Stored name of keystrokes with a code evdev doesn't know, and of new
+/// keys once `max_tracked_keys` is reached. Their codes are only kept in
+/// the diagnostics of the running logger.
+pub const UNKNOWN_KEY: &str = "UNKNOWN";
+
+/// Rows of the keyboard drawn by the heatmaps, as the printed labels and
+/// the evdev code of each key; the space bar comes below them.
+pub const HEATMAP_ROWS: [(&str, &[u16]); 4] = [
+    ("1234567890", &[2, 3, 4, 5, 6, 7, 8, 9, 10, 11]),
+    ("QWERTYUIOP", &[16, 17, 18, 19, 20, 21, 22, 23, 24, 25]),
+    ("ASDFGHJKL", &[30, 31, 32, 33, 34, 35, 36, 37, 38]),
+    ("ZXCVBNM", &[44, 45, 46, 47, 48, 49, 50]),
+];
+
+/// Broad category a key belongs to.
+///
+/// Used to group statistics on the Overview tab, to filter the Top Keys and
+/// Heatmap tabs, and to exclude whole groups of keys from counting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyClass {
+    /// Letters A-Z
+    Alpha,
+    /// Number row 0-9
+    Digit,
+    /// Punctuation and symbol keys on the main block
+    Punctuation,
+    /// Space, Enter and Tab
+    Whitespace,
+    /// Backspace, Delete and Insert
+    Editing,
+    /// Shift, Ctrl, Alt, Meta and Caps Lock
+    Modifier,
+    /// Arrow keys, Home/End and Page Up/Down
+    Navigation,
+    /// F1-F24
+    Function,
+    /// Keypad keys and Num Lock
+    Numpad,
+    /// Volume, playback, brightness and other consumer-control keys
+    Media,
+    /// Mouse buttons reported through a keyboard-like interface
+    Mouse,
+    /// Anything not covered above
+    Other,
+}
+
+impl KeyClass {
+    /// All classes in display order.
+    pub const ALL: [KeyClass; 12] = [
+        KeyClass::Alpha,
+        KeyClass::Digit,
+        KeyClass::Punctuation,
+        KeyClass::Whitespace,
+        KeyClass::Editing,
+        KeyClass::Modifier,
+        KeyClass::Navigation,
+        KeyClass::Function,
+        KeyClass::Numpad,
+        KeyClass::Media,
+        KeyClass::Mouse,
+        KeyClass::Other,
+    ];
+
+    /// Short human-readable label for the class.
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyClass::Alpha => "Alpha",
+            KeyClass::Digit => "Digit",
+            KeyClass::Punctuation => "Punctuation",
+            KeyClass::Whitespace => "Whitespace",
+            KeyClass::Editing => "Editing",
+            KeyClass::Modifier => "Modifier",
+            KeyClass::Navigation => "Navigation",
+            KeyClass::Function => "Function",
+            KeyClass::Numpad => "Numpad",
+            KeyClass::Media => "Media",
+            KeyClass::Mouse => "Mouse",
+            KeyClass::Other => "Other",
+        }
+    }
+
+    /// Classes the Top Keys and Heatmap filter cycles through.
+    pub const FILTERS: [KeyClass; 2] = [KeyClass::Alpha, KeyClass::Other];
+
+    /// Returns the class following `current` when cycling a filter,
+    /// where `None` stands for "all classes": All → Alpha → Other → All.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::keymap::KeyClass;
+    ///
+    /// assert_eq!(KeyClass::cycle(None), Some(KeyClass::Alpha));
+    /// assert_eq!(KeyClass::cycle(Some(KeyClass::Alpha)), Some(KeyClass::Other));
+    /// assert_eq!(KeyClass::cycle(Some(KeyClass::Other)), None);
+    /// // A class set some other way goes back to All
+    /// assert_eq!(KeyClass::cycle(Some(KeyClass::Media)), None);
+    /// ```
+    pub fn cycle(current: Option<KeyClass>) -> Option<KeyClass> {
+        match current {
+            None => Some(KeyClass::FILTERS[0]),
+            Some(class) => {
+                let index = KeyClass::FILTERS.iter().position(|c| *c == class)?;
+                KeyClass::FILTERS.get(index + 1).copied()
+            }
+        }
+    }
+}
+
+impl fmt::Display for KeyClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Classifies a raw evdev key code.
+///
+/// # Arguments
+/// * `code` - The evdev key code (e.g. 30 for `KEY_A`)
+///
+/// # Returns
+/// `KeyClass` - The class the key belongs to
+pub fn classify(code: u16) -> KeyClass {
+    match code {
+        16..=25 | 30..=38 | 44..=50 => KeyClass::Alpha,
+        2..=11 => KeyClass::Digit,
+        12 | 13 | 26 | 27 | 39 | 40 | 41 | 43 | 51..=53 | 86 => KeyClass::Punctuation,
+        15 | 28 | 57 => KeyClass::Whitespace,
+        14 | 110 | 111 => KeyClass::Editing,
+        29 | 42 | 54 | 56 | 58 | 97 | 100 | 125 | 126 => KeyClass::Modifier,
+        102..=109 => KeyClass::Navigation,
+        59..=68 | 87 | 88 | 183..=194 => KeyClass::Function,
+        55 | 69 | 71..=83 | 96 | 98 | 117 | 118 | 121 => KeyClass::Numpad,
+        113..=116 | 140..=182 | 195..=255 => KeyClass::Media,
+        0x110..=0x117 => KeyClass::Mouse,
+        _ => KeyClass::Other,
+    }
+}
+
+/// Checks whether a key produces a character when typed.
+///
+/// Letters, digits, punctuation and the space bar count as characters;
+/// modifiers, navigation, editing keys, Enter and Tab do not.
+pub fn is_character_key(code: u16) -> bool {
+    matches!(
+        classify(code),
+        KeyClass::Alpha | KeyClass::Digit | KeyClass::Punctuation
+    ) || code == KEY_SPACE
+}
+
+/// Suffix of key names typed with Shift (or Caps Lock) in case-sensitive mode.
+pub const SHIFTED_SUFFIX: &str = "_S";
+
+/// Extracts the evdev code from a stored key name such as `KEY_30`.
+///
+/// Shifted names (`KEY_30_S`) give the code of the physical key.
+///
+/// # Returns
+/// `Option<u16>` - The code, or None if the name is not in `KEY_<code>` form
+pub fn parse_code(key: &str) -> Option<u16> {
+    let key = key.strip_suffix(SHIFTED_SUFFIX).unwrap_or(key);
+    key.strip_prefix("KEY_")?.parse().ok()
+}
+
+/// Checks whether a stored key name was typed shifted (`KEY_<code>_S`).
+pub fn is_shifted_name(key: &str) -> bool {
+    key.ends_with(SHIFTED_SUFFIX) && parse_code(key).is_some()
+}
+
+/// Extracts the class from an anonymized key name such as `CLASS_alpha`.
+///
+/// # Returns
+/// `Option<KeyClass>` - The class, or None if the name is not in `CLASS_<class>` form
+pub fn parse_class(key: &str) -> Option<KeyClass> {
+    let name = key.strip_prefix("CLASS_")?;
+    KeyClass::ALL.into_iter().find(|class| class.label().eq_ignore_ascii_case(name))
+}
+
+/// Classifies a stored key name such as `KEY_30` or `CLASS_alpha`.
+///
+/// Names that cannot be parsed are reported as [`KeyClass::Other`].
+pub fn classify_name(key: &str) -> KeyClass {
+    parse_code(key)
+        .map(classify)
+        .or_else(|| parse_class(key))
+        .unwrap_or(KeyClass::Other)
+}
+
+/// Checks whether a stored key name stands for a character-producing key.
+///
+/// For anonymized names only letters, digits and punctuation count, since
+/// the space bar can't be told apart from Enter and Tab.
+pub fn is_character_name(key: &str) -> bool {
+    match parse_code(key) {
+        Some(code) => is_character_key(code),
+        None => matches!(
+            parse_class(key),
+            Some(KeyClass::Alpha | KeyClass::Digit | KeyClass::Punctuation)
+        ),
+    }
+}
+
+/// Returns the stored name for an evdev code (`KEY_<code>`).
+pub fn key_name(code: u16) -> String {
+    format!("KEY_{}", code)
+}
+
+/// Returns the stored name for a key typed with Shift (`KEY_<code>_S`).
+pub fn shifted_name(code: u16) -> String {
+    format!("KEY_{}{}", code, SHIFTED_SUFFIX)
+}
+
+/// Returns the characters a key types on a US layout, unshifted and shifted.
+///
+/// # Returns
+/// `Option<(char, char)>` - The pair, or None for keys that don't change
+/// with Shift (space, modifiers, navigation, ...)
+pub fn us_layout_chars(code: u16) -> Option<(char, char)> {
+    let pair = match code {
+        2 => ('1', '!'),
+        3 => ('2', '@'),
+        4 => ('3', '#'),
+        5 => ('4', '$'),
+        6 => ('5', '%'),
+        7 => ('6', '^'),
+        8 => ('7', '&'),
+        9 => ('8', '*'),
+        10 => ('9', '('),
+        11 => ('0', ')'),
+        12 => ('-', '_'),
+        13 => ('=', '+'),
+        26 => ('[', '{'),
+        27 => (']', '}'),
+        39 => (';', ':'),
+        40 => ('\'', '"'),
+        41 => ('`', '~'),
+        43 => ('\\', '|'),
+        51 => (',', '<'),
+        52 => ('.', '>'),
+        53 => ('/', '?'),
+        _ if classify(code) == KeyClass::Alpha => {
+            let name = format!("{:?}", KeyCode::new(code));
+            let letter = name.strip_prefix("KEY_")?.chars().next()?;
+            (letter.to_ascii_lowercase(), letter)
+        }
+        _ => return None,
+    };
+    Some(pair)
+}
+
+/// Returns the character a numpad key types.
+///
+/// The operators type with or without Num Lock; digits and the decimal
+/// point only with it, since without it they move the cursor.
+///
+/// # Example
+/// ```
+/// use ctrlq::keymap::numpad_char;
+///
+/// assert_eq!(numpad_char(79, true), Some('1')); // KP1
+/// assert_eq!(numpad_char(79, false), None); // End
+/// assert_eq!(numpad_char(78, false), Some('+'));
+/// assert_eq!(numpad_char(30, true), None); // not on the numpad
+/// ```
+pub fn numpad_char(code: u16, num_lock: bool) -> Option<char> {
+    let c = match code {
+        55 => '*',
+        74 => '-',
+        78 => '+',
+        98 => '/',
+        71..=73 => (b'7' + (code - 71) as u8) as char,
+        75..=77 => (b'4' + (code - 75) as u8) as char,
+        79..=81 => (b'1' + (code - 79) as u8) as char,
+        82 => '0',
+        83 => '.',
+        _ => return None,
+    };
+    let moves_cursor = c.is_ascii_digit() || c == '.';
+    (num_lock || !moves_cursor).then_some(c)
+}
+
+/// Checks whether Shift changes what a key types.
+pub fn is_shiftable(code: u16) -> bool {
+    us_layout_chars(code).is_some()
+}
+
+/// Converts a stored key name into the character it typed, telling `a`
+/// from `A` and `1` from `!`. Keys without a character fall back to
+/// [`display_name`].
+///
+/// # Example
+/// ```
+/// use ctrlq::keymap;
+///
+/// assert_eq!(keymap::typed_name(&keymap::key_name(30)), "a");
+/// assert_eq!(keymap::typed_name(&keymap::shifted_name(30)), "A");
+/// assert_eq!(keymap::typed_name(&keymap::shifted_name(2)), "!");
+/// assert_eq!(keymap::typed_name(&keymap::key_name(57)), "SPACE");
+/// ```
+pub fn typed_name(key: &str) -> String {
+    match parse_code(key).and_then(us_layout_chars) {
+        Some((_, shifted)) if is_shifted_name(key) => shifted.to_string(),
+        Some((plain, _)) => plain.to_string(),
+        None => display_name(key),
+    }
+}
+
+/// What a keystroke typed, for telling coding from writing: letters and
+/// prose punctuation make up text, symbols such as `{}();=<>` make up code.
+///
+/// Coarser than [`KeyClass`] where that doesn't matter (navigation and
+/// editing keys, everything else) and finer where it does: punctuation is
+/// split into prose punctuation and code symbols by the character typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    /// Letters, either case
+    Letters,
+    /// Digits of the number row
+    Digits,
+    /// Programming punctuation: brackets, operators, `;`, `_` and the like
+    Symbols,
+    /// Punctuation of prose, see [`PROSE_PUNCTUATION`]
+    Punctuation,
+    /// Space, Enter and Tab
+    Whitespace,
+    /// Arrow keys, Home/End, Page Up/Down, Backspace, Delete and Insert
+    Navigation,
+    /// Modifiers, function keys, the keypad and anything else
+    Other,
+}
+
+/// Characters that count as prose punctuation; any other printable
+/// character that is neither a letter nor a digit is a code symbol.
+pub const PROSE_PUNCTUATION: &str = ".,'\"?!";
+
+/// Share of code symbols among the typed characters from which a day or
+/// session counts as coding, see [`CategoryBreakdown::is_coding`]. Prose
+/// stays below 1%, source code is around 15%.
+pub const CODING_SYMBOL_RATIO: f64 = 0.05;
+
+/// Fewest typed characters [`CategoryBreakdown::is_coding`] decides on.
+pub const CODING_MIN_CHARS: u64 = 200;
+
+impl Category {
+    /// All categories in display order.
+    pub const ALL: [Category; 7] = [
+        Category::Letters,
+        Category::Digits,
+        Category::Symbols,
+        Category::Punctuation,
+        Category::Whitespace,
+        Category::Navigation,
+        Category::Other,
+    ];
+
+    /// Short human-readable label for the category.
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Letters => "Letters",
+            Category::Digits => "Digits",
+            Category::Symbols => "Symbols",
+            Category::Punctuation => "Punctuation",
+            Category::Whitespace => "Whitespace",
+            Category::Navigation => "Navigation",
+            Category::Other => "Other",
+        }
+    }
+
+    /// Whether keystrokes of the category type a character other than
+    /// whitespace.
+    pub fn is_character(self) -> bool {
+        matches!(self, Category::Letters | Category::Digits | Category::Symbols | Category::Punctuation)
+    }
+
+    /// Returns the category of a typed character.
+    pub fn of_char(c: char) -> Self {
+        if c.is_alphabetic() {
+            Category::Letters
+        } else if c.is_numeric() {
+            Category::Digits
+        } else if c.is_whitespace() {
+            Category::Whitespace
+        } else if PROSE_PUNCTUATION.contains(c) {
+            Category::Punctuation
+        } else if c.is_control() {
+            Category::Other
+        } else {
+            Category::Symbols
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Returns the category of a stored key name.
+///
+/// Shifted names (`KEY_10_S`) count as the character they typed, so with
+/// `--case-sensitive` a `(` is a symbol. Without it a key counts as what it
+/// types unshifted: `(` as the digit 9 and `<` as a comma. Anonymized
+/// punctuation can't be split and counts as symbols.
+///
+/// # Example
+/// ```
+/// use ctrlq::keymap::{self, Category};
+///
+/// assert_eq!(keymap::categorize(&keymap::key_name(30)), Category::Letters);
+/// assert_eq!(keymap::categorize(&keymap::key_name(10)), Category::Digits);
+/// assert_eq!(keymap::categorize(&keymap::shifted_name(10)), Category::Symbols);
+/// assert_eq!(keymap::categorize(&keymap::key_name(52)), Category::Punctuation);
+/// assert_eq!(keymap::categorize(&keymap::shifted_name(52)), Category::Symbols);
+/// assert_eq!(keymap::categorize(&keymap::key_name(103)), Category::Navigation);
+/// assert_eq!(keymap::categorize(&keymap::key_name(14)), Category::Navigation);
+/// assert_eq!(keymap::categorize("CLASS_whitespace"), Category::Whitespace);
+/// assert_eq!(keymap::categorize(&keymap::key_name(29)), Category::Other);
+/// ```
+pub fn categorize(key: &str) -> Category {
+    if let Some((plain, shifted)) = parse_code(key).and_then(us_layout_chars) {
+        return Category::of_char(if is_shifted_name(key) { shifted } else { plain });
+    }
+    match classify_name(key) {
+        KeyClass::Alpha => Category::Letters,
+        KeyClass::Digit => Category::Digits,
+        KeyClass::Punctuation => Category::Symbols,
+        KeyClass::Whitespace => Category::Whitespace,
+        KeyClass::Navigation | KeyClass::Editing => Category::Navigation,
+        _ => Category::Other,
+    }
+}
+
+/// Keystrokes per [`Category`], e.g. of a day or a session.
+///
+/// # Example
+/// ```
+/// use ctrlq::keymap::{self, Category, CategoryBreakdown};
+/// use std::collections::HashMap;
+///
+/// // `fn(x);` typed case-sensitive: letters, parentheses and a semicolon
+/// let keys = HashMap::from([
+///     (keymap::key_name(33), 100),
+///     (keymap::key_name(49), 100),
+///     (keymap::shifted_name(10), 50),
+///     (keymap::shifted_name(11), 50),
+///     (keymap::key_name(39), 50),
+///     (keymap::key_name(57), 50),
+/// ]);
+/// let breakdown = CategoryBreakdown::of(&keys);
+/// assert_eq!(breakdown.get(Category::Symbols), 150);
+/// assert_eq!(breakdown.share(Category::Whitespace), Some(0.125));
+/// assert_eq!(breakdown.symbol_ratio(), Some(150.0 / 350.0));
+/// assert_eq!(breakdown.is_coding(), Some(true));
+///
+/// assert_eq!(CategoryBreakdown::default().symbol_ratio(), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CategoryBreakdown {
+    /// Keystrokes by category; categories without any are left out
+    pub counts: BTreeMap<Category, u64>,
+}
+
+impl CategoryBreakdown {
+    /// Sums counts by stored key name into categories, see [`categorize`].
+    pub fn of(counts: &HashMap<String, u64>) -> Self {
+        let mut breakdown = Self::default();
+        for (key, &count) in counts {
+            breakdown.add(categorize(key), count);
+        }
+        breakdown
+    }
+
+    /// Adds keystrokes to a category.
+    pub fn add(&mut self, category: Category, count: u64) {
+        if count > 0 {
+            *self.counts.entry(category).or_insert(0) += count;
+        }
+    }
+
+    /// Keystrokes of a category.
+    pub fn get(&self, category: Category) -> u64 {
+        self.counts.get(&category).copied().unwrap_or(0)
+    }
+
+    /// Keystrokes of all categories.
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Whether no keystroke was counted.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Share of a category among all keystrokes (0.0-1.0), None without keystrokes.
+    pub fn share(&self, category: Category) -> Option<f64> {
+        let total = self.total();
+        (total > 0).then(|| self.get(category) as f64 / total as f64)
+    }
+
+    /// Typed characters: letters, digits, symbols and punctuation.
+    pub fn characters(&self) -> u64 {
+        self.counts.iter().filter(|(category, _)| category.is_character()).map(|(_, count)| count).sum()
+    }
+
+    /// Share of code symbols among the typed characters (0.0-1.0), the
+    /// symbol ratio; None without characters.
+    pub fn symbol_ratio(&self) -> Option<f64> {
+        let characters = self.characters();
+        (characters > 0).then(|| self.get(Category::Symbols) as f64 / characters as f64)
+    }
+
+    /// Whether the keystrokes look like coding rather than writing, by
+    /// [`CODING_SYMBOL_RATIO`]; None below [`CODING_MIN_CHARS`] characters.
+    pub fn is_coding(&self) -> Option<bool> {
+        if self.characters() < CODING_MIN_CHARS {
+            return None;
+        }
+        Some(self.symbol_ratio()? >= CODING_SYMBOL_RATIO)
+    }
+}
+
+/// Checks whether a key code is in evdev's key range and has a name there.
+/// Codes without one usually come from vendor-specific or misbehaving
+/// hardware.
+///
+/// # Example
+/// ```
+/// use ctrlq::keymap;
+///
+/// assert!(keymap::is_known_code(30));
+/// assert!(keymap::is_known_code(0x110));
+/// assert!(!keymap::is_known_code(keymap::KEY_MAX + 1));
+/// assert!(!keymap::is_known_code(60000));
+///
+/// assert!(keymap::is_junk_name("KEY_60000"));
+/// assert!(keymap::is_junk_name("KEY_60000_S"));
+/// assert!(!keymap::is_junk_name("KEY_30"));
+/// assert!(!keymap::is_junk_name(keymap::UNKNOWN_KEY));
+/// assert!(!keymap::is_junk_name("CLASS_alpha"));
+/// ```
+pub fn is_known_code(code: u16) -> bool {
+    code <= KEY_MAX && !format!("{:?}", KeyCode::new(code)).starts_with("unknown")
+}
+
+/// Checks whether a stored key name has a code evdev doesn't know, as
+/// written by versions before [`UNKNOWN_KEY`]; `ctrlq compact` folds them
+/// into it.
+pub fn is_junk_name(key: &str) -> bool {
+    parse_code(key).is_some_and(|code| !is_known_code(code))
+}
+
+/// Returns the anonymized name for an evdev code (`CLASS_<class>`), which
+/// only records the class of the key.
+pub fn anonymized_name(code: u16) -> String {
+    format!("CLASS_{}", classify(code).label().to_lowercase())
+}
+
+/// Converts a stored key name into a short label for display.
+///
+/// `KEY_30` becomes `A`, `KEY_57` becomes `SPACE`, and mouse buttons keep
+/// their `BTN_` prefix so they stand out. Anonymized names show their class
+/// (`CLASS_alpha` becomes `Alpha`). Unknown codes fall back to the raw number.
+pub fn display_name(key: &str) -> String {
+    if let Some(class) = parse_class(key) {
+        return class.label().to_string();
+    }
+    let Some(code) = parse_code(key) else {
+        return key.to_string();
+    };
+    if is_shifted_name(key) {
+        return format!("⇧{}", display_name(&key_name(code)));
+    }
+
+    let evdev_name = format!("{:?}", KeyCode::new(code));
+    if let Some(name) = evdev_name.strip_prefix("KEY_") {
+        name.to_string()
+    } else if evdev_name.starts_with("BTN_") {
+        evdev_name
+    } else {
+        code.to_string()
+    }
+}
+
+/// Resolves a key label from another tool into an evdev code.
+///
+/// Accepts evdev names (`KEY_A`, `BTN_SIDE`), bare names in any case
+/// (`a`, `Space`, `Page Up`), common aliases (`Return`, `Ctrl`, `Win`) and
+/// the symbol printed on US keyboards for punctuation keys (`;`, `/`, `[`).
+///
+/// # Arguments
+/// * `label` - The key label to resolve
+///
+/// # Returns
+/// `Option<u16>` - The evdev code, or None if the label is unknown
+pub fn code_from_label(label: &str) -> Option<u16> {
+    let label = label.trim();
+    if label.is_empty() {
+        return None;
+    }
+
+    let symbol = match label {
+        "," | "<" => Some("COMMA"),
+        "." | ">" => Some("DOT"),
+        "/" | "?" => Some("SLASH"),
+        ";" | ":" => Some("SEMICOLON"),
+        "'" | "\"" => Some("APOSTROPHE"),
+        "[" | "{" => Some("LEFTBRACE"),
+        "]" | "}" => Some("RIGHTBRACE"),
+        "\\" | "|" => Some("BACKSLASH"),
+        "-" | "_" => Some("MINUS"),
+        "=" | "+" => Some("EQUAL"),
+        "`" | "~" => Some("GRAVE"),
+        " " => Some("SPACE"),
+        _ => None,
+    };
+
+    let normalized = match symbol {
+        Some(name) => name.to_string(),
+        None => label.to_uppercase().replace([' ', '-'], "_"),
+    };
+
+    let alias = match normalized.as_str() {
+        "ESCAPE" => "ESC",
+        "RETURN" => "ENTER",
+        "CTRL" | "CONTROL" | "LCTRL" | "LEFT_CTRL" | "LEFT_CONTROL" => "LEFTCTRL",
+        "RCTRL" | "RIGHT_CTRL" | "RIGHT_CONTROL" => "RIGHTCTRL",
+        "SHIFT" | "LSHIFT" | "LEFT_SHIFT" => "LEFTSHIFT",
+        "RSHIFT" | "RIGHT_SHIFT" => "RIGHTSHIFT",
+        "ALT" | "LALT" | "LEFT_ALT" => "LEFTALT",
+        "RALT" | "RIGHT_ALT" | "ALTGR" | "ALT_GR" => "RIGHTALT",
+        "WIN" | "SUPER" | "META" | "LWIN" | "LEFT_WINDOWS" | "WINDOWS" => "LEFTMETA",
+        "RWIN" | "RIGHT_WINDOWS" => "RIGHTMETA",
+        "CAPS" | "CAPS_LOCK" => "CAPSLOCK",
+        "NUM_LOCK" => "NUMLOCK",
+        "SCROLL_LOCK" => "SCROLLLOCK",
+        "PGUP" | "PAGE_UP" => "PAGEUP",
+        "PGDN" | "PAGE_DOWN" => "PAGEDOWN",
+        "DEL" => "DELETE",
+        "INS" => "INSERT",
+        "BKSP" | "BACK" => "BACKSPACE",
+        "ARROW_UP" | "UP_ARROW" => "UP",
+        "ARROW_DOWN" | "DOWN_ARROW" => "DOWN",
+        "ARROW_LEFT" | "LEFT_ARROW" => "LEFT",
+        "ARROW_RIGHT" | "RIGHT_ARROW" => "RIGHT",
+        "PRINT_SCREEN" | "PRTSC" => "SYSRQ",
+        other => other,
+    };
+
+    if alias.starts_with("KEY_") || alias.starts_with("BTN_") {
+        return KeyCode::from_str(alias).ok().map(KeyCode::code);
+    }
+    KeyCode::from_str(&format!("KEY_{}", alias)).ok().map(KeyCode::code)
+}
+
+/// Checks the key tables against each other, for `ctrlq doctor --offline`:
+/// no character is typed by two keys, the symbol printed on each key
+/// resolves back to it with [`code_from_label`], stored names round-trip
+/// through [`parse_code`], and the heatmap rows match their labels without
+/// listing a key twice.
+///
+/// # Returns
+/// `Vec<String>` - Problems found, empty if the tables agree
+///
+/// # Example
+/// ```
+/// assert!(ctrlq::keymap::self_check().is_empty());
+/// ```
+pub fn self_check() -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut typed_by: HashMap<char, u16> = HashMap::new();
+    for code in 0..=KEY_MAX {
+        if parse_code(&key_name(code)) != Some(code) || parse_code(&shifted_name(code)) != Some(code) {
+            problems.push(format!("{} doesn't parse back to its code", key_name(code)));
+        }
+        let Some((plain, shifted)) = us_layout_chars(code) else {
+            continue;
+        };
+        for ch in [plain, shifted] {
+            if let Some(other) = typed_by.insert(ch, code) {
+                problems.push(format!("'{}' is typed by both KEY_{} and KEY_{}", ch, other, code));
+            }
+        }
+        if code_from_label(&plain.to_string()) != Some(code) {
+            problems.push(format!("'{}' doesn't resolve to KEY_{}", plain, code));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for (labels, codes) in HEATMAP_ROWS {
+        if labels.chars().count() != codes.len() {
+            problems.push(format!("heatmap row {} has {} labels for {} keys", labels, labels.chars().count(), codes.len()));
+        }
+        for (label, &code) in labels.chars().zip(codes) {
+            if !seen.insert(code) {
+                problems.push(format!("the heatmap lists KEY_{} twice", code));
+            }
+            if code_from_label(&label.to_string()) != Some(code) {
+                problems.push(format!("heatmap label '{}' isn't KEY_{}", label, code));
+            }
+        }
+    }
+    problems
+}
@@ -0,0 +1,434 @@
+//! # Layout Module
+//!
+//! A keystroke cost model for comparing keyboard layouts. Every physical
+//! character key has an effort based on the finger that presses it, its row
+//! and its distance from the home position; 1.0 is the index finger resting
+//! on its home key. Statistics store physical keys, so the same
+//! distribution can be priced on another layout by looking up which key
+//! types each character there.
+//!
+//! Only the character keys of the main block take part: space, Enter and
+//! modifiers cost the same on every layout and would only dilute the
+//! comparison.
+//!
+//! The same keys also have a finger each, as taught for touch typing, which
+//! finds same-finger bigrams: two keys in a row pressed by one finger, the
+//! thing layout designers most try to avoid. See [`same_finger_bigrams`].
+
+use crate::keymap;
+use anyhow::{bail, Result};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Physical keys of the number, top, home and bottom row, left to right
+/// (evdev codes, which follow the positions of a US keyboard).
+const ROWS: [&[u16]; 4] = [
+    &[2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13],
+    &[16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27],
+    &[30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40],
+    &[44, 45, 46, 47, 48, 49, 50, 51, 52, 53],
+];
+
+/// Effort of each key in [`ROWS`]. Home keys of the strong fingers are
+/// cheapest; reaching to the middle columns, up to the number row or down
+/// with the pinky costs the most.
+const EFFORT: [&[f64]; 4] = [
+    &[4.0, 3.5, 3.0, 3.0, 3.5, 4.0, 3.0, 3.0, 3.5, 4.0, 4.5, 5.0],
+    &[3.0, 2.4, 2.0, 2.2, 3.2, 3.2, 2.2, 2.0, 2.4, 3.0, 4.0, 4.5],
+    &[1.6, 1.3, 1.1, 1.0, 2.9, 2.9, 1.0, 1.1, 1.3, 1.6, 3.0],
+    &[3.2, 2.6, 2.3, 1.6, 3.0, 3.0, 1.6, 2.3, 2.6, 3.2],
+];
+
+/// A finger of touch typing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Finger {
+    /// Left little finger
+    LeftPinky,
+    /// Left ring finger
+    LeftRing,
+    /// Left middle finger
+    LeftMiddle,
+    /// Left index finger
+    LeftIndex,
+    /// Right index finger
+    RightIndex,
+    /// Right middle finger
+    RightMiddle,
+    /// Right ring finger
+    RightRing,
+    /// Right little finger
+    RightPinky,
+}
+
+impl Finger {
+    /// Name for display, e.g. `left middle`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Finger::LeftPinky => "left pinky",
+            Finger::LeftRing => "left ring",
+            Finger::LeftMiddle => "left middle",
+            Finger::LeftIndex => "left index",
+            Finger::RightIndex => "right index",
+            Finger::RightMiddle => "right middle",
+            Finger::RightRing => "right ring",
+            Finger::RightPinky => "right pinky",
+        }
+    }
+}
+
+/// Finger of each key in [`ROWS`]. The index fingers also cover the middle
+/// columns, the right pinky everything right of its home key.
+const FINGERS: [&[Finger]; 4] = {
+    use Finger::*;
+    [
+        &[LeftPinky, LeftRing, LeftMiddle, LeftIndex, LeftIndex, RightIndex, RightIndex, RightMiddle, RightRing, RightPinky, RightPinky, RightPinky],
+        &[LeftPinky, LeftRing, LeftMiddle, LeftIndex, LeftIndex, RightIndex, RightIndex, RightMiddle, RightRing, RightPinky, RightPinky, RightPinky],
+        &[LeftPinky, LeftRing, LeftMiddle, LeftIndex, LeftIndex, RightIndex, RightIndex, RightMiddle, RightRing, RightPinky, RightPinky],
+        &[LeftPinky, LeftRing, LeftMiddle, LeftIndex, LeftIndex, RightIndex, RightIndex, RightMiddle, RightRing, RightPinky],
+    ]
+};
+
+/// A keyboard layout the cost model knows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layout {
+    /// The standard US layout
+    Qwerty,
+    /// Colemak, which keeps Z-V and the punctuation of QWERTY
+    Colemak,
+    /// Dvorak Simplified Keyboard
+    Dvorak,
+}
+
+impl Layout {
+    /// All layouts, in the order reports list them.
+    pub const ALL: [Layout; 3] = [Layout::Qwerty, Layout::Colemak, Layout::Dvorak];
+
+    /// Name for display and the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Qwerty => "QWERTY",
+            Layout::Colemak => "Colemak",
+            Layout::Dvorak => "Dvorak",
+        }
+    }
+
+    /// Parses a layout name, ignoring case.
+    pub fn parse(name: &str) -> Result<Self> {
+        match Self::ALL.iter().find(|layout| layout.name().eq_ignore_ascii_case(name)) {
+            Some(&layout) => Ok(layout),
+            None => bail!("unknown layout '{}': expected qwerty, colemak or dvorak", name),
+        }
+    }
+
+    /// Unshifted characters of [`ROWS`] on this layout.
+    fn rows(self) -> [&'static str; 4] {
+        match self {
+            Layout::Qwerty => ["1234567890-=", "qwertyuiop[]", "asdfghjkl;'", "zxcvbnm,./"],
+            Layout::Colemak => ["1234567890-=", "qwfpgjluy;[]", "arstdhneio'", "zxcvbkm,./"],
+            Layout::Dvorak => ["1234567890[]", "',.pyfgcrl/=", "aoeuidhtns-", ";qjkxbmwvz"],
+        }
+    }
+
+    /// The character a physical key types on this layout, unshifted.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::layout::Layout;
+    ///
+    /// // The second key of the home row
+    /// assert_eq!(Layout::Qwerty.char_at(31), Some('s'));
+    /// assert_eq!(Layout::Colemak.char_at(31), Some('r'));
+    /// assert_eq!(Layout::Dvorak.char_at(31), Some('o'));
+    /// assert_eq!(Layout::Dvorak.position_of('s'), Some(39));
+    /// ```
+    pub fn char_at(self, code: u16) -> Option<char> {
+        ROWS.iter().zip(self.rows()).find_map(|(codes, chars)| {
+            codes.iter().position(|&c| c == code).and_then(|i| chars.chars().nth(i))
+        })
+    }
+
+    /// The physical key that types a character on this layout, unshifted.
+    pub fn position_of(self, ch: char) -> Option<u16> {
+        ROWS.iter().zip(self.rows()).find_map(|(codes, chars)| {
+            chars.chars().position(|c| c == ch).map(|i| codes[i])
+        })
+    }
+}
+
+/// Effort of pressing a physical key, or None outside the character block.
+pub fn effort(code: u16) -> Option<f64> {
+    ROWS.iter()
+        .zip(EFFORT)
+        .find_map(|(codes, costs)| codes.iter().position(|&c| c == code).map(|i| costs[i]))
+}
+
+/// Finger that presses a physical key, or None outside the character block.
+///
+/// # Example
+/// ```
+/// use ctrlq::layout::{finger, Finger};
+///
+/// assert_eq!(finger(18), Some(Finger::LeftMiddle)); // E
+/// assert_eq!(finger(32), Some(Finger::LeftMiddle)); // D
+/// assert_eq!(finger(35), Some(Finger::RightIndex)); // H
+/// assert_eq!(finger(57), None); // Space
+/// ```
+pub fn finger(code: u16) -> Option<Finger> {
+    ROWS.iter()
+        .zip(FINGERS)
+        .find_map(|(codes, fingers)| codes.iter().position(|&c| c == code).map(|i| fingers[i]))
+}
+
+/// The effort of a key distribution on one layout.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EffortScore {
+    /// Summed effort of all priced keystrokes
+    pub total: f64,
+    /// Keystrokes that were priced (character keys only)
+    pub keystrokes: u64,
+}
+
+impl EffortScore {
+    /// Average effort of one keystroke, or None without priced keystrokes.
+    pub fn per_keystroke(&self) -> Option<f64> {
+        (self.keystrokes > 0).then(|| self.total / self.keystrokes as f64)
+    }
+}
+
+/// Prices a key distribution on a layout.
+///
+/// Each stored key is turned into the character it typed on `typed_on`,
+/// then priced at the key that types that character on `priced_on`. With
+/// the same layout twice this is simply the effort of the keys pressed.
+/// Shifted keys count like their unshifted key.
+///
+/// # Arguments
+/// * `counts` - Keystrokes by stored key name (e.g. `key_counts`)
+/// * `typed_on` - Layout the keystrokes were typed on
+/// * `priced_on` - Layout to price them on
+///
+/// # Returns
+/// `EffortScore` - Total effort and the number of priced keystrokes
+///
+/// # Example
+/// A tiny distribution, priced by hand from the effort table:
+/// ```
+/// use ctrlq::layout::{score, Layout};
+/// use std::collections::HashMap;
+///
+/// // a ×10, e ×5, t ×4, z ×2 and 100 spaces, typed on QWERTY
+/// let counts: HashMap<String, u64> = [("KEY_30", 10), ("KEY_18", 5), ("KEY_20", 4), ("KEY_44", 2), ("KEY_57", 100)]
+///     .into_iter()
+///     .map(|(key, count)| (key.to_string(), count))
+///     .collect();
+///
+/// // QWERTY: a 1.6, e 2.0 (top row), t 3.2 (reach), z 3.2
+/// let qwerty = score(&counts, Layout::Qwerty, Layout::Qwerty);
+/// assert_eq!(qwerty.keystrokes, 21); // space is not priced
+/// assert!((qwerty.total - (16.0 + 10.0 + 12.8 + 6.4)).abs() < 1e-9);
+///
+/// // Colemak puts e (1.1) and t (1.0) on the home row
+/// let colemak = score(&counts, Layout::Qwerty, Layout::Colemak);
+/// assert!((colemak.total - (16.0 + 5.5 + 4.0 + 6.4)).abs() < 1e-9);
+///
+/// // Dvorak has e at 1.1 and t at 1.1, z stays a pinky reach
+/// let dvorak = score(&counts, Layout::Qwerty, Layout::Dvorak);
+/// assert!((dvorak.total - (16.0 + 5.5 + 4.4 + 6.4)).abs() < 1e-9);
+/// assert!((qwerty.per_keystroke().unwrap() - 45.2 / 21.0).abs() < 1e-9);
+/// ```
+pub fn score(counts: &HashMap<String, u64>, typed_on: Layout, priced_on: Layout) -> EffortScore {
+    let mut score = EffortScore::default();
+    for (key, &count) in counts {
+        let cost = keymap::parse_code(key)
+            .and_then(|code| typed_on.char_at(code))
+            .and_then(|ch| priced_on.position_of(ch))
+            .and_then(effort);
+        if let Some(cost) = cost {
+            score.total += cost * count as f64;
+            score.keystrokes += count;
+        }
+    }
+    score
+}
+
+/// Prices a key distribution on every layout.
+///
+/// # Arguments
+/// * `counts` - Keystrokes by stored key name
+/// * `typed_on` - Layout the keystrokes were typed on
+///
+/// # Returns
+/// `Vec<(Layout, EffortScore)>` - One score per layout, in [`Layout::ALL`] order
+pub fn compare(counts: &HashMap<String, u64>, typed_on: Layout) -> Vec<(Layout, EffortScore)> {
+    Layout::ALL
+        .iter()
+        .map(|&layout| (layout, score(counts, typed_on, layout)))
+        .collect()
+}
+
+/// Checks the tables of the cost model against each other, for
+/// `ctrlq doctor --offline`: every key has an effort and a finger, no key
+/// is listed twice, and every layout types the characters of QWERTY, each
+/// on one key.
+///
+/// # Returns
+/// `Vec<String>` - Problems found, empty if the tables agree
+///
+/// # Example
+/// ```
+/// assert!(ctrlq::layout::self_check().is_empty());
+/// ```
+pub fn self_check() -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    for (row, codes) in ROWS.iter().enumerate() {
+        if EFFORT[row].len() != codes.len() || FINGERS[row].len() != codes.len() {
+            problems.push(format!(
+                "row {} has {} keys, {} efforts and {} fingers",
+                row + 1,
+                codes.len(),
+                EFFORT[row].len(),
+                FINGERS[row].len()
+            ));
+        }
+        for &code in codes.iter() {
+            if !seen.insert(code) {
+                problems.push(format!("KEY_{} is listed twice", code));
+            }
+        }
+    }
+
+    let base: BTreeSet<char> = Layout::Qwerty.rows().concat().chars().collect();
+    for layout in Layout::ALL {
+        for (row, chars) in layout.rows().iter().enumerate() {
+            if chars.chars().count() != ROWS[row].len() {
+                problems.push(format!("{} row {} has {} characters for {} keys", layout.name(), row + 1, chars.chars().count(), ROWS[row].len()));
+            }
+        }
+        let typed: BTreeSet<char> = layout.rows().concat().chars().collect();
+        let missing: String = base.difference(&typed).collect();
+        if !missing.is_empty() {
+            problems.push(format!("{} can't type {}", layout.name(), missing));
+        }
+        for ch in typed {
+            if layout.position_of(ch).and_then(|code| layout.char_at(code)) != Some(ch) {
+                problems.push(format!("{} types '{}' on more than one key", layout.name(), ch));
+            }
+        }
+    }
+    problems
+}
+
+/// One pair of characters typed with the same finger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SameFingerBigram {
+    /// The two characters, in typing order, e.g. `ed`
+    pub pair: String,
+    /// Stored names of the two keys that type them on the priced layout
+    pub keys: (String, String),
+    /// The finger that types both on the priced layout
+    pub finger: Finger,
+    /// Times typed
+    pub count: u64,
+}
+
+/// Same-finger bigrams of a bigram distribution on one layout.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SfbStats {
+    /// Bigrams of two character keys (same-key repeats included)
+    pub bigrams: u64,
+    /// Bigrams of two different keys pressed by the same finger
+    pub same_finger: u64,
+    /// The most typed same-finger bigrams, most typed first
+    pub top: Vec<SameFingerBigram>,
+}
+
+impl SfbStats {
+    /// Share of bigrams typed with one finger, or None without bigrams.
+    pub fn rate(&self) -> Option<f64> {
+        (self.bigrams > 0).then(|| self.same_finger as f64 / self.bigrams as f64)
+    }
+}
+
+/// Finds the same-finger bigrams of a bigram distribution.
+///
+/// Like [`score`], each key is turned into the character it typed on
+/// `typed_on` and looked up on `priced_on`, so the same typing can be
+/// compared across layouts. Pressing one key twice (`ll`) uses one finger
+/// too, but no layout can avoid it: it counts as a bigram, not as a
+/// same-finger one.
+///
+/// # Arguments
+/// * `bigrams` - Counts by pair of stored key names separated by a space,
+///   e.g. `KEY_18 KEY_32`, see [`crate::keylogger::KeyStats::bigram_counts`]
+/// * `typed_on` - Layout the keystrokes were typed on
+/// * `priced_on` - Layout to look the fingers up on
+/// * `limit` - How many of the most typed same-finger bigrams to list
+///
+/// # Returns
+/// `SfbStats` - Totals and the top offenders
+///
+/// # Example
+/// A synthetic distribution typed on QWERTY:
+/// ```
+/// use ctrlq::layout::{same_finger_bigrams, Finger, Layout};
+/// use std::collections::HashMap;
+///
+/// // e→d ×30 (left middle), d→e ×10, t→h ×50, l→l ×20 (same key), j→u ×15 (right index)
+/// let bigrams: HashMap<String, u64> =
+///     [("KEY_18 KEY_32", 30), ("KEY_32 KEY_18", 10), ("KEY_20 KEY_35", 50), ("KEY_38 KEY_38", 20), ("KEY_36 KEY_22", 15)]
+///         .into_iter()
+///         .map(|(pair, count)| (pair.to_string(), count))
+///         .collect();
+///
+/// let qwerty = same_finger_bigrams(&bigrams, Layout::Qwerty, Layout::Qwerty, 2);
+/// assert_eq!(qwerty.bigrams, 125);
+/// assert_eq!(qwerty.same_finger, 55);
+/// assert!((qwerty.rate().unwrap() - 55.0 / 125.0).abs() < 1e-9);
+/// assert_eq!(qwerty.top.len(), 2);
+/// assert_eq!((qwerty.top[0].pair.as_str(), qwerty.top[0].finger, qwerty.top[0].count), ("ed", Finger::LeftMiddle, 30));
+/// assert_eq!(qwerty.top[1].pair, "ju");
+///
+/// // Colemak types e and u with the right middle finger, d with the left
+/// // index and j with the right index: none of them is left
+/// let colemak = same_finger_bigrams(&bigrams, Layout::Qwerty, Layout::Colemak, 5);
+/// assert_eq!(colemak.bigrams, 125);
+/// assert_eq!(colemak.same_finger, 0);
+/// assert!(colemak.top.is_empty());
+/// ```
+pub fn same_finger_bigrams(
+    bigrams: &HashMap<String, u64>,
+    typed_on: Layout,
+    priced_on: Layout,
+    limit: usize,
+) -> SfbStats {
+    let key = |name: &str| {
+        let ch = keymap::parse_code(name).and_then(|code| typed_on.char_at(code))?;
+        let position = priced_on.position_of(ch)?;
+        Some((ch, position, finger(position)?))
+    };
+    let mut stats = SfbStats::default();
+    let mut offenders: HashMap<(String, String), SameFingerBigram> = HashMap::new();
+    for (pair, &count) in bigrams {
+        let Some((first, second)) = pair.split_once(' ') else {
+            continue;
+        };
+        let (Some((a, a_position, a_finger)), Some((b, b_position, b_finger))) = (key(first), key(second)) else {
+            continue;
+        };
+        stats.bigrams += count;
+        if a_finger != b_finger || a_position == b_position {
+            continue;
+        }
+        stats.same_finger += count;
+        let keys = (keymap::key_name(a_position), keymap::key_name(b_position));
+        offenders
+            .entry(keys.clone())
+            .or_insert_with(|| SameFingerBigram { pair: format!("{}{}", a, b), keys, finger: a_finger, count: 0 })
+            .count += count;
+    }
+    let mut top: Vec<SameFingerBigram> = offenders.into_values().collect();
+    top.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.pair.cmp(&b.pair)));
+    top.truncate(limit);
+    stats.top = top;
+    stats
+}
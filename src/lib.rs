@@ -0,0 +1,93 @@
+//! # CtrlQ library
+//!
+//! The statistics engine behind the `ctrlq` binary, for embedding in other
+//! tools such as dashboards. It covers keystroke statistics and their
+//! persistence, device discovery and capture, key names and classes,
+//! reports and imports. The terminal UI and the CLI live in the binary.
+//!
+//! Build with `default-features = false` to leave out the terminal UI
+//! dependencies (ratatui, crossterm).
+//!
+//! ## Example
+//! Load the data file written by `ctrlq` and print the most used keys:
+//! ```no_run
+//! use ctrlq::keylogger::KeyLogger;
+//! use ctrlq::keymap;
+//!
+//! let stats = KeyLogger::load_stats(&KeyLogger::default_data_file()?)?;
+//! for (key, count) in stats.get_top_keys(10) {
+//!     println!("{:<12} {}", keymap::display_name(&key), count);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! ## Modules
+//! - `keylogger` - Statistics, persistence, device discovery and event sources
+//! - `actions` - Shortcuts counted as named actions (copy, paste, undo, ...)
+//! - `archive` - Monthly archive files for days that no longer change
+//! - `backup` - Full backups with checksums (`ctrlq backup`)
+//! - `bench` - Benchmark of the statistics pipeline with synthetic events (`ctrlq bench`)
+//! - `bindings` - Keys of the terminal UI (`[keys]` in the config)
+//! - `calibration` - Typing test that calibrates the WPM estimate (`ctrlq calibrate`)
+//! - `keymap` - Key code names, key classes and keystroke categories (code symbols vs prose)
+//! - `layout` - Keystroke effort model for comparing keyboard layouts
+//! - `config` - User configuration (`config.toml`)
+//! - `report` - Plain-text reports and period parsing
+//! - `drift` - Weekly share of a key over the whole history
+//! - `hooks` - Shell commands run on logger events (`[hooks]` in the config)
+//! - `heatmap` - Comparing the key distributions of two keyboards or profiles
+//! - `ignore` - Rules for keystrokes not counted, by device, key and time (`[[ignore]]`)
+//! - `import` - Importing per-key counts from other tools
+//! - `integrity` - Consistency checks and repairs of a data file (`ctrlq fsck`)
+//! - `ipc` - Control socket used by CLI commands to reach a running logger
+//! - `lock` - Screen lock state from logind, for `--pause-on-lock`
+//! - `numbers` - Counts written with thousands separators or compact (`3.48M`)
+//! - `notifications` - Desktop notifications (feature `notifications`)
+//! - `output` - Startup and headless messages as text, quiet or JSON lines
+//! - `power` - Battery detection for fewer disk writes (feature `battery`)
+//! - `paths` - Config, data and state directories (XDG)
+//! - `privileges` - How input devices are read: root, file capabilities or the `input` group
+//! - `profile` - Named profiles, each with its own data file
+//! - `range` - Date ranges (`--since`, `--until`) for read-only commands
+//! - `rawlog` - Opt-in binary log of every key event (`--raw-log`)
+//! - `schedule` - Hours in which keystrokes are logged (`log_hours`)
+//! - `schema` - Versioned JSON output of `ctrlq stats --json`
+//! - `status_line` - One-line status for status bars (`ctrlq status-line`)
+//! - `surprise` - Keys used unusually much or little against English or code
+//! - `summary` - Weekly Markdown summaries in `summaries/`
+//! - `xkb` - Characters typed per XKB layout (feature `xkb`)
+
+pub mod actions;
+pub mod archive;
+pub mod backup;
+pub mod bench;
+pub mod bindings;
+pub mod calibration;
+pub mod config;
+pub mod drift;
+pub mod heatmap;
+pub mod hooks;
+pub mod ignore;
+pub mod import;
+pub mod integrity;
+pub mod ipc;
+pub mod keylogger;
+pub mod keymap;
+pub mod layout;
+pub mod lock;
+pub mod notifications;
+pub mod numbers;
+pub mod output;
+pub mod paths;
+pub mod power;
+pub mod privileges;
+pub mod profile;
+pub mod range;
+pub mod rawlog;
+pub mod report;
+pub mod schedule;
+pub mod schema;
+pub mod status_line;
+pub mod summary;
+pub mod surprise;
+pub mod xkb;
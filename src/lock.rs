@@ -0,0 +1,131 @@
+//! # Lock Module
+//!
+//! Whether the desktop session is locked, for `--pause-on-lock`: anything
+//! typed at the lock screen (a password, a smart-card PIN) is then neither
+//! counted nor written to the raw log, exactly as during a manual pause.
+//!
+//! The lock state is logind's `LockedHint` property of the session, read
+//! over the system bus with `loginctl`. Under sudo the watched session is
+//! the graphical session of `SUDO_USER`, otherwise `XDG_SESSION_ID` or the
+//! session of this process. If logind can't be reached, [`LockWatcher::start`]
+//! fails once and the logger carries on without pausing.
+
+use crate::privileges;
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// Time between two reads of the lock state.
+pub const LOCK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Parses the output of `loginctl show-session -p LockedHint --value`.
+///
+/// # Example
+/// ```
+/// use ctrlq::lock::parse_locked_hint;
+///
+/// assert_eq!(parse_locked_hint("yes\n"), Some(true));
+/// assert_eq!(parse_locked_hint("no\n"), Some(false));
+/// assert_eq!(parse_locked_hint(""), None);
+/// ```
+pub fn parse_locked_hint(output: &str) -> Option<bool> {
+    match output.trim() {
+        "yes" | "true" => Some(true),
+        "no" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Watches whether the session is locked from a background thread.
+///
+/// The thread stops when the watcher is dropped. Should logind go away
+/// later, the session counts as unlocked, so a lost bus never keeps
+/// logging paused.
+pub struct LockWatcher {
+    /// The logind session being watched
+    session: String,
+    /// Latest lock state, written by the thread
+    locked: Arc<AtomicBool>,
+}
+
+impl LockWatcher {
+    /// Reads the lock state once and starts watching it.
+    ///
+    /// # Returns
+    /// `Result<Self>` - The watcher, or an error if logind can't be reached
+    pub fn start() -> Result<Self> {
+        let session = session_id();
+        let locked = Arc::new(AtomicBool::new(query(&session)?));
+        let watched = Arc::downgrade(&locked);
+        let thread_session = session.clone();
+        std::thread::Builder::new()
+            .name("ctrlq-lock".to_string())
+            .spawn(move || watch(&thread_session, watched))
+            .context("failed to start the screen lock watcher")?;
+        Ok(Self { session, locked })
+    }
+
+    /// Name of the watched logind session, e.g. `2` or `auto`.
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// Whether the session was locked at the last read.
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+}
+
+/// Polls the lock state until the watcher is dropped or logind stops answering.
+fn watch(session: &str, locked: Weak<AtomicBool>) {
+    loop {
+        std::thread::sleep(LOCK_POLL_INTERVAL);
+        let Some(state) = locked.upgrade() else {
+            return;
+        };
+        match query(session) {
+            Ok(now) => state.store(now, Ordering::Relaxed),
+            Err(_) => {
+                state.store(false, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the session to watch: the graphical session of the sudo user,
+/// `XDG_SESSION_ID`, or `auto` for the session of this process.
+fn session_id() -> String {
+    if let Some((user, _)) = privileges::sudo_user()
+        && let Ok(output) = Command::new("loginctl").args(["show-user", &user, "-p", "Display", "--value"]).output()
+        && output.status.success()
+    {
+        let display = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !display.is_empty() {
+            return display;
+        }
+    }
+    std::env::var("XDG_SESSION_ID")
+        .ok()
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| "auto".to_string())
+}
+
+/// Reads `LockedHint` of a session from logind.
+fn query(session: &str) -> Result<bool> {
+    let output = Command::new("loginctl")
+        .args(["show-session", session, "-p", "LockedHint", "--value"])
+        .output()
+        .context("failed to run loginctl")?;
+    if !output.status.success() {
+        bail!(
+            "logind didn't answer for session {}: {}",
+            session,
+            String::from_utf8_lossy(&output.stderr).trim().replace('\n', "; ")
+        );
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_locked_hint(&stdout).with_context(|| format!("unexpected LockedHint '{}'", stdout.trim()))
+}
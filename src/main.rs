@@ -13,31 +13,195 @@
 //!
 //! ## Usage
 //! ```bash
+//! # Set up permissions, the keyboard and the data directory once
+//! ctrlq setup
+//!
 //! # List available keyboard devices
 //! sudo ctrlq --list-devices
 //!
+//! # See which input devices can't be opened, and why
+//! ctrlq doctor
+//!
+//! # How to read them without sudo (setcap, the input group or a udev rule)
+//! ctrlq doctor --suggest-caps
+//!
 //! # Start with automatic device detection
 //! sudo ctrlq
 //!
 //! # Start with specific device
 //! sudo ctrlq -d /dev/input/event8
 //!
+//! # Log a laptop and an external keyboard together
+//! sudo ctrlq -d /dev/input/event3 -d /dev/input/event8
+//!
 //! # Run without UI (headless mode)
 //! sudo ctrlq --no-ui
+//!
+//! # ...printing its messages as JSON lines for a supervisor or script
+//! sudo ctrlq --no-ui --output-format json
+//!
+//! # ...and keep a text summary with an ASCII heatmap in the data directory
+//! sudo ctrlq --no-ui --summary-file --summary-interval 10
+//!
+//! # Print statistics and records from the data file
+//! ctrlq stats
+//!
+//! # Browse a data file in the UI without logging, e.g. one from a backup
+//! ctrlq view --data-file other_keystroke_data.json
+//!
+//! # The same as JSON for scripts, only the sections a status bar needs
+//! ctrlq stats --json --fields summary,records
+//!
+//! # One JSON line per session with its per-minute keystrokes, for notebooks
+//! ctrlq export --format sessions-jsonl --output sessions.jsonl
+//!
+//! # Statistics of the first quarter, or of this month only
+//! ctrlq stats --since 2024-01-01 --until 2024-03-31
+//! ctrlq stats --this-month
+//!
+//! # Today's keyboard heatmap in the terminal, without the UI
+//! ctrlq heatmap --today
+//!
+//! # Reset statistics but keep personal records
+//! ctrlq reset --keep-records
+//!
+//! # Restart only today's statistics (also works while ctrlq is running)
+//! ctrlq reset --scope today
+//!
+//! # Compare two days (or this-week / last-week)
+//! ctrlq report --compare 2024-05-01 2024-05-02
+//!
+//! # What would the same keys cost on Colemak or Dvorak?
+//! ctrlq report --layout-compare
+//!
+//! # Keys used unusually much or little for source code
+//! ctrlq report --unusual code
+//!
+//! # Write the Markdown summary of a week to summaries/ in the data directory
+//! ctrlq report --week 2024-W23
+//!
+//! # Totals of a month next to the month before
+//! ctrlq report --month 2024-06
+//!
+//! # Sessions tagged with :tag in the UI, and their totals
+//! ctrlq report --tag deep-work
+//!
+//! # Statistics of the sessions typed on the laptop's own keyboard
+//! ctrlq stats --dock mobile
+//!
+//! # Import per-key counts from WhatPulse (preview first with --dry-run)
+//! ctrlq import --format whatpulse keys.csv --dry-run
+//!
+//! # Back up all profiles and the config, e.g. to move to another machine
+//! ctrlq backup create --output ctrlq.tar
+//! ctrlq backup restore ctrlq.tar
+//!
+//! # Merge another ctrlq data file into this one
+//! ctrlq merge other_keystroke_data.json
+//!
+//! # Keep per-minute activity for the last 30 days only
+//! ctrlq compact --minute-days 30
+//!
+//! # Check that the totals of the data file add up, and fix those that don't
+//! ctrlq fsck --repair
+//!
+//! # Measure a focused 25 minute session and write a report of it
+//! sudo ctrlq --duration 25m --on-exit-export report.md
+//!
+//! # Record only key classes, for data you want to share publicly
+//! sudo ctrlq --anonymize
+//!
+//! # Stream live statistics to dashboards as Server-Sent Events
+//! sudo ctrlq --stream-port 7878
+//!
+//! # Compare ctrlq's WPM with a typing test and list earlier calibrations
+//! sudo ctrlq calibrate
+//! ctrlq calibrate --history
+//!
+//! # Keep separate statistics for another keyboard
+//! sudo ctrlq --profile ergo -d /dev/input/event8
+//! ctrlq profile list
+//!
+//! # Attribute keystrokes to a project (e.g. from a shell prompt hook)
+//! ctrlq context myrepo
+//!
+//! # Count 40 keystrokes typed where the logged keyboard can't see them (e.g. over SSH)
+//! ctrlq ping --count 40
+//!
+//! # One line for the tmux status bar, e.g. "⌨ 12.4k today · 72 WPM"
+//! ctrlq status-line --format '{today} {wpm} WPM'
+//!
+//! # Keep a raw log of every key event and convert a day of it to JSON lines
+//! sudo ctrlq --raw-log
+//! ctrlq log convert ~/.local/share/ctrlq/keystroke_data.raw/2024-05-01.ctrlqlog
 //! ```
 //!
 //! ## Architecture
 //! - `main.rs` - CLI interface and application coordination
+//! - `lib.rs` - The statistics engine as a library (see the crate docs)
 //! - `keylogger.rs` - Core keystroke monitoring and statistics
+//! - `actions.rs` - Shortcuts counted as named actions
+//! - `archive.rs` - Monthly archive files for old days
+//! - `backup.rs` - Full backups with checksums (`ctrlq backup`)
+//! - `bench.rs` - Benchmark of the statistics pipeline (`ctrlq bench`)
+//! - `calibration.rs` - Typing test behind `ctrlq calibrate`
+//! - `keymap.rs` - Key code names and key classes
+//! - `layout.rs` - Keystroke effort model for comparing layouts
+//! - `lock.rs` - Screen lock state from logind (`--pause-on-lock`)
+//! - `config.rs` - User configuration (`config.toml`)
+//! - `drift.rs` - Weekly share of a key, for the History tab's drift chart
+//! - `hooks.rs` - Shell commands run on logger events (`[hooks]`)
+//! - `paths.rs` - Config, data and state directories (XDG)
+//! - `privileges.rs` - Root, file capabilities or the `input` group (`ctrlq doctor --suggest-caps`)
+//! - `rawlog.rs` - Opt-in binary log of every key event
+//! - `range.rs` - `--since`/`--until` date ranges for `stats` and `report`
+//! - `report.rs` - Plain-text reports for the `report` subcommand
+//! - `schema.rs` - Versioned JSON output of `ctrlq stats --json` and `ctrlq export`
+//! - `status_line.rs` - One-line status for status bars (`ctrlq status-line`)
+//! - `ignore.rs` - Rules for keystrokes not counted (`[[ignore]]`)
+//! - `import.rs` - Importing per-key counts from other tools
+//! - `integrity.rs` - Consistency checks behind `ctrlq fsck`
+//! - `notifications.rs` - Desktop notifications (feature `notifications`)
+//! - `output.rs` - Startup and headless messages (`--output-format human|quiet|json`)
+//! - `setup.rs` - First-run setup wizard (`ctrlq setup`)
+//! - `stream.rs` - Live statistics stream for external dashboards
+//! - `surprise.rs` - Keys used unusually much or little (`report --unusual`)
 //! - `ui.rs` - Terminal user interface using ratatui
 
-mod keylogger;
+mod setup;
+mod stream;
 mod ui;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::{Arg, Command};
-use keylogger::{find_keyboard_devices, KeyLogger};
+use ctrlq::config::{Config, Overrides};
+use ctrlq::keylogger::{self, find_keyboard_devices, Dock, KeyLogger, KeyStats, LiveSnapshot, LoggerProblem, ResetScope};
+use ctrlq::heatmap::HeatGrid;
+use ctrlq::layout::Layout;
+use ctrlq::output::{OutputMode, Presenter};
+use ctrlq::range::DateRange;
+use ctrlq::schema::{self, Section, StatsOutput};
+use ctrlq::calibration::CalibrationHistory;
+use ctrlq::status_line::{self, StatusFormat, StatusSnapshot};
+use ctrlq::surprise::{self, Corpus, Observed};
+use ctrlq::bench::{self, BenchOptions, BenchReport};
+use ctrlq::{archive, backup, import, integrity, ipc, layout, numbers, paths, privileges, profile, rawlog, report, summary};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// How often headless mode prints a one-line summary.
+const HEADLESS_SUMMARY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Minutes between writes of `--summary-file` unless `--summary-interval` says otherwise.
+const SUMMARY_FILE_MINUTES: u64 = 5;
+
+// Counts allocations for `ctrlq bench`, one relaxed atomic add each
+#[cfg(feature = "bench-alloc")]
+#[global_allocator]
+static ALLOCATOR: bench::CountingAllocator = bench::CountingAllocator;
 
 /// Main entry point for the CtrlQ keylogger application.
 ///
@@ -56,7 +220,8 @@ fn main() -> Result<()> {
                 .short('d')
                 .long("device")
                 .value_name("DEVICE_PATH")
-                .help("Path to keyboard input device (e.g., /dev/input/event3)")
+                .action(clap::ArgAction::Append)
+                .help("Path to keyboard input device (e.g., /dev/input/event3); repeat to log several keyboards")
         )
         .arg(
             Arg::new("list-devices")
@@ -65,97 +230,2331 @@ fn main() -> Result<()> {
                 .action(clap::ArgAction::SetTrue)
                 .help("List available keyboard devices")
         )
+        .arg(
+            Arg::new("input-dir")
+                .long("input-dir")
+                .value_name("DIR")
+                .value_parser(clap::value_parser!(PathBuf))
+                .global(true)
+                .help("Look for keyboards in DIR instead of /dev/input, e.g. in a container (or set CTRLQ_INPUT_DIR)")
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(clap::ArgAction::SetTrue)
+                .help("Also show entries of /dev/input that couldn't be read while looking for keyboards")
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .requires("list-devices")
+                .action(clap::ArgAction::SetTrue)
+                .help("With --list-devices, print the device list as JSON")
+        )
         .arg(
             Arg::new("no-ui")
                 .long("no-ui")
                 .action(clap::ArgAction::SetTrue)
                 .help("Run without terminal UI (just log to file)")
         )
+        .arg(
+            Arg::new("output-format")
+                .long("output-format")
+                .value_name("FORMAT")
+                .value_parser(OutputMode::NAMES)
+                .default_value("human")
+                .help("How startup and --no-ui messages are printed: human, quiet (warnings only) or json (JSON lines)")
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("output-format")
+                .help("Only print warnings and errors, same as --output-format quiet")
+        )
+        .arg(
+            Arg::new("no-notifications")
+                .long("no-notifications")
+                .action(clap::ArgAction::SetTrue)
+                .help("Don't send desktop notifications")
+        )
+        .arg(
+            Arg::new("no-hooks")
+                .long("no-hooks")
+                .action(clap::ArgAction::SetTrue)
+                .help("Don't run the [hooks] of the config")
+        )
+        .arg(
+            Arg::new("no-auto-summary")
+                .long("no-auto-summary")
+                .action(clap::ArgAction::SetTrue)
+                .help("Don't write a summary of each finished week to summaries/")
+        )
+        .arg(
+            Arg::new("anonymize")
+                .long("anonymize")
+                .action(clap::ArgAction::SetTrue)
+                .help("Record only the class of each key (letter, digit, ...), never which key")
+        )
+        .arg(
+            Arg::new("case-sensitive")
+                .long("case-sensitive")
+                .action(clap::ArgAction::SetTrue)
+                .help("Count capitals and shifted symbols apart (A vs a, ! vs 1)")
+        )
+        .arg(
+            Arg::new("xkb-layout")
+                .long("xkb-layout")
+                .value_name("LAYOUTS")
+                .help("XKB layouts you switch between, e.g. us,hu, for counting typed characters (needs the xkb feature; default: /etc/default/keyboard)")
+        )
+        .arg(
+            Arg::new("pause-on-lock")
+                .long("pause-on-lock")
+                .action(clap::ArgAction::SetTrue)
+                .help("Don't count keystrokes while the screen is locked (reads the lock state from logind)")
+        )
+        .arg(
+            Arg::new("raw-log")
+                .long("raw-log")
+                .action(clap::ArgAction::SetTrue)
+                .help("Also write every key event with its timestamp to a binary log (sensitive!)")
+        )
+        .arg(
+            Arg::new("stream-port")
+                .long("stream-port")
+                .value_name("PORT")
+                .value_parser(clap::value_parser!(u16))
+                .help("Serve live statistics as Server-Sent Events on 127.0.0.1:PORT")
+        )
+        .arg(
+            Arg::new("stream-allow-null-origin")
+                .long("stream-allow-null-origin")
+                .action(clap::ArgAction::SetTrue)
+                .requires("stream-port")
+                .help("Let pages with the origin null, such as local files in OBS, read the stream (so can sandboxed frames of any website)")
+        )
+        .arg(
+            Arg::new("summary-file")
+                .long("summary-file")
+                .value_name("FILE")
+                .num_args(0..=1)
+                .default_missing_value("")
+                .requires("no-ui")
+                .help("With --no-ui, keep a plain-text summary with an ASCII heatmap in FILE (default: summary.txt in the data directory)")
+        )
+        .arg(
+            Arg::new("summary-interval")
+                .long("summary-interval")
+                .value_name("MIN")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .requires("summary-file")
+                .help("Minutes between writes of --summary-file (default 5)")
+        )
+        .arg(
+            Arg::new("warn-idle-minutes")
+                .long("warn-idle-minutes")
+                .value_name("MIN")
+                .value_parser(clap::value_parser!(u64))
+                .help("Warn when no key events arrived for this long, e.g. because another process grabbed the keyboard (0 = never, default 30)")
+        )
+        .arg(
+            Arg::new("tick-ms")
+                .long("tick-ms")
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64).range(20..))
+                .help("Milliseconds between UI updates; raise it over slow SSH links (default 250)")
+        )
+        .arg(
+            Arg::new("duration")
+                .long("duration")
+                .value_name("TIME")
+                .value_parser(parse_duration)
+                .help("Stop automatically after this long (e.g. 90s, 25m, 2h)")
+        )
+        .arg(
+            Arg::new("profile")
+                .short('p')
+                .long("profile")
+                .value_name("NAME")
+                .global(true)
+                .help("Keep statistics in a separate named profile (keystroke_data.NAME.json)")
+        )
+        .arg(
+            Arg::new("pretty-json")
+                .long("pretty-json")
+                .global(true)
+                .action(clap::ArgAction::SetTrue)
+                .help("Write the data file as indented JSON instead of compact JSON")
+        )
+        .arg(
+            Arg::new("on-exit-export")
+                .long("on-exit-export")
+                .value_name("FILE")
+                .help("Write a report of this session on exit (.json for JSON, otherwise Markdown)")
+        )
+        .subcommand(
+            Command::new("setup")
+                .about("Set up permissions, your keyboard and the data directory interactively")
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check which input devices can be read and which of them are keyboards")
+                .arg(
+                    Arg::new("suggest-caps")
+                        .long("suggest-caps")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Show how to read input devices without sudo: setcap, the input group or a udev rule"),
+                )
+                .arg(
+                    Arg::new("offline")
+                        .long("offline")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("suggest-caps")
+                        .help("Check the data directory, the data file, the UI and the key tables without any input device; exits 1 on failure")
+                )
+        )
+        .subcommand(
+            Command::new("calibrate")
+                .about("Take a typing test in the UI that calibrates the WPM estimate for your keyboard")
+                .arg(
+                    Arg::new("history")
+                        .long("history")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print earlier calibrations instead of taking the test")
+                )
+        )
+        .subcommand(
+            Command::new("view")
+                .about("Browse a saved data file in the terminal UI, without logging or root")
+                .arg(
+                    Arg::new("data-file")
+                        .long("data-file")
+                        .value_name("FILE")
+                        .help("Data file to view, e.g. an exported or backed up one (default: the profile's data file)")
+                )
+                .arg(
+                    Arg::new("compare")
+                        .long("compare")
+                        .value_name("PROFILE|FILE")
+                        .help("Another profile or data file to compare heatmaps with ('v' on the Heatmap tab)")
+                )
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Print statistics and personal records from the data file")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print JSON with a stable, versioned schema (see docs/stats-json-v1.json)")
+                )
+                .arg(
+                    Arg::new("fields")
+                        .long("fields")
+                        .value_name("SECTIONS")
+                        .requires("json")
+                        .help("With --json, only these sections: summary,top_keys,daily,sessions,records")
+                )
+                .arg(tag_arg("Only sessions tagged TAG (the session count and the sessions section)"))
+                .arg(dock_arg("Only sessions typed docked or mobile on a laptop (like --tag)"))
+                .args(range_args())
+        )
+        .subcommand(
+            Command::new("heatmap")
+                .about("Print the keyboard heatmap of the data file, in color on a terminal")
+                .args(range_args())
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export details of the data file for analysis elsewhere")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .value_parser(["sessions-jsonl"])
+                        .required(true)
+                        .help("sessions-jsonl: a header line, then one JSON object per session with its per-minute keystrokes")
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("File to write (default: standard output)")
+                )
+                .arg(
+                    Arg::new("include-open")
+                        .long("include-open")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Also export the session in progress of a running ctrlq")
+                )
+                .args(range_args())
+        )
+        .subcommand(
+            Command::new("reset")
+                .about("Reset statistics, in a running instance if there is one")
+                .arg(
+                    Arg::new("scope")
+                        .long("scope")
+                        .value_name("SCOPE")
+                        .value_parser(["session", "today", "all"])
+                        .default_value("all")
+                        .help("What to reset: the current session, today, or everything")
+                )
+                .arg(
+                    Arg::new("keep-records")
+                        .long("keep-records")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Keep personal records (best WPM, longest session, ...)")
+                )
+        )
+        .subcommand(
+            Command::new("report")
+                .about("Print a report from the data file")
+                .arg(
+                    Arg::new("compare")
+                        .long("compare")
+                        .num_args(2)
+                        .value_names(["A", "B"])
+                        .help("Compare two periods: YYYY-MM-DD, today, yesterday, this-week, last-week, this-month or last-month (default: yesterday today)")
+                )
+                .arg(
+                    Arg::new("layout-compare")
+                        .long("layout-compare")
+                        .num_args(0..=1)
+                        .value_name("TYPED_ON")
+                        .default_missing_value("qwerty")
+                        .conflicts_with("compare")
+                        .help("Compare the typing effort of your keys on QWERTY, Colemak and Dvorak (TYPED_ON: layout you type on, default qwerty)")
+                )
+                .arg(
+                    Arg::new("unusual")
+                        .long("unusual")
+                        .num_args(0..=1)
+                        .value_name("REFERENCE")
+                        .default_missing_value("english")
+                        .conflicts_with_all(["compare", "layout-compare"])
+                        .help("List the keys you use unusually much or little (REFERENCE: english, code or a text file, default english)")
+                )
+                .arg(
+                    Arg::new("week")
+                        .long("week")
+                        .value_name("YYYY-Www")
+                        .conflicts_with_all(["compare", "layout-compare", "unusual"])
+                        .help("Write the Markdown summary of an ISO week (e.g. 2024-W23) to summaries/ in the data directory")
+                )
+                .arg(
+                    Arg::new("month")
+                        .long("month")
+                        .value_name("YYYY-MM")
+                        .conflicts_with_all(["compare", "layout-compare", "unusual", "week"])
+                        .help("Print the totals of a calendar month (e.g. 2024-06) next to the month before")
+                )
+                .arg(
+                    tag_arg("List the sessions tagged TAG with ':tag' or 'n', and their totals")
+                        .conflicts_with_all(["compare", "layout-compare", "unusual", "week", "month"])
+                )
+                .arg(dock_arg("With --tag, only the sessions typed docked or mobile on a laptop").requires("tag"))
+                .args(range_args())
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import per-key counts exported by another tool")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .default_value("csv")
+                        .help("Input format: whatpulse, or csv with columns key,count[,date]")
+                )
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("File to import")
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Show what would change without writing the data file")
+                )
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("Merge another ctrlq data file into this one")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("Data file to merge in")
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Show what would change without writing the data file")
+                )
+        )
+        .subcommand(
+            Command::new("compact")
+                .about("Reduce the per-minute activity of old days to hourly counts")
+                .arg(
+                    Arg::new("minute-days")
+                        .long("minute-days")
+                        .value_name("DAYS")
+                        .value_parser(clap::value_parser!(u64))
+                        .help("Days that keep minute data, 0 = all (default: minute_days from the config)")
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Show what would change without writing the data file")
+                )
+        )
+        .subcommand(
+            Command::new("fsck")
+                .about("Check that the totals, days and sessions of the data file add up")
+                .arg(
+                    Arg::new("repair")
+                        .long("repair")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Recompute the totals that don't add up from the most detailed data")
+                )
+        )
+        .subcommand(
+            Command::new("context")
+                .about("Tell the running logger which project you are working on")
+                .arg(
+                    Arg::new("name")
+                        .required(true)
+                        .value_name("NAME")
+                        .help("Context to attribute new keystrokes to ('default' to clear)")
+                )
+        )
+        .subcommand(
+            Command::new("ping")
+                .about("Count keystrokes the logged keyboard can't see, e.g. typed over SSH")
+                .arg(
+                    Arg::new("count")
+                        .long("count")
+                        .short('n')
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u32).range(1..=i64::from(keylogger::MAX_REMOTE_BATCH)))
+                        .default_value("1")
+                        .help("Keystrokes to count, attributed to the 'remote' device")
+                )
+        )
+        .subcommand(
+            Command::new("bench")
+                .about("Benchmark the statistics pipeline with synthetic events (for development)")
+                .arg(
+                    Arg::new("events")
+                        .long("events")
+                        .value_name("N")
+                        .value_parser(bench::parse_count)
+                        .default_value("1_000_000")
+                        .help("Key events to feed the logging loop, presses and releases")
+                )
+                .arg(
+                    Arg::new("distinct-keys")
+                        .long("distinct-keys")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("120")
+                        .help("Distinct keys to type")
+                )
+                .arg(
+                    Arg::new("days")
+                        .long("days")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u32))
+                        .default_value("365")
+                        .help("Days of synthetic history in the data file")
+                )
+                .arg(
+                    Arg::new("baseline")
+                        .long("baseline")
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("Compare with a run saved with --save-baseline")
+                )
+                .arg(
+                    Arg::new("save-baseline")
+                        .long("save-baseline")
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("Save this run for a later --baseline")
+                )
+        )
+        .subcommand(
+            Command::new("status-line")
+                .about("Print a one-line status for tmux, waybar and other status bars")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("TEMPLATE")
+                        .default_value(status_line::DEFAULT_FORMAT)
+                        .help("Template with {today}, {total}, {wpm}, {goal_pct} and {streak}")
+                )
+        )
+        .subcommand(
+            Command::new("log")
+                .about("Work with raw event logs written by --raw-log")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("convert")
+                        .about("Convert a raw log file to JSON lines or CSV on stdout")
+                        .arg(
+                            Arg::new("file")
+                                .required(true)
+                                .value_name("FILE")
+                                .help("Raw log file (YYYY-MM-DD.ctrlqlog)")
+                        )
+                        .arg(
+                            Arg::new("format")
+                                .long("format")
+                                .value_name("FORMAT")
+                                .default_value("jsonl")
+                                .help("Output format: jsonl or csv")
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("profile")
+                .about("Manage named profiles in the data directory")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List profiles and whether they are being logged"))
+                .subcommand(
+                    Command::new("copy")
+                        .about("Copy a profile's statistics into a new profile")
+                        .arg(Arg::new("from").required(true).value_name("FROM"))
+                        .arg(Arg::new("to").required(true).value_name("TO"))
+                )
+                .subcommand(
+                    Command::new("delete")
+                        .about("Delete a profile's data file")
+                        .arg(Arg::new("name").required(true).value_name("NAME"))
+                )
+        )
+        .subcommand(
+            Command::new("backup")
+                .about("Back up or restore all profiles, their archives and the config")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("create")
+                        .about("Write a backup with checksums of every file")
+                        .arg(
+                            Arg::new("output")
+                                .long("output")
+                                .short('o')
+                                .value_name("FILE")
+                                .help("Backup file to write (default: ctrlq-backup-<date>-<time>.tar.zst); a name ending in .zst is compressed with zstd")
+                        )
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Verify a backup and restore its files")
+                        .arg(Arg::new("file").required(true).value_name("FILE"))
+                        .arg(
+                            Arg::new("force")
+                                .long("force")
+                                .action(clap::ArgAction::SetTrue)
+                                .help("Overwrite files that changed after the backup was made")
+                        )
+                )
+        )
         .get_matches();
 
+    if let Some(dir) = matches.get_one::<PathBuf>("input-dir") {
+        paths::set_input_dir(dir.clone());
+    }
+    numbers::set_separator(Config::load().map(|config| config.thousands_separator).unwrap_or_default());
+    if let Some(("setup", _)) = matches.subcommand() {
+        return setup::run();
+    }
+    if let Some(("doctor", doctor_matches)) = matches.subcommand() {
+        if doctor_matches.get_flag("offline") {
+            let profile_name = matches.get_one::<String>("profile").map_or(profile::DEFAULT_PROFILE, String::as_str);
+            return offline_check(profile_name);
+        }
+        return doctor(doctor_matches.get_flag("suggest-caps"));
+    }
+
+    let profile_name = matches
+        .get_one::<String>("profile")
+        .map(String::as_str)
+        .unwrap_or(profile::DEFAULT_PROFILE);
+    let data_file = profile::data_file(profile_name)?;
+    if let Some(data_dir) = data_file.parent() {
+        migrate_state(data_dir);
+    }
+    // Commands that rewrite the data file keep the format the logger uses
+    let pretty = matches.get_flag("pretty-json") || Config::load().is_ok_and(|config| config.pretty_json);
+
+    match matches.subcommand() {
+        Some(("profile", sub)) => return manage_profiles(sub),
+        Some(("view", sub)) => {
+            let file = sub.get_one::<String>("data-file").map_or_else(|| data_file.clone(), PathBuf::from);
+            let compare = match sub.get_one::<String>("compare") {
+                Some(other) if Path::new(other).exists() => Some(PathBuf::from(other)),
+                Some(other) => Some(profile::data_file(other)?),
+                None => None,
+            };
+            return view_data_file(&file, compare.as_deref(), profile_name);
+        }
+        Some(("backup", sub)) => return manage_backups(sub),
+        Some(("context", sub)) => {
+            return set_context(&data_file, sub.get_one::<String>("name").expect("required"));
+        }
+        Some(("ping", sub)) => {
+            return ping(&data_file, *sub.get_one::<u32>("count").expect("has default"));
+        }
+        Some(("bench", sub)) => {
+            let options = BenchOptions {
+                events: *sub.get_one::<u64>("events").expect("has default"),
+                distinct_keys: *sub.get_one::<usize>("distinct-keys").expect("has default"),
+                days: *sub.get_one::<u32>("days").expect("has default"),
+            };
+            let baseline = sub.get_one::<PathBuf>("baseline").map(|file| BenchReport::load(file)).transpose()?;
+            return run_bench(options, baseline.as_ref(), sub.get_one::<PathBuf>("save-baseline").map(PathBuf::as_path));
+        }
+        Some(("status-line", sub)) => {
+            return print_status_line(&data_file, sub.get_one::<String>("format").expect("has default"));
+        }
+        Some(("log", sub)) => {
+            if let Some(("convert", sub)) = sub.subcommand() {
+                let format = rawlog::ConvertFormat::parse(sub.get_one::<String>("format").expect("has default"))?;
+                let file = std::path::Path::new(sub.get_one::<String>("file").expect("required"));
+                let log = rawlog::convert(file, format, &mut std::io::stdout().lock())?;
+                eprintln!("📼 Converted {} event(s) from {}", log.records.len(), file.display());
+                if log.partial_bytes > 0 {
+                    eprintln!(
+                        "⚠️  Left out a partial record of {} byte(s) at the end, e.g. from a crash",
+                        log.partial_bytes
+                    );
+                }
+            }
+            return Ok(());
+        }
+        Some(("import", sub)) => {
+            let format = import::ImportFormat::parse(sub.get_one::<String>("format").expect("has default"))?;
+            let file = sub.get_one::<String>("file").expect("required");
+            let imported = import::import_file(std::path::Path::new(file), format)?;
+
+            println!("📥 Read {} row(s) from {}", imported.rows, file);
+            if !imported.skipped.is_empty() {
+                println!("⚠️  Skipped {} row(s):", imported.skipped.len());
+                for row in &imported.skipped {
+                    println!("   line {}: {}", row.line, row.reason);
+                }
+            }
+            return merge_into_data_file(&data_file, &imported.stats, sub.get_flag("dry-run"), pretty);
+        }
+        Some(("merge", sub)) => {
+            let file = std::path::Path::new(sub.get_one::<String>("file").expect("required"));
+            if !file.exists() {
+                eprintln!("❌ File not found: {}", file.display());
+                process::exit(1);
+            }
+            let other = KeyLogger::load_full_stats(file)?;
+            return merge_into_data_file(&data_file, &other, sub.get_flag("dry-run"), pretty);
+        }
+        Some(("compact", sub)) => {
+            let minute_days = match sub.get_one::<u64>("minute-days") {
+                Some(days) => *days,
+                None => Config::load().unwrap_or_default().minute_days,
+            };
+            return compact_data_file(&data_file, minute_days, sub.get_flag("dry-run"), pretty);
+        }
+        Some(("fsck", sub)) => {
+            return check_data_file(&data_file, sub.get_flag("repair"), pretty);
+        }
+        Some(("export", sub)) => {
+            let output = sub.get_one::<PathBuf>("output").map(PathBuf::as_path);
+            export_sessions(&data_file, output, sub.get_flag("include-open"), date_range(sub)?)?;
+            // The export itself worked, so this is only worth a warning
+            if let Err(e) = mark_exported(&data_file, pretty) {
+                eprintln!("⚠️  Export not recorded in the data file: {:#}", e);
+            }
+            return Ok(());
+        }
+        Some(("stats", sub)) => {
+            let range = date_range(sub)?;
+            let tag = sub.get_one::<String>("tag").map(String::as_str);
+            let dock = dock_of(sub)?;
+            if sub.get_flag("json") {
+                let sections = match sub.get_one::<String>("fields") {
+                    Some(list) => Section::parse_list(list)?,
+                    None => Section::ALL.to_vec(),
+                };
+                return print_stats_json(&data_file, &sections, range, tag, dock);
+            }
+            return print_stats(&data_file, range, tag, dock);
+        }
+        Some(("heatmap", sub)) => {
+            return print_heatmap(&data_file, date_range(sub)?);
+        }
+        Some(("report", sub)) => {
+            if let Some(tag) = sub.get_one::<String>("tag") {
+                return print_tag_report(&data_file, tag, dock_of(sub)?, date_range(sub)?);
+            }
+            if let Some(week) = sub.get_one::<String>("week") {
+                return write_week_summary(&data_file, report::parse_week(week)?);
+            }
+            if let Some(month) = sub.get_one::<String>("month") {
+                return print_month_report(&data_file, report::parse_month(month)?);
+            }
+            if let Some(typed_on) = sub.get_one::<String>("layout-compare") {
+                return print_layout_comparison(&data_file, Layout::parse(typed_on)?, date_range(sub)?);
+            }
+            if let Some(reference) = sub.get_one::<String>("unusual") {
+                return print_unusual_keys(&data_file, &Corpus::parse(reference), date_range(sub)?);
+            }
+            let periods: Vec<String> = sub
+                .get_many::<String>("compare")
+                .map(|values| values.cloned().collect())
+                .unwrap_or_else(|| vec!["yesterday".to_string(), "today".to_string()]);
+            return print_comparison(&data_file, &periods[0], &periods[1], date_range(sub)?);
+        }
+        Some(("calibrate", sub)) if sub.get_flag("history") => return print_calibrations(&data_file),
+        Some(("calibrate", _)) if matches.get_flag("no-ui") => {
+            eprintln!("❌ ctrlq calibrate shows its typing test in the terminal UI");
+            eprintln!("💡 Run it without --no-ui");
+            process::exit(1);
+        }
+        Some(("reset", sub)) => {
+            let scope = sub.get_one::<String>("scope").map(String::as_str).unwrap_or("all");
+            return reset_stats(&data_file, ResetScope::parse(scope, sub.get_flag("keep-records"))?, pretty);
+        }
+        _ => {}
+    }
+
     if matches.get_flag("list-devices") {
+        if matches.get_flag("json") {
+            let devices = find_keyboard_devices(&paths::input_dir())?;
+            let listed: Vec<ListedDevice> =
+                devices.iter().map(|device| ListedDevice { score: device.score(), device }).collect();
+            println!("{}", serde_json::to_string_pretty(&listed)?);
+            return Ok(());
+        }
+
         println!("🔍 Scanning for keyboard devices...\n");
-        match find_keyboard_devices() {
-            Ok(devices) => {
+        match keylogger::scan_input_devices(&paths::input_dir()) {
+            Ok(scan) => {
+                let devices = scan.devices;
+                if matches.get_flag("verbose") {
+                    for warning in &scan.warnings {
+                        eprintln!("⚠️  Skipped {}", warning);
+                    }
+                }
                 if devices.is_empty() {
                     println!("❌ No keyboard devices found!");
-                    println!("💡 Try running with sudo or check /dev/input/ permissions");
+                    println!("💡 Try running with sudo or check the permissions of {} (ctrlq doctor)", paths::input_dir().display());
+                    for line in privileges::guidance(privileges::access_mode(), &privileges::current_binary()) {
+                        println!("{}", line);
+                    }
                 } else {
-                    println!("📱 Found {} keyboard device(s):", devices.len());
+                    println!("📱 Found {} keyboard device(s), best match first:", devices.len());
+                    let name_width = Config::load().unwrap_or_default().device_name_width;
                     for (i, device) in devices.iter().enumerate() {
-                        println!("  {}. {}", i + 1, device);
+                        print_device(i + 1, device, name_width);
                     }
-                    println!("\n💡 Use: ctrlq -d <device_path>");
+                    println!("\n💡 Use: ctrlq -d <device_path>; the ↳ links stay the same across reboots");
                 }
             }
             Err(e) => {
-                eprintln!("❌ Error scanning devices: {}", e);
+                eprintln!("❌ Error scanning devices: {:#}", e);
                 process::exit(1);
             }
         }
         return Ok(());
     }
 
-    let device_path = if let Some(device) = matches.get_one::<String>("device") {
-        device.clone()
+    let mode = if matches.get_flag("quiet") {
+        OutputMode::Quiet
     } else {
-        match find_keyboard_devices() {
-            Ok(devices) => {
+        OutputMode::parse(matches.get_one::<String>("output-format").expect("has default"))?
+    };
+    let mut out = Presenter::new(mode);
+    let headless = matches.get_flag("no-ui");
+    // The UI's alternate screen would swallow anything printed now
+    if !headless {
+        out.defer();
+    }
+
+    let config = Config::load()?;
+    let access = privileges::access_mode();
+    if matches.get_flag("verbose") {
+        out.info("access", format!("🔑 Reading input devices as {}", access.description()));
+    }
+    let device_paths: Vec<String> = if let Some(devices) = matches.get_many::<String>("device") {
+        devices.cloned().collect()
+    } else if !config.devices.is_empty() {
+        out.info("device", format!("🎯 Keyboard(s) from {}: {}", Config::path().display(), config.devices.join(", ")));
+        config.devices.clone()
+    } else if let Some(devices) = remembered_keyboards(&data_file, &mut out) {
+        devices
+    } else {
+        let chosen = match keylogger::scan_input_devices(&paths::input_dir()) {
+            Ok(scan) => {
+                if matches.get_flag("verbose") {
+                    for warning in &scan.warnings {
+                        out.warn("device_scan", format!("⚠️  Skipped {}", warning));
+                    }
+                }
+                let devices = scan.devices;
                 if devices.is_empty() {
-                    eprintln!("❌ No keyboard devices found!");
-                    eprintln!("💡 Try: ctrlq --list-devices");
+                    out.error("no_device", "❌ No keyboard devices found!");
+                    out.hint("💡 Try: ctrlq doctor, ctrlq --list-devices, or ctrlq setup");
+                    if !scan.warnings.is_empty() {
+                        for line in privileges::guidance(access, &privileges::current_binary()) {
+                            out.hint(line);
+                        }
+                    }
                     process::exit(1);
-                } else if devices.len() == 1 {
-                    println!("🎯 Auto-detected keyboard: {}", devices[0]);
-                    devices[0].clone()
+                } else if let Some(device) = keylogger::auto_select_keyboard(&devices) {
+                    out.info(
+                        "device",
+                        format!(
+                            "🎯 Auto-detected keyboard: {} ({})",
+                            device.stable_path.as_deref().unwrap_or(&device.path),
+                            device.display_name(config.device_name_width)
+                        ),
+                    );
+                    vec![device.path.clone()]
+                } else if std::io::stdin().is_terminal() && mode != OutputMode::Json {
+                    pick_devices(&devices, config.device_name_width)?
                 } else {
-                    eprintln!("❓ Multiple keyboard devices found:");
+                    out.error("ambiguous_device", "❓ Multiple keyboard devices found:");
                     for (i, device) in devices.iter().enumerate() {
-                        eprintln!("  {}. {}", i + 1, device);
+                        out.error(
+                            "ambiguous_device",
+                            format!(
+                                "  {}. {} ({})",
+                                i + 1,
+                                device.stable_path.as_deref().unwrap_or(&device.path),
+                                device.display_name(config.device_name_width)
+                            ),
+                        );
                     }
-                    eprintln!("💡 Please specify one with: ctrlq -d <device_path>");
+                    out.hint("💡 Please specify one with: ctrlq -d <device_path>");
+                    out.hint("💡 Or log several at once: ctrlq -d <device_path> -d <device_path>");
                     process::exit(1);
                 }
             }
             Err(e) => {
-                eprintln!("❌ Error scanning devices: {}", e);
-                eprintln!("💡 Try running with sudo or specify device manually");
+                out.error("no_device", format!("❌ Error scanning devices: {:#}", e));
+                out.hint("💡 Try running with sudo, specify a device manually or run ctrlq setup");
+                for line in privileges::guidance(access, &privileges::current_binary()) {
+                    out.hint(line);
+                }
                 process::exit(1);
             }
+        };
+        remember_keyboards(&data_file, &chosen);
+        chosen
+    };
+
+    let devices = match keylogger::resolve_devices(&device_paths) {
+        Ok(devices) => devices,
+        Err(e) => {
+            out.error("no_device", format!("❌ {}", e));
+            out.hint("💡 Try: ctrlq --list-devices, or ctrlq setup to choose your keyboard again");
+            process::exit(1);
         }
     };
+    let device_names: Vec<String> = devices
+        .iter()
+        .map(|(path, node)| {
+            if Path::new(path) == node {
+                path.clone()
+            } else {
+                format!("{} → {}", path, node.display())
+            }
+        })
+        .collect();
+    let device_paths: Vec<String> = devices.into_iter().map(|(path, _)| path).collect();
 
-    if !std::path::Path::new(&device_path).exists() {
-        eprintln!("❌ Device not found: {}", device_path);
-        eprintln!("💡 Try: ctrlq --list-devices");
-        process::exit(1);
+    // A status bar has room for the essentials only
+    if headless {
+        out.info("start", "🚀 Starting CtrlQ - Developer Keylogger");
+    }
+    out.info("devices", format!("📱 Device: {}", device_names.join(", ")));
+    out.info("data_file", format!("📊 Data will be saved to: {}", data_file.display()));
+    if let Some(warning) = keylogger::data_file_size_warning(&data_file) {
+        out.warn("data_file_size", warning);
+    }
+    if headless {
+        out.info("notice", "⚠️  This tool logs keystrokes for analysis - use responsibly!");
     }
 
-    println!("🚀 Starting CtrlQ - Developer Keylogger");
-    println!("📱 Device: {}", device_path);
-    println!("📊 Statistics will be saved automatically");
-    println!("⚠️  This tool logs keystrokes for analysis - use responsibly!");
-    println!();
+    let overrides = Overrides {
+        no_notifications: matches.get_flag("no-notifications"),
+        no_auto_summary: matches.get_flag("no-auto-summary"),
+        anonymize: matches.get_flag("anonymize"),
+        case_sensitive: matches.get_flag("case-sensitive"),
+        pretty_json: matches.get_flag("pretty-json"),
+        warn_idle_minutes: matches.get_one::<u64>("warn-idle-minutes").copied(),
+        tick_ms: matches.get_one::<u64>("tick-ms").copied(),
+        xkb_layout: matches.get_one::<String>("xkb-layout").cloned(),
+        pause_on_lock: matches.get_flag("pause-on-lock"),
+        no_hooks: matches.get_flag("no-hooks"),
+    };
+    if overrides.xkb_layout.is_some() && !cfg!(feature = "xkb") {
+        out.warn("xkb", "⚠️  --xkb-layout needs a build with --features xkb; counting keys only");
+    }
+    let ui_config = config.clone();
+    let tick_rate = overrides.tick_ms.map_or_else(|| config.tick_rate(), Duration::from_millis);
+    let mut keylogger = KeyLogger::new(device_paths, config, overrides, profile_name)?;
+    if matches.get_flag("raw-log") {
+        let dir = keylogger.log_raw_events()?;
+        out.info("raw_log", format!("📼 Raw event log: {}", dir.display()));
+    }
+
+    // Bound now, served once the logger can take its problems
+    let stream = match matches.get_one::<u16>("stream-port") {
+        Some(&port) => {
+            let (live_tx, live_rx) = watch::channel(LiveSnapshot::default());
+            let server = stream::StreamServer::bind(port, matches.get_flag("stream-allow-null-origin"))?;
+            keylogger.publish_live(live_tx);
+            out.info("stream", format!("📡 Streaming live statistics on http://127.0.0.1:{}/", port));
+            Some((server, live_rx))
+        }
+        None => None,
+    };
 
-    let mut keylogger = KeyLogger::new(device_path)?;
+    let deadline = match matches.get_one::<Duration>("duration") {
+        Some(&limit) => Some(Instant::now().checked_add(limit).context("--duration is too long")?),
+        None => None,
+    };
+    if let Some(deadline) = deadline {
+        keylogger.stop_at(deadline);
+    }
+    if let Some(path) = matches.get_one::<String>("on-exit-export") {
+        keylogger.export_session_on_exit(PathBuf::from(path));
+    }
     
-    let (stats_rx, shutdown_tx, reset_tx) = keylogger.start_logging()?;
+    let channels = keylogger.start_logging()?;
+    if let Some((server, live_rx)) = stream {
+        server.start(live_rx, channels.problem_tx.clone());
+    }
 
-    if matches.get_flag("no-ui") {
-        println!("⌨️  Logging keystrokes... (Press Ctrl+C to stop)");
+    if headless {
+        out.info("logging", "⌨️  Logging keystrokes... (Press Ctrl+C to stop)");
         
+        let shutdown_tx = channels.shutdown_tx;
         ctrlc::set_handler(move || {
-            println!("\n🛑 Received Ctrl+C, shutting down...");
+            Presenter::new(mode).info("shutdown", "🛑 Received Ctrl+C, shutting down...");
             let _ = shutdown_tx.send(true);
-            process::exit(0);
         })?;
 
-        while let Ok(_stats) = stats_rx.recv() {
+        // An empty value means the flag was given without a file
+        let summary_file = matches.get_one::<String>("summary-file").map(|file| match file.as_str() {
+            "" => summary::text_summary_path(&data_file),
+            file => PathBuf::from(file),
+        });
+        let summary_interval = Duration::from_secs(
+            matches.get_one::<u64>("summary-interval").copied().unwrap_or(SUMMARY_FILE_MINUTES) * 60,
+        );
+        if let Some(path) = &summary_file {
+            out.info("summary_file", format!("📝 Summary every {} min: {}", summary_interval.as_secs() / 60, path.display()));
+        }
+
+        let mut last_summary = Instant::now();
+        let mut last_summary_file: Option<Instant> = None;
+        while let Ok(stats) = channels.stats_rx.recv() {
+            print_notices(&channels.notice_rx, &mut out);
+            print_problems(&channels.problem_rx, &mut out);
+            if last_summary.elapsed() >= HEADLESS_SUMMARY_INTERVAL {
+                print_headless_summary(&stats, deadline, &mut out);
+                last_summary = Instant::now();
+            }
+            // Paused on battery, see the power module
+            if let Some(path) = &summary_file
+                && !stats.diagnostics.power_saving
+                && last_summary_file.is_none_or(|last| last.elapsed() >= summary_interval)
+            {
+                if let Err(e) = summary::write_text_summary(path, &stats) {
+                    out.warn("summary_file", format!("❌ Summary not written: {:#}", e));
+                }
+                last_summary_file = Some(Instant::now());
+            }
         }
+        print_notices(&channels.notice_rx, &mut out);
+        print_problems(&channels.problem_rx, &mut out);
     } else {
-        ui::run_ui(stats_rx, shutdown_tx, reset_tx)?;
+        let startup = out.take_deferred();
+        let calibrate = matches!(matches.subcommand(), Some(("calibrate", _)));
+        let options = ui::UiOptions { deadline, tick_rate, calibrate, startup };
+        // What the logger said on its way out, after the UI left the screen
+        if let Some(channels) = ui::run_live_ui(&mut keylogger, channels, profile_name, &ui_config, options)? {
+            print_notices(&channels.notice_rx, &mut out);
+            print_problems(&channels.problem_rx, &mut out);
+        }
     }
 
-    println!("👋 CtrlQ stopped. Your keystroke data has been saved!");
+    keylogger.wait();
+
+    out.info("stopped", "👋 CtrlQ stopped");
+    Ok(())
+}
+
+/// Prints the notices the logging thread sent since the last call.
+///
+/// # Arguments
+/// * `notice_rx` - Notices of the logging thread
+/// * `out` - Where they are printed
+fn print_notices(notice_rx: &std::sync::mpsc::Receiver<String>, out: &mut Presenter) {
+    while let Ok(notice) = notice_rx.try_recv() {
+        out.info("notice", notice);
+    }
+}
+
+/// Prints the problems the logging thread reported since the last call.
+///
+/// # Arguments
+/// * `problem_rx` - Problems of the logging thread
+/// * `out` - Where they are printed
+fn print_problems(problem_rx: &std::sync::mpsc::Receiver<LoggerProblem>, out: &mut Presenter) {
+    while let Ok(problem) = problem_rx.try_recv() {
+        match problem {
+            LoggerProblem::Error(message) => out.warn("problem", format!("❌ {}", message)),
+            LoggerProblem::Crashed(message) => out.error("crashed", format!("💥 Logging thread crashed: {}", message)),
+        }
+    }
+}
+
+/// Returns the keyboards auto-detected on an earlier start, if all of them
+/// are still plugged in.
+///
+/// # Arguments
+/// * `data_file` - Path of the profile's data file
+/// * `out` - Where the outcome is printed
+///
+/// # Returns
+/// `Option<Vec<String>>` - Stable links to the keyboards, or None to detect them again
+fn remembered_keyboards(data_file: &Path, out: &mut Presenter) -> Option<Vec<String>> {
+    let devices = profile::remembered_devices(data_file);
+    if devices.is_empty() {
+        return None;
+    }
+    match keylogger::resolve_devices(&devices) {
+        Ok(_) => {
+            out.info("device", format!("🎯 Keyboard(s) from the last run: {}", devices.join(", ")));
+            out.hint("💡 Use -d or ctrlq setup to log another keyboard");
+            Some(devices)
+        }
+        Err(e) => {
+            out.warn("device_gone", format!("⚠️  The keyboard from the last run is gone: {}", e));
+            out.hint("🔍 Detecting keyboards again");
+            None
+        }
+    }
+}
+
+/// Remembers auto-detected keyboards by their `by-id` or `by-path` links for
+/// the next start.
+///
+/// Keyboards without such a link are not remembered: their event number
+/// may belong to another device after a reboot.
+///
+/// # Arguments
+/// * `data_file` - Path of the profile's data file
+/// * `devices` - Event device paths of the chosen keyboards
+fn remember_keyboards(data_file: &Path, devices: &[String]) {
+    let links: Option<Vec<String>> = devices.iter().map(|device| keylogger::device_alias(device)).collect();
+    let Some(links) = links else {
+        let _ = std::fs::remove_file(profile::devices_path(data_file));
+        return;
+    };
+    if let Err(e) = profile::remember_devices(data_file, &links) {
+        eprintln!("⚠️  Couldn't remember the keyboard for the next start: {:#}", e);
+    }
+}
+
+/// Asks which of several candidate devices to log.
+///
+/// # Arguments
+/// * `devices` - Candidates, best match first
+///
+/// # Returns
+/// `Result<Vec<String>>` - Paths of the chosen devices
+fn pick_devices(devices: &[keylogger::InputDeviceInfo], name_width: usize) -> Result<Vec<String>> {
+    println!("❓ Couldn't tell which device is your keyboard:");
+    for (i, device) in devices.iter().enumerate() {
+        print_device(i + 1, device, name_width);
+    }
+
+    loop {
+        print!("Device number(s), comma separated [1]: ");
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            bail!("no device chosen");
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(vec![devices[0].path.clone()]);
+        }
+
+        let chosen: Option<Vec<String>> = answer
+            .split(',')
+            .map(|n| {
+                let index = n.trim().parse::<usize>().ok()?.checked_sub(1)?;
+                devices.get(index).map(|device| device.path.clone())
+            })
+            .collect();
+        match chosen {
+            Some(paths) => return Ok(paths),
+            None => println!("❌ Enter numbers between 1 and {}", devices.len()),
+        }
+    }
+}
+
+/// Checks the input devices for `ctrlq doctor`: how many event devices
+/// there are, which couldn't be opened and why, and which are keyboards.
+///
+/// # Arguments
+/// * `suggest_caps` - Whether to list every way of reading the devices without sudo
+///
+/// # Returns
+/// `Result<()>` - Success; exits with 1 if /dev/input can't be read or no keyboard was found
+fn doctor(suggest_caps: bool) -> Result<()> {
+    let access = privileges::access_mode();
+    let binary = privileges::current_binary();
+    println!("🔑 Reading input devices as {}", access.description());
+    if suggest_caps {
+        println!("💡 To log without sudo, use one of:");
+        println!("   {}", privileges::setcap_command(&binary));
+        println!("   sudo usermod -aG {} $USER   (then log out and back in)", privileges::INPUT_GROUP);
+        println!("   {}", privileges::udev_rule_command());
+        println!("💡 setcap has to be run again whenever the binary is rebuilt or replaced");
+    }
+
+    let input_dir = paths::input_dir();
+    println!("🩺 Checking {}", input_dir.display());
+    let scan = match keylogger::scan_input_devices(&input_dir) {
+        Ok(scan) => scan,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            process::exit(1);
+        }
+    };
+
+    let unreadable = scan.warnings.len();
+    println!("📱 {} event device(s), {} could be opened", scan.scanned, scan.scanned.saturating_sub(unreadable));
+    for warning in &scan.warnings {
+        println!("⚠️  {}", warning);
+    }
+    if unreadable > 0 {
+        println!("💡 Devices that can't be opened are usually a permission problem: run with sudo, or ctrlq setup");
+        if !suggest_caps {
+            for line in privileges::guidance(access, &binary) {
+                println!("{}", line);
+            }
+        }
+    }
+
+    if scan.devices.is_empty() {
+        eprintln!("❌ No keyboard devices found");
+        process::exit(1);
+    }
+    let name_width = Config::load().unwrap_or_default().device_name_width;
+    println!("⌨️  {} keyboard device(s), best match first:", scan.devices.len());
+    for (i, device) in scan.devices.iter().enumerate() {
+        print_device(i + 1, device, name_width);
+    }
+    if unreadable == 0 {
+        println!("✅ Every event device could be opened");
+    }
     Ok(())
 }
+
+/// Runs the checks of `ctrlq doctor --offline`, which need neither root nor
+/// an input device: creates the data directory, loads the data file or
+/// creates an empty one, draws every UI tab into memory and checks the key
+/// and layout tables. Exits with 1 if any check fails.
+///
+/// # Arguments
+/// * `profile_name` - Profile whose data file is loaded
+fn offline_check(profile_name: &str) -> Result<()> {
+    println!("🩺 Offline check, no input devices are read");
+    let mut failed = 0;
+    let mut report = |name: &str, result: Result<String>| match result {
+        Ok(detail) => println!("✅ {}: {}", name, detail),
+        Err(e) => {
+            println!("❌ {}: {:#}", name, e);
+            failed += 1;
+        }
+    };
+
+    let mut loaded = None;
+    match profile::data_file(profile_name) {
+        Ok(file) => {
+            report("Data directory", Ok(file.parent().unwrap_or(&file).display().to_string()));
+            match load_or_create(&file) {
+                Ok((stats, detail)) => {
+                    report("Data file", Ok(detail));
+                    loaded = Some((file, stats));
+                }
+                Err(e) => report("Data file", Err(e)),
+            }
+        }
+        Err(e) => report("Data directory", Err(e)),
+    }
+    if let Some((file, stats)) = loaded {
+        let config = Config::load().unwrap_or_default();
+        let frames = ui::check_render(stats, &file, &config);
+        report("UI", frames.map(|frames| format!("{} frames of every tab drawn without a terminal", frames)));
+    }
+
+    let table_problems = |problems: Vec<String>, what: &str| {
+        if problems.is_empty() { Ok(format!("{} agree", what)) } else { Err(anyhow::anyhow!(problems.join("; "))) }
+    };
+    report("Key tables", table_problems(ctrlq::keymap::self_check(), "key names, characters and heatmap rows"));
+    report("Layout tables", table_problems(layout::self_check(), format!("{} layouts", Layout::ALL.len()).as_str()));
+
+    if failed > 0 {
+        eprintln!("❌ {} check(s) failed", failed);
+        process::exit(1);
+    }
+    println!("✅ All checks passed");
+    Ok(())
+}
+
+/// Loads a data file strictly, or creates an empty one if there is none,
+/// for [`offline_check`].
+///
+/// # Returns
+/// `Result<(KeyStats, String)>` - The statistics with their archived days and
+/// what was done, or an error if the file can't be read, parsed or written
+fn load_or_create(data_file: &Path) -> Result<(KeyStats, String)> {
+    if !data_file.exists() {
+        let stats = KeyStats::new();
+        KeyLogger::save_stats(&stats, data_file, false)
+            .with_context(|| format!("failed to create {}", data_file.display()))?;
+        return Ok((stats, format!("created {}", data_file.display())));
+    }
+    let mut stats =
+        KeyLogger::read_stats(data_file).with_context(|| format!("{} is not a valid data file", data_file.display()))?;
+    archive::load_into(&mut stats, data_file)?;
+    let detail = format!("loaded {} ({} keystrokes)", data_file.display(), numbers::format_count(stats.total_keystrokes));
+    Ok((stats, detail))
+}
+
+/// A device of `--list-devices --json`, with its score.
+#[derive(serde::Serialize)]
+struct ListedDevice<'a> {
+    #[serde(flatten)]
+    device: &'a keylogger::InputDeviceInfo,
+    /// How keyboard-like the device is, see `InputDeviceInfo::score`
+    score: i32,
+}
+
+/// Prints one entry of the `--list-devices` output.
+///
+/// # Arguments
+/// * `index` - Position in the list, starting at 1
+/// * `device` - The device to describe
+/// * `name_width` - Characters of the name to show, see `device_name_width`
+fn print_device(index: usize, device: &keylogger::InputDeviceInfo, name_width: usize) {
+    println!("  {}. {}  {}", index, device.path, device.display_name(name_width));
+    if let Some(link) = &device.stable_path {
+        println!("     ↳ {}", link);
+    }
+    println!(
+        "     {} · ID {:04x}:{:04x} · {} keys · score {}{}",
+        device.physical_path.as_deref().unwrap_or("no physical path"),
+        device.vendor_id,
+        device.product_id,
+        device.key_count,
+        device.score(),
+        if device.has_letter_keys { " · letters A-Z" } else { " · no letter keys" }
+    );
+    if device.has_relative_axes {
+        println!("     ⚠️  Also reports relative axes - probably a mouse or combo device");
+    }
+}
+
+/// Parses a duration such as `90s`, `25m`, `2h` or `1h30m`.
+///
+/// # Returns
+/// `Result<Duration, String>` - The duration, or a message for clap
+fn parse_duration(spec: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{}': expected e.g. 90s, 25m or 2h", spec);
+
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in spec.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(|| format!("duration '{}' is too long", spec))?;
+        number.clear();
+    }
+
+    if !number.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Prints the periodic one-line summary shown in headless mode.
+///
+/// # Arguments
+/// * `stats` - Latest statistics from the logger
+/// * `deadline` - When a `--duration` run ends, if limited
+/// * `out` - Where it is printed
+fn print_headless_summary(stats: &keylogger::KeyStats, deadline: Option<Instant>, out: &mut Presenter) {
+    let mut line = format!(
+        "📊 {} keystrokes this session · {}",
+        numbers::format_count(stats.session_keystrokes),
+        report::format_wpm(stats.get_wpm())
+    );
+    if let Some(deadline) = deadline {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        line.push_str(&format!(" · ⏱️  {} left", report::format_duration(remaining)));
+    }
+    out.info("summary", line);
+}
+
+/// The `--since`/`--until` options and their shorthands, shared by the
+/// read-only subcommands.
+fn range_args() -> [Arg; 5] {
+    let shorthands = ["since", "until", "today", "this-week", "this-month"];
+    let shorthand = |name: &'static str, help: &'static str| {
+        Arg::new(name)
+            .long(name)
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with_all(shorthands.iter().filter(|other| **other != name))
+            .help(help)
+    };
+    [
+        Arg::new("since")
+            .long("since")
+            .value_name("DATE")
+            .help("Only days from DATE on (YYYY-MM-DD or a period such as last-week)"),
+        Arg::new("until")
+            .long("until")
+            .value_name("DATE")
+            .help("Only days up to and including DATE"),
+        shorthand("today", "Only today (same as --since today --until today)"),
+        shorthand("this-week", "Only this week, starting on Monday"),
+        shorthand("this-month", "Only this month"),
+    ]
+}
+
+/// The `--tag` argument of `stats` and `report`.
+fn tag_arg(help: &'static str) -> Arg {
+    Arg::new("tag").long("tag").value_name("TAG").help(help)
+}
+
+/// The `--dock` argument of `stats` and `report`.
+fn dock_arg(help: &'static str) -> Arg {
+    Arg::new("dock").long("dock").value_name("DOCK").value_parser(Dock::ALL.map(Dock::label)).help(help)
+}
+
+/// Reads the `--dock` argument, if given.
+fn dock_of(sub: &clap::ArgMatches) -> Result<Option<Dock>> {
+    sub.get_one::<String>("dock").map(|dock| Dock::parse(dock)).transpose()
+}
+
+/// Builds the date range given with [`range_args`].
+///
+/// # Arguments
+/// * `sub` - Matches of the subcommand
+///
+/// # Returns
+/// `Result<DateRange>` - The range (unbounded if none was given), or an
+/// error for invalid dates
+fn date_range(sub: &clap::ArgMatches) -> Result<DateRange> {
+    let today = keylogger::today();
+    for period in ["today", "this-week", "this-month"] {
+        if sub.get_flag(period) {
+            return DateRange::period(period, today);
+        }
+    }
+    DateRange::parse(
+        sub.get_one::<String>("since").map(String::as_str),
+        sub.get_one::<String>("until").map(String::as_str),
+        today,
+    )
+}
+
+/// Loads the statistics of the data file, scoped to a date range.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `range` - Days to keep
+///
+/// # Returns
+/// `Result<KeyStats>` - The statistics, or an error if a limited range has
+/// no recorded days
+fn load_scoped_stats(data_file: &Path, range: DateRange) -> Result<KeyStats> {
+    let stats = KeyLogger::load_full_stats(data_file)?;
+    if range.is_unbounded() {
+        return Ok(stats);
+    }
+    let scoped = range.filter(&stats);
+    range.ensure_data(&scoped)?;
+    Ok(scoped)
+}
+
+/// Opens the terminal UI on a saved data file without starting a logger.
+///
+/// No device is opened and nothing is written, so this needs neither root
+/// nor a free profile lock.
+///
+/// # Arguments
+/// * `file` - The data file to view
+/// * `compare` - Data file whose heatmap to compare with, if any
+/// * `profile_name` - Profile shown when the file is the profile's own
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if a file isn't a ctrlq data file
+fn view_data_file(file: &Path, compare: Option<&Path>, profile_name: &str) -> Result<()> {
+    for file in std::iter::once(file).chain(compare) {
+        if !file.exists() {
+            eprintln!("❌ File not found: {}", file.display());
+            eprintln!("💡 Pass another one with: ctrlq view --data-file <file> --compare <profile|file>");
+            process::exit(1);
+        }
+    }
+    let read = |file: &Path| -> Result<KeyStats> {
+        KeyLogger::read_stats(file).with_context(|| format!("{} is not a ctrlq data file", file.display()))
+    };
+    let stats = read(file)?;
+    let compare = compare
+        .map(|other| Ok::<_, anyhow::Error>((ui::data_file_label(other), Box::new(read(other)?))))
+        .transpose()?;
+
+    let config = Config::load().unwrap_or_default();
+    let source = ui::StatsSource::Static { stats: Box::new(stats), data_file: file.to_path_buf(), compare };
+    let options = ui::UiOptions { tick_rate: config.tick_rate(), ..Default::default() };
+    ui::run_ui(source, profile_name, &config, options, || {
+        bail!("ctrlq view has no logger to restart")
+    })?;
+    Ok(())
+}
+
+/// Prints the keyboard heatmap of the saved statistics, see `ctrlq heatmap`.
+///
+/// Colors are used on a terminal unless `NO_COLOR` is set; otherwise each
+/// key is followed by a character for how much it was used.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `range` - Days to include
+///
+/// # Returns
+/// `Result<()>` - Success or data file error
+fn print_heatmap(data_file: &Path, range: DateRange) -> Result<()> {
+    let stats = load_scoped_stats(data_file, range)?;
+    if stats.anonymized {
+        eprintln!("❌ {} is anonymized: it has key classes, but no keys to place on a keyboard", data_file.display());
+        eprintln!("💡 ctrlq stats shows the keystrokes of each class");
+        process::exit(1);
+    }
+    let color = std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && std::io::stdout().is_terminal();
+
+    println!("⌨️  Keyboard heatmap ({})", data_file.display());
+    if !range.is_unbounded() {
+        println!("  Range:            {}", range.label());
+    }
+    println!("  Total keystrokes: {}", numbers::format_count(stats.total_keystrokes));
+    println!();
+    // Shifted variants (case-sensitive mode) count towards their physical key
+    print!("{}", report::console_heatmap(&HeatGrid::of_counts(&stats.physical_key_counts(), None), color));
+    Ok(())
+}
+
+/// Prints a summary of the saved statistics, including personal records.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `range` - Days to include
+/// * `tag` - Only count the sessions with this tag
+/// * `dock` - Only count the sessions typed docked or mobile
+///
+/// # Returns
+/// `Result<()>` - Success or data file error
+fn print_stats(data_file: &Path, range: DateRange, tag: Option<&str>, dock: Option<Dock>) -> Result<()> {
+    let mut stats = load_scoped_stats(data_file, range)?;
+    retain_sessions(&mut stats, tag, dock);
+
+    println!("📊 CtrlQ statistics ({})", data_file.display());
+    if !range.is_unbounded() {
+        println!("  Range:            {}", range.label());
+    }
+    if let Some(tag) = tag {
+        println!("  Sessions tagged:  {}", tag);
+    }
+    if let Some(dock) = dock {
+        println!("  Sessions typed:   {}", dock);
+    }
+    println!("  Total keystrokes: {}", numbers::format_count(stats.total_keystrokes));
+    println!("  Unique keys:      {}", numbers::format_count(stats.key_counts.len() as u64));
+    println!("  Sessions:         {}", numbers::format_count(stats.typing_sessions.len() as u64));
+    let start = range.since.unwrap_or(chrono::NaiveDate::MIN);
+    let patterns = stats.session_patterns(start, range.until.unwrap_or_else(keylogger::today));
+    let length = |d: chrono::Duration| report::format_duration(d.to_std().unwrap_or_default());
+    if let (Some(average), Some(p90)) = (patterns.average_length, patterns.p90_length) {
+        println!("  Session length:   {} on average, 90% within {}", length(average), length(p90));
+    }
+    if let Some(gap) = patterns.average_gap {
+        println!("  Between sessions: {} on average (same day)", length(gap));
+    }
+    println!("  Sessions per day: {}", report::format_sessions_per_day(&patterns, start));
+    println!("  Days recorded:    {}", numbers::format_count(stats.daily_stats.len() as u64));
+    let sfb = layout::same_finger_bigrams(&stats.bigram_counts, Layout::Qwerty, Layout::Qwerty, 0);
+    if sfb.bigrams > 0 {
+        println!(
+            "  Same-finger:      {} of {} bigrams (typed on QWERTY)",
+            report::format_percent(sfb.rate().map(|rate| rate * 100.0), 1),
+            numbers::format_count(sfb.bigrams)
+        );
+    }
+    println!();
+
+    let records = &stats.records;
+    println!("🏆 Records");
+    match records.best_wpm_1m {
+        Some(r) => println!("  Best 1-min WPM:   {:.1} ({})", r.value, r.achieved.format("%Y-%m-%d")),
+        None => println!("  Best 1-min WPM:   —"),
+    }
+    match records.best_wpm_5m {
+        Some(r) => println!("  Best 5-min WPM:   {:.1} ({})", r.value, r.achieved.format("%Y-%m-%d")),
+        None => println!("  Best 5-min WPM:   —"),
+    }
+    match records.most_keystrokes_day {
+        Some(r) => println!("  Most in a day:    {} ({})", numbers::format_count(r.value), r.achieved.format("%Y-%m-%d")),
+        None => println!("  Most in a day:    —"),
+    }
+    match records.longest_session_secs {
+        Some(r) => println!("  Longest session:  {} min ({})", r.value / 60, r.achieved.format("%Y-%m-%d")),
+        None => println!("  Longest session:  —"),
+    }
+
+    let devices = stats.device_totals();
+    if !devices.is_empty() {
+        let attributed: u64 = devices.iter().map(|(_, count)| count).sum();
+        let today = keylogger::day_key(keylogger::today());
+        let today_stats = stats.daily_stats.get(&today);
+
+        // Long device names are cut so the columns stay aligned
+        let name_width = Config::load().unwrap_or_default().device_name_width;
+        let names: Vec<String> = devices.iter().map(|(device, _)| keylogger::truncate_name(device, name_width)).collect();
+        let column = names.iter().map(|name| name.chars().count()).max().unwrap_or(0).max(20);
+
+        println!();
+        println!("⌨️  Devices");
+        for ((device, count), name) in devices.into_iter().zip(names) {
+            let today_count = today_stats
+                .and_then(|day| day.device_keystrokes.get(&device))
+                .copied()
+                .unwrap_or(0);
+            println!(
+                "  {:<column$} {:>10} ({:>6})  today: {}",
+                name,
+                numbers::format_count(count),
+                report::format_percent(report::percent(count, attributed), 1),
+                numbers::format_count(today_count)
+            );
+        }
+    }
+
+    if stats.uses_contexts() {
+        let contexts = stats.context_totals();
+        let attributed: u64 = contexts.iter().map(|(_, count)| count).sum();
+        let today = keylogger::day_key(keylogger::today());
+        let today_stats = stats.daily_stats.get(&today);
+
+        println!();
+        println!("📂 Contexts");
+        for (context, count) in contexts {
+            let today_count = today_stats
+                .and_then(|day| day.context_keystrokes.get(&context))
+                .copied()
+                .unwrap_or(0);
+            println!(
+                "  {:<32} {:>10} ({:>6})  today: {}",
+                context,
+                numbers::format_count(count),
+                report::format_percent(report::percent(count, attributed), 1),
+                numbers::format_count(today_count)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints statistics from the data file as versioned JSON.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `sections` - Sections to include
+/// * `range` - Days to include
+/// * `tag` - Only include the sessions with this tag
+/// * `dock` - Only include the sessions typed docked or mobile
+///
+/// # Returns
+/// `Result<()>` - Success or data file error
+fn print_stats_json(
+    data_file: &Path,
+    sections: &[Section],
+    range: DateRange,
+    tag: Option<&str>,
+    dock: Option<Dock>,
+) -> Result<()> {
+    let mut stats = load_scoped_stats(data_file, range)?;
+    retain_sessions(&mut stats, tag, dock);
+    let output = StatsOutput::from_stats(&stats, keylogger::today(), sections);
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// Resets statistics, through the control socket of a running instance if
+/// there is one and in the data file otherwise.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `scope` - What to reset
+/// * `pretty` - Whether to write indented JSON
+///
+/// # Returns
+/// `Result<()>` - Success, or a data file or socket error
+fn reset_stats(data_file: &Path, scope: ResetScope, pretty: bool) -> Result<()> {
+    // A running instance saved what it counted up to its last save
+    let loss = match scope {
+        ResetScope::All { .. } => KeyLogger::load_full_stats(data_file)?,
+        _ => KeyLogger::load_stats(data_file)?,
+    }
+    .reset_loss(scope);
+    println!("🗑️  Removing {}", loss.summary());
+    println!("{} {}", if loss.backup { "💾" } else { "⚠️ " }, loss.backup_line());
+    println!("{} {}", if loss.last_export_at.is_some() { "📤" } else { "⚠️ " }, loss.export_line());
+
+    let keep_records = matches!(scope, ResetScope::All { keep_records: true });
+    let request = ipc::Request::Reset { scope: scope.name().to_string(), keep_records };
+    match ipc::send(&ipc::socket_path(data_file), &request)? {
+        Some(response) if response.ok => {
+            println!("{} (in the running instance)", scope.done_message());
+            return Ok(());
+        }
+        Some(response) => {
+            bail!(
+                "running instance refused the reset: {}",
+                response.error.unwrap_or_default()
+            );
+        }
+        None => {}
+    }
+
+    let mut stats = KeyLogger::load_stats(data_file)?;
+    if let ResetScope::All { .. } = scope {
+        let backup = KeyLogger::backup_stats(&stats, data_file)?;
+        println!("💾 Backup saved to {}", backup.display());
+        archive::clear(data_file)?;
+    }
+    stats.apply_reset(scope);
+    KeyLogger::persist_stats(&mut stats, data_file, pretty)?;
+
+    println!("{}", scope.done_message());
+    Ok(())
+}
+
+/// Records that the statistics were exported, in the running instance if
+/// there is one and in the data file otherwise.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `pretty` - Whether to write indented JSON
+///
+/// # Returns
+/// `Result<()>` - Success, or a data file or socket error
+fn mark_exported(data_file: &Path, pretty: bool) -> Result<()> {
+    match ipc::send(&ipc::socket_path(data_file), &ipc::Request::Exported)? {
+        Some(response) if response.ok => Ok(()),
+        Some(response) => bail!("running instance refused the export: {}", response.error.unwrap_or_default()),
+        None if !data_file.exists() => Ok(()),
+        None => {
+            let mut stats = KeyLogger::load_stats(data_file)?;
+            stats.last_export_at = Some(chrono::Utc::now());
+            KeyLogger::persist_stats(&mut stats, data_file, pretty)
+        }
+    }
+}
+
+/// Switches the context of the running instance, see `ctrlq context`.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file, used to find its control socket
+/// * `name` - Context to attribute new keystrokes to
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if no instance runs or it refused
+fn set_context(data_file: &Path, name: &str) -> Result<()> {
+    let request = ipc::Request::SetContext { value: name.to_string() };
+    match ipc::send(&ipc::socket_path(data_file), &request)? {
+        Some(response) if response.ok => {
+            println!("📂 Context set to {}", name);
+            Ok(())
+        }
+        Some(response) => bail!(
+            "running instance refused the context: {}",
+            response.error.unwrap_or_default()
+        ),
+        None => bail!("no running ctrlq instance for {}", data_file.display()),
+    }
+}
+
+/// Reports keystrokes typed out of sight of the logged keyboards to the
+/// running instance, see `ctrlq ping`.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file, used to find its control socket
+/// * `count` - Keystrokes to count
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if no instance runs or it refused
+fn ping(data_file: &Path, count: u32) -> Result<()> {
+    match ipc::send(&ipc::socket_path(data_file), &ipc::Request::Ping { count })? {
+        Some(response) if response.ok => {
+            println!("📡 Counted {} remote keystroke(s)", count);
+            Ok(())
+        }
+        Some(response) => bail!(
+            "running instance refused the keystrokes: {}",
+            response.error.unwrap_or_default()
+        ),
+        None => bail!("no running ctrlq instance for {}", data_file.display()),
+    }
+}
+
+/// Runs `ctrlq bench` in a scratch directory and prints its results.
+///
+/// # Arguments
+/// * `options` - What to run
+/// * `baseline` - An earlier run to compare with, if any
+/// * `save` - Where to save this run for a later comparison, if anywhere
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if the run or the saving failed
+fn run_bench(options: BenchOptions, baseline: Option<&BenchReport>, save: Option<&Path>) -> Result<()> {
+    println!(
+        "⏱️  Feeding {} events over {} keys, on top of {} days of history...",
+        numbers::format_count(options.events),
+        options.distinct_keys,
+        options.days
+    );
+    if cfg!(debug_assertions) {
+        println!("💡 This is a debug build: build with --release for numbers worth comparing");
+    }
+    let scratch = std::env::temp_dir().join(format!("ctrlq-bench-{}", process::id()));
+    let result = bench::run(options, &scratch);
+    let _ = std::fs::remove_dir_all(&scratch);
+    let report = result?;
+
+    if let Some(baseline) = baseline
+        && (baseline.events, baseline.distinct_keys, baseline.days) != (report.events, report.distinct_keys, report.days)
+    {
+        println!(
+            "⚠️  The baseline ran {} events over {} keys with {} days of history, so the numbers differ in more than the build",
+            numbers::format_count(baseline.events),
+            baseline.distinct_keys,
+            baseline.days
+        );
+    }
+    println!("\n{}", bench::comparison_table(&report, baseline));
+    if let Some(path) = save {
+        report.save(path)?;
+        println!("\n💾 Saved as a baseline: {}", path.display());
+    }
+    Ok(())
+}
+
+/// Prints the one-line status of `ctrlq status-line`: from the running
+/// logger if there is one, else from the status file of its last save.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `template` - The `--format` template, see [`status_line`]
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if neither source has a status
+fn print_status_line(data_file: &Path, template: &str) -> Result<()> {
+    let format = match StatusFormat::parse(template) {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("❌ Invalid --format: {:#}", e);
+            eprintln!("💡 Example: ctrlq status-line --format '{{today}} today · {{wpm}} WPM'");
+            process::exit(1);
+        }
+    };
+    // An instance that doesn't know `status` yet counts as none
+    let live = match ipc::send(&ipc::socket_path(data_file), &ipc::Request::Status) {
+        Ok(Some(response)) => response.status,
+        _ => None,
+    };
+    let (status, stale) = match live {
+        Some(status) => (status, false),
+        None => {
+            let file = status_line::status_file(data_file);
+            let status = StatusSnapshot::load(&file)
+                .with_context(|| format!("no running ctrlq instance for {} and no status saved", data_file.display()))?;
+            (status, true)
+        }
+    };
+    let daily_goal = Config::load().ok().and_then(|config| config.daily_goal);
+    println!("{}", format.render(&status.as_of(keylogger::today()), daily_goal, stale));
+    Ok(())
+}
+
+/// Prints a side-by-side comparison of two periods.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `a` - Baseline period specification
+/// * `b` - Compared period specification
+/// * `range` - Days the periods are limited to
+///
+/// # Returns
+/// `Result<()>` - Success, or an error for invalid periods or data file problems
+fn print_comparison(data_file: &Path, a: &str, b: &str, range: DateRange) -> Result<()> {
+    let today = keylogger::today();
+    let (a_start, a_end, a_label) = report::parse_period(a, today)?;
+    let (b_start, b_end, b_label) = report::parse_period(b, today)?;
+
+    let stats = load_scoped_stats(data_file, range)?;
+
+    let comparison = stats.compare_periods((a_start, a_end, &a_label), (b_start, b_end, &b_label));
+    println!("{}", report::comparison_report(&comparison));
+    Ok(())
+}
+
+/// Drops the finished sessions without `tag` or typed other than `dock`,
+/// for those given.
+fn retain_sessions(stats: &mut KeyStats, tag: Option<&str>, dock: Option<Dock>) {
+    stats.typing_sessions.retain(|session| {
+        tag.is_none_or(|tag| session.tag.as_deref() == Some(tag)) && dock.is_none_or(|dock| session.dock == Some(dock))
+    });
+}
+
+/// Prints the sessions with one tag and their totals.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `tag` - The tag given with `:tag`
+/// * `dock` - Only the sessions typed docked or mobile
+/// * `range` - Days to include
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if no session has the tag
+fn print_tag_report(data_file: &Path, tag: &str, dock: Option<Dock>, range: DateRange) -> Result<()> {
+    let mut stats = load_scoped_stats(data_file, range)?;
+    retain_sessions(&mut stats, Some(tag), dock);
+    if stats.typing_sessions.is_empty() {
+        match dock {
+            Some(dock) => bail!("no finished {} sessions tagged {}", dock, tag),
+            None => bail!("no finished sessions tagged {}", tag),
+        }
+    }
+    let sessions: Vec<_> = stats.typing_sessions.iter().collect();
+    let label = match dock {
+        Some(dock) => format!("{} ({})", tag, dock),
+        None => tag.to_string(),
+    };
+    println!("{}", report::tag_report(&label, &sessions));
+    Ok(())
+}
+
+/// Prints every calibration of `ctrlq calibrate`, grouped by keyboard.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file; calibrations are kept next to it
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if the history can't be read
+fn print_calibrations(data_file: &Path) -> Result<()> {
+    let path = CalibrationHistory::path(data_file);
+    let history = CalibrationHistory::load(&path)?;
+    if history.runs.is_empty() {
+        println!("🎯 No calibrations yet - run sudo ctrlq calibrate");
+        return Ok(());
+    }
+
+    let mut devices: Vec<&str> = history.runs.iter().map(|run| run.device.as_str()).collect();
+    devices.sort();
+    devices.dedup();
+    println!("🎯 Calibrations in {}", path.display());
+    for device in devices {
+        println!("\n⌨️  {}", device);
+        for run in history.runs.iter().filter(|run| run.device == device) {
+            println!(
+                "  {}  {:>6.1} WPM  {:>5.1}% accuracy  ctrlq {:>6.1} WPM  ×{:.2}",
+                run.at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                run.wpm,
+                run.accuracy * 100.0,
+                run.estimated_wpm,
+                run.factor
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Writes the summary of one ISO week, replacing an existing one.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `monday` - First day of the week
+///
+/// # Returns
+/// `Result<()>` - Success, or an error for data file or write problems
+fn write_week_summary(data_file: &Path, monday: chrono::NaiveDate) -> Result<()> {
+    let stats = KeyLogger::load_full_stats(data_file)?;
+    let path = summary::write_week(&stats, data_file, monday)?;
+    println!("📝 Summary of {} written to {}", report::week_label(monday), path.display());
+    Ok(())
+}
+
+/// Prints the totals of a calendar month next to the month before.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `month` - First day of the month
+///
+/// # Returns
+/// `Result<()>` - Success, or an error for a future month or data file problems
+fn print_month_report(data_file: &Path, month: chrono::NaiveDate) -> Result<()> {
+    let stats = KeyLogger::load_full_stats(data_file)?;
+    print!("{}", report::month_report(&stats, month, keylogger::today())?);
+    Ok(())
+}
+
+/// Prints what the recorded key distribution costs on each layout.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `typed_on` - Layout the keystrokes were typed on
+/// * `range` - Days to include
+///
+/// # Returns
+/// `Result<()>` - Success, or an error for anonymized data or data file problems
+fn print_layout_comparison(data_file: &Path, typed_on: Layout, range: DateRange) -> Result<()> {
+    let stats = load_scoped_stats(data_file, range)?;
+    if stats.anonymized {
+        bail!("the data file is anonymized and has no per-key counts to price");
+    }
+
+    let scores = layout::compare(&stats.key_counts, typed_on);
+    if scores.iter().all(|(_, score)| score.keystrokes == 0) {
+        bail!("no character keystrokes recorded yet");
+    }
+    println!("{}", report::layout_report(&scores, typed_on));
+
+    if !stats.bigram_counts.is_empty() {
+        let rates: Vec<_> = Layout::ALL
+            .iter()
+            .map(|&priced_on| (priced_on, layout::same_finger_bigrams(&stats.bigram_counts, typed_on, priced_on, 10)))
+            .collect();
+        println!();
+        println!("{}", report::same_finger_report(&rates, typed_on));
+    }
+    Ok(())
+}
+
+/// Prints the keys used unusually much or little against a reference corpus.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `corpus` - What to compare with
+/// * `range` - Days to include
+///
+/// # Returns
+/// `Result<()>` - Success, or an error for anonymized data, too little
+/// typing or a reference file that can't be read
+fn print_unusual_keys(data_file: &Path, corpus: &Corpus, range: DateRange) -> Result<()> {
+    let reference = corpus.load()?;
+    let stats = load_scoped_stats(data_file, range)?;
+    if stats.anonymized {
+        bail!("the data file is anonymized and has no per-key counts to compare");
+    }
+
+    let observed = Observed::collect(&stats.key_counts, &stats.char_counts, stats.case_sensitive);
+    let Some(ranked) = surprise::rank(&observed, &reference) else {
+        bail!("{} characters recorded, at least {} are needed to tell unusual keys", observed.total(), surprise::MIN_CHARS);
+    };
+    println!("{}", report::surprise_report(&ranked, 10));
+    Ok(())
+}
+
+/// Writes the sessions of the data file as sessions JSONL, see
+/// [`ctrlq::schema::write_sessions_jsonl`].
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `output` - File to write, None for standard output
+/// * `include_open` - Whether to add the session in progress of a running ctrlq
+/// * `range` - Days whose sessions to export
+///
+/// # Returns
+/// `Result<()>` - Success, or an error reading the data file or writing the output
+fn export_sessions(data_file: &Path, output: Option<&Path>, include_open: bool, range: DateRange) -> Result<()> {
+    let stats = load_scoped_stats(data_file, range)?;
+    // Without a running logger the saved session fields are left over from
+    // the last run, which ended its session on the way out
+    let open = (include_open && range.contains(keylogger::today()) && profile::is_locked(data_file))
+        .then(|| stats.open_session(chrono::Utc::now()))
+        .flatten();
+    if include_open && open.is_none() {
+        eprintln!("💡 No session in progress, exporting the finished ones");
+    }
+
+    let Some(path) = output else {
+        let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+        schema::write_sessions_jsonl(&mut out, &stats, open.as_ref())?;
+        return Ok(());
+    };
+    let file = std::fs::File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut out = std::io::BufWriter::new(file);
+    let written = schema::write_sessions_jsonl(&mut out, &stats, open.as_ref())
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    println!("✅ Exported {} session(s) to {}", written, path.display());
+    Ok(())
+}
+
+/// Merges statistics into the data file, or just describes the change.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+/// * `other` - Statistics to merge in
+/// * `dry_run` - Only print what would change
+/// * `pretty` - Whether to write indented JSON
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if a logger is running or the data file failed
+fn merge_into_data_file(data_file: &Path, other: &keylogger::KeyStats, dry_run: bool, pretty: bool) -> Result<()> {
+    // Its next save would write over the merged data
+    if !dry_run && profile::is_locked(data_file) {
+        bail!("{} is being logged by a running ctrlq instance; stop it before merging", data_file.display());
+    }
+
+    // Archived days must take part, or merged days would replace them
+    let mut stats = KeyLogger::load_full_stats(data_file)?;
+
+    if !stats.is_empty() && !other.is_empty() && stats.anonymized != other.anonymized {
+        let (anonymized, per_key) = if stats.anonymized {
+            (data_file.display().to_string(), "the incoming data".to_string())
+        } else {
+            ("the incoming data".to_string(), data_file.display().to_string())
+        };
+        bail!(
+            "{} was captured with --anonymize but {} has per-key counts; refusing to mix them",
+            anonymized,
+            per_key
+        );
+    }
+
+    println!(
+        "➕ {} keystrokes across {} key(s), {} day(s), {} session(s)",
+        numbers::format_count(other.total_keystrokes),
+        other.key_counts.len(),
+        other.daily_stats.len(),
+        other.typing_sessions.len()
+    );
+    for (key, count) in other.get_top_keys_by_class(10, None) {
+        let before = stats.key_counts.get(&key).copied().unwrap_or(0);
+        println!(
+            "   {:<12} {:>10} → {:>10}",
+            other.key_label(&key),
+            numbers::format_count(before),
+            numbers::format_count(before + count)
+        );
+    }
+
+    if dry_run {
+        println!("🔍 Dry run: {} was not changed", data_file.display());
+        return Ok(());
+    }
+
+    stats.merge(other);
+    KeyLogger::persist_stats(&mut stats, data_file, pretty)?;
+    println!("✅ Merged into {}", data_file.display());
+    Ok(())
+}
+
+/// Moves lock files and sockets of older versions from the data directory
+/// to the state directory, see [`paths::migrate_state`].
+fn migrate_state(data_dir: &Path) {
+    // Messages go to stderr so that `stats --json` output stays clean
+    match paths::migrate_state(data_dir, &paths::state_dir()) {
+        Ok(migrated) => {
+            for file in migrated {
+                match file.to {
+                    Some(to) => eprintln!("📦 Moved {} to {}", file.from.display(), to.display()),
+                    None => eprintln!("🧹 Removed stale {}", file.from.display()),
+                }
+            }
+        }
+        Err(e) => eprintln!("⚠️  Could not move lock files to the state directory: {:#}", e),
+    }
+}
+
+/// Downsamples the minute data of days older than `minute_days` to hourly
+/// counts and folds keys with unknown codes into `UNKNOWN`, in the data
+/// file and its archive.
+///
+/// # Arguments
+/// * `data_file` - The data file to compact
+/// * `minute_days` - Days that keep their minute data (0 = all)
+/// * `dry_run` - Whether to only report what would change
+/// * `pretty` - Whether to write indented JSON
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if a logger is running or writing failed
+fn compact_data_file(data_file: &Path, minute_days: u64, dry_run: bool, pretty: bool) -> Result<()> {
+    if profile::is_locked(data_file) {
+        bail!("{} is being logged by a running ctrlq instance; stop it before compacting", data_file.display());
+    }
+
+    let before = if minute_days == 0 {
+        None
+    } else {
+        let kept = i64::try_from(minute_days - 1).ok().and_then(chrono::TimeDelta::try_days);
+        let before = kept.and_then(|kept| keylogger::today().checked_sub_signed(kept));
+        Some(before.with_context(|| format!("minute_days = {} reaches back before any date ctrlq can store", minute_days))?)
+    };
+
+    let mut stats = KeyLogger::load_full_stats(data_file)?;
+    let downsampled = match before {
+        Some(before) => stats.downsample_minutes(before),
+        None => {
+            println!("💡 minute_days is 0: minute data is kept for every day");
+            0
+        }
+    };
+    let folded = stats.fold_junk_keys();
+    if downsampled == 0 && folded == 0 {
+        if let Some(before) = before {
+            println!("✅ No minute data before {} to compact", before);
+        }
+        println!("✅ No keys with unknown codes");
+        return Ok(());
+    }
+    if dry_run {
+        if let Some(before) = before.filter(|_| downsampled > 0) {
+            println!("🔍 Dry run: would reduce {} day(s) before {} to hourly counts", downsampled, before);
+        }
+        if folded > 0 {
+            println!("🔍 Dry run: would count {} key(s) with unknown codes as {}", folded, ctrlq::keymap::UNKNOWN_KEY);
+        }
+        return Ok(());
+    }
+
+    let size_before = stored_size(data_file);
+    KeyLogger::persist_stats(&mut stats, data_file, pretty)?;
+    let size_after = stored_size(data_file);
+    if let Some(before) = before.filter(|_| downsampled > 0) {
+        println!("🗜️  Reduced {} day(s) before {} to hourly counts", downsampled, before);
+    }
+    if folded > 0 {
+        println!("🧹 Counted {} key(s) with unknown codes as {}", folded, ctrlq::keymap::UNKNOWN_KEY);
+    }
+    println!(
+        "   {} KiB → {} KiB (data file and archive)",
+        size_before.div_ceil(1024),
+        size_after.div_ceil(1024)
+    );
+    Ok(())
+}
+
+/// Checks the data file and its archive for totals that don't add up,
+/// sessions that can't have happened and timestamps in the future, and
+/// with `repair` recomputes what can be recomputed. Exits with 1 while
+/// problems remain.
+///
+/// # Arguments
+/// * `data_file` - The data file to check
+/// * `repair` - Whether to fix the repairable problems
+/// * `pretty` - Whether to write indented JSON
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if a logger is running or reading or writing failed
+fn check_data_file(data_file: &Path, repair: bool, pretty: bool) -> Result<()> {
+    if repair && profile::is_locked(data_file) {
+        bail!("{} is being logged by a running ctrlq instance; stop it before repairing", data_file.display());
+    }
+
+    let mut stats = KeyLogger::load_full_stats(data_file)?;
+    println!(
+        "🔍 Checking {} ({} days, {} sessions)",
+        data_file.display(),
+        stats.daily_stats.len(),
+        stats.typing_sessions.len()
+    );
+    let issues = integrity::check(&stats, chrono::Utc::now());
+    if issues.is_empty() {
+        println!("✅ Everything adds up");
+        return Ok(());
+    }
+    for issue in &issues {
+        let icon = if issue.repairable { "🔧" } else { "⚠️ " };
+        println!("{} {}", icon, issue.description);
+    }
+    let repairable = issues.iter().filter(|issue| issue.repairable).count();
+
+    if !repair {
+        eprintln!("❌ Found {} problem(s)", issues.len());
+        if repairable > 0 {
+            eprintln!("💡 ctrlq fsck --repair fixes the {} marked 🔧", repairable);
+        }
+        process::exit(1);
+    }
+
+    if repairable > 0 {
+        let repaired = integrity::repair(&mut stats);
+        KeyLogger::persist_stats(&mut stats, data_file, pretty)?;
+        println!("✅ Repaired {} problem(s)", repaired);
+    }
+    let remaining = integrity::check(&stats, chrono::Utc::now()).len();
+    if remaining > 0 {
+        eprintln!("❌ {} problem(s) can't be repaired automatically", remaining);
+        eprintln!("💡 Check the sessions and days listed above by hand");
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Returns the size of a data file and its archive in bytes.
+fn stored_size(data_file: &Path) -> u64 {
+    let file = std::fs::metadata(data_file).map_or(0, |meta| meta.len());
+    let archived = std::fs::read_dir(archive::archive_dir(data_file))
+        .map(|entries| entries.flatten().filter_map(|entry| entry.metadata().ok()).map(|meta| meta.len()).sum())
+        .unwrap_or(0);
+    file + archived
+}
+
+/// Creates or restores a backup, see `ctrlq backup`.
+///
+/// # Arguments
+/// * `matches` - Matches of the `backup` subcommand
+///
+/// # Returns
+/// `Result<()>` - Success, or a backup or restore error
+fn manage_backups(matches: &clap::ArgMatches) -> Result<()> {
+    let locations = backup::Locations::current()?;
+    match matches.subcommand() {
+        Some(("create", sub)) => {
+            let output = match sub.get_one::<String>("output") {
+                Some(path) => PathBuf::from(path),
+                None => PathBuf::from(format!("ctrlq-backup-{}.tar.zst", chrono::Local::now().format("%Y%m%d-%H%M%S"))),
+            };
+            let manifest = backup::create(&locations, &output)?;
+            println!("💾 Backed up {} file(s) to {}", manifest.files.len(), output.display());
+            for entry in &manifest.files {
+                println!("  {:<48} {:>8} KiB", entry.path, entry.size.div_ceil(1024));
+            }
+        }
+        Some(("restore", sub)) => {
+            let file = Path::new(sub.get_one::<String>("file").expect("required"));
+            let restored = backup::restore(&locations, file, sub.get_flag("force"))?;
+            println!("✅ Checksums verified, restored {} file(s) from {}", restored.written.len(), file.display());
+            for path in &restored.written {
+                println!("  {}", path.display());
+            }
+            if restored.unchanged > 0 {
+                println!("  ({} file(s) were already up to date)", restored.unchanged);
+            }
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+    Ok(())
+}
+
+/// Lists, copies or deletes profiles.
+///
+/// # Arguments
+/// * `matches` - Matches of the `profile` subcommand
+///
+/// # Returns
+/// `Result<()>` - Success or profile error
+fn manage_profiles(matches: &clap::ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            let profiles = profile::list()?;
+            if profiles.is_empty() {
+                println!("📂 No profiles yet in {}", profile::data_dir()?.display());
+                return Ok(());
+            }
+            println!("📂 Profiles in {}", profile::data_dir()?.display());
+            for info in profiles {
+                println!(
+                    "  {:<16} {:>8} KiB{}",
+                    info.name,
+                    info.size.div_ceil(1024),
+                    if info.in_use { "  ⌨️  logging" } else { "" }
+                );
+            }
+        }
+        Some(("copy", sub)) => {
+            let from = sub.get_one::<String>("from").expect("required");
+            let to = sub.get_one::<String>("to").expect("required");
+            let path = profile::copy(from, to)?;
+            println!("✅ Copied profile '{}' to '{}' ({})", from, to, path.display());
+        }
+        Some(("delete", sub)) => {
+            let name = sub.get_one::<String>("name").expect("required");
+            let path = profile::delete(name)?;
+            println!("🗑️  Deleted profile '{}' ({})", name, path.display());
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_in_seconds_minutes_and_hours() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("25m"), Ok(Duration::from_secs(25 * 60)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(2 * 3600)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        for spec in ["0s", "5x", "10", "", "h"] {
+            assert!(parse_duration(spec).is_err(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn overflowing_durations_are_rejected() {
+        let max = u64::MAX;
+        assert!(parse_duration(&format!("{max}h")).unwrap_err().contains("too long"));
+        assert!(parse_duration(&format!("{max}s1s")).unwrap_err().contains("too long"));
+        assert!(parse_duration("99999999999999999999s").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! # Notifications Module
+//!
+//! Desktop notifications for milestones (daily goal, broken records), break
+//! reminders and errors headless users would otherwise never see, like
+//! repeated save failures. Only active when built with the `notifications` feature.
+//!
+//! ctrlq usually runs under sudo, where root has no desktop session. In that
+//! case notifications are delivered by running `notify-send` as the invoking
+//! user (`SUDO_USER`) against their session bus in `/run/user/<uid>/bus`.
+//! If that bus can't be found, notifications stay off and the logger reports
+//! why.
+//!
+//! `notify-send` is run as a separate process rather than talking D-Bus from
+//! within ctrlq: the logger thread runs as root, a session bus only reliably
+//! accepts connections from its own user, and a process can't switch to
+//! another user for one connection.
+
+use crate::numbers;
+use crate::privileges::sudo_user;
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Kinds of notifications, each with its own rate limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    /// The daily keystroke goal was reached
+    GoalReached,
+    /// A personal record was broken
+    RecordBroken,
+    /// Saving the data file failed several times in a row
+    SaveFailed,
+    /// Typing went on for long without a break
+    BreakDue,
+}
+
+impl NotificationKind {
+    /// Minimum time between two notifications of this kind.
+    fn min_interval(self) -> Duration {
+        match self {
+            NotificationKind::GoalReached => Duration::from_secs(60 * 60),
+            NotificationKind::RecordBroken => Duration::from_secs(5 * 60),
+            NotificationKind::SaveFailed => Duration::from_secs(10 * 60),
+            NotificationKind::BreakDue => Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+/// Sends rate-limited desktop notifications.
+pub struct Notifier {
+    /// Whether notifications are sent at all
+    enabled: bool,
+    /// When each kind of notification was last sent
+    last_sent: HashMap<NotificationKind, Instant>,
+}
+
+impl Notifier {
+    /// Creates a new notifier.
+    ///
+    /// Notifications are disabled when `enabled` is false or when the binary
+    /// was built without the `notifications` feature.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether the user wants notifications
+    ///
+    /// # Returns
+    /// `Result<Notifier>` - The notifier, or an error if no session bus can be found for the user
+    pub fn new(enabled: bool) -> Result<Self> {
+        let enabled = enabled && cfg!(feature = "notifications");
+        if enabled {
+            check_session_bus()?;
+        }
+        Ok(Self {
+            enabled,
+            last_sent: HashMap::new(),
+        })
+    }
+
+    /// Creates a notifier that never sends anything.
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// Announces that today's keystroke goal was reached.
+    pub fn goal_reached(&mut self, goal: u64) -> std::io::Result<()> {
+        self.send(
+            NotificationKind::GoalReached,
+            "🎯 Daily goal reached",
+            &format!("You typed {} keystrokes today.", numbers::format_count(goal)),
+            false,
+        )
+    }
+
+    /// Announces a broken personal record.
+    pub fn record_broken(&mut self, record: &str) -> std::io::Result<()> {
+        self.send(
+            NotificationKind::RecordBroken,
+            "🏆 New record",
+            &format!("New personal best: {}", record),
+            false,
+        )
+    }
+
+    /// Suggests a break after a long stretch of typing.
+    pub fn break_due(&mut self, active: Duration) -> std::io::Result<()> {
+        self.send(
+            NotificationKind::BreakDue,
+            "☕ Time for a break",
+            &format!("You have been typing for {} minutes. Step away for 5 minutes.", active.as_secs() / 60),
+            false,
+        )
+    }
+
+    /// Warns that the data file could not be saved repeatedly.
+    pub fn save_failed(&mut self, error: &str, attempts: u32) -> std::io::Result<()> {
+        self.send(
+            NotificationKind::SaveFailed,
+            "❌ CtrlQ can't save your data",
+            &format!("Saving failed {} times in a row: {}", attempts, error),
+            true,
+        )
+    }
+
+    /// Sends a notification unless notifications are off or one of the
+    /// same kind was sent recently. Rate-limited and disabled notifications
+    /// count as sent.
+    fn send(&mut self, kind: NotificationKind, summary: &str, body: &str, critical: bool) -> std::io::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if let Some(last) = self.last_sent.get(&kind)
+            && last.elapsed() < kind.min_interval()
+        {
+            return Ok(());
+        }
+        self.last_sent.insert(kind, Instant::now());
+
+        #[cfg(feature = "notifications")]
+        return deliver(summary, body, critical);
+        #[cfg(not(feature = "notifications"))]
+        {
+            let _ = (summary, body, critical);
+            Ok(())
+        }
+    }
+}
+
+/// Checks whether a session bus is reachable for the target user.
+fn check_session_bus() -> Result<()> {
+    match sudo_user() {
+        Some((user, uid)) => {
+            let bus = format!("/run/user/{}/bus", uid);
+            if !std::path::Path::new(&bus).exists() {
+                bail!("no session bus found for {} ({})", user, bus);
+            }
+        }
+        None => {
+            if std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_none() {
+                bail!("no session bus available");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Delivers a notification through `notify-send`, as the sudo user if needed.
+#[cfg(feature = "notifications")]
+fn deliver(summary: &str, body: &str, critical: bool) -> std::io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let urgency = if critical { "--urgency=critical" } else { "--urgency=normal" };
+
+    let mut command = match sudo_user() {
+        Some((user, uid)) => {
+            let mut command = Command::new("sudo");
+            command
+                .args(["-u", &user, "env"])
+                .arg(format!("DBUS_SESSION_BUS_ADDRESS=unix:path=/run/user/{}/bus", uid))
+                .arg("notify-send");
+            command
+        }
+        None => Command::new("notify-send"),
+    };
+
+    let mut child = command
+        .args(["--app-name=CtrlQ", urgency, summary, body])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Reap the child elsewhere: a hanging notification daemon must not stall logging
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
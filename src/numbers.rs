@@ -0,0 +1,153 @@
+//! # Numbers Module
+//!
+//! How counts are written for people: with a separator between groups of
+//! three digits (`3,482,119`), or compact where columns are narrow
+//! (`3.48M`). The UI, reports and summaries all go through here; exports
+//! and JSON keep the raw integers.
+//!
+//! The separator comes from `thousands_separator` in the config and is set
+//! once at startup with [`set_separator`]. Where it is `.` or a space, the
+//! decimal mark of compact counts is a comma: `3,48M`.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Widest count [`format_compact`] writes, e.g. `12.3k` or `9,999`.
+pub const COMPACT_WIDTH: usize = 5;
+
+/// Counts from which [`format_compact`] shortens them.
+const COMPACT_FROM: u64 = 10_000;
+
+/// Separators offered for `thousands_separator`.
+const SEPARATORS: [&str; 5] = [",", ".", " ", "'", ""];
+
+/// The separator in use as a `char`, 0 for none.
+static SEPARATOR: AtomicU32 = AtomicU32::new(',' as u32);
+
+/// Separator between groups of three digits, `thousands_separator` in the
+/// config: `","` (the default), `"."`, `" "`, `"'"` or `""` for none.
+///
+/// # Example
+/// ```
+/// use ctrlq::numbers::Separator;
+///
+/// assert_eq!(Separator::parse(".").unwrap().decimal_mark(), ',');
+/// assert_eq!(Separator::parse("'").unwrap().decimal_mark(), '.');
+/// assert!(Separator::parse("::").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Separator(Option<char>);
+
+impl Default for Separator {
+    fn default() -> Self {
+        Self(Some(','))
+    }
+}
+
+impl Separator {
+    /// Parses one of the offered separators.
+    pub fn parse(text: &str) -> Result<Self> {
+        if !SEPARATORS.contains(&text) {
+            let offered: Vec<String> = SEPARATORS.iter().map(|separator| format!("\"{}\"", separator)).collect();
+            bail!("unknown thousands_separator \"{}\", expected one of {}", text, offered.join(", "));
+        }
+        Ok(Self(text.chars().next()))
+    }
+
+    /// The decimal mark that goes with the separator.
+    pub fn decimal_mark(self) -> char {
+        match self.0 {
+            Some('.' | ' ') => ',',
+            _ => '.',
+        }
+    }
+}
+
+impl TryFrom<String> for Separator {
+    type Error = anyhow::Error;
+
+    fn try_from(text: String) -> Result<Self> {
+        Self::parse(&text)
+    }
+}
+
+impl From<Separator> for String {
+    fn from(separator: Separator) -> Self {
+        separator.0.map(String::from).unwrap_or_default()
+    }
+}
+
+/// Sets the separator of all counts written from now on.
+pub fn set_separator(separator: Separator) {
+    SEPARATOR.store(separator.0.map_or(0, u32::from), Ordering::Relaxed);
+}
+
+/// Returns the separator set with [`set_separator`].
+pub fn separator() -> Separator {
+    Separator(char::from_u32(SEPARATOR.load(Ordering::Relaxed)).filter(|&separator| separator != '\0'))
+}
+
+/// Writes a count in full, with the separator between groups of digits.
+///
+/// # Example
+/// ```
+/// use ctrlq::numbers::{format_count, set_separator, Separator};
+///
+/// assert_eq!(format_count(3_482_119), "3,482,119");
+/// assert_eq!(format_count(999), "999");
+/// set_separator(Separator::parse(".")?);
+/// assert_eq!(format_count(1_000), "1.000");
+/// set_separator(Separator::parse("")?);
+/// assert_eq!(format_count(1_000), "1000");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let Some(separator) = separator().0 else {
+        return digits;
+    };
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            out.push(separator);
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// Writes a count in at most [`COMPACT_WIDTH`] characters: in full below
+/// 10,000, else with three significant digits and `k`, `M`, `B` and so on.
+///
+/// # Example
+/// ```
+/// use ctrlq::numbers::{format_compact, set_separator, Separator};
+///
+/// assert_eq!(format_compact(9_999), "9,999");
+/// assert_eq!(format_compact(12_345), "12.3k");
+/// assert_eq!(format_compact(999_999), "1.00M");
+/// assert_eq!(format_compact(3_482_119), "3.48M");
+/// assert_eq!(format_compact(u64::MAX), "18.4E");
+/// set_separator(Separator::parse(" ")?);
+/// assert_eq!(format_compact(3_482_119), "3,48M");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn format_compact(count: u64) -> String {
+    if count < COMPACT_FROM {
+        return format_count(count);
+    }
+    const UNITS: [char; 6] = ['k', 'M', 'B', 'T', 'P', 'E'];
+    let mut value = count as f64;
+    for unit in UNITS {
+        value /= 1000.0;
+        // 999,999 would round to 1000k, which is 1.00M
+        if value < 999.5 || unit == 'E' {
+            let precision = if value < 9.995 { 2 } else if value < 99.95 { 1 } else { 0 };
+            let text = format!("{:.*}", precision, value);
+            return format!("{}{}", text.replace('.', &separator().decimal_mark().to_string()), unit);
+        }
+    }
+    unreachable!("the last unit always returns")
+}
@@ -0,0 +1,211 @@
+//! # Output Module
+//!
+//! One place for what the logger tells the user outside the terminal UI:
+//! the chosen keyboard, the data file, warnings and the notices of a
+//! headless run. Messages are written the way the rest of ctrlq writes
+//! them, an emoji followed by the text, and a [`Presenter`] decides how
+//! they come out:
+//!
+//! - `human` - as they are, information on stdout and warnings on stderr
+//! - `quiet` - warnings and errors only
+//! - `json` - one JSON object per line on stdout, without the emoji:
+//!
+//! ```text
+//! {"level":"info","event":"device","message":"Auto-detected keyboard: /dev/input/event3 (Keychron K2)"}
+//! ```
+//!
+//! Before the terminal UI starts, messages are held back instead of printed
+//! (the UI's alternate screen would swallow them) and shown as its first
+//! status bar message, see [`Presenter::defer`].
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+/// How a [`Presenter`] writes messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// Emoji lines for people
+    #[default]
+    Human,
+    /// Warnings and errors only
+    Quiet,
+    /// JSON lines for scripts
+    Json,
+}
+
+impl OutputMode {
+    /// Names accepted by [`OutputMode::parse`], for `--output-format`.
+    pub const NAMES: [&'static str; 3] = ["human", "quiet", "json"];
+
+    /// Parses an output mode name.
+    ///
+    /// # Arguments
+    /// * `name` - `human`, `quiet` or `json`
+    ///
+    /// # Returns
+    /// `Result<OutputMode>` - The mode, or an error for unknown names
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "human" => Ok(Self::Human),
+            "quiet" => Ok(Self::Quiet),
+            "json" => Ok(Self::Json),
+            other => bail!("unknown output mode '{}', expected one of {}", other, Self::NAMES.join(", ")),
+        }
+    }
+}
+
+/// How important a message is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    /// Progress and settings, left out by `quiet`
+    Info,
+    /// Something the user may want to fix; logging goes on
+    Warning,
+    /// Something that stops ctrlq
+    Error,
+}
+
+/// A message as written in `json` mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Message {
+    /// How important it is
+    pub level: Level,
+    /// What it is about, e.g. `device` or `data_file`, for scripts to match on
+    pub event: String,
+    /// The text without its leading emoji
+    pub message: String,
+}
+
+/// Writes the messages of the logger's startup and headless runs, see the
+/// module docs.
+///
+/// # Example
+/// ```
+/// use ctrlq::output::{Level, OutputMode, Presenter};
+///
+/// let json = Presenter::new(OutputMode::Json);
+/// assert_eq!(
+///     json.render("data_file", Level::Info, "📊 Data will be saved to: stats.json").as_deref(),
+///     Some(r#"{"level":"info","event":"data_file","message":"Data will be saved to: stats.json"}"#)
+/// );
+///
+/// // Before the terminal UI starts, everything waits for its status bar
+/// let mut ui = Presenter::new(OutputMode::Human);
+/// ui.defer();
+/// ui.info("device", "🎯 Auto-detected keyboard: /dev/input/event3");
+/// ui.warn("xkb", "⚠️  --xkb-layout needs a build with --features xkb");
+/// assert_eq!(
+///     ui.take_deferred().as_deref(),
+///     Some("🎯 Auto-detected keyboard: /dev/input/event3 · ⚠️  --xkb-layout needs a build with --features xkb")
+/// );
+///
+/// // Quiet leaves out everything but warnings and errors
+/// let mut quiet = Presenter::new(OutputMode::Quiet);
+/// quiet.defer();
+/// quiet.info("device", "🎯 Auto-detected keyboard: /dev/input/event3");
+/// assert_eq!(quiet.take_deferred(), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Presenter {
+    mode: OutputMode,
+    /// Messages held back for the terminal UI, see [`Presenter::defer`]
+    deferred: Option<Vec<String>>,
+    /// Level of the last message, which a hint belongs to
+    last_level: Option<Level>,
+}
+
+impl Presenter {
+    /// Creates a presenter that prints right away.
+    pub fn new(mode: OutputMode) -> Self {
+        Self { mode, deferred: None, last_level: None }
+    }
+
+    /// Returns how messages are written.
+    pub fn mode(&self) -> OutputMode {
+        self.mode
+    }
+
+    /// Holds information and warnings back until [`Presenter::take_deferred`]
+    /// instead of printing them, for when the terminal UI is about to start.
+    /// Errors are still printed, as they end ctrlq before the UI starts.
+    pub fn defer(&mut self) {
+        self.deferred.get_or_insert_with(Vec::new);
+    }
+
+    /// Returns the messages held back since [`Presenter::defer`] as one
+    /// status bar line, and prints right away again from now on.
+    ///
+    /// # Returns
+    /// `Option<String>` - The messages joined with ` · `, or None if there were none
+    pub fn take_deferred(&mut self) -> Option<String> {
+        let deferred = self.deferred.take()?;
+        (!deferred.is_empty()).then(|| deferred.join(" · "))
+    }
+
+    /// Reports progress or a setting, e.g. the keyboard chosen.
+    ///
+    /// # Arguments
+    /// * `event` - What it is about, see [`Message::event`]
+    /// * `text` - The line as printed for people, emoji first
+    pub fn info(&mut self, event: &str, text: impl AsRef<str>) {
+        self.emit(event, Level::Info, text.as_ref());
+    }
+
+    /// Reports something the user may want to fix while logging goes on.
+    pub fn warn(&mut self, event: &str, text: impl AsRef<str>) {
+        self.emit(event, Level::Warning, text.as_ref());
+    }
+
+    /// Reports what stops ctrlq, usually followed by a [`Presenter::hint`]
+    /// and `process::exit(1)`.
+    pub fn error(&mut self, event: &str, text: impl AsRef<str>) {
+        self.emit(event, Level::Error, text.as_ref());
+    }
+
+    /// Suggests what to do about the previous message (`💡 ...`); it is
+    /// written like that message, so quiet mode keeps the hints of errors.
+    pub fn hint(&mut self, text: impl AsRef<str>) {
+        let level = self.last_level.unwrap_or(Level::Info);
+        self.emit("hint", level, text.as_ref());
+        self.last_level = Some(level);
+    }
+
+    /// Renders a message the way the mode writes it.
+    ///
+    /// # Returns
+    /// `Option<String>` - The line to write, or None if the mode leaves it out
+    pub fn render(&self, event: &str, level: Level, text: &str) -> Option<String> {
+        match self.mode {
+            OutputMode::Quiet if level == Level::Info => None,
+            OutputMode::Human | OutputMode::Quiet => Some(text.to_string()),
+            OutputMode::Json => {
+                let message = Message { level, event: event.to_string(), message: strip_icon(text).to_string() };
+                serde_json::to_string(&message).ok()
+            }
+        }
+    }
+
+    fn emit(&mut self, event: &str, level: Level, text: &str) {
+        self.last_level = Some(level);
+        let Some(line) = self.render(event, level, text) else {
+            return;
+        };
+        if let Some(deferred) = &mut self.deferred
+            && level != Level::Error
+        {
+            // The status bar is a single line
+            deferred.push(text.trim().to_string());
+            return;
+        }
+        match (self.mode, level) {
+            (OutputMode::Json, _) | (_, Level::Info) => println!("{}", line),
+            _ => eprintln!("{}", line),
+        }
+    }
+}
+
+/// Removes the emoji and spacing a message starts with.
+fn strip_icon(text: &str) -> &str {
+    text.trim_start_matches(|c: char| !c.is_alphanumeric() && !c.is_ascii_punctuation())
+}
@@ -0,0 +1,336 @@
+//! # Paths Module
+//!
+//! Where ctrlq keeps its files, following the XDG base directories:
+//!
+//! | Kind   | Files | Default | Overrides |
+//! |--------|-------|---------|-----------|
+//! | config | `config.toml` | `~/.config/ctrlq` | `$CTRLQ_CONFIG_DIR`, `$XDG_CONFIG_HOME/ctrlq` |
+//! | data   | data files, archives, raw logs | `~/.local/share/ctrlq` | `$CTRLQ_DATA_DIR`, `data_dir` in the config, `$XDG_DATA_HOME/ctrlq` |
+//! | state  | profile locks, control sockets, status files, remembered keyboards | `~/.local/state/ctrlq` | `$CTRLQ_STATE_DIR`, `$XDG_STATE_HOME/ctrlq` |
+//!
+//! Overrides are listed by precedence. `XDG_*` variables only count when
+//! they hold an absolute path, as the specification asks.
+//!
+//! Older versions kept lock files and sockets next to the data files;
+//! [`migrate_state`] moves them to the state directory.
+//!
+//! Keyboards are looked for in `/dev/input`, or in `--input-dir` or
+//! `$CTRLQ_INPUT_DIR` in containers that mount the devices elsewhere, see
+//! [`input_dir`].
+//!
+//! Data files are replaced with [`write_atomic`], so a crash never leaves
+//! half of one behind.
+
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable that overrides the config directory.
+pub const CONFIG_DIR_VAR: &str = "CTRLQ_CONFIG_DIR";
+
+/// Environment variable that overrides the data directory.
+pub const DATA_DIR_VAR: &str = "CTRLQ_DATA_DIR";
+
+/// Environment variable that overrides the state directory.
+pub const STATE_DIR_VAR: &str = "CTRLQ_STATE_DIR";
+
+/// Environment variable that overrides the directory of the input devices.
+pub const INPUT_DIR_VAR: &str = "CTRLQ_INPUT_DIR";
+
+/// Where the kernel's input devices are.
+pub const DEFAULT_INPUT_DIR: &str = "/dev/input";
+
+/// Directory of the input devices given with `--input-dir`.
+static INPUT_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Extensions of the files kept in the state directory.
+const STATE_EXTENSIONS: [&str; 2] = ["lock", "sock"];
+
+/// The config, data and state directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dirs {
+    /// Directory of `config.toml`
+    pub config: PathBuf,
+    /// Default directory of the data files; `data_dir` in the config and
+    /// `$CTRLQ_DATA_DIR` take precedence, see [`crate::profile::data_dir`]
+    pub data: PathBuf,
+    /// Directory of lock files, control sockets and remembered keyboards
+    pub state: PathBuf,
+}
+
+impl Dirs {
+    /// Resolves the directories from the environment of this process.
+    pub fn from_env() -> Self {
+        Self::resolve(|name| std::env::var_os(name), dirs::home_dir())
+    }
+
+    /// Resolves the directories from the given variables and home directory.
+    ///
+    /// # Arguments
+    /// * `var` - Looks up an environment variable
+    /// * `home` - The home directory, if known
+    ///
+    /// # Returns
+    /// `Dirs` - The directories; without a home directory the XDG defaults
+    /// fall back to `./ctrlq`
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::paths::Dirs;
+    /// use std::collections::HashMap;
+    /// use std::path::PathBuf;
+    ///
+    /// let env = HashMap::from([
+    ///     ("XDG_CONFIG_HOME", "/cfg"),
+    ///     ("XDG_DATA_HOME", "relative/is/ignored"),
+    ///     ("XDG_STATE_HOME", "/state"),
+    ///     ("CTRLQ_STATE_DIR", "/run/user/1000/ctrlq"),
+    /// ]);
+    /// let dirs = Dirs::resolve(|name| env.get(name).map(Into::into), Some(PathBuf::from("/home/me")));
+    ///
+    /// assert_eq!(dirs.config, PathBuf::from("/cfg/ctrlq"));
+    /// assert_eq!(dirs.data, PathBuf::from("/home/me/.local/share/ctrlq"));
+    /// assert_eq!(dirs.state, PathBuf::from("/run/user/1000/ctrlq"));
+    ///
+    /// let bare = Dirs::resolve(|_| None, Some(PathBuf::from("/home/me")));
+    /// assert_eq!(bare.config, PathBuf::from("/home/me/.config/ctrlq"));
+    /// assert_eq!(bare.state, PathBuf::from("/home/me/.local/state/ctrlq"));
+    /// ```
+    pub fn resolve(var: impl Fn(&str) -> Option<OsString>, home: Option<PathBuf>) -> Self {
+        let dir = |own: &str, xdg: &str, default: &str| {
+            if let Some(dir) = var(own).filter(|dir| !dir.is_empty()) {
+                return PathBuf::from(dir);
+            }
+            let base = var(xdg)
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+                .or_else(|| home.as_ref().map(|home| home.join(default)))
+                .unwrap_or_else(|| PathBuf::from("."));
+            base.join("ctrlq")
+        };
+        Self {
+            config: dir(CONFIG_DIR_VAR, "XDG_CONFIG_HOME", ".config"),
+            data: dir(DATA_DIR_VAR, "XDG_DATA_HOME", ".local/share"),
+            state: dir(STATE_DIR_VAR, "XDG_STATE_HOME", ".local/state"),
+        }
+    }
+}
+
+/// Returns the config directory, see [`Dirs`].
+pub fn config_dir() -> PathBuf {
+    Dirs::from_env().config
+}
+
+/// Returns the data directory used when the config file doesn't set one.
+///
+/// # Returns
+/// `PathBuf` - `$CTRLQ_DATA_DIR`, or `~/.local/share/ctrlq` by default
+pub fn default_data_dir() -> PathBuf {
+    Dirs::from_env().data
+}
+
+/// Returns `$CTRLQ_DATA_DIR` if set; it wins over `data_dir` in the config.
+pub fn data_dir_override() -> Option<PathBuf> {
+    std::env::var_os(DATA_DIR_VAR).filter(|dir| !dir.is_empty()).map(PathBuf::from)
+}
+
+/// Returns the state directory, see [`Dirs`].
+pub fn state_dir() -> PathBuf {
+    Dirs::from_env().state
+}
+
+/// Sets the directory of the input devices for this process, for
+/// `--input-dir`. It wins over `$CTRLQ_INPUT_DIR`; only the first call counts.
+pub fn set_input_dir(dir: PathBuf) {
+    let _ = INPUT_DIR.set(dir);
+}
+
+/// Returns the directory to look for keyboards in.
+///
+/// # Returns
+/// `PathBuf` - The `--input-dir` set with [`set_input_dir`], else
+/// `$CTRLQ_INPUT_DIR`, else `/dev/input`
+pub fn input_dir() -> PathBuf {
+    match INPUT_DIR.get() {
+        Some(dir) => dir.clone(),
+        None => resolve_input_dir(|name| std::env::var_os(name)),
+    }
+}
+
+/// Resolves the directory of the input devices from the given variables.
+///
+/// # Example
+/// ```
+/// use ctrlq::paths::{resolve_input_dir, INPUT_DIR_VAR};
+/// use std::path::PathBuf;
+///
+/// assert_eq!(resolve_input_dir(|_| None), PathBuf::from("/dev/input"));
+/// assert_eq!(resolve_input_dir(|_| Some("".into())), PathBuf::from("/dev/input"));
+/// let container = |name: &str| (name == INPUT_DIR_VAR).then(|| "/host/dev/input".into());
+/// assert_eq!(resolve_input_dir(container), PathBuf::from("/host/dev/input"));
+/// ```
+pub fn resolve_input_dir(var: impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    var(INPUT_DIR_VAR)
+        .filter(|dir| !dir.is_empty())
+        .map_or_else(|| PathBuf::from(DEFAULT_INPUT_DIR), PathBuf::from)
+}
+
+/// Returns a state file belonging to a data file, e.g. its lock.
+///
+/// # Arguments
+/// * `state_dir` - The state directory
+/// * `data_file` - Path of the statistics data file
+/// * `extension` - Extension of the state file
+///
+/// # Returns
+/// `PathBuf` - e.g. `~/.local/state/ctrlq/keystroke_data.lock` for
+/// `~/.local/share/ctrlq/keystroke_data.json`
+pub fn state_file(state_dir: &Path, data_file: &Path, extension: &str) -> PathBuf {
+    let name = data_file.file_name().unwrap_or(data_file.as_os_str());
+    state_dir.join(name).with_extension(extension)
+}
+
+/// A state file moved by [`migrate_state`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migrated {
+    /// Where the file was
+    pub from: PathBuf,
+    /// Where it is now, or None if it was stale and removed instead
+    pub to: Option<PathBuf>,
+}
+
+/// Moves lock files and control sockets left next to the data files by
+/// older versions into the state directory.
+///
+/// Files still in use by a running instance of an older version stay
+/// where they are; they are picked up on a later run.
+///
+/// # Arguments
+/// * `data_dir` - The data directory to look in
+/// * `state_dir` - The state directory to move to
+///
+/// # Returns
+/// `Result<Vec<Migrated>>` - The files that were moved or removed
+///
+/// # Example
+/// ```
+/// use ctrlq::paths::{self, Migrated};
+///
+/// let root = std::env::temp_dir().join(format!("ctrlq-paths-doc-{}", std::process::id()));
+/// let (data, state) = (root.join("data"), root.join("state"));
+/// std::fs::create_dir_all(&data)?;
+/// std::fs::write(data.join("keystroke_data.json"), "{}")?;
+/// std::fs::write(data.join("keystroke_data.lock"), "")?;
+/// std::fs::write(data.join("keystroke_data.work.lock"), "")?;
+///
+/// let mut moved = paths::migrate_state(&data, &state)?;
+/// moved.sort_by(|a, b| a.from.cmp(&b.from));
+/// assert_eq!(moved[0], Migrated {
+///     from: data.join("keystroke_data.lock"),
+///     to: Some(state.join("keystroke_data.lock")),
+/// });
+/// assert!(state.join("keystroke_data.work.lock").exists());
+/// assert!(data.join("keystroke_data.json").exists());
+///
+/// // Nothing is left to move on the next run
+/// assert!(paths::migrate_state(&data, &state)?.is_empty());
+/// std::fs::remove_dir_all(&root)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn migrate_state(data_dir: &Path, state_dir: &Path) -> Result<Vec<Migrated>> {
+    let mut migrated = Vec::new();
+    if data_dir == state_dir || !data_dir.is_dir() {
+        return Ok(migrated);
+    }
+
+    for entry in std::fs::read_dir(data_dir).with_context(|| format!("failed to read {}", data_dir.display()))? {
+        let from = entry?.path();
+        let is_state = from
+            .extension()
+            .is_some_and(|ext| STATE_EXTENSIONS.iter().any(|state| ext == *state));
+        let stem = from.file_stem().unwrap_or_default().to_string_lossy();
+        if !is_state || crate::profile::profile_name(&format!("{}.json", stem)).is_none() || in_use(&from) {
+            continue;
+        }
+
+        std::fs::create_dir_all(state_dir).with_context(|| format!("failed to create {}", state_dir.display()))?;
+        let to = state_dir.join(from.file_name().unwrap_or_default());
+        // Neither a stale lock nor a stale socket holds anything worth
+        // copying when the state directory is on another file system
+        let to = match std::fs::rename(&from, &to) {
+            Ok(()) => Some(to),
+            Err(_) => {
+                std::fs::remove_file(&from).with_context(|| format!("failed to remove {}", from.display()))?;
+                None
+            }
+        };
+        migrated.push(Migrated { from, to });
+    }
+    Ok(migrated)
+}
+
+/// Replaces a file in one step. The contents are written to `<path>.tmp`,
+/// synced to disk and renamed over `path`, and then the directory is synced
+/// so that the rename itself survives a crash. Whatever happens, `path`
+/// holds either the old contents or all of the new ones.
+///
+/// # Arguments
+/// * `path` - The file to replace
+/// * `write` - Writes the new contents
+///
+/// # Returns
+/// `Result<()>` - Success, or an error naming the file that failed
+///
+/// # Example
+/// ```
+/// use ctrlq::paths::write_atomic;
+/// use std::io::Write;
+///
+/// let dir = std::env::temp_dir().join(format!("ctrlq-atomic-doc-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// let file = dir.join("data.json");
+/// std::fs::write(&file, "old")?;
+///
+/// // A failed write leaves the old contents
+/// assert!(write_atomic(&file, |w| { w.write_all(b"half")?; anyhow::bail!("disk full") }).is_err());
+/// assert_eq!(std::fs::read_to_string(&file)?, "old");
+///
+/// write_atomic(&file, |w| Ok(w.write_all(b"new")?))?;
+/// assert_eq!(std::fs::read_to_string(&file)?, "new");
+/// std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn write_atomic(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> Result<()>) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let file = File::create(&temp).with_context(|| format!("failed to write {}", temp.display()))?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer).with_context(|| format!("failed to write {}", temp.display()))?;
+    writer.flush().with_context(|| format!("failed to write {}", temp.display()))?;
+    let file = writer.into_inner().map_err(|e| e.into_error()).with_context(|| format!("failed to write {}", temp.display()))?;
+    file.sync_all().with_context(|| format!("failed to write {}", temp.display()))?;
+    drop(file);
+    std::fs::rename(&temp, path).with_context(|| format!("failed to replace {}", path.display()))?;
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| format!("failed to sync {}", dir.display()))
+}
+
+/// Whether an older instance still holds a lock file or answers on a socket.
+fn in_use(path: &Path) -> bool {
+    if path.extension().is_some_and(|ext| ext == "sock") {
+        return UnixStream::connect(path).is_ok();
+    }
+    std::fs::File::open(path)
+        .is_ok_and(|file| matches!(file.try_lock_shared(), Err(std::fs::TryLockError::WouldBlock)))
+}
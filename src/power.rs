@@ -0,0 +1,124 @@
+//! # Power Module
+//!
+//! Whether the machine runs on battery, so a logger that runs all day can
+//! leave the disk alone: on battery the data file is saved every
+//! `battery_save_interval` seconds instead of every `save_interval`, the raw
+//! log collects `battery_raw_log_kb` of records before it writes them, and
+//! headless mode stops rewriting `--summary-file`. Everything returns to
+//! normal once mains power is back.
+//!
+//! The power state comes from `/sys/class/power_supply` (feature `battery`).
+//! A build without the feature, a machine without batteries or a sysfs that
+//! can't be read all count as mains power, so detection never changes
+//! anything unless it works.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Time between two reads of the power state.
+pub const POWER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where the kernel lists power supplies.
+pub const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// One power supply as described by its sysfs directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PowerSupply {
+    /// Contents of `type`: `Mains`, `USB`, `Battery`, ...
+    pub kind: String,
+    /// Contents of `online`, for chargers
+    pub online: Option<bool>,
+    /// Contents of `status`, for batteries: `Charging`, `Discharging`, ...
+    pub status: Option<String>,
+    /// Contents of `scope`; `Device` marks the battery of a mouse or headset
+    pub scope: Option<String>,
+}
+
+/// Decides from the power supplies whether the machine runs on battery.
+///
+/// That is the case when a system battery is discharging and no charger is
+/// online. Batteries of wireless devices don't count.
+///
+/// # Example
+/// ```
+/// use ctrlq::power::{on_battery, PowerSupply};
+///
+/// let charger = |online| PowerSupply { kind: "Mains".into(), online: Some(online), ..Default::default() };
+/// let battery = |status: &str| PowerSupply { kind: "Battery".into(), status: Some(status.into()), ..Default::default() };
+///
+/// assert!(on_battery(&[charger(false), battery("Discharging")]));
+/// assert!(!on_battery(&[charger(true), battery("Charging")]));
+/// // A desktop, or a wireless mouse running down next to it
+/// assert!(!on_battery(&[]));
+/// let mouse = PowerSupply { scope: Some("Device".into()), ..battery("Discharging") };
+/// assert!(!on_battery(&[charger(true), mouse]));
+/// ```
+pub fn on_battery(supplies: &[PowerSupply]) -> bool {
+    let charging = supplies.iter().any(|supply| supply.kind != "Battery" && supply.online == Some(true));
+    let discharging = supplies.iter().any(|supply| {
+        supply.kind == "Battery"
+            && supply.scope.as_deref() != Some("Device")
+            && supply.status.as_deref() == Some("Discharging")
+    });
+    discharging && !charging
+}
+
+/// Reads the power supplies listed in a sysfs directory.
+///
+/// # Arguments
+/// * `dir` - Usually [`POWER_SUPPLY_DIR`]
+///
+/// # Returns
+/// `Vec<PowerSupply>` - The supplies, empty if the directory can't be read
+pub fn read_supplies(dir: &Path) -> Vec<PowerSupply> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let read = |path: &Path, name: &str| std::fs::read_to_string(path.join(name)).ok().map(|text| text.trim().to_string());
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            Some(PowerSupply {
+                kind: read(&path, "type")?,
+                online: read(&path, "online").map(|online| online == "1"),
+                status: read(&path, "status"),
+                scope: read(&path, "scope"),
+            })
+        })
+        .collect()
+}
+
+/// Whether the machine runs on battery right now, false without the
+/// `battery` feature or if it can't be told.
+pub fn detect() -> bool {
+    #[cfg(feature = "battery")]
+    return on_battery(&read_supplies(Path::new(POWER_SUPPLY_DIR)));
+    #[cfg(not(feature = "battery"))]
+    false
+}
+
+/// Tracks the power state for the logging loop, reading it at most every
+/// [`POWER_POLL_INTERVAL`].
+#[derive(Debug, Default)]
+pub struct PowerCheck {
+    /// When the state was last read
+    last_read: Option<Instant>,
+    /// Whether the machine was on battery at the last read
+    on_battery: bool,
+}
+
+impl PowerCheck {
+    /// Returns whether the machine runs on battery, reading the state
+    /// again if it is due.
+    ///
+    /// # Arguments
+    /// * `now` - The current time
+    pub fn on_battery(&mut self, now: Instant) -> bool {
+        if self.last_read.is_none_or(|last| now.saturating_duration_since(last) >= POWER_POLL_INTERVAL) {
+            self.on_battery = detect();
+            self.last_read = Some(now);
+        }
+        self.on_battery
+    }
+}
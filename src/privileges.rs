@@ -0,0 +1,290 @@
+//! # Privileges Module
+//!
+//! Reading `/dev/input/event*` doesn't need root. Besides running under
+//! sudo, ctrlq can read the devices as a member of the `input` group, which
+//! owns them on most distributions, or with the `cap_dac_read_search` file
+//! capability on the binary, which lets it read any file without being
+//! root:
+//!
+//! ```text
+//! sudo setcap cap_dac_read_search+ep /usr/local/bin/ctrlq
+//! ```
+//!
+//! [`Privileges::detect`] reads how this process got its access from
+//! `/proc/self/status`. Only a process that really is root counts as running
+//! under sudo (see [`sudo_user`]): a capable or grouped process keeps the
+//! user's own environment, even if `SUDO_USER` was inherited from a shell
+//! started with sudo. [`guidance`] lists what would give access when the
+//! devices can't be read.
+
+use std::path::{Path, PathBuf};
+
+/// Group that owns `/dev/input/event*` on most distributions.
+pub const INPUT_GROUP: &str = "input";
+
+/// Capability to bypass all file permission checks.
+pub const CAP_DAC_OVERRIDE: u32 = 1;
+
+/// Capability to bypass read permission checks, enough for input devices.
+pub const CAP_DAC_READ_SEARCH: u32 = 2;
+
+/// How this process is allowed to read input devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessMode {
+    /// Effective user is root, e.g. under sudo
+    Root,
+    /// Not root, but with `cap_dac_read_search` or `cap_dac_override`
+    Capabilities,
+    /// Not root, but a member of the `input` group
+    InputGroup,
+    /// None of the above; devices may still be readable through ACLs
+    Unprivileged,
+}
+
+impl AccessMode {
+    /// Describes the mode for `ctrlq doctor` and `--verbose`.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Root => "root",
+            Self::Capabilities => "file capabilities (cap_dac_read_search)",
+            Self::InputGroup => "member of the 'input' group",
+            Self::Unprivileged => "unprivileged user",
+        }
+    }
+}
+
+/// Users, groups and capabilities of a process, see the module docs.
+///
+/// # Example
+/// ```
+/// use ctrlq::privileges::{AccessMode, Privileges};
+///
+/// let capable = "Uid:\t1000\t1000\t1000\t1000\nGroups:\t27 1000 \nCapEff:\t0000000000000004\n";
+/// let privileges = Privileges::parse(capable).unwrap();
+/// assert!(!privileges.is_root());
+/// assert!(privileges.can_read_devices());
+/// assert_eq!(privileges.mode(Some(104)), AccessMode::Capabilities);
+///
+/// let grouped = "Uid:\t1000\t1000\t1000\t1000\nGroups:\t27 104 1000\nCapEff:\t0000000000000000\n";
+/// assert_eq!(Privileges::parse(grouped).unwrap().mode(Some(104)), AccessMode::InputGroup);
+/// assert_eq!(Privileges::parse(grouped).unwrap().mode(None), AccessMode::Unprivileged);
+///
+/// let sudo = "Uid:\t0\t0\t0\t0\nGroups:\t0\nCapEff:\t000001ffffffffff\n";
+/// assert_eq!(Privileges::parse(sudo).unwrap().mode(Some(104)), AccessMode::Root);
+/// assert!(Privileges::parse("Name:\tctrlq\n").is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Privileges {
+    /// Real user id
+    pub uid: u32,
+    /// Effective user id, 0 under sudo
+    pub euid: u32,
+    /// Supplementary group ids
+    pub groups: Vec<u32>,
+    /// Effective capability set, one bit per capability
+    pub cap_eff: u64,
+}
+
+impl Privileges {
+    /// Reads the privileges of this process from `/proc/self/status`.
+    ///
+    /// # Returns
+    /// `Privileges` - What the process has, or an unprivileged user if the
+    /// status can't be read
+    pub fn detect() -> Self {
+        std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| Self::parse(&status))
+            .unwrap_or(Self { uid: u32::MAX, euid: u32::MAX, groups: Vec::new(), cap_eff: 0 })
+    }
+
+    /// Parses the `Uid`, `Groups` and `CapEff` lines of a `/proc/<pid>/status`.
+    ///
+    /// # Arguments
+    /// * `status` - Contents of the status file
+    ///
+    /// # Returns
+    /// `Option<Privileges>` - The privileges, or None without a `Uid` line
+    pub fn parse(status: &str) -> Option<Self> {
+        let mut ids = None;
+        let mut groups = Vec::new();
+        let mut cap_eff = 0;
+        for line in status.lines() {
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            match field {
+                "Uid" => {
+                    let mut values = value.split_whitespace().map(str::parse::<u32>);
+                    if let (Some(Ok(uid)), Some(Ok(euid))) = (values.next(), values.next()) {
+                        ids = Some((uid, euid));
+                    }
+                }
+                "Groups" => groups = value.split_whitespace().filter_map(|gid| gid.parse().ok()).collect(),
+                "CapEff" => cap_eff = u64::from_str_radix(value.trim(), 16).unwrap_or(0),
+                _ => {}
+            }
+        }
+        let (uid, euid) = ids?;
+        Some(Self { uid, euid, groups, cap_eff })
+    }
+
+    /// Whether the effective user is root.
+    pub fn is_root(&self) -> bool {
+        self.euid == 0
+    }
+
+    /// Whether a capability is in the effective set.
+    pub fn has_capability(&self, capability: u32) -> bool {
+        self.cap_eff & (1 << capability) != 0
+    }
+
+    /// Whether input devices can be read regardless of their permissions.
+    pub fn can_read_devices(&self) -> bool {
+        self.is_root() || self.has_capability(CAP_DAC_READ_SEARCH) || self.has_capability(CAP_DAC_OVERRIDE)
+    }
+
+    /// Returns how the process may read input devices.
+    ///
+    /// # Arguments
+    /// * `input_gid` - Id of the `input` group, see [`input_gid`]
+    pub fn mode(&self, input_gid: Option<u32>) -> AccessMode {
+        if self.is_root() {
+            AccessMode::Root
+        } else if self.can_read_devices() {
+            AccessMode::Capabilities
+        } else if input_gid.is_some_and(|gid| self.groups.contains(&gid)) {
+            AccessMode::InputGroup
+        } else {
+            AccessMode::Unprivileged
+        }
+    }
+}
+
+/// Returns how this process may read input devices.
+pub fn access_mode() -> AccessMode {
+    Privileges::detect().mode(input_gid())
+}
+
+/// Looks up the id of the `input` group in `/etc/group`.
+pub fn input_gid() -> Option<u32> {
+    group_id(&std::fs::read_to_string("/etc/group").ok()?, INPUT_GROUP)
+}
+
+/// Finds a group's id in the contents of `/etc/group`.
+///
+/// # Example
+/// ```
+/// use ctrlq::privileges::group_id;
+///
+/// let groups = "root:x:0:\ninput:x:104:alice\nvideo:x:44:\n";
+/// assert_eq!(group_id(groups, "input"), Some(104));
+/// assert_eq!(group_id(groups, "plugdev"), None);
+/// ```
+pub fn group_id(group_file: &str, name: &str) -> Option<u32> {
+    group_file.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        (fields.next()? == name).then_some(())?;
+        fields.nth(1)?.parse().ok()
+    })
+}
+
+/// Looks up a user's primary group and home directory in `/etc/passwd`.
+pub fn user_entry(uid: u32) -> Option<(u32, PathBuf)> {
+    passwd_entry(&std::fs::read_to_string("/etc/passwd").ok()?, uid)
+}
+
+/// Finds a user's primary group and home directory in the contents of
+/// `/etc/passwd`.
+///
+/// # Example
+/// ```
+/// use ctrlq::privileges::passwd_entry;
+/// use std::path::PathBuf;
+///
+/// let users = "root:x:0:0:root:/root:/bin/bash\nalice:x:1000:100:Alice:/home/alice:/bin/zsh\n";
+/// assert_eq!(passwd_entry(users, 1000), Some((100, PathBuf::from("/home/alice"))));
+/// assert_eq!(passwd_entry(users, 1001), None);
+/// ```
+pub fn passwd_entry(passwd_file: &str, uid: u32) -> Option<(u32, PathBuf)> {
+    passwd_file.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        (fields.len() >= 6 && fields[2].parse() == Ok(uid)).then_some(())?;
+        let gid = fields[3].parse().ok()?;
+        Some((gid, PathBuf::from(fields[5])))
+    })
+}
+
+/// Returns the invoking user's name and uid when running under sudo.
+///
+/// `SUDO_USER` is only trusted while the effective user is root; a process
+/// that reads the devices through capabilities or the `input` group already
+/// runs as that user.
+pub fn sudo_user() -> Option<(String, u32)> {
+    if !Privileges::detect().is_root() {
+        return None;
+    }
+    let user = std::env::var("SUDO_USER").ok()?;
+    let uid = std::env::var("SUDO_UID").ok()?.parse().ok()?;
+    Some((user, uid))
+}
+
+/// Returns the command that grants the binary `cap_dac_read_search`.
+pub fn setcap_command(binary: &Path) -> String {
+    format!("sudo setcap cap_dac_read_search+ep {}", binary.display())
+}
+
+/// Returns the command that installs a udev rule giving the `input` group
+/// read access to event devices.
+pub fn udev_rule_command() -> String {
+    format!(
+        "echo 'KERNEL==\"event*\", SUBSYSTEM==\"input\", GROUP=\"{}\", MODE=\"0640\"' | sudo tee /etc/udev/rules.d/70-ctrlq.rules && sudo udevadm trigger",
+        INPUT_GROUP
+    )
+}
+
+/// Lists ways to read input devices without sudo, as `💡` lines.
+///
+/// # Arguments
+/// * `mode` - How the process reads devices now, see [`access_mode`]
+/// * `binary` - Path of the ctrlq binary, for `setcap`
+///
+/// # Returns
+/// `Vec<String>` - The suggestions; the group is left out for members, and
+/// everything for processes that can read any device already
+///
+/// # Example
+/// ```
+/// use ctrlq::privileges::{guidance, AccessMode};
+/// use std::path::Path;
+///
+/// let lines = guidance(AccessMode::Unprivileged, Path::new("/usr/local/bin/ctrlq"));
+/// assert_eq!(lines[0], "💡 Grant read access to the binary: sudo setcap cap_dac_read_search+ep /usr/local/bin/ctrlq");
+/// assert!(lines.iter().any(|line| line.contains("usermod -aG input")));
+///
+/// // Members of the group whose devices still can't be read need the udev rule
+/// let lines = guidance(AccessMode::InputGroup, Path::new("/usr/local/bin/ctrlq"));
+/// assert!(!lines.iter().any(|line| line.contains("usermod")));
+/// assert!(lines.iter().any(|line| line.contains("udev")));
+/// assert!(guidance(AccessMode::Root, Path::new("ctrlq")).is_empty());
+/// assert!(guidance(AccessMode::Capabilities, Path::new("ctrlq")).is_empty());
+/// ```
+pub fn guidance(mode: AccessMode, binary: &Path) -> Vec<String> {
+    if matches!(mode, AccessMode::Root | AccessMode::Capabilities) {
+        return Vec::new();
+    }
+    let mut lines = vec![format!("💡 Grant read access to the binary: {}", setcap_command(binary))];
+    if mode != AccessMode::InputGroup {
+        lines.push(format!(
+            "💡 Or join the '{}' group: sudo usermod -aG {} $USER, then log out and back in",
+            INPUT_GROUP, INPUT_GROUP
+        ));
+    }
+    lines.push(format!("💡 If the devices aren't owned by '{}', add a udev rule: {}", INPUT_GROUP, udev_rule_command()));
+    lines
+}
+
+/// Returns the path of the running binary, for [`guidance`].
+pub fn current_binary() -> PathBuf {
+    std::env::current_exe().unwrap_or_else(|_| "ctrlq".into())
+}
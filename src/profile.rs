@@ -0,0 +1,247 @@
+//! # Profile Module
+//!
+//! Named profiles keep separate statistics, e.g. for a work keyboard, a
+//! home keyboard and an experiment with a new layout. Each profile is its
+//! own data file in the data directory:
+//!
+//! - `default` - `keystroke_data.json` (the file used before profiles existed)
+//! - `<name>` - `keystroke_data.<name>.json`
+//!
+//! A running logger holds a lock on `<profile file>.lock` in the state
+//! directory (see [`crate::paths`]), so two instances can't write the same
+//! profile while different profiles log side by side.
+
+use crate::archive;
+use crate::config::Config;
+use crate::paths;
+use anyhow::{Context, Result, bail};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// Name of the profile stored in `keystroke_data.json`.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// File name stem shared by all profile data files.
+const DATA_FILE_STEM: &str = "keystroke_data";
+
+/// A profile found in the data directory.
+#[derive(Debug, Clone)]
+pub struct ProfileInfo {
+    /// Profile name
+    pub name: String,
+    /// Path of the data file
+    pub path: PathBuf,
+    /// Size of the data file in bytes
+    pub size: u64,
+    /// Whether a running logger holds the profile's lock
+    pub in_use: bool,
+}
+
+/// A held profile lock, released when dropped.
+#[derive(Debug)]
+pub struct ProfileLock {
+    _file: File,
+}
+
+/// Returns the data directory, creating it if needed.
+///
+/// # Returns
+/// `Result<PathBuf>` - `$CTRLQ_DATA_DIR` or `data_dir` from the config
+/// file if set, otherwise `~/.local/share/ctrlq`, see [`paths`]
+pub fn data_dir() -> Result<PathBuf> {
+    let data_dir = paths::data_dir_override()
+        .or_else(|| Config::load().ok().and_then(|config| config.data_dir))
+        .unwrap_or_else(paths::default_data_dir);
+
+    std::fs::create_dir_all(&data_dir)?;
+    Ok(data_dir)
+}
+
+/// Checks that a profile name is usable in a file name.
+///
+/// # Arguments
+/// * `name` - Profile name; letters, digits, `-` and `_`
+///
+/// # Returns
+/// `Result<()>` - Ok, or an error describing the problem
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!("profile name can't be empty");
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        bail!("invalid profile name '{}': use letters, digits, '-' and '_'", name);
+    }
+    Ok(())
+}
+
+/// Returns the data file of a profile, creating the data directory if needed.
+///
+/// # Arguments
+/// * `name` - Profile name, or [`DEFAULT_PROFILE`]
+///
+/// # Returns
+/// `Result<PathBuf>` - Path of the profile's data file (which may not exist yet)
+pub fn data_file(name: &str) -> Result<PathBuf> {
+    validate_name(name)?;
+    let file_name = if name == DEFAULT_PROFILE {
+        format!("{}.json", DATA_FILE_STEM)
+    } else {
+        format!("{}.{}.json", DATA_FILE_STEM, name)
+    };
+    Ok(data_dir()?.join(file_name))
+}
+
+/// Returns the profile name of a data file name, if it is one.
+pub fn profile_name(file_name: &str) -> Option<String> {
+    let rest = file_name.strip_prefix(DATA_FILE_STEM)?.strip_suffix(".json")?;
+    if rest.is_empty() {
+        return Some(DEFAULT_PROFILE.to_string());
+    }
+    let name = rest.strip_prefix('.')?;
+    validate_name(name).ok()?;
+    Some(name.to_string())
+}
+
+/// Lists the profiles in the data directory, sorted by name.
+///
+/// # Returns
+/// `Result<Vec<ProfileInfo>>` - Profiles with an existing data file
+pub fn list() -> Result<Vec<ProfileInfo>> {
+    let mut profiles = Vec::new();
+    for entry in std::fs::read_dir(data_dir()?)? {
+        let entry = entry?;
+        let Some(name) = profile_name(&entry.file_name().to_string_lossy()) else {
+            continue;
+        };
+        let path = entry.path();
+        profiles.push(ProfileInfo {
+            name,
+            size: entry.metadata()?.len(),
+            in_use: is_locked(&path),
+            path,
+        });
+    }
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(profiles)
+}
+
+/// Copies a profile's data file to a new profile.
+///
+/// # Arguments
+/// * `from` - Existing profile
+/// * `to` - New profile; must not exist yet
+///
+/// # Returns
+/// `Result<PathBuf>` - Path of the new data file
+pub fn copy(from: &str, to: &str) -> Result<PathBuf> {
+    let source = data_file(from)?;
+    let target = data_file(to)?;
+    if !source.exists() {
+        bail!("profile '{}' does not exist", from);
+    }
+    if target.exists() {
+        bail!("profile '{}' already exists", to);
+    }
+    std::fs::copy(&source, &target)
+        .with_context(|| format!("failed to copy {} to {}", source.display(), target.display()))?;
+    archive::copy(&source, &target)?;
+    Ok(target)
+}
+
+/// Deletes a profile's data file. Profiles in use by a logger are refused.
+///
+/// # Arguments
+/// * `name` - Profile to delete
+///
+/// # Returns
+/// `Result<PathBuf>` - Path of the deleted data file
+pub fn delete(name: &str) -> Result<PathBuf> {
+    let path = data_file(name)?;
+    if !path.exists() {
+        bail!("profile '{}' does not exist", name);
+    }
+    if is_locked(&path) {
+        bail!("profile '{}' is in use by a running ctrlq instance", name);
+    }
+    std::fs::remove_file(&path).with_context(|| format!("failed to delete {}", path.display()))?;
+    archive::clear(&path)?;
+    let _ = std::fs::remove_file(lock_path(&path));
+    let _ = std::fs::remove_file(devices_path(&path));
+    Ok(path)
+}
+
+/// Returns the file remembering the keyboards auto-detected for a data
+/// file, in the state directory.
+pub fn devices_path(data_file: &Path) -> PathBuf {
+    paths::state_file(&paths::state_dir(), data_file, "devices")
+}
+
+/// Remembers the keyboards picked for a profile, so the next start without
+/// `-d` finds them again even if their event numbers changed.
+///
+/// # Arguments
+/// * `data_file` - Path of the profile's data file
+/// * `devices` - Stable links to the devices, see [`crate::keylogger::stable_device_path`]
+///
+/// # Returns
+/// `Result<PathBuf>` - The file the devices were written to
+pub fn remember_devices(data_file: &Path, devices: &[String]) -> Result<PathBuf> {
+    let path = devices_path(data_file);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let mut contents = devices.join("\n");
+    contents.push('\n');
+    std::fs::write(&path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Returns the keyboards remembered by [`remember_devices`], if any.
+pub fn remembered_devices(data_file: &Path) -> Vec<String> {
+    std::fs::read_to_string(devices_path(data_file))
+        .map(|contents| contents.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the lock file belonging to a data file, in the state directory.
+fn lock_path(data_file: &Path) -> PathBuf {
+    paths::state_file(&paths::state_dir(), data_file, "lock")
+}
+
+/// Takes the lock of a data file for the lifetime of the returned guard.
+///
+/// # Arguments
+/// * `data_file` - The profile's data file
+///
+/// # Returns
+/// `Result<ProfileLock>` - The held lock, or an error if another instance holds it
+pub fn lock(data_file: &Path) -> Result<ProfileLock> {
+    let path = lock_path(data_file);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("failed to open lock file {}", path.display()))?;
+    match file.try_lock() {
+        Ok(()) => Ok(ProfileLock { _file: file }),
+        Err(TryLockError::WouldBlock) => bail!(
+            "{} is already being logged by another ctrlq instance; use --profile to log separately",
+            data_file.display()
+        ),
+        Err(TryLockError::Error(e)) => {
+            Err(e).with_context(|| format!("failed to lock {}", path.display()))
+        }
+    }
+}
+
+/// Checks whether a running logger holds the lock of a data file.
+pub fn is_locked(data_file: &Path) -> bool {
+    let Ok(file) = File::open(lock_path(data_file)) else {
+        return false;
+    };
+    matches!(file.try_lock_shared(), Err(TryLockError::WouldBlock))
+}
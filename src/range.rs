@@ -0,0 +1,200 @@
+//! # Range Module
+//!
+//! Date ranges for the read-only commands (`stats`, `report`): `--since`,
+//! `--until` and the shorthands `--today`, `--this-week` and `--this-month`.
+//! A range is applied once, before the command runs, by [`DateRange::filter`],
+//! so every command sees the same scoped statistics.
+
+use crate::keylogger::{DayStats, KeyStats, Records};
+use crate::report;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use std::collections::HashMap;
+
+/// An inclusive range of local dates, open at either end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateRange {
+    /// First day included (None = from the beginning)
+    pub since: Option<NaiveDate>,
+    /// Last day included (None = up to today)
+    pub until: Option<NaiveDate>,
+}
+
+impl DateRange {
+    /// Builds a range from `--since` and `--until` values.
+    ///
+    /// Both accept a date (`2024-05-01`) or any period keyword of
+    /// [`report::parse_period`]; `--since` uses the first day of a period and
+    /// `--until` the last.
+    ///
+    /// # Arguments
+    /// * `since` - Value of `--since`, if given
+    /// * `until` - Value of `--until`, if given
+    /// * `today` - The date to resolve keywords against
+    ///
+    /// # Returns
+    /// `Result<Self>` - The range, or an error for invalid dates or an
+    /// `--until` before `--since`
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use ctrlq::range::DateRange;
+    ///
+    /// let today = NaiveDate::from_ymd_opt(2024, 5, 15).unwrap();
+    /// let range = DateRange::parse(Some("2024-01-01"), Some("last-week"), today).unwrap();
+    /// assert_eq!(range.since, NaiveDate::from_ymd_opt(2024, 1, 1));
+    /// assert_eq!(range.until, NaiveDate::from_ymd_opt(2024, 5, 12));
+    ///
+    /// let backwards = DateRange::parse(Some("2024-03-31"), Some("2024-01-01"), today);
+    /// assert!(backwards.unwrap_err().to_string().contains("before"));
+    /// assert!(DateRange::parse(Some("2024-13-01"), None, today).is_err());
+    /// ```
+    pub fn parse(since: Option<&str>, until: Option<&str>, today: NaiveDate) -> Result<Self> {
+        let since = since.map(|spec| report::parse_period(spec, today).map(|period| period.0)).transpose()?;
+        let until = until.map(|spec| report::parse_period(spec, today).map(|period| period.1)).transpose()?;
+        if let (Some(since), Some(until)) = (since, until)
+            && until < since
+        {
+            bail!("--until {} is before --since {}", until, since);
+        }
+        Ok(Self { since, until })
+    }
+
+    /// The range of a period keyword such as `today`, `this-week` or `this-month`.
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use ctrlq::range::DateRange;
+    ///
+    /// let today = NaiveDate::from_ymd_opt(2024, 5, 15).unwrap();
+    /// let month = DateRange::period("this-month", today).unwrap();
+    /// assert_eq!(month.since, NaiveDate::from_ymd_opt(2024, 5, 1));
+    /// assert_eq!(month.until, Some(today));
+    /// ```
+    pub fn period(spec: &str, today: NaiveDate) -> Result<Self> {
+        let (since, until, _) = report::parse_period(spec, today)?;
+        Ok(Self { since: Some(since), until: Some(until) })
+    }
+
+    /// Whether the range has no limits at all.
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Whether a day lies in the range.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.since.is_none_or(|since| since <= date) && self.until.is_none_or(|until| date <= until)
+    }
+
+    /// Describes the range for headers and messages.
+    pub fn label(&self) -> String {
+        match (self.since, self.until) {
+            (Some(since), Some(until)) if since == until => since.to_string(),
+            (Some(since), Some(until)) => format!("{} to {}", since, until),
+            (Some(since), None) => format!("since {}", since),
+            (None, Some(until)) => format!("until {}", until),
+            (None, None) => "all time".to_string(),
+        }
+    }
+
+    /// Scopes statistics to the range.
+    ///
+    /// Days and sessions outside the range are dropped; a session counts if
+    /// any part of it falls into the range. Lifetime totals (key, device and
+    /// context counts) are recomputed from the remaining days, and records
+    /// only stay if they were set in the range.
+    ///
+    /// # Arguments
+    /// * `stats` - Statistics with the archive loaded
+    ///
+    /// # Returns
+    /// `KeyStats` - The scoped copy
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::{Local, NaiveDate, TimeZone};
+    /// use ctrlq::keylogger::KeyStats;
+    /// use ctrlq::range::DateRange;
+    ///
+    /// // A small fixture: three days of typing
+    /// let mut stats = KeyStats::new();
+    /// for (day, key, count) in [(1, "KEY_30", 5), (2, "KEY_30", 3), (2, "KEY_57", 2), (3, "KEY_57", 7)] {
+    ///     for _ in 0..count {
+    ///         stats.add_keypress_at(key, Local.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap());
+    ///     }
+    /// }
+    /// stats.device_counts.insert("laptop".to_string(), 17);
+    /// let day = stats.daily_stats.get_mut("2024-05-02").unwrap();
+    /// day.device_keystrokes.insert("laptop".to_string(), 5);
+    ///
+    /// let today = NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+    /// let range = DateRange::parse(Some("2024-05-02"), Some("2024-05-02"), today).unwrap();
+    /// let scoped = range.filter(&stats);
+    /// assert_eq!(scoped.daily_stats.len(), 1);
+    /// assert_eq!(scoped.total_keystrokes, 5);
+    /// // Counted from the day's distribution, not the lifetime map
+    /// assert_eq!(scoped.key_counts["KEY_30"], 3);
+    /// assert_eq!(scoped.key_counts["KEY_57"], 2);
+    /// assert_eq!(scoped.device_counts["laptop"], 5);
+    ///
+    /// let empty = DateRange::parse(Some("2024-06-01"), None, today).unwrap().filter(&stats);
+    /// assert!(empty.daily_stats.is_empty());
+    /// assert_eq!(empty.total_keystrokes, 0);
+    /// ```
+    pub fn filter(&self, stats: &KeyStats) -> KeyStats {
+        let mut scoped = stats.clone();
+        scoped.daily_stats.retain(|date, _| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok_and(|date| self.contains(date))
+        });
+        scoped.typing_sessions.retain(|session| {
+            let start = session.start.with_timezone(&Local).date_naive();
+            let end = session.end.with_timezone(&Local).date_naive();
+            self.since.is_none_or(|since| since <= end) && self.until.is_none_or(|until| start <= until)
+        });
+
+        scoped.key_counts = sum_days(&scoped, |day| &day.key_distribution);
+        scoped.device_counts = sum_days(&scoped, |day| &day.device_keystrokes);
+        scoped.context_counts = sum_days(&scoped, |day| &day.context_keystrokes);
+        scoped.char_counts = sum_days(&scoped, |day| &day.char_counts);
+        scoped.bigram_counts = sum_days(&scoped, |day| &day.bigram_counts);
+        scoped.total_keystrokes = scoped.daily_stats.values().map(|day| day.keystrokes).sum();
+
+        let in_range = |achieved: DateTime<Utc>| self.contains(achieved.with_timezone(&Local).date_naive());
+        let records = &stats.records;
+        scoped.records = Records {
+            best_wpm_1m: records.best_wpm_1m.filter(|r| in_range(r.achieved)),
+            best_wpm_5m: records.best_wpm_5m.filter(|r| in_range(r.achieved)),
+            most_keystrokes_day: records.most_keystrokes_day.filter(|r| in_range(r.achieved)),
+            longest_session_secs: records.longest_session_secs.filter(|r| in_range(r.achieved)),
+        };
+        scoped
+    }
+
+    /// Checks that scoped statistics have any data, for a clear message
+    /// instead of a page of zeros.
+    ///
+    /// # Arguments
+    /// * `scoped` - Statistics returned by [`DateRange::filter`]
+    ///
+    /// # Returns
+    /// `Result<()>` - An error naming the range if it has no recorded days
+    pub fn ensure_data(&self, scoped: &KeyStats) -> Result<()> {
+        if scoped.daily_stats.is_empty() {
+            bail!("no statistics recorded in the range {}", self.label());
+        }
+        Ok(())
+    }
+}
+
+/// Adds up one per-day count map over all days of `stats`.
+fn sum_days(stats: &KeyStats, counts: impl Fn(&DayStats) -> &HashMap<String, u64>) -> HashMap<String, u64> {
+    let mut total = HashMap::new();
+    for day in stats.daily_stats.values() {
+        for (name, count) in counts(day) {
+            *total.entry(name.clone()).or_insert(0) += count;
+        }
+    }
+    total
+}
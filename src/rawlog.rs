@@ -0,0 +1,400 @@
+//! # Raw Log Module
+//!
+//! An opt-in, full-fidelity log of every key event (`--raw-log`), for people
+//! who want to run their own analysis. Unlike the statistics it records
+//! the exact order and timing of keys, so treat the files like the
+//! sensitive data they are.
+//!
+//! ## Format
+//! One file per local day (`YYYY-MM-DD.ctrlqlog`) in a directory next to
+//! the data file. Each file starts with the 8-byte magic `CTRLQRW1`,
+//! followed by fixed-size little-endian records of 11 bytes:
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 0-7   | `u64` microseconds since the Unix epoch |
+//! | 8-9   | `u16` evdev key code |
+//! | 10    | `u8` value: 0 released, 1 pressed, 2 auto-repeat |
+//!
+//! Records go through a buffered writer, so logging an event costs a
+//! copy of 11 bytes; the buffer is flushed with every save of the data
+//! file and on exit. A record cut off by a crash is dropped when the file
+//! is opened again, so later records stay aligned. On battery the buffer grows to `battery_raw_log_kb`,
+//! see [`crate::power`]. `ctrlq log convert` turns a file into JSON lines or CSV.
+//!
+//! The directory is only accessible to its owner (0700) and the files are
+//! only readable by their owner (0600), also when ctrlq runs with sudo.
+
+use crate::keymap;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use std::fs::{DirBuilder, File, OpenOptions, Permissions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of every raw log file.
+pub const MAGIC: &[u8; 8] = b"CTRLQRW1";
+
+/// Size of one record in bytes.
+pub const RECORD_SIZE: usize = 11;
+
+/// Bytes buffered before records are written, unless on battery.
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// File extension of raw log files.
+const EXTENSION: &str = "ctrlqlog";
+
+/// A single key event in the raw log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawRecord {
+    /// Microseconds since the Unix epoch
+    pub timestamp_micros: u64,
+    /// The evdev key code
+    pub code: u16,
+    /// 0 released, 1 pressed, 2 auto-repeat
+    pub value: u8,
+}
+
+impl RawRecord {
+    /// Encodes the record in its on-disk form.
+    pub fn to_bytes(self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0u8; RECORD_SIZE];
+        bytes[0..8].copy_from_slice(&self.timestamp_micros.to_le_bytes());
+        bytes[8..10].copy_from_slice(&self.code.to_le_bytes());
+        bytes[10] = self.value;
+        bytes
+    }
+
+    /// Decodes a record from its on-disk form.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::rawlog::RawRecord;
+    ///
+    /// let record = RawRecord { timestamp_micros: 1_714_557_600_000_000, code: 30, value: 1 };
+    /// assert_eq!(RawRecord::from_bytes(record.to_bytes()), record);
+    /// ```
+    pub fn from_bytes(bytes: [u8; RECORD_SIZE]) -> Self {
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&bytes[0..8]);
+        Self {
+            timestamp_micros: u64::from_le_bytes(timestamp),
+            code: u16::from_le_bytes([bytes[8], bytes[9]]),
+            value: bytes[10],
+        }
+    }
+
+    /// The record's time.
+    pub fn time(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_micros(self.timestamp_micros as i64).unwrap_or_default()
+    }
+
+    /// Name of the value (`released`, `pressed` or `repeated`).
+    pub fn state_name(&self) -> &'static str {
+        match self.value {
+            0 => "released",
+            1 => "pressed",
+            2 => "repeated",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Returns the raw log directory belonging to a data file.
+///
+/// # Arguments
+/// * `data_file` - Path of the statistics data file
+///
+/// # Returns
+/// `PathBuf` - e.g. `keystroke_data.raw` next to `keystroke_data.json`
+pub fn log_dir(data_file: &Path) -> PathBuf {
+    data_file.with_extension("raw")
+}
+
+/// Appends records to the raw log, starting a new file each local day.
+pub struct RawLogWriter {
+    /// Directory holding the daily files
+    dir: PathBuf,
+    /// Local date of the open file
+    date: NaiveDate,
+    /// Buffered writer of the open file
+    writer: BufWriter<File>,
+    /// Bytes buffered before records are written
+    buffer_size: usize,
+}
+
+impl RawLogWriter {
+    /// Opens today's file in `dir`, creating the directory if needed.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory for the daily files, see [`log_dir`]
+    ///
+    /// # Returns
+    /// `Result<Self>` - The writer, or an error if the file can't be opened
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::rawlog::RawLogWriter;
+    /// use std::os::unix::fs::PermissionsExt;
+    ///
+    /// let dir = std::env::temp_dir().join(format!("ctrlq-rawlog-doc-{}", std::process::id()));
+    /// let mut writer = RawLogWriter::open(&dir)?;
+    /// writer.append(chrono::Utc::now(), 30, 1)?;
+    /// writer.flush()?;
+    ///
+    /// let mode = |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    /// assert_eq!(mode(&dir), 0o700);
+    /// for file in std::fs::read_dir(&dir)? {
+    ///     assert_eq!(mode(&file?.path()), 0o600);
+    /// }
+    /// std::fs::remove_dir_all(&dir)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn open(dir: &Path) -> Result<Self> {
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+        // Directories made by earlier versions used the umask
+        std::fs::set_permissions(dir, Permissions::from_mode(0o700))
+            .with_context(|| format!("failed to restrict {}", dir.display()))?;
+        let date = Local::now().date_naive();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            date,
+            writer: Self::open_day(dir, date, DEFAULT_BUFFER_SIZE)?,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+        })
+    }
+
+    /// Opens (or continues) the file of one day.
+    ///
+    /// A partial record at the end, left by a crash in the middle of a
+    /// write, is cut off so that appended records start on a boundary.
+    fn open_day(dir: &Path, date: NaiveDate, buffer_size: usize) -> Result<BufWriter<File>> {
+        let path = dir.join(format!("{}.{}", date.format("%Y-%m-%d"), EXTENSION));
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        // The mode only applies to new files; older ones used the umask
+        file.set_permissions(Permissions::from_mode(0o600))?;
+        let len = file.metadata()?.len();
+        let header = MAGIC.len() as u64;
+        if len < header {
+            file.set_len(0)?;
+            file.write_all(MAGIC)?;
+        } else {
+            let whole = header + (len - header) / RECORD_SIZE as u64 * RECORD_SIZE as u64;
+            if whole != len {
+                file.set_len(whole)
+                    .with_context(|| format!("failed to cut the partial record off {}", path.display()))?;
+            }
+        }
+        Ok(BufWriter::with_capacity(buffer_size, file))
+    }
+
+    /// Directory the daily files are written to.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Appends one event.
+    ///
+    /// # Arguments
+    /// * `at` - When the event happened
+    /// * `code` - The evdev key code
+    /// * `value` - 0 released, 1 pressed, 2 auto-repeat
+    pub fn append(&mut self, at: DateTime<Utc>, code: u16, value: u8) -> Result<()> {
+        let date = at.with_timezone(&Local).date_naive();
+        if date != self.date {
+            self.writer.flush()?;
+            self.writer = Self::open_day(&self.dir, date, self.buffer_size)?;
+            self.date = date;
+        }
+
+        let record = RawRecord {
+            timestamp_micros: at.timestamp_micros().max(0) as u64,
+            code,
+            value,
+        };
+        self.writer.write_all(&record.to_bytes())?;
+        Ok(())
+    }
+
+    /// Writes buffered records to disk.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Changes how many bytes are buffered before records are written,
+    /// writing what is buffered so far.
+    ///
+    /// # Arguments
+    /// * `buffer_size` - Bytes to buffer, at least one record
+    pub fn set_buffer_size(&mut self, buffer_size: usize) -> Result<()> {
+        let buffer_size = buffer_size.max(RECORD_SIZE);
+        if buffer_size == self.buffer_size {
+            return Ok(());
+        }
+        self.writer.flush()?;
+        let file = self.writer.get_ref().try_clone()?;
+        self.writer = BufWriter::with_capacity(buffer_size, file);
+        self.buffer_size = buffer_size;
+        Ok(())
+    }
+}
+
+/// Output format of [`convert`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvertFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+impl ConvertFormat {
+    /// Parses a format name as given on the command line.
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "jsonl" => Ok(ConvertFormat::Jsonl),
+            "csv" => Ok(ConvertFormat::Csv),
+            _ => bail!("unknown format '{}': expected jsonl or csv", name),
+        }
+    }
+}
+
+/// The contents of a raw log file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawLog {
+    /// Records in file order
+    pub records: Vec<RawRecord>,
+    /// Bytes of a record cut off at the end, e.g. by a crash; 0 if none
+    pub partial_bytes: usize,
+}
+
+/// Reads all records of a raw log file.
+///
+/// # Arguments
+/// * `path` - The raw log file
+///
+/// # Returns
+/// `Result<RawLog>` - The whole records, and the size of a partial last
+/// record if there is one
+///
+/// # Example
+/// ```
+/// use ctrlq::rawlog::{self, RawLogWriter, RawRecord, MAGIC};
+///
+/// let dir = std::env::temp_dir().join(format!("ctrlq-rawlog-torn-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// let now = chrono::Utc::now();
+/// let path = dir.join(format!("{}.ctrlqlog", now.with_timezone(&chrono::Local).format("%Y-%m-%d")));
+///
+/// // A crash wrote the first 5 bytes of the second record
+/// let first = RawRecord { timestamp_micros: 1_714_557_600_000_000, code: 30, value: 1 };
+/// let mut bytes = MAGIC.to_vec();
+/// bytes.extend(first.to_bytes());
+/// bytes.extend(&first.to_bytes()[..5]);
+/// std::fs::write(&path, bytes)?;
+///
+/// let torn = rawlog::read_records(&path)?;
+/// assert_eq!((torn.records, torn.partial_bytes), (vec![first], 5));
+///
+/// // Appending again drops the partial record instead of misaligning the rest
+/// let mut writer = RawLogWriter::open(&dir)?;
+/// writer.append(now, 48, 0)?;
+/// writer.flush()?;
+/// let log = rawlog::read_records(&path)?;
+/// assert_eq!(log.partial_bytes, 0);
+/// assert_eq!(log.records.len(), 2);
+/// assert_eq!(log.records[0], first);
+/// assert_eq!((log.records[1].code, log.records[1].value), (48, 0));
+///
+/// std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn read_records(path: &Path) -> Result<RawLog> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0u8; 8];
+    reader
+        .read_exact(&mut magic)
+        .with_context(|| format!("{} is not a ctrlq raw log", path.display()))?;
+    if &magic != MAGIC {
+        bail!("{} is not a ctrlq raw log", path.display());
+    }
+
+    let mut rest = Vec::new();
+    reader
+        .read_to_end(&mut rest)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let chunks = rest.chunks_exact(RECORD_SIZE);
+    let partial_bytes = chunks.remainder().len();
+    let records = chunks
+        .map(|chunk| RawRecord::from_bytes(chunk.try_into().expect("chunks are one record long")))
+        .collect();
+    Ok(RawLog { records, partial_bytes })
+}
+
+/// Converts a raw log file into JSON lines or CSV.
+///
+/// # Arguments
+/// * `path` - The raw log file
+/// * `format` - Output format
+/// * `out` - Where to write the converted records
+///
+/// # Returns
+/// `Result<RawLog>` - The records written, and the size of a partial last
+/// record that was left out
+pub fn convert(path: &Path, format: ConvertFormat, out: &mut impl Write) -> Result<RawLog> {
+    let log = read_records(path)?;
+    if format == ConvertFormat::Csv {
+        writeln!(out, "timestamp_micros,time,code,key,state")?;
+    }
+
+    for record in &log.records {
+        let time = record.time().to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+        let key = keymap::display_name(&keymap::key_name(record.code));
+        match format {
+            ConvertFormat::Jsonl => {
+                let line = serde_json::json!({
+                    "timestamp_micros": record.timestamp_micros,
+                    "time": time,
+                    "code": record.code,
+                    "key": key,
+                    "state": record.state_name(),
+                });
+                writeln!(out, "{}", line)?;
+            }
+            ConvertFormat::Csv => {
+                writeln!(
+                    out,
+                    "{},{},{},{},{}",
+                    record.timestamp_micros,
+                    time,
+                    record.code,
+                    csv_field(&key),
+                    record.state_name()
+                )?;
+            }
+        }
+    }
+    Ok(log)
+}
+
+/// Quotes a CSV field if needed.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,1445 @@
+//! # Report Module
+//!
+//! Plain-text reports built from saved statistics, used by the `report`
+//! subcommand. Reports read the data file only and never touch devices.
+
+use crate::actions;
+use crate::heatmap::{Heat, HeatGrid};
+use crate::keylogger::{
+    day_key, Coverage, DayComparison, DayStats, KeyChange, KeyStats, PeriodSummary, SessionPatterns, TypingSession, DEFAULT_CONTEXT,
+};
+use crate::keymap::{self, Category, CategoryBreakdown};
+use crate::layout::{EffortScore, Layout, SfbStats};
+use crate::numbers::format_count;
+use crate::surprise::{KeySurprise, SurpriseReport};
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+
+/// A single session as written by `--on-exit-export` in JSON form.
+#[derive(Debug, Serialize)]
+pub struct SessionExport<'a> {
+    /// The session itself
+    pub session: &'a TypingSession,
+    /// Most pressed keys during the session, by display name
+    pub top_keys: Vec<(String, u64)>,
+}
+
+/// Parses a period specification into an inclusive date range.
+///
+/// Accepts a single date (`2024-05-01`) or one of the keywords `today`,
+/// `yesterday`, `this-week`, `last-week`, `this-month` and `last-month`.
+/// Weeks start on Monday.
+///
+/// # Arguments
+/// * `spec` - The period specification
+/// * `today` - The date to resolve relative keywords against
+///
+/// # Returns
+/// `Result<(NaiveDate, NaiveDate, String)>` - (first day, last day, label)
+pub fn parse_period(spec: &str, today: NaiveDate) -> Result<(NaiveDate, NaiveDate, String)> {
+    let week_start = week_start(today);
+
+    let (start, end) = match spec {
+        "today" => (today, today),
+        "yesterday" => {
+            let day = today - Duration::days(1);
+            (day, day)
+        }
+        "this-week" => (week_start, today),
+        "last-week" => (week_start - Duration::days(7), week_start - Duration::days(1)),
+        "this-month" => (today.with_day(1).unwrap_or(today), today),
+        "last-month" => {
+            let month_start = today.with_day(1).unwrap_or(today);
+            let last_day = month_start - Duration::days(1);
+            (last_day.with_day(1).unwrap_or(last_day), last_day)
+        }
+        _ => match NaiveDate::parse_from_str(spec, "%Y-%m-%d") {
+            Ok(day) => (day, day),
+            Err(_) => bail!(
+                "invalid period '{}': expected YYYY-MM-DD, today, yesterday, this-week, last-week, this-month or last-month",
+                spec
+            ),
+        },
+    };
+
+    let label = match spec {
+        "this-week" => "this week".to_string(),
+        "last-week" => "last week".to_string(),
+        "this-month" => "this month".to_string(),
+        "last-month" => "last month".to_string(),
+        _ => spec.to_string(),
+    };
+
+    Ok((start, end, label))
+}
+
+/// Parses an ISO week such as `2024-W23` into its Monday.
+///
+/// # Arguments
+/// * `spec` - The week as `YYYY-Www`
+///
+/// # Returns
+/// `Result<NaiveDate>` - Monday of the week, or an error for weeks that don't exist
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::report::parse_week;
+///
+/// assert_eq!(parse_week("2024-W23")?, NaiveDate::from_ymd_opt(2024, 6, 3).unwrap());
+/// assert_eq!(parse_week("2020-w53")?, NaiveDate::from_ymd_opt(2020, 12, 28).unwrap());
+/// assert!(parse_week("2023-W53").is_err());
+/// assert!(parse_week("2024-06-03").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_week(spec: &str) -> Result<NaiveDate> {
+    let monday = spec
+        .split_once(['W', 'w'])
+        .and_then(|(year, week)| Some((year.strip_suffix('-')?.parse().ok()?, week.parse().ok()?)))
+        .and_then(|(year, week)| NaiveDate::from_isoywd_opt(year, week, chrono::Weekday::Mon));
+    match monday {
+        Some(monday) => Ok(monday),
+        None => bail!("invalid week '{}': expected an ISO week such as 2024-W23", spec),
+    }
+}
+
+/// Parses a month such as `2024-06` into its first day.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::report::parse_month;
+///
+/// assert_eq!(parse_month("2024-06")?, NaiveDate::from_ymd_opt(2024, 6, 1).unwrap());
+/// assert!(parse_month("2024-13").is_err());
+/// assert!(parse_month("2024-06-01").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_month(spec: &str) -> Result<NaiveDate> {
+    match NaiveDate::parse_from_str(&format!("{}-01", spec.trim()), "%Y-%m-%d") {
+        Ok(first) => Ok(first),
+        Err(_) => bail!("invalid month '{}': expected YYYY-MM such as 2024-06", spec),
+    }
+}
+
+/// Returns the ISO week a day belongs to, e.g. `2024-W23`.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::report::week_label;
+///
+/// // ISO weeks belong to the year of their Thursday
+/// assert_eq!(week_label(NaiveDate::from_ymd_opt(2024, 12, 30).unwrap()), "2025-W01");
+/// ```
+pub fn week_label(day: NaiveDate) -> String {
+    let week = day.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// Returns the Monday of the ISO week a day belongs to.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::report::{week_label, week_start};
+///
+/// let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+/// assert_eq!(week_start(day(2024, 6, 9)), day(2024, 6, 3));
+/// assert_eq!(week_start(day(2024, 6, 3)), day(2024, 6, 3));
+/// // Weeks run across New Year
+/// assert_eq!(week_start(day(2025, 1, 1)), day(2024, 12, 30));
+/// assert_eq!(week_start(day(2021, 1, 3)), day(2020, 12, 28));
+/// assert_eq!(week_label(week_start(day(2021, 1, 3))), "2020-W53");
+/// ```
+pub fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(day.weekday().num_days_from_monday() as i64)
+}
+
+/// Keystrokes of one ISO week, summed from its days.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WeekTotals {
+    /// Keystrokes in the week
+    pub keystrokes: u64,
+    /// Keystrokes by physical key (evdev code), shifted variants included
+    pub keys: HashMap<u16, u64>,
+    /// Days of the week with statistics
+    pub days: usize,
+}
+
+/// Sums daily statistics into ISO weeks.
+///
+/// Days are keyed by their local date, so a week runs from Monday to Sunday
+/// in the timezone the keystrokes were counted in.
+///
+/// # Arguments
+/// * `days` - Days by date (`YYYY-MM-DD`); other keys are skipped
+///
+/// # Returns
+/// `BTreeMap<NaiveDate, WeekTotals>` - Totals by the Monday of their week, weeks
+/// without any day left out
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::keylogger::KeyStats;
+/// use ctrlq::report::weekly_totals;
+///
+/// let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+/// let mut stats = KeyStats::new();
+/// // Thursday and Sunday of 2020-W53, and Monday of 2021-W01
+/// stats.add_counts("KEY_30", 10, Some(day(2020, 12, 31)));
+/// stats.add_counts("KEY_30_S", 5, Some(day(2021, 1, 3)));
+/// stats.add_counts("KEY_48", 20, Some(day(2021, 1, 3)));
+/// stats.add_counts("KEY_30", 7, Some(day(2021, 1, 4)));
+///
+/// let weeks = weekly_totals(&stats.daily_stats);
+/// assert_eq!(weeks.keys().copied().collect::<Vec<_>>(), [day(2020, 12, 28), day(2021, 1, 4)]);
+/// let last_of_2020 = &weeks[&day(2020, 12, 28)];
+/// assert_eq!((last_of_2020.keystrokes, last_of_2020.days), (35, 2));
+/// assert_eq!(last_of_2020.keys[&30], 15);
+/// assert_eq!(weeks[&day(2021, 1, 4)].keystrokes, 7);
+///
+/// // 2025-W01 starts in December 2024
+/// stats.add_counts("KEY_30", 1, Some(day(2024, 12, 30)));
+/// stats.add_counts("KEY_30", 2, Some(day(2025, 1, 5)));
+/// assert_eq!(weekly_totals(&stats.daily_stats)[&day(2024, 12, 30)].keystrokes, 3);
+/// ```
+pub fn weekly_totals<'a>(days: impl IntoIterator<Item = (&'a String, &'a DayStats)>) -> BTreeMap<NaiveDate, WeekTotals> {
+    let mut weeks: BTreeMap<NaiveDate, WeekTotals> = BTreeMap::new();
+    for (date, day) in days {
+        let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+            continue;
+        };
+        let week = weeks.entry(week_start(date)).or_default();
+        week.keystrokes += day.keystrokes;
+        week.days += 1;
+        for (key, &count) in &day.key_distribution {
+            if let Some(code) = keymap::parse_code(key) {
+                *week.keys.entry(code).or_insert(0) += count;
+            }
+        }
+    }
+    weeks
+}
+
+/// Formats a signed integer delta with an explicit sign and grouped digits.
+///
+/// # Example
+/// ```
+/// use ctrlq::report::signed;
+///
+/// assert_eq!(signed(12_500), "+12,500");
+/// assert_eq!(signed(-3), "-3");
+/// assert_eq!(signed(0), "0");
+/// ```
+pub fn signed(delta: i64) -> String {
+    match delta {
+        1.. => format!("+{}", format_count(delta.unsigned_abs())),
+        ..0 => format!("-{}", format_count(delta.unsigned_abs())),
+        0 => "0".to_string(),
+    }
+}
+
+/// Formats a signed floating point delta with an explicit sign.
+pub fn signed_f64(delta: f64, precision: usize) -> String {
+    if delta > 0.0 {
+        format!("+{:.*}", precision, delta)
+    } else {
+        format!("{:.*}", precision, delta)
+    }
+}
+
+/// Share of `part` in `whole` as a percentage.
+///
+/// # Returns
+/// `Option<f64>` - 0-100, or None if `whole` is zero and the share is undefined
+///
+/// # Example
+/// ```
+/// use ctrlq::report::percent;
+///
+/// assert_eq!(percent(1, 4), Some(25.0));
+/// assert_eq!(percent(0, 0), None);
+/// ```
+pub fn percent(part: u64, whole: u64) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64 * 100.0)
+}
+
+/// Formats a percentage, or `—` if it is undefined.
+///
+/// # Example
+/// ```
+/// use ctrlq::report::{format_percent, percent};
+///
+/// assert_eq!(format_percent(percent(1, 3), 1), "33.3%");
+/// assert_eq!(format_percent(percent(5, 0), 1), "—");
+/// ```
+pub fn format_percent(value: Option<f64>, precision: usize) -> String {
+    optional(value, |v| format!("{:.*}%", precision, v))
+}
+
+/// Formats the share of each keystroke category, in [`Category::ALL`] order.
+///
+/// # Arguments
+/// * `breakdown` - Keystrokes per category
+///
+/// # Returns
+/// `Option<String>` - E.g. `Letters 60% · Symbols 15% · Whitespace 25%`,
+/// or None without keystrokes
+///
+/// # Example
+/// ```
+/// use ctrlq::keymap::{Category, CategoryBreakdown};
+/// use ctrlq::report::{format_category_mix, format_symbol_ratio};
+///
+/// let mut breakdown = CategoryBreakdown::default();
+/// breakdown.add(Category::Letters, 600);
+/// breakdown.add(Category::Symbols, 150);
+/// breakdown.add(Category::Whitespace, 250);
+/// assert_eq!(format_category_mix(&breakdown).as_deref(), Some("Letters 60% · Symbols 15% · Whitespace 25%"));
+/// assert_eq!(format_symbol_ratio(&breakdown), "20.0% (coding)");
+///
+/// breakdown.add(Category::Letters, 10_000);
+/// assert_eq!(format_symbol_ratio(&breakdown), "1.4% (writing)");
+/// assert_eq!(format_category_mix(&CategoryBreakdown::default()), None);
+/// ```
+pub fn format_category_mix(breakdown: &CategoryBreakdown) -> Option<String> {
+    let total = breakdown.total();
+    let parts: Vec<String> = Category::ALL
+        .iter()
+        .filter(|&&category| breakdown.get(category) > 0)
+        .map(|&category| format!("{} {}", category, format_percent(percent(breakdown.get(category), total), 0)))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// Formats the symbol ratio with what it suggests, see
+/// [`CategoryBreakdown::is_coding`]: `20.0% (coding)`, `1.4% (writing)`,
+/// just the ratio below a few hundred characters, or `—` without any.
+pub fn format_symbol_ratio(breakdown: &CategoryBreakdown) -> String {
+    let Some(ratio) = breakdown.symbol_ratio() else {
+        return "—".to_string();
+    };
+    match breakdown.is_coding() {
+        Some(true) => format!("{:.1}% (coding)", ratio * 100.0),
+        Some(false) => format!("{:.1}% (writing)", ratio * 100.0),
+        None => format!("{:.1}%", ratio * 100.0),
+    }
+}
+
+/// Formats how much of a day's typing was deleted again.
+///
+/// # Returns
+/// `Option<String>` - E.g. `~8400 chars, ~1100 deleted (13%)`, or
+/// None for a day without typed characters
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::DayStats;
+/// use ctrlq::report::format_deletions;
+///
+/// let day = DayStats { chars_typed: 8400, chars_deleted: 1100, ..DayStats::default() };
+/// assert_eq!(format_deletions(&day).as_deref(), Some("~8400 chars, ~1100 deleted (13%)"));
+/// assert_eq!(format_deletions(&DayStats::default()), None);
+/// ```
+pub fn format_deletions(day: &DayStats) -> Option<String> {
+    let share = day.deleted_percent()?;
+    Some(format!("~{} chars, ~{} deleted ({:.0}%)", day.chars_typed, day.chars_deleted, share))
+}
+
+/// Formats how long Caps Lock was on during a day and what was typed with it.
+///
+/// # Returns
+/// `Option<String>` - E.g. `14 min on · 85 letters in 3 runs`, or None
+/// for a day without Caps Lock
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::DayStats;
+/// use ctrlq::report::format_caps_lock;
+///
+/// let day = DayStats { caps_lock_ms: 14 * 60_000 + 5_000, caps_lock_letters: 85, caps_lock_runs: 3, ..DayStats::default() };
+/// assert_eq!(format_caps_lock(&day).as_deref(), Some("14 min on · 85 letters in 3 runs"));
+/// let brief = DayStats { caps_lock_ms: 20_000, ..DayStats::default() };
+/// assert_eq!(format_caps_lock(&brief).as_deref(), Some("<1 min on"));
+/// assert_eq!(format_caps_lock(&DayStats::default()), None);
+/// ```
+pub fn format_caps_lock(day: &DayStats) -> Option<String> {
+    if day.caps_lock_ms == 0 && day.caps_lock_letters == 0 {
+        return None;
+    }
+    let minutes = match day.caps_lock_minutes() {
+        0 => "<1 min on".to_string(),
+        minutes => format!("{} min on", minutes),
+    };
+    if day.caps_lock_letters == 0 {
+        return Some(minutes);
+    }
+    let runs = if day.caps_lock_runs == 1 { "1 run".to_string() } else { format!("{} runs", day.caps_lock_runs) };
+    Some(format!("{} · {} letters in {}", minutes, day.caps_lock_letters, runs))
+}
+
+/// Formats how long the logger ran on a day, see [`crate::keylogger::DayStats::coverage`].
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::Coverage;
+/// use ctrlq::report::format_coverage;
+///
+/// let coverage = Coverage { ran_secs: 51_120, expected_secs: 24 * 3600 };
+/// assert_eq!(format_coverage(coverage), "14.2h / 24h (59%)");
+/// ```
+pub fn format_coverage(coverage: Coverage) -> String {
+    let hours = |secs: u64| match secs % 3600 {
+        0 => format!("{}h", secs / 3600),
+        _ => format!("{:.1}h", secs as f64 / 3600.0),
+    };
+    format!(
+        "{} / {} ({})",
+        hours(coverage.ran_secs),
+        hours(coverage.expected_secs),
+        format_percent(Some(coverage.share() * 100.0), 0)
+    )
+}
+
+/// Formats a WPM value, or `warming up…` until there is enough data for one.
+pub fn format_wpm(wpm: Option<f64>) -> String {
+    match wpm {
+        Some(wpm) => format!("{:.1} WPM", wpm),
+        None => "warming up…".to_string(),
+    }
+}
+
+/// Renders a two-period comparison as a plain-text table.
+///
+/// # Arguments
+/// * `cmp` - The comparison to render
+///
+/// # Returns
+/// `String` - The report text
+pub fn comparison_report(cmp: &DayComparison) -> String {
+    let mut out = String::new();
+    let (a, b) = (&cmp.a, &cmp.b);
+
+    let _ = writeln!(out, "📊 Comparison: {} → {}", a.label, b.label);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{:<14}{:>14}{:>14}{:>12}", "Metric", a.label, b.label, "Δ");
+    let _ = writeln!(
+        out,
+        "{:<14}{:>14}{:>14}{:>12}",
+        "Keystrokes",
+        format_count(a.keystrokes),
+        format_count(b.keystrokes),
+        signed(cmp.keystroke_delta())
+    );
+    let _ = writeln!(
+        out,
+        "{:<14}{:>14}{:>14}{:>12}",
+        "WPM",
+        optional(a.wpm, |v| format!("{:.1}", v)),
+        optional(b.wpm, |v| format!("{:.1}", v)),
+        optional(cmp.wpm_delta(), |d| signed_f64(d, 1)),
+    );
+    let _ = writeln!(
+        out,
+        "{:<14}{:>14}{:>14}{:>12}",
+        "Error rate",
+        optional(a.error_rate, |v| format!("{:.1}%", v * 100.0)),
+        optional(b.error_rate, |v| format!("{:.1}%", v * 100.0)),
+        optional(cmp.error_rate_delta(), |d| format!("{} pp", signed_f64(d, 1))),
+    );
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Top keys ({})", b.label);
+    write_key_changes(&mut out, &cmp.top_keys);
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Biggest movers");
+    write_key_changes(&mut out, &cmp.biggest_movers);
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Actions");
+    let rows = action_rows(a, b);
+    if rows.is_empty() {
+        let _ = writeln!(out, "  (no shortcuts)");
+    }
+    for (action, count_a, count_b) in rows {
+        let _ = writeln!(
+            out,
+            "  {:<12}{:>14}{:>14}{:>12}",
+            action,
+            format_count(count_a),
+            format_count(count_b),
+            signed(count_b as i64 - count_a as i64)
+        );
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Speed by hour (WPM)");
+    write_speed_by_hour(&mut out, &a.speed_by_hour, &b.speed_by_hour);
+
+    for summary in [a, b] {
+        if let Some(note) = missing_note(summary) {
+            let _ = writeln!(out);
+            let _ = write!(out, "Note: {}", note);
+        }
+    }
+
+    out
+}
+
+/// Formats the change of a month from the previous one, e.g. `+12%`.
+///
+/// # Example
+/// ```
+/// use ctrlq::report::format_month_change;
+///
+/// assert_eq!(format_month_change(Some(0.124)), "+12%");
+/// assert_eq!(format_month_change(Some(-0.5)), "-50%");
+/// assert_eq!(format_month_change(None), "—");
+/// ```
+pub fn format_month_change(change: Option<f64>) -> String {
+    optional(change, |change| format!("{}%", signed_f64(change * 100.0, 0)))
+}
+
+/// Renders the totals of one calendar month next to the month before.
+///
+/// # Arguments
+/// * `stats` - Statistics holding both months, archived days included
+/// * `month` - First day of the month
+/// * `today` - The current day; a running month is labeled as partial
+///
+/// # Returns
+/// `Result<String>` - The report, or an error for a month that hasn't started
+///
+/// # Example
+/// ```
+/// use chrono::{Local, NaiveDate, TimeZone};
+/// use ctrlq::keylogger::KeyStats;
+/// use ctrlq::keymap;
+/// use ctrlq::report::month_report;
+///
+/// let mut stats = KeyStats::new();
+/// stats.add_keypress_at(&keymap::key_name(30), Local.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap());
+/// let june = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+///
+/// let report = month_report(&stats, june, NaiveDate::from_ymd_opt(2024, 6, 10).unwrap())?;
+/// assert!(report.starts_with("📅 2024-06 (partial: 10 of 30 days)"));
+/// assert!(report.contains("Active days:     1 of 10"));
+/// assert!(month_report(&stats, june, NaiveDate::from_ymd_opt(2024, 5, 31).unwrap()).is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn month_report(stats: &KeyStats, month: NaiveDate, today: NaiveDate) -> Result<String> {
+    let current = stats.month_stats(month, today, &HashMap::new());
+    if current.days == 0 {
+        bail!("{} hasn't started yet", current.label());
+    }
+    let previous_month = current.month.checked_sub_months(chrono::Months::new(1)).unwrap_or(current.month);
+    let previous = stats.month_stats(previous_month, today, &HashMap::new());
+    let length = current.month.checked_add_months(chrono::Months::new(1)).map_or(current.days as i64, |next| {
+        (next - current.month).num_days()
+    });
+
+    let mut out = String::new();
+    if current.partial {
+        let _ = writeln!(out, "📅 {} (partial: {} of {} days)", current.label(), current.days, length);
+    } else {
+        let _ = writeln!(out, "📅 {}", current.label());
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "  Keystrokes:      {} ({} per day vs {})",
+        format_count(current.keystrokes),
+        format_month_change(current.change(&previous)),
+        previous.label()
+    );
+    let _ = writeln!(out, "  Active days:     {} of {}", current.active_days, current.days);
+    let _ = writeln!(out, "  Per active day:  {}", optional(current.per_active_day(), |v| format_count(v.round() as u64)));
+    let _ = writeln!(out, "  Average WPM:     {}", optional(current.wpm, |v| format!("{:.1}", v)));
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{:<18}{:>14}{:>14}", "", previous.label(), current.label());
+    let _ = writeln!(out, "{:<18}{:>14}{:>14}", "Keystrokes", format_count(previous.keystrokes), format_count(current.keystrokes));
+    let _ = writeln!(out, "{:<18}{:>14}{:>14}", "Active days", previous.active_days, current.active_days);
+    let _ = writeln!(
+        out,
+        "{:<18}{:>14}{:>14}",
+        "Per active day",
+        optional(previous.per_active_day(), |v| format_count(v.round() as u64)),
+        optional(current.per_active_day(), |v| format_count(v.round() as u64))
+    );
+    let _ = writeln!(
+        out,
+        "{:<18}{:>14}{:>14}",
+        "WPM",
+        optional(previous.wpm, |v| format!("{:.1}", v)),
+        optional(current.wpm, |v| format!("{:.1}", v))
+    );
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Top keys");
+    if current.top_keys.is_empty() {
+        let _ = writeln!(out, "  (no keystrokes)");
+    }
+    for (key, count) in &current.top_keys {
+        let share = format_percent(percent(*count, current.keystrokes), 1);
+        let _ = writeln!(out, "  {:<12}{:>10}{:>9}", stats.key_label(key), format_count(*count), share);
+    }
+    Ok(out)
+}
+
+/// Renders one ISO week as a Markdown summary: totals compared with the
+/// week before, a daily breakdown and the top keys.
+///
+/// # Arguments
+/// * `stats` - Statistics holding the week and the week before it
+/// * `monday` - First day of the week
+///
+/// # Returns
+/// `String` - The summary text
+pub fn weekly_summary(stats: &KeyStats, monday: NaiveDate) -> String {
+    let mut out = String::new();
+    let sunday = monday + Duration::days(6);
+    let (label, previous_label) = (week_label(monday), week_label(monday - Duration::days(7)));
+    let cmp = stats.compare_periods(
+        (monday - Duration::days(7), sunday - Duration::days(7), &previous_label),
+        (monday, sunday, &label),
+    );
+    let (previous, week) = (&cmp.a, &cmp.b);
+    let sessions = |summary: &PeriodSummary| sessions_between(stats, summary.start, summary.end).count();
+    let peak = |summary: &PeriodSummary| {
+        sessions_between(stats, summary.start, summary.end)
+            .filter_map(|s| s.wpm)
+            .max_by(f64::total_cmp)
+    };
+    let active_days = |summary: &PeriodSummary| summary.day_count() as usize - summary.missing_days.len();
+    let wpm = |v: f64| format!("{:.1}", v);
+
+    let _ = writeln!(out, "# CtrlQ week {}", label);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{} – {}", monday.format("%a %Y-%m-%d"), sunday.format("%a %Y-%m-%d"));
+    let _ = writeln!(out);
+    let _ = writeln!(out, "## Totals");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "| Metric | {} | {} | Δ |", previous_label, label);
+    let _ = writeln!(out, "|--------|------|------|---|");
+    let _ = writeln!(
+        out,
+        "| Keystrokes | {} | {} | {} |",
+        format_count(previous.keystrokes),
+        format_count(week.keystrokes),
+        signed(cmp.keystroke_delta())
+    );
+    let _ = writeln!(
+        out,
+        "| Active days | {} | {} | {} |",
+        active_days(previous),
+        active_days(week),
+        signed(active_days(week) as i64 - active_days(previous) as i64)
+    );
+    let _ = writeln!(
+        out,
+        "| Sessions | {} | {} | {} |",
+        sessions(previous),
+        sessions(week),
+        signed(sessions(week) as i64 - sessions(previous) as i64)
+    );
+    let (previous_patterns, week_patterns) = (
+        stats.session_patterns(previous.start, previous.end),
+        stats.session_patterns(week.start, week.end),
+    );
+    let minutes = |d: Duration| format!("{} min", d.num_minutes());
+    let lengths = [
+        ("Average session", previous_patterns.average_length, week_patterns.average_length),
+        ("P90 session", previous_patterns.p90_length, week_patterns.p90_length),
+        ("Gap between sessions", previous_patterns.average_gap, week_patterns.average_gap),
+    ];
+    for (metric, a, b) in lengths {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            metric,
+            optional(a, minutes),
+            optional(b, minutes),
+            optional(b.zip(a).map(|(b, a)| b - a), |d| format!("{} min", signed(d.num_minutes())))
+        );
+    }
+    let _ = writeln!(
+        out,
+        "| Sessions per day | {} | {} | {} |",
+        format_sessions_per_day(&previous_patterns, previous.start),
+        format_sessions_per_day(&week_patterns, week.start),
+        optional(
+            week_patterns.sessions_per_day.zip(previous_patterns.sessions_per_day).map(|(w, p)| w - p),
+            |d| signed_f64(d, 1)
+        )
+    );
+    let _ = writeln!(
+        out,
+        "| Average WPM | {} | {} | {} |",
+        optional(previous.wpm, wpm),
+        optional(week.wpm, wpm),
+        optional(cmp.wpm_delta(), |d| signed_f64(d, 1))
+    );
+    let _ = writeln!(
+        out,
+        "| Peak WPM | {} | {} | {} |",
+        optional(peak(previous), wpm),
+        optional(peak(week), wpm),
+        optional(peak(week).zip(peak(previous)).map(|(w, p)| w - p), |d| signed_f64(d, 1))
+    );
+    let _ = writeln!(
+        out,
+        "| Error rate | {} | {} | {} |",
+        optional(previous.error_rate, |v| format!("{:.1}%", v * 100.0)),
+        optional(week.error_rate, |v| format!("{:.1}%", v * 100.0)),
+        optional(cmp.error_rate_delta(), |d| format!("{} pp", signed_f64(d, 1)))
+    );
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "## Days");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "| Day | Keystrokes | Sessions | WPM | Busiest hour |");
+    let _ = writeln!(out, "|-----|------------|----------|-----|--------------|");
+    for day in monday.iter_days().take(7) {
+        let Some(stats_of_day) = stats.daily_stats.get(&day_key(day)) else {
+            let _ = writeln!(out, "| {} | — | — | — | — |", day.format("%a %m-%d"));
+            continue;
+        };
+        let wpms: Vec<f64> = sessions_between(stats, day, day).filter_map(|s| s.wpm).collect();
+        let average = (!wpms.is_empty()).then(|| wpms.iter().sum::<f64>() / wpms.len() as f64);
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            day.format("%a %m-%d"),
+            format_count(stats_of_day.keystrokes),
+            stats_of_day.sessions.max(sessions_between(stats, day, day).count() as u64),
+            optional(average, wpm),
+            optional(stats_of_day.busiest_hour().or(stats_of_day.most_active_hour), |h| format!("{:02}:00", h))
+        );
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "## Top keys");
+    let _ = writeln!(out);
+    if cmp.top_keys.is_empty() {
+        let _ = writeln!(out, "No keystrokes recorded.");
+    }
+    for (i, change) in cmp.top_keys.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "{}. `{}` - {:.1}% ({} pp)",
+            i + 1,
+            keymap::display_name(&change.key),
+            change.share_b * 100.0,
+            signed_f64(change.delta_points(), 1)
+        );
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "## Actions");
+    let _ = writeln!(out);
+    let rows = action_rows(previous, week);
+    if rows.is_empty() {
+        let _ = writeln!(out, "No shortcuts recorded.");
+    } else {
+        let _ = writeln!(out, "| Action | {} | {} | Δ |", previous_label, label);
+        let _ = writeln!(out, "|--------|------|------|---|");
+    }
+    for (action, count_previous, count_week) in rows {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            action,
+            format_count(count_previous),
+            format_count(count_week),
+            signed(count_week as i64 - count_previous as i64)
+        );
+    }
+
+    for summary in [week, previous] {
+        if let Some(note) = missing_note(summary) {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "Note: {}", note);
+        }
+    }
+
+    out
+}
+
+/// Sessions that started on a local day in `start..=end`.
+fn sessions_between(stats: &KeyStats, start: NaiveDate, end: NaiveDate) -> impl Iterator<Item = &TypingSession> {
+    stats.typing_sessions
+        .iter()
+        .filter(move |s| (start..=end).contains(&s.start.with_timezone(&chrono::Local).date_naive()))
+}
+
+/// Intensity characters of [`ascii_heatmap`], from the least to the most used key.
+pub const HEAT_RAMP: &[u8] = b".:-=+*#%@";
+
+/// Draws the keyboard of the Heatmap tab in plain text, with one
+/// [`HEAT_RAMP`] character after each key for how much it was used.
+///
+/// # Arguments
+/// * `stats` - Statistics whose lifetime key counts are drawn
+///
+/// # Returns
+/// `String` - The keyboard, the space bar and a legend
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::KeyStats;
+/// use ctrlq::report::ascii_heatmap;
+///
+/// let mut stats = KeyStats::new();
+/// stats.add_counts("KEY_30", 100, None); // A
+/// stats.add_counts("KEY_16", 50, None); // Q
+/// stats.add_counts("KEY_57", 1, None); // Space
+///
+/// let heatmap = ascii_heatmap(&stats);
+/// assert!(heatmap.contains("A@"));
+/// assert!(heatmap.contains("Q+"));
+/// assert!(heatmap.contains("SPACE."));
+/// assert!(heatmap.contains("Z "));
+/// ```
+pub fn ascii_heatmap(stats: &KeyStats) -> String {
+    let mut out = String::new();
+    if stats.anonymized {
+        let _ = writeln!(out, "(anonymized data has no per-key counts)");
+        return out;
+    }
+
+    let counts = stats.physical_key_counts();
+    let count = |code: u16| counts.get(&code).copied().unwrap_or(0);
+    let max = keymap::HEATMAP_ROWS
+        .iter()
+        .flat_map(|(_, codes)| codes.iter().copied())
+        .chain([keymap::KEY_SPACE])
+        .map(count)
+        .max()
+        .unwrap_or(0);
+    // Any use at all shows up, only the most used keys get the last character
+    let level = |code: u16| match count(code) {
+        0 => ' ',
+        used => HEAT_RAMP[((used * HEAT_RAMP.len() as u64).div_ceil(max) - 1) as usize] as char,
+    };
+
+    for (indent, (labels, codes)) in keymap::HEATMAP_ROWS.iter().enumerate() {
+        let cells: Vec<String> = labels.chars().zip(codes.iter()).map(|(label, &code)| format!("{}{}", label, level(code))).collect();
+        let _ = writeln!(out, "{}{}", " ".repeat(indent), cells.join(" ").trim_end());
+    }
+    let _ = writeln!(out, "      SPACE{}", level(keymap::KEY_SPACE));
+    let _ = writeln!(
+        out,
+        "Legend: ' ' unused, '{}' least … '{}' most ({} presses)",
+        HEAT_RAMP[0] as char,
+        HEAT_RAMP[HEAT_RAMP.len() - 1] as char,
+        format_count(max)
+    );
+    out
+}
+
+/// ANSI 256-color background of each level in [`console_heatmap`].
+fn heat_background(heat: Heat) -> u8 {
+    match heat {
+        Heat::VeryHigh => 196,
+        Heat::High => 209,
+        Heat::Medium => 220,
+        Heat::Low => 76,
+        Heat::VeryLow => 39,
+        Heat::Unused => 250,
+    }
+}
+
+/// Draws a heat grid for `ctrlq heatmap`: each key with its count on an
+/// ANSI 256-color background, or followed by its [`Heat::symbol`] where
+/// colors are off. Stripped of its colors the output only depends on the
+/// counts, so it can be compared as text.
+///
+/// # Arguments
+/// * `grid` - The keys, see [`HeatGrid::of_counts`]
+/// * `color` - Whether to use ANSI colors
+///
+/// # Returns
+/// `String` - The keyboard, centered, and a legend
+///
+/// # Example
+/// ```
+/// use ctrlq::heatmap::HeatGrid;
+/// use ctrlq::report::console_heatmap;
+/// use std::collections::HashMap;
+///
+/// // E 40 times, Space 25 times, A 10 times
+/// let grid = HeatGrid::of_counts(&HashMap::from([(18, 40), (57, 25), (30, 10)]), None);
+/// assert_eq!(console_heatmap(&grid, false).lines().collect::<Vec<_>>(), [
+///     "  1  2  3  4  5  6  7  8  9  0",
+///     "Q  W  E[40]@ R  T  Y  U  I  O  P",
+///     " A[10]- S  D  F  G  H  J  K  L",
+///     "      Z  X  C  V  B  N  M",
+///     "           SPACE[25]#",
+///     "",
+///     "Legend: '@' Very High, '#' High, '+' Medium, '-' Low, '.' Very Low, ' ' Unused",
+/// ]);
+///
+/// let colored = console_heatmap(&grid, true);
+/// assert!(colored.contains("\x1b[48;5;196m\x1b[38;5;16mE[40] \x1b[0m"));
+/// ```
+pub fn console_heatmap(grid: &HeatGrid<Heat>, color: bool) -> String {
+    let paint = |text: &str, heat: Heat| format!("\x1b[48;5;{}m\x1b[38;5;16m{}\x1b[0m", heat_background(heat), text);
+    // Each line with its width on screen, for centering
+    let mut lines: Vec<(String, usize)> = grid
+        .rows
+        .iter()
+        .map(|row| {
+            let keys: Vec<String> = row
+                .iter()
+                .map(|cell| if color { paint(&format!("{} ", cell.text()), cell.level) } else { format!("{}{}", cell.text(), cell.level.symbol()) })
+                .collect();
+            let width = row.iter().map(|cell| cell.text().chars().count() + 2).sum::<usize>().saturating_sub(1);
+            (keys.join(" "), width)
+        })
+        .collect();
+    let space = if color {
+        paint(&format!("      {}      ", grid.space.text()), grid.space.level)
+    } else {
+        format!("{}{}", grid.space.text(), grid.space.level.symbol())
+    };
+    let space_width = grid.space.text().chars().count() + if color { 12 } else { 1 };
+    lines.push((space, space_width));
+
+    let widest = lines.iter().map(|(_, width)| *width).max().unwrap_or(0);
+    let mut out = String::new();
+    for (line, width) in lines {
+        let _ = writeln!(out, "{}{}", " ".repeat((widest - width) / 2), line.trim_end());
+    }
+    let legend: Vec<String> = Heat::ALL
+        .into_iter()
+        .map(|heat| if color { paint(&format!(" {} ", heat.label()), heat) } else { format!("'{}' {}", heat.symbol(), heat.label()) })
+        .collect();
+    let _ = writeln!(out, "\nLegend: {}", legend.join(if color { " " } else { ", " }));
+    out
+}
+
+/// Renders the plain-text summary written by `--summary-file`: totals,
+/// today, the current session, the top 10 keys and an [`ascii_heatmap`].
+///
+/// # Arguments
+/// * `stats` - Latest statistics from the logger
+/// * `now` - Current local time, shown in the header
+///
+/// # Returns
+/// `String` - The summary text
+pub fn text_summary(stats: &KeyStats, now: chrono::DateTime<chrono::Local>) -> String {
+    let mut out = String::new();
+    let today_stats = stats.daily_stats.get(&day_key(now.date_naive()));
+    let today = today_stats.map_or(0, |day| day.keystrokes);
+
+    let _ = writeln!(out, "CtrlQ summary - {}", now.format("%Y-%m-%d %H:%M"));
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{:<10}{:>10} keystrokes", "Total", format_count(stats.total_keystrokes));
+    let _ = writeln!(out, "{:<10}{:>10} keystrokes", "Today", format_count(today));
+    let _ = writeln!(
+        out,
+        "{:<10}{:>10} keystrokes · {}",
+        "Session",
+        format_count(stats.session_keystrokes),
+        format_wpm(stats.get_wpm())
+    );
+    if let Some(line) = today_stats.and_then(|day| actions::summary_line(&day.actions, 5)) {
+        let _ = writeln!(out, "{:<10}{} today", "Actions", line);
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Top keys");
+    let top_keys = stats.get_top_keys(10);
+    if top_keys.is_empty() {
+        let _ = writeln!(out, "  (no keystrokes)");
+    }
+    for (i, (key, count)) in top_keys.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "  {:>2}. {:<12}{:>10}{:>8}",
+            i + 1,
+            keymap::display_name(key),
+            format_count(*count),
+            format_percent(percent(*count, stats.total_keystrokes), 1)
+        );
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Heatmap");
+    out.push_str(&ascii_heatmap(stats));
+    out
+}
+
+/// Renders what one key distribution costs on each layout.
+///
+/// # Arguments
+/// * `scores` - Scores from [`crate::layout::compare`]
+/// * `typed_on` - Layout the keystrokes were typed on, the baseline of Δ
+///
+/// # Returns
+/// `String` - The report text
+pub fn layout_report(scores: &[(Layout, EffortScore)], typed_on: Layout) -> String {
+    let mut out = String::new();
+    let baseline = scores
+        .iter()
+        .find(|(layout, _)| *layout == typed_on)
+        .and_then(|(_, score)| score.per_keystroke());
+
+    let _ = writeln!(out, "⌨️  Layout effort (typed on {})", typed_on.name());
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{:<14}{:>14}{:>14}{:>12}", "Layout", "Effort/key", "Total", "Δ");
+    for (layout, score) in scores {
+        let per_key = score.per_keystroke();
+        let delta = match (per_key, baseline) {
+            (Some(v), Some(base)) if *layout != typed_on => format!("{}%", signed_f64((v / base - 1.0) * 100.0, 1)),
+            _ => "—".to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{:<14}{:>14}{:>14}{:>12}",
+            layout.name(),
+            optional(per_key, |v| format!("{:.2}", v)),
+            format_count(score.total.round() as u64),
+            delta
+        );
+    }
+
+    let priced = scores.first().map_or(0, |(_, score)| score.keystrokes);
+    let _ = writeln!(out);
+    let _ = write!(
+        out,
+        "Based on {} character keystrokes; space, Enter and modifiers cost the same everywhere.",
+        format_count(priced)
+    );
+    out
+}
+
+/// Renders the same-finger bigrams of one bigram distribution on each layout.
+///
+/// # Arguments
+/// * `rates` - Same-finger bigrams per layout, from
+///   [`crate::layout::same_finger_bigrams`]; the offenders of `typed_on` are listed
+/// * `typed_on` - Layout the keystrokes were typed on
+///
+/// # Returns
+/// `String` - The report text
+///
+/// # Example
+/// ```
+/// use ctrlq::layout::{same_finger_bigrams, Layout};
+/// use ctrlq::report::same_finger_report;
+/// use std::collections::HashMap;
+///
+/// // e→d ×3, a→s ×1 on QWERTY
+/// let bigrams = HashMap::from([("KEY_18 KEY_32".to_string(), 3), ("KEY_30 KEY_31".to_string(), 1)]);
+/// let rates: Vec<_> = Layout::ALL
+///     .iter()
+///     .map(|&layout| (layout, same_finger_bigrams(&bigrams, Layout::Qwerty, layout, 5)))
+///     .collect();
+///
+/// let report = same_finger_report(&rates, Layout::Qwerty);
+/// assert!(report.contains("75.0%"));
+/// assert!(report.contains("ed"));
+/// assert!(report.contains("left middle"));
+/// ```
+pub fn same_finger_report(rates: &[(Layout, SfbStats)], typed_on: Layout) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "🖐️  Same-finger bigrams (typed on {})", typed_on.name());
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{:<14}{:>14}{:>14}", "Layout", "SFB", "Rate");
+    for (layout, sfb) in rates {
+        let _ = writeln!(
+            out,
+            "{:<14}{:>14}{:>14}",
+            layout.name(),
+            format_count(sfb.same_finger),
+            format_percent(sfb.rate().map(|rate| rate * 100.0), 1)
+        );
+    }
+
+    let bigrams = rates.first().map_or(0, |(_, sfb)| sfb.bigrams);
+    if let Some((_, sfb)) = rates.iter().find(|(layout, _)| *layout == typed_on)
+        && !sfb.top.is_empty()
+    {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "Worst pairs on {}:", typed_on.name());
+        for bigram in &sfb.top {
+            let _ = writeln!(
+                out,
+                "  {:<6}{:>10} ({:>6})  {}",
+                bigram.pair,
+                format_count(bigram.count),
+                format_percent(percent(bigram.count, bigrams), 1),
+                bigram.finger.name()
+            );
+        }
+    }
+    let _ = writeln!(out);
+    let _ = write!(
+        out,
+        "Based on {} pairs of character keys typed within a second of each other; repeats of one key count, but not as same-finger.",
+        format_count(bigrams)
+    );
+    out
+}
+
+/// Renders the keys used most unusually much and little against a reference.
+///
+/// # Arguments
+/// * `report` - Ranking from [`crate::surprise::rank`]
+/// * `limit` - Most keys to list on each side
+///
+/// # Returns
+/// `String` - The report text
+///
+/// # Example
+/// ```
+/// use ctrlq::report::surprise_report;
+/// use ctrlq::surprise::{rank, Corpus, Observed};
+/// use std::collections::HashMap;
+///
+/// let counts = HashMap::from([('e', 300), ('t', 200), ('_', 100)]);
+/// let observed = Observed { counts, letters_only: false };
+/// let ranked = rank(&observed, &Corpus::English.load().unwrap()).unwrap();
+///
+/// let report = surprise_report(&ranked, 5);
+/// assert!(report.contains("vs English"));
+/// assert!(report.contains("Used more"));
+/// assert!(report.lines().any(|line| line.trim_start().starts_with('_')));
+/// ```
+pub fn surprise_report(report: &SurpriseReport, limit: usize) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "🔎 Most unusual keys vs {} ({} characters)", report.reference, format_count(report.total));
+
+    let sections: [(&str, Vec<&KeySurprise>); 2] = [
+        ("Used more", report.overused().take(limit).collect()),
+        ("Used less", report.underused().take(limit).collect()),
+    ];
+    for (title, keys) in sections {
+        if keys.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{}", title);
+        let _ = writeln!(out, "  {:<6}{:>10}{:>12}{:>10}{:>10}", "Key", "Yours", "Reference", "Ratio", "Score");
+        for key in keys {
+            let _ = writeln!(
+                out,
+                "  {:<6}{:>10}{:>12}{:>10}{:>10}",
+                key.key,
+                format_percent(Some(key.observed * 100.0), 2),
+                format_percent(Some(key.expected * 100.0), 2),
+                format!("×{:.1}", key.ratio()),
+                signed_f64(key.score, 2)
+            );
+        }
+    }
+
+    if report.letters_only {
+        let _ = writeln!(out);
+        let _ = write!(
+            out,
+            "Only letters were compared: without XKB characters or --case-sensitive, - and _ are one key."
+        );
+    }
+    out.trim_end().to_string()
+}
+
+/// Describes missing days in a period, if any.
+///
+/// # Returns
+/// `Option<String>` - e.g. "no data for 2024-05-01 (counted as zero)"
+pub fn missing_note(summary: &PeriodSummary) -> Option<String> {
+    match summary.missing_days.len() {
+        0 => None,
+        n if n as i64 == summary.day_count() => {
+            Some(format!("no data for {} (counted as zero)", summary.label))
+        }
+        n => Some(format!("{} of {} days in {} have no data", n, summary.day_count(), summary.label)),
+    }
+}
+
+/// Actions of either period with their counts in both, most frequent in
+/// the second period first.
+fn action_rows<'a>(a: &'a PeriodSummary, b: &'a PeriodSummary) -> Vec<(&'a str, u64, u64)> {
+    let count = |summary: &PeriodSummary, action: &str| summary.actions.get(action).copied().unwrap_or(0);
+    let mut rows: Vec<(&str, u64, u64)> = a
+        .actions
+        .keys()
+        .chain(b.actions.keys().filter(|action| !a.actions.contains_key(*action)))
+        .map(|action| (action.as_str(), count(a, action), count(b, action)))
+        .filter(|&(_, count_a, count_b)| count_a + count_b > 0)
+        .collect();
+    rows.sort_by(|x, y| y.2.cmp(&x.2).then(y.1.cmp(&x.1)).then(x.0.cmp(y.0)));
+    rows
+}
+
+fn write_key_changes(out: &mut String, changes: &[KeyChange]) {
+    if changes.is_empty() {
+        let _ = writeln!(out, "  (no keystrokes)");
+        return;
+    }
+
+    for change in changes {
+        let _ = writeln!(
+            out,
+            "  {:<12}{:>7.1}% → {:>5.1}%  ({} pp)",
+            keymap::display_name(&change.key),
+            change.share_a * 100.0,
+            change.share_b * 100.0,
+            signed_f64(change.delta_points(), 1)
+        );
+    }
+}
+
+/// Writes the hours in which either period has a typing speed.
+fn write_speed_by_hour(out: &mut String, a: &[Option<f64>], b: &[Option<f64>]) {
+    let hour = |speeds: &[Option<f64>], hour: usize| speeds.get(hour).copied().flatten();
+    let hours: Vec<usize> = (0..24).filter(|&h| hour(a, h).is_some() || hour(b, h).is_some()).collect();
+    if hours.is_empty() {
+        let _ = writeln!(out, "  (no hour with a minute of typing)");
+        return;
+    }
+
+    for h in hours {
+        let (a, b) = (hour(a, h), hour(b, h));
+        let delta = a.zip(b).map(|(a, b)| b - a);
+        let _ = writeln!(
+            out,
+            "  {:<12}{:>14}{:>14}{:>12}",
+            format!("{:02}:00", h),
+            optional(a, |v| format!("{:.1}", v)),
+            optional(b, |v| format!("{:.1}", v)),
+            optional(delta, |d| signed_f64(d, 1)),
+        );
+    }
+}
+
+fn optional<T>(value: Option<T>, format: impl Fn(T) -> String) -> String {
+    value.map(format).unwrap_or_else(|| "—".to_string())
+}
+
+/// Formats a duration as `H:MM:SS`, or `M:SS` below one hour.
+pub fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Formats the sessions per day of [`SessionPatterns`], saying from which
+/// day on they are known instead of showing zeros for older days.
+///
+/// # Arguments
+/// * `patterns` - Patterns of a period
+/// * `start` - First day of that period
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::keylogger::SessionPatterns;
+/// use ctrlq::report::format_sessions_per_day;
+///
+/// let may_1 = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+/// let may_3 = NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+/// let counted = |per_day, from| SessionPatterns { sessions_per_day: per_day, counted_from: from, ..Default::default() };
+///
+/// assert_eq!(format_sessions_per_day(&counted(Some(4.25), Some(may_1)), may_1), "4.2");
+/// assert_eq!(format_sessions_per_day(&counted(Some(4.25), Some(may_3)), may_1), "4.2 (n/a before 2024-05-03)");
+/// assert_eq!(format_sessions_per_day(&counted(None, Some(may_3)), may_1), "n/a before 2024-05-03");
+/// assert_eq!(format_sessions_per_day(&counted(None, None), may_1), "n/a until a session ends");
+/// ```
+pub fn format_sessions_per_day(patterns: &SessionPatterns, start: NaiveDate) -> String {
+    match (patterns.sessions_per_day, patterns.counted_from) {
+        (_, None) => "n/a until a session ends".to_string(),
+        (None, Some(from)) => format!("n/a before {}", from),
+        (Some(per_day), Some(from)) if from > start => format!("{:.1} (n/a before {})", per_day, from),
+        (Some(per_day), Some(_)) => format!("{:.1}", per_day),
+    }
+}
+
+/// Renders the sessions with one tag, newest last, and their totals.
+///
+/// # Arguments
+/// * `tag` - The tag, for the heading
+/// * `sessions` - Sessions with that tag, see [`TypingSession::tag`]
+///
+/// # Returns
+/// `String` - The report text
+///
+/// # Example
+/// ```
+/// use chrono::{Duration, TimeZone, Utc};
+/// use ctrlq::keylogger::TypingSession;
+/// use ctrlq::report::tag_report;
+///
+/// let session = |day: u32, minutes: i64, wpm: f64, note: Option<&str>| {
+///     let start = Utc.with_ymd_and_hms(2024, 5, day, 8, 0, 0).unwrap();
+///     TypingSession {
+///         start,
+///         end: start + Duration::minutes(minutes),
+///         keystrokes: 100 * minutes as u64,
+///         wpm: Some(wpm),
+///         net_wpm: None,
+///         words: 0,
+///         real_wpm: None,
+///         corrections: 0,
+///         top_keys: Vec::new(),
+///         timeline: Vec::new(),
+///         contexts: Vec::new(),
+///         first_key_secs: None,
+///         warmup: [0; 30],
+///         tag: Some("deep-work".to_string()),
+///         note: note.map(str::to_string),
+///         categories: Default::default(),
+///         dock: None,
+///     }
+/// };
+/// let sessions = [session(1, 30, 40.0, Some("parser rewrite")), session(2, 90, 60.0, None)];
+///
+/// let report = tag_report("deep-work", &sessions.iter().collect::<Vec<_>>());
+/// assert!(report.contains("parser rewrite"));
+/// // 2 hours in total; the longer session weighs more in the average
+/// assert!(report.contains("2 sessions, 2:00:00, 12,000 keystrokes, 55.0 WPM"));
+/// ```
+pub fn tag_report(tag: &str, sessions: &[&TypingSession]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "🏷️  Sessions tagged {}", tag);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{:<18}{:>10}{:>12}{:>8}  Note", "Start", "Duration", "Keystrokes", "WPM");
+    let mut total = std::time::Duration::ZERO;
+    let (mut keystrokes, mut weighted_wpm, mut wpm_secs) = (0, 0.0, 0.0);
+    for session in sessions {
+        let length = (session.end - session.start).to_std().unwrap_or_default();
+        total += length;
+        keystrokes += session.keystrokes;
+        if let Some(wpm) = session.wpm {
+            weighted_wpm += wpm * length.as_secs_f64();
+            wpm_secs += length.as_secs_f64();
+        }
+        let _ = writeln!(
+            out,
+            "{:<18}{:>10}{:>12}{:>8}  {}",
+            session.start.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            format_duration(length),
+            format_count(session.keystrokes),
+            optional(session.wpm, |v| format!("{:.1}", v)),
+            session.note.as_deref().unwrap_or("")
+        );
+    }
+    let average = (wpm_secs > 0.0).then(|| weighted_wpm / wpm_secs);
+    let _ = writeln!(out);
+    let _ = write!(
+        out,
+        "{} session{}, {}, {} keystrokes, {} WPM",
+        sessions.len(),
+        if sessions.len() == 1 { "" } else { "s" },
+        format_duration(total),
+        format_count(keystrokes),
+        optional(average, |v| format!("{:.1}", v))
+    );
+    out
+}
+
+/// Renders a single session as a Markdown report.
+///
+/// # Arguments
+/// * `session` - The session to report on
+///
+/// # Returns
+/// `String` - The report text
+pub fn session_report(session: &TypingSession) -> String {
+    let mut out = String::new();
+    let length = (session.end - session.start).to_std().unwrap_or_default();
+
+    let _ = writeln!(out, "# CtrlQ session report");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "| Metric | Value |");
+    let _ = writeln!(out, "|--------|-------|");
+    let _ = writeln!(out, "| Start | {} |", session.start.format("%Y-%m-%d %H:%M:%S UTC"));
+    let _ = writeln!(out, "| End | {} |", session.end.format("%Y-%m-%d %H:%M:%S UTC"));
+    let _ = writeln!(out, "| Duration | {} |", format_duration(length));
+    let _ = writeln!(out, "| Keystrokes | {} |", format_count(session.keystrokes));
+    let _ = writeln!(out, "| WPM | {} |", optional(session.wpm, |v| format!("{:.1}", v)));
+    let _ = writeln!(out, "| Net WPM | {} |", optional(session.net_wpm, |v| format!("{:.1}", v)));
+    let _ = writeln!(out, "| Words | {} |", format_count(session.words));
+    let _ = writeln!(out, "| Real WPM | {} |", optional(session.real_wpm, |v| format!("{:.1}", v)));
+    let _ = writeln!(out, "| Corrections | {} |", session.corrections);
+    if let Some(tag) = &session.tag {
+        let _ = writeln!(out, "| Tag | {} |", tag);
+    }
+    if let Some(note) = &session.note {
+        let _ = writeln!(out, "| Note | {} |", note.replace('|', "\\|"));
+    }
+    if let Some(dock) = session.dock {
+        let _ = writeln!(out, "| Dock | {} |", dock);
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "## Top keys");
+    let _ = writeln!(out);
+    let top_keys = session_top_keys(session);
+    if top_keys.is_empty() {
+        let _ = writeln!(out, "No keystrokes recorded.");
+    }
+    for (i, (key, count)) in top_keys.iter().enumerate() {
+        let _ = writeln!(out, "{}. `{}` - {}", i + 1, key, format_count(*count));
+    }
+
+    // Only sessions that used `ctrlq context` have more than the default context
+    if session.contexts.iter().any(|(context, _)| context != DEFAULT_CONTEXT) {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "## Contexts");
+        let _ = writeln!(out);
+        for (context, count) in &session.contexts {
+            let _ = writeln!(
+                out,
+                "- {} - {} ({})",
+                context,
+                format_count(*count),
+                format_percent(percent(*count, session.keystrokes), 0)
+            );
+        }
+    }
+
+    out
+}
+
+/// Writes a session report to a file, as JSON for `.json` files and Markdown otherwise.
+///
+/// # Arguments
+/// * `path` - File to write
+/// * `session` - The session to report on
+///
+/// # Returns
+/// `Result<()>` - Success or an error naming the file
+pub fn write_session_export(path: &Path, session: &TypingSession) -> Result<()> {
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let content = if is_json {
+        serde_json::to_string_pretty(&SessionExport {
+            session,
+            top_keys: session_top_keys(session),
+        })?
+    } else {
+        session_report(session)
+    };
+
+    std::fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Returns a session's top keys with display names instead of stored names.
+fn session_top_keys(session: &TypingSession) -> Vec<(String, u64)> {
+    session.top_keys
+        .iter()
+        .map(|(key, count)| (keymap::display_name(key), *count))
+        .collect()
+}
@@ -0,0 +1,308 @@
+//! # Schedule Module
+//!
+//! Hours in which keystrokes are logged at all, from `log_hours` in the
+//! config. Without it everything is logged.
+//!
+//! ```toml
+//! # Every day
+//! log_hours = "09:00-18:00"
+//!
+//! # Or by weekday; days left out use `default`, which is all day if unset
+//! [log_hours]
+//! default = "09:00-18:00"
+//! fri = "09:00-14:00"
+//! sat = "off"
+//! sun = "off"
+//! ```
+//!
+//! The hours of a day are one or more `HH:MM-HH:MM` windows separated by
+//! commas, such as `"09:00-12:00, 13:00-18:00"`, `all day` or `off`. A
+//! window that ends earlier than it starts runs past midnight and belongs to
+//! the day it starts on: `fri = "22:00-02:00"` logs early Saturday morning
+//! even if Saturday is `off`.
+//!
+//! Outside the hours key events are read but dropped, as while paused, and
+//! the session in progress is closed, so no session spans the edge of a
+//! window. Windows follow the local wall clock: on the night the clocks
+//! skip an hour, a window starting in the skipped hour opens right after
+//! it, and a window covering the hour that repeats in autumn stays open
+//! both times. The `o` key of the UI (`outside_hours` in `[keys]`) logs
+//! outside the hours for the rest of the day.
+
+use crate::ignore::TimeWindow;
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Seconds in a day on the wall clock.
+const DAY_SECONDS: i64 = 24 * 3600;
+
+/// Names of the weekdays in the `[log_hours]` table, Monday first.
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// The logging hours of one day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hours {
+    /// Logged all day
+    AllDay,
+    /// Not logged, apart from a window of the day before running past midnight
+    Off,
+    /// Logged in these windows, which don't overlap
+    Windows(Vec<TimeWindow>),
+}
+
+impl Hours {
+    /// Parses the hours of a day: windows such as `09:00-18:00` separated by
+    /// commas, `all day` or `off`.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::schedule::Hours;
+    ///
+    /// assert_eq!(Hours::parse("off").unwrap(), Hours::Off);
+    /// assert_eq!(Hours::parse("09:00-12:00, 13:00-18:00").unwrap().to_string(), "09:00-12:00, 13:00-18:00");
+    /// assert!(Hours::parse("09:00").is_err());
+    /// assert!(Hours::parse("09:00-18:00, 17:00-19:00").is_err());
+    /// ```
+    pub fn parse(text: &str) -> Result<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "off" => return Ok(Self::Off),
+            "all day" => return Ok(Self::AllDay),
+            _ => {}
+        }
+        let windows = text
+            .split(',')
+            .map(|window| match window.split_once('-') {
+                Some((start, end)) => TimeWindow::parse(start, end),
+                None => bail!("invalid hours '{}', expected HH:MM-HH:MM such as 09:00-18:00, all day or off", window.trim()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (index, window) in windows.iter().enumerate() {
+            if let Some(other) = windows[index + 1..].iter().find(|other| window.overlaps(other)) {
+                bail!("hours {} and {} overlap, merge them into one", window, other);
+            }
+        }
+        Ok(Self::Windows(windows))
+    }
+
+    /// Whether a time of the day is logged, leaving out windows of the day
+    /// before.
+    fn contains(&self, time: NaiveTime) -> bool {
+        match self {
+            Self::AllDay => true,
+            Self::Off => false,
+            Self::Windows(windows) => windows
+                .iter()
+                .any(|window| if window.start < window.end { window.contains(time) } else { time >= window.start }),
+        }
+    }
+
+    /// Whether a time of the day is still logged by a window of this day
+    /// running past midnight.
+    fn runs_into(&self, time: NaiveTime) -> bool {
+        match self {
+            Self::Windows(windows) => windows.iter().any(|window| window.start > window.end && time < window.end),
+            Self::AllDay | Self::Off => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Hours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AllDay => write!(f, "all day"),
+            Self::Off => write!(f, "off"),
+            Self::Windows(windows) => {
+                let windows: Vec<String> = windows.iter().map(TimeWindow::to_string).collect();
+                write!(f, "{}", windows.join(", "))
+            }
+        }
+    }
+}
+
+/// `log_hours` as written in the config: the hours of every day, or a table
+/// by weekday.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawSchedule {
+    /// `log_hours = "09:00-18:00"`
+    Every(String),
+    /// `[log_hours]` with `default` and `mon` to `sun`
+    ByDay(BTreeMap<String, String>),
+}
+
+/// Logging hours by weekday, see the module docs.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::config::Config;
+///
+/// let config: Config = toml::from_str(r#"
+///     [log_hours]
+///     default = "09:00-18:00"
+///     fri = "22:00-02:00"
+///     sat = "off"
+/// "#)?;
+/// let hours = config.log_hours.unwrap();
+/// let at = |day, time: &str| NaiveDate::from_ymd_opt(2024, 6, day).unwrap().and_time(time.parse().unwrap());
+///
+/// // Thursday the 6th
+/// assert!(hours.contains(at(6, "09:00:00")));
+/// assert!(!hours.contains(at(6, "18:00:00")));
+/// // Friday is only logged at night, into Saturday, which is off otherwise
+/// assert!(!hours.contains(at(7, "12:00:00")));
+/// assert!(hours.contains(at(7, "23:30:00")));
+/// assert!(hours.contains(at(8, "01:59:00")));
+/// assert!(!hours.contains(at(8, "02:00:00")));
+/// assert!(!hours.contains(at(8, "12:00:00")));
+/// // Sunday uses the default
+/// assert!(hours.contains(at(9, "12:00:00")));
+/// assert_eq!(hours.hours_on(at(8, "12:00:00").date()).to_string(), "off");
+///
+/// assert!(toml::from_str::<Config>("log_hours = \"9-18\"").is_err());
+/// assert!(toml::from_str::<Config>("[log_hours]\nfriday = \"off\"").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// Hours follow the wall clock across DST changes:
+/// ```
+/// use chrono::{FixedOffset, NaiveDate, TimeZone};
+/// use ctrlq::schedule::Schedule;
+///
+/// let early = Schedule::parse("02:30-06:00")?;
+/// let late = Schedule::parse("22:00-02:30")?;
+/// let (winter, summer) = (FixedOffset::east_opt(3600).unwrap(), FixedOffset::east_opt(7200).unwrap());
+/// let utc = |hour, minute| NaiveDate::from_ymd_opt(2024, 3, 31).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+///
+/// // Clocks go from 02:00 to 03:00: 01:59 is followed by 03:00
+/// let before = winter.from_utc_datetime(&utc(0, 59)).naive_local();
+/// let after = summer.from_utc_datetime(&utc(1, 0)).naive_local();
+/// assert_eq!(after.format("%H:%M").to_string(), "03:00");
+/// assert!(!early.contains(before) && early.contains(after));
+/// assert!(late.contains(before) && !late.contains(after));
+///
+/// // Back from 03:00 to 02:00 in October: 02:15 comes twice, logged both times
+/// let utc = |hour, minute| NaiveDate::from_ymd_opt(2024, 10, 27).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+/// let (first, second) = (summer.from_utc_datetime(&utc(0, 15)), winter.from_utc_datetime(&utc(1, 15)));
+/// assert_eq!(first.naive_local(), second.naive_local());
+/// assert!(late.contains(first.naive_local()) && late.contains(second.naive_local()));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawSchedule", into = "RawSchedule")]
+pub struct Schedule {
+    /// Hours of each weekday, Monday first
+    days: [Hours; 7],
+    /// The schedule as written, for saving the config again
+    raw: RawSchedule,
+}
+
+impl TryFrom<RawSchedule> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawSchedule) -> Result<Self> {
+        let days = match &raw {
+            RawSchedule::Every(hours) => {
+                let hours = Hours::parse(hours)?;
+                std::array::from_fn(|_| hours.clone())
+            }
+            RawSchedule::ByDay(table) => {
+                if let Some(name) = table.keys().find(|name| *name != "default" && !DAY_NAMES.contains(&name.as_str())) {
+                    bail!("unknown day '{}' in log_hours, expected default or one of {}", name, DAY_NAMES.join(", "));
+                }
+                let parse = |name: &str| {
+                    table
+                        .get(name)
+                        .map(|hours| Hours::parse(hours).with_context(|| format!("invalid log_hours for {}", name)))
+                        .transpose()
+                };
+                let default = parse("default")?.unwrap_or(Hours::AllDay);
+                let mut days: [Hours; 7] = std::array::from_fn(|_| default.clone());
+                for (day, name) in days.iter_mut().zip(DAY_NAMES) {
+                    if let Some(hours) = parse(name)? {
+                        *day = hours;
+                    }
+                }
+                days
+            }
+        };
+        Ok(Self { days, raw })
+    }
+}
+
+impl From<Schedule> for RawSchedule {
+    fn from(schedule: Schedule) -> Self {
+        schedule.raw
+    }
+}
+
+impl Schedule {
+    /// Parses the same hours for every day, as `log_hours = "..."` does.
+    pub fn parse(hours: &str) -> Result<Self> {
+        Self::try_from(RawSchedule::Every(hours.to_string()))
+    }
+
+    /// Returns the hours of a weekday.
+    pub fn hours(&self, weekday: Weekday) -> &Hours {
+        &self.days[weekday.num_days_from_monday() as usize]
+    }
+
+    /// Returns the hours of the weekday of a date.
+    pub fn hours_on(&self, date: chrono::NaiveDate) -> &Hours {
+        self.hours(date.weekday())
+    }
+
+    /// Seconds of a day that are logged, counting only the part before
+    /// `until` and windows of the day before running past midnight.
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use ctrlq::schedule::Schedule;
+    ///
+    /// let hours = Schedule::parse("09:00-12:00, 22:00-02:00")?;
+    /// let day = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+    /// let next = day.succ_opt().unwrap().and_hms_opt(0, 0, 0).unwrap();
+    ///
+    /// // 2 hours after midnight, 3 in the morning and 2 before midnight
+    /// assert_eq!(Schedule::parse("off")?.logged_seconds(day, next), 0);
+    /// assert_eq!(hours.logged_seconds(day, next), 7 * 3600);
+    /// assert_eq!(hours.logged_seconds(day, day.and_hms_opt(10, 30, 0).unwrap()), (2 * 60 + 90) * 60);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn logged_seconds(&self, date: chrono::NaiveDate, until: NaiveDateTime) -> u64 {
+        let second = |time: NaiveTime| i64::from(time.num_seconds_from_midnight());
+        let mut spans = Vec::new();
+        match self.hours_on(date) {
+            Hours::AllDay => spans.push((0, DAY_SECONDS)),
+            Hours::Off => {}
+            Hours::Windows(windows) => spans.extend(windows.iter().map(|window| {
+                let end = if window.start < window.end { second(window.end) } else { DAY_SECONDS };
+                (second(window.start), end)
+            })),
+        }
+        if let Some(Hours::Windows(windows)) = date.pred_opt().map(|yesterday| self.hours_on(yesterday)) {
+            spans.extend(windows.iter().filter(|window| window.start > window.end).map(|window| (0, second(window.end))));
+        }
+        let limit = (until - date.and_time(NaiveTime::MIN)).num_seconds();
+        spans.into_iter().map(|(start, end)| (end.min(limit) - start).max(0) as u64).sum()
+    }
+
+    /// Seconds of a day before `until`, the logged time without
+    /// `log_hours`.
+    pub fn all_day_seconds(date: chrono::NaiveDate, until: NaiveDateTime) -> u64 {
+        (until - date.and_time(NaiveTime::MIN)).num_seconds().clamp(0, DAY_SECONDS) as u64
+    }
+
+    /// Whether keystrokes are logged at a local wall-clock time, by the
+    /// hours of its day or a window of the day before running past midnight.
+    ///
+    /// # Arguments
+    /// * `at` - Local date and time, e.g. `chrono::Local::now().naive_local()`
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        let yesterday = at.date().pred_opt().map(|date| self.hours_on(date));
+        self.hours_on(at.date()).contains(at.time()) || yesterday.is_some_and(|hours| hours.runs_into(at.time()))
+    }
+}
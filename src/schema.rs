@@ -0,0 +1,535 @@
+//! # Schema Module
+//!
+//! The machine-readable output of `ctrlq stats --json` and `ctrlq export`.
+//! These types are deliberately separate from [`KeyStats`]: the data file
+//! may change with any release, this output only changes together with
+//! [`SCHEMA_VERSION`] (or [`SESSIONS_JSONL_VERSION`] for the export).
+//!
+//! ## Stability
+//! Within one schema version, fields are never renamed, removed or given a
+//! different type; new fields may be added. Values that can be unknown
+//! (e.g. WPM of a session without enough typing) are `null`, never missing.
+//! A section left out with `--fields` is missing entirely.
+//!
+//! `docs/stats-json-v1.json` shows a complete document of version 1.
+//!
+//! ## Sessions JSONL
+//! `ctrlq export --format sessions-jsonl` writes one JSON object per line: a
+//! [`SessionsHeader`] first, then a [`SessionLine`] per session, oldest
+//! first. Lines are written one at a time, so thousands of sessions never
+//! sit in memory as JSON.
+//!
+//! ```text
+//! {"schema_version":1,"format":"sessions-jsonl","sessions":2}
+//! {"start":"2024-05-01T09:00:00Z","end":"2024-05-01T09:42:00Z",...,"minutes":[31,58,...]}
+//! ```
+
+use crate::keylogger::{day_key, Dock, KeyStats, Record, TypingSession};
+use crate::layout::{self, Layout};
+use anyhow::{bail, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Version of the output schema, bumped on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Version of the sessions JSONL export, bumped on incompatible changes.
+pub const SESSIONS_JSONL_VERSION: u32 = 1;
+
+/// Number of keys in the `top_keys` section.
+pub const TOP_KEYS: usize = 10;
+
+/// Number of pairs in the `same_finger_bigrams` section.
+pub const TOP_SAME_FINGER: usize = 10;
+
+/// A section of the output that `--fields` can select.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    /// Lifetime totals
+    Summary,
+    /// Most pressed keys
+    TopKeys,
+    /// One entry per recorded day
+    Daily,
+    /// Finished typing sessions
+    Sessions,
+    /// Personal records
+    Records,
+    /// Same-finger bigrams, typed on QWERTY
+    SameFingerBigrams,
+}
+
+impl Section {
+    /// All sections, in output order.
+    pub const ALL: [Section; 6] = [
+        Section::Summary,
+        Section::TopKeys,
+        Section::Daily,
+        Section::Sessions,
+        Section::Records,
+        Section::SameFingerBigrams,
+    ];
+
+    /// Name of the section as used in the output and by `--fields`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Section::Summary => "summary",
+            Section::TopKeys => "top_keys",
+            Section::Daily => "daily",
+            Section::Sessions => "sessions",
+            Section::Records => "records",
+            Section::SameFingerBigrams => "same_finger_bigrams",
+        }
+    }
+
+    /// Parses a comma-separated list of section names.
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::schema::Section;
+    ///
+    /// let sections = Section::parse_list("summary, records").unwrap();
+    /// assert_eq!(sections, vec![Section::Summary, Section::Records]);
+    /// assert!(Section::parse_list("summary,keys").is_err());
+    /// ```
+    pub fn parse_list(list: &str) -> Result<Vec<Section>> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| match Section::ALL.iter().find(|section| section.name() == name) {
+                Some(&section) => Ok(section),
+                None => {
+                    let known: Vec<&str> = Section::ALL.iter().map(|section| section.name()).collect();
+                    bail!("unknown field '{}': expected {}", name, known.join(", "))
+                }
+            })
+            .collect()
+    }
+}
+
+/// The document printed by `ctrlq stats --json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsOutput {
+    /// Always [`SCHEMA_VERSION`]
+    pub schema_version: u32,
+    /// Lifetime totals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SummaryOutput>,
+    /// Most pressed keys, most used first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_keys: Option<Vec<KeyOutput>>,
+    /// Recorded days, oldest first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<Vec<DayOutput>>,
+    /// Finished typing sessions, oldest first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<Vec<SessionOutput>>,
+    /// Personal records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub records: Option<RecordsOutput>,
+    /// Same-finger bigrams
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_finger_bigrams: Option<SameFingerOutput>,
+}
+
+/// Lifetime totals.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SummaryOutput {
+    /// Keystrokes ever recorded
+    pub total_keystrokes: u64,
+    /// Distinct keys ever pressed
+    pub unique_keys: usize,
+    /// Finished typing sessions
+    pub sessions: usize,
+    /// Days with statistics
+    pub days_recorded: usize,
+    /// Keystrokes on the current local day
+    pub today_keystrokes: u64,
+}
+
+/// One of the most pressed keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyOutput {
+    /// Stored key name (e.g. `KEY_30`)
+    pub key: String,
+    /// Name for display (e.g. `A`)
+    pub label: String,
+    /// Times pressed
+    pub count: u64,
+}
+
+/// Statistics of one day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DayOutput {
+    /// Local date, `YYYY-MM-DD`
+    pub date: String,
+    /// Keystrokes on that day
+    pub keystrokes: u64,
+    /// Hour (0-23) with the most keystrokes, if known
+    pub most_active_hour: Option<u8>,
+    /// Words typed, counted at word boundaries
+    pub words: u64,
+    /// Lines typed (Enter after typing)
+    pub lines: u64,
+}
+
+/// One finished typing session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionOutput {
+    /// Start time (RFC 3339, UTC)
+    pub start: DateTime<Utc>,
+    /// End time (RFC 3339, UTC)
+    pub end: DateTime<Utc>,
+    /// Length in seconds
+    pub duration_secs: i64,
+    /// Keystrokes in the session
+    pub keystrokes: u64,
+    /// Words per minute, if the session was long enough
+    pub wpm: Option<f64>,
+    /// Words per minute after corrections, if the session was long enough
+    pub net_wpm: Option<f64>,
+    /// Backspace presses
+    pub corrections: u64,
+    /// Words typed, counted at word boundaries
+    pub words: u64,
+    /// Words per minute based on `words`, if any were typed
+    pub real_wpm: Option<f64>,
+    /// Tag given with `:tag`, if any
+    pub tag: Option<String>,
+    /// Note given with the tag, if any
+    pub note: Option<String>,
+    /// `docked` or `mobile`, if it was typed on a laptop, see [`Dock`]
+    #[serde(default)]
+    pub dock: Option<Dock>,
+}
+
+impl From<&TypingSession> for SessionOutput {
+    fn from(session: &TypingSession) -> Self {
+        Self {
+            start: session.start,
+            end: session.end,
+            duration_secs: session.duration().num_seconds(),
+            keystrokes: session.keystrokes,
+            wpm: session.wpm,
+            net_wpm: session.net_wpm,
+            corrections: session.corrections,
+            words: session.words,
+            real_wpm: session.real_wpm,
+            tag: session.tag.clone(),
+            note: session.note.clone(),
+            dock: session.dock,
+        }
+    }
+}
+
+/// First line of the sessions JSONL export.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionsHeader {
+    /// Always [`SESSIONS_JSONL_VERSION`]
+    pub schema_version: u32,
+    /// Always `sessions-jsonl`
+    pub format: String,
+    /// Session lines that follow
+    pub sessions: usize,
+}
+
+/// One session of the sessions JSONL export: the fields of
+/// [`SessionOutput`] and its details.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionLine {
+    /// The session as in `ctrlq stats --json`
+    #[serde(flatten)]
+    pub session: SessionOutput,
+    /// Share of keystrokes that were Backspace (0.0-1.0), if any keys were pressed
+    pub error_rate: Option<f64>,
+    /// Whether the session was still going on when exported (`--include-open`)
+    pub open: bool,
+    /// Most pressed keys, most used first; empty for the open session
+    pub top_keys: Vec<KeyOutput>,
+    /// Keystrokes in each minute from the start
+    pub minutes: Vec<u32>,
+    /// Keystrokes per context (project), most used first
+    pub contexts: Vec<ContextOutput>,
+}
+
+/// Keystrokes of a session in one context.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextOutput {
+    /// Name of the context, see `ctrlq context`
+    pub context: String,
+    /// Keystrokes typed in it
+    pub count: u64,
+}
+
+impl SessionLine {
+    /// Describes a session, naming its keys like the statistics do.
+    ///
+    /// # Arguments
+    /// * `session` - The session
+    /// * `open` - Whether it is still going on
+    /// * `stats` - Statistics it belongs to, for key labels
+    pub fn new(session: &TypingSession, open: bool, stats: &KeyStats) -> Self {
+        Self {
+            session: session.into(),
+            error_rate: session.error_rate(),
+            open,
+            top_keys: session
+                .top_keys
+                .iter()
+                .map(|(key, count)| KeyOutput { key: key.clone(), label: stats.key_label(key), count: *count })
+                .collect(),
+            minutes: session.timeline.clone(),
+            contexts: session
+                .contexts
+                .iter()
+                .map(|(context, count)| ContextOutput { context: context.clone(), count: *count })
+                .collect(),
+        }
+    }
+}
+
+/// Writes the finished sessions of the statistics, and optionally the open
+/// one, as sessions JSONL (see the module docs), one line at a time.
+///
+/// # Arguments
+/// * `out` - Where to write, e.g. a buffered file
+/// * `stats` - Statistics whose finished sessions to write, oldest first
+/// * `open` - The session in progress, written last, if it is wanted
+///
+/// # Returns
+/// `Result<usize>` - Sessions written, or the first write error
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::KeyStats;
+/// use ctrlq::keymap;
+/// use ctrlq::schema::{write_sessions_jsonl, SessionLine, SessionsHeader};
+///
+/// let mut stats = KeyStats::new();
+/// for _ in 0..5 {
+///     stats.add_keypress(&keymap::key_name(30));
+/// }
+/// stats.end_session();
+/// stats.add_keypress(&keymap::key_name(48));
+///
+/// let mut out = Vec::new();
+/// let open = stats.current_session();
+/// assert_eq!(write_sessions_jsonl(&mut out, &stats, Some(&open)).unwrap(), 2);
+///
+/// let text = String::from_utf8(out).unwrap();
+/// let mut lines = text.lines();
+/// let header: SessionsHeader = serde_json::from_str(lines.next().unwrap()).unwrap();
+/// assert_eq!((header.schema_version, header.sessions), (1, 2));
+///
+/// let first: SessionLine = serde_json::from_str(lines.next().unwrap()).unwrap();
+/// assert_eq!(first.session.keystrokes, 5);
+/// assert_eq!(first.top_keys[0].label, "A");
+/// assert_eq!(first.minutes, vec![5]);
+/// assert!(!first.open);
+///
+/// let last: SessionLine = serde_json::from_str(lines.next().unwrap()).unwrap();
+/// assert!(last.open);
+/// assert!(lines.next().is_none());
+/// ```
+pub fn write_sessions_jsonl(out: &mut impl Write, stats: &KeyStats, open: Option<&TypingSession>) -> Result<usize> {
+    let header = SessionsHeader {
+        schema_version: SESSIONS_JSONL_VERSION,
+        format: "sessions-jsonl".to_string(),
+        sessions: stats.typing_sessions.len() + usize::from(open.is_some()),
+    };
+    serde_json::to_writer(&mut *out, &header)?;
+    writeln!(out)?;
+
+    let sessions = stats.typing_sessions.iter().map(|session| (session, false));
+    for (session, is_open) in sessions.chain(open.map(|session| (session, true))) {
+        serde_json::to_writer(&mut *out, &SessionLine::new(session, is_open, stats))?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(header.sessions)
+}
+
+/// Personal records; a record not set yet is `null`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordsOutput {
+    /// Highest WPM over one minute
+    pub best_wpm_1m: Option<RecordOutput<f64>>,
+    /// Highest WPM over five minutes
+    pub best_wpm_5m: Option<RecordOutput<f64>>,
+    /// Most keystrokes in a day
+    pub most_keystrokes_day: Option<RecordOutput<u64>>,
+    /// Longest session in seconds
+    pub longest_session_secs: Option<RecordOutput<u64>>,
+}
+
+/// Same-finger bigrams, assuming the keys were typed on QWERTY.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SameFingerOutput {
+    /// Pairs of character keys typed within a second of each other
+    pub bigrams: u64,
+    /// Pairs of two different keys pressed by the same finger
+    pub same_finger: u64,
+    /// `same_finger / bigrams`, if there are any bigrams
+    pub rate: Option<f64>,
+    /// The most typed same-finger pairs, most typed first
+    pub top: Vec<SameFingerBigramOutput>,
+}
+
+/// One pair of keys typed with the same finger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SameFingerBigramOutput {
+    /// The two characters in typing order (e.g. `ed`)
+    pub pair: String,
+    /// Stored names of the two keys (e.g. `KEY_18`, `KEY_32`)
+    pub keys: [String; 2],
+    /// The finger (e.g. `left middle`)
+    pub finger: String,
+    /// Times typed
+    pub count: u64,
+}
+
+/// A personal record and when it was set.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecordOutput<T> {
+    /// The record value
+    pub value: T,
+    /// When it was set (RFC 3339, UTC)
+    pub achieved: DateTime<Utc>,
+}
+
+impl<T: Copy> From<Record<T>> for RecordOutput<T> {
+    fn from(record: Record<T>) -> Self {
+        Self { value: record.value, achieved: record.achieved }
+    }
+}
+
+impl StatsOutput {
+    /// Builds the output document from statistics.
+    ///
+    /// # Arguments
+    /// * `stats` - Statistics, usually with the archive loaded
+    /// * `today` - Current local date, for `today_keystrokes`
+    /// * `sections` - Sections to include
+    ///
+    /// # Example
+    /// The golden file in `docs/` is exactly what these statistics produce,
+    /// so any change to the output shape shows up here:
+    /// ```
+    /// use chrono::{Local, NaiveDate, TimeZone, Utc};
+    /// use ctrlq::keylogger::{KeyStats, Record, TypingSession};
+    /// use ctrlq::schema::{Section, StatsOutput};
+    ///
+    /// let mut stats = KeyStats::new();
+    /// // Stored key names are evdev codes: A, A, Space, then Backspace
+    /// for key in ["KEY_30", "KEY_30", "KEY_57"] {
+    ///     stats.add_keypress_at(key, Local.with_ymd_and_hms(2024, 5, 1, 9, 30, 0).unwrap());
+    /// }
+    /// stats.add_keypress_at("KEY_14", Local.with_ymd_and_hms(2024, 5, 2, 14, 0, 0).unwrap());
+    /// stats.finish_day(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap());
+    /// stats.typing_sessions.push(TypingSession {
+    ///     start: Utc.with_ymd_and_hms(2024, 5, 1, 7, 30, 0).unwrap(),
+    ///     end: Utc.with_ymd_and_hms(2024, 5, 1, 7, 45, 0).unwrap(),
+    ///     keystrokes: 3,
+    ///     wpm: Some(42.5),
+    ///     net_wpm: None,
+    ///     words: 6,
+    ///     real_wpm: Some(0.4),
+    ///     corrections: 0,
+    ///     top_keys: Vec::new(),
+    ///     timeline: Vec::new(),
+    ///     contexts: Vec::new(),
+    ///     first_key_secs: None,
+    ///     warmup: [0; 30],
+    ///     tag: Some("deep-work".to_string()),
+    ///     note: None,
+    ///     categories: Default::default(),
+    ///     dock: None,
+    /// });
+    /// // e→d ×3 (same finger), a→s ×5
+    /// stats.bigram_counts.insert("KEY_18 KEY_32".to_string(), 3);
+    /// stats.bigram_counts.insert("KEY_30 KEY_31".to_string(), 5);
+    /// stats.records.most_keystrokes_day = Some(Record {
+    ///     value: 3,
+    ///     achieved: Utc.with_ymd_and_hms(2024, 5, 1, 22, 0, 0).unwrap(),
+    /// });
+    ///
+    /// let today = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+    /// let output = StatsOutput::from_stats(&stats, today, &Section::ALL);
+    /// let golden: serde_json::Value =
+    ///     serde_json::from_str(include_str!("../docs/stats-json-v1.json")).unwrap();
+    /// assert_eq!(serde_json::to_value(&output).unwrap(), golden);
+    ///
+    /// // Sections left out with --fields are missing, the version never is
+    /// let small = StatsOutput::from_stats(&stats, today, &[Section::Summary]);
+    /// let json = serde_json::to_value(&small).unwrap();
+    /// assert_eq!(json["schema_version"], 1);
+    /// assert_eq!(json["summary"]["today_keystrokes"], 1);
+    /// assert!(json.get("top_keys").is_none());
+    /// ```
+    pub fn from_stats(stats: &KeyStats, today: NaiveDate, sections: &[Section]) -> Self {
+        let wants = |section| sections.contains(&section);
+        let mut days: Vec<_> = stats.daily_stats.iter().collect();
+        days.sort_by(|a, b| a.0.cmp(b.0));
+
+        Self {
+            schema_version: SCHEMA_VERSION,
+            summary: wants(Section::Summary).then(|| SummaryOutput {
+                total_keystrokes: stats.total_keystrokes,
+                unique_keys: stats.key_counts.len(),
+                sessions: stats.typing_sessions.len(),
+                days_recorded: stats.daily_stats.len(),
+                today_keystrokes: stats.daily_stats.get(&day_key(today)).map_or(0, |day| day.keystrokes),
+            }),
+            top_keys: wants(Section::TopKeys).then(|| {
+                stats
+                    .get_top_keys(TOP_KEYS)
+                    .into_iter()
+                    .map(|(key, count)| KeyOutput { label: stats.key_label(&key), key, count })
+                    .collect()
+            }),
+            daily: wants(Section::Daily).then(|| {
+                days.iter()
+                    .map(|(date, day)| DayOutput {
+                        date: date.to_string(),
+                        keystrokes: day.keystrokes,
+                        most_active_hour: day.most_active_hour.or_else(|| day.busiest_hour()),
+                        words: day.words,
+                        lines: day.lines,
+                    })
+                    .collect()
+            }),
+            sessions: wants(Section::Sessions).then(|| {
+                stats
+                    .typing_sessions
+                    .iter()
+                    .map(SessionOutput::from)
+                    .collect()
+            }),
+            records: wants(Section::Records).then(|| RecordsOutput {
+                best_wpm_1m: stats.records.best_wpm_1m.map(Into::into),
+                best_wpm_5m: stats.records.best_wpm_5m.map(Into::into),
+                most_keystrokes_day: stats.records.most_keystrokes_day.map(Into::into),
+                longest_session_secs: stats.records.longest_session_secs.map(Into::into),
+            }),
+            same_finger_bigrams: wants(Section::SameFingerBigrams).then(|| {
+                let sfb = layout::same_finger_bigrams(&stats.bigram_counts, Layout::Qwerty, Layout::Qwerty, TOP_SAME_FINGER);
+                SameFingerOutput {
+                    bigrams: sfb.bigrams,
+                    same_finger: sfb.same_finger,
+                    rate: sfb.rate(),
+                    top: sfb
+                        .top
+                        .into_iter()
+                        .map(|bigram| SameFingerBigramOutput {
+                            pair: bigram.pair,
+                            keys: [bigram.keys.0, bigram.keys.1],
+                            finger: bigram.finger.name().to_string(),
+                            count: bigram.count,
+                        })
+                        .collect(),
+                }
+            }),
+        }
+    }
+}
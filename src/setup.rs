@@ -0,0 +1,265 @@
+//! # Setup Module
+//!
+//! The `ctrlq setup` wizard for first runs. It checks that input devices
+//! can be read (offering to join the `input` group so sudo isn't needed),
+//! finds the keyboard by asking the user to type on it, asks for a data
+//! directory and writes `config.toml`, so that a plain `ctrlq` needs no
+//! flags afterwards.
+
+use anyhow::{bail, Context, Result};
+use ctrlq::config::Config;
+use ctrlq::keylogger::{self, find_keyboard_devices, InputDeviceInfo};
+use ctrlq::paths;
+use ctrlq::privileges::{self, AccessMode, INPUT_GROUP};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+/// Key presses that identify the keyboard being typed on.
+const IDENTIFY_PRESSES: u64 = 3;
+
+/// How long to wait for the user to type on the keyboard.
+const IDENTIFY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Runs the setup wizard.
+///
+/// # Returns
+/// `Result<()>` - Success, or an error if no keyboard could be set up
+pub fn run() -> Result<()> {
+    println!("🧙 CtrlQ setup\n");
+
+    if !check_permissions()? {
+        return Ok(());
+    }
+
+    println!("\n🔍 Scanning for keyboard devices...");
+    let input_dir = paths::input_dir();
+    let devices = find_keyboard_devices(&input_dir)?;
+    if devices.is_empty() {
+        bail!("no keyboard devices found in {}", input_dir.display());
+    }
+    for (i, device) in devices.iter().enumerate() {
+        super::print_device(i + 1, device, Config::default().device_name_width);
+    }
+
+    let mut chosen = Vec::new();
+    loop {
+        let remaining: Vec<InputDeviceInfo> = devices
+            .iter()
+            .filter(|device| !chosen.contains(&device.path))
+            .cloned()
+            .collect();
+        match identify(&remaining)? {
+            Some(path) => chosen.push(path),
+            None if chosen.is_empty() => chosen = super::pick_devices(&devices, Config::default().device_name_width)?,
+            None => {}
+        }
+        if remaining.len() <= 1 || !confirm("Log another keyboard as well?", false)? {
+            break;
+        }
+    }
+
+    let mut config = Config::load().unwrap_or_default();
+    config.devices = chosen.iter().map(|path| keylogger::stable_device_path(path)).collect();
+    config.data_dir = ask_data_dir(config.data_dir.take())?;
+
+    if Config::path().exists()
+        && !confirm(&format!("Overwrite {}? Comments in it are not kept", Config::path().display()), true)?
+    {
+        println!("❌ Setup cancelled, nothing written");
+        return Ok(());
+    }
+    let path = config.save()?;
+
+    println!("\n✅ Wrote {}", path.display());
+    for device in &config.devices {
+        println!("   ⌨️  {}", device);
+    }
+    println!("\n🚀 From now on, just run: ctrlq");
+    Ok(())
+}
+
+/// Checks that input devices are readable and offers to add the user to
+/// the `input` group.
+///
+/// # Returns
+/// `Result<bool>` - Whether the wizard can go on to detect keyboards
+fn check_permissions() -> Result<bool> {
+    let readable = input_devices_readable()?;
+    let access = privileges::access_mode();
+    let sudo_user = privileges::sudo_user().map(|(user, _)| user);
+    let user = sudo_user.clone().or_else(|| std::env::var("USER").ok());
+    let in_group = user.as_deref().is_some_and(in_input_group);
+
+    if readable && access == AccessMode::Capabilities {
+        println!("✅ Input devices are readable through the binary's file capabilities");
+        return Ok(true);
+    } else if readable {
+        println!("✅ Input devices are readable");
+    } else {
+        println!("🔒 Input devices in /dev/input can't be read by this user");
+    }
+
+    if let Some(user) = user.as_deref()
+        && !in_group
+        && (!readable || sudo_user.is_some())
+    {
+        println!("💡 Members of the '{}' group can run ctrlq without sudo", INPUT_GROUP);
+        if confirm(&format!("Add {} to the '{}' group now?", user, INPUT_GROUP), true)? {
+            add_to_input_group(user, sudo_user.is_none())?;
+            println!("✅ Added {} to '{}'", user, INPUT_GROUP);
+            println!("💡 Log out and back in (or run `newgrp {}`) for it to take effect", INPUT_GROUP);
+            if !readable {
+                println!("💡 Then run `ctrlq setup` again");
+                return Ok(false);
+            }
+        }
+    }
+
+    if !readable {
+        for line in privileges::guidance(access, &privileges::current_binary()) {
+            println!("{}", line);
+        }
+        bail!("can't read input devices; run `sudo ctrlq setup` or join the '{}' group", INPUT_GROUP);
+    }
+    if sudo_user.is_some() {
+        println!("⚠️  Running under sudo: the config is written to {}", Config::path().display());
+    }
+    Ok(true)
+}
+
+/// Whether at least one event device can be opened for reading.
+fn input_devices_readable() -> Result<bool> {
+    let mut found = false;
+    let input_dir = paths::input_dir();
+    for entry in std::fs::read_dir(&input_dir).with_context(|| format!("failed to list {}", input_dir.display()))? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("event")) {
+            found = true;
+            if std::fs::File::open(&path).is_ok() {
+                return Ok(true);
+            }
+        }
+    }
+    if !found {
+        bail!("no input devices found in {}", input_dir.display());
+    }
+    Ok(false)
+}
+
+/// Whether a user belongs to the `input` group, according to `id`.
+fn in_input_group(user: &str) -> bool {
+    Command::new("id")
+        .args(["-nG", user])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).split_whitespace().any(|g| g == INPUT_GROUP))
+}
+
+/// Adds a user to the `input` group with `usermod`.
+///
+/// # Arguments
+/// * `user` - User to add
+/// * `with_sudo` - Whether `usermod` needs to run through sudo
+fn add_to_input_group(user: &str, with_sudo: bool) -> Result<()> {
+    let mut command = if with_sudo {
+        let mut command = Command::new("sudo");
+        command.arg("usermod");
+        command
+    } else {
+        Command::new("usermod")
+    };
+    let status = command.args(["-aG", INPUT_GROUP, user]).status()?;
+    if !status.success() {
+        bail!("usermod failed ({})", status);
+    }
+    Ok(())
+}
+
+/// Asks the user to type on their keyboard and reports which device it was.
+///
+/// # Arguments
+/// * `devices` - Candidates to listen on
+///
+/// # Returns
+/// `Result<Option<String>>` - Path of the confirmed device, or None if
+/// nothing was typed or the user rejected the detected device
+fn identify(devices: &[InputDeviceInfo]) -> Result<Option<String>> {
+    println!(
+        "\n⌨️  Press a few keys on the keyboard you want to log ({}s)...",
+        IDENTIFY_TIMEOUT.as_secs()
+    );
+    let paths: Vec<String> = devices.iter().map(|device| device.path.clone()).collect();
+    let detected = keylogger::identify_typing_device(&paths, IDENTIFY_PRESSES, IDENTIFY_TIMEOUT, |path, count| {
+        // Rewrite the line so it always shows the device typed on last
+        print!("\r\x1b[K   👉 {} ({}) - {} key(s)", path, device_name(devices, path), count);
+        let _ = std::io::stdout().flush();
+    })?;
+    println!();
+
+    let Some(path) = detected else {
+        println!("⏱️  No key presses detected");
+        return Ok(None);
+    };
+    println!("🎯 That was {} ({})", path, device_name(devices, &path));
+    Ok(confirm("Log this keyboard?", true)?.then_some(path))
+}
+
+/// Returns the name of the device at `path`, short enough for one line.
+fn device_name(devices: &[InputDeviceInfo], path: &str) -> String {
+    devices
+        .iter()
+        .find(|device| device.path == path)
+        .map_or_else(|| "unnamed".to_string(), |device| device.display_name(Config::default().device_name_width))
+}
+
+/// Asks where to keep the data files.
+///
+/// # Arguments
+/// * `current` - Directory from an existing config, if any
+///
+/// # Returns
+/// `Result<Option<PathBuf>>` - The chosen directory, or None for the default
+fn ask_data_dir(current: Option<PathBuf>) -> Result<Option<PathBuf>> {
+    let default = paths::default_data_dir();
+    let suggested = current.unwrap_or_else(|| default.clone());
+    let answer = ask(&format!("\n📁 Data directory [{}]: ", suggested.display()))?;
+
+    let dir = if answer.is_empty() {
+        suggested
+    } else if let Some(rest) = answer.strip_prefix("~/") {
+        dirs::home_dir().unwrap_or_default().join(rest)
+    } else {
+        PathBuf::from(answer)
+    };
+    std::fs::create_dir_all(&dir)?;
+    Ok((dir != default).then_some(dir))
+}
+
+/// Asks a yes/no question.
+///
+/// # Arguments
+/// * `question` - The question, without the `[Y/n]` hint
+/// * `default` - Answer when the user just presses Enter
+fn confirm(question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    loop {
+        match ask(&format!("{} {} ", question, hint))?.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("❌ Please answer y or n"),
+        }
+    }
+}
+
+/// Prints a prompt and reads one trimmed line from stdin.
+fn ask(prompt: &str) -> Result<String> {
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer)? == 0 {
+        bail!("setup cancelled");
+    }
+    Ok(answer.trim().to_string())
+}
@@ -0,0 +1,301 @@
+//! # Status Line Module
+//!
+//! A one-line status for tmux, waybar and other status bars, printed by
+//! `ctrlq status-line`:
+//!
+//! ```text
+//! ⌨ 12.4k today · 72 WPM
+//! ```
+//!
+//! Status bars run the command every few seconds, so it never reads the data
+//! file. It asks the running logger over the control socket (`status`, see
+//! [`crate::ipc`]) and otherwise falls back to the small status file that
+//! every save writes next to the socket, marking the line as stale.
+//!
+//! The line comes from a template with placeholders in braces; `{{` and `}}`
+//! stand for literal braces:
+//!
+//! - `{today}` - Keystrokes today, compact (`12.4k`)
+//! - `{total}` - Keystrokes over the lifetime, compact
+//! - `{wpm}` - WPM of the current session
+//! - `{goal_pct}` - Percentage of `daily_goal` reached today
+//! - `{streak}` - Days in a row with keystrokes, see [`streak`]
+//!
+//! Values that aren't known, such as `{goal_pct}` without a goal, are `—`.
+
+use crate::keylogger::{day_key, KeyStats};
+use crate::numbers;
+use crate::paths;
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Template used without `--format`.
+pub const DEFAULT_FORMAT: &str = "⌨ {today} today · {wpm} WPM";
+
+/// Put in front of lines read from the status file instead of the logger.
+pub const STALE_MARKER: &str = "⚠";
+
+/// Written for values that aren't known.
+const MISSING: &str = "—";
+
+/// What a status line is made of, as sent by the logger and saved in the
+/// status file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusSnapshot {
+    /// Day the counts of `today` and `streak` belong to
+    pub date: NaiveDate,
+    /// Keystrokes on `date`
+    pub today: u64,
+    /// Keystrokes over the lifetime
+    pub total: u64,
+    /// WPM of the session, if it can be told
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wpm: Option<f64>,
+    /// Days in a row with keystrokes up to `date`
+    pub streak: u32,
+}
+
+impl StatusSnapshot {
+    /// Takes the status from the statistics.
+    ///
+    /// # Arguments
+    /// * `stats` - The statistics
+    /// * `today` - Current local date
+    pub fn from_stats(stats: &KeyStats, today: NaiveDate) -> Self {
+        Self {
+            date: today,
+            today: stats.daily_stats.get(&day_key(today)).map_or(0, |day| day.keystrokes),
+            total: stats.total_keystrokes,
+            wpm: stats.get_wpm(),
+            streak: streak(stats, today),
+        }
+    }
+
+    /// Moves a snapshot taken on an earlier day to `today`: nothing was
+    /// typed today since, and the streak only lasts if it reached yesterday.
+    ///
+    /// # Example
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use ctrlq::status_line::StatusSnapshot;
+    ///
+    /// let day = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+    /// let saved = StatusSnapshot { date: day(1), today: 900, total: 50_000, wpm: Some(64.0), streak: 4 };
+    ///
+    /// let next_day = saved.clone().as_of(day(2));
+    /// assert_eq!((next_day.today, next_day.streak, next_day.total), (0, 4, 50_000));
+    /// assert_eq!(saved.clone().as_of(day(3)).streak, 0);
+    /// assert_eq!(saved.clone().as_of(day(1)), saved);
+    /// ```
+    pub fn as_of(self, today: NaiveDate) -> Self {
+        if self.date >= today {
+            return self;
+        }
+        let yesterday = today.pred_opt().unwrap_or(today);
+        Self {
+            date: today,
+            today: 0,
+            streak: if self.date == yesterday { self.streak } else { 0 },
+            ..self
+        }
+    }
+
+    /// Reads a status file written by [`StatusSnapshot::save`].
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("invalid status file {}", path.display()))
+    }
+
+    /// Writes the status file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Returns the status file belonging to a data file.
+///
+/// # Arguments
+/// * `data_file` - Path of the statistics data file
+///
+/// # Returns
+/// `PathBuf` - e.g. `keystroke_data.status.json` in the state directory, see [`paths`]
+pub fn status_file(data_file: &Path) -> PathBuf {
+    paths::state_file(&paths::state_dir(), data_file, "status.json")
+}
+
+/// Counts the days in a row with keystrokes, ending today, or yesterday
+/// while nothing was typed today yet. Days moved to the archive don't count.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::keylogger::{DayStats, KeyStats};
+/// use ctrlq::status_line::streak;
+///
+/// let mut stats = KeyStats::default();
+/// for day in ["2024-05-01", "2024-05-03", "2024-05-04"] {
+///     stats.daily_stats.insert(day.to_string(), DayStats { keystrokes: 100, ..Default::default() });
+/// }
+/// let day = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+///
+/// assert_eq!(streak(&stats, day(4)), 2);
+/// assert_eq!(streak(&stats, day(5)), 2);
+/// assert_eq!(streak(&stats, day(6)), 0);
+/// ```
+pub fn streak(stats: &KeyStats, today: NaiveDate) -> u32 {
+    let typed = |date: NaiveDate| stats.daily_stats.get(&day_key(date)).is_some_and(|day| day.keystrokes > 0);
+    let mut date = if typed(today) { today } else { today.pred_opt().unwrap_or(today) };
+    let mut days = 0;
+    while typed(date) {
+        days += 1;
+        let Some(previous) = date.pred_opt() else {
+            break;
+        };
+        date = previous;
+    }
+    days
+}
+
+/// A placeholder of the template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Today,
+    Total,
+    Wpm,
+    GoalPct,
+    Streak,
+}
+
+impl Field {
+    /// All placeholders, in the order of the module docs.
+    const ALL: [Field; 5] = [Self::Today, Self::Total, Self::Wpm, Self::GoalPct, Self::Streak];
+
+    /// Returns the name between the braces.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Today => "today",
+            Self::Total => "total",
+            Self::Wpm => "wpm",
+            Self::GoalPct => "goal_pct",
+            Self::Streak => "streak",
+        }
+    }
+
+    /// Writes the value of the placeholder.
+    fn value(self, status: &StatusSnapshot, daily_goal: Option<u64>) -> String {
+        match self {
+            Self::Today => numbers::format_compact(status.today),
+            Self::Total => numbers::format_compact(status.total),
+            Self::Wpm => status.wpm.map_or_else(|| MISSING.to_string(), |wpm| format!("{:.0}", wpm)),
+            Self::GoalPct => match daily_goal.filter(|&goal| goal > 0) {
+                Some(goal) => format!("{:.0}", status.today as f64 * 100.0 / goal as f64),
+                None => MISSING.to_string(),
+            },
+            Self::Streak => status.streak.to_string(),
+        }
+    }
+}
+
+/// A piece of the template.
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    /// Written as is
+    Text(String),
+    /// Replaced by its value
+    Field(Field),
+}
+
+/// A status line template, see the module docs.
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::status_line::{StatusFormat, StatusSnapshot, DEFAULT_FORMAT};
+///
+/// let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+/// let status = StatusSnapshot { date, today: 12_412, total: 3_482_119, wpm: Some(71.6), streak: 9 };
+///
+/// assert_eq!(StatusFormat::parse(DEFAULT_FORMAT)?.render(&status, None, false), "⌨ 12.4k today · 72 WPM");
+/// let format = StatusFormat::parse("{today}/{total} {goal_pct}% {{{streak}d}}")?;
+/// assert_eq!(format.render(&status, Some(15_000), false), "12.4k/3.48M 83% {9d}");
+/// assert_eq!(format.render(&status, None, true), "⚠ 12.4k/3.48M —% {9d}");
+///
+/// let error = StatusFormat::parse("{today} {keys}").unwrap_err().to_string();
+/// assert!(error.contains("unknown placeholder {keys}"));
+/// assert!(StatusFormat::parse("{today").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusFormat {
+    parts: Vec<Part>,
+}
+
+impl StatusFormat {
+    /// Parses a template.
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("placeholder {{{} is not closed, add a }}", name),
+                        }
+                    }
+                    let Some(field) = Field::ALL.into_iter().find(|field| field.name() == name.trim()) else {
+                        let names: Vec<String> =
+                            Field::ALL.iter().map(|field| format!("{{{}}}", field.name())).collect();
+                        bail!("unknown placeholder {{{}}}, expected one of {}", name, names.join(", "));
+                    };
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(field));
+                }
+                '}' => bail!("stray }} in the template, write }}}} for a brace"),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Writes the status line.
+    ///
+    /// # Arguments
+    /// * `status` - The values to fill in
+    /// * `daily_goal` - `daily_goal` of the config, for `{goal_pct}`
+    /// * `stale` - Whether the values come from the status file rather than
+    ///   the running logger; the line then starts with [`STALE_MARKER`]
+    pub fn render(&self, status: &StatusSnapshot, daily_goal: Option<u64>, stale: bool) -> String {
+        let mut line = if stale { format!("{} ", STALE_MARKER) } else { String::new() };
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => line.push_str(text),
+                Part::Field(field) => line.push_str(&field.value(status, daily_goal)),
+            }
+        }
+        line
+    }
+}
@@ -0,0 +1,357 @@
+//! # Stream Module
+//!
+//! Serves live statistics as Server-Sent Events on localhost so external
+//! dashboards (OBS browser sources, status bars, web pages) can follow along.
+//!
+//! Each client connects with a plain `GET` and receives one
+//! `data: {json}` event per change, throttled to a few updates per second.
+//! The server runs on its own thread and only ever reads the latest
+//! snapshot, so slow or disconnecting clients never hold up the logger.
+//!
+//! Requests must name the server as `127.0.0.1:<port>` or `localhost:<port>`
+//! in their `Host` header. A web page that points its own domain at
+//! 127.0.0.1 (DNS rebinding) sends its domain instead and is refused.
+//!
+//! ## Example
+//! ```bash
+//! sudo ctrlq --stream-port 7878
+//! curl -N http://127.0.0.1:7878/
+//! ```
+
+use anyhow::{Context, Result};
+use ctrlq::keylogger::{LiveSnapshot, LoggerProblem};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+/// Minimum time between two events sent to the same client.
+const MIN_EVENT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Pause after a failed `accept`, so errors such as running out of file
+/// descriptors don't turn the loop into a busy wait.
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+/// Largest request read, headers included; longer requests are refused.
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// Hosts of the pages allowed to read the stream from a browser.
+const LOCAL_HOSTS: [&str; 2] = ["http://localhost", "http://127.0.0.1"];
+
+/// Whether a browser page may read the stream: pages served from
+/// localhost on any port. Any other website open in the browser may not.
+///
+/// Local files such as an OBS browser source send the origin `null`, but
+/// so does a sandboxed iframe on any website, so `null` is only allowed
+/// with `--stream-allow-null-origin`.
+fn is_local_origin(origin: &str, allow_null: bool) -> bool {
+    (allow_null && origin == "null")
+        || LOCAL_HOSTS.iter().any(|host| {
+            origin.strip_prefix(host).is_some_and(|rest| {
+                rest.is_empty()
+                    || rest.strip_prefix(':').is_some_and(|port| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()))
+            })
+        })
+}
+
+/// Names the server answers to in the `Host` header.
+const LOCAL_NAMES: [&str; 2] = ["127.0.0.1", "localhost"];
+
+/// Value of a request header, if the request has it.
+fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(header, _)| header.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+/// Whether a request was addressed to this server by a local name, and not
+/// by a domain that merely resolves to 127.0.0.1.
+fn is_local_host(host: &str, port: u16) -> bool {
+    // Port 80 is left out of the header
+    let (name, host_port) = host.rsplit_once(':').unwrap_or((host, "80"));
+    LOCAL_NAMES.iter().any(|local| name.eq_ignore_ascii_case(local)) && host_port == port.to_string()
+}
+
+/// Headers shared by every response: the CORS headers that let a local
+/// browser page read the stream, only if the request came from one.
+fn common_headers(request: &str, allow_null_origin: bool) -> String {
+    match header(request, "origin").filter(|origin| is_local_origin(origin, allow_null_origin)) {
+        Some(origin) => format!(
+            "Access-Control-Allow-Origin: {}\r\n\
+             Access-Control-Allow-Methods: GET, OPTIONS\r\n\
+             Access-Control-Allow-Headers: *\r\n\
+             Vary: Origin\r\n",
+            origin
+        ),
+        None => "Vary: Origin\r\n".to_string(),
+    }
+}
+
+/// The SSE server, listening on `127.0.0.1:<port>` but not serving yet.
+pub struct StreamServer {
+    listener: std::net::TcpListener,
+    port: u16,
+    allow_null_origin: bool,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl StreamServer {
+    /// Binds the port, so an address already in use is reported before the
+    /// logger starts instead of failing silently later.
+    ///
+    /// # Arguments
+    /// * `port` - TCP port to listen on
+    /// * `allow_null_origin` - Let pages with the origin `null`, such as
+    ///   local files, read the stream
+    ///
+    /// # Returns
+    /// `Result<StreamServer>` - The bound server, or an error if the port can't be used
+    pub fn bind(port: u16, allow_null_origin: bool) -> Result<Self> {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", port))
+            .with_context(|| format!("failed to listen on 127.0.0.1:{}", port))?;
+        listener.set_nonblocking(true)?;
+        // Port 0 picks a free port, which requests then have to name
+        let port = listener.local_addr()?.port();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("failed to start stream runtime")?;
+        Ok(Self { listener, port, allow_null_origin, runtime })
+    }
+
+    /// Serves clients on a background thread from now on.
+    ///
+    /// # Arguments
+    /// * `live_rx` - Receiver for live snapshots published by the logger
+    /// * `problem_tx` - Where failures are reported, see [`ctrlq::keylogger::LoggerChannels::problem_tx`]
+    pub fn start(self, live_rx: watch::Receiver<LiveSnapshot>, problem_tx: mpsc::Sender<LoggerProblem>) {
+        let Self { listener, port, allow_null_origin, runtime } = self;
+        thread::spawn(move || {
+            runtime.block_on(async move {
+                let listener = match TcpListener::from_std(listener) {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        let _ = problem_tx.send(LoggerProblem::Error(format!("Stream server failed: {}", e)));
+                        return;
+                    }
+                };
+
+                // Only a new kind of error is reported, not every retry
+                let mut last_error = None;
+                loop {
+                    match listener.accept().await {
+                        Ok((socket, _)) => {
+                            last_error = None;
+                            tokio::spawn(serve_client(socket, port, allow_null_origin, live_rx.clone()));
+                        }
+                        Err(e) => {
+                            if last_error.replace(e.kind()) != Some(e.kind()) {
+                                let _ = problem_tx.send(LoggerProblem::Error(format!(
+                                    "Stream server can't accept connections: {}",
+                                    e
+                                )));
+                            }
+                            tokio::time::sleep(ACCEPT_RETRY).await;
+                        }
+                    }
+                }
+            });
+        });
+    }
+}
+
+/// Reads a request up to the end of its headers, however many reads that
+/// takes.
+///
+/// # Returns
+/// `Option<Vec<u8>>` - The request, or None if the client left first or
+/// sent more than [`MAX_REQUEST`] bytes without ending its headers
+async fn read_request(socket: &mut TcpStream) -> Option<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        if request.len() >= MAX_REQUEST {
+            return None;
+        }
+        match socket.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(read) => request.extend_from_slice(&chunk[..read]),
+        }
+    }
+    Some(request)
+}
+
+/// Handles one client connection until it disconnects.
+async fn serve_client(
+    mut socket: TcpStream,
+    port: u16,
+    allow_null_origin: bool,
+    mut live_rx: watch::Receiver<LiveSnapshot>,
+) {
+    // Only the request line and the Host and Origin headers matter
+    let Some(request) = read_request(&mut socket).await else {
+        let response = "HTTP/1.1 431 Request Header Fields Too Large\r\nContent-Length: 0\r\n\r\n";
+        let _ = socket.write_all(response.as_bytes()).await;
+        return;
+    };
+    let request = String::from_utf8_lossy(&request);
+    if !header(&request, "host").is_some_and(|host| is_local_host(host, port)) {
+        let response = "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n";
+        let _ = socket.write_all(response.as_bytes()).await;
+        return;
+    }
+    let headers = common_headers(&request, allow_null_origin);
+
+    if request.starts_with("OPTIONS") {
+        let response = format!("HTTP/1.1 204 No Content\r\n{}Content-Length: 0\r\n\r\n", headers);
+        let _ = socket.write_all(response.as_bytes()).await;
+        return;
+    }
+    if !request.starts_with("GET") {
+        let response = format!(
+            "HTTP/1.1 405 Method Not Allowed\r\n{}Allow: GET, OPTIONS\r\nContent-Length: 0\r\n\r\n",
+            headers
+        );
+        let _ = socket.write_all(response.as_bytes()).await;
+        return;
+    }
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/event-stream\r\n\
+         Cache-Control: no-cache\r\n\
+         Connection: keep-alive\r\n\
+         {}\r\n",
+        headers
+    );
+    if socket.write_all(response.as_bytes()).await.is_err() {
+        return;
+    }
+
+    // Send the current state right away, then one event per change
+    live_rx.mark_changed();
+    while live_rx.changed().await.is_ok() {
+        let snapshot = live_rx.borrow_and_update().clone();
+        let Ok(json) = serde_json::to_string(&snapshot) else {
+            continue;
+        };
+        if socket.write_all(format!("data: {}\n\n", json).as_bytes()).await.is_err() {
+            return;
+        }
+        tokio::time::sleep(MIN_EVENT_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    /// Sends a request in pieces, with a pause between them, and returns the
+    /// status line of the response.
+    fn status(port: u16, pieces: &[&str]) -> String {
+        let mut socket = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        for piece in pieces {
+            socket.write_all(piece.as_bytes()).unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut response = Vec::new();
+        let mut chunk = [0u8; 256];
+        while !response.windows(2).any(|window| window == b"\r\n") {
+            match socket.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => response.extend_from_slice(&chunk[..read]),
+            }
+        }
+        let response = String::from_utf8_lossy(&response);
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    /// Starts a server on a free port and returns the port.
+    fn server() -> u16 {
+        let (live_tx, live_rx) = watch::channel(LiveSnapshot::default());
+        std::mem::forget(live_tx);
+        let (problem_tx, _) = mpsc::channel();
+        let server = StreamServer::bind(0, false).unwrap();
+        let port = server.port;
+        server.start(live_rx, problem_tx);
+        port
+    }
+
+    #[test]
+    fn host_must_name_the_server() {
+        assert!(is_local_host("127.0.0.1:7878", 7878));
+        assert!(is_local_host("localhost:7878", 7878));
+        assert!(is_local_host("LocalHost:7878", 7878));
+        assert!(is_local_host("localhost", 80));
+
+        assert!(!is_local_host("evil.com", 7878));
+        assert!(!is_local_host("evil.com:7878", 7878));
+        assert!(!is_local_host("localhost:7879", 7878));
+        assert!(!is_local_host("localhost", 7878));
+        assert!(!is_local_host("localhost.evil.com:7878", 7878));
+        assert!(!is_local_host("", 7878));
+    }
+
+    #[test]
+    fn only_local_pages_may_read_the_stream() {
+        assert!(is_local_origin("http://localhost", false));
+        assert!(is_local_origin("http://localhost:3000", false));
+        assert!(is_local_origin("http://127.0.0.1:8080", false));
+
+        assert!(!is_local_origin("null", false));
+        assert!(!is_local_origin("https://evil.com", false));
+        assert!(!is_local_origin("http://localhost.evil.com", false));
+        assert!(!is_local_origin("http://localhost:", false));
+        assert!(!is_local_origin("http://127.0.0.1:80abc", false));
+
+        assert!(is_local_origin("null", true));
+        assert!(!is_local_origin("https://evil.com", true));
+    }
+
+    #[test]
+    fn cors_headers_only_for_local_origins() {
+        let request = |origin: &str| format!("GET / HTTP/1.1\r\nHost: localhost:7878\r\nOrigin: {}\r\n\r\n", origin);
+        let allowed = common_headers(&request("http://localhost:3000"), false);
+        assert!(allowed.contains("Access-Control-Allow-Origin: http://localhost:3000\r\n"));
+        assert!(!common_headers(&request("null"), false).contains("Access-Control-Allow-Origin"));
+        assert!(common_headers(&request("null"), true).contains("Access-Control-Allow-Origin: null\r\n"));
+        assert!(!common_headers(&request("https://evil.com"), true).contains("Access-Control-Allow-Origin"));
+    }
+
+    #[test]
+    fn header_lookup() {
+        let request = "GET / HTTP/1.1\r\nhOsT:  localhost:7878 \r\nX-Empty:\r\n\r\nOrigin: http://localhost";
+        assert_eq!(header(request, "Host"), Some("localhost:7878"));
+        assert_eq!(header(request, "x-empty"), Some(""));
+        // The body is not a header
+        assert_eq!(header(request, "origin"), None);
+        assert_eq!(header("GET / HTTP/1.1\r\n\r\n", "host"), None);
+    }
+
+    #[test]
+    fn requests_are_read_to_the_end_of_their_headers() {
+        let port = server();
+        let host = format!("Host: localhost:{}\r\n", port);
+
+        assert_eq!(status(port, &["GET / HTTP/1.1\r\n", &host, "\r\n"]), "HTTP/1.1 200 OK");
+        // A Host header past the first KiB
+        let padding = format!("X-Padding: {}\r\n", "a".repeat(2000));
+        assert_eq!(status(port, &[&format!("GET / HTTP/1.1\r\n{}{}\r\n", padding, host)]), "HTTP/1.1 200 OK");
+
+        assert_eq!(status(port, &["GET / HTTP/1.1\r\nHost: evil.com\r\n\r\n"]), "HTTP/1.1 403 Forbidden");
+        let other_port = format!("GET / HTTP/1.1\r\nHost: localhost:{}\r\n\r\n", port.wrapping_add(1));
+        assert_eq!(status(port, &[&other_port]), "HTTP/1.1 403 Forbidden");
+        assert_eq!(status(port, &["GET / HTTP/1.1\r\n\r\n"]), "HTTP/1.1 403 Forbidden");
+
+        let endless = format!("GET / HTTP/1.1\r\n{}X-Padding: {}", host, "a".repeat(MAX_REQUEST));
+        assert_eq!(status(port, &[&endless]), "HTTP/1.1 431 Request Header Fields Too Large");
+    }
+}
@@ -0,0 +1,214 @@
+//! # Summary Module
+//!
+//! Markdown summaries of finished ISO weeks, kept in `summaries/` in the
+//! data directory. Profiles other than the default one add their name:
+//!
+//! ```text
+//! summaries/2024-W23.md
+//! summaries/2024-W23.work.md
+//! ```
+//!
+//! A running logger writes the summary of a week when the week rolls over,
+//! and on startup when the week before has none yet; `auto_summary = false`
+//! in the config (or `--no-auto-summary`) turns that off. `ctrlq report
+//! --week 2024-W23` writes any week on demand. The text itself comes from
+//! [`report::weekly_summary`].
+//!
+//! With `--no-ui --summary-file`, the logger also keeps a plain-text
+//! snapshot of its statistics ([`report::text_summary`]) up to date in
+//! `summary.txt`, written with [`write_text_summary`].
+
+use crate::keylogger::{day_key, KeyStats};
+use crate::profile;
+use crate::report;
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDate};
+use std::path::{Path, PathBuf};
+
+/// Returns the directory of the weekly summaries.
+///
+/// # Arguments
+/// * `data_file` - Path of the statistics data file
+///
+/// # Returns
+/// `PathBuf` - `summaries` next to the data file
+pub fn summaries_dir(data_file: &Path) -> PathBuf {
+    data_file.parent().unwrap_or(Path::new(".")).join("summaries")
+}
+
+/// Returns the summary file of the week starting on `monday`.
+///
+/// # Arguments
+/// * `data_file` - Path of the statistics data file
+/// * `monday` - First day of the week
+///
+/// # Returns
+/// `PathBuf` - e.g. `summaries/2024-W23.md`, or `summaries/2024-W23.work.md`
+/// for the profile `work`
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::summary::summary_path;
+/// use std::path::{Path, PathBuf};
+///
+/// let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+/// assert_eq!(
+///     summary_path(Path::new("/data/keystroke_data.json"), monday),
+///     PathBuf::from("/data/summaries/2024-W23.md")
+/// );
+/// assert_eq!(
+///     summary_path(Path::new("/data/keystroke_data.work.json"), monday),
+///     PathBuf::from("/data/summaries/2024-W23.work.md")
+/// );
+/// ```
+pub fn summary_path(data_file: &Path, monday: NaiveDate) -> PathBuf {
+    let week = report::week_label(monday);
+    let file_name = match profile_of(data_file) {
+        Some(name) => format!("{}.{}.md", week, name),
+        None => format!("{}.md", week),
+    };
+    summaries_dir(data_file).join(file_name)
+}
+
+/// Returns the default file of `--summary-file`.
+///
+/// # Arguments
+/// * `data_file` - Path of the statistics data file
+///
+/// # Returns
+/// `PathBuf` - `summary.txt` next to the data file, or `summary.NAME.txt`
+/// for the profile `NAME`
+///
+/// # Example
+/// ```
+/// use ctrlq::summary::text_summary_path;
+/// use std::path::{Path, PathBuf};
+///
+/// assert_eq!(text_summary_path(Path::new("/data/keystroke_data.json")), PathBuf::from("/data/summary.txt"));
+/// assert_eq!(text_summary_path(Path::new("/data/keystroke_data.work.json")), PathBuf::from("/data/summary.work.txt"));
+/// ```
+pub fn text_summary_path(data_file: &Path) -> PathBuf {
+    let file_name = match profile_of(data_file) {
+        Some(name) => format!("summary.{}.txt", name),
+        None => "summary.txt".to_string(),
+    };
+    data_file.parent().unwrap_or(Path::new(".")).join(file_name)
+}
+
+/// Writes the plain-text summary of `stats` to `path`.
+///
+/// The text goes to a temporary file next to `path` first and is renamed
+/// over it, so readers such as `watch cat` never see half a summary.
+///
+/// # Arguments
+/// * `path` - File to write
+/// * `stats` - Latest statistics from the logger
+///
+/// # Returns
+/// `Result<()>` - Success or an error naming the file
+///
+/// # Example
+/// ```
+/// use ctrlq::keylogger::KeyStats;
+/// use ctrlq::summary::write_text_summary;
+///
+/// let dir = std::env::temp_dir().join(format!("ctrlq-text-summary-doc-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// let path = dir.join("summary.txt");
+///
+/// write_text_summary(&path, &KeyStats::new())?;
+/// assert!(std::fs::read_to_string(&path)?.starts_with("CtrlQ summary"));
+/// assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+/// std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn write_text_summary(path: &Path, stats: &KeyStats) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    std::fs::write(&temp, report::text_summary(stats, chrono::Local::now()))
+        .with_context(|| format!("failed to write {}", temp.display()))?;
+    std::fs::rename(&temp, path).with_context(|| format!("failed to replace {}", path.display()))
+}
+
+/// Returns the profile of a data file, or None for the default profile.
+fn profile_of(data_file: &Path) -> Option<String> {
+    data_file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(profile::profile_name)
+        .filter(|name| name != profile::DEFAULT_PROFILE)
+}
+
+/// Writes the summary of one week, replacing an existing one.
+///
+/// # Arguments
+/// * `stats` - Statistics holding the week and the week before it
+/// * `data_file` - The data file the summary belongs to
+/// * `monday` - First day of the week
+///
+/// # Returns
+/// `Result<PathBuf>` - Path of the written summary
+pub fn write_week(stats: &KeyStats, data_file: &Path, monday: NaiveDate) -> Result<PathBuf> {
+    let dir = summaries_dir(data_file);
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let path = summary_path(data_file, monday);
+    std::fs::write(&path, report::weekly_summary(stats, monday))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Writes the summary of the week before `today` unless it exists already
+/// or nothing was typed that week.
+///
+/// The week before is always in the data file, see [`crate::archive`], so
+/// the statistics of a running logger are enough.
+///
+/// # Arguments
+/// * `stats` - Statistics of the running logger
+/// * `data_file` - The data file the summary belongs to
+/// * `today` - Current local date
+///
+/// # Returns
+/// `Result<Option<PathBuf>>` - Path of the summary if one was written
+///
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use ctrlq::keylogger::KeyStats;
+/// use ctrlq::summary;
+///
+/// let dir = std::env::temp_dir().join(format!("ctrlq-summary-doc-{}", std::process::id()));
+/// let data_file = dir.join("keystroke_data.json");
+/// let today = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+/// let mut stats = KeyStats::new();
+///
+/// // Nothing typed last week: no summary
+/// assert_eq!(summary::write_due(&stats, &data_file, today)?, None);
+///
+/// stats.add_counts("KEY_30", 120, NaiveDate::from_ymd_opt(2024, 6, 5));
+/// let path = summary::write_due(&stats, &data_file, today)?.unwrap();
+/// assert!(std::fs::read_to_string(&path)?.starts_with("# CtrlQ week 2024-W23"));
+///
+/// // Written once per week
+/// assert_eq!(summary::write_due(&stats, &data_file, today)?, None);
+/// std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn write_due(stats: &KeyStats, data_file: &Path, today: NaiveDate) -> Result<Option<PathBuf>> {
+    let monday = report::week_start(today) - Duration::days(7);
+    if summary_path(data_file, monday).exists() {
+        return Ok(None);
+    }
+    let sunday = day_key(monday + Duration::days(6));
+    let last_week = stats
+        .daily_stats
+        .iter()
+        .filter(|(date, _)| date.as_str() >= day_key(monday).as_str() && date.as_str() <= sunday.as_str());
+    let typed = report::weekly_totals(last_week).get(&monday).is_some_and(|week| week.keystrokes > 0);
+    if !typed {
+        return Ok(None);
+    }
+    write_week(stats, data_file, monday).map(Some)
+}
@@ -0,0 +1,348 @@
+//! # Surprise Module
+//!
+//! Which keys you use unusually much or little, compared with a reference:
+//! English prose, source code, or a text file of your own. Each character's
+//! share of your typing is set against its share in the reference, and the
+//! keys are ranked by how far apart the two are, so a heavy `_` and `=` or
+//! a rare `q` stand out.
+//!
+//! Punctuation only means something when shifted characters are counted
+//! apart from their keys, i.e. with XKB characters or `--case-sensitive`.
+//! Otherwise `-` and `_` are the same key, and only letters are compared.
+//! Letters are compared without their case.
+//!
+//! The built-in references are rough shares rounded from published English
+//! letter frequencies and from counts over a mix of C, Rust, Python and
+//! JavaScript sources; whitespace is left out of both.
+
+use crate::keymap;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Fewest characters typed before keys are ranked; below it every rare key
+/// looks unusual.
+pub const MIN_CHARS: u64 = 500;
+
+/// Share assumed for characters a reference doesn't have, so one of them
+/// doesn't rank as infinitely surprising.
+const MIN_SHARE: f64 = 0.0005;
+
+/// Weights of English prose, letters about 94% of the characters.
+const ENGLISH: &[(char, f64)] = &[
+    ('e', 11.9), ('t', 8.5), ('a', 7.7), ('o', 7.1), ('i', 6.6), ('n', 6.3), ('s', 6.0),
+    ('h', 5.7), ('r', 5.6), ('d', 4.0), ('l', 3.8), ('c', 2.6), ('u', 2.6), ('m', 2.3),
+    ('w', 2.2), ('f', 2.1), ('g', 1.9), ('y', 1.9), ('p', 1.8), ('b', 1.2), ('v', 0.9),
+    ('k', 0.7), ('j', 0.14), ('x', 0.14), ('q', 0.09), ('z', 0.07),
+    ('.', 1.5), (',', 1.5), ('\'', 0.5), ('"', 0.4), ('-', 0.4), ('?', 0.1), ('!', 0.1),
+    (':', 0.1), (';', 0.05), ('(', 0.05), (')', 0.05),
+    ('0', 0.15), ('1', 0.15), ('2', 0.1), ('3', 0.07), ('4', 0.06), ('5', 0.06),
+    ('6', 0.05), ('7', 0.05), ('8', 0.05), ('9', 0.06),
+];
+
+/// Weights of source code, letters about 70% of the characters.
+const CODE: &[(char, f64)] = &[
+    ('e', 7.4), ('t', 5.9), ('r', 4.3), ('s', 4.3), ('i', 4.4), ('n', 4.1), ('a', 3.9),
+    ('o', 3.8), ('l', 2.8), ('c', 2.5), ('u', 2.0), ('d', 2.0), ('p', 1.8), ('m', 1.6),
+    ('f', 1.6), ('g', 1.3), ('h', 1.1), ('b', 0.9), ('y', 0.8), ('v', 0.8), ('w', 0.6),
+    ('x', 0.6), ('k', 0.6), ('j', 0.2), ('q', 0.15), ('z', 0.15),
+    ('(', 2.2), (')', 2.2), ('.', 1.9), ('_', 1.6), ('=', 1.3), (',', 1.2), (';', 1.0),
+    ('"', 1.0), (':', 0.9), ('-', 0.6), ('\'', 0.5), ('{', 0.5), ('}', 0.5), ('/', 0.5),
+    ('>', 0.5), ('[', 0.4), (']', 0.4), ('<', 0.3), ('*', 0.3), ('&', 0.3), ('+', 0.2),
+    ('!', 0.2), ('#', 0.2), ('|', 0.1), ('$', 0.1), ('%', 0.1), ('\\', 0.1), ('?', 0.1),
+    ('@', 0.05), ('`', 0.05), ('~', 0.02), ('^', 0.02),
+    ('0', 0.8), ('1', 0.7), ('2', 0.4), ('3', 0.2), ('4', 0.2), ('5', 0.1), ('6', 0.1),
+    ('7', 0.1), ('8', 0.15), ('9', 0.1),
+];
+
+/// What typing is compared with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Corpus {
+    /// English prose
+    English,
+    /// Source code
+    Code,
+    /// A text file, whose characters are counted
+    File(PathBuf),
+}
+
+impl Corpus {
+    /// Parses a corpus name: `english`, `code`, or the path of a text file.
+    pub fn parse(spec: &str) -> Self {
+        match spec.to_lowercase().as_str() {
+            "english" => Self::English,
+            "code" => Self::Code,
+            _ => Self::File(PathBuf::from(spec)),
+        }
+    }
+
+    /// Name of the corpus in titles, e.g. `English` or the file name.
+    pub fn label(&self) -> String {
+        match self {
+            Self::English => "English".to_string(),
+            Self::Code => "code".to_string(),
+            Self::File(path) => path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Loads the reference distribution of the corpus.
+    ///
+    /// # Returns
+    /// `Result<Reference>` - The distribution, or an error if the file
+    /// can't be read or has too few characters
+    pub fn load(&self) -> Result<Reference> {
+        match self {
+            Self::English => Ok(Reference::from_weights(&self.label(), ENGLISH)),
+            Self::Code => Ok(Reference::from_weights(&self.label(), CODE)),
+            Self::File(path) => Reference::from_file(path),
+        }
+    }
+}
+
+/// Share of each character in a reference corpus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    /// Name of the corpus, see [`Corpus::label`]
+    pub name: String,
+    /// Share of each character (0.0-1.0), letters in lower case
+    pub shares: HashMap<char, f64>,
+}
+
+impl Reference {
+    fn from_weights(name: &str, weights: &[(char, f64)]) -> Self {
+        let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+        let shares = weights.iter().map(|&(c, weight)| (c, weight / total)).collect();
+        Self { name: name.to_string(), shares }
+    }
+
+    /// Counts the characters of a text as a reference.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the corpus
+    /// * `text` - The text; whitespace and control characters are left out
+    ///
+    /// # Returns
+    /// `Result<Reference>` - The reference, or an error for fewer than [`MIN_CHARS`] characters
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::surprise::Reference;
+    ///
+    /// let text = "let x_1 = y_2;\n".repeat(50);
+    /// let reference = Reference::from_text("snippets", &text).unwrap();
+    /// assert_eq!(reference.shares[&'_'], 2.0 / 11.0);
+    /// assert!(!reference.shares.contains_key(&' '));
+    ///
+    /// assert!(Reference::from_text("short", "too short").is_err());
+    /// ```
+    pub fn from_text(name: &str, text: &str) -> Result<Self> {
+        let mut counts: HashMap<char, u64> = HashMap::new();
+        for c in text.chars().filter(|c| !c.is_whitespace() && !c.is_control()) {
+            *counts.entry(normalize(c)).or_insert(0) += 1;
+        }
+        let total: u64 = counts.values().sum();
+        if total < MIN_CHARS {
+            bail!("{} has {} characters, at least {} are needed for a reference", name, total, MIN_CHARS);
+        }
+        let shares = counts.into_iter().map(|(c, count)| (c, count as f64 / total as f64)).collect();
+        Ok(Self { name: name.to_string(), shares })
+    }
+
+    /// Reads a text file as a reference, see [`Reference::from_text`].
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_text(&Corpus::File(path.to_path_buf()).label(), &text)
+    }
+
+    /// The same reference restricted to letters, for typing whose
+    /// punctuation can't be told apart.
+    fn letters(&self) -> HashMap<char, f64> {
+        let letters: HashMap<char, f64> = self.shares.iter().filter(|(c, _)| c.is_alphabetic()).map(|(&c, &share)| (c, share)).collect();
+        let total: f64 = letters.values().sum();
+        letters.into_iter().map(|(c, share)| (c, share / total)).collect()
+    }
+}
+
+/// Characters typed, as far as the statistics tell them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Observed {
+    /// Count of each character, letters in lower case
+    pub counts: HashMap<char, u64>,
+    /// Whether only letters could be told apart
+    pub letters_only: bool,
+}
+
+impl Observed {
+    /// Collects the typed characters from character counts if there are
+    /// any, else from key counts.
+    ///
+    /// # Arguments
+    /// * `keys` - Key counts (`KEY_<code>`, with `_S` for shifted keys when case-sensitive)
+    /// * `chars` - Character counts of XKB, see [`crate::xkb`]
+    /// * `case_sensitive` - Whether the key counts tell shifted keys apart
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::surprise::Observed;
+    /// use std::collections::HashMap;
+    ///
+    /// // A and minus typed, one of them with Shift
+    /// let keys = HashMap::from([("KEY_30".to_string(), 5), ("KEY_30_S".to_string(), 1), ("KEY_12_S".to_string(), 3)]);
+    /// let observed = Observed::collect(&keys, &HashMap::new(), true);
+    /// assert_eq!(observed.counts, HashMap::from([('a', 6), ('_', 3)]));
+    /// assert!(!observed.letters_only);
+    ///
+    /// // Without case-sensitive counts, minus and underscore are one key
+    /// let keys = HashMap::from([("KEY_30".to_string(), 5), ("KEY_12".to_string(), 3)]);
+    /// let observed = Observed::collect(&keys, &HashMap::new(), false);
+    /// assert_eq!(observed.counts, HashMap::from([('a', 5)]));
+    /// assert!(observed.letters_only);
+    /// ```
+    pub fn collect(keys: &HashMap<String, u64>, chars: &HashMap<String, u64>, case_sensitive: bool) -> Self {
+        let mut counts: HashMap<char, u64> = HashMap::new();
+        let typed_chars = chars.iter().filter_map(|(text, &count)| {
+            let mut chars = text.chars();
+            let c = chars.next()?;
+            chars.next().is_none().then_some((c, count))
+        });
+        let from_chars: Vec<(char, u64)> = typed_chars.collect();
+        if !from_chars.is_empty() {
+            for (c, count) in from_chars {
+                if !c.is_whitespace() && !c.is_control() {
+                    *counts.entry(normalize(c)).or_insert(0) += count;
+                }
+            }
+            return Self { counts, letters_only: false };
+        }
+
+        for (key, &count) in keys {
+            let Some((plain, shifted)) = keymap::parse_code(key).and_then(keymap::us_layout_chars) else {
+                continue;
+            };
+            let c = if keymap::is_shifted_name(key) { shifted } else { plain };
+            if case_sensitive || c.is_alphabetic() {
+                *counts.entry(normalize(c)).or_insert(0) += count;
+            }
+        }
+        Self { counts, letters_only: !case_sensitive }
+    }
+
+    /// Characters counted.
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+}
+
+/// How unusually much or little one key is used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySurprise {
+    /// The character, letters in lower case
+    pub key: char,
+    /// Share of the typed characters (0.0-1.0)
+    pub observed: f64,
+    /// Share in the reference (0.0-1.0)
+    pub expected: f64,
+    /// How far apart the shares are, relative to the expected share:
+    /// positive for overused keys, negative for underused ones
+    pub score: f64,
+}
+
+impl KeySurprise {
+    /// How many times the reference share the key is used (2.0 = twice as much).
+    pub fn ratio(&self) -> f64 {
+        self.observed / self.expected
+    }
+}
+
+/// Keys ranked by surprise, see [`rank`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurpriseReport {
+    /// Name of the reference corpus
+    pub reference: String,
+    /// Whether only letters were compared
+    pub letters_only: bool,
+    /// Characters typed
+    pub total: u64,
+    /// Every key, most unusual first
+    pub keys: Vec<KeySurprise>,
+}
+
+impl SurpriseReport {
+    /// Keys used more than the reference, most unusual first.
+    pub fn overused(&self) -> impl Iterator<Item = &KeySurprise> {
+        self.keys.iter().filter(|key| key.score > 0.0)
+    }
+
+    /// Keys used less than the reference, most unusual first.
+    pub fn underused(&self) -> impl Iterator<Item = &KeySurprise> {
+        self.keys.iter().filter(|key| key.score < 0.0)
+    }
+}
+
+/// Ranks the typed keys by how unusual their share is against a reference.
+///
+/// The score of a key is `(observed − expected) / √expected` on shares, a
+/// standardized residual: a rare key used a little more stands out more
+/// than a common key used a little more, but a common key used much less
+/// still does.
+///
+/// # Arguments
+/// * `observed` - Typed characters, see [`Observed::collect`]
+/// * `reference` - What to compare with
+///
+/// # Returns
+/// `Option<SurpriseReport>` - The ranking, or None below [`MIN_CHARS`] characters
+///
+/// # Example
+/// ```
+/// use ctrlq::surprise::{rank, Corpus, Observed};
+/// use std::collections::HashMap;
+///
+/// // Plenty of underscores and equals signs, not a single q
+/// let typed = "let snake_case_name = other_value;".repeat(20);
+/// let mut counts = HashMap::new();
+/// for c in typed.chars().filter(|c| !c.is_whitespace()) {
+///     *counts.entry(c).or_insert(0) += 1;
+/// }
+/// let observed = Observed { counts, letters_only: false };
+///
+/// let english = rank(&observed, &Corpus::English.load().unwrap()).unwrap();
+/// assert_eq!(english.keys[0].key, '_');
+/// assert!(english.underused().any(|key| key.key == 'q'));
+///
+/// // Code has its share of underscores, so they surprise less
+/// let code = rank(&observed, &Corpus::Code.load().unwrap()).unwrap();
+/// let underscore = |report: &ctrlq::surprise::SurpriseReport| {
+///     report.keys.iter().find(|key| key.key == '_').unwrap().score
+/// };
+/// assert!(underscore(&code) < underscore(&english));
+///
+/// let few = Observed { counts: HashMap::from([('a', 10)]), letters_only: true };
+/// assert!(rank(&few, &Corpus::English.load().unwrap()).is_none());
+/// ```
+pub fn rank(observed: &Observed, reference: &Reference) -> Option<SurpriseReport> {
+    let total = observed.total();
+    if total < MIN_CHARS {
+        return None;
+    }
+    let expected_shares = if observed.letters_only { reference.letters() } else { reference.shares.clone() };
+
+    let mut keys: Vec<char> = expected_shares.keys().copied().collect();
+    keys.extend(observed.counts.keys().filter(|c| !expected_shares.contains_key(c)));
+    let mut keys: Vec<KeySurprise> = keys
+        .into_iter()
+        .map(|key| {
+            let observed = observed.counts.get(&key).copied().unwrap_or(0) as f64 / total as f64;
+            let expected = expected_shares.get(&key).copied().unwrap_or(0.0).max(MIN_SHARE);
+            KeySurprise { key, observed, expected, score: (observed - expected) / expected.sqrt() }
+        })
+        .collect();
+    keys.sort_by(|a, b| b.score.abs().total_cmp(&a.score.abs()).then(a.key.cmp(&b.key)));
+
+    Some(SurpriseReport { reference: reference.name.clone(), letters_only: observed.letters_only, total, keys })
+}
+
+/// Letters compare without case.
+fn normalize(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
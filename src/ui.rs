@@ -5,36 +5,548 @@
 //! and interactive controls.
 //!
 //! ## Features
-//! - Multi-tab interface (Overview, Top Keys, Heatmap, Sessions)
-//! - Real-time statistics updates
-//! - Keyboard heatmap with color coding
+//! - Multi-tab interface (Overview, Top Keys, Heatmap, Sessions, History, Timeline)
+//! - Real-time statistics updates, dimmed and slower while the terminal has no focus
+//! - Keyboard heatmap with color coding, comparing two keyboards or profiles with 'v'
 //! - Interactive navigation with Tab/Shift+Tab
 //! - Reset functionality with 'r' key
+//! - Key class filter for Top Keys and Heatmap with 'c' key
+//! - Top Keys for today, the last 7 days or all time with 't'/'w'/'a'
+//! - Typed characters instead of physical keys on Top Keys with 'x' (feature `xkb`)
+//! - Keys used unusually much or little against English or code on Top Keys with 'u'
+//! - Scrollable session table with a per-session detail pane
+//! - Weekly share of up to three keys over the whole history on History with 'f'
+//! - Help overlay with '?' key
+//! - Problems of the logging thread in an overlay ('e'), with restart after a crash
+//! - Device picker that switches the logged keyboard without a restart ('d')
+//! - Typing test that calibrates the WPM estimate (`ctrlq calibrate`, `:calibrate`)
+//! - Read-only viewer over a saved data file (`ctrlq view`, [`StatsSource::Static`])
 //! - Quit with 'q' key
 
-use crate::keylogger::KeyStats;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event, KeyCode, KeyEvent,
+        KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use chrono::{DateTime, NaiveDate, Utc};
+use ctrlq::keylogger::{
+    self, DayStats, DayTimeline, Derived, Dock, InputDeviceInfo, KeyLogger, KeyStats, LoggerChannels, LoggerCommand, LoggerProblem, MonthStats, Rollover, Record, ResetLoss, ResetScope,
+    ShareChange, TypingSession, WarmupCurve, LOGGER_THREAD, TIMELINE_BUCKET_MINUTES, WARMUP_WINDOW,
+};
+use ctrlq::bindings::{Key, KeyBindings, KeySpec, UiAction};
+use ctrlq::calibration::{self, Calibration, CalibrationHistory, TypedKey, TypingTest};
+use ctrlq::config::Config;
+use ctrlq::drift::{DriftSeries, MAX_DRIFT_KEYS};
+use ctrlq::heatmap::{Heat, HeatCell, HeatGrid, HeatmapComparison, Lean};
+use ctrlq::keymap::{self, KeyClass};
+use ctrlq::schedule::Schedule;
+use ctrlq::surprise::{self, Corpus, Observed, Reference, SurpriseReport};
+use ctrlq::{actions, archive, layout, numbers, paths, profile, report};
 use ratatui::{
-    backend::{Backend, CrosstermBackend},
+    backend::{Backend, CrosstermBackend, TestBackend},
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols::Marker,
     text::{Line, Span, Text},
     widgets::{
-        Block, Borders, Gauge, List, ListItem, Paragraph, Tabs, Wrap,
+        Axis, Bar, BarChart, BarGroup, Block, Borders, Cell, Chart, Clear, Dataset, Gauge, GraphType, List, ListItem,
+        ListState, Paragraph, Row, Sparkline, Table, TableState, Tabs, Wrap,
     },
     Frame, Terminal,
 };
 use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
     io,
+    path::{Path, PathBuf},
     sync::mpsc,
     time::{Duration, Instant},
 };
-use tokio::sync::watch;
+
+/// Titles of the UI tabs, in order.
+const TAB_TITLES: [&str; 7] = [
+    "Overview", "Top Keys", "Heatmap", "Sessions", "History", "Timeline", "Debug",
+];
+
+/// Rows skipped by Page Up / Page Down in the session table.
+const SESSION_PAGE_SIZE: usize = 10;
+
+/// Months listed by the History tab's months view, the current one included.
+const HISTORY_MONTHS: u32 = 12;
+
+/// Time between checks for something to redraw once statistics stopped
+/// changing (or the configured tick rate, if slower).
+const IDLE_TICK_RATE: Duration = Duration::from_secs(1);
+
+/// Time between checks for something to redraw while the terminal doesn't
+/// have focus (or the configured tick rate, if slower).
+const UNFOCUSED_TICK_RATE: Duration = Duration::from_secs(2);
+
+/// How long statistics must stay unchanged before redraws slow down.
+const IDLE_AFTER: Duration = Duration::from_secs(5);
+
+/// How long a status bar message stays visible.
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+/// Time between checks for typed characters during a typing test, so the
+/// sentence keeps up with the typing.
+const CALIBRATION_TICK_RATE: Duration = Duration::from_millis(30);
+
+/// Number of recent logger problems kept for the problems overlay.
+const PROBLEM_HISTORY: usize = 10;
+
+/// Difference to the typical pace (5%) below which the Overview trend
+/// counts as on par.
+const TREND_MARGIN: f64 = 0.05;
+
+/// Matches listed by the command palette at most.
+const PALETTE_MATCHES: usize = 8;
+
+/// Pause between two keys after which the Recent Keys panel shows the time.
+const RECENT_KEYS_GAP_SECS: i64 = 10;
+
+/// A command of the `:` command palette.
+pub struct PaletteCommand {
+    /// Words that find the command, e.g. `set goal`
+    pub name: &'static str,
+    /// What the arguments look like: empty for none, `[...]` if optional
+    pub args: &'static str,
+    /// What the command does, shown in the palette and the help
+    pub description: &'static str,
+    /// Runs the command with its arguments; the message is flashed in the
+    /// status bar, an error stays in the palette. There are no channels
+    /// while `ctrlq view` shows a saved file, see [`live`]
+    pub run: fn(&mut App, Option<&LoggerChannels>, &str) -> Result<Option<String>>,
+}
+
+impl PaletteCommand {
+    /// Checks the arguments against `args`, then runs the command.
+    fn execute(&self, app: &mut App, channels: Option<&LoggerChannels>, args: &str) -> Result<Option<String>> {
+        if self.args.is_empty() && !args.is_empty() {
+            bail!("{} takes no arguments", self.name);
+        }
+        if !self.args.is_empty() && !self.args.starts_with('[') && args.is_empty() {
+            bail!("usage: {} {}", self.name, self.args);
+        }
+        (self.run)(app, channels, args)
+    }
+}
+
+/// Every command of the command palette, in the order they are listed.
+pub const COMMANDS: &[PaletteCommand] = &[
+    PaletteCommand {
+        name: "reset session",
+        args: "",
+        description: "restart the current session",
+        run: |_, channels, _| {
+            live(channels)?.reset_tx.send(ResetScope::Session).context("logger is not running")?;
+            Ok(None)
+        },
+    },
+    PaletteCommand {
+        name: "reset today",
+        args: "",
+        description: "clear today's statistics",
+        run: |_, channels, _| {
+            live(channels)?.reset_tx.send(ResetScope::Today).context("logger is not running")?;
+            Ok(None)
+        },
+    },
+    PaletteCommand {
+        name: "export report",
+        args: "[file]",
+        description: "write a report of the session, Markdown or .json",
+        run: |app, channels, args| {
+            let path = match args {
+                "" => PathBuf::from(chrono::Local::now().format("ctrlq-session-%Y%m%d-%H%M.md").to_string()),
+                path => PathBuf::from(path),
+            };
+            report::write_session_export(&path, &app.stats.current_session())?;
+            if let Some(channels) = channels {
+                let _ = channels.command_tx.send(LoggerCommand::MarkExported);
+            }
+            Ok(Some(format!("📝 Session report written to {}", path.display())))
+        },
+    },
+    PaletteCommand {
+        name: "scope",
+        args: "today|week|all",
+        description: "time range of the Top Keys tab",
+        run: |app, _, args| {
+            app.key_scope = match args {
+                "today" | "t" => KeyScope::Today,
+                "week" | "w" => KeyScope::Week,
+                "all" | "a" => KeyScope::AllTime,
+                other => bail!("unknown scope '{}': use today, week or all", other),
+            };
+            app.selected_tab = 1;
+            Ok(Some(format!("🔝 Top Keys: {}", app.key_scope.label())))
+        },
+    },
+    PaletteCommand {
+        name: "unusual",
+        args: "english|code|<file>|off",
+        description: "keys used unusually much or little on Top Keys",
+        run: |app, _, args| {
+            let corpus = match args {
+                "off" => None,
+                "" => Some(Corpus::English),
+                spec => Some(Corpus::parse(spec)),
+            };
+            app.set_unusual_reference(corpus)?;
+            app.selected_tab = 1;
+            Ok(Some(match &app.unusual {
+                Some((corpus, _)) => format!("🔎 Unusual keys vs {}", corpus.label()),
+                None => "🔝 Top Keys".to_string(),
+            }))
+        },
+    },
+    PaletteCommand {
+        name: "set goal",
+        args: "<keystrokes>|off",
+        description: "daily goal until ctrlq exits",
+        run: |app, _, args| {
+            app.daily_goal = match args {
+                "off" => None,
+                goal => match goal.replace('_', "").parse::<u64>() {
+                    Ok(0) | Err(_) => bail!("'{}' is not a number of keystrokes: try set goal 15000", goal),
+                    Ok(goal) => Some(goal),
+                },
+            };
+            Ok(Some(match app.daily_goal {
+                Some(goal) => format!("🎯 Daily goal: {} keystrokes", numbers::format_count(goal)),
+                None => "🎯 Daily goal off".to_string(),
+            }))
+        },
+    },
+    PaletteCommand {
+        name: "pause",
+        args: "",
+        description: "stop counting keystrokes",
+        run: |app, channels, _| {
+            live(channels)?.command_tx.send(LoggerCommand::Pause).context("logger is not running")?;
+            app.paused = true;
+            Ok(None)
+        },
+    },
+    PaletteCommand {
+        name: "resume",
+        args: "",
+        description: "count keystrokes again after pause",
+        run: |app, channels, _| {
+            live(channels)?.command_tx.send(LoggerCommand::Resume).context("logger is not running")?;
+            app.paused = false;
+            Ok(None)
+        },
+    },
+    PaletteCommand {
+        name: "outside hours",
+        args: "[on|off]",
+        description: "count outside log_hours until midnight (default: toggle)",
+        run: |app, channels, args| {
+            let on = match args {
+                "" => !app.stats.diagnostics.hours_override,
+                "on" => true,
+                "off" => false,
+                other => bail!("'{}' is neither on nor off: try outside hours on", other),
+            };
+            live(channels)?.command_tx.send(LoggerCommand::OutsideHours(on)).context("logger is not running")?;
+            Ok(None)
+        },
+    },
+    PaletteCommand {
+        name: "save now",
+        args: "",
+        description: "save the statistics without waiting",
+        run: |_, channels, _| {
+            live(channels)?.command_tx.send(LoggerCommand::Save).context("logger is not running")?;
+            Ok(None)
+        },
+    },
+    PaletteCommand {
+        name: "switch device",
+        args: "[path]",
+        description: "log another keyboard (default: pick from a list)",
+        run: |app, channels, args| {
+            let channels = live(channels)?;
+            if args.is_empty() {
+                app.open_device_picker()?;
+                return Ok(None);
+            }
+            app.switch_device(channels, args)?;
+            Ok(Some(format!("🔌 Switching to {}", args)))
+        },
+    },
+    PaletteCommand {
+        name: "tag",
+        args: "<tag> [note]|off",
+        description: "label the current session, e.g. tag deep-work parser",
+        run: |app, channels, args| app.tag_session(live(channels)?, args),
+    },
+    PaletteCommand {
+        name: "calibrate",
+        args: "",
+        description: "typing test that calibrates the WPM estimate",
+        run: |app, channels, _| {
+            app.start_calibration(live(channels)?)?;
+            Ok(None)
+        },
+    },
+    PaletteCommand {
+        name: "help",
+        args: "",
+        description: "show the keys and commands",
+        run: |app, _, _| {
+            app.show_help = true;
+            Ok(None)
+        },
+    },
+    PaletteCommand {
+        name: "quit",
+        args: "",
+        description: "stop logging and exit",
+        run: |app, _, _| {
+            app.should_quit = true;
+            Ok(None)
+        },
+    },
+];
+
+/// Returns the channels of the running logger for commands that need one,
+/// or an error while `ctrlq view` shows a saved file.
+fn live(channels: Option<&LoggerChannels>) -> Result<&LoggerChannels> {
+    channels.context("viewing a saved file: this needs a running ctrlq")
+}
+
+/// Scores how well typed text matches a command name.
+///
+/// Every character must appear in the name in order; runs of characters
+/// and starts of words score higher, and shorter names win ties.
+///
+/// # Returns
+/// `Option<i32>` - The score, or None if the text doesn't match
+fn fuzzy_score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.chars().flat_map(char::to_lowercase) {
+        let found = (next..name.len()).find(|&i| name[i] == c)?;
+        score += 10;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 15;
+        }
+        if found == 0 || name[found - 1] == ' ' {
+            score += 10;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score - name.len() as i32)
+}
+
+/// Finds the commands matching the palette input, best first.
+///
+/// The leading words of the input select the command and the rest are its
+/// arguments, so `goal 15000` finds `set goal` with `15000`.
+///
+/// # Returns
+/// `Vec<(&PaletteCommand, String)>` - Matching commands and their arguments
+fn match_commands(input: &str) -> Vec<(&'static PaletteCommand, String)> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let mut matches: Vec<(i32, usize, &PaletteCommand, String)> = COMMANDS
+        .iter()
+        .filter_map(|command| {
+            if words.is_empty() {
+                return Some((0, 0, command, String::new()));
+            }
+            (1..=words.len()).rev().find_map(|taken| {
+                let score = fuzzy_score(&words[..taken].join(" "), command.name)?;
+                Some((score, taken, command, words[taken..].join(" ")))
+            })
+        })
+        .collect();
+    // More words matched first, so `scope week` isn't read as `scope` + nonsense
+    matches.sort_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(&a.0)));
+    matches.into_iter().map(|(_, _, command, args)| (command, args)).collect()
+}
+
+/// The `:` command palette while it is open.
+#[derive(Debug, Default)]
+pub struct Palette {
+    /// What was typed after the colon
+    pub input: String,
+    /// Index of the highlighted match
+    pub selected: usize,
+    /// Why the last command failed, shown until the input changes
+    pub error: Option<String>,
+}
+
+/// The keyboards offered by the device picker ('d').
+#[derive(Debug, Clone)]
+pub struct DevicePicker {
+    /// Keyboard candidates, best match first, see [`keylogger::find_keyboard_devices`]
+    pub devices: Vec<InputDeviceInfo>,
+    /// Whether each candidate is logged right now
+    pub logged: Vec<bool>,
+    /// Index of the highlighted candidate
+    pub selected: usize,
+}
+
+/// The typing test of `ctrlq calibrate` while it is open.
+#[derive(Debug, Clone)]
+pub struct CalibrationScreen {
+    /// The test being typed
+    pub test: TypingTest,
+    /// The result once the sentence is typed
+    pub result: Option<Calibration>,
+    /// The keyboard's calibration before this one, for comparison
+    pub previous: Option<Calibration>,
+}
+
+/// What the History tab compares, if anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareMode {
+    /// Plain daily history
+    Off,
+    /// Today against yesterday
+    Days,
+    /// This week against last week
+    Weeks,
+}
+
+impl CompareMode {
+    /// Returns the next mode when cycling with 'v'.
+    pub fn next(self) -> Self {
+        match self {
+            CompareMode::Off => CompareMode::Days,
+            CompareMode::Days => CompareMode::Weeks,
+            CompareMode::Weeks => CompareMode::Off,
+        }
+    }
+}
+
+/// Keys picked for the History tab's drift chart, see [`ctrlq::drift`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DriftView {
+    /// Position of the cursor in the key list
+    pub cursor: usize,
+    /// Evdev codes of the plotted keys, at most [`MAX_DRIFT_KEYS`]
+    pub keys: Vec<u16>,
+}
+
+/// How the Heatmap tab shows keys, see [`ctrlq::heatmap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapView {
+    /// Keystrokes of all keyboards
+    Single,
+    /// Two keyboards or profiles next to each other, in per-mille
+    SideBySide,
+    /// One keyboard colored by which side uses each key relatively more
+    Diff,
+}
+
+impl HeatmapView {
+    /// Returns the next view when cycling with 'v'.
+    pub fn next(self) -> Self {
+        match self {
+            HeatmapView::Single => HeatmapView::SideBySide,
+            HeatmapView::SideBySide => HeatmapView::Diff,
+            HeatmapView::Diff => HeatmapView::Single,
+        }
+    }
+}
+
+/// Time range the Top Keys tab counts keys over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyScope {
+    /// Today's key distribution
+    Today,
+    /// The last 7 days, today included
+    Week,
+    /// Lifetime key counts
+    AllTime,
+}
+
+impl KeyScope {
+    /// Name shown in the Top Keys title.
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyScope::Today => "Today",
+            KeyScope::Week => "Last 7 Days",
+            KeyScope::AllTime => "All Time",
+        }
+    }
+
+    /// The period the scope's key shares are compared with: yesterday for
+    /// today, the 7 days before for the last 7 days.
+    ///
+    /// # Returns
+    /// `Option<(NaiveDate, NaiveDate)>` - First and last day, or None for
+    /// all time
+    pub fn previous_period(self, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        let days = match self {
+            KeyScope::Today => 1,
+            KeyScope::Week => 7,
+            KeyScope::AllTime => return None,
+        };
+        let end = today.checked_sub_days(chrono::Days::new(days))?;
+        Some((end.checked_sub_days(chrono::Days::new(days - 1))?, end))
+    }
+
+    /// Name of the previous period in the Top Keys title.
+    pub fn previous_label(self) -> &'static str {
+        match self {
+            KeyScope::Today => "yesterday",
+            KeyScope::Week => "previous 7 days",
+            KeyScope::AllTime => "",
+        }
+    }
+}
+
+/// Lines of the Heatmap tab, kept until the statistics, the class filter
+/// or the view change so that redraws don't rebuild them.
+pub struct HeatmapCache {
+    /// Title and lines (keyboard and legend) of each column: one, or two
+    /// for [`HeatmapView::SideBySide`]
+    pub columns: Vec<(String, Vec<Line<'static>>)>,
+}
+
+/// What [`ScopedKeys`] are derived for: the statistics revision, the day
+/// (for the Today and Week scopes), the scope, the characters mode, the
+/// class filter and the reference of the unusual keys.
+pub type ScopedKeysFor = (u64, NaiveDate, KeyScope, bool, Option<KeyClass>, Option<String>);
+
+/// Key counts of the selected [`KeyScope`] and everything the Top Keys tab
+/// derives from them, kept until the statistics or the view change.
+pub struct ScopedKeys {
+    /// Scope the counts were computed for
+    pub scope: KeyScope,
+    /// Whether the counts are typed characters rather than keys
+    pub characters: bool,
+    /// Keystrokes in the scope, the base of the percentages
+    pub total: u64,
+    /// Change of each key's share since the previous period, None for all
+    /// time or too little typing in either period
+    pub changes: Option<HashMap<String, ShareChange>>,
+    /// The 20 most typed keys in the class filter, most typed first
+    pub top: Vec<(String, u64)>,
+    /// Effort of the counts on each layout, QWERTY first
+    pub effort: Vec<(layout::Layout, layout::EffortScore)>,
+    /// Effort of the session's keys
+    pub session_effort: layout::EffortScore,
+    /// Same-finger bigrams of the scope on each layout, QWERTY first and with
+    /// its worst pairs; none in the characters mode
+    pub same_finger: Vec<(layout::Layout, layout::SfbStats)>,
+    /// Name of the reference the keys are compared with ('u'), if any
+    pub unusual_reference: Option<String>,
+    /// Keys of the scope ranked against that reference, None without one
+    /// or with too little typing
+    pub unusual: Option<SurpriseReport>,
+}
 
 /// Main application state for the terminal UI.
 ///
@@ -43,12 +555,122 @@ use tokio::sync::watch;
 pub struct App {
     /// Whether the application should quit
     pub should_quit: bool,
-    /// Currently selected tab index (0-3)
+    /// Currently selected tab index (0-6)
     pub selected_tab: usize,
     /// Current keystroke statistics
     pub stats: KeyStats,
     /// When the statistics were last updated
     pub last_update: Instant,
+    /// When the keystroke counts last changed
+    pub last_change: Instant,
+    /// Key class shown on the Top Keys and Heatmap tabs (None = all)
+    pub class_filter: Option<KeyClass>,
+    /// Temporary message shown in the status bar and when it was set
+    pub status_message: Option<(String, Instant)>,
+    /// Whether statistics from the logger have been received yet
+    pub has_live_stats: bool,
+    /// Whether the help overlay is shown
+    pub show_help: bool,
+    /// Keys of the actions, see [`ctrlq::bindings`]
+    pub keys: KeyBindings,
+    /// Comparison shown on the History tab
+    pub compare_mode: CompareMode,
+    /// When a `--duration` run ends, if limited
+    pub deadline: Option<Instant>,
+    /// Scroll and selection state of the session table
+    pub session_table: TableState,
+    /// Start time of the selected session, so the selection survives refreshes
+    pub selected_session: Option<DateTime<Utc>>,
+    /// Whether the detail pane for the selected session is open
+    pub show_session_detail: bool,
+    /// Timeline bucket under the cursor (None = follow the current bucket)
+    pub timeline_cursor: Option<usize>,
+    /// Buckets per timeline column at the last render, used to move the cursor
+    pub timeline_step: usize,
+    /// Reset menu shown after pressing `r`, if open
+    pub reset_prompt: Option<ResetPrompt>,
+    /// What a full reset would remove, archived days included; set when
+    /// its confirmation opens
+    pub reset_loss: Option<ResetLoss>,
+    /// Name of the profile being logged, shown in the title
+    pub profile: String,
+    /// Time range of the Top Keys tab
+    pub key_scope: KeyScope,
+    /// Whether the Top Keys tab lists typed characters instead of keys
+    pub show_characters: bool,
+    /// Cached key counts of `key_scope`, see [`App::refresh_scoped_keys`]
+    pub scoped_keys: Derived<ScopedKeysFor, ScopedKeys>,
+    /// Corpus the Top Keys tab compares with instead of listing the top
+    /// keys, and its loaded distribution (None = off)
+    pub unusual: Option<(Corpus, Reference)>,
+    /// Text file offered after English and code by 'u' (`unusual_reference`
+    /// in the config)
+    pub custom_reference: Option<PathBuf>,
+    /// How the Heatmap tab shows keys
+    pub heatmap_view: HeatmapView,
+    /// Statistics compared on the Heatmap tab instead of two keyboards, and
+    /// their name (`ctrlq view --compare`)
+    pub compare_stats: Option<(String, Box<KeyStats>)>,
+    /// Cached Heatmap tab, see [`App::refresh_heatmap`]
+    pub heatmap: Derived<(u64, Option<KeyClass>, HeatmapView), HeatmapCache>,
+    /// Time between checks for input and new statistics (`--tick-ms`)
+    pub tick_rate: Duration,
+    /// Whether something changed since the last draw
+    pub needs_redraw: bool,
+    /// Whether the terminal has focus; stays true on terminals that don't
+    /// report focus changes
+    pub focused: bool,
+    /// Data file of the profile, used to find its archive
+    pub data_file: Option<PathBuf>,
+    /// Whether the statistics come from a saved file without a logger
+    /// (`ctrlq view`), so reset and device switching are off
+    pub read_only: bool,
+    /// Archived days, loaded when the History tab is first opened
+    pub archived_days: Option<HashMap<String, DayStats>>,
+    /// Day shown by the History tab's browse mode (None = the daily list)
+    pub browse_date: Option<NaiveDate>,
+    /// Keys of the History tab's drift chart (None = the daily list)
+    pub drift: Option<DriftView>,
+    /// Whether the History tab lists months instead of days
+    pub show_months: bool,
+    /// The last 12 months for the History tab, by revision, archived days
+    /// and the current day
+    pub months: Derived<(u64, usize, NaiveDate), Vec<MonthStats>>,
+    /// Daily keystroke goal from the config, if set
+    pub daily_goal: Option<u64>,
+    /// Logging hours from the config, the base of each day's coverage
+    pub log_hours: Option<Schedule>,
+    /// Recent errors of the logging thread with when they arrived, oldest first
+    pub problems: VecDeque<(DateTime<chrono::Local>, String)>,
+    /// Whether the problems overlay is shown
+    pub show_problems: bool,
+    /// Why the logging thread crashed, if it did
+    pub crash: Option<String>,
+    /// Characters of a device name shown before it is cut (0 = whole name)
+    pub device_name_width: usize,
+    /// The command palette, if open
+    pub palette: Option<Palette>,
+    /// The device picker, if open
+    pub device_picker: Option<DevicePicker>,
+    /// Whether the logger was told to stop counting
+    pub paused: bool,
+    /// Input of `:tag` that replaces an existing tag once run again
+    pub tag_confirm: Option<String>,
+    /// The typing test, if open
+    pub calibration: Option<CalibrationScreen>,
+    /// Earlier typing test results, loaded at startup
+    pub calibrations: CalibrationHistory,
+    /// Whether the Overview shows calibrated WPM (`calibrated_wpm` in the config)
+    pub calibrated_wpm: bool,
+}
+
+/// State of the reset menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetPrompt {
+    /// Choosing a scope
+    Menu,
+    /// Waiting for confirmation of a full reset
+    ConfirmAll,
 }
 
 impl App {
@@ -59,374 +681,3929 @@ impl App {
             selected_tab: 0,
             stats: KeyStats::new(),
             last_update: Instant::now(),
+            last_change: Instant::now(),
+            class_filter: None,
+            status_message: None,
+            has_live_stats: false,
+            show_help: false,
+            keys: KeyBindings::default(),
+            compare_mode: CompareMode::Off,
+            deadline: None,
+            session_table: TableState::default().with_selected(Some(0)),
+            selected_session: None,
+            show_session_detail: false,
+            timeline_cursor: None,
+            timeline_step: 1,
+            reset_prompt: None,
+            reset_loss: None,
+            profile: profile::DEFAULT_PROFILE.to_string(),
+            key_scope: KeyScope::AllTime,
+            show_characters: false,
+            scoped_keys: Derived::default(),
+            unusual: None,
+            custom_reference: None,
+            heatmap_view: HeatmapView::Single,
+            compare_stats: None,
+            heatmap: Derived::default(),
+            tick_rate: Duration::from_millis(250),
+            needs_redraw: true,
+            focused: true,
+            data_file: None,
+            read_only: false,
+            archived_days: None,
+            browse_date: None,
+            drift: None,
+            show_months: false,
+            months: Derived::default(),
+            daily_goal: None,
+            log_hours: None,
+            problems: VecDeque::new(),
+            show_problems: false,
+            crash: None,
+            device_name_width: 40,
+            palette: None,
+            tag_confirm: None,
+            device_picker: None,
+            paused: false,
+            calibration: None,
+            calibrations: CalibrationHistory::default(),
+            calibrated_wpm: false,
         }
     }
 
-    /// Updates the app with new keystroke statistics.
-    ///
-    /// # Arguments
-    /// * `stats` - New statistics to display
-    pub fn update_stats(&mut self, stats: KeyStats) {
-        self.stats = stats;
-        self.last_update = Instant::now();
+    /// Aggregates the key (or character) counts of the selected scope into
+    /// `scoped_keys` and sorts them, unless the statistics revision and the
+    /// view are unchanged since the last call.
+    pub fn refresh_scoped_keys(&mut self) {
+        let characters = self.show_characters;
+        let (scope, class_filter, stats) = (self.key_scope, self.class_filter, &self.stats);
+        let reference = self.unusual.as_ref().map(|(_, reference)| reference);
+        let unusual_reference = reference.map(|reference| reference.name.clone());
+        let key = (stats.revision, keylogger::today(), scope, characters, class_filter, unusual_reference.clone());
+        self.scoped_keys.get(key, || {
+            let counts = match (scope, characters) {
+                (KeyScope::Today, false) => stats.recent_key_distribution(1),
+                (KeyScope::Week, false) => stats.recent_key_distribution(7),
+                (KeyScope::AllTime, false) => stats.key_counts.clone(),
+                (KeyScope::Today, true) => stats.recent_char_distribution(1),
+                (KeyScope::Week, true) => stats.recent_char_distribution(7),
+                (KeyScope::AllTime, true) => stats.char_counts.clone(),
+            };
+            let total = counts.values().sum();
+            let bigrams = match (scope, characters) {
+                (_, true) => HashMap::new(),
+                (KeyScope::Today, false) => stats.recent_bigram_distribution(1),
+                (KeyScope::Week, false) => stats.recent_bigram_distribution(7),
+                (KeyScope::AllTime, false) => stats.bigram_counts.clone(),
+            };
+            let changes = scope.previous_period(keylogger::today()).and_then(|(start, end)| {
+                let previous = if characters {
+                    stats.char_distribution_between(start, end)
+                } else {
+                    stats.key_distribution_between(start, end)
+                };
+                keylogger::share_changes(&counts, &previous)
+            });
+            // Characters aren't keys, so they have no class, effort or fingers
+            let top = keylogger::top_keys(&counts, 20, class_filter.filter(|_| !characters));
+            let effort = layout::compare(&counts, layout::Layout::Qwerty);
+            let session_effort = layout::score(&stats.session_key_counts, layout::Layout::Qwerty, layout::Layout::Qwerty);
+            let same_finger = layout::Layout::ALL
+                .iter()
+                .map(|&priced_on| {
+                    let limit = if priced_on == layout::Layout::Qwerty { 5 } else { 0 };
+                    (priced_on, layout::same_finger_bigrams(&bigrams, layout::Layout::Qwerty, priced_on, limit))
+                })
+                .collect();
+            // Ranked on keys and characters alike, whichever the statistics have
+            let unusual = reference.and_then(|reference| {
+                let (keys, chars) = match scope {
+                    KeyScope::Today => (stats.recent_key_distribution(1), stats.recent_char_distribution(1)),
+                    KeyScope::Week => (stats.recent_key_distribution(7), stats.recent_char_distribution(7)),
+                    KeyScope::AllTime => (stats.key_counts.clone(), stats.char_counts.clone()),
+                };
+                surprise::rank(&Observed::collect(&keys, &chars, stats.case_sensitive), reference)
+            });
+            ScopedKeys {
+                scope,
+                characters,
+                total,
+                changes,
+                top,
+                effort,
+                session_effort,
+                same_finger,
+                unusual_reference,
+                unusual,
+            }
+        });
     }
-}
 
-/// Runs the main terminal UI event loop.
-///
-/// Initializes the terminal, handles user input, and displays real-time
-/// keystroke statistics across multiple tabs.
-///
-/// # Arguments
-/// * `stats_rx` - Channel to receive statistics updates
-/// * `shutdown_tx` - Channel to send shutdown signal
-/// * `reset_tx` - Channel to send reset signal
-///
-/// # Returns
-/// `Result<()>` - Success or terminal/UI error
-pub fn run_ui(
-    stats_rx: mpsc::Receiver<KeyStats>,
-    shutdown_tx: watch::Sender<bool>,
-    reset_tx: mpsc::Sender<()>,
-) -> Result<()> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    /// Builds the Heatmap tab's lines into `heatmap`, unless the statistics
+    /// revision, the class filter and the view are unchanged since the last
+    /// call. A comparison that has no two sides (any more) shows all keys.
+    pub fn refresh_heatmap(&mut self) {
+        let (class_filter, view) = (self.class_filter, self.heatmap_view);
+        let comparison = (view != HeatmapView::Single)
+            .then(|| self.heatmap_comparison(class_filter))
+            .flatten();
+        let stats = &self.stats;
+        self.heatmap.get((stats.revision, class_filter, view), || {
+            let columns = match (&comparison, view) {
+                (Some(comparison), HeatmapView::SideBySide) => vec![
+                    (filtered_title(&format!("{} (‰)", comparison.label_a), class_filter), share_lines(&comparison.a)),
+                    (filtered_title(&format!("{} (‰)", comparison.label_b), class_filter), share_lines(&comparison.b)),
+                ],
+                (Some(comparison), _) => vec![(
+                    filtered_title(&format!("{} vs {} (‰ points)", comparison.label_a, comparison.label_b), class_filter),
+                    diff_lines(comparison),
+                )],
+                (None, _) => vec![(filtered_title("Keyboard Heatmap", class_filter), heatmap_lines(stats, class_filter))],
+            };
+            HeatmapCache { columns }
+        });
+    }
 
-    let mut app = App::new();
+    /// Returns the key distributions the Heatmap tab compares, limited to
+    /// a key class: the statistics of `ctrlq view --compare`, or else the
+    /// two most used keyboards with key counts.
+    ///
+    /// # Returns
+    /// `Option<HeatmapComparison>` - None without two sides to compare
+    pub fn heatmap_comparison(&self, class_filter: Option<KeyClass>) -> Option<HeatmapComparison> {
+        let in_filter = |counts: HashMap<u16, u64>| -> HashMap<u16, u64> {
+            counts
+                .into_iter()
+                .filter(|(code, _)| class_filter.is_none_or(|c| keymap::classify(*code) == c))
+                .collect()
+        };
+        if let Some((label, other)) = &self.compare_stats {
+            let own = self
+                .data_file
+                .as_deref()
+                .map_or_else(|| self.profile.clone(), data_file_label);
+            return Some(HeatmapComparison::new(
+                &own,
+                &in_filter(self.stats.physical_key_counts()),
+                label,
+                &in_filter(other.physical_key_counts()),
+            ));
+        }
+        let mut devices = self
+            .stats
+            .device_totals()
+            .into_iter()
+            .filter(|(device, _)| self.stats.device_key_counts.contains_key(device));
+        let (a, _) = devices.next()?;
+        let (b, _) = devices.next()?;
+        Some(HeatmapComparison::new(
+            &keylogger::truncate_name(&a, self.device_name_width),
+            &in_filter(self.stats.device_physical_key_counts(&a)),
+            &keylogger::truncate_name(&b, self.device_name_width),
+            &in_filter(self.stats.device_physical_key_counts(&b)),
+        ))
+    }
 
-    let res = run_app(&mut terminal, &mut app, stats_rx, shutdown_tx, reset_tx);
+    /// Switches the Heatmap tab to its next view, if there is something to
+    /// compare.
+    pub fn cycle_heatmap_view(&mut self) {
+        if self.heatmap_view == HeatmapView::Single && self.heatmap_comparison(None).is_none() {
+            self.flash(
+                "⌨️  Comparing heatmaps needs two keyboards with key counts, or ctrlq view --compare <profile>".to_string(),
+            );
+            return;
+        }
+        self.heatmap_view = self.heatmap_view.next();
+    }
 
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    /// Compares the Top Keys tab with a reference corpus instead of listing
+    /// the top keys, or lists them again.
+    ///
+    /// # Arguments
+    /// * `corpus` - What to compare with, None to stop comparing
+    ///
+    /// # Returns
+    /// `Result<()>` - Success, or an error if a reference file can't be read
+    pub fn set_unusual_reference(&mut self, corpus: Option<Corpus>) -> Result<()> {
+        self.unusual = match corpus {
+            Some(corpus) => {
+                let reference = corpus.load()?;
+                Some((corpus, reference))
+            }
+            None => None,
+        };
+        Ok(())
+    }
 
-    if let Err(err) = res {
-        println!("{:?}", err);
+    /// Moves on to the next reference of the Top Keys tab: off, English,
+    /// code, then the configured text file if there is one.
+    pub fn cycle_unusual_reference(&mut self) {
+        let next = match self.unusual.as_ref().map(|(corpus, _)| corpus) {
+            None => Some(Corpus::English),
+            Some(Corpus::English) => Some(Corpus::Code),
+            Some(Corpus::Code) => self.custom_reference.clone().map(Corpus::File),
+            Some(Corpus::File(_)) => None,
+        };
+        if let Err(e) = self.set_unusual_reference(next) {
+            self.unusual = None;
+            self.flash(format!("❌ {:#}", e));
+        }
     }
 
-    Ok(())
-}
+    /// Loads the archived days for the History tab, unless already loaded.
+    pub fn refresh_archived_days(&mut self) {
+        if self.archived_days.is_none() {
+            let days = self.data_file.as_deref().and_then(|path| archive::load_days(path).ok());
+            self.archived_days = Some(days.unwrap_or_default());
+        }
+    }
 
-fn run_app<B: Backend>(
-    terminal: &mut Terminal<B>,
-    app: &mut App,
-    stats_rx: mpsc::Receiver<KeyStats>,
-    shutdown_tx: watch::Sender<bool>,
-    reset_tx: mpsc::Sender<()>,
-) -> Result<()> {
-    let mut last_tick = Instant::now();
-    let tick_rate = Duration::from_millis(250);
+    /// Dates of all days with statistics, in memory or archived.
+    fn recorded_days(&self) -> impl Iterator<Item = &String> {
+        self.stats.daily_stats.keys().chain(self.archived_days.iter().flat_map(|days| days.keys()))
+    }
 
-    loop {
-        terminal.draw(|f| ui(f, app))?;
+    /// Turns the History tab's browse mode on, at the most recent recorded
+    /// day, or off again.
+    pub fn toggle_browse(&mut self) {
+        if self.browse_date.take().is_some() {
+            return;
+        }
+        self.refresh_archived_days();
+        let latest = self.recorded_days().max().and_then(|date| parse_day(date));
+        self.browse_date = Some(latest.unwrap_or_else(keylogger::today));
+        self.compare_mode = CompareMode::Off;
+        self.drift = None;
+        self.show_months = false;
+    }
 
-        let timeout = tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or_else(|| Duration::from_secs(0));
+    /// Switches the History tab between the daily list and the months.
+    pub fn toggle_months(&mut self) {
+        self.show_months = !self.show_months;
+        self.refresh_archived_days();
+        self.browse_date = None;
+        self.compare_mode = CompareMode::Off;
+        self.drift = None;
+    }
 
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => {
-                        app.should_quit = true;
-                    }
-                    KeyCode::Tab => {
-                        app.selected_tab = (app.selected_tab + 1) % 4;
-                    }
-                    KeyCode::Char('r') => {
-                        if reset_tx.send(()).is_ok() {
-                            app.stats = KeyStats::new();
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
+    /// Aggregates the last 12 months for the History tab, unless the
+    /// statistics are unchanged since the last time.
+    pub fn refresh_months(&mut self) {
+        self.refresh_archived_days();
+        let archived = self.archived_days.as_ref().expect("refreshed above");
+        let today = keylogger::today();
+        let stats = &self.stats;
+        self.months.get((stats.revision, archived.len(), today), || stats.recent_months(HISTORY_MONTHS, today, archived));
+    }
 
-        while let Ok(stats) = stats_rx.try_recv() {
-            app.update_stats(stats);
+    /// Turns the History tab's drift chart on, plotting the most used key,
+    /// or off again.
+    pub fn toggle_drift(&mut self) {
+        if self.drift.take().is_some() {
+            return;
         }
+        self.refresh_archived_days();
+        let keys = drift_candidates(&self.stats).first().map(|&(code, _)| vec![code]).unwrap_or_default();
+        self.drift = Some(DriftView { cursor: 0, keys });
+        self.browse_date = None;
+        self.compare_mode = CompareMode::Off;
+        self.show_months = false;
+    }
 
-        if last_tick.elapsed() >= tick_rate {
-            last_tick = Instant::now();
+    /// Moves the cursor of the drift chart's key list by `rows`.
+    pub fn move_drift_cursor(&mut self, rows: isize) {
+        let count = drift_candidates(&self.stats).len();
+        if let Some(view) = &mut self.drift {
+            view.cursor = view.cursor.saturating_add_signed(rows).min(count.saturating_sub(1));
         }
+    }
 
-        if app.should_quit {
-            let _ = shutdown_tx.send(true);
-            break;
+    /// Adds the key under the cursor to the drift chart, or removes it.
+    pub fn toggle_drift_key(&mut self) {
+        let candidates = drift_candidates(&self.stats);
+        let Some(view) = &mut self.drift else {
+            return;
+        };
+        let Some(&(code, _)) = candidates.get(view.cursor) else {
+            return;
+        };
+        if let Some(index) = view.keys.iter().position(|&key| key == code) {
+            view.keys.remove(index);
+        } else if view.keys.len() < MAX_DRIFT_KEYS {
+            view.keys.push(code);
+        } else {
+            self.flash(format!("📈 Up to {} keys at once; Enter on a plotted key removes it", MAX_DRIFT_KEYS));
         }
     }
 
-    Ok(())
-}
+    /// Moves the browse cursor to the previous (`-1`) or next (`1`) day with
+    /// statistics, skipping days without any.
+    pub fn move_browse_day(&mut self, direction: isize) {
+        let Some(date) = self.browse_date else {
+            return;
+        };
+        let current = keylogger::day_key(date);
+        let days = self.recorded_days().map(String::as_str);
+        let target = if direction < 0 {
+            days.filter(|day| *day < current.as_str()).max()
+        } else {
+            days.filter(|day| *day > current.as_str()).min()
+        };
+        if let Some(day) = target.and_then(parse_day) {
+            self.browse_date = Some(day);
+        }
+    }
 
-fn ui(f: &mut Frame, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(0),
-        ])
-        .split(f.area());
+    /// Moves the browse cursor by whole weeks. The cursor may land on a day
+    /// without statistics, which is shown as empty.
+    pub fn move_browse_week(&mut self, weeks: i64) {
+        if let Some(date) = self.browse_date {
+            let moved = date + chrono::Duration::weeks(weeks);
+            let earliest = self.recorded_days().min().and_then(|day| parse_day(day)).unwrap_or(moved);
+            self.browse_date = Some(moved.clamp(earliest.min(date), keylogger::today().max(date)));
+        }
+    }
 
-    let titles: Vec<Line> = vec!["Overview", "Top Keys", "Heatmap", "Sessions"]
-        .iter()
-        .cloned()
-        .map(Line::from)
-        .collect();
-    
-    let tabs = Tabs::new(titles)
-        .block(Block::default().borders(Borders::ALL).title("CtrlQ - Developer Keylogger"))
-        .style(Style::default().fg(Color::White))
-        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
-        .select(app.selected_tab);
-    
-    f.render_widget(tabs, chunks[0]);
+    /// Moves the timeline cursor by `columns` display columns.
+    pub fn move_timeline_cursor(&mut self, columns: isize) {
+        let current = DayTimeline::bucket_index(chrono::Local::now());
+        let cursor = self.timeline_cursor.unwrap_or(current) as isize;
+        let moved = (cursor + columns * self.timeline_step as isize).clamp(0, current as isize) as usize;
+        // Reaching the present again resumes following it
+        self.timeline_cursor = (moved / self.timeline_step != current / self.timeline_step).then_some(moved);
+    }
 
-    match app.selected_tab {
-        0 => render_overview(f, chunks[1], app),
-        1 => render_top_keys(f, chunks[1], app),
-        2 => render_heatmap(f, chunks[1], app),
-        3 => render_sessions(f, chunks[1], app),
-        _ => {}
+    /// Returns the rows of the session table, newest first.
+    ///
+    /// The first row is always the session in progress.
+    pub fn session_rows(&self) -> Vec<TypingSession> {
+        std::iter::once(self.stats.current_session())
+            .chain(self.stats.typing_sessions.iter().rev().cloned())
+            .collect()
     }
-}
 
-fn render_overview(f: &mut Frame, area: Rect, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(7),
-            Constraint::Length(10),
-            Constraint::Min(0),
-        ])
-        .split(area);
+    /// Moves the session selection by `delta` rows, clamped to the table.
+    pub fn move_session_selection(&mut self, delta: isize) {
+        let rows = self.session_rows();
+        let current = self.selected_session_index(&rows) as isize;
+        let index = (current + delta).clamp(0, rows.len() as isize - 1) as usize;
+        self.selected_session = Some(rows[index].start);
+    }
 
-    let total_keys = app.stats.total_keystrokes;
-    let wpm = app.stats.get_wpm().unwrap_or(0.0);
-    let session_duration = chrono::Utc::now()
-        .signed_duration_since(app.stats.session_start)
-        .num_minutes();
+    /// Finds the table row of the selected session, falling back to the top.
+    fn selected_session_index(&self, rows: &[TypingSession]) -> usize {
+        self.selected_session
+            .and_then(|start| rows.iter().position(|s| s.start == start))
+            .unwrap_or(0)
+    }
 
-    let summary = Paragraph::new(format!(
-        "📊 Session Stats\n\
-         Total Keystrokes: {}\n\
-         Session Duration: {} minutes\n\
-         Typing Speed: {:.1} WPM\n\
-         Unique Keys: {}",
-        total_keys,
-        session_duration,
-        wpm,
-        app.stats.key_counts.len()
-    ))
-    .block(Block::default().borders(Borders::ALL).title("Summary"))
-    .style(Style::default().fg(Color::Green));
+    /// Updates the app with new keystroke statistics.
+    ///
+    /// # Arguments
+    /// * `stats` - New statistics to display
+    pub fn update_stats(&mut self, stats: KeyStats) {
+        if self.has_live_stats {
+            for record in stats.records.broken_since(&self.stats.records) {
+                self.flash(format!("🏆 New record: {}!", record));
+            }
+        }
+        if stats.total_keystrokes != self.stats.total_keystrokes
+            || stats.session_keystrokes != self.stats.session_keystrokes
+        {
+            self.last_change = Instant::now();
+        }
+        // Fewer days means some were just archived (or reset) - reload the archive
+        if stats.daily_stats.len() < self.stats.daily_stats.len() {
+            self.archived_days = None;
+        }
+        // Cached tabs are keyed by the revision and rebuild themselves
+        self.stats = stats;
+        self.has_live_stats = true;
+        self.needs_redraw = true;
+        self.last_update = Instant::now();
+    }
 
-    f.render_widget(summary, chunks[0]);
+    /// Shows a temporary message in the status bar.
+    ///
+    /// # Arguments
+    /// * `message` - Text to display
+    pub fn flash(&mut self, message: String) {
+        self.status_message = Some((message, Instant::now()));
+        self.needs_redraw = true;
+    }
 
-    let recent_keys: String = app.stats.key_sequences
-        .iter()
-        .rev()
-        .take(20)
-        .map(|k| {
-            match k.as_str() {
-                "KEY_SPACE" => " ",
-                "KEY_ENTER" => "↵",
-                "KEY_TAB" => "⭾",
-                "KEY_BACKSPACE" => "⌫",
-                k if k.starts_with("KEY_") => &k[4..],
-                _ => k,
+    /// Takes in a problem reported by the logging thread and opens the
+    /// problems overlay for it.
+    pub fn report_problem(&mut self, problem: LoggerProblem) {
+        match problem {
+            LoggerProblem::Error(message) => {
+                if self.problems.len() >= PROBLEM_HISTORY {
+                    self.problems.pop_front();
+                }
+                self.problems.push_back((chrono::Local::now(), message));
+                self.show_problems = true;
             }
-        })
-        .collect::<Vec<_>>()
-        .into_iter()
-        .rev()
-        .collect::<Vec<_>>()
-        .join(" ");
-
-    let recent = Paragraph::new(recent_keys)
-        .block(Block::default().borders(Borders::ALL).title("Recent Keys"))
-        .wrap(Wrap { trim: true })
-        .style(Style::default().fg(Color::Cyan));
+            LoggerProblem::Crashed(message) => self.crash = Some(message),
+        }
+        self.needs_redraw = true;
+    }
 
-    f.render_widget(recent, chunks[1]);
+    /// Asks to confirm a full reset, working out first what it would
+    /// remove, archived days included.
+    pub fn open_reset_confirmation(&mut self) {
+        let mut full = self.stats.clone();
+        if let Some(data_file) = &self.data_file
+            && let Err(e) = archive::load_into(&mut full, data_file)
+        {
+            self.flash(format!("❌ Archived days not counted: {:#}", e));
+        }
+        self.reset_loss = Some(full.reset_loss(ResetScope::All { keep_records: false }));
+        self.reset_prompt = Some(ResetPrompt::ConfirmAll);
+    }
 
-    let activity_level = if total_keys > 0 { 
-        std::cmp::min(100, (total_keys as f64 / 10.0) as u16)
-    } else { 
-        0 
-    };
+    /// Runs a command of the palette without arguments, as bound to a key;
+    /// its message or error is flashed in the status bar.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the command, see [`COMMANDS`]
+    /// * `channels` - Channels of the running logger, if any
+    pub fn run_command(&mut self, name: &str, channels: Option<&LoggerChannels>) {
+        let Some(command) = COMMANDS.iter().find(|command| command.name == name) else {
+            return;
+        };
+        match command.execute(self, channels, "") {
+            Ok(Some(message)) => self.flash(message),
+            Ok(None) => {}
+            Err(e) => self.flash(format!("❌ {:#}", e)),
+        }
+    }
 
-    let gauge = Gauge::default()
-        .block(Block::default().borders(Borders::ALL).title("Activity Level"))
-        .gauge_style(Style::default().fg(Color::Magenta))
-        .percent(activity_level)
-        .label(format!("{}%", activity_level));
+    /// Runs the command highlighted in the palette, closing the palette
+    /// unless the command fails.
+    pub fn run_palette(&mut self, channels: Option<&LoggerChannels>) {
+        let Some(palette) = &self.palette else {
+            return;
+        };
+        let matches = match_commands(&palette.input);
+        let Some((command, args)) = matches.get(palette.selected.min(matches.len().saturating_sub(1))) else {
+            if let Some(palette) = &mut self.palette {
+                palette.error = Some(format!("no command matches '{}'", palette.input.trim()));
+            }
+            return;
+        };
+        match command.execute(self, channels, args) {
+            Ok(message) => {
+                self.palette = None;
+                if let Some(message) = message {
+                    self.flash(message);
+                }
+            }
+            Err(e) => {
+                if let Some(palette) = &mut self.palette {
+                    palette.error = Some(format!("{:#}", e));
+                }
+            }
+        }
+    }
+
+    /// Tags the current session, see [`keylogger::parse_session_label`].
+    ///
+    /// A tag that differs from the one the session already has is only
+    /// replaced when the same input is run a second time.
+    ///
+    /// # Arguments
+    /// * `channels` - Channels of the running logger
+    /// * `args` - `<tag> [note]`, or `off` to remove the tag
+    ///
+    /// # Returns
+    /// `Result<Option<String>>` - Nothing to flash (the logger confirms),
+    /// or an error asking for confirmation or describing a bad tag
+    pub fn tag_session(&mut self, channels: &LoggerChannels, args: &str) -> Result<Option<String>> {
+        let (tag, note) = match args {
+            "off" => (None, None),
+            args => {
+                let (tag, note) = keylogger::parse_session_label(args)?;
+                (Some(tag), note)
+            }
+        };
+        let current = (&self.stats.session_tag, &self.stats.session_note);
+        if let Some(existing) = &self.stats.session_tag
+            && current != (&tag, &note)
+            && self.tag_confirm.as_deref() != Some(args)
+        {
+            self.tag_confirm = Some(args.to_string());
+            bail!("this session is tagged {} already: press Enter again to replace it", existing);
+        }
+        self.tag_confirm = None;
+        channels
+            .command_tx
+            .send(LoggerCommand::TagSession { tag, note })
+            .context("logger is not running")?;
+        Ok(None)
+    }
+
+    /// Opens the palette on `:tag`, with the current tag and note to edit.
+    pub fn open_tag_input(&mut self) {
+        let mut input = "tag ".to_string();
+        if let Some(tag) = &self.stats.session_tag {
+            input.push_str(tag);
+            if let Some(note) = &self.stats.session_note {
+                input.push(' ');
+                input.push_str(note);
+            }
+        }
+        self.palette = Some(Palette { input, ..Palette::default() });
+    }
+
+    /// Has the logger read another keyboard instead of the current ones.
+    /// The logger keeps the old keyboard if the new one can't be opened.
+    ///
+    /// # Arguments
+    /// * `channels` - Channels of the running logger
+    /// * `path` - Event device path or a link to it
+    pub fn switch_device(&mut self, channels: &LoggerChannels, path: &str) -> Result<()> {
+        channels
+            .command_tx
+            .send(LoggerCommand::SwitchDevice(path.to_string()))
+            .context("logger is not running")
+    }
+
+    /// Scans for keyboards and opens the device picker on them, with the
+    /// keyboard the logger suggested (or the first one not logged) selected.
+    pub fn open_device_picker(&mut self) -> Result<()> {
+        let input_dir = paths::input_dir();
+        let devices = keylogger::find_keyboard_devices(&input_dir)
+            .with_context(|| format!("couldn't scan {}", input_dir.display()))?;
+        if devices.is_empty() {
+            bail!("no keyboard devices found");
+        }
+        let node = |path: &str| std::fs::canonicalize(path).ok();
+        let logged_nodes: Vec<_> = self.stats.diagnostics.devices.iter().filter_map(|device| node(&device.path)).collect();
+        let logged: Vec<bool> = devices
+            .iter()
+            .map(|device| node(&device.path).is_some_and(|node| logged_nodes.contains(&node)))
+            .collect();
+        let suggested = self.stats.diagnostics.device_suggestion.as_ref().map(|suggestion| node(&suggestion.path));
+        let selected = devices
+            .iter()
+            .position(|device| suggested.as_ref().is_some_and(|suggested| *suggested == node(&device.path)))
+            .or_else(|| logged.iter().position(|logged| !logged))
+            .unwrap_or(0);
+        self.device_picker = Some(DevicePicker { devices, logged, selected });
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Switches to the keyboard highlighted in the device picker and closes it.
+    pub fn pick_device(&mut self, channels: &LoggerChannels) {
+        let Some(picker) = self.device_picker.take() else {
+            return;
+        };
+        let device = &picker.devices[picker.selected];
+        let name = device.display_name(self.device_name_width);
+        if picker.logged[picker.selected] {
+            self.flash(format!("⌨️  Already logging {}", name));
+            return;
+        }
+        match self.switch_device(channels, &device.path) {
+            Ok(()) => self.flash(format!("🔌 Switching to {}", name)),
+            Err(e) => self.flash(format!("❌ {:#}", e)),
+        }
+    }
+
+    /// Opens the typing test and has the logger forward typed characters.
+    pub fn start_calibration(&mut self, channels: &LoggerChannels) -> Result<()> {
+        if self.paused {
+            bail!("logging is paused: resume first, the test reads the keyboard through the logger");
+        }
+        channels.command_tx.send(LoggerCommand::Capture(true)).context("logger is not running")?;
+        // Keys of an earlier test that arrived after it closed
+        while channels.typed_rx.try_recv().is_ok() {}
+        let prompt = calibration::prompt(Utc::now().timestamp() as u64);
+        self.calibration = Some(CalibrationScreen { test: TypingTest::new(prompt), result: None, previous: None });
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Closes the typing test, also when it is still running.
+    pub fn stop_calibration(&mut self, channels: &LoggerChannels) {
+        if self.calibration.take().is_some_and(|screen| screen.result.is_none()) {
+            let _ = channels.command_tx.send(LoggerCommand::Capture(false));
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Applies a typed character to the typing test, and scores and stores
+    /// the test once the sentence is complete.
+    pub fn calibration_input(&mut self, key: TypedKey, channels: &LoggerChannels) {
+        let Some(screen) = &mut self.calibration else {
+            return;
+        };
+        self.needs_redraw = true;
+        if screen.result.is_some() {
+            return;
+        }
+        screen.test.input(&key);
+        if !screen.test.is_finished() {
+            return;
+        }
+        let _ = channels.command_tx.send(LoggerCommand::Capture(false));
+        let Some(run) = screen.test.result(Utc::now()) else {
+            self.calibration = None;
+            self.flash("❌ The test was too short to measure, try again".to_string());
+            return;
+        };
+        screen.previous = self.calibrations.latest(&run.device).cloned();
+        screen.result = Some(run.clone());
+        let saved = match &self.data_file {
+            Some(data_file) => self.calibrations.record(&CalibrationHistory::path(data_file), run),
+            None => Err(anyhow::anyhow!("no data directory")),
+        };
+        if let Err(e) = saved {
+            self.flash(format!("❌ Calibration not saved: {:#}", e));
+        }
+    }
+
+    /// Returns the calibration factor for the Overview, if enabled: the
+    /// one of the keyboard typed on most today.
+    pub fn calibration_factor(&self) -> Option<f64> {
+        if !self.calibrated_wpm {
+            return None;
+        }
+        let today = self.stats.daily_stats.get(&keylogger::day_key(keylogger::today()));
+        let device = today.and_then(|day| {
+            day.device_keystrokes.iter().max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        });
+        self.calibrations.factor_for(device.map(|(device, _)| device.as_str()))
+    }
+
+    /// Returns the status bar message if it hasn't expired yet.
+    pub fn current_status(&self) -> Option<&str> {
+        self.status_message
+            .as_ref()
+            .filter(|(_, set_at)| set_at.elapsed() < STATUS_MESSAGE_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+}
+
+/// Where the terminal UI gets its statistics from.
+pub enum StatsSource {
+    /// A running logger, whose statistics update as keys are typed
+    Live(LoggerChannels),
+    /// Statistics loaded once from a data file, for `ctrlq view`
+    Static {
+        /// The statistics to show
+        stats: Box<KeyStats>,
+        /// The file they came from, for the title and the archive
+        data_file: PathBuf,
+        /// Statistics to compare heatmaps with and their name (`--compare`)
+        compare: Option<(String, Box<KeyStats>)>,
+    },
+}
+
+/// How a terminal UI run starts, besides its statistics and configuration.
+#[derive(Debug, Clone, Default)]
+pub struct UiOptions {
+    /// When a `--duration` run ends, shown as a countdown
+    pub deadline: Option<Instant>,
+    /// Time between checks for input and new statistics
+    pub tick_rate: Duration,
+    /// Open the typing test of `ctrlq calibrate` right away
+    pub calibrate: bool,
+    /// First status bar message: what was printed before the UI started,
+    /// see [`ctrlq::output::Presenter::defer`]
+    pub startup: Option<String>,
+}
+
+/// Runs the main terminal UI event loop.
+///
+/// Initializes the terminal, handles user input, and displays real-time
+/// keystroke statistics across multiple tabs. With [`StatsSource::Static`]
+/// nothing is logged and the keys that need a logger only explain why
+/// they do nothing.
+///
+/// # Arguments
+/// * `source` - The running logger, or statistics to view
+/// * `profile` - Name of the profile being logged
+/// * `config` - User configuration, for the daily goal and display settings
+/// * `options` - Countdown, tick rate and what to show first
+/// * `restart` - Starts a new logging thread after the old one crashed
+///
+/// # Returns
+/// `Result<Option<LoggerChannels>>` - The channels of the logger when the UI
+/// ended (None when viewing), or a terminal error after the screen was restored
+pub fn run_ui(
+    source: StatsSource,
+    profile: &str,
+    config: &Config,
+    options: UiOptions,
+    mut restart: impl FnMut() -> Result<LoggerChannels>,
+) -> Result<Option<LoggerChannels>> {
+    let guard = TerminalGuard::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    // Panics of the logging thread are caught and shown in the UI; any
+    // other panic must leave the terminal usable before it is printed,
+    // which happens before the guard is dropped while unwinding
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name() == Some(LOGGER_THREAD) {
+            return;
+        }
+        restore_terminal();
+        default_hook(info);
+    }));
+
+    let mut app = App::new();
+    app.deadline = options.deadline;
+    app.daily_goal = config.daily_goal;
+    app.log_hours = config.log_hours.clone();
+    app.tick_rate = options.tick_rate;
+    app.device_name_width = config.device_name_width;
+    app.calibrated_wpm = config.calibrated_wpm;
+    app.keys = config.keys.clone();
+    app.custom_reference = config.unusual_reference.clone();
+    app.profile = profile.to_string();
+    let channels = match source {
+        StatsSource::Live(channels) => {
+            app.data_file = profile::data_file(profile).ok();
+            Some(channels)
+        }
+        StatsSource::Static { stats, data_file, compare } => {
+            app.stats = *stats;
+            // Comparing is what --compare is for
+            if compare.is_some() {
+                app.heatmap_view = HeatmapView::SideBySide;
+            }
+            app.compare_stats = compare;
+            app.data_file = Some(data_file);
+            app.read_only = true;
+            None
+        }
+    };
+    if let Some(data_file) = &app.data_file {
+        match CalibrationHistory::load(&CalibrationHistory::path(data_file)) {
+            Ok(history) => app.calibrations = history,
+            Err(e) => app.flash(format!("❌ {:#}", e)),
+        }
+    }
+    if let Some(startup) = options.startup {
+        app.flash(startup);
+    }
+    if options.calibrate
+        && let Some(channels) = &channels
+        && let Err(e) = app.start_calibration(channels)
+    {
+        app.flash(format!("❌ {:#}", e));
+    }
+
+    let res = run_app(&mut terminal, &mut app, channels, &mut restart);
+
+    let _ = std::panic::take_hook();
+    drop(guard);
+
+    res.context("the terminal UI failed")
+}
+
+/// Runs the UI on a running logger, see [`run_ui`]. However the UI ends,
+/// also by panicking, the logger saves the statistics first: the UI
+/// either told it to stop or dropped its channels, so it stops on its own.
+///
+/// # Arguments
+/// * `keylogger` - The logger, already started
+/// * `channels` - The channels of the logger
+/// * `profile` - Name of the profile being logged
+/// * `config` - User configuration, for the daily goal and display settings
+/// * `options` - Countdown, tick rate and what to show first
+///
+/// # Returns
+/// `Result<Option<LoggerChannels>>` - The channels of the stopped logger, with
+/// what it sent on its way out, or the error of the UI. A panic of the UI is
+/// resumed once the logger has saved, so the exit code still reports it.
+pub fn run_live_ui(
+    keylogger: &mut KeyLogger,
+    channels: LoggerChannels,
+    profile: &str,
+    config: &Config,
+    options: UiOptions,
+) -> Result<Option<LoggerChannels>> {
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        run_ui(StatsSource::Live(channels), profile, config, options, || keylogger.restart_logging())
+    }));
+    if let Ok(Ok(Some(channels))) = &result {
+        let _ = channels.shutdown_tx.send(true);
+    }
+    keylogger.wait();
+    match result {
+        Ok(result) => result,
+        // The panic was already printed
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+/// Terminal sizes `check_render` draws each tab at: the classic 80x24 and a
+/// large window.
+const CHECK_SIZES: [(u16, u16); 2] = [(80, 24), (200, 60)];
+
+/// Draws one frame of every tab into memory, without a terminal, for
+/// `ctrlq doctor --offline`.
+///
+/// # Arguments
+/// * `stats` - Statistics to show
+/// * `data_file` - Their data file, for the archived days of the History tab
+/// * `config` - User configuration, for the daily goal and display settings
+///
+/// # Returns
+/// `Result<usize>` - Frames drawn, or an error naming the first tab that
+/// failed to draw
+pub fn check_render(stats: KeyStats, data_file: &Path, config: &Config) -> Result<usize> {
+    let mut app = App::new();
+    app.stats = stats;
+    app.data_file = Some(data_file.to_path_buf());
+    app.read_only = true;
+    app.daily_goal = config.daily_goal;
+    app.log_hours = config.log_hours.clone();
+    app.device_name_width = config.device_name_width;
+    app.calibrated_wpm = config.calibrated_wpm;
+
+    // A panic is reported as the tab that failed, not printed
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let mut frames = 0;
+    let mut result = Ok(());
+    'tabs: for (tab, title) in TAB_TITLES.iter().enumerate() {
+        app.selected_tab = tab;
+        for (width, height) in CHECK_SIZES {
+            let drawn = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| -> Result<()> {
+                let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+                terminal.draw(|f| ui(f, &mut app))?;
+                Ok(())
+            }));
+            match drawn {
+                Ok(Ok(())) => frames += 1,
+                Ok(Err(e)) => {
+                    result = Err(e.context(format!("the {} tab failed to draw at {}x{}", title, width, height)));
+                    break 'tabs;
+                }
+                Err(panic) => {
+                    let message = panic
+                        .downcast_ref::<&str>()
+                        .map(|s| s.to_string())
+                        .or_else(|| panic.downcast_ref::<String>().cloned())
+                        .unwrap_or_default();
+                    result = Err(anyhow::anyhow!("the {} tab panicked at {}x{}: {}", title, width, height, message));
+                    break 'tabs;
+                }
+            }
+        }
+    }
+    std::panic::set_hook(default_hook);
+    result.map(|()| frames)
+}
+
+/// Raw mode, the alternate screen, mouse capture and focus reporting for as long as the
+/// guard lives. Dropping it restores the terminal, also when `run_ui`
+/// returns early with an error or unwinds from a panic.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    /// Switches the terminal into the mode the UI needs.
+    fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        // From here on a failure must undo raw mode again
+        let guard = TerminalGuard;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Leaves raw mode, the alternate screen, mouse capture and focus reporting
+/// and shows the cursor. Safe to call more than once.
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, DisableFocusChange, crossterm::cursor::Show);
+}
+
+/// Returns the key of a terminal key event as bound in the config, or
+/// None for keys that can't be bound.
+fn key_spec(key: &KeyEvent) -> Option<KeySpec> {
+    let code = match key.code {
+        KeyCode::Char(c) => Key::Char(c),
+        KeyCode::F(number) => Key::F(number),
+        KeyCode::Tab => Key::Tab,
+        KeyCode::BackTab => Key::BackTab,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        _ => return None,
+    };
+    let modifiers = key.modifiers;
+    Some(KeySpec::new(
+        code,
+        modifiers.contains(KeyModifiers::CONTROL),
+        modifiers.contains(KeyModifiers::ALT),
+        modifiers.contains(KeyModifiers::SHIFT),
+    ))
+}
+
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mut channels: Option<LoggerChannels>,
+    restart: &mut dyn FnMut() -> Result<LoggerChannels>,
+) -> Result<Option<LoggerChannels>> {
+    let mut last_tick = Instant::now();
+    let mut drawn_second = None;
+
+    loop {
+        // Redraw only for input, new statistics or a new second of the
+        // clocks and countdowns; over a slow link every frame costs. Without
+        // focus the clocks only move every few seconds
+        let second = chrono::Local::now().timestamp();
+        let clock_step = if app.focused { 1 } else { UNFOCUSED_TICK_RATE.as_secs() as i64 };
+        if app.needs_redraw || drawn_second.is_none_or(|drawn: i64| (second - drawn).abs() >= clock_step) {
+            terminal.draw(|f| ui(f, app))?;
+            app.needs_redraw = false;
+            drawn_second = Some(second);
+        }
+
+        // Nothing is moving: check less often until keystrokes arrive again
+        let tick_rate = if app.calibration.is_some() {
+            app.tick_rate.min(CALIBRATION_TICK_RATE)
+        } else if !app.focused {
+            app.tick_rate.max(UNFOCUSED_TICK_RATE)
+        } else if app.last_change.elapsed() > IDLE_AFTER {
+            app.tick_rate.max(IDLE_TICK_RATE)
+        } else {
+            app.tick_rate
+        };
+
+        let timeout = tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0));
+
+        let event = if crossterm::event::poll(timeout)? { Some(event::read()?) } else { None };
+        if matches!(event, Some(Event::Key(_) | Event::Resize(..))) {
+            app.needs_redraw = true;
+        }
+        match event {
+            Some(Event::FocusLost) => {
+                app.focused = false;
+                app.needs_redraw = true;
+            }
+            Some(Event::FocusGained) => {
+                app.focused = true;
+                app.needs_redraw = true;
+            }
+            _ => {}
+        }
+
+        if let Some(Event::Key(key)) = event {
+            let action = key_spec(&key).and_then(|spec| app.keys.action(&spec));
+            if app.crash.is_some() && action != Some(UiAction::Quit) {
+                // Only restarting and quitting make sense without a logging thread
+                if key.code == KeyCode::Char('R') {
+                    match restart() {
+                        Ok(new_channels) => {
+                            channels = Some(new_channels);
+                            app.crash = None;
+                            app.paused = false;
+                            app.flash("🔄 Logging restarted".to_string());
+                        }
+                        Err(e) => app.crash = Some(format!("restart failed: {:#}", e)),
+                    }
+                }
+                continue;
+            }
+
+            if let Some(prompt) = app.reset_prompt {
+                // The reset menu takes all keys until it is closed
+                let scope = match (prompt, key.code) {
+                    (ResetPrompt::Menu, KeyCode::Char('s')) => Some(ResetScope::Session),
+                    (ResetPrompt::Menu, KeyCode::Char('t')) => Some(ResetScope::Today),
+                    (ResetPrompt::Menu, KeyCode::Char('a')) => {
+                        app.open_reset_confirmation();
+                        continue;
+                    }
+                    (ResetPrompt::ConfirmAll, KeyCode::Char('y')) => {
+                        Some(ResetScope::All { keep_records: false })
+                    }
+                    (ResetPrompt::ConfirmAll, KeyCode::Char('k')) => {
+                        Some(ResetScope::All { keep_records: true })
+                    }
+                    _ => None,
+                };
+                app.reset_prompt = None;
+                if let Some(scope) = scope
+                    && channels.as_ref().is_none_or(|channels| channels.reset_tx.send(scope).is_err())
+                {
+                    app.flash("❌ Logger is not running".to_string());
+                }
+                continue;
+            }
+
+            if let Some(screen) = &app.calibration
+                && let Some(channels) = &channels
+            {
+                // The test reads the keyboard through the logger; terminal
+                // keys only close or repeat it
+                match key.code {
+                    KeyCode::Esc => app.stop_calibration(channels),
+                    KeyCode::Enter if screen.result.is_some() => app.stop_calibration(channels),
+                    KeyCode::Char('r') if screen.result.is_some() => {
+                        if let Err(e) = app.start_calibration(channels) {
+                            app.calibration = None;
+                            app.flash(format!("❌ {:#}", e));
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Some(picker) = &mut app.device_picker {
+                // The picker takes all keys until it is closed
+                match action {
+                    Some(UiAction::Close | UiAction::Device | UiAction::Quit) => app.device_picker = None,
+                    Some(UiAction::Select) => {
+                        if let Some(channels) = &channels {
+                            app.pick_device(channels);
+                        }
+                    }
+                    Some(UiAction::ScrollUp) => picker.selected = picker.selected.saturating_sub(1),
+                    Some(UiAction::ScrollDown) => {
+                        picker.selected = (picker.selected + 1).min(picker.devices.len() - 1);
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Some(palette) = &mut app.palette {
+                // The palette takes all keys until it is closed
+                match key.code {
+                    KeyCode::Esc => app.palette = None,
+                    KeyCode::Enter => app.run_palette(channels.as_ref()),
+                    KeyCode::Up => palette.selected = palette.selected.saturating_sub(1),
+                    KeyCode::Down => palette.selected = (palette.selected + 1).min(PALETTE_MATCHES - 1),
+                    KeyCode::Backspace if palette.input.is_empty() => app.palette = None,
+                    KeyCode::Backspace => {
+                        palette.input.pop();
+                        palette.selected = 0;
+                        palette.error = None;
+                    }
+                    KeyCode::Char(c) => {
+                        palette.input.push(c);
+                        palette.selected = 0;
+                        palette.error = None;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            match action {
+                Some(UiAction::Quit) => {
+                    app.should_quit = true;
+                }
+                Some(UiAction::Commands) => {
+                    app.palette = Some(Palette::default());
+                }
+                Some(UiAction::NextTab) => {
+                    app.selected_tab = (app.selected_tab + 1) % TAB_TITLES.len();
+                }
+                Some(UiAction::PrevTab) => {
+                    app.selected_tab = (app.selected_tab + TAB_TITLES.len() - 1) % TAB_TITLES.len();
+                }
+                Some(UiAction::Reset | UiAction::Device | UiAction::Tag) if app.read_only => {
+                    app.flash("🔒 Viewing a saved file: reset, device switching and tags need a running ctrlq".to_string());
+                }
+                Some(UiAction::Tag) => {
+                    app.open_tag_input();
+                }
+                Some(UiAction::Reset) => {
+                    app.reset_prompt = Some(ResetPrompt::Menu);
+                }
+                Some(UiAction::Pause) => {
+                    app.run_command(if app.paused { "resume" } else { "pause" }, channels.as_ref());
+                }
+                Some(UiAction::OutsideHours) => {
+                    app.run_command("outside hours", channels.as_ref());
+                }
+                Some(UiAction::Save) => {
+                    app.run_command("save now", channels.as_ref());
+                }
+                Some(UiAction::ClassFilter) => {
+                    app.class_filter = KeyClass::cycle(app.class_filter);
+                }
+                Some(UiAction::Help) => {
+                    app.show_help = !app.show_help;
+                }
+                Some(UiAction::Problems) => {
+                    app.show_problems = !app.show_problems;
+                }
+                Some(UiAction::Device) => {
+                    if let Err(e) = app.open_device_picker() {
+                        app.flash(format!("❌ {:#}", e));
+                    }
+                }
+                Some(UiAction::Close) => {
+                    app.show_help = false;
+                    app.show_problems = false;
+                    app.show_session_detail = false;
+                    app.browse_date = None;
+                    app.drift = None;
+                    app.show_months = false;
+                }
+                Some(UiAction::ScrollUp) if app.selected_tab == 4 && app.drift.is_some() => {
+                    app.move_drift_cursor(-1);
+                }
+                Some(UiAction::ScrollDown) if app.selected_tab == 4 && app.drift.is_some() => {
+                    app.move_drift_cursor(1);
+                }
+                Some(UiAction::Select) if app.selected_tab == 4 && app.drift.is_some() => {
+                    app.toggle_drift_key();
+                }
+                Some(UiAction::ScrollLeft) if app.selected_tab == 4 => {
+                    app.move_browse_day(-1);
+                }
+                Some(UiAction::ScrollRight) if app.selected_tab == 4 => {
+                    app.move_browse_day(1);
+                }
+                Some(UiAction::PageUp) if app.selected_tab == 4 => {
+                    app.move_browse_week(-1);
+                }
+                Some(UiAction::PageDown) if app.selected_tab == 4 => {
+                    app.move_browse_week(1);
+                }
+                Some(UiAction::ScrollUp) if app.selected_tab == 3 => {
+                    app.move_session_selection(-1);
+                }
+                Some(UiAction::ScrollDown) if app.selected_tab == 3 => {
+                    app.move_session_selection(1);
+                }
+                Some(UiAction::PageUp) if app.selected_tab == 3 => {
+                    app.move_session_selection(-(SESSION_PAGE_SIZE as isize));
+                }
+                Some(UiAction::PageDown) if app.selected_tab == 3 => {
+                    app.move_session_selection(SESSION_PAGE_SIZE as isize);
+                }
+                Some(UiAction::Home) if app.selected_tab == 3 => {
+                    app.selected_session = None;
+                }
+                Some(UiAction::End) if app.selected_tab == 3 => {
+                    app.move_session_selection(isize::MAX / 2);
+                }
+                Some(UiAction::Select) if app.selected_tab == 3 => {
+                    app.show_session_detail = !app.show_session_detail;
+                }
+                Some(UiAction::ScrollLeft) if app.selected_tab == 5 => {
+                    app.move_timeline_cursor(-1);
+                }
+                Some(UiAction::ScrollRight) if app.selected_tab == 5 => {
+                    app.move_timeline_cursor(1);
+                }
+                Some(_) => {}
+                // Keys of a single tab, which bound actions win over
+                None => match key.code {
+                    KeyCode::Char('t') if app.selected_tab == 1 => {
+                        app.key_scope = KeyScope::Today;
+                    }
+                    KeyCode::Char('w') if app.selected_tab == 1 => {
+                        app.key_scope = KeyScope::Week;
+                    }
+                    KeyCode::Char('a') if app.selected_tab == 1 => {
+                        app.key_scope = KeyScope::AllTime;
+                    }
+                    KeyCode::Char('x') if app.selected_tab == 1 => {
+                        app.show_characters = !app.show_characters;
+                    }
+                    KeyCode::Char('u') if app.selected_tab == 1 => {
+                        app.cycle_unusual_reference();
+                    }
+                    KeyCode::Char('v') if app.selected_tab == 2 => {
+                        app.cycle_heatmap_view();
+                    }
+                    KeyCode::Char('v') if app.selected_tab == 4 => {
+                        app.compare_mode = app.compare_mode.next();
+                        app.browse_date = None;
+                        app.drift = None;
+                        app.show_months = false;
+                    }
+                    KeyCode::Char('m') if app.selected_tab == 4 => {
+                        app.toggle_months();
+                    }
+                    KeyCode::Char('f') if app.selected_tab == 4 => {
+                        app.toggle_drift();
+                    }
+                    KeyCode::Char(' ') if app.selected_tab == 4 && app.drift.is_some() => {
+                        app.toggle_drift_key();
+                    }
+                    KeyCode::Char('b') if app.selected_tab == 4 => {
+                        app.toggle_browse();
+                    }
+                    _ => {}
+                },
+            }
+        }
+
+        if let Some(channels) = &channels {
+            receive_from_logger(app, channels);
+        }
+
+        if last_tick.elapsed() >= tick_rate {
+            last_tick = Instant::now();
+        }
+
+        if app.should_quit {
+            if let Some(channels) = &channels {
+                let _ = channels.shutdown_tx.send(true);
+            }
+            break;
+        }
+    }
+
+    Ok(channels)
+}
+
+/// Takes in everything the logger sent since the last tick: notices,
+/// problems, typed characters and statistics.
+fn receive_from_logger(app: &mut App, channels: &LoggerChannels) {
+    while let Ok(notice) = channels.notice_rx.try_recv() {
+        app.flash(notice);
+    }
+
+    while let Ok(problem) = channels.problem_rx.try_recv() {
+        app.report_problem(problem);
+    }
+
+    while let Ok(key) = channels.typed_rx.try_recv() {
+        app.calibration_input(key, channels);
+    }
+
+    // Only the newest snapshot is shown, older ones are dropped unseen
+    let mut newest = None;
+    loop {
+        match channels.stats_rx.try_recv() {
+            Ok(stats) => newest = Some(stats),
+            Err(mpsc::TryRecvError::Empty) => break,
+            // A crash is reported before the statistics stop, so the
+            // overlay can offer a restart instead of quitting
+            Err(mpsc::TryRecvError::Disconnected) if app.crash.is_some() => break,
+            // The logger stopped on its own, e.g. at the end of --duration
+            Err(mpsc::TryRecvError::Disconnected) => {
+                app.should_quit = true;
+                break;
+            }
+        }
+    }
+    if let Some(stats) = newest {
+        app.update_stats(stats);
+    }
+}
+
+fn ui(f: &mut Frame, app: &mut App) {
+    #[cfg(test)]
+    if app.stats.total_keystrokes >= tests::PANIC_AT_KEYSTROKES.load(std::sync::atomic::Ordering::Relaxed) {
+        panic!("render panic injected by a test");
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    let titles: Vec<Line> = TAB_TITLES
+        .iter()
+        .cloned()
+        .map(Line::from)
+        .collect();
+    
+    let title = if app.read_only {
+        let file = app.data_file.as_deref().and_then(Path::file_name).unwrap_or_default();
+        format!("CtrlQ - Viewing {} (read-only)", file.to_string_lossy())
+    } else if app.profile == profile::DEFAULT_PROFILE {
+        "CtrlQ - Developer Keylogger".to_string()
+    } else {
+        format!("CtrlQ - Developer Keylogger [{}]", app.profile)
+    };
+    let tabs = Tabs::new(titles)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(Style::default().fg(Color::White))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .select(app.selected_tab);
+    
+    f.render_widget(tabs, chunks[0]);
+
+    match app.selected_tab {
+        0 => {
+            let ticker = app.stats.top_recent_keys(3, Utc::now());
+            let factor = app.calibration_factor();
+            render_overview(f, chunks[1], &app.stats, &ticker, app.daily_goal, app.device_name_width, factor)
+        }
+        1 => {
+            app.refresh_scoped_keys();
+            let scoped = app.scoped_keys.value().expect("refreshed above");
+            render_top_keys(f, chunks[1], &app.stats, scoped, app.class_filter);
+        }
+        2 => {
+            app.refresh_heatmap();
+            let heatmap = app.heatmap.value().expect("refreshed above");
+            render_heatmap(f, chunks[1], &app.stats, heatmap);
+        }
+        3 => render_sessions(f, chunks[1], app),
+        4 => {
+            app.refresh_archived_days();
+            if app.show_months {
+                app.refresh_months();
+            }
+            render_history(f, chunks[1], app);
+        }
+        5 => render_timeline(f, chunks[1], app),
+        6 => render_debug(f, chunks[1], &app.stats, app.device_name_width),
+        _ => {}
+    }
+
+    render_status_bar(f, chunks[2], app);
+
+    if app.show_help {
+        render_help(f, f.area(), &app.keys);
+    }
+
+    if let Some(prompt) = app.reset_prompt {
+        render_reset_prompt(f, f.area(), prompt, &app.stats, app.reset_loss.as_ref());
+    }
+
+    if let Some(palette) = &app.palette {
+        render_palette(f, f.area(), palette);
+    }
+
+    if let Some(screen) = &app.calibration {
+        render_calibration(f, f.area(), screen);
+    }
+
+    if let Some(picker) = &app.device_picker {
+        render_device_picker(f, f.area(), picker, app.stats.diagnostics.device_suggestion.as_ref(), app.device_name_width);
+    }
+
+    if app.show_problems && !app.problems.is_empty() {
+        render_problems(f, f.area(), &app.problems, &app.keys);
+    }
+
+    if let Some(message) = &app.crash {
+        render_crash(f, f.area(), message);
+    }
+
+    // Dimmed while another window has focus, as a sign the numbers update slowly
+    if !app.focused {
+        let area = f.area();
+        f.buffer_mut().set_style(area, Style::default().add_modifier(Modifier::DIM));
+    }
+}
+
+fn render_problems(f: &mut Frame, area: Rect, problems: &VecDeque<(DateTime<chrono::Local>, String)>, keys: &KeyBindings) {
+    let popup = centered_rect(70, 40, area);
+
+    let items: Vec<ListItem> = problems
+        .iter()
+        .rev()
+        .map(|(at, message)| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{} ", at.format("%H:%M:%S")), Style::default().fg(Color::DarkGray)),
+                Span::styled(message.clone(), Style::default().fg(Color::Red)),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(
+                "Problems, newest first ({} to close, {} to reopen)",
+                keys.label(UiAction::Close),
+                keys.label(UiAction::Problems)
+            )),
+    );
+
+    f.render_widget(Clear, popup);
+    f.render_widget(list, popup);
+}
+
+fn render_crash(f: &mut Frame, area: Rect, message: &str) {
+    let popup = centered_rect(60, 40, area);
+
+    let text = format!(
+        "The logging thread crashed:\n{}\n\n\
+         Nothing is being counted right now. Statistics up to the crash were saved.\n\n\
+         R  restart logging\n\
+         q  quit",
+        message
+    );
+    let crash = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("Logging stopped"))
+        .wrap(Wrap { trim: false })
+        .style(Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD));
+
+    f.render_widget(Clear, popup);
+    f.render_widget(crash, popup);
+}
+
+/// Draws the keyboards `d` can switch to, marking the logged ones and the
+/// one the silent-device check saw typing.
+fn render_device_picker(
+    f: &mut Frame,
+    area: Rect,
+    picker: &DevicePicker,
+    suggestion: Option<&keylogger::DeviceSuggestion>,
+    name_width: usize,
+) {
+    let popup = centered_rect(70, 60, area);
+    let suggested = suggestion.and_then(|suggestion| std::fs::canonicalize(&suggestion.path).ok());
+
+    let mut lines = Vec::new();
+    for (i, device) in picker.devices.iter().enumerate() {
+        let style = if i == picker.selected {
+            Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let marker = if picker.logged[i] {
+            Span::styled(" ● logging", style.fg(Color::Green))
+        } else if suggested.is_some() && std::fs::canonicalize(&device.path).ok() == suggested {
+            Span::styled(" ◆ typed on", style.fg(Color::Yellow))
+        } else {
+            Span::styled("", style)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!(" {}. ", i + 1), style.fg(Color::Yellow)),
+            Span::styled(device.display_name(name_width), style.fg(Color::White)),
+            marker,
+        ]));
+        lines.push(Line::from(Span::styled(
+            format!(
+                "    {} · {} keys · score {}{}",
+                device.stable_path.as_deref().unwrap_or(&device.path),
+                device.key_count,
+                device.score(),
+                if device.has_relative_axes { " · also a mouse" } else { "" }
+            ),
+            Style::default().fg(Color::Gray),
+        )));
+    }
+
+    let picker_widget = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Switch keyboard (Enter switch · ↑/↓ select · Esc close)"),
+    );
+
+    f.render_widget(Clear, popup);
+    f.render_widget(picker_widget, popup);
+}
+
+/// Draws the typing test: the sentence coloured by what was typed so far,
+/// then the result next to the keyboard's previous calibration.
+fn render_calibration(f: &mut Frame, area: Rect, screen: &CalibrationScreen) {
+    let popup = centered_rect(70, 50, area);
+
+    let mut typed = screen.test.typed.chars();
+    let mut sentence: Vec<Span> = Vec::new();
+    let mut cursor_shown = false;
+    for expected in screen.test.prompt.chars() {
+        let span = match typed.next() {
+            Some(c) if c == expected => Span::styled(expected.to_string(), Style::default().fg(Color::Green)),
+            // Mistyped spaces would be invisible without a background
+            Some(_) => Span::styled(expected.to_string(), Style::default().fg(Color::White).bg(Color::Red)),
+            None if !cursor_shown => {
+                cursor_shown = true;
+                Span::styled(expected.to_string(), Style::default().add_modifier(Modifier::REVERSED))
+            }
+            None => Span::styled(expected.to_string(), Style::default().fg(Color::DarkGray)),
+        };
+        sentence.push(span);
+    }
+
+    let mut lines = vec![Line::from(sentence), Line::from("")];
+    match &screen.result {
+        None => {
+            lines.push(Line::from(Span::styled(
+                "Type the sentence on your logged keyboard; the clock starts with the first key.",
+                Style::default().fg(Color::Gray),
+            )));
+            lines.push(Line::from(Span::styled("Esc cancel", Style::default().fg(Color::DarkGray))));
+        }
+        Some(run) => {
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:.1} WPM", run.wpm), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+                Span::raw(format!(" · accuracy {:.1}%", run.accuracy * 100.0)),
+            ]));
+            lines.push(Line::from(format!(
+                "ctrlq estimated {:.1} WPM from the same keystrokes: factor ×{:.2}",
+                run.estimated_wpm, run.factor
+            )));
+            lines.push(Line::from(Span::styled(
+                match &screen.previous {
+                    Some(previous) => format!(
+                        "Before: ×{:.2} ({:.1} WPM) on {}",
+                        previous.factor,
+                        previous.wpm,
+                        previous.at.with_timezone(&chrono::Local).format("%Y-%m-%d")
+                    ),
+                    None => format!("First calibration of {}", run.device),
+                },
+                Style::default().fg(Color::Gray),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                "r test again · Enter close · calibrated_wpm = true in the config shows it on the Overview",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+    }
+
+    let title = match &screen.result {
+        Some(run) => format!("Calibration of {}", run.device),
+        None => format!("Typing test ({}/{})", screen.test.typed.chars().count(), screen.test.prompt.chars().count()),
+    };
+    let test = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(Clear, popup);
+    f.render_widget(test, popup);
+}
+
+fn render_reset_prompt(f: &mut Frame, area: Rect, prompt: ResetPrompt, stats: &KeyStats, loss: Option<&ResetLoss>) {
+    let popup = centered_rect(60, 40, area);
+
+    let detail = |text: String| Line::from(Span::styled(format!("   {}", text), Style::default().fg(Color::Gray)));
+    let text = match prompt {
+        ResetPrompt::Menu => vec![
+            Line::from("s  restart the current session"),
+            detail(stats.reset_loss(ResetScope::Session).summary()),
+            Line::from("t  clear today's statistics"),
+            detail(stats.reset_loss(ResetScope::Today).summary()),
+            Line::from("a  clear everything (asks again)"),
+            Line::from(""),
+            Line::from("any other key cancels"),
+        ],
+        ResetPrompt::ConfirmAll => {
+            let mut lines = vec![Line::from("Delete all statistics?"), Line::from("")];
+            if let Some(loss) = loss {
+                // Data that never left the data file is gone for good, backup aside
+                let export_style = if loss.last_export_at.is_none() {
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::Gray)
+                };
+                lines.extend([
+                    Line::from(Span::styled(loss.summary(), Style::default().add_modifier(Modifier::BOLD))),
+                    Line::from(Span::styled(loss.backup_line(), Style::default().fg(Color::Gray))),
+                    Line::from(Span::styled(loss.export_line(), export_style)),
+                    Line::from(""),
+                ]);
+            }
+            lines.extend([
+                Line::from("y  yes, clear everything"),
+                Line::from("k  clear everything but keep personal records"),
+                Line::from(""),
+                Line::from("any other key cancels"),
+            ]);
+            lines
+        }
+    };
+
+    let menu = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("Reset"))
+        .wrap(Wrap { trim: false })
+        .style(Style::default().fg(Color::Yellow));
+
+    f.render_widget(Clear, popup);
+    f.render_widget(menu, popup);
+}
+
+/// Draws the command palette above the status bar: the input line, an
+/// error if the last command failed, and the matching commands.
+fn render_palette(f: &mut Frame, area: Rect, palette: &Palette) {
+    let matches = match_commands(&palette.input);
+    let shown = matches.len().min(PALETTE_MATCHES);
+    let selected = palette.selected.min(shown.saturating_sub(1));
+
+    let mut lines = vec![Line::from(vec![
+        Span::styled(":", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(palette.input.clone()),
+        Span::styled("█", Style::default().fg(Color::Gray)),
+    ])];
+    if let Some(error) = &palette.error {
+        lines.push(Line::from(Span::styled(format!("❌ {}", error), Style::default().fg(Color::Red))));
+    }
+    if matches.is_empty() {
+        lines.push(Line::from(Span::styled("no matching command", Style::default().fg(Color::DarkGray))));
+    }
+    for (i, (command, args)) in matches.iter().take(shown).enumerate() {
+        let usage = if args.is_empty() { command.args.to_string() } else { args.clone() };
+        let style = if i == selected {
+            Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!(" {:<14}", command.name), style.fg(Color::Cyan)),
+            Span::styled(format!("{:<18}", usage), style.fg(Color::White)),
+            Span::styled(command.description, style.fg(Color::Gray)),
+        ]));
+    }
+
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + 1,
+        y: area.bottom().saturating_sub(height + 1),
+        width: area.width.saturating_sub(2),
+        height,
+    };
+    let list = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Commands (Enter run · ↑/↓ select · Esc close)"),
+    );
+
+    f.render_widget(Clear, popup);
+    f.render_widget(list, popup);
+}
+
+fn render_help(f: &mut Frame, area: Rect, keys: &KeyBindings) {
+    let popup = centered_rect(70, 80, area);
+
+    // The keys in effect, as configured in [keys]; unbound actions are left out
+    let bound: String = UiAction::ALL
+        .iter()
+        .filter(|&&action| !keys.keys(action).is_empty())
+        .map(|&action| format!("{:<12} {}\n", keys.label(action), action.description()))
+        .collect();
+    let commands: String = COMMANDS
+        .iter()
+        .map(|command| format!("{:<30} {}\n", format!("{} {}", command.name, command.args), command.description))
+        .collect();
+    let help = Paragraph::new(format!(
+        "Keys (rebind them in [keys] of the config)\n\
+         {}\
+         t/w/a        today, last 7 days or all time (Top Keys)\n\
+         x            typed characters instead of keys (Top Keys)\n\
+         u            unusual keys vs English, code or a file (Top Keys)\n\
+         v            compare two keyboards or profiles side by side / as a diff (Heatmap)\n\
+         v            compare days / weeks (History)\n\
+         b            browse single days (History)\n\
+         f            weekly share of up to 3 keys (History)\n\
+         m            months with the change from the month before (History)\n\n\
+         Commands (:)\n\
+         {}\n\
+         Metrics\n\
+         WPM      character keys / 5 per minute; net WPM subtracts backspaces\n\
+         Key diversity  Shannon entropy of the session's keys in bits.\n\
+                  Higher means keystrokes are spread over more keys: English\n\
+                  prose sits around 4 bits, code usually scores higher.",
+        bound, commands
+    ))
+    .block(Block::default().borders(Borders::ALL).title(format!("Help ({} to close)", keys.label(UiAction::Close))))
+    .wrap(Wrap { trim: false })
+    .style(Style::default().fg(Color::White));
+
+    f.render_widget(Clear, popup);
+    f.render_widget(help, popup);
+}
+
+/// Returns a rectangle of the given percentage size centered in `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn render_status_bar(f: &mut Frame, area: Rect, app: &App) {
+    let area = match app.deadline {
+        Some(deadline) => {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(18)])
+                .split(area);
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let countdown = Paragraph::new(format!("⏱ {} left", report::format_duration(remaining)))
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+                .alignment(Alignment::Right);
+            f.render_widget(countdown, chunks[1]);
+            chunks[0]
+        }
+        None => area,
+    };
+    // Shown next to any message, since it changes what is typed
+    let area = if app.stats.diagnostics.caps_lock {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(7)])
+            .split(area);
+        let caps = Paragraph::new("⇪ CAPS")
+            .style(Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .alignment(Alignment::Right);
+        f.render_widget(caps, chunks[1]);
+        chunks[0]
+    } else {
+        area
+    };
+
+    let diagnostics = &app.stats.diagnostics;
+    let idle_warning = diagnostics.idle_warning.as_deref();
+    let status = match (app.current_status(), idle_warning) {
+        (Some(message), _) => Paragraph::new(message.to_string())
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        // Nothing is counted until it is dealt with, so it wins over the idle warning
+        (None, _) if let Some(suggestion) = &diagnostics.device_suggestion => Paragraph::new(format!(
+            "⚠️  Selected device is silent; did you mean '{}'? press '{}' to switch",
+            keylogger::truncate_name(&suggestion.label, app.device_name_width),
+            app.keys.label(UiAction::Device)
+        ))
+        .style(Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD)),
+        // Stays up until events arrive again, unlike flashed messages
+        (None, Some(warning)) => Paragraph::new(format!("⚠️  {}", warning))
+            .style(Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD)),
+        // Stays up until 5 minutes away from the keyboard clear it
+        (None, None) if let Some(active) = diagnostics.break_due => Paragraph::new(format!(
+            "☕ {} min of typing without a break - step away for 5 minutes",
+            active.as_secs() / 60
+        ))
+        .style(Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        (None, None) if app.read_only => {
+            let hint = key_hints(&app.keys, &[
+                (UiAction::Quit, "quit"),
+                (UiAction::NextTab, "switch tabs"),
+                (UiAction::ClassFilter, "key class filter"),
+                (UiAction::Commands, "commands"),
+                (UiAction::Help, "help"),
+            ]);
+            Paragraph::new(format!("🔒 Read-only · {}", hint)).style(Style::default().fg(Color::DarkGray))
+        }
+        (None, None) if diagnostics.screen_locked => {
+            Paragraph::new("🔒 Paused (screen locked) - keystrokes are not counted until you unlock")
+                .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        }
+        (None, None) if app.paused => Paragraph::new("⏸️  Paused - keystrokes are not counted · :resume to count again")
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        (None, None) if let Some(hours) = &diagnostics.outside_hours => {
+            let hint = key_hints(&app.keys, &[(UiAction::OutsideHours, "count anyway until midnight")]);
+            let mut text = format!("🌙 Outside logging hours ({} today) - keystrokes are not counted", hours);
+            if !hint.is_empty() {
+                text = format!("{} · {}", text, hint);
+            }
+            Paragraph::new(text).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        }
+        (None, None) => {
+            let devices: Vec<String> = diagnostics
+                .devices
+                .iter()
+                .map(|device| keylogger::truncate_name(&device.label, app.device_name_width))
+                .collect();
+            let hint = key_hints(&app.keys, &[
+                (UiAction::Quit, "quit"),
+                (UiAction::NextTab, "switch tabs"),
+                (UiAction::Reset, "reset"),
+                (UiAction::ClassFilter, "key class filter"),
+                (UiAction::Device, "device"),
+                (UiAction::Commands, "commands"),
+                (UiAction::Help, "help"),
+            ]);
+            let mut text = if devices.is_empty() { hint.to_string() } else { format!("⌨️  {} · {}", devices.join(", "), hint) };
+            if diagnostics.hours_override {
+                text = format!("🌙 Outside logging hours · {}", text);
+            }
+            if diagnostics.power_saving {
+                text = format!("🔋 Power saving · {}", text);
+            }
+            Paragraph::new(text).style(Style::default().fg(Color::DarkGray))
+        }
+    };
+
+    f.render_widget(status, area);
+}
+
+/// Lists the first key of each action for the status bar, such as
+/// `q quit · ? help`; unbound actions are left out.
+fn key_hints(keys: &KeyBindings, actions: &[(UiAction, &str)]) -> String {
+    let hints: Vec<String> = actions
+        .iter()
+        .filter_map(|&(action, text)| keys.keys(action).first().map(|key| format!("{} {}", key, text)))
+        .collect();
+    hints.join(" · ")
+}
+
+fn render_overview(
+    f: &mut Frame,
+    area: Rect,
+    stats: &KeyStats,
+    ticker: &[(String, u32)],
+    daily_goal: Option<u64>,
+    name_width: usize,
+    calibration_factor: Option<f64>,
+) {
+    // One more line in the summary for the calibrated speed
+    let summary_height = if calibration_factor.is_some() { 13 } else { 12 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(summary_height),
+            Constraint::Length(10),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    let total_keys = stats.total_keystrokes;
+    let today = stats.daily_stats.get(&keylogger::day_key(keylogger::today()));
+    let typing_speed = match (stats.get_wpm(), stats.get_net_wpm()) {
+        (Some(wpm), Some(net_wpm)) => format!("{:.1} WPM (net {:.1})", wpm, net_wpm),
+        (wpm, _) => report::format_wpm(wpm),
+    };
+    let calibrated = match (calibration_factor, stats.get_wpm()) {
+        (Some(factor), Some(wpm)) => format!("\nCalibrated: {:.1} WPM (×{:.2})", wpm * factor, factor),
+        (Some(factor), None) => format!("\nCalibrated: — (×{:.2})", factor),
+        (None, _) => String::new(),
+    };
+    let session_duration = chrono::Utc::now()
+        .signed_duration_since(stats.session_start)
+        .num_minutes();
+
+    let summary = Paragraph::new(format!(
+        "📊 Session Stats\n\
+         Total Keystrokes: {}\n\
+         Session Duration: {} minutes\n\
+         Typing Speed: {}{}\n\
+         Real Speed: {}\n\
+         Today: {} words · {} lines\n\
+         Typed today: {}\n\
+         Actions today: {}\n\
+         Unique Keys: {}\n\
+         Key Diversity: {}",
+        numbers::format_count(total_keys),
+        session_duration,
+        typing_speed,
+        calibrated,
+        stats.get_real_wpm().map_or("—".to_string(), |wpm| format!("{:.1} WPM (counted words)", wpm)),
+        numbers::format_count(today.map_or(0, |day| day.words)),
+        numbers::format_count(today.map_or(0, |day| day.lines)),
+        today.and_then(report::format_deletions).unwrap_or_else(|| "—".to_string()),
+        today.and_then(|day| actions::summary_line(&day.actions, 3)).unwrap_or_else(|| "—".to_string()),
+        numbers::format_count(stats.key_counts.len() as u64),
+        stats
+            .get_entropy()
+            .map(|bits| format!("{:.1} bits", bits))
+            .unwrap_or_else(|| "—".to_string())
+    ))
+    .block(Block::default().borders(Borders::ALL).title("Summary"))
+    .style(Style::default().fg(Color::Green));
+
+    let devices = stats.device_totals();
+    let top_constraints = if devices.len() > 1 {
+        vec![Constraint::Percentage(40), Constraint::Percentage(30), Constraint::Percentage(30)]
+    } else {
+        vec![Constraint::Percentage(50), Constraint::Percentage(50)]
+    };
+    let top_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(top_constraints)
+        .split(chunks[0]);
+
+    f.render_widget(summary, top_chunks[0]);
+
+    // Only worth the space once keystrokes come from more than one keyboard
+    if devices.len() > 1 {
+        let attributed: u64 = devices.iter().map(|(_, count)| count).sum();
+        let device_text = devices
+            .iter()
+            .map(|(device, count)| {
+                format!("{}: {} ({})", keylogger::truncate_name(device, name_width), numbers::format_count(*count), report::format_percent(report::percent(*count, attributed), 0))
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let device_panel = Paragraph::new(device_text)
+            .block(Block::default().borders(Borders::ALL).title("Devices"))
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::Blue));
+
+        f.render_widget(device_panel, top_chunks[2]);
+    }
+
+    let mut class_text = stats.class_totals()
+        .iter()
+        .map(|(class, count)| format!("{}: {}", class, numbers::format_count(*count)))
+        .collect::<Vec<_>>()
+        .join("  ");
+    if let Some(share) = stats.shifted_share() {
+        class_text.push_str(&format!("\nShifted (capitals and symbols): {:.1}%", share * 100.0));
+    }
+    let categories = stats.get_category_breakdown();
+    if let Some(mix) = report::format_category_mix(&categories) {
+        class_text.push_str(&format!("\nMix: {}\nSymbol ratio: {}", mix, report::format_symbol_ratio(&categories)));
+    }
+
+    let classes = Paragraph::new(class_text)
+        .block(Block::default().borders(Borders::ALL).title("Key Classes"))
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::Yellow));
+
+    f.render_widget(classes, top_chunks[1]);
+
+    // What the last minute was mostly made of, e.g. arrow keys or prose
+    let ticker_text = if ticker.is_empty() {
+        "nothing typed".to_string()
+    } else {
+        ticker
+            .iter()
+            .map(|(key, count)| format!("{}×{}", stats.key_label(key), count))
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    let ticker = Paragraph::new(ticker_text)
+        .block(Block::default().borders(Borders::ALL).title("Last Minute"))
+        .style(Style::default().fg(Color::Yellow));
+
+    // Contexts only appear once `ctrlq context` has been used
+    let recent_area = if stats.uses_contexts() {
+        let middle_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(chunks[1]);
+
+        let contexts = stats.context_totals();
+        let attributed: u64 = contexts.iter().map(|(_, count)| count).sum();
+        let context_text = contexts
+            .iter()
+            .map(|(context, count)| {
+                format!("{}: {} ({})", context, numbers::format_count(*count), report::format_percent(report::percent(*count, attributed), 0))
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let context_panel = Paragraph::new(context_text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Contexts (now: {})", stats.current_context())),
+            )
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::Magenta));
+
+        f.render_widget(context_panel, middle_chunks[1]);
+        middle_chunks[0]
+    } else {
+        chunks[1]
+    };
+    let recent_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(recent_area);
+    f.render_widget(ticker, recent_chunks[0]);
+
+    // As many of the last keys as fit, newest at the bottom
+    let block = Block::default().borders(Borders::ALL).title("Recent Keys");
+    let inner = block.inner(recent_chunks[1]);
+    let recent_lines = recent_key_lines(stats, Utc::now(), inner.width as usize, inner.height as usize);
+    let recent = Paragraph::new(recent_lines)
+        .block(block)
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(recent, recent_chunks[1]);
+
+    let indicator_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[2]);
+    let now = chrono::Local::now();
+    let today = stats.daily_stats.get(&keylogger::day_key(now.date_naive())).map_or(0, |day| day.keystrokes);
+    render_goal(f, indicator_chunks[0], today, daily_goal);
+    render_trend(f, indicator_chunks[1], stats, now);
+}
+
+/// Lays out the last keys typed for the Recent Keys panel.
+///
+/// Keys run on in rows of `width` columns. A gap of more than
+/// [`RECENT_KEYS_GAP_SECS`] between two keys becomes a separator with the
+/// time of the key after it, however long the gap; keys older than
+/// [`keylogger::RECENT_KEYS_WINDOW_SECS`], or loaded without a time, are
+/// dimmed.
+///
+/// # Returns
+/// `Vec<Line>` - At most `height` lines, the newest keys last
+fn recent_key_lines(stats: &KeyStats, now: DateTime<Utc>, width: usize, height: usize) -> Vec<Line<'static>> {
+    let faded = Style::default().fg(Color::DarkGray);
+    let mut lines: Vec<Line> = Vec::new();
+    let mut row: Vec<Span> = Vec::new();
+    let mut row_width = 0;
+    let mut previous: Option<DateTime<Utc>> = None;
+    for (key, at) in stats.recent_sequence() {
+        if let (Some(previous), Some(at)) = (previous, at)
+            && (at - previous).num_seconds() > RECENT_KEYS_GAP_SECS
+        {
+            if !row.is_empty() {
+                lines.push(Line::from(std::mem::take(&mut row)));
+                row_width = 0;
+            }
+            let time = at.with_timezone(&chrono::Local).format("%H:%M");
+            lines.push(Line::styled(format!("· · {} · ·", time), faded));
+        }
+        previous = at;
+
+        let glyph = match stats.key_label(key).as_str() {
+            "SPACE" => " ".to_string(),
+            "ENTER" => "↵".to_string(),
+            "TAB" => "⭾".to_string(),
+            "BACKSPACE" => "⌫".to_string(),
+            name => name.to_string(),
+        };
+        let recent = at.is_some_and(|at| (now - at).num_seconds() <= keylogger::RECENT_KEYS_WINDOW_SECS);
+        let span = Span::styled(glyph, if recent { Style::default() } else { faded });
+        let gap = usize::from(!row.is_empty());
+        if row_width + gap + span.width() > width && !row.is_empty() {
+            lines.push(Line::from(std::mem::take(&mut row)));
+            row_width = 0;
+        } else if gap == 1 {
+            row.push(Span::raw(" "));
+            row_width += 1;
+        }
+        row_width += span.width();
+        row.push(span);
+    }
+    if !row.is_empty() {
+        lines.push(Line::from(row));
+    }
+    let skip = lines.len().saturating_sub(height);
+    lines.split_off(skip)
+}
+
+/// Progress towards the daily goal, or a hint on how to set one.
+fn render_goal(f: &mut Frame, area: Rect, today: u64, daily_goal: Option<u64>) {
+    let block = Block::default().borders(Borders::ALL).title("Daily Goal");
+    let Some(goal) = daily_goal.filter(|&goal| goal > 0) else {
+        let hint = Paragraph::new("No goal set - add daily_goal to config.toml")
+            .block(block)
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(hint, area);
+        return;
+    };
+
+    let ratio = (today as f64 / goal as f64).min(1.0);
+    let color = if today >= goal { Color::Green } else { Color::Magenta };
+    let gauge = Gauge::default()
+        .block(block)
+        .gauge_style(Style::default().fg(color))
+        .ratio(ratio)
+        .label(format!("{} / {} ({:.0}%)", numbers::format_count(today), numbers::format_count(goal), today as f64 / goal as f64 * 100.0));
+    f.render_widget(gauge, area);
+}
+
+/// Today's pace compared with a typical day of the same weekday.
+fn render_trend(f: &mut Frame, area: Rect, stats: &KeyStats, now: DateTime<chrono::Local>) {
+    let weekday = now.format("%A");
+    let (text, color) = match stats.pace_trend(now) {
+        Some(trend) => {
+            let change = trend.change();
+            let (arrow, color) = if change >= TREND_MARGIN {
+                ("▲", Color::Green)
+            } else if change <= -TREND_MARGIN {
+                ("▼", Color::Red)
+            } else {
+                ("▶", Color::Yellow)
+            };
+            (
+                format!(
+                    "{} {:+.0}% vs typical {}\n{} so far, usually {} by {} ({} day(s) of history)",
+                    arrow,
+                    change * 100.0,
+                    weekday,
+                    numbers::format_count(trend.so_far),
+                    numbers::format_count(trend.typical.round() as u64),
+                    now.format("%H:%M"),
+                    trend.days
+                ),
+                color,
+            )
+        }
+        None => (format!("No {} history for this time of day yet", weekday), Color::DarkGray),
+    };
+
+    let trend = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("Trend"))
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(color).add_modifier(Modifier::BOLD));
+    f.render_widget(trend, area);
+}
+
+/// Formats the counts of a list column to one width, at least `min_width`,
+/// so the columns after it stay aligned however large the counts grow.
+fn count_column(counts: impl Iterator<Item = u64>, min_width: usize) -> Vec<String> {
+    let counts: Vec<String> = counts.map(numbers::format_count).collect();
+    let width = counts.iter().map(|count| count.chars().count()).max().unwrap_or(0).max(min_width);
+    counts.into_iter().map(|count| format!("{:>width$}", count)).collect()
+}
+
+fn render_top_keys(f: &mut Frame, area: Rect, stats: &KeyStats, scoped: &ScopedKeys, class_filter: Option<KeyClass>) {
+    if let Some(reference) = &scoped.unusual_reference {
+        render_unusual_keys(f, area, scoped, reference);
+        return;
+    }
+    if scoped.characters {
+        render_top_characters(f, area, stats, scoped);
+        return;
+    }
+    let scope = scoped.scope;
+    let total = scoped.total;
+    let counts = count_column(scoped.top.iter().map(|(_, count)| *count), 8);
+
+    let items: Vec<ListItem> = scoped
+        .top
+        .iter()
+        .zip(counts)
+        .enumerate()
+        .map(|(i, ((key, count), count_text))| {
+            let key_display = stats.key_label(key);
+            
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:2}. ", i + 1),
+                    Style::default().fg(Color::Yellow),
+                ),
+                // Long names, e.g. imported ones, would push the counts out of line
+                Span::styled(
+                    format!("{:<15}", keylogger::truncate_name(&key_display, 14)),
+                    Style::default().fg(Color::White),
+                ),
+                Span::styled(count_text, Style::default().fg(Color::Green)),
+                Span::styled(
+                    format!(" ({:>6})", report::format_percent(report::percent(*count, total), 1)),
+                    Style::default().fg(Color::Gray),
+                ),
+                share_change_span(scoped.changes.as_ref().map(|changes| changes.get(key))),
+            ]))
+        })
+        .collect();
+
+    let title = match &scoped.changes {
+        Some(_) => format!("Top Keys - {} vs {} ('t'/'w'/'a')", scope.label(), scope.previous_label()),
+        None => format!("Top Keys - {} ('t'/'w'/'a')", scope.label()),
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(filtered_title(&title, class_filter)))
+        .style(Style::default().fg(Color::White));
+
+    // Anonymized data has no individual keys to price
+    if stats.anonymized {
+        f.render_widget(list, area);
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(4), Constraint::Length(8)])
+        .split(area);
+    f.render_widget(list, chunks[0]);
+    render_effort(f, chunks[1], scoped);
+    render_same_finger(f, chunks[2], scoped);
+}
+
+/// The keys of the scope used most unusually much (▲) or little (▼)
+/// against a reference corpus, most unusual first.
+fn render_unusual_keys(f: &mut Frame, area: Rect, scoped: &ScopedKeys, reference: &str) {
+    let scope = scoped.scope;
+    let Some(unusual) = &scoped.unusual else {
+        let text = format!(
+            "Fewer than {} characters typed ({}), too few to tell unusual keys.\nPress 'a' for all time, or 'u' for the next reference.",
+            surprise::MIN_CHARS,
+            scope.label()
+        );
+        let hint = Paragraph::new(text)
+            .block(Block::default().borders(Borders::ALL).title(format!("Unusual Keys - {} vs {} ('u')", scope.label(), reference)))
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(hint, area);
+        return;
+    };
+
+    let items: Vec<ListItem> = unusual
+        .keys
+        .iter()
+        .take(20)
+        .enumerate()
+        .map(|(i, key)| {
+            let (arrow, color) = if key.score > 0.0 { ("▲", Color::Green) } else { ("▼", Color::Red) };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:2}. ", i + 1), Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{:<6}", key.key), Style::default().fg(Color::White)),
+                Span::styled(
+                    format!("{:>7}", report::format_percent(Some(key.observed * 100.0), 2)),
+                    Style::default().fg(Color::Green),
+                ),
+                Span::styled(
+                    format!(" vs {:>7}", report::format_percent(Some(key.expected * 100.0), 2)),
+                    Style::default().fg(Color::Gray),
+                ),
+                Span::styled(format!("  {} ×{:.1}", arrow, key.ratio()), Style::default().fg(color)),
+            ]))
+        })
+        .collect();
+
+    let letters = if unusual.letters_only { ", letters only" } else { "" };
+    let title = format!("Unusual Keys - {} vs {}{} ('u')", scope.label(), unusual.reference, letters);
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(Style::default().fg(Color::White));
+    f.render_widget(list, area);
+}
+
+/// The characters mode of the Top Keys tab: what the keys typed on the
+/// active XKB layout, so `z` and `y` stay apart across a layout switch.
+fn render_top_characters(f: &mut Frame, area: Rect, stats: &KeyStats, scoped: &ScopedKeys) {
+    let counts = count_column(scoped.top.iter().map(|(_, count)| *count), 8);
+    let items: Vec<ListItem> = scoped
+        .top
+        .iter()
+        .zip(counts)
+        .enumerate()
+        .map(|(i, ((c, count), count_text))| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:2}. ", i + 1), Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{:<15}", c), Style::default().fg(Color::White)),
+                Span::styled(count_text, Style::default().fg(Color::Green)),
+                Span::styled(
+                    format!(" ({:>6})", report::format_percent(report::percent(*count, scoped.total), 1)),
+                    Style::default().fg(Color::Gray),
+                ),
+                share_change_span(scoped.changes.as_ref().map(|changes| changes.get(c))),
+            ]))
+        })
+        .collect();
+
+    let layout = stats.diagnostics.xkb_layout.as_deref().map_or(String::new(), |layout| format!(" · {}", layout));
+    let title = match &scoped.changes {
+        Some(_) => format!("Top Characters - {} vs {}{} ('x' keys)", scoped.scope.label(), scoped.scope.previous_label(), layout),
+        None => format!("Top Characters - {}{} ('x' keys)", scoped.scope.label(), layout),
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
+    if items.is_empty() {
+        let hint = if stats.anonymized {
+            "Characters are not counted with --anonymize."
+        } else if cfg!(feature = "xkb") {
+            "No characters counted yet. They are counted while an XKB layout is known:\n\
+             --xkb-layout, xkb_layout in config.toml or /etc/default/keyboard."
+        } else {
+            "Characters need a build with --features xkb (libxkbcommon).\n\
+             Without it, only physical keys are counted."
+        };
+        let hint = Paragraph::new(hint).block(block).style(Style::default().fg(Color::Gray)).wrap(Wrap { trim: false });
+        f.render_widget(hint, area);
+        return;
+    }
+    f.render_widget(List::new(items).block(block).style(Style::default().fg(Color::White)), area);
+}
+
+/// Change of a key's share since the previous period: green up, red down.
+///
+/// Changes beyond +500% come from keys that were barely used before and are
+/// shown capped rather than as their exact, meaningless value.
+fn share_change_span(change: Option<Option<&ShareChange>>) -> Span<'static> {
+    let (text, color) = match change {
+        // No comparison for this scope
+        None => return Span::raw(""),
+        Some(None) => ("".to_string(), Color::Gray),
+        Some(Some(ShareChange::New)) => ("new".to_string(), Color::Cyan),
+        Some(Some(&ShareChange::Change(change))) => {
+            let percent = (change * 100.0).round();
+            if percent.abs() < 1.0 {
+                ("±0%".to_string(), Color::Gray)
+            } else if percent > 500.0 {
+                ("▲>500%".to_string(), Color::Green)
+            } else if percent > 0.0 {
+                (format!("▲{}%", percent), Color::Green)
+            } else {
+                (format!("▼{}%", -percent), Color::Red)
+            }
+        }
+    };
+    Span::styled(format!(" {:>7}", text), Style::default().fg(color))
+}
+
+/// Effort per keystroke of the scoped and session keys, and what the scoped
+/// keys would cost on the other layouts.
+fn render_effort(f: &mut Frame, area: Rect, scoped: &ScopedKeys) {
+    let per_key = |score: &layout::EffortScore| {
+        score.per_keystroke().map(|v| format!("{:.2}", v)).unwrap_or_else(|| "—".to_string())
+    };
+    let session = &scoped.session_effort;
+    let scores = &scoped.effort;
+    let baseline = scores[0].1.per_keystroke();
+
+    let mut comparison = Vec::new();
+    for (i, (layout, score)) in scores.iter().enumerate() {
+        if i > 0 {
+            comparison.push(Span::styled(" · ", Style::default().fg(Color::Gray)));
+        }
+        comparison.push(Span::styled(format!("{} ", layout.name()), Style::default().fg(Color::White)));
+        comparison.push(Span::styled(per_key(score), Style::default().fg(Color::Green)));
+        if i > 0 && let (Some(v), Some(base)) = (score.per_keystroke(), baseline) {
+            comparison.push(Span::styled(
+                format!(" ({}%)", report::signed_f64((v / base - 1.0) * 100.0, 1)),
+                Style::default().fg(Color::Gray),
+            ));
+        }
+    }
+
+    let text = vec![
+        Line::from(vec![
+            Span::styled(format!("{}: ", scoped.scope.label()), Style::default().fg(Color::Gray)),
+            Span::styled(per_key(&scores[0].1), Style::default().fg(Color::Yellow)),
+            Span::styled(" per key · This session: ", Style::default().fg(Color::Gray)),
+            Span::styled(per_key(session), Style::default().fg(Color::Yellow)),
+            Span::styled(format!(" per key ({:.0} total)", session.total), Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(comparison),
+    ];
+
+    let effort = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("Effort (typed on QWERTY)"));
+    f.render_widget(effort, area);
+}
+
+/// Same-finger bigram rate of the scoped keys, what it would be on the
+/// other layouts, and the pairs that cost the most.
+fn render_same_finger(f: &mut Frame, area: Rect, scoped: &ScopedKeys) {
+    let block = Block::default().borders(Borders::ALL).title("Same-finger bigrams (typed on QWERTY)");
+    let rate = |sfb: &layout::SfbStats| report::format_percent(sfb.rate().map(|rate| rate * 100.0), 1);
+    let qwerty = &scoped.same_finger[0].1;
+    if qwerty.bigrams == 0 {
+        let hint = Paragraph::new("No bigrams yet: pairs of letters typed within a second of each other.")
+            .block(block)
+            .style(Style::default().fg(Color::Gray));
+        f.render_widget(hint, area);
+        return;
+    }
+
+    let mut headline = vec![
+        Span::styled(rate(qwerty), Style::default().fg(Color::Yellow)),
+        Span::styled(format!(" of {} bigrams", qwerty.bigrams), Style::default().fg(Color::Gray)),
+    ];
+    for (priced_on, other) in &scoped.same_finger[1..] {
+        headline.push(Span::styled(format!(" · {} ", priced_on.name()), Style::default().fg(Color::White)));
+        headline.push(Span::styled(rate(other), Style::default().fg(Color::Green)));
+    }
+
+    let mut text = vec![Line::from(headline)];
+    let counts = count_column(qwerty.top.iter().map(|sfb| sfb.count), 8);
+    for (sfb, count_text) in qwerty.top.iter().zip(counts) {
+        text.push(Line::from(vec![
+            Span::styled(format!("{:<4}", sfb.pair), Style::default().fg(Color::White)),
+            Span::styled(count_text, Style::default().fg(Color::Green)),
+            Span::styled(
+                format!(" ({:>6})  {}", report::format_percent(report::percent(sfb.count, qwerty.bigrams), 1), sfb.finger.name()),
+                Style::default().fg(Color::Gray),
+            ),
+        ]));
+    }
+    f.render_widget(Paragraph::new(text).block(block), area);
+}
+
+fn render_heatmap(f: &mut Frame, area: Rect, stats: &KeyStats, cache: &HeatmapCache) {
+    // Anonymized data has no individual keys to place on a keyboard
+    if stats.anonymized {
+        render_class_distribution(f, area, stats);
+        return;
+    }
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, cache.columns.len() as u32); cache.columns.len()])
+        .split(area);
+    for ((title, lines), area) in cache.columns.iter().zip(columns.iter()) {
+        // Borrow the cached spans instead of cloning their text on every draw
+        let lines: Vec<Line> = lines
+            .iter()
+            .map(|line| Line::from(line.spans.iter().map(|span| Span::styled(span.content.as_ref(), span.style)).collect::<Vec<_>>()))
+            .collect();
+
+        let heatmap = Paragraph::new(Text::from(lines))
+            .block(Block::default().borders(Borders::ALL).title(title.as_str()))
+            .alignment(Alignment::Center);
+
+        f.render_widget(heatmap, *area);
+    }
+}
+
+/// Color of a heat level on the Heatmap tab.
+fn heat_color(heat: Heat) -> Color {
+    match heat {
+        Heat::VeryHigh => Color::Red,
+        Heat::High => Color::LightRed,
+        Heat::Medium => Color::Yellow,
+        Heat::Low => Color::Green,
+        Heat::VeryLow => Color::Blue,
+        Heat::Unused => Color::Gray,
+    }
+}
+
+/// Color of a [`Lean`] in [`HeatmapView::Diff`]: red where the first side
+/// uses a key relatively more, blue where the second does.
+fn lean_color(lean: Lean) -> Color {
+    match lean {
+        Lean::StrongA => Color::Red,
+        Lean::A => Color::LightRed,
+        Lean::Even => Color::Gray,
+        Lean::B => Color::LightBlue,
+        Lean::StrongB => Color::Blue,
+    }
+}
+
+/// Lays out the keys of a [`HeatGrid`], each colored by its level.
+fn keyboard_lines<T: Copy>(grid: &HeatGrid<T>, color: impl Fn(T) -> Color) -> Vec<Line<'static>> {
+    let style = |cell: &HeatCell<T>| Style::default().fg(color(cell.level)).add_modifier(Modifier::BOLD);
+    let mut heatmap_text = Vec::new();
+    for row in &grid.rows {
+        let line_spans: Vec<Span> = row.iter().map(|cell| Span::styled(format!("{} ", cell.text()), style(cell))).collect();
+        heatmap_text.push(Line::from(line_spans));
+        heatmap_text.push(Line::from(""));
+    }
+    heatmap_text.push(Line::from(Span::styled(format!("      {}      ", grid.space.text()), style(&grid.space))));
+    heatmap_text.push(Line::from(""));
+    heatmap_text
+}
+
+/// The legend of [`heat_color`].
+fn heat_legend() -> Line<'static> {
+    let mut spans = vec![Span::styled("Legend: ", Style::default().fg(Color::White))];
+    spans.extend(
+        Heat::ALL.into_iter().map(|heat| Span::styled(format!("■ {} ", heat.label()), Style::default().fg(heat_color(heat)))),
+    );
+    Line::from(spans)
+}
+
+/// Builds the keyboard and legend of the Heatmap tab.
+fn heatmap_lines(stats: &KeyStats, class_filter: Option<KeyClass>) -> Vec<Line<'static>> {
+    // Shifted variants (case-sensitive mode) count towards their physical key
+    let grid = HeatGrid::of_counts(&stats.physical_key_counts(), class_filter);
+    let mut heatmap_text = keyboard_lines(&grid, heat_color);
+    heatmap_text.push(heat_legend());
+    heatmap_text
+}
+
+/// Builds the keyboard and legend of one side of
+/// [`HeatmapView::SideBySide`], with per-mille instead of counts.
+fn share_lines(shares: &HashMap<u16, f64>) -> Vec<Line<'static>> {
+    let mut heatmap_text = keyboard_lines(&HeatGrid::of_shares(shares), heat_color);
+    heatmap_text.push(heat_legend());
+    heatmap_text
+}
+
+/// Builds the keyboard and legend of [`HeatmapView::Diff`].
+fn diff_lines(comparison: &HeatmapComparison) -> Vec<Line<'static>> {
+    let mut heatmap_text = keyboard_lines(&HeatGrid::of_comparison(comparison), lean_color);
+    heatmap_text.push(Line::from(vec![
+        Span::styled("Legend: ", Style::default().fg(Color::White)),
+        Span::styled(format!("■ Much more on {} ", comparison.label_a), Style::default().fg(lean_color(Lean::StrongA))),
+        Span::styled("■ More ", Style::default().fg(lean_color(Lean::A))),
+        Span::styled("■ Even ", Style::default().fg(lean_color(Lean::Even))),
+        Span::styled("■ More ", Style::default().fg(lean_color(Lean::B))),
+        Span::styled(format!("■ Much more on {} ", comparison.label_b), Style::default().fg(lean_color(Lean::StrongB))),
+    ]));
+    heatmap_text
+}
+
+/// Name of a data file for titles: its profile, or else the file name.
+pub fn data_file_label(data_file: &Path) -> String {
+    let file_name = data_file.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    profile::profile_name(&file_name).unwrap_or(file_name)
+}
+
+fn render_class_distribution(f: &mut Frame, area: Rect, stats: &KeyStats) {
+    let totals = stats.class_totals();
+    let max_count = totals.first().map(|(_, count)| *count).unwrap_or(1);
+    let total: u64 = totals.iter().map(|(_, count)| count).sum();
+    let bar_width = area.width.saturating_sub(40) as u64;
+
+    let items: Vec<ListItem> = totals
+        .iter()
+        .map(|(class, count)| {
+            let bar_len = (count * bar_width / max_count.max(1)) as usize;
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<12}", class.label()), Style::default().fg(Color::White)),
+                Span::styled("█".repeat(bar_len), Style::default().fg(Color::Magenta)),
+                Span::styled(format!(" {}", numbers::format_count(*count)), Style::default().fg(Color::Green)),
+                Span::styled(
+                    format!(" ({})", report::format_percent(report::percent(*count, total), 1)),
+                    Style::default().fg(Color::Gray),
+                ),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("🕶️ Key Class Distribution (anonymized)"));
+
+    f.render_widget(list, area);
+}
+
+fn render_sessions(f: &mut Frame, area: Rect, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(9),
+        ])
+        .split(area);
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(50),
+        ])
+        .split(chunks[2]);
+
+    render_session_patterns(f, chunks[0], app);
+
+    let rows = app.session_rows();
+    let selected = app.selected_session_index(&rows);
+    app.session_table.select(Some(selected));
+
+    let table_area = if app.show_session_detail {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(chunks[1]);
+        let curve = app.stats.warmup_curve();
+        render_session_detail(f, halves[1], &rows[selected], selected == 0, curve.as_ref());
+        halves[0]
+    } else {
+        chunks[1]
+    };
+
+    // Only laptops tell docked from mobile
+    let show_dock = rows.iter().any(|session| session.dock.is_some());
+    let table_rows: Vec<Row> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, session)| {
+            let live = i == 0;
+            let start = session.start.with_timezone(&chrono::Local);
+            let mut cells = vec![
+                Cell::from(if live {
+                    "● now".to_string()
+                } else {
+                    start.format("%Y-%m-%d %H:%M").to_string()
+                }),
+                Cell::from(format_session_duration(session.duration())),
+                Cell::from(numbers::format_count(session.keystrokes)),
+                Cell::from(session.wpm.map(|w| format!("{:.1}", w)).unwrap_or_else(|| "—".to_string())),
+                Cell::from(
+                    session.error_rate()
+                        .map(|r| format!("{:.1}%", r * 100.0))
+                        .unwrap_or_else(|| "—".to_string()),
+                ),
+            ];
+            if show_dock {
+                cells.push(Cell::from(session.dock.map_or("", Dock::label)).style(Style::default().fg(Color::Cyan)));
+            }
+            cells.push(Cell::from(session.tag.clone().unwrap_or_default()).style(Style::default().fg(Color::Magenta)));
+            let row = Row::new(cells);
+            if live {
+                row.style(Style::default().fg(Color::Green))
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    let mut widths = vec![
+        Constraint::Length(17),
+        Constraint::Length(9),
+        Constraint::Length(11),
+        Constraint::Length(7),
+        Constraint::Length(8),
+    ];
+    let mut header = vec!["Start", "Duration", "Keystrokes", "WPM", "Errors"];
+    if show_dock {
+        widths.push(Constraint::Length(7));
+        header.push("Dock");
+    }
+    widths.push(Constraint::Min(0));
+    header.push("Tag");
+    let table = Table::new(table_rows, widths)
+        .header(Row::new(header).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "Sessions ({} of {}) ↑/↓ select · Enter details",
+            selected + 1,
+            rows.len()
+        )))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+        .highlight_symbol("▶ ");
+
+    f.render_stateful_widget(table, table_area, &mut app.session_table);
+
+    let records = &app.stats.records;
+    let mut records_text = format!(
+        "Best 1-min WPM: {}\n\
+         Best 5-min WPM: {}\n\
+         Most keystrokes in a day: {}\n\
+         Longest session: {}",
+        format_wpm_record(records.best_wpm_1m),
+        format_wpm_record(records.best_wpm_5m),
+        records.most_keystrokes_day
+            .map(|r| format!("{} ({})", r.value, r.achieved.format("%Y-%m-%d")))
+            .unwrap_or_else(|| "—".to_string()),
+        records.longest_session_secs
+            .map(|r| format!("{} min ({})", r.value / 60, r.achieved.format("%Y-%m-%d")))
+            .unwrap_or_else(|| "—".to_string()),
+    );
+    // Only once break reminders have suggested something
+    let (week_taken, week_suggested) = app.stats.break_compliance(7);
+    if week_suggested > 0 {
+        let (taken, suggested) = app.stats.break_compliance(1);
+        records_text.push_str(&format!(
+            "\n\nBreaks taken today: {} of {}\nBreaks taken in 7 days: {} of {} ({})",
+            taken,
+            suggested,
+            week_taken,
+            week_suggested,
+            report::format_percent(report::percent(week_taken, week_suggested), 0)
+        ));
+    }
+
+    let records_widget = Paragraph::new(records_text)
+        .block(Block::default().borders(Borders::ALL).title("🏆 Records"))
+        .style(Style::default().fg(Color::Yellow));
+
+    f.render_widget(records_widget, bottom[0]);
+    render_speed_by_hour(f, bottom[1], &app.stats);
+}
+
+/// Draws the average typing speed in each hour of the day over all days
+/// in memory, one bar per hour.
+fn render_speed_by_hour(f: &mut Frame, area: Rect, stats: &KeyStats) {
+    let speeds = keylogger::speed_by_hour(stats.daily_stats.values());
+    let block = Block::default().borders(Borders::ALL).title("⚡ Speed by Hour (WPM)");
+    if speeds.iter().all(Option::is_none) {
+        let empty = Paragraph::new("Not enough typing yet: an hour needs a minute of typing to count")
+            .wrap(Wrap { trim: true })
+            .style(Style::default().fg(Color::Gray))
+            .block(block);
+        f.render_widget(empty, area);
+        return;
+    }
+
+    let fastest = speeds.iter().flatten().copied().fold(0.0, f64::max);
+    let (fastest_hour, _) = speeds
+        .iter()
+        .enumerate()
+        .find(|(_, speed)| **speed == Some(fastest))
+        .expect("some hour has a speed");
+    let bars: Vec<Bar> = speeds
+        .iter()
+        .enumerate()
+        .map(|(hour, speed)| {
+            let color = if hour == fastest_hour { Color::Green } else { Color::Cyan };
+            Bar::default()
+                .value(speed.map_or(0, |wpm| wpm.round() as u64))
+                .text_value(String::new())
+                .style(Style::default().fg(color))
+        })
+        .collect();
+
+    let block = block.title_bottom(format!(" fastest {:02}:00 · {:.0} WPM ", fastest_hour, fastest));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    // One column per bar and one between bars: two columns per hour
+    let chart = BarChart::default()
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(1)
+        .bar_gap(1);
+    f.render_widget(chart, rows[0]);
+    let marks = Paragraph::new(hour_marks(48, 30)).style(Style::default().fg(Color::Gray));
+    f.render_widget(marks, rows[1]);
+}
+
+fn render_session_detail(f: &mut Frame, area: Rect, session: &TypingSession, live: bool, curve: Option<&WarmupCurve>) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    let title = if live {
+        "Session in progress".to_string()
+    } else {
+        format!("Session {}", session.start.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"))
+    };
+
+    // One bar per minute, squeezed to fit the pane
+    let width = chunks[0].width.saturating_sub(2).max(1) as usize;
+    let per_bar = session.timeline.len().div_ceil(width).max(1);
+    let timeline: Vec<u64> = session.timeline
+        .chunks(per_bar)
+        .map(|minutes| minutes.iter().map(|&c| c as u64).sum())
+        .collect();
+
+    let sparkline = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "{} · keystrokes per {} min",
+            title, per_bar
+        )))
+        .data(&timeline)
+        .style(Style::default().fg(Color::Cyan));
+
+    f.render_widget(sparkline, chunks[0]);
+
+    // Average pace by minute after the first keystroke, over all sessions
+    match curve {
+        Some(curve) => {
+            let wpm: Vec<u64> = curve.wpm.iter().map(|w| w.map_or(0, |w| w.round() as u64)).collect();
+            let warmup = Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title(format!(
+                    "Warm-up · WPM by minute, {} session(s)",
+                    curve.sessions
+                )))
+                .data(&wpm)
+                .style(Style::default().fg(Color::Magenta));
+            f.render_widget(warmup, chunks[1]);
+        }
+        None => {
+            let empty = Paragraph::new("No finished sessions with warm-up data yet")
+                .block(Block::default().borders(Borders::ALL).title("Warm-up"));
+            f.render_widget(empty, chunks[1]);
+        }
+    }
+
+    let format_wpm = |wpm: Option<f64>| wpm.map(|w| format!("{:.1}", w)).unwrap_or_else(|| "—".to_string());
+    let mut items: Vec<ListItem> = Vec::new();
+    if let Some(tag) = &session.tag {
+        let mut label = vec![Span::styled(format!("🏷️  {}", tag), Style::default().fg(Color::Magenta))];
+        if let Some(note) = &session.note {
+            label.push(Span::styled(format!(" · {}", note), Style::default().fg(Color::Gray)));
+        }
+        items.push(ListItem::new(Line::from(label)));
+    }
+    if let Some(dock) = session.dock {
+        let keyboard = match dock {
+            Dock::Docked => "external keyboard",
+            Dock::Mobile => "built-in keyboard",
+        };
+        items.push(ListItem::new(Span::styled(
+            format!("{} · mostly typed on the {}", dock, keyboard),
+            Style::default().fg(Color::Cyan),
+        )));
+    }
+    items.push(ListItem::new(format!(
+        "Net WPM: {}   Corrections: {}",
+        format_wpm(session.net_wpm),
+        session.corrections
+    )));
+    if !session.categories.is_empty() {
+        items.push(ListItem::new(format!("Symbol ratio: {}", report::format_symbol_ratio(&session.categories))));
+    }
+    if let Some(first_key_secs) = session.first_key_secs {
+        items.push(ListItem::new(format!(
+            "First {} min: {} WPM vs {} · first key after {}",
+            WARMUP_WINDOW,
+            format_wpm(session.warmup_wpm()),
+            format_wpm(session.wpm),
+            format_session_duration(chrono::Duration::seconds(first_key_secs as i64))
+        )));
+    }
+    match curve.and_then(WarmupCurve::full_speed_after) {
+        Some(0) => items.push(ListItem::new("You type at full speed from the first minute")),
+        Some(minutes) => items.push(ListItem::new(format!("You reach full speed after ~{} min", minutes))),
+        None => {}
+    }
+    let counts = count_column(session.top_keys.iter().map(|(_, count)| *count), 7);
+    items.extend(session.top_keys.iter().zip(counts).enumerate().map(|(i, ((key, _), count_text))| {
+        ListItem::new(Line::from(vec![
+            Span::styled(format!("{:2}. ", i + 1), Style::default().fg(Color::Yellow)),
+            Span::styled(
+                format!("{:<12}", keylogger::truncate_name(&keymap::display_name(key), 11)),
+                Style::default().fg(Color::White),
+            ),
+            Span::styled(count_text, Style::default().fg(Color::Green)),
+        ]))
+    }));
+    if session.top_keys.is_empty() {
+        items.push(ListItem::new("No per-key data for this session"));
+    }
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Top Keys (Esc to close)"));
+
+    f.render_widget(list, chunks[2]);
+}
+
+fn render_debug(f: &mut Frame, area: Rect, stats: &KeyStats, name_width: usize) {
+    let diagnostics = &stats.diagnostics;
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
+    let warn = |count: u64| {
+        if count > 0 {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::Green)
+        }
+    };
+    let key_list = |codes: &std::collections::BTreeMap<u16, u64>| {
+        codes
+            .iter()
+            .map(|(&code, count)| format!("{} ×{}", keymap::display_name(&keymap::key_name(code)), count))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let unknown: u64 = diagnostics.unknown_codes.values().sum();
+    let stuck = diagnostics.stuck_key_count();
+    let mut lines = vec![
+        Line::from(format!("Presses:          {}", numbers::format_count(diagnostics.presses))),
+        Line::from(format!("Releases:         {}", numbers::format_count(diagnostics.releases))),
+        Line::from(format!("Auto-repeats:     {}", numbers::format_count(diagnostics.repeats))),
+        Line::from(Span::styled(
+            format!("Orphan releases:  {}", diagnostics.orphan_releases),
+            warn(diagnostics.orphan_releases),
+        )),
+        Line::from(Span::styled(
+            format!("Double presses:   {}", diagnostics.double_presses),
+            warn(diagnostics.double_presses),
+        )),
+        Line::from(Span::styled(format!("Stuck keys:       {}", stuck), warn(stuck))),
+    ];
+    if stuck > 0 {
+        lines.push(Line::from(format!("  {}", key_list(&diagnostics.stuck_keys))));
+    }
+    lines.push(Line::from(format!(
+        "Average hold:     {}",
+        diagnostics.average_hold().map_or("—".to_string(), |hold| format!("{} ms", hold.as_millis()))
+    )));
+    lines.push(Line::from(Span::styled(
+        format!("Hold anomalies:   {}", diagnostics.hold_anomalies),
+        warn(diagnostics.hold_anomalies),
+    )));
+    lines.push(Line::from(Span::styled(format!("Unknown codes:    {}", unknown), warn(unknown))));
+    if unknown > 0 {
+        lines.push(Line::from(format!("  {}", key_list(&diagnostics.unknown_codes))));
+    }
+    if let Some(layout) = &diagnostics.xkb_layout {
+        lines.push(Line::from(format!("XKB layout:       {}", layout)));
+    }
+    if !diagnostics.ignored.is_empty() {
+        let ignored: u64 = diagnostics.ignored.iter().map(|(_, count)| count).sum();
+        lines.push(Line::from(format!("Ignored by rules: {}", numbers::format_count(ignored))));
+        for (rule, count) in &diagnostics.ignored {
+            lines.push(Line::from(format!("  {} ×{}", rule, numbers::format_count(*count))));
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Raw events by type", Style::default().fg(Color::Cyan))));
+    for (event_type, count) in &diagnostics.event_types {
+        lines.push(Line::from(format!("  {:<16}{}", event_type, numbers::format_count(*count))));
+    }
+
+    let events = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Events since start"))
+        .wrap(Wrap { trim: false });
+    f.render_widget(events, chunks[0]);
+
+    let items: Vec<ListItem> = if diagnostics.errors.is_empty() {
+        vec![ListItem::new(Span::styled("No device errors", Style::default().fg(Color::Green)))]
+    } else {
+        diagnostics
+            .errors
+            .iter()
+            .rev()
+            .map(|error| {
+                let repeated = if error.count > 1 {
+                    format!(" ×{} until {}", error.count, error.last_seen.format("%H:%M:%S"))
+                } else {
+                    String::new()
+                };
+                ListItem::new(vec![
+                    Line::from(vec![
+                        Span::styled(error.first_seen.format("%H:%M:%S").to_string(), Style::default().fg(Color::Gray)),
+                        Span::raw(format!(" {}{}", keylogger::truncate_name(&error.device, name_width), repeated)),
+                    ]),
+                    Line::from(Span::styled(format!("  {}", error.message), Style::default().fg(Color::Red))),
+                ])
+            })
+            .collect()
+    };
+
+    let errors = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Device errors ({} total)", diagnostics.error_count)),
+    );
 
-    f.render_widget(gauge, chunks[2]);
+    let rollover = &stats.rollover;
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(rollover.presses_by_depth.len() as u16 + 6),
+            Constraint::Min(0),
+        ])
+        .split(chunks[1]);
+    render_rollover(f, right[0], rollover);
+    f.render_widget(errors, right[1]);
 }
 
-fn render_top_keys(f: &mut Frame, area: Rect, app: &App) {
-    let top_keys = app.stats.get_top_keys(20);
-    
-    let items: Vec<ListItem> = top_keys
+/// Shows how many keys were held at once when keys were pressed.
+fn render_rollover(f: &mut Frame, area: Rect, rollover: &Rollover) {
+    const BAR_WIDTH: usize = 20;
+
+    let presses: u64 = rollover.presses_by_depth.iter().sum();
+    let chorded = rollover.chorded_presses();
+    let mut lines = vec![
+        Line::from(format!(
+            "Max rollover:     {}",
+            match rollover.max_depth() {
+                0 => "—".to_string(),
+                1 => "1 key".to_string(),
+                depth => format!("{} keys", depth),
+            }
+        )),
+        Line::from(format!(
+            "Chorded presses:  {} ({})",
+            numbers::format_count(chorded),
+            report::format_percent(report::percent(chorded, presses), 1)
+        )),
+        Line::from(format!("Keys down now:    {}", rollover.current)),
+        Line::from(""),
+    ];
+
+    let most = rollover.presses_by_depth.iter().copied().max().unwrap_or(0);
+    for (index, &count) in rollover.presses_by_depth.iter().enumerate() {
+        let width = if most > 0 { (count * BAR_WIDTH as u64).div_ceil(most) as usize } else { 0 };
+        lines.push(Line::from(vec![
+            Span::raw(format!("{:>2} down  ", index + 1)),
+            Span::styled(format!("{:<BAR_WIDTH$}", "█".repeat(width)), Style::default().fg(Color::Cyan)),
+            Span::raw(format!(
+                " {} ({})",
+                numbers::format_count(count),
+                report::format_percent(report::percent(count, presses), 1)
+            )),
+        ]));
+    }
+
+    let panel = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Rollover (keys held at each press)"));
+    f.render_widget(panel, area);
+}
+
+fn render_timeline(f: &mut Frame, area: Rect, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(7),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    let now = chrono::Local::now();
+    let buckets = app.stats.timeline.today(now);
+
+    // Squeeze the day into the available width by merging neighbouring buckets
+    let width = chunks[0].width.saturating_sub(2).max(1) as usize;
+    let step = buckets.len().div_ceil(width).max(1);
+    app.timeline_step = step;
+    let columns: Vec<u32> = buckets
+        .chunks(step)
+        .map(|group| group.iter().map(|b| b.keystrokes).sum())
+        .collect();
+
+    let cursor_bucket = app.timeline_cursor.unwrap_or(buckets.len() - 1).min(buckets.len() - 1);
+    let cursor_column = cursor_bucket / step;
+
+    let bar = activity_bar(&columns);
+    let pointer: String = (0..columns.len())
+        .map(|i| if i == cursor_column { '▲' } else { ' ' })
+        .collect();
+    let minutes_per_column = step as u32 * TIMELINE_BUCKET_MINUTES;
+
+    let timeline = Paragraph::new(vec![
+        Line::from(bar.clone()),
+        Line::from(bar),
+        Line::from(Span::styled(pointer, Style::default().fg(Color::Cyan))),
+        Line::from(Span::styled(hour_marks(columns.len(), minutes_per_column), Style::default().fg(Color::Gray))),
+    ])
+    .block(Block::default().borders(Borders::ALL).title(format!(
+        "Today since midnight ({} min per column) ←/→ move cursor",
+        minutes_per_column
+    )));
+
+    f.render_widget(timeline, chunks[0]);
+
+    // Details of the column under the cursor
+    let group = &buckets[cursor_column * step..((cursor_column + 1) * step).min(buckets.len())];
+    let from = now
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        + chrono::Duration::minutes((cursor_column as u32 * minutes_per_column) as i64);
+    let to = from + chrono::Duration::minutes(minutes_per_column as i64);
+    let total: u32 = group.iter().map(|b| b.keystrokes).sum();
+
+    let mut keys: HashMap<&str, u32> = HashMap::new();
+    for bucket in group {
+        for (key, count) in &bucket.keys {
+            *keys.entry(key.as_str()).or_insert(0) += count;
+        }
+    }
+    let mut keys: Vec<(&str, u32)> = keys.into_iter().collect();
+    keys.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} – {}", from.format("%H:%M"), to.format("%H:%M")),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!("Keystrokes: {}", numbers::format_count(u64::from(total)))),
+        Line::from(""),
+    ];
+    if keys.is_empty() {
+        lines.push(Line::from("No keystrokes in this period"));
+    } else {
+        lines.push(Line::from("Dominant keys:"));
+    }
+    for (key, count) in keys.into_iter().take(5) {
+        lines.push(Line::from(format!(
+            "  {:<12}{:>6} ({})",
+            app.stats.key_label(key),
+            count,
+            report::format_percent(report::percent(count.into(), total.into()), 0)
+        )));
+    }
+
+    let details = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Selected Period"));
+
+    f.render_widget(details, chunks[1]);
+}
+
+/// Colours keystroke counts by how they compare with the largest one.
+fn activity_bar(columns: &[u32]) -> Vec<Span<'static>> {
+    let max = columns.iter().copied().max().unwrap_or(0).max(1);
+    columns
         .iter()
-        .enumerate()
-        .map(|(i, (key, count))| {
-            let key_display = match key.as_str() {
-                "KEY_SPACE" => "SPACE".to_string(),
-                "KEY_ENTER" => "ENTER".to_string(),
-                "KEY_TAB" => "TAB".to_string(),
-                "KEY_BACKSPACE" => "BACKSPACE".to_string(),
-                k if k.starts_with("KEY_") => k[4..].to_string(),
-                _ => key.clone(),
+        .map(|&count| {
+            let intensity = count as f64 / max as f64;
+            let color = if count == 0 {
+                Color::DarkGray
+            } else if intensity > 0.75 {
+                Color::Red
+            } else if intensity > 0.5 {
+                Color::Yellow
+            } else if intensity > 0.25 {
+                Color::Green
+            } else {
+                Color::Blue
             };
-            
+            let symbol = if count == 0 { "·" } else { "█" };
+            Span::styled(symbol, Style::default().fg(color))
+        })
+        .collect()
+}
+
+/// Hour marks under an activity bar, every few hours depending on the width.
+fn hour_marks(columns: usize, minutes_per_column: u32) -> String {
+    let mut hours = vec![' '; columns];
+    for hour in (0..24).step_by(if columns < 80 { 6 } else { 3 }) {
+        let column = (hour * 60 / minutes_per_column) as usize;
+        for (offset, c) in format!("{:02}", hour).chars().enumerate() {
+            if let Some(slot) = hours.get_mut(column + offset) {
+                *slot = c;
+            }
+        }
+    }
+    hours.into_iter().collect()
+}
+
+/// Formats a session length as `1h 05m`, `12m` or `45s`.
+/// Renders the session patterns of the last 30 days above the sessions table.
+fn render_session_patterns(f: &mut Frame, area: Rect, app: &App) {
+    let today = keylogger::today();
+    let start = today - chrono::Duration::days(29);
+    let patterns = app.stats.session_patterns(start, today);
+    let dash = || "—".to_string();
+    let label = Style::default().fg(Color::Gray);
+    let value = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    let line = Line::from(vec![
+        Span::styled("Average ", label),
+        Span::styled(patterns.average_length.map(format_session_duration).unwrap_or_else(dash), value),
+        Span::styled("  ·  P90 ", label),
+        Span::styled(patterns.p90_length.map(format_session_duration).unwrap_or_else(dash), value),
+        Span::styled("  ·  Gap ", label),
+        Span::styled(patterns.average_gap.map(format_session_duration).unwrap_or_else(dash), value),
+        Span::styled("  ·  Per day ", label),
+        Span::styled(report::format_sessions_per_day(&patterns, start), value),
+    ]);
+    let title = format!("Last 30 days ({} sessions)", patterns.sessions);
+    f.render_widget(Paragraph::new(line).block(Block::default().borders(Borders::ALL).title(title)), area);
+}
+
+fn format_session_duration(duration: chrono::Duration) -> String {
+    let secs = duration.num_seconds().max(0);
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, secs / 60 % 60)
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Formats a WPM record with the date it was set, or a dash if unset.
+fn format_wpm_record(record: Option<Record<f64>>) -> String {
+    record
+        .map(|r| format!("{:.1} ({})", r.value, r.achieved.format("%Y-%m-%d")))
+        .unwrap_or_else(|| "—".to_string())
+}
+
+/// Builds a block title that shows the active key class filter.
+fn filtered_title(title: &str, filter: Option<KeyClass>) -> String {
+    match filter {
+        Some(class) => format!("{} [{}] ('c' to cycle)", title, class),
+        None => format!("{} [All] ('c' to cycle)", title),
+    }
+}
+
+/// Shows the History tab: the daily list, or the day, comparison, drift
+/// chart or months picked with its keys. Call after
+/// [`App::refresh_archived_days`], and [`App::refresh_months`] for the months.
+fn render_history(f: &mut Frame, area: Rect, app: &App) {
+    let stats = &app.stats;
+    let archived = app.archived_days.as_ref().expect("refreshed by the caller");
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(7),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    // Archived days first, so the days in memory win
+    let all_days: BTreeMap<&String, &DayStats> = archived.iter().chain(stats.daily_stats.iter()).collect();
+
+    // Sparkline only takes integers, so plot centi-bits
+    let entropy_points: Vec<u64> = all_days
+        .values()
+        .filter_map(|day| day.entropy())
+        .map(|bits| (bits * 100.0).round() as u64)
+        .collect();
+
+    let sparkline = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title("Key Diversity per Day"))
+        .data(&entropy_points)
+        .style(Style::default().fg(Color::Cyan));
+
+    // Tenths of a percent; days from before deletions were counted have none
+    let deleted_days: Vec<f64> = all_days.values().filter_map(|day| day.deleted_percent()).collect();
+    let deleted_points: Vec<u64> = deleted_days.iter().map(|share| (share * 10.0).round() as u64).collect();
+    let deleted_title = match deleted_days.last() {
+        Some(last) => format!("Deleted per Day (last {:.0}%)", last),
+        None => "Deleted per Day".to_string(),
+    };
+    let deleted = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(deleted_title))
+        .data(&deleted_points)
+        .style(Style::default().fg(Color::Red));
+
+    let trend_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[0]);
+    f.render_widget(sparkline, trend_chunks[0]);
+    f.render_widget(deleted, trend_chunks[1]);
+
+    if let Some(date) = app.browse_date {
+        let key = keylogger::day_key(date);
+        let day = stats.daily_stats.get(&key).or_else(|| archived.get(&key));
+        render_day(f, chunks[1], stats, date, day, app.log_hours.as_ref());
+        return;
+    }
+
+    if app.compare_mode != CompareMode::Off {
+        render_comparison(f, chunks[1], stats, app.compare_mode);
+        return;
+    }
+
+    if let Some(view) = &app.drift {
+        render_drift(f, chunks[1], stats, &all_days, view);
+        return;
+    }
+
+    if app.show_months
+        && let Some(months) = app.months.value()
+    {
+        render_months(f, chunks[1], stats, months);
+        return;
+    }
+
+    let now = chrono::Local::now().naive_local();
+    let items: Vec<ListItem> = all_days
+        .iter()
+        .rev()
+        .map(|(date, day)| {
+            let diversity = day.entropy()
+                .map(|bits| format!("{:.2} bits", bits))
+                .unwrap_or_else(|| "—".to_string());
+            // Days with low coverage are partial, not quiet
+            let coverage = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| day.coverage(date, app.log_hours.as_ref(), now));
+            let (ran, ran_color) = match coverage {
+                Some(coverage) if coverage.is_low() => (format!("⚠ ran {:.0}%", coverage.share() * 100.0), Color::Red),
+                Some(coverage) => (format!("ran {:.0}%", coverage.share() * 100.0), Color::DarkGray),
+                None => (String::new(), Color::DarkGray),
+            };
+
             ListItem::new(Line::from(vec![
+                Span::styled(format!("{}  ", date), Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{:>8} keys  ", numbers::format_count(day.keystrokes)), Style::default().fg(Color::Green)),
+                Span::styled(format!("{:<11}", ran), Style::default().fg(ran_color)),
+                Span::styled(format!("diversity {:<10}", diversity), Style::default().fg(Color::Cyan)),
                 Span::styled(
-                    format!("{:2}. ", i + 1),
-                    Style::default().fg(Color::Yellow),
-                ),
-                Span::styled(
-                    format!("{:<15}", key_display),
-                    Style::default().fg(Color::White),
-                ),
-                Span::styled(
-                    format!("{:>8}", count),
-                    Style::default().fg(Color::Green),
+                    format!("  symbols {:<16}", report::format_symbol_ratio(&day.category_breakdown())),
+                    Style::default().fg(Color::Magenta),
                 ),
                 Span::styled(
-                    format!(" ({:.1}%)", *count as f64 / app.stats.total_keystrokes as f64 * 100.0),
-                    Style::default().fg(Color::Gray),
+                    format!("  deleted {}", report::format_percent(day.deleted_percent(), 0)),
+                    Style::default().fg(Color::Red),
                 ),
             ]))
         })
         .collect();
 
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Top Keys"))
+        .block(Block::default().borders(Borders::ALL).title("Daily History ('v' to compare, 'b' to browse, 'f' for key drift, 'm' for months)"))
         .style(Style::default().fg(Color::White));
 
+    f.render_widget(list, chunks[1]);
+}
+
+/// Width of the keystroke bars of the months view.
+const MONTH_BAR_WIDTH: usize = 20;
+
+/// Shows the History tab's months: totals of each calendar month, newest
+/// first, with a bar relative to the busiest month and the change from the
+/// month before.
+///
+/// # Arguments
+/// * `months` - The months, oldest first, see [`KeyStats::recent_months`]
+fn render_months(f: &mut Frame, area: Rect, stats: &KeyStats, months: &[MonthStats]) {
+    let busiest = months.iter().map(|month| month.keystrokes).max().unwrap_or(0).max(1);
+    let items: Vec<ListItem> = months
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, month)| {
+            let filled = (month.keystrokes as f64 / busiest as f64 * MONTH_BAR_WIDTH as f64).round() as usize;
+            let bar = format!("{}{}", "█".repeat(filled), "░".repeat(MONTH_BAR_WIDTH - filled));
+            let change = index.checked_sub(1).and_then(|previous| month.change(&months[previous]));
+            let change_color = match change {
+                Some(change) if change < 0.0 => Color::Red,
+                Some(_) => Color::Green,
+                None => Color::DarkGray,
+            };
+            let top: Vec<String> = month.top_keys.iter().take(3).map(|(key, _)| stats.key_label(key)).collect();
+            let label = if month.partial { format!("{} (so far)", month.label()) } else { month.label() };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<18}", label), Style::default().fg(Color::Yellow)),
+                Span::styled(bar, Style::default().fg(Color::Cyan)),
+                Span::styled(format!("{:>10} keys", numbers::format_count(month.keystrokes)), Style::default().fg(Color::Green)),
+                Span::styled(format!("{:>6}", report::format_month_change(change)), Style::default().fg(change_color)),
+                Span::raw(format!(
+                    "  {:>2}/{:<2} days active  {:>6} per active day  {:>10}",
+                    month.active_days,
+                    month.days,
+                    month.per_active_day().map_or("—".to_string(), |per_day| format!("{:.0}", per_day)),
+                    month.wpm.map_or("—".to_string(), |wpm| format!("{:.1} WPM", wpm)),
+                )),
+                Span::styled(format!("  {}", top.join(" ")), Style::default().fg(Color::Magenta)),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Months: change in keys per day vs the month before ('m' for days)"))
+        .style(Style::default().fg(Color::White));
     f.render_widget(list, area);
 }
 
-fn render_heatmap(f: &mut Frame, area: Rect, app: &App) {
-    let key_layout = [
-        ("1234567890", vec![2, 3, 4, 5, 6, 7, 8, 9, 10, 11]),
-        ("QWERTYUIOP", vec![16, 17, 18, 19, 20, 21, 22, 23, 24, 25]),
-        ("ASDFGHJKL", vec![30, 31, 32, 33, 34, 35, 36, 37, 38]),
-        ("ZXCVBNM", vec![44, 45, 46, 47, 48, 49, 50]),
+/// Line styles of the drift chart's keys, in the order they were picked.
+const DRIFT_STYLES: [(Color, Marker, GraphType); MAX_DRIFT_KEYS] = [
+    (Color::Cyan, Marker::Braille, GraphType::Line),
+    (Color::Yellow, Marker::Dot, GraphType::Line),
+    (Color::Magenta, Marker::HalfBlock, GraphType::Scatter),
+];
+
+/// Keys the drift chart offers, most used first.
+fn drift_candidates(stats: &KeyStats) -> Vec<(u16, u64)> {
+    let mut keys: Vec<(u16, u64)> = stats.physical_key_counts().into_iter().filter(|&(_, count)| count > 0).collect();
+    keys.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    keys
+}
+
+/// Shows the History tab's drift chart: the weekly share of up to three
+/// keys over the whole history, next to the list they are picked from.
+///
+/// # Arguments
+/// * `days` - Every recorded day, archived ones included
+/// * `view` - The picked keys and the list cursor
+fn render_drift(f: &mut Frame, area: Rect, stats: &KeyStats, days: &BTreeMap<&String, &DayStats>, view: &DriftView) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(24), Constraint::Min(0)])
+        .split(area);
+
+    let candidates = drift_candidates(stats);
+    let items: Vec<ListItem> = candidates
+        .iter()
+        .map(|&(code, count)| {
+            let marker = match view.keys.iter().position(|&key| key == code) {
+                Some(slot) => Span::styled("● ", Style::default().fg(DRIFT_STYLES[slot].0)),
+                None => Span::styled("○ ", Style::default().fg(Color::DarkGray)),
+            };
+            ListItem::new(Line::from(vec![
+                marker,
+                Span::raw(format!("{:<10}", keymap::display_name(&keymap::key_name(code)))),
+                Span::styled(format!("{:>8}", numbers::format_compact(count)), Style::default().fg(Color::Green)),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Keys (Enter)"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected((!candidates.is_empty()).then_some(view.cursor));
+    f.render_stateful_widget(list, columns[0], &mut state);
+
+    let title = "Key Drift: share of each week's keystrokes ('f' to close)";
+    let weeks = report::weekly_totals(days.iter().map(|(&date, &day)| (date, day)));
+    let (Some(&first), Some(&last)) = (weeks.keys().next(), weeks.keys().next_back()) else {
+        let empty = Paragraph::new("No days recorded yet")
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(empty, columns[1]);
+        return;
+    };
+    if view.keys.is_empty() {
+        let empty = Paragraph::new(format!("Pick up to {} keys with ↑/↓ and Enter", MAX_DRIFT_KEYS))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(empty, columns[1]);
+        return;
+    }
+
+    let series: Vec<DriftSeries> = view.keys.iter().map(|&code| DriftSeries::new(&weeks, code)).collect();
+    let points: Vec<Vec<(f64, f64)>> = series
+        .iter()
+        .map(|series| {
+            series
+                .points
+                .iter()
+                .map(|&(monday, share)| ((monday - first).num_weeks() as f64, share))
+                .collect()
+        })
+        .collect();
+    let datasets: Vec<Dataset> = series
+        .iter()
+        .zip(&points)
+        .zip(DRIFT_STYLES)
+        .map(|((series, points), (color, marker, graph_type))| {
+            let change = series
+                .change()
+                .map(|change| format!(" {} pp", report::signed_f64(change, 1)))
+                .unwrap_or_default();
+            Dataset::default()
+                .name(format!("{}{}", keymap::display_name(&keymap::key_name(series.code)), change))
+                .marker(marker)
+                .graph_type(graph_type)
+                .style(Style::default().fg(color))
+                .data(points)
+        })
+        .collect();
+
+    let span = ((last - first).num_weeks() as f64).max(1.0);
+    let top = series.iter().map(DriftSeries::peak).fold(0.0, f64::max).max(0.1) * 1.1;
+    let chart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .hidden_legend_constraints((Constraint::Ratio(1, 3), Constraint::Ratio(1, 2)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, span])
+                .labels([report::week_label(first), report::week_label(last)]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, top])
+                .labels(["0%".to_string(), format!("{:.1}%", top / 2.0), format!("{:.1}%", top)]),
+        );
+    f.render_widget(chart, columns[1]);
+}
+
+/// Shows one day of the History tab's browse mode.
+///
+/// # Arguments
+/// * `date` - The day under the browse cursor
+/// * `day` - Its statistics, None for a day without any
+fn render_day(
+    f: &mut Frame,
+    area: Rect,
+    stats: &KeyStats,
+    date: NaiveDate,
+    day: Option<&DayStats>,
+    log_hours: Option<&Schedule>,
+) {
+    let title = format!("{} {} ('←/→' day, PgUp/PgDn week)", date, date.format("%a"));
+    let Some(day) = day else {
+        let empty = Paragraph::new("No keystrokes recorded on this day")
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(empty, area);
+        return;
+    };
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let left = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(0)])
+        .split(columns[0]);
+    render_day_activity(f, left[0], day);
+
+    let busiest = day
+        .most_active_hour
+        .or_else(|| day.busiest_hour())
+        .map(|hour| format!("{:02}:00 – {:02}:00", hour, (hour + 1) % 24))
+        .unwrap_or_else(|| "—".to_string());
+    let sessions = stats.sessions_on(date);
+    let mut lines = vec![
+        Line::from(format!("Keystrokes: {}", numbers::format_count(day.keystrokes))),
+        Line::from(format!("Most active hour: {}", busiest)),
+        Line::from(format!("Sessions: {}", sessions.len())),
     ];
+    if let Some(coverage) = day.coverage(date, log_hours, chrono::Local::now().naive_local()) {
+        let ran = format!("Logger ran: {}", report::format_coverage(coverage));
+        lines.push(if coverage.is_low() {
+            Line::from(Span::styled(format!("⚠ {} - low coverage, the totals are partial", ran), Style::default().fg(Color::Red)))
+        } else {
+            Line::from(ran)
+        });
+    }
+    let categories = day.category_breakdown();
+    if let Some(mix) = report::format_category_mix(&categories) {
+        lines.push(Line::from(format!("Mix: {}", mix)));
+        lines.push(Line::from(format!("Symbol ratio: {}", report::format_symbol_ratio(&categories))));
+    }
+    if let Some(curve) = WarmupCurve::from_sessions(sessions.iter().copied()) {
+        let warmup = match curve.full_speed_after() {
+            Some(minutes) => format!("Warm-up: full speed after {} min", minutes),
+            None => "Warm-up: sessions too short to tell".to_string(),
+        };
+        lines.push(Line::from(warmup));
+    }
+    if let Some(caps) = report::format_caps_lock(day) {
+        lines.push(Line::from(format!("Caps Lock: {}", caps)));
+    }
+    lines.push(Line::from(""));
+    for session in sessions {
+        let start = session.start.with_timezone(&chrono::Local);
+        let end = session.end.with_timezone(&chrono::Local);
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} – {}  ", start.format("%H:%M"), end.format("%H:%M")),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::styled(format!("{:>6} keys  ", numbers::format_count(session.keystrokes)), Style::default().fg(Color::Green)),
+            Span::raw(format!("WPM {}", session.wpm.map(|w| format!("{:.1}", w)).unwrap_or_else(|| "—".to_string()))),
+            Span::styled(
+                session.categories.symbol_ratio().map(|ratio| format!("  symbols {:.1}%", ratio * 100.0)).unwrap_or_default(),
+                Style::default().fg(Color::Magenta),
+            ),
+        ]));
+    }
+    let summary = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .wrap(Wrap { trim: false });
+    f.render_widget(summary, left[1]);
 
-    let mut heatmap_text = Vec::new();
-    let max_count = app.stats.key_counts.values().max().copied().unwrap_or(1);
-
-    for (chars, codes) in key_layout.iter() {
-        let mut line_spans = Vec::new();
-        for (i, ch) in chars.chars().enumerate() {
-            if let Some(&key_code) = codes.get(i) {
-                let key_name = format!("KEY_{}", key_code);
-                let count = app.stats.key_counts.get(&key_name).copied().unwrap_or(0);
-                let intensity = if max_count > 0 { count as f64 / max_count as f64 } else { 0.0 };
-                
-                let color = if intensity > 0.8 {
-                    Color::Red
-                } else if intensity > 0.6 {
-                    Color::LightRed
-                } else if intensity > 0.4 {
-                    Color::Yellow
-                } else if intensity > 0.2 {
-                    Color::Green
-                } else if intensity > 0.0 {
-                    Color::Blue
-                } else {
-                    Color::Gray
-                };
+    let items: Vec<ListItem> = keylogger::top_keys(&day.key_distribution, 10, None)
+        .into_iter()
+        .map(|(key, count)| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<12}", stats.key_label(&key)), Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{:>8}", numbers::format_count(count)), Style::default().fg(Color::Green)),
+                Span::raw(format!(
+                    "  {:>6}",
+                    report::format_percent(report::percent(count, day.keystrokes), 1)
+                )),
+            ]))
+        })
+        .collect();
+    let top_keys = List::new(items).block(Block::default().borders(Borders::ALL).title("Top Keys"));
+    f.render_widget(top_keys, columns[1]);
+}
 
-                let display_char = if count > 0 {
-                    format!("{}[{}] ", ch, count)
-                } else {
-                    format!("{} ", ch)
-                };
+/// Draws a day's keystrokes from midnight to midnight, by minute where the
+/// day still has minute data and by hour after `ctrlq compact`.
+fn render_day_activity(f: &mut Frame, area: Rect, day: &DayStats) {
+    let width = area.width.saturating_sub(2).max(1) as u32;
+    let (mut columns, minutes_per_column) = if day.minute_keystrokes.is_empty() {
+        let hours: Vec<u32> = day.hourly_keystrokes.iter().map(|&count| u32::try_from(count).unwrap_or(u32::MAX)).collect();
+        (hours, 60)
+    } else {
+        let step = (24 * 60u32).div_ceil(width);
+        (day.minute_keystrokes.buckets(step), step)
+    };
+    columns.resize((24 * 60u32).div_ceil(minutes_per_column) as usize, 0);
 
-                line_spans.push(Span::styled(
-                    display_char,
-                    Style::default().fg(color).add_modifier(Modifier::BOLD),
-                ));
+    let activity = Paragraph::new(vec![
+        Line::from(activity_bar(&columns)),
+        Line::from(Span::styled(hour_marks(columns.len(), minutes_per_column), Style::default().fg(Color::Gray))),
+    ])
+    .block(Block::default().borders(Borders::ALL).title(format!("Activity ({} min per column)", minutes_per_column)));
+    f.render_widget(activity, area);
+}
+
+/// Parses a `daily_stats` key back into a date.
+fn parse_day(key: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(key, "%Y-%m-%d").ok()
+}
+
+fn render_comparison(f: &mut Frame, area: Rect, stats: &KeyStats, mode: CompareMode) {
+    let today = keylogger::today();
+    let period = |spec: &str| report::parse_period(spec, today).expect("built-in period keyword");
+
+    let cmp = match mode {
+        CompareMode::Weeks => {
+            let (a, b) = (period("last-week"), period("this-week"));
+            stats.compare_periods((a.0, a.1, &a.2), (b.0, b.1, &b.2))
+        }
+        _ => stats.diff_days(today - chrono::Duration::days(1), today),
+    };
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
+    for (column, summary) in [(columns[0], &cmp.a), (columns[1], &cmp.b)] {
+        let mut lines = vec![
+            Line::from(format!("Keystrokes: {}", numbers::format_count(summary.keystrokes))),
+            Line::from(format!(
+                "WPM: {}",
+                summary.wpm.map(|w| format!("{:.1}", w)).unwrap_or_else(|| "—".to_string())
+            )),
+            Line::from(format!(
+                "Error rate: {}",
+                summary.error_rate.map(|e| format!("{:.1}%", e * 100.0)).unwrap_or_else(|| "—".to_string())
+            )),
+        ];
+        if let Some(note) = report::missing_note(summary) {
+            lines.push(Line::from(Span::styled(note, Style::default().fg(Color::DarkGray))));
+        }
+
+        let is_current = std::ptr::eq(summary, &cmp.b);
+        if is_current {
+            lines[0].spans.push(delta_span(cmp.keystroke_delta() as f64, report::signed(cmp.keystroke_delta())));
+            if let Some(d) = cmp.wpm_delta() {
+                lines[1].spans.push(delta_span(d, report::signed_f64(d, 1)));
+            }
+            if let Some(d) = cmp.error_rate_delta() {
+                // A falling error rate is an improvement
+                lines[2].spans.push(delta_span(-d, format!("{} pp", report::signed_f64(d, 1))));
+            }
+
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Biggest movers", Style::default().add_modifier(Modifier::BOLD))));
+            for change in &cmp.biggest_movers {
+                lines.push(Line::from(vec![
+                    Span::raw(format!(
+                        "{:<10}{:>5.1}% → {:>5.1}% ",
+                        keymap::display_name(&change.key),
+                        change.share_a * 100.0,
+                        change.share_b * 100.0
+                    )),
+                    delta_span(change.delta_points(), format!("{} pp", report::signed_f64(change.delta_points(), 1))),
+                ]));
+            }
+        } else {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!("Top keys ({})", cmp.b.label),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            for change in &cmp.top_keys {
+                lines.push(Line::from(vec![
+                    Span::raw(format!(
+                        "{:<10}{:>5.1}% → {:>5.1}% ",
+                        keymap::display_name(&change.key),
+                        change.share_a * 100.0,
+                        change.share_b * 100.0
+                    )),
+                    delta_span(change.delta_points(), format!("{} pp", report::signed_f64(change.delta_points(), 1))),
+                ]));
             }
         }
-        heatmap_text.push(Line::from(line_spans));
-        heatmap_text.push(Line::from(""));
+
+        let widget = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(summary.label.clone()))
+            .style(Style::default().fg(Color::White));
+        f.render_widget(widget, column);
     }
+}
 
-    // Add space bar
-    let space_count = app.stats.key_counts.get("KEY_57").copied().unwrap_or(0);
-    let space_intensity = if max_count > 0 { space_count as f64 / max_count as f64 } else { 0.0 };
-    let space_color = if space_intensity > 0.8 {
-        Color::Red
-    } else if space_intensity > 0.6 {
-        Color::LightRed
-    } else if space_intensity > 0.4 {
-        Color::Yellow
-    } else if space_intensity > 0.2 {
+/// Styles a delta value green when positive and red when negative.
+fn delta_span(value: f64, text: String) -> Span<'static> {
+    let color = if value > 0.0 {
         Color::Green
-    } else if space_intensity > 0.0 {
-        Color::Blue
+    } else if value < 0.0 {
+        Color::Red
     } else {
         Color::Gray
     };
+    Span::styled(format!("  {}", text), Style::default().fg(color))
+}
 
-    heatmap_text.push(Line::from(vec![
-        Span::styled(
-            format!("      SPACE[{}]      ", space_count),
-            Style::default().fg(space_color).add_modifier(Modifier::BOLD),
-        )
-    ]));
-    heatmap_text.push(Line::from(""));
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Add legend
-    heatmap_text.push(Line::from(vec![
-        Span::styled("Legend: ", Style::default().fg(Color::White)),
-        Span::styled("■ Very High ", Style::default().fg(Color::Red)),
-        Span::styled("■ High ", Style::default().fg(Color::LightRed)),
-        Span::styled("■ Medium ", Style::default().fg(Color::Yellow)),
-        Span::styled("■ Low ", Style::default().fg(Color::Green)),
-        Span::styled("■ Very Low ", Style::default().fg(Color::Blue)),
-        Span::styled("■ Unused ", Style::default().fg(Color::Gray)),
-    ]));
+    /// Draws one frame of a tab and returns it as text, one line per row.
+    fn render(app: &mut App, tab: usize, width: u16, height: u16) -> String {
+        app.selected_tab = tab;
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| ui(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect::<String>().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
-    let heatmap = Paragraph::new(Text::from(heatmap_text))
-        .block(Block::default().borders(Borders::ALL).title("Keyboard Heatmap"))
-        .alignment(Alignment::Center);
-
-    f.render_widget(heatmap, area);
-}
-
-fn render_sessions(f: &mut Frame, area: Rect, app: &App) {
-    let info = Paragraph::new(format!(
-        "📈 Session Information\n\n\
-         Current Session Started: {}\n\
-         Total Sessions: {}\n\
-         Average WPM: {:.1}\n\n\
-         Daily Statistics:\n\
-         Today's Keystrokes: {}\n\n\
-         Press 'q' to quit, 'Tab' to switch tabs, 'r' to reset stats",
-        app.stats.session_start.format("%Y-%m-%d %H:%M:%S"),
-        app.stats.typing_sessions.len() + 1,
-        app.stats.get_wpm().unwrap_or(0.0),
-        app.stats.total_keystrokes,
-    ))
-    .block(Block::default().borders(Borders::ALL).title("Session Info"))
-    .wrap(Wrap { trim: true })
-    .style(Style::default().fg(Color::White));
+    /// Statistics with the given lifetime key counts.
+    fn stats_with(counts: &[(&str, u64)]) -> KeyStats {
+        let mut stats = KeyStats::new();
+        for &(key, count) in counts {
+            stats.key_counts.insert(key.to_string(), count);
+            stats.total_keystrokes += count;
+        }
+        stats
+    }
+
+    /// The parts of every tab that don't depend on the clock: the tab bar,
+    /// the panel titles, the top key rows, the heatmap legend and the status bar.
+    fn outline(stats: KeyStats) -> String {
+        let mut app = App::new();
+        app.stats = stats;
+        let mut out = Vec::new();
+        for (tab, title) in TAB_TITLES.iter().enumerate() {
+            let frame = render(&mut app, tab, 80, 24);
+            let lines: Vec<&str> = frame.split('\n').collect();
+            if tab == 0 {
+                out.push(format!("tabs: {}", lines[2].trim_matches(|c: char| c == '│' || c.is_whitespace())));
+                // Inside the margin of one row
+                out.push(format!("status: {}", lines[lines.len() - 2].trim()));
+            }
+            out.push(format!("## {}", title));
+            for line in &lines {
+                for (start, _) in line.match_indices('┌') {
+                    let panel: String = line[start + '┌'.len_utf8()..].chars().take_while(|&c| c != '─' && c != '┐').collect();
+                    // The Timeline squeezes the hours since midnight, so its scale changes over the day
+                    let panel = match panel.split_once(" min per column") {
+                        Some((head, tail)) => {
+                            format!("{}N min per column{}", head.trim_end_matches(|c: char| c.is_ascii_digit()), tail)
+                        }
+                        None => panel,
+                    };
+                    if !panel.is_empty() {
+                        out.push(format!("title: {}", panel));
+                    }
+                }
+                let inner = line.trim_matches(|c: char| c == '│' || c.is_whitespace());
+                let ranked = inner.split_once(". ").is_some_and(|(rank, _)| rank.parse::<usize>().is_ok());
+                if (tab == 1 && ranked) || inner.starts_with("Legend:") {
+                    out.push(inner.to_string());
+                }
+            }
+        }
+        out.join("\n")
+    }
+
+    #[test]
+    fn snapshot_empty() {
+        insta::assert_snapshot!(outline(KeyStats::new()));
+    }
+
+    #[test]
+    fn snapshot_small() {
+        let stats = stats_with(&[("KEY_30", 1200), ("KEY_57", 900), ("KEY_18", 640), ("KEY_14", 120), ("KEY_28", 80)]);
+        insta::assert_snapshot!(outline(stats));
+    }
 
-    f.render_widget(info, area);
+    #[test]
+    fn snapshot_huge_counts() {
+        let stats = stats_with(&[
+            ("KEY_30", 9_876_543_210_987),
+            ("KEY_57", 1_234_567_890_123),
+            ("KEY_18", 999_999_999),
+            ("KEY_14", 1),
+        ]);
+        insta::assert_snapshot!(outline(stats));
+    }
+
+    #[test]
+    fn snapshot_long_key_names() {
+        let stats = stats_with(&[
+            ("KEY_611", 500),
+            ("KEY_633", 400),
+            ("SOME_IMPORTED_KEY_WITH_A_VERY_LONG_NAME_INDEED", 300),
+            ("KEY_30", 200),
+        ]);
+        insta::assert_snapshot!(outline(stats));
+    }
+
+    #[test]
+    fn empty_stats_never_show_nan_or_inf() {
+        let mut app = App::new();
+        app.stats = KeyStats::new();
+        // Every tab, then again with the months view and the session detail open
+        for details in [false, true] {
+            app.show_months = details;
+            app.show_session_detail = details;
+            for (tab, title) in TAB_TITLES.iter().enumerate() {
+                for (width, height) in CHECK_SIZES {
+                    let frame = render(&mut app, tab, width, height);
+                    let bad = frame.split(|c: char| !c.is_alphanumeric()).find(|word| ["NaN", "inf"].contains(word));
+                    assert_eq!(bad, None, "{} tab at {}x{}:\n{}", title, width, height, frame);
+                }
+            }
+        }
+    }
+
+    /// Total keystrokes at which `ui` panics, for the crash test; never
+    /// outside of it.
+    pub(super) static PANIC_AT_KEYSTROKES: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(u64::MAX);
+
+    /// Set in the process `render_panic_restores_terminal_and_saves` runs
+    /// its crash in.
+    const CRASH_CHILD: &str = "CTRLQ_TEST_CRASH_CHILD";
+
+    /// Hands out the events sent to it, like a device queue.
+    struct Scripted(mpsc::Receiver<keylogger::KeyEvent>);
+
+    impl keylogger::EventSource for Scripted {
+        fn label(&self) -> &str {
+            "scripted"
+        }
+        fn read_events(&mut self) -> Result<Vec<keylogger::KeyEvent>> {
+            Ok(self.0.try_iter().collect())
+        }
+    }
+
+    /// Local mode flags of a terminal; raw mode clears ICANON and ECHO.
+    fn local_modes(fd: i32) -> libc::tcflag_t {
+        // SAFETY: termios is plain data that tcgetattr fills in
+        let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+        assert_eq!(unsafe { libc::tcgetattr(fd, &mut termios) }, 0);
+        termios.c_lflag
+    }
+
+    /// A panic while drawing leaves the terminal as it was and the keys
+    /// typed so far saved. The crash runs in a process of its own, with a
+    /// pseudo terminal as stdin and stdout, its own data directory and
+    /// nobody else's panic hook.
+    #[test]
+    fn render_panic_restores_terminal_and_saves() {
+        if std::env::var_os(CRASH_CHILD).is_some() {
+            return crash_on_pty();
+        }
+        let dir = std::env::temp_dir().join(format!("ctrlq-ui-crash-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap());
+        child.args(["--exact", "ui::tests::render_panic_restores_terminal_and_saves", "--nocapture"]);
+        child.env(CRASH_CHILD, "1");
+        for var in ["CTRLQ_DATA_DIR", "CTRLQ_STATE_DIR", "CTRLQ_CONFIG_DIR"] {
+            child.env(var, &dir);
+        }
+        let output = child.output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{}{}", String::from_utf8_lossy(&output.stdout), stderr);
+        assert!(stderr.contains("render panic injected by a test"), "{}", stderr);
+
+        let saved = KeyLogger::load_stats(&dir.join("keystroke_data.json")).unwrap();
+        assert_eq!(saved.key_counts, HashMap::from([("KEY_30".to_string(), 3)]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// The crash of `render_panic_restores_terminal_and_saves`: types three
+    /// keys into a logger and runs the UI on a pseudo terminal until it
+    /// panics drawing them.
+    fn crash_on_pty() {
+        use std::io::Read;
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+        let (mut master, mut slave) = (0, 0);
+        let size = libc::winsize { ws_row: 24, ws_col: 80, ws_xpixel: 0, ws_ypixel: 0 };
+        // SAFETY: openpty only writes the two descriptors
+        let opened = unsafe { libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size) };
+        assert_eq!(opened, 0);
+        // SAFETY: both descriptors were just opened and are owned here
+        let (mut master, slave) = unsafe { (std::fs::File::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+        // Everything the UI writes, read as it comes so writing never blocks
+        let screen = std::thread::spawn(move || {
+            let mut written = Vec::new();
+            // Ends with EIO once the last descriptor of the slave is closed
+            let _ = master.read_to_end(&mut written);
+            String::from_utf8_lossy(&written).into_owned()
+        });
+        let cooked = local_modes(slave.as_raw_fd());
+        assert_ne!(cooked & libc::ICANON, 0);
+        // SAFETY: plain descriptor juggling; stdin and stdout are put back below
+        let saved = [0, 1].map(|fd| unsafe { libc::dup(fd) });
+        for fd in [0, 1] {
+            assert_eq!(unsafe { libc::dup2(slave.as_raw_fd(), fd) }, fd);
+        }
+
+        let (queue, events) = mpsc::channel();
+        let mut logger =
+            KeyLogger::new(Vec::new(), Config::default(), ctrlq::config::Overrides::default(), "default").unwrap();
+        logger.log_from(vec![Box::new(Scripted(events))]);
+        let channels = logger.start_logging().unwrap();
+        for _ in 0..3 {
+            for state in [keylogger::KeyState::Pressed, keylogger::KeyState::Released] {
+                let event = keylogger::KeyEvent { code: 30, state, time: std::time::SystemTime::now() };
+                queue.send(event).unwrap();
+            }
+        }
+        PANIC_AT_KEYSTROKES.store(3, std::sync::atomic::Ordering::Relaxed);
+        let crashed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run_live_ui(&mut logger, channels, "default", &Config::default(), UiOptions::default())
+        }));
+        let after = local_modes(slave.as_raw_fd());
+
+        for (fd, saved) in [0, 1].into_iter().zip(saved) {
+            assert_eq!(unsafe { libc::dup2(saved, fd) }, fd);
+            unsafe { libc::close(saved) };
+        }
+        drop(slave);
+        let screen = screen.join().unwrap();
+        drop(logger);
+
+        assert!(crashed.is_err(), "the panic must reach the caller after the save");
+        assert_eq!(after, cooked, "raw mode still on");
+        let entered = screen.find("\x1b[?1049h").expect("alternate screen never entered");
+        let left = screen.rfind("\x1b[?1049l").expect("alternate screen never left");
+        assert!(left > entered);
+        assert!(screen[left..].contains("\x1b[?25h"), "cursor still hidden");
+    }
 }
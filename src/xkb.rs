@@ -0,0 +1,439 @@
+//! # XKB Module
+//!
+//! Character statistics for people who type in more than one keyboard
+//! layout. Key counts are per physical key, so a `z` on a US layout and a
+//! `y` on a Hungarian one are the same key and `é` is just `;`. With the
+//! `xkb` feature, every key event also goes through an XKB keymap built
+//! with libxkbcommon, which turns it into the character it produced, and
+//! those characters are counted next to the keys.
+//!
+//! The layouts come from `--xkb-layout`, `xkb_layout` in the config or the
+//! system keyboard configuration in `/etc/default/keyboard`, see
+//! [`XkbNames::resolve`]. The keymap follows layout switches made with the
+//! group hotkey from the XKB options (e.g. `grp:alt_shift_toggle`), the
+//! same way the desktop does, as long as ctrlq starts with the first layout
+//! active.
+//!
+//! Without the feature, or without any layout, nothing changes: only keys
+//! are counted.
+
+use crate::config::Config;
+use crate::keylogger::{KeyEvent, LockState};
+use anyhow::Result;
+
+/// The system keyboard configuration read when neither `--xkb-layout` nor
+/// the config name a layout.
+pub const SYSTEM_KEYBOARD_FILE: &str = "/etc/default/keyboard";
+
+/// Layouts, variants and options of an XKB keymap, the L, V and O of
+/// the rules, model, layout, variant, options ("RMLVO") names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XkbNames {
+    /// Comma separated layouts, e.g. `us,hu`
+    pub layout: String,
+    /// Variant of each layout, e.g. `,qwerty`
+    pub variant: String,
+    /// Options, e.g. `grp:alt_shift_toggle` for the layout switch hotkey
+    pub options: String,
+}
+
+impl XkbNames {
+    /// Reads the names from the contents of `/etc/default/keyboard`.
+    ///
+    /// # Returns
+    /// `Option<Self>` - The names, or None if the file sets no layout
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::xkb::XkbNames;
+    ///
+    /// let names = XkbNames::parse_keyboard_file(
+    ///     "XKBMODEL=\"pc105\"\nXKBLAYOUT=\"us,hu\"\nXKBVARIANT=\",qwerty\"\nXKBOPTIONS=\"grp:alt_shift_toggle\"\n",
+    /// ).unwrap();
+    /// assert_eq!(names.layout, "us,hu");
+    /// assert_eq!(names.variant, ",qwerty");
+    /// assert_eq!(names.options, "grp:alt_shift_toggle");
+    ///
+    /// assert_eq!(XkbNames::parse_keyboard_file("XKBLAYOUT=\"\"\n"), None);
+    /// ```
+    pub fn parse_keyboard_file(content: &str) -> Option<Self> {
+        let value = |name: &str| {
+            content
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix(name)?.strip_prefix('='))
+                .next_back()
+                .map(|value| value.trim().trim_matches('"').trim_matches('\'').to_string())
+                .unwrap_or_default()
+        };
+        let names = Self { layout: value("XKBLAYOUT"), variant: value("XKBVARIANT"), options: value("XKBOPTIONS") };
+        (!names.layout.is_empty()).then_some(names)
+    }
+
+    /// Picks the names from the configuration or the system keyboard
+    /// configuration.
+    ///
+    /// A layout in the config (with `--xkb-layout` already applied) wins
+    /// and brings its own variants; the system's options still apply
+    /// unless the config sets `xkb_options`, since they hold the layout
+    /// switch hotkey. An empty `xkb_layout` turns character counting off.
+    ///
+    /// # Arguments
+    /// * `config` - User configuration
+    /// * `system` - Names from [`SYSTEM_KEYBOARD_FILE`], if any
+    ///
+    /// # Returns
+    /// `Option<Self>` - The names, or None if no layout is known
+    ///
+    /// # Example
+    /// ```
+    /// use ctrlq::config::Config;
+    /// use ctrlq::xkb::XkbNames;
+    ///
+    /// let system = XkbNames { layout: "us".into(), variant: "intl".into(), options: "grp:win_space_toggle".into() };
+    /// let mut config = Config::default();
+    /// assert_eq!(XkbNames::from_sources(&config, Some(system.clone())), Some(system.clone()));
+    ///
+    /// config.xkb_layout = Some("us,hu".into());
+    /// let names = XkbNames::from_sources(&config, Some(system.clone())).unwrap();
+    /// assert_eq!((names.layout.as_str(), names.variant.as_str()), ("us,hu", ""));
+    /// assert_eq!(names.options, "grp:win_space_toggle");
+    ///
+    /// config.xkb_layout = Some(String::new());
+    /// assert_eq!(XkbNames::from_sources(&config, Some(system)), None);
+    /// ```
+    pub fn from_sources(config: &Config, system: Option<XkbNames>) -> Option<Self> {
+        let mut names = match &config.xkb_layout {
+            Some(layout) if layout.trim().is_empty() => return None,
+            Some(layout) => Self {
+                layout: layout.trim().to_string(),
+                variant: config.xkb_variant.clone().unwrap_or_default(),
+                options: system.map(|system| system.options).unwrap_or_default(),
+            },
+            None => system?,
+        };
+        if let Some(options) = &config.xkb_options {
+            names.options = options.clone();
+        }
+        Some(names)
+    }
+
+    /// Picks the names like [`XkbNames::from_sources`], reading
+    /// [`SYSTEM_KEYBOARD_FILE`] if it exists.
+    pub fn resolve(config: &Config) -> Option<Self> {
+        let system = std::fs::read_to_string(SYSTEM_KEYBOARD_FILE)
+            .ok()
+            .and_then(|content| Self::parse_keyboard_file(&content));
+        Self::from_sources(config, system)
+    }
+}
+
+/// Declarations of the parts of libxkbcommon that ctrlq uses.
+#[cfg(feature = "xkb")]
+mod ffi {
+    use std::ffi::{c_char, c_int, c_void};
+
+    /// `struct xkb_rule_names`; null fields use the library's defaults.
+    #[repr(C)]
+    pub struct RuleNames {
+        pub rules: *const c_char,
+        pub model: *const c_char,
+        pub layout: *const c_char,
+        pub variant: *const c_char,
+        pub options: *const c_char,
+    }
+
+    /// `XKB_KEY_UP` and `XKB_KEY_DOWN`
+    pub const KEY_UP: c_int = 0;
+    pub const KEY_DOWN: c_int = 1;
+    /// `XKB_STATE_MODS_DEPRESSED`, `_LATCHED` and `_LOCKED`
+    pub const STATE_MODS_DEPRESSED: c_int = 1 << 0;
+    pub const STATE_MODS_LATCHED: c_int = 1 << 1;
+    pub const STATE_MODS_LOCKED: c_int = 1 << 2;
+    /// `XKB_STATE_LAYOUT_DEPRESSED`, `_LATCHED` and `_LOCKED`
+    pub const STATE_LAYOUT_DEPRESSED: c_int = 1 << 4;
+    pub const STATE_LAYOUT_LATCHED: c_int = 1 << 5;
+    pub const STATE_LAYOUT_LOCKED: c_int = 1 << 6;
+    /// `XKB_STATE_LAYOUT_EFFECTIVE`
+    pub const STATE_LAYOUT_EFFECTIVE: c_int = 1 << 7;
+    /// `XKB_MOD_INVALID`
+    pub const MOD_INVALID: u32 = 0xffff_ffff;
+    /// `XKB_MOD_NAME_CAPS` and `XKB_MOD_NAME_NUM`
+    pub const MOD_NAME_CAPS: &std::ffi::CStr = c"Lock";
+    pub const MOD_NAME_NUM: &std::ffi::CStr = c"Mod2";
+    /// XKB keycodes are evdev codes shifted by 8, a leftover of X11
+    pub const EVDEV_OFFSET: u32 = 8;
+
+    // Linked by its soname, so the runtime library is enough to build and
+    // no development package is needed
+    #[link(name = "libxkbcommon.so.0", kind = "dylib", modifiers = "+verbatim")]
+    unsafe extern "C" {
+        pub fn xkb_context_new(flags: c_int) -> *mut c_void;
+        pub fn xkb_context_unref(context: *mut c_void);
+        pub fn xkb_keymap_new_from_names(context: *mut c_void, names: *const RuleNames, flags: c_int) -> *mut c_void;
+        pub fn xkb_keymap_unref(keymap: *mut c_void);
+        pub fn xkb_keymap_layout_get_name(keymap: *mut c_void, index: u32) -> *const c_char;
+        pub fn xkb_state_new(keymap: *mut c_void) -> *mut c_void;
+        pub fn xkb_state_unref(state: *mut c_void);
+        pub fn xkb_state_update_key(state: *mut c_void, key: u32, direction: c_int) -> c_int;
+        pub fn xkb_state_key_get_utf8(state: *mut c_void, key: u32, buffer: *mut c_char, size: usize) -> c_int;
+        pub fn xkb_state_serialize_layout(state: *mut c_void, components: c_int) -> u32;
+        pub fn xkb_state_serialize_mods(state: *mut c_void, components: c_int) -> u32;
+        pub fn xkb_state_update_mask(
+            state: *mut c_void,
+            depressed_mods: u32,
+            latched_mods: u32,
+            locked_mods: u32,
+            depressed_layout: u32,
+            latched_layout: u32,
+            locked_layout: u32,
+        ) -> c_int;
+        pub fn xkb_keymap_mod_get_index(keymap: *mut c_void, name: *const c_char) -> u32;
+    }
+}
+
+/// Turns key events into the characters they produce on the active layout.
+///
+/// One translator is shared by all logged keyboards, like the desktop
+/// shares one layout between them. It must stay on the thread that created
+/// it.
+pub struct XkbTranslator {
+    #[cfg(feature = "xkb")]
+    context: *mut std::ffi::c_void,
+    #[cfg(feature = "xkb")]
+    keymap: *mut std::ffi::c_void,
+    #[cfg(feature = "xkb")]
+    state: *mut std::ffi::c_void,
+}
+
+impl XkbTranslator {
+    /// Compiles the keymap for the given names.
+    ///
+    /// # Arguments
+    /// * `names` - Layouts, variants and options
+    ///
+    /// # Returns
+    /// `Result<Self>` - The translator, or an error if ctrlq was built
+    /// without the `xkb` feature or the names don't make a keymap
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "xkb")] {
+    /// use ctrlq::keylogger::{KeyEvent, KeyState};
+    /// use ctrlq::xkb::{XkbNames, XkbTranslator};
+    /// use std::time::SystemTime;
+    ///
+    /// let names = XkbNames { layout: "us,hu".into(), variant: String::new(), options: "grp:alt_shift_toggle".into() };
+    /// let mut xkb = XkbTranslator::new(&names)?;
+    /// let mut tap = |code: u16| {
+    ///     let time = SystemTime::now();
+    ///     let typed = xkb.feed(KeyEvent { code, state: KeyState::Pressed, time });
+    ///     xkb.feed(KeyEvent { code, state: KeyState::Released, time });
+    ///     typed
+    /// };
+    /// assert_eq!(tap(44), Some('z'));
+    ///
+    /// // Alt+Shift switches to the Hungarian layout: z and y swap, ; types é
+    /// let time = SystemTime::now();
+    /// xkb.feed(KeyEvent { code: 56, state: KeyState::Pressed, time });
+    /// xkb.feed(KeyEvent { code: 42, state: KeyState::Pressed, time });
+    /// xkb.feed(KeyEvent { code: 42, state: KeyState::Released, time });
+    /// xkb.feed(KeyEvent { code: 56, state: KeyState::Released, time });
+    /// assert_eq!(xkb.active_layout().as_deref(), Some("Hungarian"));
+    /// let mut tap = |code: u16| {
+    ///     let typed = xkb.feed(KeyEvent { code, state: KeyState::Pressed, time });
+    ///     xkb.feed(KeyEvent { code, state: KeyState::Released, time });
+    ///     typed
+    /// };
+    /// assert_eq!(tap(44), Some('y'));
+    /// assert_eq!(tap(39), Some('é'));
+    /// # }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn new(names: &XkbNames) -> Result<Self> {
+        #[cfg(feature = "xkb")]
+        {
+            use anyhow::{bail, Context};
+            use std::ffi::CString;
+
+            let layout = CString::new(names.layout.as_str()).context("invalid xkb layout")?;
+            let variant = CString::new(names.variant.as_str()).context("invalid xkb variant")?;
+            let options = CString::new(names.options.as_str()).context("invalid xkb options")?;
+            let rule_names = ffi::RuleNames {
+                rules: std::ptr::null(),
+                model: std::ptr::null(),
+                layout: layout.as_ptr(),
+                variant: variant.as_ptr(),
+                options: options.as_ptr(),
+            };
+            // SAFETY: the names outlive the call, and every object is checked
+            // for null before use and released in Drop
+            unsafe {
+                let context = ffi::xkb_context_new(0);
+                if context.is_null() {
+                    bail!("libxkbcommon could not create a context");
+                }
+                let keymap = ffi::xkb_keymap_new_from_names(context, &rule_names, 0);
+                if keymap.is_null() {
+                    ffi::xkb_context_unref(context);
+                    bail!("no XKB keymap for layout '{}' variant '{}'", names.layout, names.variant);
+                }
+                let state = ffi::xkb_state_new(keymap);
+                if state.is_null() {
+                    ffi::xkb_keymap_unref(keymap);
+                    ffi::xkb_context_unref(context);
+                    bail!("libxkbcommon could not create a keyboard state");
+                }
+                Ok(Self { context, keymap, state })
+            }
+        }
+        #[cfg(not(feature = "xkb"))]
+        {
+            let _ = names;
+            anyhow::bail!("ctrlq was built without the xkb feature (cargo build --features xkb)")
+        }
+    }
+
+    /// Updates the keyboard state with a key event.
+    ///
+    /// Every event must go through here, including modifier and excluded
+    /// keys, or Shift and layout switches are missed.
+    ///
+    /// # Returns
+    /// `Option<char>` - The printable character a press produced; None for
+    /// releases, repeats and keys without a character
+    pub fn feed(&mut self, event: KeyEvent) -> Option<char> {
+        #[cfg(feature = "xkb")]
+        {
+            use crate::keylogger::KeyState;
+
+            let key = event.code as u32 + ffi::EVDEV_OFFSET;
+            let direction = match event.state {
+                KeyState::Pressed => ffi::KEY_DOWN,
+                KeyState::Released => ffi::KEY_UP,
+                KeyState::Repeated => return None,
+            };
+            // SAFETY: the state is valid for the lifetime of self; the
+            // buffer size is passed along and the result is bounds checked
+            unsafe {
+                // The character comes from the state before the press, as
+                // libxkbcommon asks
+                let typed = if direction == ffi::KEY_DOWN {
+                    let mut buffer = [0 as std::ffi::c_char; 16];
+                    let len = ffi::xkb_state_key_get_utf8(self.state, key, buffer.as_mut_ptr(), buffer.len());
+                    let bytes: Vec<u8> = buffer.iter().take(len.clamp(0, 15) as usize).map(|&b| b as u8).collect();
+                    let text = String::from_utf8(bytes).unwrap_or_default();
+                    let mut chars = text.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if !c.is_control() && !c.is_whitespace() => Some(c),
+                        _ => None,
+                    }
+                } else {
+                    None
+                };
+                ffi::xkb_state_update_key(self.state, key, direction);
+                typed
+            }
+        }
+        #[cfg(not(feature = "xkb"))]
+        {
+            let _ = event;
+            None
+        }
+    }
+
+    /// Sets Caps Lock and Num Lock to what the keyboard LEDs show.
+    ///
+    /// The keymap starts with both off and only sees the lock keys pressed
+    /// while logging, so it is corrected at start and whenever the LEDs
+    /// change; the rest of the state is kept.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(feature = "xkb")] {
+    /// use ctrlq::keylogger::{KeyEvent, KeyState, LockState};
+    /// use ctrlq::xkb::{XkbNames, XkbTranslator};
+    /// use std::time::SystemTime;
+    ///
+    /// let names = XkbNames { layout: "us".into(), ..XkbNames::default() };
+    /// let mut xkb = XkbTranslator::new(&names)?;
+    /// let mut tap = |xkb: &mut XkbTranslator, code: u16| {
+    ///     let time = SystemTime::now();
+    ///     let typed = xkb.feed(KeyEvent { code, state: KeyState::Pressed, time });
+    ///     xkb.feed(KeyEvent { code, state: KeyState::Released, time });
+    ///     typed
+    /// };
+    /// xkb.set_locks(LockState { caps_lock: true, num_lock: true });
+    /// assert_eq!(tap(&mut xkb, 30), Some('A'));
+    /// assert_eq!(tap(&mut xkb, 79), Some('1')); // KP1
+    /// xkb.set_locks(LockState::default());
+    /// assert_eq!(tap(&mut xkb, 30), Some('a'));
+    /// # }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_locks(&mut self, locks: LockState) {
+        #[cfg(feature = "xkb")]
+        {
+            // SAFETY: the keymap and state are valid for the lifetime of self,
+            // and the modifier names are static C strings
+            unsafe {
+                let serialize = |components| ffi::xkb_state_serialize_mods(self.state, components);
+                let layout = |components| ffi::xkb_state_serialize_layout(self.state, components);
+                let mut locked = serialize(ffi::STATE_MODS_LOCKED);
+                for (name, on) in [(ffi::MOD_NAME_CAPS, locks.caps_lock), (ffi::MOD_NAME_NUM, locks.num_lock)] {
+                    let index = ffi::xkb_keymap_mod_get_index(self.keymap, name.as_ptr());
+                    if index == ffi::MOD_INVALID || index >= 32 {
+                        continue;
+                    }
+                    if on {
+                        locked |= 1 << index;
+                    } else {
+                        locked &= !(1 << index);
+                    }
+                }
+                ffi::xkb_state_update_mask(
+                    self.state,
+                    serialize(ffi::STATE_MODS_DEPRESSED),
+                    serialize(ffi::STATE_MODS_LATCHED),
+                    locked,
+                    layout(ffi::STATE_LAYOUT_DEPRESSED),
+                    layout(ffi::STATE_LAYOUT_LATCHED),
+                    layout(ffi::STATE_LAYOUT_LOCKED),
+                );
+            }
+        }
+        #[cfg(not(feature = "xkb"))]
+        {
+            let _ = locks;
+        }
+    }
+
+    /// Returns the name of the active layout, e.g. `Hungarian`.
+    pub fn active_layout(&self) -> Option<String> {
+        #[cfg(feature = "xkb")]
+        {
+            // SAFETY: the keymap and state are valid for the lifetime of self;
+            // the name is owned by the keymap and copied right away
+            unsafe {
+                let index = ffi::xkb_state_serialize_layout(self.state, ffi::STATE_LAYOUT_EFFECTIVE);
+                let name = ffi::xkb_keymap_layout_get_name(self.keymap, index);
+                (!name.is_null()).then(|| std::ffi::CStr::from_ptr(name).to_string_lossy().into_owned())
+            }
+        }
+        #[cfg(not(feature = "xkb"))]
+        {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "xkb")]
+impl Drop for XkbTranslator {
+    fn drop(&mut self) {
+        // SAFETY: created in new and released exactly once here
+        unsafe {
+            ffi::xkb_state_unref(self.state);
+            ffi::xkb_keymap_unref(self.keymap);
+            ffi::xkb_context_unref(self.context);
+        }
+    }
+}